@@ -0,0 +1,122 @@
+use colabri_doc::models::{ColabModel, ColabStatementModel};
+use colabri_doc::models::lorodoc::{colab_to_loro_doc, stmt_to_loro_doc};
+use colabri_doc::services::acl_service;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use loro::{ExportMode, LoroDoc, ToJson};
+use serde_json::{json, Value};
+
+// Fixtures sized after real documents: statements translated in many languages with a few
+// paragraphs each, and sheets with text blocks and statement grids holding local statements.
+
+const LANGUAGES: usize = 40;
+const PARAGRAPHS: usize = 8;
+const SHEET_TEXT_BLOCKS: usize = 20;
+const SHEET_GRID_ROWS: usize = 60;
+const SHEET_ROW_LANGUAGES: usize = 10;
+
+fn text_element(paragraphs: usize, seed: usize) -> Value {
+    let children: Vec<Value> = (0..paragraphs)
+        .map(|p| json!({
+            "nodeName": "paragraph",
+            "attributes": {},
+            "children": [format!("Paragraph {} of element {}. Keep out of reach of children. Store below 25°C and protect from light and moisture.", p, seed)],
+        }))
+        .collect();
+    json!({ "nodeName": "doc", "attributes": {}, "children": children })
+}
+
+fn acls(seed: usize) -> Value {
+    json!({
+        "view": [format!("org/u/{:08}-0000-0000-0000-000000000000", seed)],
+        "edit": [format!("org/u/{:08}-0000-0000-0000-000000000001", seed)],
+    })
+}
+
+fn statement(languages: usize, paragraphs: usize) -> Value {
+    let content: serde_json::Map<String, Value> = (0..languages)
+        .map(|l| (format!("l{:02}", l), json!({
+            "textElement": text_element(paragraphs, l),
+            "acls": acls(l),
+            "comments": [],
+            "approvals": {},
+        })))
+        .collect();
+    json!({
+        "properties": { "type": "colab-statement", "contentType": "warning" },
+        "acls": acls(0),
+        "content": content,
+    })
+}
+
+fn sheet() -> Value {
+    let mut blocks = vec![json!({ "type": "properties" })];
+    for b in 0..SHEET_TEXT_BLOCKS {
+        blocks.push(json!({
+            "type": "text",
+            "acls": acls(b),
+            "title": text_element(1, b),
+            "textElement": text_element(PARAGRAPHS, b),
+            "approvals": {},
+        }));
+    }
+    let rows: Vec<Value> = (0..SHEET_GRID_ROWS)
+        .map(|_| json!({ "type": "local", "statement": statement(SHEET_ROW_LANGUAGES, 2) }))
+        .collect();
+    blocks.push(json!({
+        "type": "statement-grid",
+        "acls": acls(0),
+        "title": text_element(1, 0),
+        "rows": rows,
+    }));
+    json!({
+        "properties": { "type": "colab-sheet", "contentType": "leaflet" },
+        "acls": acls(0),
+        "content": blocks,
+    })
+}
+
+fn statement_model() -> ColabStatementModel {
+    serde_json::from_value(statement(LANGUAGES, PARAGRAPHS)).expect("valid statement fixture")
+}
+
+fn sheet_doc() -> LoroDoc {
+    let model: ColabModel = serde_json::from_value(sheet()).expect("valid sheet fixture");
+    colab_to_loro_doc(&model).expect("sheet fixture converts to a Loro document")
+}
+
+fn bench_model_conversion(c: &mut Criterion) {
+    let stmt = statement_model();
+    c.bench_function("stmt_to_loro_doc", |b| b.iter(|| stmt_to_loro_doc(black_box(&stmt))));
+
+    let sheet: ColabModel = serde_json::from_value(sheet()).expect("valid sheet fixture");
+    c.bench_function("sheet_to_loro_doc", |b| b.iter(|| colab_to_loro_doc(black_box(&sheet))));
+}
+
+fn bench_snapshots(c: &mut Criterion) {
+    let doc = sheet_doc();
+    c.bench_function("snapshot_export", |b| b.iter(|| doc.export(ExportMode::Snapshot).unwrap()));
+
+    let snapshot = doc.export(ExportMode::Snapshot).unwrap();
+    c.bench_function("snapshot_import", |b| b.iter(|| {
+        let doc = LoroDoc::new();
+        doc.import(black_box(&snapshot)).unwrap();
+        doc
+    }));
+}
+
+fn bench_deep_json(c: &mut Criterion) {
+    let doc = sheet_doc();
+    c.bench_function("deep_json", |b| b.iter(|| doc.get_deep_value().to_json_value()));
+}
+
+fn bench_acl_reset(c: &mut Criterion) {
+    let doc = sheet_doc();
+    c.bench_function("acl_reset", |b| b.iter_batched(
+        || doc.fork(),
+        |doc| acl_service::reset_acls(&doc).unwrap(),
+        BatchSize::LargeInput,
+    ));
+}
+
+criterion_group!(benches, bench_model_conversion, bench_snapshots, bench_deep_json, bench_acl_reset);
+criterion_main!(benches);
@@ -0,0 +1,122 @@
+use colabri_doc::models::{DocumentSettings, StorageTier};
+use colabri_doc::services::hub_service;
+use colabri_doc::ws::docctx::DocContext;
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures_util::future::join_all;
+use loro_websocket_server::{HubRegistry, LoadDocArgs, LoadedDoc, ServerConfig};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+// Latency of the room access in hub_service with 1k rooms open, spread over the hubs of a few orgs
+// like on a busy instance. The bursts run all their reads and context updates concurrently.
+
+const ORGS: usize = 10;
+const ROOMS: usize = 1_000;
+const BURST_WRITES: usize = 100;
+const BURST_DASHBOARD_READS: usize = 100;
+
+fn room(i: usize) -> (String, String) {
+    (format!("org-{}", i % ORGS), format!("{:08}-0000-0000-0000-000000000000", i))
+}
+
+fn context(org: &str) -> DocContext {
+    DocContext {
+        org: org.to_string(),
+        doc_id: Uuid::new_v4(),
+        doc_stream_id: Uuid::new_v4(),
+        doc_version: 1,
+        doc_owner: "bench".to_string(),
+        peer_map: HashMap::new(),
+        last_updating_peer: None,
+        tier: StorageTier::Hot,
+        settings: DocumentSettings::default(),
+        state_only: false,
+        block_id: None,
+    }
+}
+
+fn load_empty(args: LoadDocArgs) -> Pin<Box<dyn Future<Output = Result<LoadedDoc<DocContext>, String>> + Send>> {
+    Box::pin(async move { Ok(LoadedDoc { snapshot: None, ctx: Some(context(&args.workspace)) }) })
+}
+
+// A registry with ROOMS open rooms, each holding a small edit
+fn registry_with_rooms(rt: &Runtime) -> Arc<HubRegistry<DocContext>> {
+    let registry = Arc::new(HubRegistry::new(ServerConfig {
+        on_load_document: Some(Arc::new(load_empty)),
+        ..Default::default()
+    }));
+    rt.block_on(async {
+        for i in 0..ROOMS {
+            let (org, doc) = room(i);
+            registry
+                .edit_loro_doc(&org, &doc, |doc| doc.get_text("text").insert(0, "bench").map_err(|e| e.to_string()), Some(true))
+                .await
+                .expect("room opens");
+        }
+    });
+    registry
+}
+
+fn bench_rooms(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread().enable_all().build().expect("runtime");
+    let registry = registry_with_rooms(&rt);
+    let mut group = c.benchmark_group("rooms_1k");
+
+    group.bench_function("snapshot_rooms", |b| b.iter(|| rt.block_on(hub_service::snapshot_rooms(&registry))));
+    group.bench_function("cached_rooms", |b| b.iter(|| rt.block_on(hub_service::cached_rooms(&registry))));
+
+    // A read of every room while contexts are updated
+    group.bench_function("doc_reads_during_ctx_updates", |b| b.iter(|| rt.block_on(async {
+        let reads = (0..ROOMS).map(|i| {
+            let registry = registry.clone();
+            tokio::spawn(async move {
+                let (org, doc) = room(i);
+                hub_service::get_open_doc_handle(&registry, &org, &doc).await.map(|(doc, _)| doc.state_vv())
+            })
+        });
+        let writes = (0..BURST_WRITES).map(|i| {
+            let registry = registry.clone();
+            tokio::spawn(async move {
+                let (org, doc) = room(i * (ROOMS / BURST_WRITES));
+                hub_service::update_doc_ctx(&registry, &org, &doc, |ctx| ctx.doc_version += 1).await
+            })
+        });
+        let (reads, writes): (Vec<_>, Vec<_>) = (reads.collect(), writes.collect());
+        join_all(reads).await;
+        join_all(writes).await;
+    })));
+
+    // Dashboards polling the room metadata while contexts are updated
+    for (name, cached) in [("dashboard_reads_during_ctx_updates/snapshot", false), ("dashboard_reads_during_ctx_updates/cached", true)] {
+        group.bench_function(name, |b| b.iter(|| rt.block_on(async {
+            let reads = (0..BURST_DASHBOARD_READS).map(|_| {
+                let registry = registry.clone();
+                tokio::spawn(async move {
+                    if cached {
+                        hub_service::cached_rooms(&registry).await.len()
+                    } else {
+                        hub_service::snapshot_rooms(&registry).await.len()
+                    }
+                })
+            });
+            let writes = (0..BURST_WRITES).map(|i| {
+                let registry = registry.clone();
+                tokio::spawn(async move {
+                    let (org, doc) = room(i * (ROOMS / BURST_WRITES));
+                    hub_service::update_doc_ctx(&registry, &org, &doc, |ctx| ctx.doc_version += 1).await
+                })
+            });
+            let (reads, writes): (Vec<_>, Vec<_>) = (reads.collect(), writes.collect());
+            join_all(reads).await;
+            join_all(writes).await;
+        })));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_rooms);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+-- Document workflow states
+--
+-- The current state of every document, per-org workflow definitions and the
+-- history of all transitions. Like the other tables, rows are scoped by `org`
+-- and only accessed after `SET LOCAL app.orgs`, the policies below enforce it.
+
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS workflow_state TEXT NOT NULL DEFAULT 'draft';
+
+CREATE TABLE IF NOT EXISTS org_workflows (
+    org         TEXT PRIMARY KEY,
+    definition  JSONB NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by  TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS document_workflow_transitions (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    org         TEXT NOT NULL,
+    document    UUID NOT NULL REFERENCES documents(id),
+    from_state  TEXT NOT NULL,
+    to_state    TEXT NOT NULL,
+    comment     TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by  TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS document_workflow_transitions_document_idx
+    ON document_workflow_transitions (org, document, created_at);
+
+-- The service owns the tables, FORCE applies the policies to it as well
+ALTER TABLE org_workflows ENABLE ROW LEVEL SECURITY;
+ALTER TABLE org_workflows FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS org_workflows_orgs ON org_workflows;
+CREATE POLICY org_workflows_orgs ON org_workflows
+    USING (org = ANY (string_to_array(current_setting('app.orgs', true), ',')));
+
+ALTER TABLE document_workflow_transitions ENABLE ROW LEVEL SECURITY;
+ALTER TABLE document_workflow_transitions FORCE ROW LEVEL SECURITY;
+DROP POLICY IF EXISTS document_workflow_transitions_orgs ON document_workflow_transitions;
+CREATE POLICY document_workflow_transitions_orgs ON document_workflow_transitions
+    USING (org = ANY (string_to_array(current_setting('app.orgs', true), ',')));
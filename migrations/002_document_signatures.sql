@@ -0,0 +1,23 @@
+-- Detached signatures of published documents
+--
+-- Every time a document is published, the exported snapshot and JSON are hashed
+-- and the hashes are signed with the Ed25519 signing key of the service.
+
+CREATE TABLE IF NOT EXISTS document_signatures (
+    id              UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL REFERENCES documents(id),
+    version         INTEGER NOT NULL,
+    version_v       JSONB NOT NULL,
+    snapshot_sha256 TEXT NOT NULL,
+    json_sha256     TEXT NOT NULL,
+    algorithm       TEXT NOT NULL,
+    key_id          TEXT NOT NULL,
+    public_key      TEXT NOT NULL,
+    signature       TEXT NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by      TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS document_signatures_document_idx
+    ON document_signatures (org, document, created_at);
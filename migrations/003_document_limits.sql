@@ -0,0 +1,12 @@
+-- Document size and complexity limits
+--
+-- Per-org overrides of the document limits. `limits` holds any subset of
+-- maxBlocks, maxTextDepth, maxSnapshotBytes and maxLanguages; missing keys
+-- fall back to the service defaults.
+
+CREATE TABLE IF NOT EXISTS org_document_limits (
+    org         TEXT PRIMARY KEY,
+    limits      JSONB NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by  TEXT NOT NULL
+);
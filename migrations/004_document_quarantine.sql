@@ -0,0 +1,10 @@
+-- Quarantine of documents that fail to load
+--
+-- Consecutive load failures are counted per document. Once the configured
+-- threshold is reached the document is quarantined: loads are refused until
+-- an admin retries or repairs it.
+
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS load_failures INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS quarantined_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS quarantine_reason TEXT;
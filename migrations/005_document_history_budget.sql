@@ -0,0 +1,7 @@
+-- Per-document history budget
+--
+-- The number of most recent versions of a document to keep. Older versions
+-- are trimmed by the compaction job. NULL keeps the full history.
+
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS history_budget INTEGER CHECK (history_budget IS NULL OR history_budget > 0);
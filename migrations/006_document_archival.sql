@@ -0,0 +1,25 @@
+-- Auto-archival of inactive documents
+--
+-- `last_activity_at` is bumped whenever a document is opened or saved. Documents
+-- inactive for longer than the policy of their org are first flagged as archival
+-- candidates and only archived once the grace period after flagging has passed,
+-- so admins can review the candidates before anything happens.
+--
+-- The policies are read by the archival job across all organizations.
+
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS last_activity_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN IF NOT EXISTS archive_candidate_since TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS documents_last_activity_idx
+    ON documents (org, last_activity_at)
+    WHERE deleted = FALSE AND workflow_state <> 'archived';
+
+CREATE TABLE IF NOT EXISTS org_archival_policies (
+    org             TEXT PRIMARY KEY,
+    inactive_days   INTEGER NOT NULL CHECK (inactive_days > 0),
+    grace_days      INTEGER NOT NULL DEFAULT 7 CHECK (grace_days >= 0),
+    enabled         BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by      TEXT NOT NULL
+);
@@ -0,0 +1,21 @@
+-- Merges of diverged document versions
+--
+-- When two versions of a document diverged (e.g. replicas that split or a restore
+-- that created a fork), both heads are imported into one LoroDoc and the merged
+-- state is saved as a new version. Every merge is recorded here for auditing.
+
+CREATE TABLE IF NOT EXISTS document_merges (
+    id              UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL REFERENCES documents(id),
+    version_a       INTEGER NOT NULL,
+    version_b       INTEGER NOT NULL,
+    merged_version  INTEGER NOT NULL,
+    merged_stream   UUID NOT NULL REFERENCES document_streams(id),
+    version_v       JSONB NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by      TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS document_merges_document_idx
+    ON document_merges (org, document, created_at);
@@ -0,0 +1,15 @@
+-- Save policies per document type
+--
+-- Per-org overrides of how often and how documents are persisted. `policies` holds
+-- a "docTypes" map keyed by document type (colab-statement, colab-sheet) and a
+-- "contentTypes" map keyed by the contentType of a document. Each entry holds any
+-- subset of intervalMs, debounceMs and persistence ("snapshot" or "updates").
+-- A content type entry takes precedence over the entry of its document type,
+-- missing keys fall back to the service defaults.
+
+CREATE TABLE IF NOT EXISTS org_save_policies (
+    org         TEXT PRIMARY KEY,
+    policies    JSONB NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by  TEXT NOT NULL
+);
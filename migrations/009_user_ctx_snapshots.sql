@@ -0,0 +1,15 @@
+-- Warm persistence of user principals
+--
+-- The principals fetched from the app service are kept here for a short time, so a
+-- pod that just started can serve the reconnect storm after a deploy from this table
+-- instead of asking the app service for every user at once. Users are not scoped to
+-- an organization, the table is read and written across organizations.
+
+CREATE TABLE IF NOT EXISTS user_ctx_snapshots (
+    uid         TEXT PRIMARY KEY,
+    principals  JSONB NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS user_ctx_snapshots_updated_idx
+    ON user_ctx_snapshots (updated_at);
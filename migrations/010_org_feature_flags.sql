@@ -0,0 +1,14 @@
+-- Feature flags per organization
+--
+-- Gates capabilities (comments, suggestions, translation, publishing) per org so
+-- they can be rolled out gradually. Features without a row for an org fall back
+-- to the service defaults.
+
+CREATE TABLE IF NOT EXISTS org_feature_flags (
+    org         TEXT NOT NULL,
+    feature     TEXT NOT NULL,
+    enabled     BOOLEAN NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by  TEXT NOT NULL,
+    PRIMARY KEY (org, feature)
+);
@@ -0,0 +1,10 @@
+-- Per-document settings
+--
+-- Overrides of the behavior of a single document, so problematic documents can
+-- be special-cased without a redeploy. `settings` holds any subset of
+-- savePolicy (intervalMs, debounceMs, persistence), limits (maxBlocks,
+-- maxTextDepth, maxSnapshotBytes, maxLanguages) and readOnly. NULL keeps the
+-- org and service defaults.
+
+ALTER TABLE documents
+    ADD COLUMN IF NOT EXISTS settings JSONB;
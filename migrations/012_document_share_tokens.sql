@@ -0,0 +1,20 @@
+-- Share tokens for the public read-only view of published documents
+--
+-- A share token gives anyone holding it read access to the rendered, published
+-- version of a single document. Only the SHA-256 of the token is stored.
+-- `branding` holds any subset of title, logoUrl, accentColor and footer.
+
+CREATE TABLE IF NOT EXISTS document_share_tokens (
+    id              UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL,
+    token_sha256    TEXT NOT NULL UNIQUE,
+    branding        JSONB,
+    expires_at      TIMESTAMPTZ,
+    revoked_at      TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by      TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_share_tokens_document
+    ON document_share_tokens (org, document);
@@ -0,0 +1,12 @@
+-- Embed settings per organization
+--
+-- `frame_ancestors` lists the origins allowed to frame the embed mode of the
+-- public view, e.g. https://intranet.example.com or https://*.example.com.
+-- Organizations without a row can't be embedded anywhere.
+
+CREATE TABLE IF NOT EXISTS org_embed_settings (
+    org                 TEXT PRIMARY KEY,
+    frame_ancestors     TEXT[] NOT NULL DEFAULT '{}',
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by          TEXT NOT NULL
+);
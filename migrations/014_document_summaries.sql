@@ -0,0 +1,15 @@
+-- Summaries of published documents
+--
+-- A short abstract of the published version of a document, generated on publish
+-- so library views can show a preview without loading the document. `provider`
+-- is the URL of the summary provider, or 'extract' for the built-in fallback.
+
+CREATE TABLE IF NOT EXISTS document_summaries (
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL,
+    version         INTEGER NOT NULL,
+    summary         TEXT NOT NULL,
+    provider        TEXT NOT NULL,
+    generated_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (org, document)
+);
@@ -0,0 +1,23 @@
+-- Findings of the policy scanner
+--
+-- Each save of a document in an org with policy scanning enabled is checked for
+-- PII, banned claims and classified terms. A scan replaces the open findings of
+-- the document; resolved findings are kept as the review trail. `block_path` is
+-- the path of the scanned block or language, e.g. /content/<blockId>.
+
+CREATE TABLE IF NOT EXISTS document_policy_findings (
+    id              UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL,
+    block_path      TEXT NOT NULL,
+    category        TEXT NOT NULL,
+    severity        TEXT NOT NULL,
+    rule            TEXT,
+    excerpt         TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at     TIMESTAMPTZ,
+    resolved_by     TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_policy_findings_document
+    ON document_policy_findings (org, document);
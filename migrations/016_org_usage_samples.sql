@@ -0,0 +1,20 @@
+-- Usage samples per organization
+--
+-- Every instance samples the open rooms, connections and accepted updates of each
+-- organization once per interval (a minute by default). Samples of different
+-- pods taken in the same minute add up to the usage of the organization.
+-- Samples older than the retention are deleted by the sampler.
+
+CREATE TABLE IF NOT EXISTS org_usage_samples (
+    org             TEXT NOT NULL,
+    pod             TEXT NOT NULL,
+    sampled_at      TIMESTAMPTZ NOT NULL,
+    n_rooms         INTEGER NOT NULL,
+    n_doc_rooms     INTEGER NOT NULL,
+    n_conn          INTEGER NOT NULL,
+    n_updates       BIGINT NOT NULL,
+    PRIMARY KEY (org, sampled_at, pod)
+);
+
+CREATE INDEX IF NOT EXISTS idx_org_usage_samples_sampled_at
+    ON org_usage_samples (sampled_at);
@@ -0,0 +1,32 @@
+-- Usage records for billing
+--
+-- `org_active_documents` holds every document that received an update in a
+-- month. `org_export_events` logs every export (evidence bundles, CSV exports).
+-- `org_storage_samples` holds the stored bytes per org, sampled daily. Together
+-- with `org_usage_samples` they make up the monthly billing report.
+
+CREATE TABLE IF NOT EXISTS org_active_documents (
+    org             TEXT NOT NULL,
+    month           DATE NOT NULL,
+    document        UUID NOT NULL,
+    PRIMARY KEY (org, month, document)
+);
+
+CREATE TABLE IF NOT EXISTS org_export_events (
+    id              UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL,
+    kind            TEXT NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by      TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_org_export_events_created_at
+    ON org_export_events (created_at);
+
+CREATE TABLE IF NOT EXISTS org_storage_samples (
+    org             TEXT NOT NULL,
+    day             DATE NOT NULL,
+    bytes           BIGINT NOT NULL,
+    PRIMARY KEY (org, day)
+);
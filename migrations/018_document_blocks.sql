@@ -0,0 +1,17 @@
+-- Bodies of lazily loaded sheet blocks
+--
+-- A sheet in lazy block mode keeps only the skeleton of its statement grids (type,
+-- id, title, acls). The rows of every grid are a Loro document of their own, synced
+-- in the room `<document>~<block_id>` and stored here as a CBOR ColabPackage.
+-- `json` holds the rows, so the JSON of the whole sheet can be assembled.
+
+CREATE TABLE IF NOT EXISTS document_blocks (
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL,
+    block_id        TEXT NOT NULL,
+    package         BYTEA NOT NULL,
+    json            JSONB NOT NULL,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by      TEXT NOT NULL,
+    PRIMARY KEY (org, document, block_id)
+);
@@ -0,0 +1,21 @@
+-- Statements of sheets stored as linked subdocuments
+--
+-- A row of a statement grid can link a statement subdocument instead of embedding
+-- a local statement. The subdocument is a Loro document of its own with the
+-- structure of a colab-statement, synced in the room `<sheet>~stmt-<id>` and stored
+-- here as a CBOR ColabPackage. `json` holds the statement for composing the sheet.
+
+CREATE TABLE IF NOT EXISTS statement_subdocs (
+    org             TEXT NOT NULL,
+    id              UUID NOT NULL,
+    sheet           UUID NOT NULL,
+    package         BYTEA NOT NULL,
+    json            JSONB NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by      TEXT NOT NULL,
+    PRIMARY KEY (org, id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_statement_subdocs_sheet
+    ON statement_subdocs (org, sheet);
@@ -0,0 +1,19 @@
+-- Blocks of sheets transcluding a block of another document
+--
+-- Indexed from the saved JSON of every sheet, so the sheets showing a block can be
+-- found when the source document is saved. `source_hash` is the hash of the source
+-- block the sheet was last notified about, sheets are only notified on changes.
+
+CREATE TABLE IF NOT EXISTS document_transclusions (
+    org             TEXT NOT NULL,
+    sheet           UUID NOT NULL,
+    block_id        TEXT NOT NULL,
+    source          UUID NOT NULL,
+    source_block    TEXT NOT NULL,
+    source_hash     TEXT,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (org, sheet, block_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_transclusions_source
+    ON document_transclusions (org, source);
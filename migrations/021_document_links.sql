@@ -0,0 +1,19 @@
+-- References between documents
+--
+-- Indexed from the saved JSON of every document: referenced statements of statement
+-- grids, transcluded blocks and document ids mentioned in attribute values. `path`
+-- is the location of the reference in the referencing document. Documents are
+-- indexed on their next save.
+
+CREATE TABLE IF NOT EXISTS document_links (
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL,
+    target          UUID NOT NULL,
+    kind            TEXT NOT NULL,
+    path            TEXT NOT NULL,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (org, document, target, kind, path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_links_target
+    ON document_links (org, target);
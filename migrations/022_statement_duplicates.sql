@@ -0,0 +1,13 @@
+-- Duplicated statements per org
+--
+-- The latest report of the deduplication analysis: clusters of statements with the
+-- same or nearly the same text, across statement documents and the statements
+-- embedded in sheets. Every run replaces the report of the org.
+
+CREATE TABLE IF NOT EXISTS org_statement_duplicates (
+    org             TEXT PRIMARY KEY,
+    threshold       DOUBLE PRECISION NOT NULL,
+    statements      INTEGER NOT NULL,
+    clusters        JSONB NOT NULL,
+    generated_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
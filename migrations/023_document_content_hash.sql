@@ -0,0 +1,9 @@
+-- Content hash per saved version
+--
+-- Hash of the content of a version without its metadata (ACLs, properties and
+-- transclusion notifications). Consumers like search indexers skip versions whose
+-- content hash equals the one of the previous version. NULL for versions saved
+-- before the hash was recorded.
+
+ALTER TABLE document_streams
+    ADD COLUMN IF NOT EXISTS content_sha256 TEXT;
@@ -0,0 +1,15 @@
+-- First-open initializations of documents
+--
+-- The initialization hooks of the content type of a document run the first time
+-- the document is loaded into a room. A row marks the document as initialized and
+-- records which hooks ran, by whom and when.
+
+CREATE TABLE IF NOT EXISTS document_initializations (
+    org          TEXT NOT NULL,
+    document     UUID NOT NULL REFERENCES documents(id),
+    content_type TEXT NOT NULL,
+    hooks        TEXT[] NOT NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by   TEXT NOT NULL,
+    PRIMARY KEY (org, document)
+);
@@ -0,0 +1,14 @@
+-- Document types per organization
+--
+-- `types` maps every document type (colab-statement, colab-sheet) to the content
+-- types registered for it. In strict mode documents whose type or content type
+-- isn't registered can't be loaded, imported or saved. Organizations without a
+-- row aren't strict.
+
+CREATE TABLE IF NOT EXISTS org_document_types (
+    org         TEXT PRIMARY KEY,
+    strict      BOOLEAN NOT NULL DEFAULT FALSE,
+    types       JSONB NOT NULL DEFAULT '{}',
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by  TEXT NOT NULL
+);
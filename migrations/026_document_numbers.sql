@@ -0,0 +1,27 @@
+-- Stable numbers of documents and blocks
+--
+-- Numbers like CLM-0042 are handed out per organization and prefix from
+-- `org_number_sequences`, which only counts up. A number is recorded in
+-- `document_numbers` once and never moved to another document or block, nor
+-- handed out again when its block is removed. `block_id` is empty for the
+-- number of the document itself.
+
+CREATE TABLE IF NOT EXISTS org_number_sequences (
+    org         TEXT NOT NULL,
+    prefix      TEXT NOT NULL,
+    last        INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (org, prefix)
+);
+
+CREATE TABLE IF NOT EXISTS document_numbers (
+    org         TEXT NOT NULL,
+    number      TEXT NOT NULL,
+    prefix      TEXT NOT NULL,
+    seq         INTEGER NOT NULL,
+    document    UUID NOT NULL,
+    block_id    TEXT NOT NULL DEFAULT '',
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by  TEXT NOT NULL,
+    PRIMARY KEY (org, number),
+    UNIQUE (org, document, block_id)
+);
@@ -0,0 +1,38 @@
+-- Bulk find-and-replace jobs and version tags
+--
+-- A job replaces a pattern across the documents of an org or library in the
+-- background, `results` holds the matches per document and grows while the job
+-- runs. Before a document is changed it is tagged: the tag records the state
+-- the replacement was applied to and the peer applying it, so the replacement
+-- can be reverted.
+
+CREATE TABLE IF NOT EXISTS replace_jobs (
+    id          UUID PRIMARY KEY,
+    org         TEXT NOT NULL,
+    request     JSONB NOT NULL,
+    dry_run     BOOLEAN NOT NULL,
+    status      TEXT NOT NULL,
+    documents   INTEGER NOT NULL DEFAULT 0,
+    processed   INTEGER NOT NULL DEFAULT 0,
+    results     JSONB NOT NULL DEFAULT '[]',
+    error       TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by  TEXT NOT NULL,
+    finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_replace_jobs_org
+    ON replace_jobs (org, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS document_version_tags (
+    org         TEXT NOT NULL,
+    document    UUID NOT NULL,
+    tag         TEXT NOT NULL,
+    -- Latest saved version when the document was tagged
+    version     INTEGER,
+    state_vv    JSONB NOT NULL,
+    peer        TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by  TEXT NOT NULL,
+    PRIMARY KEY (org, document, tag)
+);
@@ -0,0 +1,19 @@
+-- Preferences and read markers of a user per document
+--
+-- `last_read_vv` is the version vector of the document when the user last
+-- marked it read, keyed by peer id. Changes made after it are unread.
+-- `pinned_blocks` and `collapsed_sections` hold block ids of sheets or
+-- language codes of statements.
+
+CREATE TABLE IF NOT EXISTS document_user_prefs (
+    org                 TEXT NOT NULL,
+    document            UUID NOT NULL,
+    prpl                TEXT NOT NULL,
+    last_read_version   INTEGER,
+    last_read_vv        JSONB,
+    last_read_at        TIMESTAMPTZ,
+    pinned_blocks       TEXT[] NOT NULL DEFAULT '{}',
+    collapsed_sections  TEXT[] NOT NULL DEFAULT '{}',
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (org, document, prpl)
+);
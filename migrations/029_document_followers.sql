@@ -0,0 +1,20 @@
+-- Documents followed by users for the digests
+--
+-- `frequency` is "daily" or "weekly". `last_vv` is the version vector of the
+-- document when the last digest was compiled, keyed by peer id, the next
+-- digest lists the changes since. `last_digest_at` starts at the time the
+-- document was followed.
+
+CREATE TABLE IF NOT EXISTS document_followers (
+    org             TEXT NOT NULL,
+    document        UUID NOT NULL,
+    prpl            TEXT NOT NULL,
+    frequency       TEXT NOT NULL DEFAULT 'daily',
+    last_vv         JSONB,
+    last_digest_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (org, document, prpl)
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_followers_digest
+    ON document_followers (last_digest_at);
@@ -0,0 +1,18 @@
+-- Users watching documents
+--
+-- Watching subscribes a user to the events of a document, apart from the
+-- principals in its ACLs. Notifications of the events go to the watchers, and
+-- the digests only cover followed documents that are still watched.
+
+CREATE TABLE IF NOT EXISTS document_watchers (
+    org         TEXT NOT NULL,
+    document    UUID NOT NULL,
+    prpl        TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (org, document, prpl)
+);
+
+-- Followed documents are watched
+INSERT INTO document_watchers (org, document, prpl, created_at)
+SELECT org, document, prpl, created_at FROM document_followers
+ON CONFLICT DO NOTHING;
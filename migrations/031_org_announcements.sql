@@ -0,0 +1,20 @@
+-- Announcements broadcast to the connected clients of an organization
+--
+-- An announcement (maintenance window, policy notice) is pushed to the open
+-- ephemeral rooms of the organization from `starts_at` until `expires_at`. It
+-- never touches the content of documents.
+
+CREATE TABLE IF NOT EXISTS org_announcements (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    org         TEXT NOT NULL,
+    message     TEXT NOT NULL,
+    level       TEXT NOT NULL DEFAULT 'info',
+    starts_at   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at  TIMESTAMPTZ NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by  TEXT NOT NULL,
+    CHECK (expires_at > starts_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_org_announcements_expiry
+    ON org_announcements (expires_at);
@@ -0,0 +1,17 @@
+-- Locale settings per organization
+--
+-- The locale, timezone and date formats used to render dates in exports (HTML,
+-- PDF, CSV) and digests. `content_types` maps a content type to settings that
+-- override the ones of the organization for its documents, unset fields fall
+-- back to the organization and then to the defaults of the service.
+
+CREATE TABLE IF NOT EXISTS org_locale_settings (
+    org              TEXT PRIMARY KEY,
+    locale           TEXT,
+    timezone         TEXT,
+    date_format      TEXT,
+    datetime_format  TEXT,
+    content_types    JSONB NOT NULL DEFAULT '{}',
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by       TEXT NOT NULL
+);
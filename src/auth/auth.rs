@@ -4,7 +4,7 @@ use axum::{http::StatusCode, Json};
 use uuid::Uuid;
 use crate::models::ErrorResponse;
 
-const CLOUD_ADMIN_PRPL: &str = "r/Colabri-CloudAdmin";
+pub const CLOUD_ADMIN_PRPL: &str = "r/Colabri-CloudAdmin";
 
 pub fn _is_authenticated(prpls: &Vec<String>) -> bool {
     !prpls.is_empty()
@@ -58,7 +58,7 @@ pub fn ensure_service(prpls: &Vec<String>, service_name: &str) -> Result<String,
     })))
 }
 
-pub fn _ensure_org_member(prpls: &Vec<String>, org_id: &str) -> Result<(Option<Uuid>, String), (StatusCode, Json<ErrorResponse>)> {
+pub fn ensure_org_member(prpls: &Vec<String>, org_id: &str) -> Result<(Option<Uuid>, String), (StatusCode, Json<ErrorResponse>)> {
     let org_prefix = format!("{}/u/", org_id);
     if let Some(p) = prpls.iter().find(|p| p.starts_with(&org_prefix)) {
         let uuid_str: String = p.strip_prefix(&org_prefix).unwrap().to_string();
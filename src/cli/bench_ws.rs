@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use loro::{Container, ExportMode, LoroDoc, LoroText, ValueOrContainer, VersionVector};
+use loro_protocol::{BatchId, CrdtType, ProtocolMessage};
+use serde::Serialize;
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, http::HeaderValue, Message};
+use uuid::Uuid;
+
+// Load test of a running server with simulated collaborative clients.
+// Every client joins one of the rooms, syncs its state and edits the first text of the document at the
+// configured rate, inserting a character and deleting it again so the document doesn't grow. The sync
+// latency is the time from a client sending an edit until another client of the room imported it.
+// With the API URL, the save status of the rooms is polled during the run and until every change is
+// saved after it, the save lag is the age of the last save while a room has unsaved changes.
+
+const SAVE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// Options of a load test
+pub struct BenchOptions {
+    // WebSocket URL of the server, without the org
+    pub url: String,
+    // API URL of the server to poll the save status, the save lag isn't measured without it
+    pub api_url: Option<String>,
+    pub org: String,
+    pub docs: Vec<Uuid>,
+    pub token: String,
+    pub clients: usize,
+    // Edits per second of every client
+    pub rate: f64,
+    pub duration: Duration,
+}
+
+/// Percentiles of a series of measurements in milliseconds
+#[derive(Serialize, Default)]
+pub struct Percentiles {
+    pub samples: usize,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// Report of a load test
+#[derive(Serialize)]
+pub struct BenchReport {
+    pub clients: usize,
+    pub connected: usize,
+    pub rooms: usize,
+    #[serde(rename = "durationSecs")]
+    pub duration_secs: u64,
+    pub edits: u64,
+    #[serde(rename = "editsPerSec")]
+    pub edits_per_sec: f64,
+    #[serde(rename = "joinMs")]
+    pub join_ms: Percentiles,
+    #[serde(rename = "syncMs")]
+    pub sync_ms: Percentiles,
+    #[serde(rename = "saveLagMs", skip_serializing_if = "Option::is_none")]
+    pub save_lag_ms: Option<Percentiles>,
+    // Time after the last edit until every change of a room was saved
+    #[serde(rename = "flushMs", skip_serializing_if = "Option::is_none")]
+    pub flush_ms: Option<Percentiles>,
+    #[serde(rename = "errorCount")]
+    pub error_count: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Default)]
+struct Measurements {
+    join_ms: Vec<f64>,
+    sync_ms: Vec<f64>,
+    save_lag_ms: Vec<f64>,
+    flush_ms: Vec<f64>,
+    edits: u64,
+    connected: usize,
+    errors: Vec<String>,
+}
+
+// When the edits of every peer were sent, by the counter they end at
+type SentEdits = Arc<Mutex<HashMap<u64, BTreeMap<i32, Instant>>>>;
+
+fn percentiles(mut values: Vec<f64>) -> Percentiles {
+    if values.is_empty() {
+        return Percentiles::default();
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let at = |q: f64| values[((values.len() - 1) as f64 * q).round() as usize];
+    Percentiles {
+        samples: values.len(),
+        p50: at(0.50),
+        p95: at(0.95),
+        p99: at(0.99),
+        max: values[values.len() - 1],
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// Small xorshift generator, the edits only need to be spread over the text
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        let seed = u64::from_le_bytes(Uuid::new_v4().as_bytes()[..8].try_into().unwrap_or_default());
+        Rng(seed | 1)
+    }
+
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound.max(1) as u64) as usize
+    }
+}
+
+fn batch_id() -> BatchId {
+    BatchId(Uuid::new_v4().as_bytes()[..8].try_into().unwrap_or_default())
+}
+
+// The first text of a container, depth first
+fn first_text(container: &Container, depth: usize) -> Option<LoroText> {
+    const MAX_DEPTH: usize = 100; // Prevent stack overflow
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let mut children: Vec<Container> = Vec::new();
+    match container {
+        Container::Text(text) => return Some(text.clone()),
+        Container::Map(map) => map.for_each(|_, value| {
+            if let ValueOrContainer::Container(child) = value {
+                children.push(child);
+            }
+        }),
+        Container::List(list) => (0..list.len()).for_each(|idx| {
+            if let Some(ValueOrContainer::Container(child)) = list.get(idx) {
+                children.push(child);
+            }
+        }),
+        Container::MovableList(list) => (0..list.len()).for_each(|idx| {
+            if let Some(ValueOrContainer::Container(child)) = list.get(idx) {
+                children.push(child);
+            }
+        }),
+        _ => {}
+    }
+    children.iter().find_map(|child| first_text(child, depth + 1))
+}
+
+fn doc_text(doc: &LoroDoc) -> Option<LoroText> {
+    first_text(&Container::Map(doc.get_map("content")), 0)
+        .or_else(|| first_text(&Container::MovableList(doc.get_movable_list("content")), 0))
+}
+
+// Record the sync latency of the edits of other peers a client just imported
+fn record_synced(doc: &LoroDoc, before: &VersionVector, sent: &SentEdits, measurements: &Mutex<Measurements>) {
+    let now = Instant::now();
+    let own_peer = doc.peer_id();
+    let mut latencies = Vec::new();
+    if let Ok(sent) = sent.lock() {
+        for (peer, counter) in doc.oplog_vv().iter() {
+            if *peer == own_peer {
+                continue;
+            }
+            let from = before.get(peer).copied().unwrap_or(0);
+            if let Some(edits) = sent.get(peer) {
+                latencies.extend(edits.range(from + 1..=*counter).map(|(_, sent_at)| millis(now - *sent_at)));
+            }
+        }
+    }
+    if let Ok(mut measurements) = measurements.lock() {
+        measurements.sync_ms.extend(latencies);
+    }
+}
+
+fn record_error(measurements: &Mutex<Measurements>, error: String) {
+    if let Ok(mut measurements) = measurements.lock() {
+        measurements.errors.push(error);
+    }
+}
+
+// A simulated client: join a room, keep it in sync and edit it until the deadline
+async fn run_client(client: usize, options: Arc<BenchOptions>, doc_id: Uuid, deadline: Instant, sent: SentEdits, measurements: Arc<Mutex<Measurements>>) -> Result<(), String> {
+    let url = format!("{}/{}", options.url.trim_end_matches('/'), options.org);
+    let mut request = url.as_str().into_client_request().map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    let authorization = HeaderValue::from_str(&format!("Bearer {}", options.token)).map_err(|e| format!("Invalid token: {}", e))?;
+    request.headers_mut().insert("Authorization", authorization);
+    let (socket, _) = tokio_tungstenite::connect_async(request).await.map_err(|e| format!("Client {} failed to connect: {}", client, e))?;
+    let (mut sink, mut stream) = socket.split();
+    let room_id = doc_id.to_string();
+
+    // 1. Join the room
+    let started = Instant::now();
+    let join = ProtocolMessage::JoinRequest { crdt: CrdtType::Loro, room_id: room_id.clone(), auth: Vec::new(), version: Vec::new() };
+    let join = loro_protocol::encode(&join).map_err(|e| format!("Failed to encode the join: {}", e))?;
+    sink.send(Message::Binary(join.into())).await.map_err(|e| format!("Client {} failed to join: {}", client, e))?;
+    let doc = LoroDoc::new();
+    let mut fragments: HashMap<BatchId, Vec<Option<Vec<u8>>>> = HashMap::new();
+    let mut joined = false;
+
+    // 2. Sync and edit until the deadline
+    let mut rng = Rng::new();
+    let mut inserted: Option<usize> = None;
+    let mut sent_vv = doc.oplog_vv();
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / options.rate.max(0.001)));
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline.into()) => break,
+            _ = ticker.tick(), if joined => {
+                let Some(text) = doc_text(&doc) else {
+                    continue;
+                };
+                let edited = match inserted.take() {
+                    Some(pos) if pos < text.len_unicode() => text.delete(pos, 1),
+                    _ => {
+                        let pos = rng.next(text.len_unicode() + 1);
+                        inserted = Some(pos);
+                        text.insert(pos, &((b'a' + rng.next(26) as u8) as char).to_string())
+                    }
+                };
+                if let Err(e) = edited {
+                    record_error(&measurements, format!("Client {} failed to edit: {}", client, e));
+                    continue;
+                }
+                doc.commit();
+                let update = doc.export(ExportMode::updates(&sent_vv)).map_err(|e| format!("Failed to export the edit: {}", e))?;
+                sent_vv = doc.oplog_vv();
+                if let (Ok(mut sent), Some(counter)) = (sent.lock(), sent_vv.get(&doc.peer_id())) {
+                    sent.entry(doc.peer_id()).or_default().insert(*counter, Instant::now());
+                }
+                let message = ProtocolMessage::DocUpdate { crdt: CrdtType::Loro, room_id: room_id.clone(), updates: vec![update], batch_id: batch_id() };
+                let message = loro_protocol::encode(&message).map_err(|e| format!("Failed to encode the edit: {}", e))?;
+                sink.send(Message::Binary(message.into())).await.map_err(|e| format!("Client {} failed to send an edit: {}", client, e))?;
+                if let Ok(mut measurements) = measurements.lock() {
+                    measurements.edits += 1;
+                }
+            }
+            message = stream.next() => {
+                let data = match message {
+                    Some(Ok(Message::Binary(data))) => data,
+                    Some(Ok(Message::Text(text))) if text.as_str() == "ping" => {
+                        let _ = sink.send(Message::Text("pong".into())).await;
+                        continue;
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(format!("Client {} lost the connection: {}", client, e)),
+                    None => return Err(format!("Client {} was disconnected", client)),
+                };
+                let updates = match loro_protocol::decode(&data) {
+                    Ok(ProtocolMessage::JoinResponseOk { .. }) => {
+                        joined = true;
+                        if let Ok(mut measurements) = measurements.lock() {
+                            measurements.join_ms.push(millis(started.elapsed()));
+                            measurements.connected += 1;
+                        }
+                        continue;
+                    }
+                    Ok(ProtocolMessage::JoinError { message, .. }) => return Err(format!("Client {} was refused: {}", client, message)),
+                    Ok(ProtocolMessage::DocUpdate { updates, .. }) => updates,
+                    Ok(ProtocolMessage::DocUpdateFragmentHeader { batch_id, fragment_count, .. }) => {
+                        fragments.insert(batch_id, vec![None; fragment_count as usize]);
+                        continue;
+                    }
+                    Ok(ProtocolMessage::DocUpdateFragment { batch_id, index, fragment, .. }) => {
+                        let complete = match fragments.get_mut(&batch_id) {
+                            Some(parts) => {
+                                if let Some(part) = parts.get_mut(index as usize) {
+                                    *part = Some(fragment);
+                                }
+                                parts.iter().all(|part| part.is_some())
+                            }
+                            None => false,
+                        };
+                        if !complete {
+                            continue;
+                        }
+                        let parts = fragments.remove(&batch_id).unwrap_or_default();
+                        vec![parts.into_iter().flatten().flatten().collect()]
+                    }
+                    Ok(_) => continue,
+                    Err(e) => {
+                        record_error(&measurements, format!("Client {} received an invalid message: {}", client, e));
+                        continue;
+                    }
+                };
+                let before = doc.oplog_vv();
+                for update in updates {
+                    if let Err(e) = doc.import(&update) {
+                        record_error(&measurements, format!("Client {} failed to import an update: {}", client, e));
+                    }
+                }
+                record_synced(&doc, &before, &sent, &measurements);
+                sent_vv.merge(&doc.oplog_vv());
+            }
+        }
+    }
+    let _ = sink.send(Message::Close(None)).await;
+    Ok(())
+}
+
+// The save status of a room, with the age of its last save when it has unsaved changes
+async fn save_status(http: &reqwest::Client, options: &BenchOptions, doc_id: Uuid) -> Result<(bool, Option<f64>), String> {
+    let api_url = options.api_url.as_deref().unwrap_or_default().trim_end_matches('/');
+    let url = format!("{}/api/v1/{}/documents/{}/save-status", api_url, options.org, doc_id);
+    let status: serde_json::Value = http.get(&url)
+        .bearer_auth(&options.token)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to get the save status of '{}': {}", doc_id, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid save status of '{}': {}", doc_id, e))?;
+    let all_saved = status.get("allSaved").and_then(|v| v.as_bool()).unwrap_or(false);
+    let dirty = status.get("dirty").and_then(|v| v.as_bool()).unwrap_or(false);
+    let last_saved_at = status.get("lastSavedAt").and_then(|v| v.as_str()).and_then(|v| v.parse::<DateTime<Utc>>().ok());
+    let lag = match last_saved_at {
+        Some(last_saved_at) if dirty => Some((Utc::now() - last_saved_at).num_milliseconds().max(0) as f64),
+        _ => None,
+    };
+    Ok((all_saved, lag))
+}
+
+// Poll the save lag of the rooms during the run, then wait for every room to be saved
+async fn watch_saves(options: Arc<BenchOptions>, deadline: Instant, measurements: Arc<Mutex<Measurements>>) {
+    let http = reqwest::Client::new();
+    while Instant::now() < deadline {
+        tokio::time::sleep(SAVE_POLL_INTERVAL).await;
+        for doc_id in &options.docs {
+            match save_status(&http, &options, *doc_id).await {
+                Ok((_, Some(lag))) => {
+                    if let Ok(mut measurements) = measurements.lock() {
+                        measurements.save_lag_ms.push(lag);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => record_error(&measurements, e),
+            }
+        }
+    }
+    for doc_id in &options.docs {
+        let flush_started = Instant::now();
+        loop {
+            match save_status(&http, &options, *doc_id).await {
+                Ok((true, _)) => {
+                    if let Ok(mut measurements) = measurements.lock() {
+                        measurements.flush_ms.push(millis(deadline.elapsed()));
+                    }
+                    break;
+                }
+                Ok(_) if flush_started.elapsed() < FLUSH_TIMEOUT => tokio::time::sleep(Duration::from_millis(500)).await,
+                Ok(_) => {
+                    record_error(&measurements, format!("Room '{}' wasn't saved within {} s after the run", doc_id, FLUSH_TIMEOUT.as_secs()));
+                    break;
+                }
+                Err(e) => {
+                    record_error(&measurements, e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Run a load test, the clients are spread over the rooms round robin
+pub async fn run(options: BenchOptions) -> BenchReport {
+    let options = Arc::new(options);
+    let measurements: Arc<Mutex<Measurements>> = Arc::new(Mutex::new(Measurements::default()));
+    let sent: SentEdits = Arc::new(Mutex::new(HashMap::new()));
+    let started = Instant::now();
+    let deadline = started + options.duration;
+
+    let mut tasks = Vec::new();
+    for client in 0..options.clients {
+        let doc_id = options.docs[client % options.docs.len()];
+        let (options, sent, measurements) = (options.clone(), sent.clone(), measurements.clone());
+        tasks.push(tokio::spawn(async move {
+            if let Err(e) = run_client(client, options, doc_id, deadline, sent, measurements.clone()).await {
+                record_error(&measurements, e);
+            }
+        }));
+    }
+    let watcher = options.api_url.as_ref().map(|_| tokio::spawn(watch_saves(options.clone(), deadline, measurements.clone())));
+    for task in tasks {
+        let _ = task.await;
+    }
+    if let Some(watcher) = watcher {
+        let _ = watcher.await;
+    }
+
+    let measurements = std::mem::take(&mut *measurements.lock().unwrap_or_else(|e| e.into_inner()));
+    let with_api = options.api_url.is_some();
+    BenchReport {
+        clients: options.clients,
+        connected: measurements.connected,
+        rooms: options.docs.len(),
+        duration_secs: options.duration.as_secs(),
+        edits: measurements.edits,
+        edits_per_sec: measurements.edits as f64 / options.duration.as_secs_f64().max(1.0),
+        join_ms: percentiles(measurements.join_ms),
+        sync_ms: percentiles(measurements.sync_ms),
+        save_lag_ms: Some(percentiles(measurements.save_lag_ms)).filter(|_| with_api),
+        flush_ms: Some(percentiles(measurements.flush_ms)).filter(|_| with_api),
+        error_count: measurements.errors.len(),
+        errors: measurements.errors.into_iter().take(MAX_REPORTED_ERRORS).collect(),
+    }
+}
@@ -0,0 +1,404 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use clap::{Parser, Subcommand, ValueEnum};
+use loro::{ExportMode, LoroDoc, ToJson};
+use loro_websocket_server::HubRegistry;
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::doctypes;
+use crate::models::{ColabModel, ColabPackage, DocumentCreateRequest};
+use crate::services::{doc_create_service, doc_load_service, limits_service, recording_service, signing_service, storage_service};
+use crate::ws::docctx::DocContext;
+
+mod bench_ws;
+
+// Maintenance subcommands of the binary.
+// They reuse the service layer against the configured database without starting the HTTP and
+// WebSocket servers, so operators can run them from a shell. No rooms are open in the process, every
+// document is read from and written to the database. Changes are attributed to the principal of
+// `--by`, the service itself by default.
+
+const CLI_PRPL: &str = "s/colabri-doc";
+
+#[derive(Parser)]
+#[command(name = "colabri-doc", about = "Colabri document server", version)]
+pub struct Cli {
+    /// Check the configured dependencies and exit non-zero when one fails
+    #[arg(long)]
+    pub selftest: bool,
+
+    /// Answer the principals of users from a JSON file instead of the app service, development only
+    #[arg(long, value_name = "FILE")]
+    pub mock_app_service: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Export the latest or a specific version of a document
+    ExportDoc {
+        #[arg(long)]
+        org: String,
+        #[arg(long)]
+        doc: Uuid,
+        #[arg(long)]
+        version: Option<u32>,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// File to write to, stdout by default
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Create a document from the JSON of an exported document
+    ImportDoc {
+        #[arg(long)]
+        org: String,
+        /// The JSON of the document
+        #[arg(long)]
+        file: PathBuf,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        owner: String,
+        /// Library or folder holding the document
+        #[arg(long)]
+        container: Option<Uuid>,
+        #[arg(long)]
+        container_type: Option<String>,
+        #[arg(long, default_value = CLI_PRPL)]
+        by: String,
+    },
+    /// Delete the versions of a document beyond its history budget
+    Compact {
+        #[arg(long)]
+        org: String,
+        #[arg(long)]
+        doc: Uuid,
+        /// Only list the versions that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long, default_value = CLI_PRPL)]
+        by: String,
+    },
+    /// Check that a document loads, is valid, matches its stored hash and its publication signature
+    Verify {
+        #[arg(long)]
+        org: String,
+        #[arg(long)]
+        doc: Uuid,
+    },
+    /// Apply the database migrations embedded in the binary, the server doesn't run them at startup
+    Migrate,
+    /// Re-encode the stored versions of documents as snapshots or as updates only
+    ReencodeStreams {
+        #[arg(long)]
+        org: String,
+        /// A single document, every document of the org by default
+        #[arg(long)]
+        doc: Option<Uuid>,
+        #[arg(long, value_enum, default_value_t = StreamEncoding::Snapshot)]
+        encoding: StreamEncoding,
+        /// Only count the versions that would be re-encoded
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long, default_value = CLI_PRPL)]
+        by: String,
+    },
+    /// Simulate collaborative clients against a running server and report sync latency and save lag
+    BenchWs {
+        /// WebSocket URL of the server, without the org
+        #[arg(long, default_value = "ws://localhost:9001")]
+        url: String,
+        /// API URL of the server to measure the save lag, e.g. http://localhost:3000
+        #[arg(long)]
+        api_url: Option<String>,
+        #[arg(long)]
+        org: String,
+        /// Documents to edit, the clients are spread over them
+        #[arg(long = "doc", required = true)]
+        docs: Vec<Uuid>,
+        /// Bearer token of the clients
+        #[arg(long)]
+        token: String,
+        #[arg(long, default_value_t = 10)]
+        clients: usize,
+        /// Edits per second of every client
+        #[arg(long, default_value_t = 1.0)]
+        rate: f64,
+        /// Duration of the run in seconds
+        #[arg(long, default_value_t = 60)]
+        duration: u64,
+    },
+    /// Replay a session recording into a fresh document and check the resulting content hash
+    ReplayRecording {
+        /// The recording, as downloaded from the admin API
+        #[arg(long)]
+        file: PathBuf,
+        /// Snapshot to start from instead of the one the recording loaded
+        #[arg(long)]
+        fixture: Option<PathBuf>,
+        /// Content hash the replayed document must have
+        #[arg(long)]
+        expect_hash: Option<String>,
+        /// File to write the JSON of the replayed document to
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Snapshot,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StreamEncoding {
+    Snapshot,
+    Updates,
+}
+
+// Run a subcommand, the error is reported by the caller and ends the process with a failure
+pub async fn run(command: Command, registry: Arc<HubRegistry<DocContext>>) -> Result<(), String> {
+    match command {
+        Command::ExportDoc { org, doc, version, format, out } => export_doc(&registry, &org, doc, version, format, out).await,
+        Command::ImportDoc { org, file, name, owner, container, container_type, by } => {
+            import_doc(&org, file, name, owner, container, container_type, by).await
+        }
+        Command::Compact { org, doc, dry_run, by } => compact(&org, doc, dry_run, &by).await,
+        Command::Verify { org, doc } => verify(&registry, &org, doc).await,
+        Command::Migrate => {
+            let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+            db.run_migrations().await.map_err(|e| format!("Failed to migrate the database: {}", e))?;
+            println!("Migrations applied");
+            Ok(())
+        }
+        Command::ReencodeStreams { org, doc, encoding, dry_run, by } => reencode_streams(&org, doc, encoding, dry_run, &by).await,
+        Command::BenchWs { url, api_url, org, docs, token, clients, rate, duration } => {
+            if rate <= 0.0 {
+                return Err("The rate must be positive".to_string());
+            }
+            let options = bench_ws::BenchOptions { url, api_url, org, docs, token, clients, rate, duration: std::time::Duration::from_secs(duration) };
+            let report = bench_ws::run(options).await;
+            println!("{}", serde_json::to_string_pretty(&report).map_err(|e| format!("Failed to serialize the report: {}", e))?);
+            Ok(())
+        }
+        Command::ReplayRecording { file, fixture, expect_hash, out } => replay_recording(file, fixture, expect_hash, out).await,
+    }
+}
+
+async fn export_doc(registry: &Arc<HubRegistry<DocContext>>, org: &str, doc: Uuid, version: Option<u32>, format: ExportFormat, out: Option<PathBuf>) -> Result<(), String> {
+    let doc_id = doc.to_string();
+    let loaded = match version {
+        Some(version) => doc_load_service::load_loro_doc_version(registry, org, &doc_id, version).await?,
+        None => doc_load_service::load_loro_doc(registry, org, &doc_id).await?,
+    };
+    let (loro_doc, _) = loaded.ok_or_else(|| format!("Document '{}' not found", doc_id))?;
+    let bytes = match format {
+        ExportFormat::Json => serde_json::to_vec_pretty(&loro_doc.get_deep_value().to_json_value())
+            .map_err(|e| format!("Failed to serialize document '{}': {}", doc_id, e))?,
+        ExportFormat::Snapshot => loro_doc.export(ExportMode::Snapshot)
+            .map_err(|e| format!("Failed to export snapshot of document '{}': {}", doc_id, e))?,
+    };
+    match out {
+        Some(path) => {
+            tokio::fs::write(&path, &bytes).await.map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            eprintln!("Exported document '{}' to {}", doc_id, path.display());
+        }
+        None => {
+            use std::io::Write;
+            std::io::stdout().write_all(&bytes).map_err(|e| format!("Failed to write the export: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+async fn import_doc(org: &str, file: PathBuf, name: String, owner: String, container: Option<Uuid>, container_type: Option<String>, by: String) -> Result<(), String> {
+    let content = tokio::fs::read(&file).await.map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let json: serde_json::Value = serde_json::from_slice(&content).map_err(|e| format!("Invalid JSON in {}: {}", file.display(), e))?;
+    let property = |key: &str| json.pointer(&format!("/properties/{}", key)).and_then(|v| v.as_str()).map(|v| v.to_string());
+    let request = DocumentCreateRequest {
+        doc_type: property("type").ok_or_else(|| "The JSON has no properties.type".to_string())?,
+        content_type: property("contentType").ok_or_else(|| "The JSON has no properties.contentType".to_string())?,
+        name,
+        owner,
+        container: container.map(|container| container.to_string()),
+        container_type,
+        master_lang_code: property("masterLangCode"),
+        template: None,
+        acls: None,
+        by_prpl: by,
+    };
+    let doc_uuid = Uuid::new_v4();
+    doc_create_service::import(org, doc_uuid, container, json, &request).await??;
+    println!("{}", doc_uuid);
+    Ok(())
+}
+
+async fn compact(org: &str, doc: Uuid, dry_run: bool, by: &str) -> Result<(), String> {
+    let report = storage_service::storage_report(org, doc).await?
+        .ok_or_else(|| format!("Document '{}' not found", doc))?;
+    let prunable: Vec<i32> = report.versions.iter().filter(|v| v.prunable).map(|v| v.version as i32).collect();
+    if prunable.is_empty() {
+        println!("Nothing to compact, {} versions within the history budget", report.versions.len());
+        return Ok(());
+    }
+    if dry_run {
+        println!("Would delete {} versions ({} bytes): {:?}", prunable.len(), report.prunable_bytes, prunable);
+        return Ok(());
+    }
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let deleted = db.delete_doc_stream_versions(org, doc, &prunable, by)
+        .await
+        .map_err(|e| format!("Failed to delete versions of document '{}': {}", doc, e))?;
+    println!("Deleted {} versions ({} bytes)", deleted, report.prunable_bytes);
+    Ok(())
+}
+
+async fn verify(registry: &Arc<HubRegistry<DocContext>>, org: &str, doc: Uuid) -> Result<(), String> {
+    let doc_id = doc.to_string();
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let mut problems = Vec::new();
+
+    // 1. The latest version loads and is a valid document within the limits
+    let (loro_doc, _) = doc_load_service::load_loro_doc(registry, org, &doc_id).await?
+        .ok_or_else(|| format!("Document '{}' not found", doc_id))?;
+    let json = loro_doc.get_deep_value().to_json_value();
+    let doc_type = json.pointer("/properties/type").and_then(|t| t.as_str()).unwrap_or_default();
+    if let Err(e) = doctypes::get_or_error(doc_type).and_then(|t| t.validate(&json)) {
+        problems.push(format!("Invalid document: {}", e));
+    }
+    if let Err(e) = serde_json::from_value::<ColabModel>(json.clone()) {
+        problems.push(format!("Doesn't parse as a document model: {}", e));
+    }
+    if let Err(violation) = limits_service::check_json(&json, &limits_service::get_limits(org).await) {
+        problems.push(format!("Beyond the limits of the organization: {}", violation));
+    }
+
+    // 2. The content matches the hash stored with the latest version
+    let versions = db.get_document_stream_sizes(org, doc).await.map_err(|e| format!("Failed to load versions of document '{}': {}", doc_id, e))?;
+    match versions.last().and_then(|latest| latest.content_sha256.as_ref()) {
+        Some(stored) if *stored != storage_service::content_hash(&json) => problems.push("The content doesn't match the hash stored with the latest version".to_string()),
+        Some(_) => println!("Content hash matches"),
+        None => println!("No content hash stored with the latest version"),
+    }
+
+    // 3. The published version matches its signature
+    match db.get_latest_document_signature(org, doc).await.map_err(|e| format!("Failed to load signature of document '{}': {}", doc_id, e))? {
+        Some(row) => {
+            let published = doc_load_service::load_loro_doc_version(registry, org, &doc_id, row.version.max(0) as u32).await?;
+            let published_json = published.map(|(published, _)| published.get_deep_value().to_json_value());
+            let (signature_valid, _, json_matches) = signing_service::verify_row(&row, None, published_json.as_ref())?;
+            if !signature_valid {
+                problems.push(format!("The signature of published version {} is invalid", row.version));
+            } else if json_matches == Some(false) {
+                problems.push(format!("Published version {} doesn't match its signature", row.version));
+            } else if json_matches.is_none() {
+                problems.push(format!("Published version {} is missing", row.version));
+            } else {
+                println!("Published version {} matches its signature", row.version);
+            }
+        }
+        None => println!("Not published"),
+    }
+
+    if problems.is_empty() {
+        println!("Document '{}' verified", doc_id);
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+// Re-encode the state of a stored version, None when it already has the encoding
+fn reencode(content: &[u8], encoding: StreamEncoding) -> Result<Option<Vec<u8>>, String> {
+    let package: ColabPackage = serde_cbor::from_slice(content).map_err(|e| format!("Failed to decode ColabPackage: {}", e))?;
+    let loro_doc = LoroDoc::new();
+    loro_doc.import(&package.snapshot).map_err(|e| format!("Failed to import the stored state: {}", e))?;
+    let snapshot = match encoding {
+        StreamEncoding::Snapshot => loro_doc.export(ExportMode::Snapshot),
+        StreamEncoding::Updates => loro_doc.export(ExportMode::all_updates()),
+    }
+    .map_err(|e| format!("Failed to export the state: {}", e))?;
+    let blob = serde_cbor::to_vec(&ColabPackage { snapshot, peer_map: package.peer_map })
+        .map_err(|e| format!("Failed to serialize ColabPackage: {}", e))?;
+    Ok(Some(blob).filter(|blob| blob.as_slice() != content))
+}
+
+async fn reencode_streams(org: &str, doc: Option<Uuid>, encoding: StreamEncoding, dry_run: bool, by: &str) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let documents = match doc {
+        Some(doc) => vec![doc],
+        None => db.get_org_documents(org, None)
+            .await
+            .map_err(|e| format!("Failed to list the documents of '{}': {}", org, e))?
+            .into_iter()
+            .map(|document| document.id)
+            .collect(),
+    };
+    let (mut reencoded, mut unchanged, mut failed) = (0, 0, 0);
+    for doc_uuid in documents {
+        let document = match db.load_colab_doc(org, doc_uuid).await {
+            Ok(Some(document)) => document,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("Failed to load document '{}': {}", doc_uuid, e);
+                failed += 1;
+                continue;
+            }
+        };
+        // Streams in the cold tier are left alone, they are re-encoded once promoted
+        for stream in document.streams.iter().filter(|stream| stream.name == "main") {
+            let Some(content) = &stream.content else {
+                continue;
+            };
+            match reencode(content, encoding) {
+                Ok(None) => unchanged += 1,
+                Ok(Some(_)) if dry_run => reencoded += 1,
+                Ok(Some(blob)) => match db.update_doc_stream_content(org, stream.id, blob, by).await {
+                    Ok(_) => reencoded += 1,
+                    Err(e) => {
+                        eprintln!("Failed to store version {} of document '{}': {}", stream.version, doc_uuid, e);
+                        failed += 1;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Failed to re-encode version {} of document '{}': {}", stream.version, doc_uuid, e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    let verb = if dry_run { "Would re-encode" } else { "Re-encoded" };
+    println!("{} {} versions, {} unchanged, {} failed", verb, reencoded, unchanged, failed);
+    if failed > 0 {
+        return Err(format!("{} versions failed", failed));
+    }
+    Ok(())
+}
+
+async fn replay_recording(file: PathBuf, fixture: Option<PathBuf>, expect_hash: Option<String>, out: Option<PathBuf>) -> Result<(), String> {
+    let recording = tokio::fs::read(&file).await.map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let frames = recording_service::parse_frames(&recording)?;
+    let fixture = match fixture {
+        Some(path) => Some(tokio::fs::read(&path).await.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?),
+        None => None,
+    };
+    let outcome = recording_service::replay(fixture.as_deref(), &frames)?;
+    println!(
+        "Replayed {} frames: {} updates applied, {} rejected, {} unanswered, content hash {}",
+        frames.len(), outcome.applied, outcome.rejected, outcome.unanswered, outcome.content_hash
+    );
+    if let Some(path) = out {
+        let json = serde_json::to_vec_pretty(&outcome.json).map_err(|e| format!("Failed to serialize the replayed document: {}", e))?;
+        tokio::fs::write(&path, json).await.map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    match expect_hash {
+        Some(expected) if expected.trim() != outcome.content_hash => {
+            Err(format!("The replayed content hash {} doesn't match the expected {}", outcome.content_hash, expected.trim()))
+        }
+        _ => Ok(()),
+    }
+}
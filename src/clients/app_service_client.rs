@@ -2,7 +2,9 @@ use chrono::{Duration, Utc};
 use jsonwebtoken::{encode, EncodingKey, Header};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use uuid::Uuid;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::OnceCell;
 use tracing::info;
@@ -15,6 +17,9 @@ pub struct AppServiceClient {
     base_url: String,
     jwt_secret: String,
     service_name: String,
+    // Principals per uid answered in-process instead of calling the app service, for local development.
+    // The "*" entry answers every uid without an entry of its own.
+    mock_prpls: Option<HashMap<String, Vec<String>>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,10 +42,31 @@ impl AppServiceClient {
             base_url,
             jwt_secret,
             service_name,
+            mock_prpls: None,
         }
     }
 
-    fn generate_token(&self) -> String {
+    /// Create a client that never calls the app service and answers the principals of the mapping
+    pub fn mock(mock_prpls: HashMap<String, Vec<String>>) -> Self {
+        Self {
+            mock_prpls: Some(mock_prpls),
+            ..Self::new(String::new(), String::new(), String::new())
+        }
+    }
+
+    pub fn is_mock(&self) -> bool {
+        self.mock_prpls.is_some()
+    }
+
+    fn mocked_prpls(mock_prpls: &HashMap<String, Vec<String>>, uid: &str) -> Vec<String> {
+        mock_prpls
+            .get(uid)
+            .or_else(|| mock_prpls.get("*"))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn generate_token(&self) -> String {
         let expiration = Utc::now()
             .checked_add_signed(Duration::seconds(60)) // 1 minute expiration
             .expect("valid timestamp")
@@ -77,6 +103,9 @@ impl AppServiceClient {
 
     /// Call the /auth/prpls/{uid} endpoint to get PRPLs for a user
     pub async fn get_prpls(&self, uid: &str) -> Result<serde_json::Value, reqwest::Error> {
+        if let Some(mock_prpls) = &self.mock_prpls {
+            return Ok(json!({ "prpls": Self::mocked_prpls(mock_prpls, uid) }));
+        }
         let token = self.generate_token();
         let url = format!("{}/auth/prpls/{}", self.base_url, uid);
         info!(
@@ -101,6 +130,10 @@ impl AppServiceClient {
         &self, org_id: &str,
         doc_id: &Uuid,
     ) -> Result<serde_json::Value, reqwest::Error> {
+        if self.is_mock() {
+            info!("Mock app service skipped the sync of document {} in org {}", doc_id, org_id);
+            return Ok(json!({}));
+        }
         let token = self.generate_token();
         let url = format!("{}/api/v1/{}/documents/{}/sync", self.base_url, org_id, doc_id);
         info!(
@@ -120,6 +153,41 @@ impl AppServiceClient {
             .await
     }
 
+    /// Call the /auth/prpls/{uid} endpoint and only return the status, for the self-test
+    pub async fn probe_prpls(&self, uid: &str) -> Result<reqwest::StatusCode, reqwest::Error> {
+        if self.is_mock() {
+            return Ok(reqwest::StatusCode::OK);
+        }
+        let token = self.generate_token();
+        let url = format!("{}/auth/prpls/{}", self.base_url, uid);
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        Ok(response.status())
+    }
+
+    // Call the /api/v1/{org_id}/digests endpoint to deliver the digest of a user
+    pub async fn send_digest(
+        &self, org_id: &str,
+        digest: &serde_json::Value,
+    ) -> Result<reqwest::StatusCode, reqwest::Error> {
+        if self.is_mock() {
+            info!("Mock app service skipped a digest in org {}", org_id);
+            return Ok(reqwest::StatusCode::OK);
+        }
+        let token = self.generate_token();
+        let url = format!("{}/api/v1/{}/digests", self.base_url, org_id);
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(digest)
+            .send()
+            .await?;
+        Ok(response.status())
+    }
+
     // Add more methods here as needed
 }
 
@@ -135,6 +203,20 @@ pub fn init_app_service_client(
         .map_err(|_| "AppServiceClient already initialized")
 }
 
+/// Initialize the global AppServiceClient as a mock answering the principals of a JSON file.
+/// The file maps uids to their principals, e.g. `{"alice": ["acme/u/alice"], "*": []}`.
+pub fn init_mock_app_service_client(path: &str) -> Result<usize, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read the mock principals {}: {}", path, e))?;
+    let mock_prpls: HashMap<String, Vec<String>> = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid mock principals in {}: {}", path, e))?;
+    let users = mock_prpls.len();
+    APP_SERVICE_CLIENT
+        .set(Arc::new(AppServiceClient::mock(mock_prpls)))
+        .map_err(|_| "AppServiceClient already initialized".to_string())?;
+    Ok(users)
+}
+
 /// Get the global AppServiceClient instance
 pub fn get_app_service_client() -> Option<Arc<AppServiceClient>> {
     APP_SERVICE_CLIENT.get().cloned()
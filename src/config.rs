@@ -32,6 +32,13 @@ pub struct Config {
     #[serde(default = "default_websocket_port")]
     pub websocket_port: u16,
 
+    /// Port of the internal admin listener serving the probes, metrics and admin endpoints
+    #[serde(default = "default_admin_port")]
+    pub admin_port: u16,
+
+    /// Interface of the internal admin listener, defaults to the server host
+    pub admin_host: Option<String>,
+
     /// Environment (dev, staging, prod)
     #[serde(default = "default_environment")]
     pub environment: String,
@@ -42,6 +49,10 @@ pub struct Config {
 
     // Cloud service identifiers
     pub cloud_pod: Option<String>,
+    /// All pods sharing the rooms (comma separated), rooms are assigned to pods by consistent hashing
+    pub cloud_pods: Option<String>,
+    /// WebSocket URL of a pod, `{pod}` is replaced by the pod name. Defaults to wss://{pod}.{cloud_service_domain}
+    pub cloud_pod_ws_url: Option<String>,
     #[serde(default = "default_service_name")]
     pub cloud_service_name: String,
     #[serde(default = "default_service_domain")]
@@ -51,7 +62,7 @@ pub struct Config {
     #[serde(default = "default_root_service_domain")]
     pub cloud_root_domain: String,
 
-    /// CORS allowed origins
+    /// Origins allowed by CORS and WebSocket handshakes, comma separated, `*` matches any subdomain or port
     #[serde(default = "default_cors_origins")]
     pub cloud_cors_origins: String,
 
@@ -64,8 +75,190 @@ pub struct Config {
     /// Database URL
     pub db_url: Option<String>,
 
-    /// Document save interval in milliseconds
+    /// Document save interval in milliseconds, the default of the save policies
     pub doc_save_interval_ms: Option<u64>,
+
+    /// Tick of the save scheduler in milliseconds, defaults to the save interval.
+    /// Save policies with a shorter interval than the tick save on every tick.
+    pub doc_save_tick_ms: Option<u64>,
+
+    /// Quiet time after the last update before a document is saved in milliseconds, 0 disables debouncing
+    pub doc_save_debounce_ms: Option<u64>,
+
+    /// How documents are persisted by default, "snapshot" (state and history) or "updates" (history only, smaller)
+    pub doc_save_persistence: Option<String>,
+
+    /// Delay before the first retry of a failed save in milliseconds, doubled with every further failure
+    pub save_retry_base_ms: Option<u64>,
+
+    /// Maximum delay between retries of a failed save in milliseconds
+    pub save_retry_max_ms: Option<u64>,
+
+    /// Age of unsaved changes of a failing document in seconds after which an alert is logged
+    pub save_alert_after_secs: Option<u64>,
+
+    /// Directory of the write-ahead journal of accepted updates, journaling is disabled without it
+    pub update_journal_dir: Option<String>,
+
+    /// Comma separated features enabled for organizations without a feature flag
+    pub features_enabled_by_default: Option<String>,
+
+    /// How long the principals of users are persisted for warm restarts in seconds, 0 disables the persistence
+    pub user_ctx_persist_ttl_secs: Option<u64>,
+
+    /// Time after startup in seconds during which reconnecting users get their persisted principals
+    /// instead of a forced refresh from the app service
+    pub user_ctx_warm_start_secs: Option<u64>,
+
+    /// Interval of the liveness watchdog in milliseconds
+    pub watchdog_interval_ms: Option<u64>,
+
+    /// Consecutive failed watchdog probes before the health check fails
+    pub watchdog_failure_threshold: Option<u32>,
+
+    /// Key for signing evidence bundle manifests (HMAC-SHA256), bundles are unsigned without it
+    pub evidence_signing_key: Option<String>,
+
+    /// Ed25519 key for signing published documents (base64 encoded 32 byte seed), injected from the KMS secret
+    pub publish_signing_key: Option<String>,
+
+    /// Identifier of the publish signing key, derived from the public key if not set
+    pub publish_signing_key_id: Option<String>,
+
+    /// Default maximum number of blocks in a sheet, can be overridden per org
+    pub doc_max_blocks: Option<usize>,
+
+    /// Default maximum nesting depth of text elements, can be overridden per org
+    pub doc_max_text_depth: Option<usize>,
+
+    /// Default maximum size of a document snapshot in bytes, can be overridden per org
+    pub doc_max_snapshot_bytes: Option<usize>,
+
+    /// Default maximum number of languages in a document, can be overridden per org
+    pub doc_max_languages: Option<usize>,
+
+    /// Consecutive load failures after which a document is quarantined
+    pub doc_quarantine_threshold: Option<u32>,
+
+    /// Interval of the job archiving inactive documents in milliseconds, 0 disables it
+    pub archival_interval_ms: Option<u64>,
+
+    /// Base URL of the object storage API holding cold tier streams, defaults to Google Cloud Storage
+    pub cold_storage_api_url: Option<String>,
+
+    /// Reads within a day after which a cold tier stream is promoted back to the hot tier, 0 disables promotion
+    pub cold_promote_after_reads: Option<u32>,
+
+    /// URL of the HTTP provider summarizing published documents, an extract of the text is used without it
+    pub summary_provider_url: Option<String>,
+
+    /// Bearer token sent to the summary provider
+    pub summary_provider_token: Option<String>,
+
+    /// Maximum length of a document summary in characters
+    pub summary_max_chars: Option<usize>,
+
+    /// URL of the HTTP policy scanner checking saved documents, None disables scanning
+    pub policy_scanner_url: Option<String>,
+
+    /// Bearer token sent to the policy scanner
+    pub policy_scanner_token: Option<String>,
+
+    /// Lowest finding severity that puts a document in maintenance: low, medium, high or critical
+    pub policy_maintenance_severity: Option<String>,
+
+    /// Connections below which an org is merged into the "other" bucket of the aggregated org diagnostics
+    pub diagnostics_org_min_conn: Option<u32>,
+
+    /// Interval between the usage samples per org in milliseconds, 0 disables sampling
+    pub analytics_sample_interval_ms: Option<u64>,
+
+    /// Days the usage samples are kept
+    pub analytics_retention_days: Option<u32>,
+
+    /// Load rooms of archived and read-only documents from a state-only snapshot, without the op history
+    pub state_only_read_rooms: Option<bool>,
+
+    /// Budget of API requests in milliseconds, the handler is cancelled with a 504 beyond it
+    pub api_timeout_ms: Option<u64>,
+
+    /// Budget of API requests exporting, importing or reworking documents in milliseconds
+    pub api_long_timeout_ms: Option<u64>,
+
+    /// Initialization hooks per content type run on the first load of a document, e.g. `claim=ensure-languages`
+    pub doc_init_hooks: Option<String>,
+
+    /// Prefixes of the stable numbers per content type, e.g. `claim=CLM;datasheet=DS`
+    pub doc_number_prefixes: Option<String>,
+
+    /// Pause between the documents of a bulk find-and-replace job in milliseconds
+    pub bulk_replace_pause_ms: Option<u64>,
+
+    /// Cold storage location the self-test writes, reads and deletes an object under, e.g. `gs://bucket/selftest`
+    pub selftest_blob_pointer: Option<String>,
+
+    /// User whose principals the self-test fetches from the app service, an unknown test user by default
+    pub selftest_user_uid: Option<String>,
+
+    /// Permission of handshakes without a token on the guest documents, `view` or `write`. Guests are refused when unset
+    pub guest_access: Option<String>,
+
+    /// Comma separated ids of the documents guests may open
+    pub guest_docs: Option<String>,
+
+    /// Directory the sync traffic of recorded documents is written to
+    pub recording_dir: Option<String>,
+
+    /// Longest duration of a recording in seconds
+    pub recording_max_secs: Option<u64>,
+
+    /// Lowest client library version whose handshakes are accepted, e.g. `1.4.0`
+    pub client_min_version: Option<String>,
+
+    /// Client library versions below this one are accepted but reported as deprecated
+    pub client_warn_version: Option<String>,
+
+    /// Lowest document schema version whose handshakes are accepted
+    pub client_min_schema_version: Option<String>,
+
+    /// Interval of the job sending the digests of followed documents in milliseconds, 0 disables it
+    pub digest_interval_ms: Option<u64>,
+
+    /// Interval of the job pushing the running announcements to the open rooms in milliseconds, 0 disables it
+    pub announcement_interval_ms: Option<u64>,
+
+    /// Shape of the JSON stored with saved documents: "canonical" (the ColabModel JSON) or "raw" (the Loro deep value)
+    pub save_json_projection: Option<String>,
+
+    /// Whether saves verify the JSON they write against the JSON generated again from the snapshot they write
+    pub save_verification: Option<bool>,
+
+    /// Locale of exports and digests for orgs that don't set one, e.g. "en-US"
+    pub default_locale: Option<String>,
+
+    /// IANA timezone of exports and digests for orgs that don't set one, e.g. "Europe/Brussels"
+    pub default_timezone: Option<String>,
+
+    /// Longest time a shutdown waits for the dirty documents to be flushed before exiting anyway
+    pub shutdown_timeout_ms: Option<u64>,
+
+    /// Maximum number of documents loaded at once, 0 to not limit them
+    pub max_concurrent_loads: Option<usize>,
+
+    /// Maximum number of loads waiting for a free slot, loads beyond it fail right away
+    pub load_queue_size: Option<usize>,
+
+    /// Longest time a load waits for a free slot before it fails
+    pub load_queue_timeout_ms: Option<u64>,
+
+    /// How long the access decision of a user joining a document is reused in milliseconds, 0 disables the cache
+    pub acl_cache_ttl_ms: Option<u64>,
+
+    /// Whether WebSocket connections on the HTTP port may negotiate permessage-deflate
+    pub ws_compression: Option<bool>,
+
+    /// Smallest message sent compressed to the connections that negotiated permessage-deflate
+    pub ws_compression_min_bytes: Option<usize>,
 }
 
 impl Config {
@@ -97,11 +290,24 @@ impl Config {
         format!("{}:{}", self.host, self.port)
     }
 
+    /// Get the address of the internal admin listener
+    pub fn admin_address(&self) -> String {
+        format!("{}:{}", self.admin_host.as_deref().unwrap_or(&self.host), self.admin_port)
+    }
+
     /// Get the WebSocket port
     pub fn websocket_port(&self) -> u16 {
         self.websocket_port
     }
 
+    /// Get the WebSocket URL of a pod
+    pub fn pod_ws_url(&self, pod: &str) -> String {
+        match &self.cloud_pod_ws_url {
+            Some(template) => template.replace("{pod}", pod),
+            None => format!("wss://{}.{}", pod, self.cloud_service_domain),
+        }
+    }
+
     /// Get the App Service URL
     pub fn app_service_url(&self) -> String {
         if self.environment == "development" {
@@ -118,9 +324,13 @@ impl Default for Config {
             host: default_host(),
             port: default_port(),
             websocket_port: default_websocket_port(),
+            admin_port: default_admin_port(),
+            admin_host: None,
             environment: default_environment(),
             log_level: default_log_level(),
             cloud_pod: None,
+            cloud_pods: None,
+            cloud_pod_ws_url: None,
             cloud_service_name: default_service_name(),
             cloud_service_domain: default_service_domain(),
             cloud_app_service_domain: default_app_service_domain(),
@@ -130,6 +340,66 @@ impl Default for Config {
             gcp_project_id: None,
             db_url: None,
             doc_save_interval_ms: Some(30_000), // Default to 30 seconds
+            doc_save_tick_ms: None,
+            doc_save_debounce_ms: Some(0),
+            doc_save_persistence: Some("snapshot".to_string()),
+            save_retry_base_ms: Some(1_000), // Default to 1 second
+            save_retry_max_ms: Some(300_000), // Default to 5 minutes
+            save_alert_after_secs: Some(300), // Default to 5 minutes
+            update_journal_dir: None,
+            features_enabled_by_default: Some("comments,suggestions,publishing".to_string()),
+            user_ctx_persist_ttl_secs: Some(0),
+            user_ctx_warm_start_secs: Some(300), // Default to 5 minutes
+            watchdog_interval_ms: Some(10_000), // Default to 10 seconds
+            watchdog_failure_threshold: Some(3),
+            evidence_signing_key: None,
+            publish_signing_key: None,
+            publish_signing_key_id: None,
+            doc_max_blocks: Some(500),
+            doc_max_text_depth: Some(32),
+            doc_max_snapshot_bytes: Some(16 * 1024 * 1024), // Default to 16 MiB
+            doc_max_languages: Some(50),
+            doc_quarantine_threshold: Some(3),
+            archival_interval_ms: Some(3_600_000), // Default to 1 hour
+            cold_storage_api_url: None,
+            cold_promote_after_reads: Some(3),
+            summary_provider_url: None,
+            summary_provider_token: None,
+            summary_max_chars: Some(400),
+            policy_scanner_url: None,
+            policy_scanner_token: None,
+            policy_maintenance_severity: Some("high".to_string()),
+            diagnostics_org_min_conn: Some(5),
+            analytics_sample_interval_ms: Some(60_000), // Default to 1 minute
+            analytics_retention_days: Some(90),
+            state_only_read_rooms: Some(true),
+            api_timeout_ms: Some(5_000), // Default to 5 seconds
+            api_long_timeout_ms: Some(30_000), // Default to 30 seconds
+            doc_init_hooks: None,
+            doc_number_prefixes: None,
+            bulk_replace_pause_ms: Some(250),
+            selftest_blob_pointer: None,
+            selftest_user_uid: None,
+            guest_access: None,
+            guest_docs: None,
+            recording_dir: Some("/tmp/colabri-doc-recordings".to_string()),
+            recording_max_secs: Some(900), // Default to 15 minutes
+            client_min_version: None,
+            client_warn_version: None,
+            client_min_schema_version: None,
+            digest_interval_ms: Some(3_600_000), // Default to 1 hour
+            announcement_interval_ms: Some(30_000), // Default to 30 seconds
+            save_json_projection: Some("canonical".to_string()),
+            save_verification: Some(false),
+            default_locale: Some("en-US".to_string()),
+            default_timezone: Some("UTC".to_string()),
+            shutdown_timeout_ms: Some(25_000), // Default to 25 seconds, within the default grace period of Kubernetes
+            max_concurrent_loads: Some(8),
+            load_queue_size: Some(256),
+            load_queue_timeout_ms: Some(10_000), // Default to 10 seconds
+            acl_cache_ttl_ms: Some(10_000), // Default to 10 seconds
+            ws_compression: Some(true),
+            ws_compression_min_bytes: Some(256),
         }
     }
 }
@@ -162,6 +432,10 @@ fn default_websocket_port() -> u16 {
     9001
 }
 
+fn default_admin_port() -> u16 {
+    3001
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
@@ -8,6 +8,7 @@ use std::time::Duration;
 use tokio::sync::OnceCell;
 use tracing::{error, info};
 use crate::db::util::escape_sql_string_literal;
+use crate::doctypes;
 
 // Global database instance
 static DB: OnceCell<Arc<DbColab>> = OnceCell::const_new();
@@ -113,6 +114,345 @@ pub struct DocumentAclRow {
     pub created_by: String,
 }
 
+/// Library ACL Row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryAclRow {
+    pub library: uuid::Uuid,
+    pub prpl: String,
+    pub permission: String,
+}
+
+/// Size of a stored version of a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentStreamSizeRow {
+    pub id: uuid::Uuid,
+    pub version: i32,
+    pub size: i64,
+    pub cold: bool,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub content_sha256: Option<String>,
+}
+
+/// Auto-archival policy of an organization
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchivalPolicyRow {
+    pub org: String,
+    pub inactive_days: i32,
+    pub grace_days: i32,
+}
+
+/// Document that has been inactive for longer than the archival policy allows
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ArchivalCandidateRow {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub workflow_state: String,
+    pub last_activity_at: DateTime<Utc>,
+    pub archive_candidate_since: Option<DateTime<Utc>>,
+}
+
+/// Load failure and quarantine status of a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentQuarantineRow {
+    pub load_failures: i32,
+    pub quarantined_at: Option<DateTime<Utc>>,
+    pub quarantine_reason: Option<String>,
+}
+
+/// Merge of two diverged versions of a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentMergeRow {
+    pub id: uuid::Uuid,
+    pub version_a: i32,
+    pub version_b: i32,
+    pub merged_version: i32,
+    pub merged_stream: uuid::Uuid,
+    pub version_v: Json<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+}
+
+/// Feature flag of an organization
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrgFeatureFlagRow {
+    pub feature: String,
+    pub enabled: bool,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: String,
+}
+
+/// Embed settings of an organization
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrgEmbedSettingsRow {
+    pub frame_ancestors: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: String,
+}
+
+/// Document types registered by an organization
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrgDocumentTypesRow {
+    pub strict: bool,
+    pub types: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: String,
+}
+
+/// Stable number of a document or of one of its blocks
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentNumberRow {
+    pub number: String,
+    pub document: Uuid,
+    // Empty for the number of the document itself
+    pub block_id: String,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+}
+
+/// A document of an org, without its content
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentRefRow {
+    pub id: uuid::Uuid,
+    pub name: String,
+    #[sqlx(rename = "type")]
+    pub doc_type: String,
+}
+
+/// A bulk find-and-replace job
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ReplaceJobRow {
+    pub id: uuid::Uuid,
+    pub request: Json<serde_json::Value>,
+    pub dry_run: bool,
+    pub status: String,
+    pub documents: i32,
+    pub processed: i32,
+    pub results: Json<serde_json::Value>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Preferences and read marker of a user on a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentUserPrefsRow {
+    pub last_read_version: Option<i32>,
+    pub last_read_vv: Option<Json<serde_json::Value>>,
+    pub last_read_at: Option<DateTime<Utc>>,
+    pub pinned_blocks: Vec<String>,
+    pub collapsed_sections: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An announcement broadcast to the connected clients of an organization
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrgAnnouncementRow {
+    pub id: uuid::Uuid,
+    pub org: String,
+    pub message: String,
+    pub level: String,
+    pub starts_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+}
+
+/// Locale settings of an organization
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrgLocaleSettingsRow {
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub date_format: Option<String>,
+    pub datetime_format: Option<String>,
+    pub content_types: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: String,
+}
+
+/// A user watching a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentWatcherRow {
+    pub org: String,
+    pub document: uuid::Uuid,
+    pub prpl: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user following a document for the digests
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentFollowerRow {
+    pub org: String,
+    pub document: uuid::Uuid,
+    pub prpl: String,
+    pub frequency: String,
+    pub last_vv: Option<Json<serde_json::Value>>,
+    pub last_digest_at: DateTime<Utc>,
+}
+
+/// Summary of the published version of a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentSummaryRow {
+    pub document: uuid::Uuid,
+    pub version: i32,
+    pub summary: String,
+    pub provider: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Finding of the policy scanner in a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentPolicyFindingRow {
+    pub id: uuid::Uuid,
+    pub block_path: String,
+    pub category: String,
+    pub severity: String,
+    pub rule: Option<String>,
+    pub excerpt: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<String>,
+}
+
+/// Usage sample of an organization taken by an instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrgUsageSampleRow {
+    pub org: String,
+    pub n_rooms: i32,
+    pub n_doc_rooms: i32,
+    pub n_conn: i32,
+    pub n_updates: i64,
+}
+
+/// Usage of an organization aggregated over a time bucket
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrgUsageBucketRow {
+    pub bucket_start: DateTime<Utc>,
+    pub peak_rooms: i64,
+    pub peak_doc_rooms: i64,
+    pub peak_conn: i64,
+    pub avg_conn: f64,
+    // Sum of the connections of every sample, times the sample interval gives connection time
+    pub conn_samples: i64,
+    pub n_updates: i64,
+}
+
+/// A usage figure of an organization for a billing period
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrgBillingFigureRow {
+    pub org: String,
+    pub value: i64,
+}
+
+/// Stored bytes of an organization over a billing period
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrgBillingStorageRow {
+    pub org: String,
+    pub avg_bytes: i64,
+    pub peak_bytes: i64,
+}
+
+/// Body of a lazily loaded sheet block
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentBlockRow {
+    pub block_id: String,
+    pub package: Vec<u8>,
+    pub json: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: String,
+}
+
+/// Statement of a sheet stored as a linked subdocument
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StatementSubdocRow {
+    pub id: uuid::Uuid,
+    pub sheet: uuid::Uuid,
+    pub package: Vec<u8>,
+    pub json: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: String,
+}
+
+/// Block of a sheet transcluding a block of another document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentTransclusionRow {
+    pub sheet: uuid::Uuid,
+    pub block_id: String,
+    pub source: uuid::Uuid,
+    pub source_block: String,
+    pub source_hash: Option<String>,
+}
+
+/// Reference of a document to another one
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentLinkRow {
+    pub document: uuid::Uuid,
+    pub target: uuid::Uuid,
+    pub kind: String,
+    pub path: String,
+    // Name of the document on the other end of the link, None when it doesn't exist
+    pub name: Option<String>,
+}
+
+/// Saved JSON of a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentJsonRow {
+    pub id: uuid::Uuid,
+    pub name: String,
+    #[sqlx(rename = "type")]
+    pub doc_type: String,
+    pub json: Json<serde_json::Value>,
+}
+
+/// Latest report of the duplicated statements of an org
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StatementDuplicatesRow {
+    pub threshold: f64,
+    pub statements: i32,
+    pub clusters: Json<serde_json::Value>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Share token of the public view of a document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentShareTokenRow {
+    pub id: uuid::Uuid,
+    pub org: String,
+    pub document: uuid::Uuid,
+    pub branding: Option<Json<serde_json::Value>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+}
+
+/// Detached signature of a published document
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DocumentSignatureRow {
+    pub id: uuid::Uuid,
+    pub org: String,
+    pub document: uuid::Uuid,
+    pub version: i32,
+    pub version_v: Json<serde_json::Value>,
+    pub snapshot_sha256: String,
+    pub json_sha256: String,
+    pub algorithm: String,
+    pub key_id: String,
+    pub public_key: String,
+    pub signature: String,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+}
+
+/// The access related rows of a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentAccessRows {
+    pub owner: String,
+    pub acls: Vec<DocumentAclRow>,
+    pub library_acls: Vec<LibraryAclRow>,
+}
+
 /// Database connection pool
 pub struct DbColab {
     pool: PgPool,
@@ -148,6 +488,46 @@ impl DbColab {
         &self.pool
     }
 
+    /// Check that a connection can be acquired and used
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn ping(&self) -> Result<(), SqlxError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Check that the policy context of an org can be set and read back in a transaction
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<String, SqlxError>` - The policy context as seen by the database
+    pub async fn check_policy_context(&self, org: &str) -> Result<String, SqlxError> {
+
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let orgs: String = sqlx::query_scalar("SELECT current_setting('app.orgs')")
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.rollback().await?;
+
+        Ok(orgs)
+    }
+
+    /// The number of idle and total connections in the pool
+    pub fn pool_state(&self) -> (usize, u32) {
+        (self.pool.num_idle(), self.pool.size())
+    }
+
     /// Get a document if the user has view access to it
     ///
     /// # Arguments
@@ -412,6 +792,7 @@ impl DbColab {
     /// * `snapshot` - The snapshot of the LoroDoc to update
     /// * `doc_stmt_id` - The UUID of the document statement to update with the new JSON
     /// * `json` - The JSON representation of the loro document
+    /// * `content_sha256` - Hash of the content of the JSON without its metadata
     ///
     /// # Returns
     /// * `Result<uuid::Uuid, SqlxError>` - Stream ID
@@ -423,6 +804,7 @@ impl DbColab {
         doc_stream_id: uuid::Uuid,
         colab_package_blob: Vec<u8>,
         json: serde_json::Value,
+        content_sha256: &str,
         state_vv_json: serde_json::Value,
         peer_map_json: serde_json::Value,
         by_prpl: &str,
@@ -456,7 +838,8 @@ impl DbColab {
             SET content = $1,
                 size = $2,
                 updated_at = NOW(),
-                updated_by = $3
+                updated_by = $3,
+                content_sha256 = $6
             WHERE org = $4
                 AND id = $5
                 AND deleted = FALSE
@@ -468,14 +851,14 @@ impl DbColab {
             .bind(by_prpl)
             .bind(org)
             .bind(doc_stream_id)
+            .bind(content_sha256)
             .fetch_optional(&mut *tx)
             .await?;
 
         // Execute the document type specific update
-        let doc_table_name = match doc_type {
-            "colab-statement" => "document_statements",
-            "colab-sheet" => "document_sheets",
-            _ => {
+        let doc_table_name = match doctypes::get(doc_type) {
+            Some(doc_type) => doc_type.table(),
+            None => {
                 error!("Unsupported document type for update: {}", doc_type);
                 return Err(SqlxError::RowNotFound);
             }
@@ -653,4 +1036,4030 @@ impl DbColab {
             }
         }
     }
+
+    /// Get the owner and ACL rows (document and library) of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Option<DocumentAccessRows>, SqlxError>` - The access rows or None if the document was not found
+    pub async fn get_document_access(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Option<DocumentAccessRows>, SqlxError> {
+        // Begin a transaction
+        let mut tx = match self.pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                error!("Failed to acquire connection from pool for document {}: {}. Pool state: {} idle, {} total",
+                       document_id, e, self.pool.num_idle(), self.pool.size());
+                return Err(e);
+            }
+        };
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT
+                d.owner,
+                COALESCE(
+                    (SELECT json_agg(da.*) FROM document_acl da WHERE da.document = d.id),
+                    '[]'
+                ) AS acls,
+                COALESCE(
+                    (SELECT json_agg(
+                        json_build_object(
+                            'library', la.library,
+                            'prpl', la.prpl,
+                            'permission', la.permission
+                        )
+                    ) FROM library_acl la WHERE d.container_type = 'library' AND la.library = d.container),
+                    '[]'
+                ) AS library_acls
+            FROM documents d
+            WHERE
+                d.org = $1
+                AND d.id = $2
+                AND d.deleted = FALSE;
+        "#;
+
+        let row = sqlx::query(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        match row {
+            Some(row) => {
+                let acls: Vec<DocumentAclRow> = serde_json::from_value(row.try_get("acls")?)
+                    .map_err(|e| SqlxError::Decode(Box::new(e)))?;
+                let library_acls: Vec<LibraryAclRow> = serde_json::from_value(row.try_get("library_acls")?)
+                    .map_err(|e| SqlxError::Decode(Box::new(e)))?;
+                Ok(Some(DocumentAccessRows {
+                    owner: row.try_get("owner")?,
+                    acls,
+                    library_acls,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the workflow definition of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<serde_json::Value>, SqlxError>` - The definition or None if the org uses the default workflow
+    pub async fn get_org_workflow(
+        &self,
+        org: &str,
+    ) -> Result<Option<serde_json::Value>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT definition FROM org_workflows WHERE org = $1;
+        "#;
+        let row = sqlx::query(query_sql)
+            .bind(org)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        match row {
+            Some(row) => {
+                let definition: Json<serde_json::Value> = row.try_get("definition")?;
+                Ok(Some(definition.0))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the document limit overrides of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<serde_json::Value>, SqlxError>` - The overrides or None if the org uses the default limits
+    pub async fn get_org_document_limits(
+        &self,
+        org: &str,
+    ) -> Result<Option<serde_json::Value>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT limits FROM org_document_limits WHERE org = $1;
+        "#;
+        let row = sqlx::query(query_sql)
+            .bind(org)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        match row {
+            Some(row) => {
+                let limits: Json<serde_json::Value> = row.try_get("limits")?;
+                Ok(Some(limits.0))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the save policy overrides of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<serde_json::Value>, SqlxError>` - The overrides or None if the org uses the default policies
+    pub async fn get_org_save_policies(
+        &self,
+        org: &str,
+    ) -> Result<Option<serde_json::Value>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT policies FROM org_save_policies WHERE org = $1;
+        "#;
+        let row = sqlx::query(query_sql)
+            .bind(org)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        match row {
+            Some(row) => {
+                let policies: Json<serde_json::Value> = row.try_get("policies")?;
+                Ok(Some(policies.0))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the workflow state of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Option<String>, SqlxError>` - The state or None if the document was not found
+    pub async fn get_document_workflow_state(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Option<String>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT workflow_state FROM documents
+            WHERE org = $1 AND id = $2 AND deleted = FALSE;
+        "#;
+        let row = sqlx::query(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get("workflow_state")?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Move a document from one workflow state to another and record the transition.
+    /// The update only happens if the document is still in `from_state`.
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `from_state` - The expected current state
+    /// * `to_state` - The new state
+    /// * `comment` - Optional comment on the transition
+    /// * `by_prpl` - Principal making the transition
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False if the document was not in `from_state` anymore
+    pub async fn update_document_workflow_state(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        from_state: &str,
+        to_state: &str,
+        comment: Option<&str>,
+        by_prpl: &str,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = match self.pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                error!("Failed to acquire connection from pool for document {}: {}. Pool state: {} idle, {} total",
+                       document_id, e, self.pool.num_idle(), self.pool.size());
+                return Err(e);
+            }
+        };
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE documents SET
+                workflow_state = $4,
+                updated_at = CURRENT_TIMESTAMP,
+                updated_by = $5
+            WHERE org = $1 AND id = $2 AND workflow_state = $3 AND deleted = FALSE
+            RETURNING id;
+        "#;
+        let row = sqlx::query(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(from_state)
+            .bind(to_state)
+            .bind(by_prpl)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if row.is_none() {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        let insert_sql = r#"
+            INSERT INTO document_workflow_transitions(org, document, from_state, to_state, comment, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6);
+        "#;
+        sqlx::query(insert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(from_state)
+            .bind(to_state)
+            .bind(comment)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        info!("Document '{}' moved from workflow state '{}' to '{}'", document_id, from_state, to_state);
+        Ok(true)
+    }
+
+    /// Delete the document ACL rows that grant one of the given permissions
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `permissions` - The permissions to revoke
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - The number of deleted rows
+    pub async fn delete_document_acls(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        permissions: &[String],
+    ) -> Result<u64, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let delete_sql = r#"
+            DELETE FROM document_acl
+            WHERE org = $1 AND document = $2 AND permission = ANY($3);
+        "#;
+        let result = sqlx::query(delete_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(permissions)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        info!("Deleted {} ACL rows of document '{}'", result.rows_affected(), document_id);
+        Ok(result.rows_affected())
+    }
+
+    /// Store the signature of a published document
+    ///
+    /// # Arguments
+    /// * `signature` - The signature row, `id` and `created_at` are assigned by the database
+    ///
+    /// # Returns
+    /// * `Result<uuid::Uuid, SqlxError>` - The id of the stored signature
+    pub async fn insert_document_signature(
+        &self,
+        signature: &DocumentSignatureRow,
+    ) -> Result<uuid::Uuid, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(&signature.org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let insert_sql = r#"
+            INSERT INTO document_signatures(org, document, version, version_v, snapshot_sha256, json_sha256, algorithm, key_id, public_key, signature, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING id;
+        "#;
+        let row = sqlx::query(insert_sql)
+            .bind(&signature.org)
+            .bind(signature.document)
+            .bind(signature.version)
+            .bind(&signature.version_v)
+            .bind(&signature.snapshot_sha256)
+            .bind(&signature.json_sha256)
+            .bind(&signature.algorithm)
+            .bind(&signature.key_id)
+            .bind(&signature.public_key)
+            .bind(&signature.signature)
+            .bind(&signature.created_by)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        let id: uuid::Uuid = row.try_get("id")?;
+        info!("Stored signature {} of document '{}' version {}", id, signature.document, signature.version);
+        Ok(id)
+    }
+
+    /// Get the latest signature of a published document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Option<DocumentSignatureRow>, SqlxError>` - The signature or None if the document was never published
+    pub async fn get_latest_document_signature(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Option<DocumentSignatureRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT * FROM document_signatures
+            WHERE org = $1 AND document = $2
+            ORDER BY created_at DESC
+            LIMIT 1;
+        "#;
+        let row = sqlx::query_as::<_, DocumentSignatureRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Get the load failure and quarantine status of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Option<DocumentQuarantineRow>, SqlxError>` - The status or None if the document does not exist
+    pub async fn get_document_quarantine(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Option<DocumentQuarantineRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT load_failures, quarantined_at, quarantine_reason FROM documents
+            WHERE org = $1 AND id = $2 AND deleted = FALSE;
+        "#;
+        let row = sqlx::query_as::<_, DocumentQuarantineRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Record a failed load of a document, quarantining it once the failures reach the threshold
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `reason` - Why the load failed
+    /// * `threshold` - Consecutive failures after which the document is quarantined
+    ///
+    /// # Returns
+    /// * `Result<Option<DocumentQuarantineRow>, SqlxError>` - The new status or None if the document does not exist
+    pub async fn record_document_load_failure(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        reason: &str,
+        threshold: i32,
+    ) -> Result<Option<DocumentQuarantineRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE documents SET
+                load_failures = load_failures + 1,
+                quarantined_at = CASE
+                    WHEN quarantined_at IS NULL AND load_failures + 1 >= $4 THEN CURRENT_TIMESTAMP
+                    ELSE quarantined_at
+                END,
+                quarantine_reason = CASE
+                    WHEN quarantined_at IS NULL AND load_failures + 1 >= $4 THEN $3
+                    ELSE quarantine_reason
+                END
+            WHERE org = $1 AND id = $2 AND deleted = FALSE
+            RETURNING load_failures, quarantined_at, quarantine_reason;
+        "#;
+        let row = sqlx::query_as::<_, DocumentQuarantineRow>(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(reason)
+            .bind(threshold)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Reset the load failures of a document and lift its quarantine
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - True if there was anything to reset
+    pub async fn reset_document_load_failures(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE documents SET
+                load_failures = 0,
+                quarantined_at = NULL,
+                quarantine_reason = NULL
+            WHERE org = $1 AND id = $2
+                AND (load_failures > 0 OR quarantined_at IS NOT NULL);
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the sizes of all stored versions of the main stream of a document, oldest first
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentStreamSizeRow>, SqlxError>` - The versions, empty if the document does not exist
+    pub async fn get_document_stream_sizes(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Vec<DocumentStreamSizeRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT id, version, size, (content IS NULL AND pointer IS NOT NULL) AS cold, created_at, created_by, content_sha256 FROM document_streams
+            WHERE org = $1 AND document = $2 AND name = 'main' AND deleted = FALSE
+            ORDER BY version ASC;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentStreamSizeRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Get the history budget of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Option<Option<i32>>, SqlxError>` - None if the document does not exist, the budget otherwise
+    pub async fn get_document_history_budget(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Option<Option<i32>>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT history_budget FROM documents
+            WHERE org = $1 AND id = $2 AND deleted = FALSE;
+        "#;
+        let row = sqlx::query(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get("history_budget")?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the history budget of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `budget` - Number of versions to keep, None keeps the full history
+    /// * `by_prpl` - Principal setting the budget
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False if the document does not exist
+    pub async fn set_document_history_budget(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        budget: Option<i32>,
+        by_prpl: &str,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE documents SET
+                history_budget = $3,
+                updated_at = CURRENT_TIMESTAMP,
+                updated_by = $4
+            WHERE org = $1 AND id = $2 AND deleted = FALSE;
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(budget)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record activity on a document, which also withdraws it as archival candidate
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn touch_document_activity(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE documents SET
+                last_activity_at = CURRENT_TIMESTAMP,
+                archive_candidate_since = NULL
+            WHERE org = $1 AND id = $2;
+        "#;
+        sqlx::query(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the enabled auto-archival policies of all organizations
+    ///
+    /// # Returns
+    /// * `Result<Vec<ArchivalPolicyRow>, SqlxError>` - The policies
+    pub async fn get_archival_policies(&self) -> Result<Vec<ArchivalPolicyRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT org, inactive_days, grace_days FROM org_archival_policies
+            WHERE enabled = TRUE;
+        "#;
+        sqlx::query_as::<_, ArchivalPolicyRow>(query_sql)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Get the enabled auto-archival policy of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<ArchivalPolicyRow>, SqlxError>` - The policy or None if the org has no enabled policy
+    pub async fn get_archival_policy(
+        &self,
+        org: &str,
+    ) -> Result<Option<ArchivalPolicyRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT org, inactive_days, grace_days FROM org_archival_policies
+            WHERE org = $1 AND enabled = TRUE;
+        "#;
+        sqlx::query_as::<_, ArchivalPolicyRow>(query_sql)
+            .bind(org)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Flag the documents of an organization that have been inactive for too long as archival candidates
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `inactive_days` - Days without activity after which a document becomes a candidate
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - Number of newly flagged documents
+    pub async fn flag_archival_candidates(
+        &self,
+        org: &str,
+        inactive_days: i32,
+    ) -> Result<u64, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE documents SET archive_candidate_since = CURRENT_TIMESTAMP
+            WHERE org = $1
+                AND deleted = FALSE
+                AND workflow_state <> 'archived'
+                AND archive_candidate_since IS NULL
+                AND last_activity_at < CURRENT_TIMESTAMP - make_interval(days => $2);
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(inactive_days)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Get the documents of an organization that have been inactive for too long, flagged or not
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `inactive_days` - Days without activity after which a document becomes a candidate
+    ///
+    /// # Returns
+    /// * `Result<Vec<ArchivalCandidateRow>, SqlxError>` - The candidates, least recently active first
+    pub async fn list_archival_candidates(
+        &self,
+        org: &str,
+        inactive_days: i32,
+    ) -> Result<Vec<ArchivalCandidateRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT id, name, workflow_state, last_activity_at, archive_candidate_since FROM documents
+            WHERE org = $1
+                AND deleted = FALSE
+                AND workflow_state <> 'archived'
+                AND last_activity_at < CURRENT_TIMESTAMP - make_interval(days => $2)
+            ORDER BY last_activity_at ASC;
+        "#;
+        let rows = sqlx::query_as::<_, ArchivalCandidateRow>(query_sql)
+            .bind(org)
+            .bind(inactive_days)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Move a cold tier stream back to the hot tier by storing its content inline again.
+    /// The pointer is kept, the object is cleaned up by the tiering job.
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `stream_id` - Document stream UUID
+    /// * `content` - The content rehydrated from object storage
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False if the stream was not in the cold tier anymore
+    pub async fn promote_doc_stream(
+        &self,
+        org: &str,
+        stream_id: uuid::Uuid,
+        content: Vec<u8>,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE document_streams SET
+                content = $3,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE org = $1 AND id = $2 AND content IS NULL AND deleted = FALSE;
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(stream_id)
+            .bind(content)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Store the merge of two diverged versions as a new version of a document and record the merge
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `doc_type` - Type of the document, "colab-statement" or "colab-sheet"
+    /// * `colab_package_blob` - The ColabPackage of the merged document
+    /// * `json` - The JSON representation of the merged document
+    /// * `state_vv_json` - The version vector of the merged document
+    /// * `peer_map_json` - The merged peer map
+    /// * `sources` - The two versions that were merged
+    /// * `by_prpl` - The principal performing the merge
+    ///
+    /// # Returns
+    /// * `Result<DocumentMergeRow, SqlxError>` - The recorded merge
+    pub async fn insert_merged_doc_stream(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        doc_type: &str,
+        colab_package_blob: Vec<u8>,
+        json: serde_json::Value,
+        state_vv_json: serde_json::Value,
+        peer_map_json: serde_json::Value,
+        sources: (u32, u32),
+        by_prpl: &str,
+    ) -> Result<DocumentMergeRow, SqlxError> {
+        let doc_table_name = match doctypes::get(doc_type) {
+            Some(doc_type) => doc_type.table(),
+            None => {
+                error!("Unsupported document type for merge: {}", doc_type);
+                return Err(SqlxError::RowNotFound);
+            }
+        };
+        let content_size = colab_package_blob.len() as i64;
+
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        // Insert the merged stream as the next version of the main stream
+        let insert_stream_sql = r#"
+            INSERT INTO document_streams(org, document, name, content, version, size, created_by, updated_by)
+            SELECT $1, $2, 'main', $3,
+                COALESCE((SELECT MAX(version) FROM document_streams WHERE org = $1 AND document = $2 AND name = 'main'), 0) + 1,
+                $4, $5, $5
+            RETURNING id, version;
+        "#;
+        let stream_row = sqlx::query(insert_stream_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(colab_package_blob)
+            .bind(content_size)
+            .bind(by_prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+        let stream_id: uuid::Uuid = stream_row.try_get("id")?;
+        let merged_version: i32 = stream_row.try_get("version")?;
+
+        // Update the JSON of the document
+        let update_model_sql = format!(r#"
+            UPDATE {}
+            SET json = $1,
+                version_v = $2,
+                peer_map = $3,
+                synced = FALSE,
+                updated_at = NOW(),
+                updated_by = $4
+            WHERE org = $5
+                AND document = $6;
+        "#, doc_table_name);
+        sqlx::query(&update_model_sql)
+            .bind(json)
+            .bind(&state_vv_json)
+            .bind(peer_map_json)
+            .bind(by_prpl)
+            .bind(org)
+            .bind(document_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Record the merge
+        let insert_merge_sql = r#"
+            INSERT INTO document_merges(org, document, version_a, version_b, merged_version, merged_stream, version_v, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, version_a, version_b, merged_version, merged_stream, version_v, created_at, created_by;
+        "#;
+        let merge = sqlx::query_as::<_, DocumentMergeRow>(insert_merge_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(sources.0 as i32)
+            .bind(sources.1 as i32)
+            .bind(merged_version)
+            .bind(stream_id)
+            .bind(state_vv_json)
+            .bind(by_prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        info!("Merged versions {} and {} of document {} into version {}", sources.0, sources.1, document_id, merged_version);
+        Ok(merge)
+    }
+
+    /// Get the merges of diverged versions of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentMergeRow>, SqlxError>` - The merges, the latest first
+    pub async fn get_document_merges(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Vec<DocumentMergeRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT id, version_a, version_b, merged_version, merged_stream, version_v, created_at, created_by
+            FROM document_merges
+            WHERE org = $1 AND document = $2
+            ORDER BY created_at DESC;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentMergeRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Get the persisted principals of a user, if they are recent enough
+    ///
+    /// # Arguments
+    /// * `uid` - User ID
+    /// * `max_age_secs` - Maximum age of the principals in seconds
+    ///
+    /// # Returns
+    /// * `Result<Option<Vec<String>>, SqlxError>` - The principals or None if there are no recent ones
+    pub async fn get_user_ctx_snapshot(
+        &self,
+        uid: &str,
+        max_age_secs: u64,
+    ) -> Result<Option<Vec<String>>, SqlxError> {
+        let query_sql = r#"
+            SELECT principals FROM user_ctx_snapshots
+            WHERE uid = $1 AND updated_at > NOW() - make_interval(secs => $2);
+        "#;
+        let row = sqlx::query(query_sql)
+            .bind(uid)
+            .bind(max_age_secs as f64)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => {
+                let principals: Json<Vec<String>> = row.try_get("principals")?;
+                Ok(Some(principals.0))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Persist the principals of a user
+    ///
+    /// # Arguments
+    /// * `uid` - User ID
+    /// * `principals` - The principals fetched from the app service
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn upsert_user_ctx_snapshot(
+        &self,
+        uid: &str,
+        principals: &[String],
+    ) -> Result<(), SqlxError> {
+        let query_sql = r#"
+            INSERT INTO user_ctx_snapshots (uid, principals, updated_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (uid) DO UPDATE SET
+                principals = EXCLUDED.principals,
+                updated_at = EXCLUDED.updated_at;
+        "#;
+        sqlx::query(query_sql)
+            .bind(uid)
+            .bind(Json(principals))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete persisted principals that are too old to be used
+    ///
+    /// # Arguments
+    /// * `max_age_secs` - Maximum age of the principals in seconds
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - The number of deleted snapshots
+    pub async fn prune_user_ctx_snapshots(
+        &self,
+        max_age_secs: u64,
+    ) -> Result<u64, SqlxError> {
+        let query_sql = r#"
+            DELETE FROM user_ctx_snapshots
+            WHERE updated_at <= NOW() - make_interval(secs => $1);
+        "#;
+        let result = sqlx::query(query_sql)
+            .bind(max_age_secs as f64)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Get the feature flags set for an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Vec<OrgFeatureFlagRow>, SqlxError>` - The flags set for the organization
+    pub async fn get_org_feature_flags(
+        &self,
+        org: &str,
+    ) -> Result<Vec<OrgFeatureFlagRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT feature, enabled, updated_at, updated_by
+            FROM org_feature_flags
+            WHERE org = $1
+            ORDER BY feature;
+        "#;
+        let rows = sqlx::query_as::<_, OrgFeatureFlagRow>(query_sql)
+            .bind(org)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Enable or disable a feature for an organization, or reset it to the default
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `feature` - Feature name
+    /// * `enabled` - Whether the feature is enabled, None removes the flag so the default applies
+    /// * `by_prpl` - Principal setting the flag
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn set_org_feature_flag(
+        &self,
+        org: &str,
+        feature: &str,
+        enabled: Option<bool>,
+        by_prpl: &str,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        match enabled {
+            Some(enabled) => {
+                let upsert_sql = r#"
+                    INSERT INTO org_feature_flags (org, feature, enabled, updated_at, updated_by)
+                    VALUES ($1, $2, $3, NOW(), $4)
+                    ON CONFLICT (org, feature) DO UPDATE SET
+                        enabled = EXCLUDED.enabled,
+                        updated_at = EXCLUDED.updated_at,
+                        updated_by = EXCLUDED.updated_by;
+                "#;
+                sqlx::query(upsert_sql)
+                    .bind(org)
+                    .bind(feature)
+                    .bind(enabled)
+                    .bind(by_prpl)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            None => {
+                let delete_sql = r#"
+                    DELETE FROM org_feature_flags WHERE org = $1 AND feature = $2;
+                "#;
+                sqlx::query(delete_sql)
+                    .bind(org)
+                    .bind(feature)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the settings of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Option<Option<serde_json::Value>>, SqlxError>` - None if the document doesn't exist, otherwise its settings (None without settings)
+    pub async fn get_document_settings(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Option<Option<serde_json::Value>>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT settings FROM documents
+            WHERE org = $1 AND id = $2 AND deleted = FALSE;
+        "#;
+        let row = sqlx::query(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        match row {
+            Some(row) => {
+                let settings: Option<Json<serde_json::Value>> = row.try_get("settings")?;
+                Ok(Some(settings.map(|settings| settings.0)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Set the settings of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `settings` - The settings, None removes all settings
+    /// * `by_prpl` - Principal setting the settings
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - Whether the document was found
+    pub async fn set_document_settings(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        settings: Option<serde_json::Value>,
+        by_prpl: &str,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE documents SET
+                settings = $3,
+                updated_at = CURRENT_TIMESTAMP,
+                updated_by = $4
+            WHERE org = $1 AND id = $2 AND deleted = FALSE;
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(settings.map(Json))
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Create a share token for the public view of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `token_sha256` - SHA-256 of the token, the token itself is never stored
+    /// * `branding` - Branding options of the view
+    /// * `expires_at` - When the token expires, None never expires
+    /// * `by_prpl` - Principal creating the token
+    ///
+    /// # Returns
+    /// * `Result<DocumentShareTokenRow, SqlxError>` - The created token
+    pub async fn insert_share_token(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        token_sha256: &str,
+        branding: Option<serde_json::Value>,
+        expires_at: Option<DateTime<Utc>>,
+        by_prpl: &str,
+    ) -> Result<DocumentShareTokenRow, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let insert_sql = r#"
+            INSERT INTO document_share_tokens (org, document, token_sha256, branding, expires_at, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, org, document, branding, expires_at, revoked_at, created_at, created_by;
+        "#;
+        let row = sqlx::query_as::<_, DocumentShareTokenRow>(insert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(token_sha256)
+            .bind(branding.map(Json))
+            .bind(expires_at)
+            .bind(by_prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// List the share tokens of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentShareTokenRow>, SqlxError>` - The share tokens, newest first
+    pub async fn list_share_tokens(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Vec<DocumentShareTokenRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT id, org, document, branding, expires_at, revoked_at, created_at, created_by
+            FROM document_share_tokens
+            WHERE org = $1 AND document = $2
+            ORDER BY created_at DESC;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentShareTokenRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Revoke a share token of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `token_id` - Share token ID
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - Whether an active token was revoked
+    pub async fn revoke_share_token(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        token_id: uuid::Uuid,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE document_share_tokens SET revoked_at = NOW()
+            WHERE org = $1 AND document = $2 AND id = $3 AND revoked_at IS NULL;
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(token_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Find an active share token by its hash, across organizations
+    ///
+    /// # Arguments
+    /// * `token_sha256` - SHA-256 of the token
+    ///
+    /// # Returns
+    /// * `Result<Option<DocumentShareTokenRow>, SqlxError>` - The token if it exists, is not revoked and not expired
+    pub async fn get_active_share_token(
+        &self,
+        token_sha256: &str,
+    ) -> Result<Option<DocumentShareTokenRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT id, org, document, branding, expires_at, revoked_at, created_at, created_by
+            FROM document_share_tokens
+            WHERE token_sha256 = $1
+                AND revoked_at IS NULL
+                AND (expires_at IS NULL OR expires_at > NOW());
+        "#;
+        let row = sqlx::query_as::<_, DocumentShareTokenRow>(query_sql)
+            .bind(token_sha256)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    /// Get the embed settings of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<OrgEmbedSettingsRow>, SqlxError>` - The settings, None if they were never set
+    pub async fn get_org_embed_settings(
+        &self,
+        org: &str,
+    ) -> Result<Option<OrgEmbedSettingsRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT frame_ancestors, updated_at, updated_by
+            FROM org_embed_settings
+            WHERE org = $1;
+        "#;
+        let row = sqlx::query_as::<_, OrgEmbedSettingsRow>(query_sql)
+            .bind(org)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Set the origins allowed to frame the embedded views of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `frame_ancestors` - Allowed origins, empty to disallow embedding
+    /// * `by_prpl` - Principal changing the settings
+    ///
+    /// # Returns
+    /// * `Result<OrgEmbedSettingsRow, SqlxError>` - The updated settings
+    pub async fn set_org_embed_frame_ancestors(
+        &self,
+        org: &str,
+        frame_ancestors: &[String],
+        by_prpl: &str,
+    ) -> Result<OrgEmbedSettingsRow, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO org_embed_settings (org, frame_ancestors, updated_at, updated_by)
+            VALUES ($1, $2, NOW(), $3)
+            ON CONFLICT (org) DO UPDATE SET
+                frame_ancestors = EXCLUDED.frame_ancestors,
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by
+            RETURNING frame_ancestors, updated_at, updated_by;
+        "#;
+        let row = sqlx::query_as::<_, OrgEmbedSettingsRow>(upsert_sql)
+            .bind(org)
+            .bind(frame_ancestors)
+            .bind(by_prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Store the summary of a document, replacing the previous one
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `version` - Version the summary was generated from
+    /// * `summary` - The summary
+    /// * `provider` - Provider that generated the summary
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn upsert_document_summary(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        version: i32,
+        summary: &str,
+        provider: &str,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO document_summaries (org, document, version, summary, provider, generated_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            ON CONFLICT (org, document) DO UPDATE SET
+                version = EXCLUDED.version,
+                summary = EXCLUDED.summary,
+                provider = EXCLUDED.provider,
+                generated_at = EXCLUDED.generated_at;
+        "#;
+        sqlx::query(upsert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(version)
+            .bind(summary)
+            .bind(provider)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the summaries of documents
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_ids` - Document UUIDs
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentSummaryRow>, SqlxError>` - The summaries, documents without one are left out
+    pub async fn get_document_summaries(
+        &self,
+        org: &str,
+        document_ids: &[uuid::Uuid],
+    ) -> Result<Vec<DocumentSummaryRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT document, version, summary, provider, generated_at
+            FROM document_summaries
+            WHERE org = $1 AND document = ANY($2);
+        "#;
+        let rows = sqlx::query_as::<_, DocumentSummaryRow>(query_sql)
+            .bind(org)
+            .bind(document_ids)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Replace the open policy findings of a document with the findings of a new scan
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `findings` - Findings of the scan, their id and timestamps are ignored
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentPolicyFindingRow>, SqlxError>` - The stored findings
+    pub async fn replace_policy_findings(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        findings: &[DocumentPolicyFindingRow],
+    ) -> Result<Vec<DocumentPolicyFindingRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let delete_sql = r#"
+            DELETE FROM document_policy_findings
+            WHERE org = $1 AND document = $2 AND resolved_at IS NULL;
+        "#;
+        sqlx::query(delete_sql)
+            .bind(org)
+            .bind(document_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let insert_sql = r#"
+            INSERT INTO document_policy_findings (org, document, block_path, category, severity, rule, excerpt)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, block_path, category, severity, rule, excerpt, created_at, resolved_at, resolved_by;
+        "#;
+        let mut rows = Vec::with_capacity(findings.len());
+        for finding in findings {
+            let row = sqlx::query_as::<_, DocumentPolicyFindingRow>(insert_sql)
+                .bind(org)
+                .bind(document_id)
+                .bind(&finding.block_path)
+                .bind(&finding.category)
+                .bind(&finding.severity)
+                .bind(&finding.rule)
+                .bind(&finding.excerpt)
+                .fetch_one(&mut *tx)
+                .await?;
+            rows.push(row);
+        }
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Get the policy findings of a document, newest first
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `include_resolved` - Whether resolved findings are included
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentPolicyFindingRow>, SqlxError>` - The findings
+    pub async fn get_policy_findings(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        include_resolved: bool,
+    ) -> Result<Vec<DocumentPolicyFindingRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT id, block_path, category, severity, rule, excerpt, created_at, resolved_at, resolved_by
+            FROM document_policy_findings
+            WHERE org = $1 AND document = $2 AND ($3 OR resolved_at IS NULL)
+            ORDER BY created_at DESC, block_path;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentPolicyFindingRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(include_resolved)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Resolve all open policy findings of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `by_prpl` - Principal reviewing the findings
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - The number of resolved findings
+    pub async fn resolve_policy_findings(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        by_prpl: &str,
+    ) -> Result<u64, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE document_policy_findings
+            SET resolved_at = NOW(), resolved_by = $3
+            WHERE org = $1 AND document = $2 AND resolved_at IS NULL;
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Store the usage samples of the organizations active on an instance
+    ///
+    /// # Arguments
+    /// * `pod` - Instance that took the samples
+    /// * `sampled_at` - Time of the samples
+    /// * `samples` - One sample per organization
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn insert_org_usage_samples(
+        &self,
+        pod: &str,
+        sampled_at: DateTime<Utc>,
+        samples: &[OrgUsageSampleRow],
+    ) -> Result<(), SqlxError> {
+        // Samples of all orgs are written at once, outside of an org policy context
+        let mut tx = self.pool.begin().await?;
+
+        let insert_sql = r#"
+            INSERT INTO org_usage_samples (org, pod, sampled_at, n_rooms, n_doc_rooms, n_conn, n_updates)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (org, sampled_at, pod) DO NOTHING;
+        "#;
+        for sample in samples {
+            sqlx::query(insert_sql)
+                .bind(&sample.org)
+                .bind(pod)
+                .bind(sampled_at)
+                .bind(sample.n_rooms)
+                .bind(sample.n_doc_rooms)
+                .bind(sample.n_conn)
+                .bind(sample.n_updates)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Delete the usage samples taken before a time
+    ///
+    /// # Arguments
+    /// * `before` - Samples taken before this time are deleted
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - The number of deleted samples
+    pub async fn delete_org_usage_samples_before(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<u64, SqlxError> {
+        let delete_sql = r#"
+            DELETE FROM org_usage_samples WHERE sampled_at < $1;
+        "#;
+        let result = sqlx::query(delete_sql)
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Get the usage of an organization aggregated per time bucket
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `since` - Start of the range
+    /// * `bucket_secs` - Length of a bucket in seconds
+    ///
+    /// # Returns
+    /// * `Result<Vec<OrgUsageBucketRow>, SqlxError>` - The buckets with samples, oldest first
+    pub async fn get_org_usage(
+        &self,
+        org: &str,
+        since: DateTime<Utc>,
+        bucket_secs: i64,
+    ) -> Result<Vec<OrgUsageBucketRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        // The samples of all pods in the same minute add up to the usage of the org at that time
+        let query_sql = r#"
+            WITH per_minute AS (
+                SELECT date_trunc('minute', sampled_at) AS minute,
+                       SUM(n_rooms) AS n_rooms,
+                       SUM(n_doc_rooms) AS n_doc_rooms,
+                       SUM(n_conn) AS n_conn,
+                       SUM(n_updates) AS n_updates
+                FROM org_usage_samples
+                WHERE org = $1 AND sampled_at >= $2
+                GROUP BY 1
+            )
+            SELECT to_timestamp(floor(extract(epoch FROM minute) / $3) * $3) AS bucket_start,
+                   MAX(n_rooms)::BIGINT AS peak_rooms,
+                   MAX(n_doc_rooms)::BIGINT AS peak_doc_rooms,
+                   MAX(n_conn)::BIGINT AS peak_conn,
+                   AVG(n_conn)::FLOAT8 AS avg_conn,
+                   SUM(n_conn)::BIGINT AS conn_samples,
+                   SUM(n_updates)::BIGINT AS n_updates
+            FROM per_minute
+            GROUP BY 1
+            ORDER BY 1;
+        "#;
+        let rows = sqlx::query_as::<_, OrgUsageBucketRow>(query_sql)
+            .bind(org)
+            .bind(since)
+            .bind(bucket_secs as f64)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Record the documents that received updates in a month
+    ///
+    /// # Arguments
+    /// * `month` - First day of the month
+    /// * `documents` - Organization and UUID of every updated document
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn insert_org_active_documents(
+        &self,
+        month: chrono::NaiveDate,
+        documents: &[(String, uuid::Uuid)],
+    ) -> Result<(), SqlxError> {
+        // Documents of all orgs are written at once, outside of an org policy context
+        let mut tx = self.pool.begin().await?;
+
+        let insert_sql = r#"
+            INSERT INTO org_active_documents (org, month, document)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (org, month, document) DO NOTHING;
+        "#;
+        for (org, document_id) in documents {
+            sqlx::query(insert_sql)
+                .bind(org)
+                .bind(month)
+                .bind(document_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Log an export of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `kind` - Kind of export, e.g. evidence or statement-grid-csv
+    /// * `by_prpl` - Principal performing the export
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn insert_export_event(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        kind: &str,
+        by_prpl: &str,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let insert_sql = r#"
+            INSERT INTO org_export_events (org, document, kind, created_by)
+            VALUES ($1, $2, $3, $4);
+        "#;
+        sqlx::query(insert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(kind)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Sample the bytes stored per organization for today
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - The number of sampled organizations
+    pub async fn sample_org_storage(&self) -> Result<u64, SqlxError> {
+        let upsert_sql = r#"
+            INSERT INTO org_storage_samples (org, day, bytes)
+            SELECT org, CURRENT_DATE, COALESCE(SUM(size), 0)
+            FROM document_streams
+            WHERE deleted = FALSE
+            GROUP BY org
+            ON CONFLICT (org, day) DO UPDATE SET bytes = EXCLUDED.bytes;
+        "#;
+        let result = sqlx::query(upsert_sql)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Get the number of active documents per organization in a month
+    ///
+    /// # Arguments
+    /// * `month` - First day of the month
+    ///
+    /// # Returns
+    /// * `Result<Vec<OrgBillingFigureRow>, SqlxError>` - The count per organization
+    pub async fn get_billing_active_documents(
+        &self,
+        month: chrono::NaiveDate,
+    ) -> Result<Vec<OrgBillingFigureRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT org, COUNT(*)::BIGINT AS value
+            FROM org_active_documents
+            WHERE month = $1
+            GROUP BY org;
+        "#;
+        sqlx::query_as::<_, OrgBillingFigureRow>(query_sql)
+            .bind(month)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Get the sum of the sampled connections per organization in a period
+    ///
+    /// # Arguments
+    /// * `from` - Start of the period
+    /// * `to` - End of the period, exclusive
+    ///
+    /// # Returns
+    /// * `Result<Vec<OrgBillingFigureRow>, SqlxError>` - The sampled connections per organization
+    pub async fn get_billing_connection_samples(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<OrgBillingFigureRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT org, SUM(n_conn)::BIGINT AS value
+            FROM org_usage_samples
+            WHERE sampled_at >= $1 AND sampled_at < $2
+            GROUP BY org;
+        "#;
+        sqlx::query_as::<_, OrgBillingFigureRow>(query_sql)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Get the number of exports per organization in a period
+    ///
+    /// # Arguments
+    /// * `from` - Start of the period
+    /// * `to` - End of the period, exclusive
+    ///
+    /// # Returns
+    /// * `Result<Vec<OrgBillingFigureRow>, SqlxError>` - The exports per organization
+    pub async fn get_billing_exports(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<OrgBillingFigureRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT org, COUNT(*)::BIGINT AS value
+            FROM org_export_events
+            WHERE created_at >= $1 AND created_at < $2
+            GROUP BY org;
+        "#;
+        sqlx::query_as::<_, OrgBillingFigureRow>(query_sql)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Get the average and peak stored bytes per organization over a period of days
+    ///
+    /// # Arguments
+    /// * `from` - First day of the period
+    /// * `to` - Day after the period
+    ///
+    /// # Returns
+    /// * `Result<Vec<OrgBillingStorageRow>, SqlxError>` - The stored bytes per organization
+    pub async fn get_billing_storage(
+        &self,
+        from: chrono::NaiveDate,
+        to: chrono::NaiveDate,
+    ) -> Result<Vec<OrgBillingStorageRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT org, AVG(bytes)::BIGINT AS avg_bytes, MAX(bytes)::BIGINT AS peak_bytes
+            FROM org_storage_samples
+            WHERE day >= $1 AND day < $2
+            GROUP BY org;
+        "#;
+        sqlx::query_as::<_, OrgBillingStorageRow>(query_sql)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Store the body of a lazily loaded sheet block
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID of the sheet
+    /// * `block_id` - Identifier of the block in the sheet
+    /// * `package` - CBOR encoded ColabPackage of the block body
+    /// * `json` - JSON of the rows of the block
+    /// * `by_prpl` - Principal that made the last change
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn upsert_document_block(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        block_id: &str,
+        package: Vec<u8>,
+        json: serde_json::Value,
+        by_prpl: &str,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO document_blocks (org, document, block_id, package, json, updated_at, updated_by)
+            VALUES ($1, $2, $3, $4, $5, NOW(), $6)
+            ON CONFLICT (org, document, block_id) DO UPDATE SET
+                package = EXCLUDED.package,
+                json = EXCLUDED.json,
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by;
+        "#;
+        sqlx::query(upsert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(block_id)
+            .bind(package)
+            .bind(json)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the body of a lazily loaded sheet block
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID of the sheet
+    /// * `block_id` - Identifier of the block in the sheet
+    ///
+    /// # Returns
+    /// * `Result<Option<DocumentBlockRow>, SqlxError>` - The block body, None when it isn't stored
+    pub async fn get_document_block(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        block_id: &str,
+    ) -> Result<Option<DocumentBlockRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let select_sql = r#"
+            SELECT block_id, package, json, updated_at, updated_by
+            FROM document_blocks
+            WHERE org = $1 AND document = $2 AND block_id = $3;
+        "#;
+        let row = sqlx::query_as::<_, DocumentBlockRow>(select_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(block_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Get the bodies of all lazily loaded blocks of a sheet
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID of the sheet
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentBlockRow>, SqlxError>` - The block bodies
+    pub async fn get_document_blocks(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Vec<DocumentBlockRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let select_sql = r#"
+            SELECT block_id, package, json, updated_at, updated_by
+            FROM document_blocks
+            WHERE org = $1 AND document = $2;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentBlockRow>(select_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Delete the bodies of the lazily loaded blocks of a sheet
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID of the sheet
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - Number of deleted block bodies
+    pub async fn delete_document_blocks(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<u64, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let delete_sql = r#"
+            DELETE FROM document_blocks
+            WHERE org = $1 AND document = $2;
+        "#;
+        let result = sqlx::query(delete_sql)
+            .bind(org)
+            .bind(document_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Store a statement subdocument of a sheet
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `subdoc_id` - UUID of the subdocument
+    /// * `sheet_id` - Document UUID of the sheet owning the subdocument
+    /// * `package` - CBOR encoded ColabPackage of the subdocument
+    /// * `json` - JSON of the statement
+    /// * `by_prpl` - Principal that made the last change
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn upsert_statement_subdoc(
+        &self,
+        org: &str,
+        subdoc_id: uuid::Uuid,
+        sheet_id: uuid::Uuid,
+        package: Vec<u8>,
+        json: serde_json::Value,
+        by_prpl: &str,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO statement_subdocs (org, id, sheet, package, json, created_at, updated_at, updated_by)
+            VALUES ($1, $2, $3, $4, $5, NOW(), NOW(), $6)
+            ON CONFLICT (org, id) DO UPDATE SET
+                package = EXCLUDED.package,
+                json = EXCLUDED.json,
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by;
+        "#;
+        sqlx::query(upsert_sql)
+            .bind(org)
+            .bind(subdoc_id)
+            .bind(sheet_id)
+            .bind(package)
+            .bind(json)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get statement subdocuments
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `subdoc_ids` - UUIDs of the subdocuments
+    ///
+    /// # Returns
+    /// * `Result<Vec<StatementSubdocRow>, SqlxError>` - The subdocuments that were found
+    pub async fn get_statement_subdocs(
+        &self,
+        org: &str,
+        subdoc_ids: &[uuid::Uuid],
+    ) -> Result<Vec<StatementSubdocRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let select_sql = r#"
+            SELECT id, sheet, package, json, updated_at, updated_by
+            FROM statement_subdocs
+            WHERE org = $1 AND id = ANY($2);
+        "#;
+        let rows = sqlx::query_as::<_, StatementSubdocRow>(select_sql)
+            .bind(org)
+            .bind(subdoc_ids)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Replace the transclusions of a sheet, the notified hashes of unchanged ones are kept
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `sheet_id` - Document UUID of the sheet
+    /// * `links` - Block id, source document and source block of every transclusion
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn replace_document_transclusions(
+        &self,
+        org: &str,
+        sheet_id: uuid::Uuid,
+        links: &[(String, uuid::Uuid, String)],
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let block_ids: Vec<String> = links.iter().map(|(block_id, _, _)| block_id.clone()).collect();
+        let delete_sql = r#"
+            DELETE FROM document_transclusions
+            WHERE org = $1 AND sheet = $2 AND NOT (block_id = ANY($3));
+        "#;
+        sqlx::query(delete_sql)
+            .bind(org)
+            .bind(sheet_id)
+            .bind(&block_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        let upsert_sql = r#"
+            INSERT INTO document_transclusions (org, sheet, block_id, source, source_block, updated_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            ON CONFLICT (org, sheet, block_id) DO UPDATE SET
+                source = EXCLUDED.source,
+                source_block = EXCLUDED.source_block,
+                source_hash = CASE
+                    WHEN document_transclusions.source = EXCLUDED.source
+                        AND document_transclusions.source_block = EXCLUDED.source_block
+                    THEN document_transclusions.source_hash
+                END,
+                updated_at = EXCLUDED.updated_at;
+        "#;
+        for (block_id, source, source_block) in links {
+            sqlx::query(upsert_sql)
+                .bind(org)
+                .bind(sheet_id)
+                .bind(block_id)
+                .bind(source)
+                .bind(source_block)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the transclusions showing a block of a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `source_id` - Document UUID of the source
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentTransclusionRow>, SqlxError>` - The transclusions of the source's blocks
+    pub async fn get_transclusions_of_source(
+        &self,
+        org: &str,
+        source_id: uuid::Uuid,
+    ) -> Result<Vec<DocumentTransclusionRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let select_sql = r#"
+            SELECT sheet, block_id, source, source_block, source_hash
+            FROM document_transclusions
+            WHERE org = $1 AND source = $2;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentTransclusionRow>(select_sql)
+            .bind(org)
+            .bind(source_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Record the hash of the source block a transclusion was notified about
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `sheet_id` - Document UUID of the sheet
+    /// * `block_id` - Identifier of the transclusion block
+    /// * `source_hash` - Hash of the source block
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn set_transclusion_source_hash(
+        &self,
+        org: &str,
+        sheet_id: uuid::Uuid,
+        block_id: &str,
+        source_hash: &str,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE document_transclusions
+            SET source_hash = $4, updated_at = NOW()
+            WHERE org = $1 AND sheet = $2 AND block_id = $3;
+        "#;
+        sqlx::query(update_sql)
+            .bind(org)
+            .bind(sheet_id)
+            .bind(block_id)
+            .bind(source_hash)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replace the references of a document to other documents
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID of the referencing document
+    /// * `links` - Target document, kind and path of every reference
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn replace_document_links(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        links: &[(uuid::Uuid, String, String)],
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let delete_sql = r#"
+            DELETE FROM document_links
+            WHERE org = $1 AND document = $2;
+        "#;
+        sqlx::query(delete_sql)
+            .bind(org)
+            .bind(document_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let insert_sql = r#"
+            INSERT INTO document_links (org, document, target, kind, path, updated_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            ON CONFLICT (org, document, target, kind, path) DO NOTHING;
+        "#;
+        for (target, kind, path) in links {
+            sqlx::query(insert_sql)
+                .bind(org)
+                .bind(document_id)
+                .bind(target)
+                .bind(kind)
+                .bind(path)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the references of a document to other documents
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentLinkRow>, SqlxError>` - The references, named after their target.
+    ///   Mentions of ids that aren't documents are left out.
+    pub async fn get_document_links(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Vec<DocumentLinkRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT l.document, l.target, l.kind, l.path, d.name
+            FROM document_links l
+            LEFT JOIN documents d ON d.id = l.target AND d.deleted = FALSE
+            WHERE l.org = $1 AND l.document = $2
+                AND (l.kind <> 'mention' OR d.id IS NOT NULL)
+            ORDER BY l.path;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentLinkRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Get the references of other documents to a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID of the target
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentLinkRow>, SqlxError>` - The references of documents that aren't deleted,
+    ///   named after the referencing document
+    pub async fn get_document_backlinks(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Vec<DocumentLinkRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT l.document, l.target, l.kind, l.path, d.name
+            FROM document_links l
+            JOIN documents d ON d.id = l.document AND d.deleted = FALSE
+            WHERE l.org = $1 AND l.target = $2
+            ORDER BY d.name, l.path;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentLinkRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Get the saved JSON of every document of an org that isn't deleted
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentJsonRow>, SqlxError>` - The statements and sheets of the org
+    pub async fn get_org_document_json(
+        &self,
+        org: &str,
+    ) -> Result<Vec<DocumentJsonRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT d.id, d.name, d.type, st.json
+            FROM documents d
+            JOIN document_statements st ON d.id = st.document
+            WHERE d.org = $1 AND d.type = 'colab-statement' AND d.deleted = FALSE AND st.json IS NOT NULL
+            UNION ALL
+            SELECT d.id, d.name, d.type, sh.json
+            FROM documents d
+            JOIN document_sheets sh ON d.id = sh.document
+            WHERE d.org = $1 AND d.type = 'colab-sheet' AND d.deleted = FALSE AND sh.json IS NOT NULL;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentJsonRow>(query_sql)
+            .bind(org)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Replace the report of the duplicated statements of an org
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `threshold` - Similarity from which statements are duplicates
+    /// * `statements` - Number of analyzed statements
+    /// * `clusters` - The clusters of duplicated statements
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or error
+    pub async fn upsert_statement_duplicates(
+        &self,
+        org: &str,
+        threshold: f64,
+        statements: i32,
+        clusters: serde_json::Value,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO org_statement_duplicates (org, threshold, statements, clusters, generated_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            ON CONFLICT (org) DO UPDATE SET
+                threshold = EXCLUDED.threshold,
+                statements = EXCLUDED.statements,
+                clusters = EXCLUDED.clusters,
+                generated_at = EXCLUDED.generated_at;
+        "#;
+        sqlx::query(upsert_sql)
+            .bind(org)
+            .bind(threshold)
+            .bind(statements)
+            .bind(Json(clusters))
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get the report of the duplicated statements of an org
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<StatementDuplicatesRow>, SqlxError>` - The latest report, None when it was never analyzed
+    pub async fn get_statement_duplicates(
+        &self,
+        org: &str,
+    ) -> Result<Option<StatementDuplicatesRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT threshold, statements, clusters, generated_at
+            FROM org_statement_duplicates
+            WHERE org = $1;
+        "#;
+        let row = sqlx::query_as::<_, StatementDuplicatesRow>(query_sql)
+            .bind(org)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Create a colab document with its model and its first stream, before anyone connects to it
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - The reserved UUID of the document
+    /// * `name` - Name of the document
+    /// * `doc_type` - "colab-statement" or "colab-sheet"
+    /// * `owner` - Principal owning the document
+    /// * `container` - Library or folder holding the document, if any
+    /// * `container_type` - Type of the container, if any
+    /// * `json` - The JSON of the initial state
+    /// * `colab_package_blob` - The CBOR encoded package with the initial snapshot
+    /// * `state_vv_json` - The version vector of the initial state
+    /// * `peer_map_json` - The peer map of the initial state
+    /// * `content_sha256` - Hash of the content of the JSON without its metadata
+    /// * `by_prpl` - Principal creating the document
+    ///
+    /// # Returns
+    /// * `Result<uuid::Uuid, SqlxError>` - The id of the first stream
+    pub async fn create_colab_doc(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        name: &str,
+        doc_type: &str,
+        owner: &str,
+        container: Option<uuid::Uuid>,
+        container_type: Option<&str>,
+        json: serde_json::Value,
+        colab_package_blob: Vec<u8>,
+        state_vv_json: serde_json::Value,
+        peer_map_json: serde_json::Value,
+        content_sha256: &str,
+        by_prpl: &str,
+    ) -> Result<uuid::Uuid, SqlxError> {
+        let doc_table_name = match doctypes::get(doc_type) {
+            Some(doc_type) => doc_type.table(),
+            None => {
+                error!("Unsupported document type for creation: {}", doc_type);
+                return Err(SqlxError::RowNotFound);
+            }
+        };
+        let content_size = colab_package_blob.len() as i64;
+
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let document_sql = r#"
+            INSERT INTO documents (id, org, name, type, owner, container, container_type, created_by, updated_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8);
+        "#;
+        sqlx::query(document_sql)
+            .bind(document_id)
+            .bind(org)
+            .bind(name)
+            .bind(doc_type)
+            .bind(owner)
+            .bind(container)
+            .bind(container_type)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        // The model is left unsynced, the app picks up its ACLs like after any save
+        let model_sql = format!(r#"
+            INSERT INTO {} (org, document, json, version_v, peer_map, synced, updated_at, updated_by)
+            VALUES ($1, $2, $3, $4, $5, FALSE, NOW(), $6);
+        "#, doc_table_name);
+        sqlx::query(&model_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(json)
+            .bind(state_vv_json)
+            .bind(peer_map_json)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        let stream_sql = r#"
+            INSERT INTO document_streams (org, document, name, content, version, size, created_by, updated_by, content_sha256)
+            VALUES ($1, $2, 'main', $3, 1, $4, $5, $5, $6)
+            RETURNING id;
+        "#;
+        let row = sqlx::query(stream_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(colab_package_blob)
+            .bind(content_size)
+            .bind(by_prpl)
+            .bind(content_sha256)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        let stream_id: uuid::Uuid = row.try_get("id")?;
+        info!("Document '{}' created with stream {}", document_id, stream_id);
+        Ok(stream_id)
+    }
+
+    /// Check whether the initialization hooks of a document ran
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - The UUID of the document
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - Whether the document was initialized
+    pub async fn is_document_initialized(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT 1 FROM document_initializations
+            WHERE org = $1 AND document = $2;
+        "#;
+        let row = sqlx::query(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row.is_some())
+    }
+
+    /// Record the initialization hooks that ran on a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - The UUID of the document
+    /// * `content_type` - Content type whose hooks ran
+    /// * `hooks` - Names of the hooks that ran
+    /// * `by_prpl` - Principal running the hooks
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False when the document was already initialized
+    pub async fn insert_document_initialization(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        content_type: &str,
+        hooks: &[String],
+        by_prpl: &str,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let insert_sql = r#"
+            INSERT INTO document_initializations (org, document, content_type, hooks, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (org, document) DO NOTHING;
+        "#;
+        let result = sqlx::query(insert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(content_type)
+            .bind(hooks)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the document types registered by an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<OrgDocumentTypesRow>, SqlxError>` - The registered types, None when the org has none
+    pub async fn get_org_document_types(
+        &self,
+        org: &str,
+    ) -> Result<Option<OrgDocumentTypesRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT strict, types, updated_at, updated_by
+            FROM org_document_types
+            WHERE org = $1;
+        "#;
+        let row = sqlx::query_as::<_, OrgDocumentTypesRow>(query_sql)
+            .bind(org)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Set the document types registered by an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `strict` - Whether documents of unregistered types are rejected
+    /// * `types` - The content types registered per document type
+    /// * `by_prpl` - Principal changing the registration
+    ///
+    /// # Returns
+    /// * `Result<OrgDocumentTypesRow, SqlxError>` - The updated registration
+    pub async fn set_org_document_types(
+        &self,
+        org: &str,
+        strict: bool,
+        types: serde_json::Value,
+        by_prpl: &str,
+    ) -> Result<OrgDocumentTypesRow, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO org_document_types (org, strict, types, updated_at, updated_by)
+            VALUES ($1, $2, $3, NOW(), $4)
+            ON CONFLICT (org) DO UPDATE SET
+                strict = EXCLUDED.strict,
+                types = EXCLUDED.types,
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by
+            RETURNING strict, types, updated_at, updated_by;
+        "#;
+        let row = sqlx::query_as::<_, OrgDocumentTypesRow>(upsert_sql)
+            .bind(org)
+            .bind(strict)
+            .bind(types)
+            .bind(by_prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Reserve the next numbers of a prefix, numbers are never handed out twice
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `prefix` - Prefix of the numbers, e.g. CLM
+    /// * `count` - Amount of numbers to reserve
+    ///
+    /// # Returns
+    /// * `Result<Vec<i32>, SqlxError>` - The reserved sequence numbers, ascending
+    pub async fn reserve_number_sequence(
+        &self,
+        org: &str,
+        prefix: &str,
+        count: i32,
+    ) -> Result<Vec<i32>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO org_number_sequences (org, prefix, last)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (org, prefix) DO UPDATE SET
+                last = org_number_sequences.last + EXCLUDED.last
+            RETURNING last;
+        "#;
+        let last: i32 = sqlx::query_scalar(upsert_sql)
+            .bind(org)
+            .bind(prefix)
+            .bind(count)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(((last - count + 1)..=last).collect())
+    }
+
+    /// Record the numbers of a document and its blocks, parts that already have a number keep it
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - UUID of the document
+    /// * `numbers` - Block id (empty for the document), number, prefix and sequence number
+    /// * `by_prpl` - Principal the numbers are assigned by
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - The amount of numbers recorded
+    pub async fn insert_document_numbers(
+        &self,
+        org: &str,
+        document_id: Uuid,
+        numbers: &[(String, String, String, i32)],
+        by_prpl: &str,
+    ) -> Result<u64, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let insert_sql = r#"
+            INSERT INTO document_numbers (org, number, prefix, seq, document, block_id, created_at, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW(), $7)
+            ON CONFLICT DO NOTHING;
+        "#;
+        let mut recorded = 0;
+        for (block_id, number, prefix, seq) in numbers {
+            let result = sqlx::query(insert_sql)
+                .bind(org)
+                .bind(number)
+                .bind(prefix)
+                .bind(seq)
+                .bind(document_id)
+                .bind(block_id)
+                .bind(by_prpl)
+                .execute(&mut *tx)
+                .await?;
+            recorded += result.rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(recorded)
+    }
+
+    /// Get the numbers of a document and its blocks
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - UUID of the document
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentNumberRow>, SqlxError>` - The numbers of the document
+    pub async fn get_document_numbers(
+        &self,
+        org: &str,
+        document_id: Uuid,
+    ) -> Result<Vec<DocumentNumberRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT number, document, block_id, created_at, created_by
+            FROM document_numbers
+            WHERE org = $1 AND document = $2;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentNumberRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Find the document or block carrying a number
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `number` - The number, e.g. CLM-0042
+    ///
+    /// # Returns
+    /// * `Result<Option<DocumentNumberRow>, SqlxError>` - The number, None when it was never handed out
+    pub async fn get_document_number(
+        &self,
+        org: &str,
+        number: &str,
+    ) -> Result<Option<DocumentNumberRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT number, document, block_id, created_at, created_by
+            FROM document_numbers
+            WHERE org = $1 AND number = $2;
+        "#;
+        let row = sqlx::query_as::<_, DocumentNumberRow>(query_sql)
+            .bind(org)
+            .bind(number)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Get the documents of an org that aren't deleted, optionally only those of a library
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `library` - UUID of the library, None for all documents of the org
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentRefRow>, SqlxError>` - The statements and sheets, by name
+    pub async fn get_org_documents(
+        &self,
+        org: &str,
+        library: Option<uuid::Uuid>,
+    ) -> Result<Vec<DocumentRefRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT id, name, type
+            FROM documents
+            WHERE org = $1 AND deleted = FALSE
+                AND type IN ('colab-statement', 'colab-sheet')
+                AND ($2::uuid IS NULL OR (container = $2 AND container_type = 'library'))
+            ORDER BY name, id;
+        "#;
+        let rows = sqlx::query_as::<_, DocumentRefRow>(query_sql)
+            .bind(org)
+            .bind(library)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Create a bulk find-and-replace job
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `job_id` - UUID of the job
+    /// * `request` - The request of the job
+    /// * `dry_run` - Whether the job only counts the matches
+    /// * `by_prpl` - Principal starting the job
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or database error
+    pub async fn insert_replace_job(
+        &self,
+        org: &str,
+        job_id: uuid::Uuid,
+        request: serde_json::Value,
+        dry_run: bool,
+        by_prpl: &str,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let insert_sql = r#"
+            INSERT INTO replace_jobs (id, org, request, dry_run, status, created_at, created_by)
+            VALUES ($1, $2, $3, $4, 'running', NOW(), $5);
+        "#;
+        sqlx::query(insert_sql)
+            .bind(job_id)
+            .bind(org)
+            .bind(Json(request))
+            .bind(dry_run)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Record the progress of a bulk find-and-replace job
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `job_id` - UUID of the job
+    /// * `status` - running, done or failed
+    /// * `documents` - Number of documents the job goes through
+    /// * `processed` - Number of documents handled so far
+    /// * `results` - The results of the documents with matches
+    /// * `error` - Why the job failed, if it did
+    ///
+    /// # Returns
+    /// * `Result<(), SqlxError>` - Success or database error
+    pub async fn update_replace_job(
+        &self,
+        org: &str,
+        job_id: uuid::Uuid,
+        status: &str,
+        documents: i32,
+        processed: i32,
+        results: serde_json::Value,
+        error: Option<&str>,
+    ) -> Result<(), SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE replace_jobs
+            SET status = $3, documents = $4, processed = $5, results = $6, error = $7,
+                finished_at = CASE WHEN $3 = 'running' THEN NULL ELSE NOW() END
+            WHERE org = $1 AND id = $2;
+        "#;
+        sqlx::query(update_sql)
+            .bind(org)
+            .bind(job_id)
+            .bind(status)
+            .bind(documents)
+            .bind(processed)
+            .bind(Json(results))
+            .bind(error)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get a bulk find-and-replace job
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `job_id` - UUID of the job
+    ///
+    /// # Returns
+    /// * `Result<Option<ReplaceJobRow>, SqlxError>` - The job, None when it doesn't exist
+    pub async fn get_replace_job(
+        &self,
+        org: &str,
+        job_id: uuid::Uuid,
+    ) -> Result<Option<ReplaceJobRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT id, request, dry_run, status, documents, processed, results, error, created_at, created_by, finished_at
+            FROM replace_jobs
+            WHERE org = $1 AND id = $2;
+        "#;
+        let row = sqlx::query_as::<_, ReplaceJobRow>(query_sql)
+            .bind(org)
+            .bind(job_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Tag the state of a document, e.g. before it is changed by a bulk job
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - UUID of the document
+    /// * `tag` - Name of the tag, unique per document
+    /// * `state_vv` - Version vector of the tagged state
+    /// * `peer` - Peer of the changes made after the tagged state, if any
+    /// * `by_prpl` - Principal tagging the document
+    ///
+    /// # Returns
+    /// * `Result<Option<i32>, SqlxError>` - The latest saved version of the document when it was tagged
+    pub async fn insert_document_version_tag(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        tag: &str,
+        state_vv: serde_json::Value,
+        peer: Option<&str>,
+        by_prpl: &str,
+    ) -> Result<Option<i32>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let insert_sql = r#"
+            INSERT INTO document_version_tags (org, document, tag, version, state_vv, peer, created_at, created_by)
+            SELECT $1, $2, $3,
+                (SELECT MAX(version) FROM document_streams WHERE org = $1 AND document = $2 AND name = 'main'),
+                $4, $5, NOW(), $6
+            ON CONFLICT (org, document, tag) DO UPDATE SET
+                version = EXCLUDED.version,
+                state_vv = EXCLUDED.state_vv,
+                peer = EXCLUDED.peer,
+                created_at = EXCLUDED.created_at,
+                created_by = EXCLUDED.created_by
+            RETURNING version;
+        "#;
+        let version: Option<i32> = sqlx::query_scalar(insert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(tag)
+            .bind(Json(state_vv))
+            .bind(peer)
+            .bind(by_prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(version)
+    }
+
+    /// Apply the migrations embedded in the binary that weren't applied yet
+    ///
+    /// # Returns
+    /// * `Result<(), MigrateError>` - Success or error
+    pub async fn run_migrations(&self) -> Result<(), sqlx::migrate::MigrateError> {
+        sqlx::migrate!("./migrations").run(&self.pool).await
+    }
+
+    /// Soft delete versions of the main stream of a document, the latest version is never deleted
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `versions` - The versions to delete
+    /// * `by_prpl` - The principal deleting them
+    ///
+    /// # Returns
+    /// * `Result<u64, SqlxError>` - The number of deleted versions
+    pub async fn delete_doc_stream_versions(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        versions: &[i32],
+        by_prpl: &str,
+    ) -> Result<u64, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE document_streams SET
+                deleted = TRUE,
+                updated_at = CURRENT_TIMESTAMP,
+                updated_by = $4
+            WHERE org = $1 AND document = $2 AND name = 'main' AND version = ANY($3) AND deleted = FALSE
+                AND version < (SELECT MAX(version) FROM document_streams WHERE org = $1 AND document = $2 AND name = 'main' AND deleted = FALSE);
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(versions)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Replace the inline content of a stream with the same state in another encoding
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `stream_id` - Document stream UUID
+    /// * `content` - The re-encoded ColabPackage
+    /// * `by_prpl` - The principal re-encoding it
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False if the stream has no inline content
+    pub async fn update_doc_stream_content(
+        &self,
+        org: &str,
+        stream_id: uuid::Uuid,
+        content: Vec<u8>,
+        by_prpl: &str,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE document_streams SET
+                content = $3,
+                size = $4,
+                updated_at = CURRENT_TIMESTAMP,
+                updated_by = $5
+            WHERE org = $1 AND id = $2 AND content IS NOT NULL AND deleted = FALSE;
+        "#;
+        let size = content.len() as i64;
+        let result = sqlx::query(update_sql)
+            .bind(org)
+            .bind(stream_id)
+            .bind(content)
+            .bind(size)
+            .bind(by_prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+    /// Get the preferences and read marker of a user on a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `prpl` - Principal of the user
+    ///
+    /// # Returns
+    /// * `Result<Option<DocumentUserPrefsRow>, SqlxError>` - The preferences, None if they were never set
+    pub async fn get_document_user_prefs(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        prpl: &str,
+    ) -> Result<Option<DocumentUserPrefsRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT last_read_version, last_read_vv, last_read_at, pinned_blocks, collapsed_sections, updated_at
+            FROM document_user_prefs
+            WHERE org = $1 AND document = $2 AND prpl = $3;
+        "#;
+        let row = sqlx::query_as::<_, DocumentUserPrefsRow>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(prpl)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Store the preferences and read marker of a user on a document.
+    /// Fields passed as None keep their stored value.
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `prpl` - Principal of the user
+    /// * `read_marker` - Latest saved version and version vector the user has read, if it moves
+    /// * `pinned_blocks` - Pinned blocks or languages
+    /// * `collapsed_sections` - Collapsed blocks or languages
+    ///
+    /// # Returns
+    /// * `Result<DocumentUserPrefsRow, SqlxError>` - The stored preferences
+    pub async fn upsert_document_user_prefs(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        prpl: &str,
+        read_marker: Option<(i32, serde_json::Value)>,
+        pinned_blocks: Option<&[String]>,
+        collapsed_sections: Option<&[String]>,
+    ) -> Result<DocumentUserPrefsRow, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let (last_read_version, last_read_vv) = match read_marker {
+            Some((version, vv)) => (Some(version), Some(Json(vv))),
+            None => (None, None),
+        };
+        let upsert_sql = r#"
+            INSERT INTO document_user_prefs (org, document, prpl, last_read_version, last_read_vv, last_read_at, pinned_blocks, collapsed_sections, updated_at)
+            VALUES ($1, $2, $3, $4, $5, CASE WHEN $5::JSONB IS NULL THEN NULL ELSE NOW() END, COALESCE($6, '{}'), COALESCE($7, '{}'), NOW())
+            ON CONFLICT (org, document, prpl) DO UPDATE SET
+                last_read_version = CASE WHEN $5::JSONB IS NULL THEN document_user_prefs.last_read_version ELSE EXCLUDED.last_read_version END,
+                last_read_vv = COALESCE(EXCLUDED.last_read_vv, document_user_prefs.last_read_vv),
+                last_read_at = COALESCE(EXCLUDED.last_read_at, document_user_prefs.last_read_at),
+                pinned_blocks = COALESCE($6, document_user_prefs.pinned_blocks),
+                collapsed_sections = COALESCE($7, document_user_prefs.collapsed_sections),
+                updated_at = EXCLUDED.updated_at
+            RETURNING last_read_version, last_read_vv, last_read_at, pinned_blocks, collapsed_sections, updated_at;
+        "#;
+        let row = sqlx::query_as::<_, DocumentUserPrefsRow>(upsert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(prpl)
+            .bind(last_read_version)
+            .bind(last_read_vv)
+            .bind(pinned_blocks)
+            .bind(collapsed_sections)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+    /// Follow a document, or change the digest frequency of a followed document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `prpl` - Principal of the user
+    /// * `frequency` - "daily" or "weekly"
+    /// * `last_vv` - Version vector of the document now, the first digest lists the changes since
+    ///
+    /// # Returns
+    /// * `Result<DocumentFollowerRow, SqlxError>` - The follow
+    pub async fn follow_document(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        prpl: &str,
+        frequency: &str,
+        last_vv: serde_json::Value,
+    ) -> Result<DocumentFollowerRow, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO document_followers (org, document, prpl, frequency, last_vv, last_digest_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
+            ON CONFLICT (org, document, prpl) DO UPDATE SET
+                frequency = EXCLUDED.frequency
+            RETURNING org, document, prpl, frequency, last_vv, last_digest_at;
+        "#;
+        let row = sqlx::query_as::<_, DocumentFollowerRow>(upsert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(prpl)
+            .bind(frequency)
+            .bind(Json(last_vv))
+            .fetch_one(&mut *tx)
+            .await?;
+
+        // Following a document watches it
+        let watch_sql = r#"
+            INSERT INTO document_watchers (org, document, prpl, created_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (org, document, prpl) DO NOTHING;
+        "#;
+        sqlx::query(watch_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Stop following a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `prpl` - Principal of the user
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False if the user didn't follow the document
+    pub async fn unfollow_document(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        prpl: &str,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let delete_sql = r#"
+            DELETE FROM document_followers
+            WHERE org = $1 AND document = $2 AND prpl = $3;
+        "#;
+        let result = sqlx::query(delete_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the follows of every organization whose digest is due, only watched documents are covered
+    ///
+    /// # Returns
+    /// * `Result<Vec<DocumentFollowerRow>, SqlxError>` - The follows, grouped by organization and user
+    pub async fn get_due_document_followers(&self) -> Result<Vec<DocumentFollowerRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT f.org, f.document, f.prpl, f.frequency, f.last_vv, f.last_digest_at FROM document_followers f
+            JOIN document_watchers w ON w.org = f.org AND w.document = f.document AND w.prpl = f.prpl
+            WHERE f.last_digest_at < NOW() - make_interval(days => CASE f.frequency WHEN 'weekly' THEN 7 ELSE 1 END)
+            ORDER BY f.org, f.prpl, f.document;
+        "#;
+        sqlx::query_as::<_, DocumentFollowerRow>(query_sql)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Claim the digest of a follow, so only one pod sends it
+    ///
+    /// # Arguments
+    /// * `follower` - The follow as it was read
+    /// * `last_vv` - Version vector of the document the digest was compiled from
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False if the digest was claimed in the meantime
+    pub async fn claim_document_follower_digest(
+        &self,
+        follower: &DocumentFollowerRow,
+        last_vv: serde_json::Value,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(&follower.org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let update_sql = r#"
+            UPDATE document_followers SET
+                last_vv = $4,
+                last_digest_at = NOW()
+            WHERE org = $1 AND document = $2 AND prpl = $3 AND last_digest_at = $5;
+        "#;
+        let result = sqlx::query(update_sql)
+            .bind(&follower.org)
+            .bind(follower.document)
+            .bind(&follower.prpl)
+            .bind(Json(last_vv))
+            .bind(follower.last_digest_at)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Watch a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `prpl` - Principal of the user
+    ///
+    /// # Returns
+    /// * `Result<DocumentWatcherRow, SqlxError>` - The watch, as it was created the first time
+    pub async fn watch_document(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        prpl: &str,
+    ) -> Result<DocumentWatcherRow, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO document_watchers (org, document, prpl, created_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (org, document, prpl) DO UPDATE SET
+                created_at = document_watchers.created_at
+            RETURNING org, document, prpl, created_at;
+        "#;
+        let row = sqlx::query_as::<_, DocumentWatcherRow>(upsert_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Stop watching a document, which also stops following it
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    /// * `prpl` - Principal of the user
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False if the user didn't watch the document
+    pub async fn unwatch_document(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+        prpl: &str,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let unfollow_sql = r#"
+            DELETE FROM document_followers
+            WHERE org = $1 AND document = $2 AND prpl = $3;
+        "#;
+        sqlx::query(unfollow_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        let delete_sql = r#"
+            DELETE FROM document_watchers
+            WHERE org = $1 AND document = $2 AND prpl = $3;
+        "#;
+        let result = sqlx::query(delete_sql)
+            .bind(org)
+            .bind(document_id)
+            .bind(prpl)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the principals watching a document
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `document_id` - Document UUID
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, SqlxError>` - The principals, in the order they started watching
+    pub async fn get_document_watchers(
+        &self,
+        org: &str,
+        document_id: uuid::Uuid,
+    ) -> Result<Vec<String>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT prpl FROM document_watchers
+            WHERE org = $1 AND document = $2
+            ORDER BY created_at, prpl;
+        "#;
+        let prpls = sqlx::query_scalar::<_, String>(query_sql)
+            .bind(org)
+            .bind(document_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(prpls)
+    }
+
+    /// Schedule an announcement for the connected clients of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `message` - Text of the announcement
+    /// * `level` - "info", "warning" or "critical"
+    /// * `starts_at` - When the announcement starts being broadcast
+    /// * `expires_at` - When the announcement expires
+    /// * `by_prpl` - Principal creating the announcement
+    ///
+    /// # Returns
+    /// * `Result<OrgAnnouncementRow, SqlxError>` - The created announcement
+    pub async fn insert_org_announcement(
+        &self,
+        org: &str,
+        message: &str,
+        level: &str,
+        starts_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+        by_prpl: &str,
+    ) -> Result<OrgAnnouncementRow, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let insert_sql = r#"
+            INSERT INTO org_announcements (org, message, level, starts_at, expires_at, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, org, message, level, starts_at, expires_at, created_at, created_by;
+        "#;
+        let row = sqlx::query_as::<_, OrgAnnouncementRow>(insert_sql)
+            .bind(org)
+            .bind(message)
+            .bind(level)
+            .bind(starts_at)
+            .bind(expires_at)
+            .bind(by_prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// List the announcements of an organization that didn't expire yet
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Vec<OrgAnnouncementRow>, SqlxError>` - The running and scheduled announcements, the first to start first
+    pub async fn list_org_announcements(
+        &self,
+        org: &str,
+    ) -> Result<Vec<OrgAnnouncementRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT id, org, message, level, starts_at, expires_at, created_at, created_by
+            FROM org_announcements
+            WHERE org = $1 AND expires_at > NOW()
+            ORDER BY starts_at, created_at;
+        "#;
+        let rows = sqlx::query_as::<_, OrgAnnouncementRow>(query_sql)
+            .bind(org)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Withdraw an announcement of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `announcement_id` - Announcement UUID
+    ///
+    /// # Returns
+    /// * `Result<bool, SqlxError>` - False if the announcement doesn't exist
+    pub async fn delete_org_announcement(
+        &self,
+        org: &str,
+        announcement_id: uuid::Uuid,
+    ) -> Result<bool, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let delete_sql = r#"
+            DELETE FROM org_announcements
+            WHERE org = $1 AND id = $2;
+        "#;
+        let result = sqlx::query(delete_sql)
+            .bind(org)
+            .bind(announcement_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the announcements of every organization that are running now
+    ///
+    /// # Returns
+    /// * `Result<Vec<OrgAnnouncementRow>, SqlxError>` - The announcements, grouped by organization
+    pub async fn get_running_org_announcements(&self) -> Result<Vec<OrgAnnouncementRow>, SqlxError> {
+        let query_sql = r#"
+            SELECT id, org, message, level, starts_at, expires_at, created_at, created_by
+            FROM org_announcements
+            WHERE starts_at <= NOW() AND expires_at > NOW()
+            ORDER BY org, starts_at;
+        "#;
+        sqlx::query_as::<_, OrgAnnouncementRow>(query_sql)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Get the locale settings of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    ///
+    /// # Returns
+    /// * `Result<Option<OrgLocaleSettingsRow>, SqlxError>` - The settings, None when the org has none
+    pub async fn get_org_locale_settings(
+        &self,
+        org: &str,
+    ) -> Result<Option<OrgLocaleSettingsRow>, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let query_sql = r#"
+            SELECT locale, timezone, date_format, datetime_format, content_types, updated_at, updated_by
+            FROM org_locale_settings
+            WHERE org = $1;
+        "#;
+        let row = sqlx::query_as::<_, OrgLocaleSettingsRow>(query_sql)
+            .bind(org)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    /// Set the locale settings of an organization
+    ///
+    /// # Arguments
+    /// * `org` - Organization identifier
+    /// * `locale` - Locale of the organization, e.g. "en-US"
+    /// * `timezone` - IANA timezone of the organization, e.g. "Europe/Brussels"
+    /// * `date_format` - strftime format of dates
+    /// * `datetime_format` - strftime format of timestamps
+    /// * `content_types` - The settings overridden per content type
+    /// * `by_prpl` - Principal changing the settings
+    ///
+    /// # Returns
+    /// * `Result<OrgLocaleSettingsRow, SqlxError>` - The updated settings
+    #[allow(clippy::too_many_arguments)]
+    pub async fn set_org_locale_settings(
+        &self,
+        org: &str,
+        locale: Option<&str>,
+        timezone: Option<&str>,
+        date_format: Option<&str>,
+        datetime_format: Option<&str>,
+        content_types: serde_json::Value,
+        by_prpl: &str,
+    ) -> Result<OrgLocaleSettingsRow, SqlxError> {
+        // Begin a transaction
+        let mut tx = self.pool.begin().await?;
+
+        // Set the policy context
+        let safe_org = escape_sql_string_literal(org);
+        let policy_sql = format!("SET LOCAL app.orgs = '{}'", safe_org);
+        sqlx::query(&policy_sql).execute(&mut *tx).await?;
+
+        let upsert_sql = r#"
+            INSERT INTO org_locale_settings (org, locale, timezone, date_format, datetime_format, content_types, updated_at, updated_by)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW(), $7)
+            ON CONFLICT (org) DO UPDATE SET
+                locale = EXCLUDED.locale,
+                timezone = EXCLUDED.timezone,
+                date_format = EXCLUDED.date_format,
+                datetime_format = EXCLUDED.datetime_format,
+                content_types = EXCLUDED.content_types,
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by
+            RETURNING locale, timezone, date_format, datetime_format, content_types, updated_at, updated_by;
+        "#;
+        let row = sqlx::query_as::<_, OrgLocaleSettingsRow>(upsert_sql)
+            .bind(org)
+            .bind(locale)
+            .bind(timezone)
+            .bind(date_format)
+            .bind(datetime_format)
+            .bind(content_types)
+            .bind(by_prpl)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
 }
@@ -0,0 +1,78 @@
+use serde_json::{json, Map, Value};
+use utoipa::ToSchema;
+use crate::services::webhook_service::{WebhookEvent, WebhookPayload};
+use crate::models::{DocumentQuarantinedEvent, DocumentStateChangedEvent, UserDigestEvent};
+
+/// AsyncAPI document of the webhook events, generated from the event types
+/// 
+/// Every event is POSTed to the webhooks of the workflow of the organization, with the event name in the
+/// `X-Colabri-Event` header and a `WebhookEvent` envelope whose `data` holds the payload of the event.
+pub fn events_schema() -> Value {
+    let mut channels = Map::new();
+    let mut messages = Map::new();
+    let mut schemas = Map::new();
+
+    let (envelope_name, envelope_schema) = WebhookEvent::schema();
+    schemas.insert(envelope_name.to_string(), to_value(envelope_schema));
+
+    for (event, description, payload_name, payload_schema) in [
+        event_type::<DocumentStateChangedEvent>(),
+        event_type::<DocumentQuarantinedEvent>(),
+        event_type::<UserDigestEvent>(),
+    ] {
+        schemas.insert(payload_name.to_string(), payload_schema);
+        messages.insert(event.to_string(), json!({
+            "name": event,
+            "summary": description,
+            "contentType": "application/json",
+            "headers": {
+                "type": "object",
+                "properties": {
+                    "X-Colabri-Event": { "type": "string", "const": event }
+                }
+            },
+            "payload": {
+                "allOf": [
+                    { "$ref": format!("#/components/schemas/{}", envelope_name) },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "event": { "type": "string", "const": event },
+                            "data": { "$ref": format!("#/components/schemas/{}", payload_name) }
+                        }
+                    }
+                ]
+            }
+        }));
+        channels.insert(event.to_string(), json!({
+            "description": description,
+            "subscribe": {
+                "message": { "$ref": format!("#/components/messages/{}", event) }
+            }
+        }));
+    }
+
+    json!({
+        "asyncapi": "2.6.0",
+        "info": {
+            "title": "Colabri Doc webhook events",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Events POSTed to the webhooks configured in the workflow of an organization."
+        },
+        "defaultContentType": "application/json",
+        "channels": channels,
+        "components": {
+            "messages": messages,
+            "schemas": schemas
+        }
+    })
+}
+
+fn event_type<P: WebhookPayload>() -> (&'static str, &'static str, String, Value) {
+    let (name, schema) = P::schema();
+    (P::EVENT, P::DESCRIPTION, name.to_string(), to_value(schema))
+}
+
+fn to_value(schema: utoipa::openapi::RefOr<utoipa::openapi::schema::Schema>) -> Value {
+    serde_json::to_value(schema).unwrap_or(Value::Null)
+}
@@ -1,13 +1,16 @@
+pub mod events;
+
 use crate::models::*;
 use utoipa::OpenApi;
 
 /// Health check endpoint
 #[utoipa::path(
     get,
-    path = "/api/health",
+    path = "/health",
     tag = "health",
     responses(
-        (status = 200, description = "Service is healthy", body = HealthResponse)
+        (status = 200, description = "Service is healthy", body = HealthResponse),
+        (status = 503, description = "Watchdog probes are failing", body = HealthResponse)
     )
 )]
 #[allow(dead_code)]
@@ -16,16 +19,33 @@ pub async fn health_check_doc() {}
 /// Health check endpoint
 #[utoipa::path(
     get,
-    path = "/api/ready",
+    path = "/ready",
     tag = "health",
     responses(
-        (status = 200, description = "Service is ready", body = ReadyResponse)
+        (status = 200, description = "Service is ready", body = ReadyResponse),
+        (status = 503, description = "Pod is draining", body = ReadyResponse)
     )
 )]
 #[allow(dead_code)]
 pub async fn ready_check_doc() {}
 
+/// Metrics of the instance
+/// 
+/// Connections, rooms, unsaved, quarantined and failing documents, health and draining of this instance as gauges in the Prometheus text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", content_type = "text/plain", body = String)
+    )
+)]
+#[allow(dead_code)]
+pub async fn metrics_doc() {}
+
 /// Get diagnostics for the server
+/// 
+/// Includes the documents whose saves are failing and waiting for a retry, and the number of them with unsaved changes older than the alert threshold.
 #[utoipa::path(
     get,
     path = "/api/v1/diagnostics",
@@ -37,20 +57,113 @@ pub async fn ready_check_doc() {}
 #[allow(dead_code)]
 pub async fn diagnostics_doc() {}
 
+/// Get diagnostics per organization
+/// 
+/// Connections, rooms, unsaved documents and failing saves per organization on this instance. By default organizations with fewer connections than the configured threshold are merged into one entry without identifier, so the view can be shared without exposing the activity of small tenants. The detailed view (`aggregate=false`) is limited to cloud admins.
+#[utoipa::path(
+    get,
+    path = "/api/v1/diagnostics/orgs",
+    tag = "diagnostics",
+    responses(
+        (status = 200, description = "Diagnostics per organization", body = OrgDiagnosticsResponse),
+        (status = 403, description = "Not allowed to see the requested view", body = ErrorResponse)
+    ),
+    params(
+        ("aggregate" = Option<bool>, Query, description = "Bucket the small organizations together, true by default")
+    )
+)]
+#[allow(dead_code)]
+pub async fn diagnostics_orgs_doc() {}
+
+/// Get the usage of an organization
+/// 
+/// Open rooms, connections and accepted updates of the organization, sampled every minute on every instance and aggregated per bucket: 5 minutes for ranges up to a day, an hour up to a week and a day beyond. Connection minutes are the sampled connections times the sample interval.
+#[utoipa::path(
+    get,
+    path = "/api/orgs/{org_id}/analytics",
+    tag = "diagnostics",
+    responses(
+        (status = 200, description = "Usage of the organization", body = OrgAnalyticsResponse),
+        (status = 400, description = "Invalid range", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("range" = Option<String>, Query, description = "Range up to now, e.g. 60m, 24h or 7d (the default), at most 90 days")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_analytics_doc() {}
+
+/// Create a document
+/// 
+/// Creates an empty document of a type, or one starting from the latest content of a template of the same type, and returns its id before the first websocket connection. Approvals and the workflow state of the template aren't copied. Without ACLs the owner can view, edit and manage the document.
+#[utoipa::path(
+    post,
+    path = "/api/orgs/{org_id}/docs",
+    tag = "documents",
+    request_body = DocumentCreateRequest,
+    responses(
+        (status = 201, description = "Document created", body = DocumentCreateResponse),
+        (status = 400, description = "Invalid request, unregistered type, unknown template or content beyond the limits of the organization", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_create_doc() {}
+
+/// Get the billing report of a month
+/// 
+/// Usage of every organization in the month: documents that received updates, editor minutes from the usage samples, the average and peak stored bytes from the daily storage samples, and the number of exports (evidence bundles and CSV exports). A report of the running month is partial.
+#[utoipa::path(
+    get,
+    path = "/api/admin/billing/{period}",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Billing report, as CSV when requested", body = BillingReportResponse),
+        (status = 400, description = "Invalid period or format", body = ErrorResponse),
+        (status = 403, description = "Not a cloud admin", body = ErrorResponse)
+    ),
+    params(
+        ("period" = String, Path, description = "Month of the report, e.g. 2026-09"),
+        ("format" = Option<String>, Query, description = "'json' (the default) or 'csv'")
+    )
+)]
+#[allow(dead_code)]
+pub async fn billing_report_doc() {}
+
 /// Export a document
 /// 
-/// This endpoint will always return the latest state of a document.
+/// This endpoint will always return the latest state of a document. The Accept header picks the representation: the JSON envelope (`application/json`, the default), the raw snapshot (`application/octet-stream`) or a rendering for reading (`text/markdown`, `text/html`, `application/pdf`). The `format` param only applies to the JSON envelope.
+/// 
+/// Polling integrations pass the state they know as `since_version` or `since_vv`. The answer is 204 when the document didn't change since, otherwise only the ids of the changed and removed blocks (language codes for statements) with the latest version vector. Rows of lazy blocks and linked statements are stored apart and aren't compared.
+/// 
+/// The raw snapshot supports resumable downloads: the answer carries `Accept-Ranges`, `Content-Length`, an `ETag` and the SHA-256 of the whole snapshot in `Repr-Digest`. A single `Range` of bytes is answered with 206, guard it with `If-Range` and the ETag to get the full snapshot again when the document changed in between.
 #[utoipa::path(
     get,
     path = "/api/v1/{org_id}/documents/{doc_id}",
     tag = "documents",
     responses(
-        (status = 200, description = "Latest document state retrieved successfully", body = DocumentLatestResponse)
+        (status = 200, description = "Latest document state retrieved successfully, a DocumentChangesResponse with `since_version` or `since_vv`", content(
+            (DocumentLatestResponse = "application/json"),
+            (Vec<u8> = "application/octet-stream"),
+            (String = "text/markdown"),
+            (String = "text/html"),
+            (Vec<u8> = "application/pdf")
+        )),
+        (status = 204, description = "Nothing changed since `since_version` or `since_vv`"),
+        (status = 206, description = "The requested byte range of the raw snapshot", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 400, description = "Both since params given, or the earlier state doesn't exist", body = ErrorResponse),
+        (status = 406, description = "None of the accepted representations can be produced, or `format` was combined with another representation than JSON", body = ErrorResponse),
+        (status = 416, description = "The byte range is outside of the raw snapshot")
     ),
     params(
         ("org_id" = String, Path, description = "Organization ID"),
         ("doc_id" = String, Path, description = "Document ID"),
-        ("format" = Option<String>, Query, description = "Output format: json, binary, or both (default: json)")
+        ("format" = Option<String>, Query, description = "Output format of the JSON envelope: json, binary, or both (default: json)"),
+        ("since_version" = Option<u32>, Query, description = "Only answer the blocks changed since this saved version"),
+        ("since_vv" = Option<String>, Query, description = "Only answer the blocks changed since this version vector, a JSON object of counters keyed by peer id or principal")
     )
 )]
 #[allow(dead_code)]
@@ -58,14 +171,24 @@ pub async fn doc_latest_doc() {}
 
 /// Export a document
 /// 
-/// This endpoint will return the state of a document at a specific point in time determined by the version parameters. Since the version vector can be large, this is a POST endpoint that accepts the version parameters in the request body. It does however never modify the state of the document.
+/// This endpoint will return the state of a document at a specific point in time determined by the version parameters. Since the version vector can be large, this is a POST endpoint that accepts the version parameters in the request body. It does however never modify the state of the document. The counters of the version vector are keyed by peer id or by principal, a principal is resolved through the peer map of the version when it has a single peer. The response returns the version vector keyed by peer id in `versionV` and with the principal of each peer in `versionVNamed`. The Accept header picks the representation as for the latest state of a document, `format` only applies to the JSON envelope. The raw snapshot supports `Range` requests for resumable downloads, as for the latest state.
 #[utoipa::path(
     post,
     path = "/api/v1/{org_id}/documents/{doc_id}/version",
     tag = "documents",
     request_body(content = DocumentVersionRequest, description = "Version request parameters"),
     responses(
-        (status = 200, description = "Document version state retrieved successfully", body = DocumentVersionResponse)
+        (status = 200, description = "Document version state retrieved successfully", content(
+            (DocumentVersionResponse = "application/json"),
+            (Vec<u8> = "application/octet-stream"),
+            (String = "text/markdown"),
+            (String = "text/html"),
+            (Vec<u8> = "application/pdf")
+        )),
+        (status = 206, description = "The requested byte range of the raw snapshot", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 400, description = "Invalid request, e.g. a version vector with principals, peers or counters the document doesn't have", body = ErrorResponse),
+        (status = 406, description = "None of the accepted representations can be produced, or `format` was combined with another representation than JSON", body = ErrorResponse),
+        (status = 416, description = "The byte range is outside of the raw snapshot")
     ),
     params(
         ("org_id" = String, Path, description = "Organization ID"),
@@ -78,13 +201,14 @@ pub async fn doc_version_doc() {}
 
 /// Delete a document
 /// 
-/// This endpoint will delete a document. It is used when a user wants to remove a document from their personal space or a shared library.
+/// This endpoint will delete a document. It is used when a user wants to remove a document from their personal space or a shared library. A document referenced by other documents (see the backlinks) is only deleted when `force` is set.
 #[utoipa::path(
     delete,
     path = "/api/v1/{org_id}/documents/{doc_id}",
     tag = "documents",
     responses(
-        (status = 200, description = "Document deleted successfully", body = DocumentDeleteResponse)
+        (status = 200, description = "Document deleted successfully", body = DocumentDeleteResponse),
+        (status = 409, description = "The document is referenced by other documents, listed in `referencedBy`", body = DocumentDeleteResponse)
     ),
     params(
         ("org_id" = String, Path, description = "Organization ID"),
@@ -113,33 +237,2089 @@ pub async fn doc_delete_doc() {}
 #[allow(dead_code)]
 pub async fn doc_move_lib_doc() {}
 
+/// Explain the permissions of a principal on a document
+/// 
+/// This endpoint evaluates, for every scope of the document (document, block and language level), which permissions the principal holds and which database ACL rows or Loro ACL entries grant them. A permission without grants is denied.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/permissions",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Permissions explained successfully", body = DocumentPermissionsResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("prpl" = String, Query, description = "Principal to explain the permissions for")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_permissions_doc() {}
+
+/// Access report of a document
+/// 
+/// This endpoint lists every principal with any access to the document and their effective permissions on every scope, flattened from the database and Loro ACLs. Use `format=csv` to download the report for periodic access reviews.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/access-report",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Access report generated successfully", body = DocumentAccessReportResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("format" = Option<String>, Query, description = "Output format: json or csv (default: json)")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_access_report_doc() {}
+
+/// List the comment threads of a document
+/// 
+/// This endpoint returns the comments of every block and language grouped into threads. Replies are ordered by timestamp. Use `path` to only return the threads of a single block or language. Anchored comments are returned with the offsets of their range in the current text.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/comments",
+    tag = "comments",
+    responses(
+        (status = 200, description = "Comment threads retrieved successfully", body = CommentThreadList)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("path" = Option<String>, Query, description = "Path of the block or language to list the threads for"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_comments_doc() {}
+
+/// Add a comment
+/// 
+/// This endpoint adds a comment to a block or language. Set `parentId` to reply to an existing comment on the same path, set `anchor` to attach the comment to a character range of the text. The author needs at least some permission on the path.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/comments",
+    tag = "comments",
+    request_body(content = DocumentCommentAddRequest, description = "Comment to add"),
+    responses(
+        (status = 200, description = "Comment added successfully", body = DocumentCommentResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_comment_add_doc() {}
+
+/// Edit a comment
+/// 
+/// This endpoint replaces the text of a comment. Only the author of the comment can edit it.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/{org_id}/documents/{doc_id}/comments/{comment_id}",
+    tag = "comments",
+    request_body(content = DocumentCommentEditRequest, description = "New text of the comment"),
+    responses(
+        (status = 200, description = "Comment edited successfully", body = DocumentCommentResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("comment_id" = String, Path, description = "Comment ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_comment_edit_doc() {}
+
+/// Resolve a comment thread
+/// 
+/// This endpoint resolves the thread the comment belongs to, including the root and all replies. Set `resolved` to false to reopen the thread.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/comments/{comment_id}/resolve",
+    tag = "comments",
+    request_body(content = DocumentCommentResolveRequest, description = "Resolve request parameters"),
+    responses(
+        (status = 200, description = "Thread updated successfully", body = DocumentCommentResolveResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("comment_id" = String, Path, description = "Comment ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_comment_resolve_doc() {}
+
+/// List the reactions of a document
+/// 
+/// This endpoint returns the emoji reactions on the blocks, languages and comments of a document, counted per emoji with the principals that reacted. Use `path` to only return the reactions of a single block or language. The comment threads carry the reactions on their comments as well.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/reactions",
+    tag = "comments",
+    responses(
+        (status = 200, description = "Reactions retrieved successfully", body = ReactionSummaryList)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("path" = Option<String>, Query, description = "Path of the block or language to list the reactions for"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_reactions_doc() {}
+
+/// Add a reaction
+/// 
+/// This endpoint adds the emoji reaction of a principal to a block or language, or to one of its comments when `commentId` is set. A principal reacts once per emoji, adding it again changes nothing. The principal needs at least some permission on the path.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/reactions",
+    tag = "comments",
+    request_body(content = DocumentReactionRequest, description = "Reaction to add"),
+    responses(
+        (status = 200, description = "Reaction added", body = DocumentReactionResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_reaction_add_doc() {}
+
+/// Remove a reaction
+/// 
+/// This endpoint withdraws the emoji reaction of a principal from a block, language or comment.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/{org_id}/documents/{doc_id}/reactions",
+    tag = "comments",
+    request_body(content = DocumentReactionRequest, description = "Reaction to remove"),
+    responses(
+        (status = 200, description = "Reaction removed", body = DocumentReactionResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_reaction_remove_doc() {}
+
+/// List the suggestions of a document
+/// 
+/// This endpoint lists the suggested edits of a document. Pending suggestions include a preview of the block or language as it would look like when accepted.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/suggestions",
+    tag = "suggestions",
+    responses(
+        (status = 200, description = "Suggestions retrieved successfully", body = SuggestionList)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("path" = Option<String>, Query, description = "Only list the suggestions for this block or language"),
+        ("state" = Option<String>, Query, description = "Only list suggestions in this state: pending, accepted or rejected"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_suggestions_doc() {}
+
+/// Suggest an edit
+/// 
+/// This endpoint records a suggested edit to a block or language without changing its content. The edit is a base64 encoded Loro update made on top of the current document state, and may only modify the targeted block or language. The author needs the suggest or edit permission on the path.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/suggestions",
+    tag = "suggestions",
+    request_body(content = DocumentSuggestionAddRequest, description = "Suggested edit"),
+    responses(
+        (status = 200, description = "Suggestion recorded successfully", body = DocumentSuggestionResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_suggestion_add_doc() {}
+
+/// Accept a suggestion
+/// 
+/// This endpoint imports the changes of a pending suggestion into the document. The changes stay attributed to the author of the suggestion. Requires the edit permission on the path of the suggestion.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/suggestions/{suggestion_id}/accept",
+    tag = "suggestions",
+    request_body(content = DocumentSuggestionDecisionRequest, description = "Decision request parameters"),
+    responses(
+        (status = 200, description = "Suggestion accepted successfully", body = DocumentSuggestionResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("suggestion_id" = String, Path, description = "Suggestion ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_suggestion_accept_doc() {}
+
+/// Reject a suggestion
+/// 
+/// This endpoint rejects a pending suggestion, its changes are never applied. Requires the edit permission on the path of the suggestion.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/suggestions/{suggestion_id}/reject",
+    tag = "suggestions",
+    request_body(content = DocumentSuggestionDecisionRequest, description = "Decision request parameters"),
+    responses(
+        (status = 200, description = "Suggestion rejected successfully", body = DocumentSuggestionResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("suggestion_id" = String, Path, description = "Suggestion ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_suggestion_reject_doc() {}
+
+/// List the approval rounds of a document
+/// 
+/// This endpoint lists the approval rounds of a document together with the response of every approver on every frozen block or language. A round completes as soon as one approver rejects or all approvers approved.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/approval-rounds",
+    tag = "approvals",
+    responses(
+        (status = 200, description = "Approval rounds retrieved successfully", body = ApprovalRoundList)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_approval_rounds_doc() {}
+
+/// Start an approval round
+/// 
+/// This endpoint records the current version of the document and freezes the given blocks or languages (all of them by default) for everyone but the approvers. Approvers respond through the approvals of the frozen blocks or languages. The blocks unfreeze when the round completes or is cancelled. Requires the manage permission on the document.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/approval-rounds",
+    tag = "approvals",
+    request_body(content = DocumentApprovalRoundStartRequest, description = "Approval round parameters"),
+    responses(
+        (status = 200, description = "Approval round started successfully", body = DocumentApprovalRoundResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_approval_round_start_doc() {}
+
+/// Cancel an approval round
+/// 
+/// This endpoint cancels an active approval round and unfreezes its blocks or languages. Can be done by the principal that started the round or by a manager of the document.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/approval-rounds/{round_id}/cancel",
+    tag = "approvals",
+    request_body(content = DocumentApprovalRoundCancelRequest, description = "Cancel request parameters"),
+    responses(
+        (status = 200, description = "Approval round cancelled successfully", body = DocumentApprovalRoundResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("round_id" = String, Path, description = "Approval round ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_approval_round_cancel_doc() {}
+
+/// Get the workflow state of a document
+/// 
+/// This endpoint returns the workflow state of a document and the transitions that can be made from it, according to the workflow of the organization. When a principal is given, every transition tells whether that principal holds the required permission.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/state",
+    tag = "workflow",
+    responses(
+        (status = 200, description = "Workflow state retrieved successfully", body = DocumentStateResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("prpl" = Option<String>, Query, description = "Principal to check the transitions for")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_state_doc() {}
+
+/// Move a document to another workflow state
+/// 
+/// This endpoint validates the transition against the workflow of the organization, records it, mirrors the new state in the document properties and applies the ACL template of the state. Archiving a document revokes all edit, manage and suggest ACLs (view is kept), closes the room and only allows reopening it for reading. Publishing a document signs its exported snapshot and JSON. Webhooks of the workflow are called afterwards. Requires the permission of the transition on the document.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/state",
+    tag = "workflow",
+    request_body(content = DocumentStateTransitionRequest, description = "Transition parameters"),
+    responses(
+        (status = 200, description = "Document moved successfully", body = DocumentStateTransitionResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_state_transition_doc() {}
+
+/// Cite a statement language
+/// 
+/// This endpoint renders one language of a statement at a specific version (the latest by default) as clean HTML and plain text, together with the citation metadata: document id, version, approval status, approvers and the date of the latest approval decision. Meant for embedding statements in regulatory submissions.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/citation",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Citation rendered successfully", body = DocumentCitationResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("version" = Option<u32>, Query, description = "Version of the document, the latest version by default"),
+        ("lang" = String, Query, description = "Language code of the statement language")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_citation_doc() {}
+
+/// Get the evidence bundle of a document version
+/// 
+/// This endpoint produces a zip archive for audits with the content of a document version (the latest by default), the rendered statement languages, the approval chain with timestamps and user identities, the ACLs at that version and a manifest. The manifest holds the SHA-256 hash of every file and of the whole bundle, signed with HMAC-SHA256 when an evidence signing key is configured.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/evidence",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Evidence bundle produced successfully", content_type = "application/zip", body = Vec<u8>),
+        (status = 406, description = "The Accept header doesn't allow application/zip", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("version" = Option<u32>, Query, description = "Version of the document, the latest version by default"),
+        ("prpl" = Option<String>, Query, description = "Principal requesting the bundle, recorded in the manifest")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_evidence_doc() {}
+
+/// Get the signature of the published version of a document
+/// 
+/// When a document is published it is exported as snapshot and JSON, both are hashed and the hashes are signed with the Ed25519 publish key. This endpoint returns the detached signature, the signed message and the public key, so downstream consumers can verify the integrity and origin of the content.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/published/signature",
+    tag = "workflow",
+    responses(
+        (status = 200, description = "Signature retrieved successfully", body = DocumentSignatureResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_published_signature_doc() {}
+
+/// Verify content against the published signature
+/// 
+/// This endpoint checks the stored signature of the published version and compares the hashes of the given snapshot (base64) and/or JSON with the signed hashes.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/published/verify",
+    tag = "workflow",
+    request_body(content = DocumentSignatureVerifyRequest, description = "Content to verify"),
+    responses(
+        (status = 200, description = "Content verified", body = DocumentSignatureVerifyResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_published_verify_doc() {}
+
+/// Get the pod owning the room of a document
+/// 
+/// Rooms are spread over the pods by consistent hashing over the document id. Clients ask this endpoint for the WebSocket URL of the owning pod before joining a room; with `redirect=true` a room owned by another pod answers with a 307 to its WebSocket URL. Joining a room on the wrong pod is refused with a `redirect:<url>` reason.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/room",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Room assignment retrieved successfully", body = DocumentRoomResponse),
+        (status = 307, description = "The room is owned by another pod")
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("redirect" = Option<bool>, Query, description = "Redirect to the owning pod instead of describing it")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_room_doc() {}
+
+/// Get the quarantine status of a document
+/// 
+/// Documents that fail to load too many times in a row are quarantined. Loads of a quarantined document are refused, the REST endpoints answer with a 422.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/quarantine",
+    tag = "diagnostics",
+    responses(
+        (status = 200, description = "Quarantine status retrieved successfully", body = DocumentQuarantineResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_quarantine_doc() {}
+
+/// Lift the quarantine of a document and try to load it again
+/// 
+/// When the load still fails the failure is counted again, the returned status tells whether the document is back in quarantine.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/quarantine/retry",
+    tag = "diagnostics",
+    responses(
+        (status = 200, description = "Load retried", body = DocumentQuarantineResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_quarantine_retry_doc() {}
+
+/// Repair a document and lift its quarantine
+/// 
+/// The latest stream of the document is rebuilt from its JSON representation. The edit history is lost, the content as of the last save is kept.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/quarantine/repair",
+    tag = "diagnostics",
+    responses(
+        (status = 200, description = "Document repaired", body = DocumentQuarantineResponse),
+        (status = 422, description = "The document can't be repaired", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_quarantine_repair_doc() {}
+
+/// Get the storage used by the versions of a document
+/// 
+/// Lists the size of every stored version with the cumulative bytes. Versions beyond the history budget of the document are marked prunable, they are trimmed by the next compaction run. Every version carries the `contentHash` of its content without metadata, consumers skip versions whose hash equals the one of the previous version.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/storage",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Storage retrieved successfully", body = DocumentStorageResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_storage_doc() {}
+
+/// List the versions of a document
+/// 
+/// Lists the saved versions of a document oldest first, with the same storage details as the storage report. Use this endpoint to page through long histories.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/versions",
+    tag = "documents",
+    responses(
+        (status = 200, description = "A page of the versions of the document", body = VersionStorageList),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_versions_doc() {}
+
+/// Set the history budget of a document
+/// 
+/// The compaction job keeps only the given number of most recent versions of the document. A null budget keeps the full history.
+#[utoipa::path(
+    put,
+    path = "/api/v1/{org_id}/documents/{doc_id}/storage/budget",
+    tag = "documents",
+    request_body = DocumentHistoryBudgetRequest,
+    responses(
+        (status = 200, description = "History budget set, the storage preview under the new budget is returned", body = DocumentStorageResponse),
+        (status = 400, description = "Invalid budget", body = ErrorResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_storage_budget_doc() {}
+
+/// Get the archival candidates of an organization
+/// 
+/// Documents without opens or saves for longer than the archival policy of the organization. The archival job flags them on its next run and moves them to the archived workflow state once the grace period has passed; any activity in between withdraws the candidate.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/archival/candidates",
+    tag = "workflow",
+    responses(
+        (status = 200, description = "A page of the archival candidates", body = ArchivalCandidateList),
+        (status = 404, description = "The organization has no archival policy", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn archival_candidates_doc() {}
+
+/// Replay the edits of a document
+/// 
+/// Walks the history of a document version between two version vectors in causal order and returns a frame every `step` changes, either with the full document state or with the updates of the frame. At most 500 frames are returned; longer ranges need a larger step.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/playback",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Playback retrieved successfully", body = DocumentPlaybackResponse),
+        (status = 400, description = "Invalid range or too many frames", body = ErrorResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("version" = Option<u32>, Query, description = "Version of the document, the latest one by default"),
+        ("from_vv" = Option<String>, Query, description = "JSON encoded version vector to start from, the beginning of the history by default"),
+        ("to_vv" = Option<String>, Query, description = "JSON encoded version vector to end at, the latest state by default"),
+        ("step" = Option<usize>, Query, description = "Number of changes per frame, 1 by default"),
+        ("format" = Option<String>, Query, description = "'state' (default) or 'diff'")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_playback_doc() {}
+
+/// Blame a block of a document
+/// 
+/// Returns the text of a sheet block, or of a statement language when the block id is a language code, as runs of characters that were inserted by the same change. Each run carries the peer and principal of the change and its time when recorded.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/blocks/{block_id}/blame",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Blame retrieved successfully", body = DocumentBlameResponse),
+        (status = 404, description = "Document or block not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("block_id" = String, Path, description = "Block ID, or language code of a statement"),
+        ("version" = Option<u32>, Query, description = "Version of the document, the latest one by default")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_blame_doc() {}
+
+/// Revert the changes of an author
+/// 
+/// Reverts all changes made by a principal, or a single peer, after a version vector. Changes made by others since are kept. With dryRun the changes and the resulting document are returned without touching the document. Requires a cloud admin.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/revert-author",
+    tag = "documents",
+    request_body = DocumentRevertRequest,
+    responses(
+        (status = 200, description = "Changes reverted, or previewed with dryRun", body = DocumentRevertResponse),
+        (status = 400, description = "Invalid principal, peer or version vector", body = ErrorResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_revert_author_doc() {}
+
+/// Get the divergence status of a document
+/// 
+/// Loads every stored version of a document and reports the heads, the versions no other version contains. More than one head means the versions diverged, e.g. after a split-brain or a restore that created a fork. Earlier merges are listed too. Requires a cloud admin.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/reconcile",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Versions retrieved successfully", body = DocumentReconcileResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_reconcile_doc() {}
+
+/// Merge diverged versions of a document
+/// 
+/// Imports two versions, the two heads by default, into one document and saves the merged state as a new version. The merge is recorded with both source versions. Requires a cloud admin.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/reconcile",
+    tag = "documents",
+    request_body = DocumentMergeRequest,
+    responses(
+        (status = 200, description = "Versions merged successfully", body = DocumentMergeResponse),
+        (status = 409, description = "The versions can't be merged", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_reconcile_merge_doc() {}
+
+/// Drain the pod
+/// 
+/// Prepares the pod for a deploy: no new rooms are accepted, the readiness check fails, all dirty documents are saved and the rooms without connected clients are closed. The drain runs in the background, poll the status for its progress. Requires a cloud admin.
+#[utoipa::path(
+    post,
+    path = "/api/admin/drain",
+    tag = "admin",
+    responses(
+        (status = 202, description = "Drain started", body = DrainStatusResponse),
+        (status = 200, description = "Drain already running or done", body = DrainStatusResponse)
+    )
+)]
+#[allow(dead_code)]
+pub async fn drain_start_doc() {}
+
+/// Get the drain progress
+/// 
+/// Reports the progress of draining the pod. Deploy pipelines can roll the pod once the state is "drained".
+#[utoipa::path(
+    get,
+    path = "/api/admin/drain/status",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Drain progress", body = DrainStatusResponse)
+    )
+)]
+#[allow(dead_code)]
+pub async fn drain_status_doc() {}
+
+/// Start recording the sync traffic of a document
+/// 
+/// Records the joins, updates, answers, loads, saves and closed connections of the rooms of the document on this pod as JSON lines, with the size and SHA-256 of every payload and the payload itself when `includePayloads` is set. The recording ends after `durationSecs`, capped by RECORDING_MAX_SECS, and replaces the previous recording of the document. Requires a cloud admin.
+#[utoipa::path(
+    post,
+    path = "/api/admin/{org_id}/documents/{doc_id}/recording",
+    tag = "admin",
+    request_body = RecordingStartRequest,
+    responses(
+        (status = 201, description = "Recording started", body = RecordingStatus),
+        (status = 409, description = "Document already being recorded", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_recording_start_doc() {}
+
+/// Get the recording of a document
+/// 
+/// Reports the state of the latest recording of the document and the frames written so far. Requires a cloud admin.
+#[utoipa::path(
+    get,
+    path = "/api/admin/{org_id}/documents/{doc_id}/recording",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Latest recording", body = RecordingStatus),
+        (status = 404, description = "Document was not recorded", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_recording_status_doc() {}
+
+/// Stop recording a document
+/// 
+/// Stops the running recording of the document before its duration ends, the recording stays available for download. Requires a cloud admin.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/{org_id}/documents/{doc_id}/recording",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Recording stopped", body = RecordingStatus),
+        (status = 404, description = "Document was not recorded", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_recording_stop_doc() {}
+
+/// Download the recording of a document
+/// 
+/// Answers the frames of the latest recording of the document as newline delimited JSON. Requires a cloud admin.
+#[utoipa::path(
+    get,
+    path = "/api/admin/{org_id}/documents/{doc_id}/recording/download",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Recorded frames", content_type = "application/x-ndjson", body = String),
+        (status = 404, description = "Document was not recorded", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_recording_download_doc() {}
+
+/// Migrate the documents of an organization
+/// 
+/// Upgrades every document of the organization whose `properties.schemaVersion` is older than the current schema version, one document at a time. Documents are also migrated when their room loads, the job migrates the documents nobody opens. The migration runs in the background, poll its progress. Requires a cloud admin.
+#[utoipa::path(
+    post,
+    path = "/api/admin/{org_id}/schema-migration",
+    tag = "admin",
+    responses(
+        (status = 202, description = "Migration started", body = SchemaMigrationStatus),
+        (status = 200, description = "Migration already running", body = SchemaMigrationStatus)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_schema_migration_start_doc() {}
+
+/// Get the schema migration progress
+/// 
+/// Reports the progress of the latest schema migration of the organization started on this pod. Requires a cloud admin.
+#[utoipa::path(
+    get,
+    path = "/api/admin/{org_id}/schema-migration",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Migration progress", body = SchemaMigrationStatus),
+        (status = 404, description = "No migration ran on this pod", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_schema_migration_status_doc() {}
+
+/// Schedule an announcement
+/// 
+/// Broadcasts a transient announcement (maintenance window, policy notice) to every client connected to the organization, as an `announcement:<id>` entry of the ephemeral rooms. The announcement is pushed from `startsAt` (default now) until `expiresAt`, clients drop it when it is no longer pushed. The content of documents is never touched. Requires a cloud admin.
+#[utoipa::path(
+    post,
+    path = "/api/admin/{org_id}/announcements",
+    tag = "admin",
+    request_body = OrgAnnouncementRequest,
+    responses(
+        (status = 201, description = "Announcement scheduled", body = OrgAnnouncement),
+        (status = 400, description = "Empty message, unknown level or expiry in the past", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_announcement_create_doc() {}
+
+/// List the announcements
+/// 
+/// Lists the running and scheduled announcements of the organization, the first to start first. Requires a cloud admin.
+#[utoipa::path(
+    get,
+    path = "/api/admin/{org_id}/announcements",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The announcements that didn't expire", body = OrgAnnouncementList)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_announcements_doc() {}
+
+/// Withdraw an announcement
+/// 
+/// Deletes an announcement before it expires, connected clients drop it. Requires a cloud admin.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/{org_id}/announcements/{announcement_id}",
+    tag = "admin",
+    responses(
+        (status = 204, description = "Announcement withdrawn"),
+        (status = 404, description = "Announcement not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("announcement_id" = String, Path, description = "Announcement ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_announcement_delete_doc() {}
+
+/// Get the locale settings of an organization
+#[utoipa::path(
+    get,
+    path = "/api/admin/{org_id}/locale",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Locale settings, with the defaults of the service filled in under `resolved`", body = OrgLocaleSettingsResponse),
+        (status = 403, description = "Not a cloud admin or the app service", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_locale_settings_doc() {}
+
+/// Set the locale settings of an organization
+/// 
+/// Replaces the locale, timezone and date formats used for the dates in HTML, PDF and CSV exports and in digests. Settings per content type override those of the organization for its documents, unset fields fall back to the organization and then to the defaults of the service. Without a date format the locale picks one. Only content types registered with the document types of the organization can be set, any when it registers none.
+#[utoipa::path(
+    put,
+    path = "/api/admin/{org_id}/locale",
+    tag = "admin",
+    request_body = OrgLocaleSettingsRequest,
+    responses(
+        (status = 200, description = "Locale settings updated", body = OrgLocaleSettingsResponse),
+        (status = 400, description = "Invalid locale, timezone or date format, or unregistered content type", body = ErrorResponse),
+        (status = 403, description = "Not a cloud admin or the app service", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_locale_settings_set_doc() {}
+
+/// Push the principals of a user
+/// 
+/// The app service pushes the principals of a user when they change, e.g. after an org membership change. The cached user context is updated and the permissions of the live connections of the user are re-evaluated: connections that may no longer write are downgraded to read, rooms a connection may no longer view are closed so all clients reconnect and are authorized again. Requires the colabri-app service.
+#[utoipa::path(
+    post,
+    path = "/api/internal/users/{uid}/principals",
+    tag = "admin",
+    request_body = UserPrincipalsRequest,
+    responses(
+        (status = 200, description = "Principals applied", body = UserPrincipalsResponse),
+        (status = 403, description = "Caller is not the app service", body = ErrorResponse)
+    ),
+    params(
+        ("uid" = String, Path, description = "User ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn user_principals_push_doc() {}
+
+/// Get the save status of a document
+/// 
+/// Reports whether the changes in the room of a document are persisted: the version vector and time of the last successful save, the current version vector of the room and the last save error. Only the pod owning the room knows its save status. Editors can use it to show "All changes saved" accurately.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/save-status",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Save status retrieved successfully", body = DocumentSaveStatusResponse),
+        (status = 403, description = "No access to the organization", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_save_status_doc() {}
+
+/// Get the preferences of the calling user on a document
+/// 
+/// Returns the pinned blocks, collapsed sections and read marker of the calling user on a document. `unread` lists the blocks (or languages of a statement) that changed since the user last marked the document read, compared against the version vector stored with the marker. The caller must be a user of the organization with access to the document.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/me/prefs",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Preferences retrieved successfully", body = DocumentUserPrefsResponse),
+        (status = 403, description = "No access to the document", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_user_prefs_doc() {}
+
+/// Update the preferences of the calling user on a document
+/// 
+/// Replaces the pinned blocks or collapsed sections that are set in the request, the others keep their value. Set `markRead` to move the read marker to the latest state of the document.
+#[utoipa::path(
+    put,
+    path = "/api/v1/{org_id}/documents/{doc_id}/me/prefs",
+    tag = "documents",
+    request_body(content = DocumentUserPrefsRequest, description = "Preferences to update"),
+    responses(
+        (status = 200, description = "Preferences updated successfully", body = DocumentUserPrefsResponse),
+        (status = 403, description = "No access to the document", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_user_prefs_put_doc() {}
+
+/// Get the changes since the last visit
+/// 
+/// Returns a catch-up digest of the changes to a document: which blocks (or languages of a statement) were added, changed or removed, who changed them and what happened to their text. The earlier state is the read marker of the calling user, stored with `markRead` on the preferences, or the version vector passed as `since_vv`. Services must pass `since_vv`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/unread",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Digest of the changes", body = DocumentUnreadResponse),
+        (status = 400, description = "The version vector isn't part of the history of the document", body = ErrorResponse),
+        (status = 404, description = "The user never marked the document read", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("since_vv" = Option<String>, Query, description = "Version vector of the earlier state as a JSON object of counters keyed by peer id or principal")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_unread_doc() {}
+
+/// Follow a document
+/// 
+/// Adds the document to the digest of the calling user, sent daily or weekly by the digest job through the app service and as `user.digest` webhook event. The digest lists the blocks that changed since the previous digest, the new comments of others and the paths waiting for the approval of the user. Following a document also watches it, following a followed document changes the frequency.
+#[utoipa::path(
+    put,
+    path = "/api/v1/{org_id}/documents/{doc_id}/me/follow",
+    tag = "documents",
+    request_body(content = DocumentFollowRequest, description = "Frequency of the digest, \"daily\" or \"weekly\""),
+    responses(
+        (status = 200, description = "Document followed", body = DocumentFollowResponse),
+        (status = 400, description = "Unknown frequency", body = ErrorResponse),
+        (status = 403, description = "No access to the document", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_follow_doc() {}
+
+/// Stop following a document
+#[utoipa::path(
+    delete,
+    path = "/api/v1/{org_id}/documents/{doc_id}/me/follow",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Document no longer followed", body = DocumentFollowResponse),
+        (status = 403, description = "No access to the document", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_unfollow_doc() {}
+
+/// Watch a document
+/// 
+/// Subscribes the calling user to the events of a document, kept apart from the ACLs of the document. Workflow notifications and `document.state-changed` events list the watchers, and the digests only cover followed documents that are still watched. Watching a watched document keeps the original watch.
+#[utoipa::path(
+    put,
+    path = "/api/v1/{org_id}/documents/{doc_id}/watch",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Document watched", body = DocumentWatchResponse),
+        (status = 403, description = "No access to the document", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_watch_doc() {}
+
+/// Stop watching a document
+/// 
+/// Unsubscribes the calling user from the events of a document, this also stops following it.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/{org_id}/documents/{doc_id}/watch",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Document no longer watched", body = DocumentWatchResponse),
+        (status = 403, description = "No access to the document", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_unwatch_doc() {}
+
+/// List the features of an organization
+/// 
+/// Reports for every feature (comments, suggestions, translation, publishing) whether it is enabled for the organization, and whether that comes from a flag or the service default. Requires a cloud admin or the colabri-app service.
+#[utoipa::path(
+    get,
+    path = "/api/admin/{org_id}/features",
+    tag = "admin",
+    responses(
+        (status = 200, description = "A page of the features of the organization", body = OrgFeatureList),
+        (status = 403, description = "Cloud admin access required", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_features_doc() {}
+
+/// Enable or disable a feature for an organization
+/// 
+/// Sets the flag of a feature for the organization, an `enabled` of null removes the flag so the service default applies again. Endpoints of a disabled feature answer 403. Requires a cloud admin.
+#[utoipa::path(
+    put,
+    path = "/api/admin/{org_id}/features/{feature}",
+    tag = "admin",
+    request_body = OrgFeatureSetRequest,
+    responses(
+        (status = 200, description = "The feature as it applies now", body = OrgFeature),
+        (status = 400, description = "Unknown feature", body = ErrorResponse),
+        (status = 403, description = "Cloud admin access required", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("feature" = String, Path, description = "Feature: comments, suggestions, translation or publishing")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_feature_set_doc() {}
+
+/// Get the settings of a document
+/// 
+/// The overrides of the behavior of a single document: its save policy, its limits and whether it is read-only. Requires a cloud admin or the colabri-app service.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/settings",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Settings retrieved successfully", body = DocumentSettingsResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_settings_doc() {}
+
+/// Change the settings of a document
+/// 
+/// Applies a JSON merge patch to the settings of a document, null removes a setting. The settings apply to the live room right away, so problematic documents can be special-cased without a redeploy. Requires a cloud admin.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/{org_id}/documents/{doc_id}/settings",
+    tag = "documents",
+    request_body = DocumentSettingsPatchRequest,
+    responses(
+        (status = 200, description = "Settings changed", body = DocumentSettingsResponse),
+        (status = 400, description = "Invalid settings", body = ErrorResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_settings_patch_doc() {}
+
+/// Export a statement grid as CSV
+/// 
+/// Flattens a statement-grid block into CSV rows, one per statement language: row, source (local or ref), statement id, version, language, text, approval state and the date of the latest approval decision, formatted with the locale settings of the organization for the content type of the sheet. Referenced statements are resolved at their pinned version, unresolvable ones get the approval state "unresolved".
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/blocks/{block_id}/export.csv",
+    tag = "documents",
+    responses(
+        (status = 200, description = "CSV export of the statement grid", body = String, content_type = "text/csv"),
+        (status = 400, description = "Block is not a statement grid", body = ErrorResponse),
+        (status = 404, description = "Document or block not found", body = ErrorResponse),
+        (status = 406, description = "The Accept header doesn't allow text/csv", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("block_id" = String, Path, description = "Block ID"),
+        ("version" = Option<u32>, Query, description = "Version of the sheet, the latest when omitted")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_grid_export_doc() {}
+
+/// Import a CSV into a sheet
+/// 
+/// Builds a new block from a CSV with a header row and adds it to the sheet. A statement-grid block gets a local statement per CSV row, with the languages taken from the columns in `mapping.languages`. An attributes block gets an attribute per CSV row from `mapping.keyColumn`, `mapping.valueColumn` and optionally `mapping.displayColumn`. Rows without content are skipped.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/blocks/import-csv",
+    tag = "documents",
+    request_body = DocumentCsvImportRequest,
+    responses(
+        (status = 201, description = "Block imported", body = DocumentCsvImportResponse),
+        (status = 400, description = "Invalid CSV or column mapping", body = ErrorResponse),
+        (status = 422, description = "The block could not be added to the document", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_csv_import_doc() {}
+
+/// Load the statement grids of a sheet lazily
+/// 
+/// Moves the rows of every statement grid into a document of its own, synced in the room `<doc_id>~<block_id>`. The sheet keeps the skeleton of the grids with `lazy: true` and the `bodyRoom` to open for the rows, so the initial sync of the sheet stays small. Connected clients are disconnected and reconnect to the skeleton.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/blocks/lazy",
+    tag = "documents",
+    responses(
+        (status = 200, description = "The sheet loads its statement grids lazily", body = DocumentLazyBlocksResponse),
+        (status = 400, description = "Invalid document ID", body = ErrorResponse),
+        (status = 422, description = "The document is not a sheet, is already split or changed while splitting", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_blocks_split_doc() {}
+
+/// Stop loading the statement grids of a sheet lazily
+/// 
+/// Closes the block rooms, puts their rows back into the sheet and deletes the stored block bodies.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/{org_id}/documents/{doc_id}/blocks/lazy",
+    tag = "documents",
+    responses(
+        (status = 200, description = "The statement grids are part of the sheet again", body = DocumentLazyBlocksResponse),
+        (status = 400, description = "Invalid document ID", body = ErrorResponse),
+        (status = 422, description = "The blocks could not be joined", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_blocks_join_doc() {}
+
+/// Link the statements of a sheet as subdocuments
+/// 
+/// Moves every local statement of the statement grids into a subdocument of its own, synced in the room `<doc_id>~stmt-<subdoc_id>`. The rows become `type: "linked"` rows holding the `subdocId`. Stored and exported sheets are composed with the statements of their subdocuments. Sheets in lazy block mode need to be joined first.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/statements/link",
+    tag = "documents",
+    responses(
+        (status = 200, description = "The local statements are linked subdocuments", body = DocumentLinkStatementsResponse),
+        (status = 400, description = "Invalid document ID", body = ErrorResponse),
+        (status = 422, description = "The document is not a sheet, is in lazy block mode or changed while linking", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_statements_link_doc() {}
+
+/// Resolve the transclusions of a sheet
+/// 
+/// Resolves every `type: "transclusion"` block of the sheet to the latest content of its source block, checked against the ACLs of the source document for the principal. A transclusion is `forbidden` without view permission on the source block, `missing` when the source document or block doesn't exist and `nested` when the source block is a transclusion itself.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/transclusions",
+    tag = "documents",
+    responses(
+        (status = 200, description = "The resolved transclusions of the sheet", body = DocumentTransclusionsResponse),
+        (status = 400, description = "Invalid document ID", body = ErrorResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("prpl" = String, Query, description = "Principal to resolve the source blocks for")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_transclusions_doc() {}
+
+/// List the documents a document references
+/// 
+/// Lists the statements referenced by the statement grids (`statementRef`), the sources of transcluded blocks (`transclusion`) and the documents whose id is mentioned in an attribute value (`mention`). The references are indexed when the document is saved.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/links",
+    tag = "documents",
+    responses(
+        (status = 200, description = "The references of the document", body = DocumentLinkList),
+        (status = 400, description = "Invalid document ID", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_links_doc() {}
+
+/// List the documents referencing a document
+/// 
+/// Lists where a document is used by documents that aren't deleted, e.g. the sheets referencing a statement, so the impact of editing or deleting it is known up front.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/backlinks",
+    tag = "documents",
+    responses(
+        (status = 200, description = "The references to the document", body = DocumentLinkList),
+        (status = 400, description = "Invalid document ID", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_backlinks_doc() {}
+
+/// Find and replace text across a document
+/// 
+/// Replaces a plain text or, with `regex`, a regular expression in the titles and text elements of the blocks and languages of a document, optionally only in the given blocks and languages. Text blocks of sheets count as the master language of the sheet. Every match is replaced within its run of text, a match doesn't span text with different formatting. With `dryRun` the matches are only counted.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/replace",
+    tag = "documents",
+    request_body = DocumentReplaceRequest,
+    responses(
+        (status = 200, description = "Matches per block and language", body = DocumentReplaceResponse),
+        (status = 400, description = "Invalid pattern", body = ErrorResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_replace_doc() {}
+
+/// Compare a document with an external model
+/// 
+/// Takes the JSON of a document of the same type as `model`, e.g. the regulatory source the document was copied from, and compares the plain text of every block and language with the latest state of the document. Blocks are matched by id, blocks without id by position. Returns the blocks and languages that are `missing` from the document, `extra` in the document or `changed`. Formatting, ACLs and approvals aren't compared.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/compare",
+    tag = "documents",
+    request_body = DocumentCompareRequest,
+    responses(
+        (status = 200, description = "Differences per block and language", body = DocumentCompareResponse),
+        (status = 400, description = "Invalid external model or of another document type", body = ErrorResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_compare_doc() {}
+
+/// Re-sync a document from an external model
+/// 
+/// Applies the changes of `model`, the source of truth of the document, to the live document. Statements are synced per language, sheets per block, blocks are matched by id and blocks without id by position. Blocks and languages `missing` from the document are added. Those `changed`, or `extra` in the document, are resolved by `strategy`: `external-wins` replaces and removes them, `doc-wins` keeps them and `manual` follows `decisions`, `external` or `document` per block id or language, reporting undecided ones as `unresolved`. Replaced blocks keep their id, number, ACLs, comments and approvals. The state before the re-sync is recorded as version tag `resync-<uuid>`. With `dryRun` only the change report is returned.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/resync",
+    tag = "documents",
+    request_body = DocumentResyncRequest,
+    responses(
+        (status = 200, description = "Change report per block and language", body = DocumentResyncResponse),
+        (status = 400, description = "Invalid external model or strategy, of another document type or a sheet with lazy blocks", body = ErrorResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_resync_doc() {}
+
+/// Start a find-and-replace job across an organization
+/// 
+/// Replaces a pattern across all documents of the organization, or of one `library`, in the background, one document at a time. With `dryRun` the job only reports the matches per document as a preview. Otherwise every changed document gets a version tag `replace-<jobId>` with the state the replacement was applied to and the peer that applied it: reverting the changes of that peer after that version vector (revert-author) rolls the document back. One job runs per organization at a time.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/replace-jobs",
+    tag = "documents",
+    request_body = BulkReplaceRequest,
+    responses(
+        (status = 202, description = "The job was started", body = BulkReplaceStartResponse),
+        (status = 400, description = "Invalid pattern or library, or a job is already running", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn replace_job_start_doc() {}
+
+/// Get a find-and-replace job
+/// 
+/// Returns the progress of the job and the matches per document found so far, with the version tags of the changed documents.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/replace-jobs/{job_id}",
+    tag = "documents",
+    responses(
+        (status = 200, description = "The job", body = BulkReplaceJobResponse),
+        (status = 404, description = "Job not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("job_id" = String, Path, description = "Job ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn replace_job_doc() {}
+
+/// Look up a number
+/// 
+/// Returns the document or block carrying a stable number like `CLM-0042`. Statements are numbered when they are created, blocks of sheets when the sheet is saved. A number is never moved to another document or block nor handed out again, numbers of removed blocks still resolve to the block they were given to.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/numbers/{number}",
+    tag = "documents",
+    responses(
+        (status = 200, description = "The numbered document or block", body = DocumentNumberResponse),
+        (status = 404, description = "Number not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("number" = String, Path, description = "The number, case insensitive")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_number_doc() {}
+
+/// Get the duplicated statements of an organization
+/// 
+/// Returns the latest report of the deduplication analysis: clusters of statement documents and statements embedded in sheets with the same (`exact`) or nearly the same text, largest clusters first. Candidates to consolidate into one shared, referenced statement.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/statements/duplicates",
+    tag = "statements",
+    responses(
+        (status = 200, description = "The latest report", body = StatementDuplicatesReport),
+        (status = 404, description = "The statements were never analyzed", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn statement_duplicates_doc() {}
+
+/// Analyze the statements of an organization for duplicates
+/// 
+/// Starts the deduplication analysis in the background. The text of the master language of every statement is normalized, statements with the same text are clustered and statements whose words have a Jaccard similarity of at least `threshold` are joined. The report replaces the previous one once the analysis is done.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/statements/duplicates",
+    tag = "statements",
+    responses(
+        (status = 202, description = "The analysis was started, or was already running", body = StatementDuplicatesAnalyzeResponse),
+        (status = 400, description = "Invalid threshold", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("threshold" = Option<f64>, Query, description = "Similarity from which statements are duplicates, between 0.5 and 1, 0.85 by default")
+    )
+)]
+#[allow(dead_code)]
+pub async fn statement_duplicates_analyze_doc() {}
+
+/// Create a share token
+/// 
+/// Creates a token giving unauthenticated, read-only access to the published (latest signed) version of the document at `/public/{token}`. The token itself is only returned in this response, only its hash is stored. Requires the publishing feature.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/share-tokens",
+    tag = "documents",
+    request_body = DocumentShareTokenCreateRequest,
+    responses(
+        (status = 201, description = "Share token created", body = DocumentShareToken),
+        (status = 400, description = "Invalid document id or expiry", body = ErrorResponse),
+        (status = 403, description = "Publishing is disabled for the organization", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_share_token_create_doc() {}
+
+/// List the share tokens of a document
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/share-tokens",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Share tokens, including revoked and expired ones", body = ShareTokenList),
+        (status = 400, description = "Invalid document id", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_share_tokens_doc() {}
+
+/// Revoke a share token
+#[utoipa::path(
+    delete,
+    path = "/api/v1/{org_id}/documents/{doc_id}/share-tokens/{token_id}",
+    tag = "documents",
+    responses(
+        (status = 204, description = "Share token revoked"),
+        (status = 404, description = "No active share token with this id", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("token_id" = String, Path, description = "Share token ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_share_token_revoke_doc() {}
+
+/// Public view of a shared document
+/// 
+/// Renders the published version of the document behind a share token as HTML, with the branding of the token. No authentication is needed. Responses carry an ETag and may be cached for five minutes. The page can't be framed, use the embed mode for that.
+#[utoipa::path(
+    get,
+    path = "/public/{share_token}",
+    tag = "public",
+    responses(
+        (status = 200, description = "Rendered document", body = String, content_type = "text/html"),
+        (status = 304, description = "Not modified since the given ETag"),
+        (status = 404, description = "Unknown, revoked or expired token, or nothing published yet", body = String, content_type = "text/html")
+    ),
+    params(
+        ("share_token" = String, Path, description = "Share token")
+    )
+)]
+#[allow(dead_code)]
+pub async fn public_view_doc() {}
+
+/// Embedded public view of a shared document
+/// 
+/// The public view without header and footer, for use in an iframe. Its `Content-Security-Policy` only allows framing by the origins configured for the organization, by none when nothing is configured.
+#[utoipa::path(
+    get,
+    path = "/public/{share_token}/embed",
+    tag = "public",
+    responses(
+        (status = 200, description = "Rendered document", body = String, content_type = "text/html"),
+        (status = 304, description = "Not modified since the given ETag"),
+        (status = 404, description = "Unknown, revoked or expired token, or nothing published yet", body = String, content_type = "text/html")
+    ),
+    params(
+        ("share_token" = String, Path, description = "Share token")
+    )
+)]
+#[allow(dead_code)]
+pub async fn public_embed_doc() {}
+
+/// Link preview of a shared document
+/// 
+/// Title, excerpt and publication time of the shared document for unfurling links in chat tools, together with an iframe snippet of the embed mode. Modelled after an oEmbed `rich` response.
+#[utoipa::path(
+    get,
+    path = "/public/{share_token}/card.json",
+    tag = "public",
+    responses(
+        (status = 200, description = "Link preview", body = PublicViewCard),
+        (status = 404, description = "Unknown, revoked or expired token, or nothing published yet", body = String, content_type = "text/html")
+    ),
+    params(
+        ("share_token" = String, Path, description = "Share token")
+    )
+)]
+#[allow(dead_code)]
+pub async fn public_view_card_doc() {}
+
+/// Get the embed settings of an organization
+#[utoipa::path(
+    get,
+    path = "/api/admin/{org_id}/embed",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Embed settings", body = OrgEmbedSettingsResponse),
+        (status = 403, description = "Not a cloud admin", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_embed_settings_doc() {}
+
+/// Get the summary of a document
+/// 
+/// A short abstract of the published version of the document. It is generated in the background when the document is published, by the configured summary provider or from the start of the text.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/summary",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Document summary", body = DocumentSummary),
+        (status = 404, description = "No summary for the document", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_summary_doc() {}
+
+/// Regenerate the summary of a document
+/// 
+/// Summarizes the published version of the document again and waits for the result.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/summary",
+    tag = "documents",
+    responses(
+        (status = 200, description = "Document summary", body = DocumentSummary),
+        (status = 404, description = "The document was never published", body = ErrorResponse),
+        (status = 502, description = "The summary provider failed", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_summary_regenerate_doc() {}
+
+/// Get the summaries of several documents
+/// 
+/// For library views showing previews of many documents at once, at most 500 per request. Documents without a summary are left out.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/summaries",
+    tag = "documents",
+    request_body = DocumentSummariesRequest,
+    responses(
+        (status = 200, description = "Document summaries", body = DocumentSummariesResponse),
+        (status = 400, description = "Invalid document id or too many documents", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_summaries_doc() {}
+
+/// List the policy findings of a document
+/// 
+/// Findings of the policy scanner, which checks every save of documents in organizations with the `policy-scanning` feature. A scan replaces the open findings. When a finding reaches the configured severity the document is put in maintenance: updates by users are rejected until the findings are reviewed. The maintenance hold is reported in the `maintenance` of the document settings.
+#[utoipa::path(
+    get,
+    path = "/api/v1/{org_id}/documents/{doc_id}/policy-findings",
+    tag = "documents",
+    responses(
+        (status = 200, description = "A page of the policy findings", body = PolicyFindingList),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID"),
+        ("includeResolved" = Option<bool>, Query, description = "Include the resolved findings, false by default"),
+        ("limit" = Option<usize>, Query, description = "Number of items per page, 50 by default and at most 500"),
+        ("offset" = Option<usize>, Query, description = "Number of items to skip, `meta.pagination.nextOffset` of the previous page")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_policy_findings_doc() {}
+
+/// Review the policy findings of a document
+/// 
+/// Resolves the open findings and lifts the maintenance hold of the document.
+#[utoipa::path(
+    post,
+    path = "/api/v1/{org_id}/documents/{doc_id}/policy-findings/review",
+    tag = "documents",
+    request_body = DocumentPolicyReviewRequest,
+    responses(
+        (status = 200, description = "Findings resolved", body = DocumentPolicyReviewResponse),
+        (status = 404, description = "Document not found", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID"),
+        ("doc_id" = String, Path, description = "Document ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn doc_policy_review_doc() {}
+
+/// Set the embed settings of an organization
+/// 
+/// Replaces the origins allowed to frame the embedded public views. Origins must be https, a leading `*.` allows all subdomains. An empty list disallows embedding.
+#[utoipa::path(
+    put,
+    path = "/api/admin/{org_id}/embed",
+    tag = "admin",
+    request_body = OrgEmbedSettingsRequest,
+    responses(
+        (status = 200, description = "Embed settings updated", body = OrgEmbedSettingsResponse),
+        (status = 400, description = "Invalid origin", body = ErrorResponse),
+        (status = 403, description = "Not a cloud admin", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_embed_settings_set_doc() {}
+
+/// Get the document types registered by an organization
+#[utoipa::path(
+    get,
+    path = "/api/admin/{org_id}/document-types",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Registered document types", body = OrgDocumentTypesResponse),
+        (status = 403, description = "Not a cloud admin", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_document_types_doc() {}
+
+/// Register the document types of an organization
+/// 
+/// Replaces the content types registered per document type. In strict mode documents whose type or content type isn't registered are refused when loaded into a room, imported, created or saved. Without strict mode documents of unknown types are only logged.
+#[utoipa::path(
+    put,
+    path = "/api/admin/{org_id}/document-types",
+    tag = "admin",
+    request_body = OrgDocumentTypesRequest,
+    responses(
+        (status = 200, description = "Document types updated", body = OrgDocumentTypesResponse),
+        (status = 400, description = "Unknown document type", body = ErrorResponse),
+        (status = 403, description = "Not a cloud admin", body = ErrorResponse)
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization ID")
+    )
+)]
+#[allow(dead_code)]
+pub async fn org_document_types_set_doc() {}
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
         health_check_doc,
         ready_check_doc,
+        metrics_doc,
         diagnostics_doc,
+        diagnostics_orgs_doc,
+        org_analytics_doc,
+        billing_report_doc,
+        doc_create_doc,
         doc_latest_doc,
         doc_version_doc,
         doc_delete_doc,
         doc_move_lib_doc,
+        doc_permissions_doc,
+        doc_access_report_doc,
+        doc_comments_doc,
+        doc_comment_add_doc,
+        doc_comment_edit_doc,
+        doc_comment_resolve_doc,
+        doc_reactions_doc,
+        doc_reaction_add_doc,
+        doc_reaction_remove_doc,
+        doc_suggestions_doc,
+        doc_suggestion_add_doc,
+        doc_suggestion_accept_doc,
+        doc_suggestion_reject_doc,
+        doc_approval_rounds_doc,
+        doc_approval_round_start_doc,
+        doc_approval_round_cancel_doc,
+        doc_state_doc,
+        doc_state_transition_doc,
+        doc_citation_doc,
+        doc_evidence_doc,
+        doc_published_signature_doc,
+        doc_published_verify_doc,
+        doc_room_doc,
+        doc_quarantine_doc,
+        doc_quarantine_retry_doc,
+        doc_quarantine_repair_doc,
+        doc_storage_doc,
+        doc_versions_doc,
+        doc_storage_budget_doc,
+        archival_candidates_doc,
+        doc_playback_doc,
+        doc_blame_doc,
+        doc_revert_author_doc,
+        doc_reconcile_doc,
+        doc_reconcile_merge_doc,
+        doc_save_status_doc,
+        doc_user_prefs_doc,
+        doc_user_prefs_put_doc,
+        doc_unread_doc,
+        doc_follow_doc,
+        doc_unfollow_doc,
+        doc_watch_doc,
+        doc_unwatch_doc,
+        org_features_doc,
+        org_feature_set_doc,
+        doc_settings_doc,
+        doc_settings_patch_doc,
+        doc_grid_export_doc,
+        doc_csv_import_doc,
+        doc_blocks_split_doc,
+        doc_blocks_join_doc,
+        doc_statements_link_doc,
+        doc_transclusions_doc,
+        doc_links_doc,
+        doc_backlinks_doc,
+        doc_number_doc,
+        doc_replace_doc,
+        doc_compare_doc,
+        doc_resync_doc,
+        replace_job_start_doc,
+        replace_job_doc,
+        statement_duplicates_doc,
+        statement_duplicates_analyze_doc,
+        doc_share_token_create_doc,
+        doc_share_tokens_doc,
+        doc_share_token_revoke_doc,
+        public_view_doc,
+        public_embed_doc,
+        public_view_card_doc,
+        org_embed_settings_doc,
+        org_embed_settings_set_doc,
+        org_document_types_doc,
+        org_document_types_set_doc,
+        doc_summary_doc,
+        doc_summary_regenerate_doc,
+        doc_summaries_doc,
+        doc_policy_findings_doc,
+        doc_policy_review_doc,
+        drain_start_doc,
+        drain_status_doc,
+        doc_recording_start_doc,
+        doc_recording_status_doc,
+        doc_recording_stop_doc,
+        doc_recording_download_doc,
+        org_schema_migration_start_doc,
+        org_schema_migration_status_doc,
+        org_announcement_create_doc,
+        org_announcements_doc,
+        org_announcement_delete_doc,
+        org_locale_settings_doc,
+        org_locale_settings_set_doc,
+        user_principals_push_doc,
     ),
     components(
         schemas(HealthResponse, 
             ReadyResponse, 
             DiagnosticsResponse, 
+            FailingSaveInfo,
+            ClientVersionCount,
+            OrgDiagnostics,
+            OrgDiagnosticsResponse,
+            OrgUsageBucket,
+            OrgAnalyticsResponse,
+            OrgBillingUsage,
+            BillingReportResponse,
             DocumentLatestResponse, 
+            DocumentChangesResponse,
+            DocumentCreateRequest,
+            DocumentCreateResponse,
             DocumentVersionRequest, 
             DocumentVersionResponse,
+            NamedVersionEntry,
             DocumentDeleteResponse,
             DocumentMoveLibRequest,
             DocumentMoveLibResponse,
+            DocumentPermissionsResponse,
+            ScopePermissions,
+            PermissionExplanation,
+            PermissionGrant,
+            DocumentAccessReportResponse,
+            AccessReportEntry,
+            CommentThread,
+            CommentView,
+            CommentAnchorView,
+            CommentAnchorRequest,
+            DocumentCommentAddRequest,
+            DocumentCommentEditRequest,
+            DocumentCommentResolveRequest,
+            ReactionCount,
+            ReactionSummary,
+            DocumentReactionRequest,
+            DocumentReactionResponse,
+            DocumentCommentResponse,
+            DocumentCommentResolveResponse,
+            SuggestionView,
+            DocumentSuggestionAddRequest,
+            DocumentSuggestionDecisionRequest,
+            DocumentSuggestionResponse,
+            ApprovalRoundView,
+            ApproverResponseView,
+            DocumentApprovalRoundStartRequest,
+            DocumentApprovalRoundCancelRequest,
+            DocumentApprovalRoundResponse,
+            WorkflowDefinition,
+            WorkflowStateDefinition,
+            WorkflowTransitionDefinition,
+            AvailableTransition,
+            DocumentStateResponse,
+            DocumentStateTransitionRequest,
+            DocumentStateTransitionResponse,
+            DocumentCitationResponse,
+            CitationApprover,
+            DocumentSignatureResponse,
+            DocumentSignatureVerifyRequest,
+            DocumentSignatureVerifyResponse,
+            DocumentRoomResponse,
+            DocumentQuarantineResponse,
+            StorageTier,
+            VersionStorage,
+            DocumentStorageResponse,
+            DocumentHistoryBudgetRequest,
+            ArchivalCandidate,
+            PlaybackFrame,
+            DocumentPlaybackResponse,
+            BlameRun,
+            DocumentBlameResponse,
+            DocumentRevertRequest,
+            RevertedChange,
+            DocumentRevertResponse,
+            ReconcileVersion,
+            DocumentMerge,
+            DocumentReconcileResponse,
+            DocumentMergeRequest,
+            DocumentMergeResponse,
+            DocumentSaveStatusResponse,
+            DocumentUserPrefsResponse,
+            UnreadChanges,
+            DocumentUserPrefsRequest,
+            DocumentUnreadResponse,
+            UnreadBlock,
+            DocumentFollowRequest,
+            DocumentFollowResponse,
+            DocumentWatchResponse,
+            OrgFeature,
+            OrgFeatureSetRequest,
+            DocumentSettings,
+            DocumentSavePolicySettings,
+            DocumentLimitSettings,
+            DocumentSettingsResponse,
+            DocumentSettingsPatchRequest,
+            CsvColumnMapping,
+            DocumentCsvImportRequest,
+            DocumentCsvImportResponse,
+            DocumentLazyBlocksResponse,
+            LinkedStatement,
+            DocumentLinkStatementsResponse,
+            ResolvedTransclusion,
+            DocumentTransclusionsResponse,
+            DocumentLink,
+            DuplicateOccurrence,
+            DuplicateCluster,
+            DocumentNumberResponse,
+            DocumentReplaceRequest,
+            ReplaceMatches,
+            DocumentReplaceResponse,
+            BulkReplaceRequest,
+            BulkReplaceStartResponse,
+            DocumentVersionTag,
+            BulkReplaceDocumentResult,
+            BulkReplaceJobResponse,
+            DocumentCompareRequest,
+            ContentDiff,
+            DocumentCompareResponse,
+            DocumentResyncRequest,
+            ResyncChange,
+            DocumentResyncResponse,
+            StatementDuplicatesReport,
+            StatementDuplicatesAnalyzeResponse,
+            Pagination,
+            ListMeta,
+            VersionStorageList,
+            CommentThreadList,
+            ReactionSummaryList,
+            SuggestionList,
+            ApprovalRoundList,
+            ShareTokenList,
+            DocumentLinkList,
+            OrgAnnouncementList,
+            ArchivalCandidateList,
+            OrgFeatureList,
+            PolicyFindingList,
+            ShareBranding,
+            DocumentShareTokenCreateRequest,
+            DocumentShareToken,
+            OrgEmbedSettingsResponse,
+            OrgEmbedSettingsRequest,
+            OrgDocumentTypesResponse,
+            OrgDocumentTypesRequest,
+            PublicViewCard,
+            DocumentSummary,
+            DocumentSummariesRequest,
+            DocumentSummariesResponse,
+            DocumentMaintenance,
+            PolicyFinding,
+            DocumentPolicyReviewRequest,
+            DocumentPolicyReviewResponse,
+            DrainStatusResponse,
+            RecordingStartRequest,
+            RecordingStatus,
+            SchemaMigrationStatus,
+            OrgAnnouncementRequest,
+            OrgAnnouncement,
+            LocaleSettings,
+            ResolvedLocaleSettings,
+            OrgLocaleSettingsResponse,
+            OrgLocaleSettingsRequest,
+            UserPrincipalsRequest,
+            UserPrincipalsResponse,
             ErrorResponse)
     ),
     tags(
-        (name = "health", description = "Health check endpoints"),
-        (name = "diagnostics", description = "Diagnostics endpoints"),
-        (name = "documents", description = "Document management endpoints")
+        (name = "health", description = "Health check and metrics endpoints, served on the internal admin port"),
+        (name = "diagnostics", description = "Diagnostics endpoints, served on the internal admin port"),
+        (name = "documents", description = "Document management endpoints"),
+        (name = "comments", description = "Document comment endpoints"),
+        (name = "suggestions", description = "Suggested edit endpoints"),
+        (name = "approvals", description = "Approval round endpoints"),
+        (name = "workflow", description = "Document workflow endpoints"),
+        (name = "public", description = "Unauthenticated public views"),
+        (name = "admin", description = "Pod administration endpoints, served on the internal admin port"),
+        (name = "statements", description = "Org-wide statement analysis endpoints")
     )
 )]
 pub struct ApiDoc;
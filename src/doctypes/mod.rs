@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use futures_util::future::BoxFuture;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use crate::models::ColabModel;
+use crate::services::acl_service::AclScope;
+use crate::ws::docctx::DocContext;
+
+pub mod sheet;
+pub mod statement;
+
+// The type specific behavior of documents.
+// Every value of `properties.type` has a DocType registered here, services look up the type of a
+// document instead of matching on it. A new document type is a new module implementing DocType and
+// one line in `registry`.
+
+/// Counts of a document checked against the limits of an organization
+pub struct DocStats {
+    // Blocks of the document, None for types without blocks
+    pub blocks: Option<usize>,
+    // Languages the document has content for
+    pub languages: usize,
+}
+
+/// A part of a document carrying a stable number
+pub struct NumberedPart {
+    // The block, None for the document itself
+    pub block: Option<String>,
+    // The number the part carries in the document, if any
+    pub number: Option<String>,
+}
+
+/// The behavior of a document type
+pub trait DocType: Send + Sync {
+    /// The value of `properties.type`
+    fn name(&self) -> &'static str;
+
+    /// The table holding the JSON of the documents
+    fn table(&self) -> &'static str;
+
+    /// The JSON of an empty document with the given properties
+    fn empty_json(&self, properties: Value) -> Value;
+
+    /// Add the ACL scopes below the document scope, which is the first scope
+    fn collect_acl_scopes(&self, doc: &LoroDoc, scopes: &mut Vec<AclScope>);
+
+    /// Remove the ACLs of the document and of all of its content
+    fn reset_acls(&self, doc: &LoroDoc) -> Result<(), String>;
+
+    /// Counts of the document in its JSON form
+    fn stats(&self, json: &Value) -> DocStats;
+
+    /// Check the structure of the document in its JSON form beyond what deserializing it checks
+    fn validate(&self, _json: &Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// The parts of the document in its JSON form that get a stable number, the document itself by default
+    fn numbered_parts(&self, json: &Value) -> Vec<NumberedPart> {
+        let number = json.pointer("/properties/number").and_then(|n| n.as_str()).map(|n| n.to_string());
+        vec![NumberedPart { block: None, number }]
+    }
+
+    /// The document as Markdown
+    fn render_markdown<'a>(&'a self, registry: &'a Arc<HubRegistry<DocContext>>, org_id: &'a str, model: &'a ColabModel) -> BoxFuture<'a, String>;
+}
+
+static REGISTRY: OnceLock<HashMap<&'static str, Box<dyn DocType>>> = OnceLock::new();
+
+fn registry() -> &'static HashMap<&'static str, Box<dyn DocType>> {
+    REGISTRY.get_or_init(|| {
+        let doc_types: Vec<Box<dyn DocType>> = vec![
+            Box::new(statement::ColabStatement),
+            Box::new(sheet::ColabSheet),
+        ];
+        doc_types.into_iter().map(|doc_type| (doc_type.name(), doc_type)).collect()
+    })
+}
+
+/// The registered type with the given name
+pub fn get(name: &str) -> Option<&'static dyn DocType> {
+    registry().get(name).map(|doc_type| doc_type.as_ref())
+}
+
+/// The type of a document, an error naming the type when it isn't registered
+pub fn get_or_error(name: &str) -> Result<&'static dyn DocType, String> {
+    get(name).ok_or_else(|| format!("Unknown or unsupported document type: {}", name))
+}
+
+/// The names of all registered types, sorted
+pub fn names() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = registry().keys().copied().collect();
+    names.sort();
+    names
+}
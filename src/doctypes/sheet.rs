@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use futures_util::future::BoxFuture;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use serde_json::{json, Value};
+use tracing::warn;
+use crate::models::{ColabModel, ColabSheetBlock};
+use crate::services::acl_service::{self, AclScope};
+use crate::services::{legacy_approvals_service, render_service, transclusion_service};
+use crate::ws::docctx::DocContext;
+use super::{DocStats, DocType, NumberedPart};
+
+/// A sheet: a list of blocks
+pub struct ColabSheet;
+
+impl DocType for ColabSheet {
+    fn name(&self) -> &'static str {
+        "colab-sheet"
+    }
+
+    fn table(&self) -> &'static str {
+        "document_sheets"
+    }
+
+    fn empty_json(&self, properties: Value) -> Value {
+        json!({ "properties": properties, "approvals": {}, "acls": {}, "content": [] })
+    }
+
+    fn collect_acl_scopes(&self, doc: &LoroDoc, scopes: &mut Vec<AclScope>) {
+        acl_service::collect_sheet_scopes(doc, scopes);
+    }
+
+    fn reset_acls(&self, doc: &LoroDoc) -> Result<(), String> {
+        acl_service::reset_acls_sheet_doc(doc)
+    }
+
+    fn stats(&self, json: &Value) -> DocStats {
+        DocStats {
+            blocks: Some(json.get("content").and_then(|c| c.as_array()).map(|c| c.len()).unwrap_or(0)),
+            languages: 0,
+        }
+    }
+
+    // Columns and cards of boards need ids unique within their board and cards a status
+    fn validate(&self, json: &Value) -> Result<(), String> {
+        legacy_approvals_service::validate(json)?;
+        let blocks = json.get("content").and_then(|c| c.as_array()).into_iter().flatten();
+        for (i, block) in blocks.enumerate().filter(|(_, block)| block.get("type").and_then(|t| t.as_str()) == Some("board")) {
+            let block_id = block.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()).unwrap_or_else(|| i.to_string());
+            let mut ids = HashSet::new();
+            for column in block.get("columns").and_then(|c| c.as_array()).into_iter().flatten() {
+                let cards: Vec<&Value> = column.get("cards").and_then(|c| c.as_array()).into_iter().flatten().collect();
+                for item in std::iter::once(column).chain(cards.iter().copied()) {
+                    let id = item.get("id").and_then(|id| id.as_str()).unwrap_or_default();
+                    if id.is_empty() || !ids.insert(id) {
+                        return Err(format!("Board '{}' has a missing or duplicate column or card id '{}'", block_id, id));
+                    }
+                }
+                if cards.iter().any(|card| card.get("status").and_then(|s| s.as_str()).map_or(true, str::is_empty)) {
+                    return Err(format!("Board '{}' has a card without status", block_id));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Every block with an id except the properties and transclusions, which show the number of their source
+    fn numbered_parts(&self, json: &Value) -> Vec<NumberedPart> {
+        json.get("content")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|block| !matches!(block.get("type").and_then(|t| t.as_str()), Some("properties") | Some(transclusion_service::BLOCK_TYPE)))
+            .filter_map(|block| {
+                let block_id = block.get("id")?.as_str()?.to_string();
+                let number = block.get("number").and_then(|n| n.as_str()).map(|n| n.to_string());
+                Some(NumberedPart { block: Some(block_id), number })
+            })
+            .collect()
+    }
+
+    // Transcluded blocks show the latest published version of their source
+    fn render_markdown<'a>(&'a self, registry: &'a Arc<HubRegistry<DocContext>>, org_id: &'a str, model: &'a ColabModel) -> BoxFuture<'a, String> {
+        Box::pin(async move {
+            let mut markdown = String::new();
+            let ColabModel::Sheet(sheet) = model else {
+                return markdown;
+            };
+            for block in &sheet.content {
+                let ColabSheetBlock::Transclusion(transclusion) = block else {
+                    render_service::markdown_block(registry, org_id, block, &mut markdown).await;
+                    continue;
+                };
+                match transclusion_service::published_block(registry, org_id, transclusion).await {
+                    Ok(Some((_, source_block))) => render_service::markdown_block(registry, org_id, &source_block, &mut markdown).await,
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to render transcluded block '{}' of document '{}': {}", transclusion.source_block_id, transclusion.source_doc_id, e),
+                }
+            }
+            markdown
+        })
+    }
+}
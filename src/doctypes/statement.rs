@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use futures_util::future::BoxFuture;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use serde_json::{json, Value};
+use crate::models::ColabModel;
+use crate::services::acl_service::{self, AclScope};
+use crate::services::{legacy_approvals_service, render_service};
+use crate::ws::docctx::DocContext;
+use super::{DocStats, DocType};
+
+/// A statement: its content is keyed by language
+pub struct ColabStatement;
+
+impl DocType for ColabStatement {
+    fn name(&self) -> &'static str {
+        "colab-statement"
+    }
+
+    fn table(&self) -> &'static str {
+        "document_statements"
+    }
+
+    fn empty_json(&self, properties: Value) -> Value {
+        json!({ "properties": properties, "acls": {}, "content": {} })
+    }
+
+    fn collect_acl_scopes(&self, doc: &LoroDoc, scopes: &mut Vec<AclScope>) {
+        acl_service::collect_language_scopes(&doc.get_map("content"), "", 0, scopes);
+    }
+
+    fn reset_acls(&self, doc: &LoroDoc) -> Result<(), String> {
+        acl_service::reset_acls_statement_doc(doc)
+    }
+
+    fn stats(&self, json: &Value) -> DocStats {
+        DocStats {
+            blocks: None,
+            languages: json.get("content").and_then(|c| c.as_object()).map(|c| c.len()).unwrap_or(0),
+        }
+    }
+
+    fn validate(&self, json: &Value) -> Result<(), String> {
+        legacy_approvals_service::validate(json)
+    }
+
+    fn render_markdown<'a>(&'a self, _registry: &'a Arc<HubRegistry<DocContext>>, _org_id: &'a str, model: &'a ColabModel) -> BoxFuture<'a, String> {
+        Box::pin(async move {
+            let mut markdown = String::new();
+            if let ColabModel::Statement(statement) = model {
+                render_service::markdown_statement(statement, &mut markdown);
+            }
+            markdown
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{auth::auth, models::{api_error, ApiError, OrgAnalyticsResponse, OrgUsageBucket}, services::analytics_service};
+use axum::{extract::{Extension, Path, Query}, http::StatusCode, Json};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::error;
+
+#[derive(Deserialize)]
+pub struct AnalyticsQuery {
+    range: Option<String>,
+}
+
+/// Usage of an organization over a range, for capacity planning and billing
+pub async fn org_analytics(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<(StatusCode, Json<OrgAnalyticsResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin or the app service
+    if auth::ensure_service(&prpls, "colabri-app").is_err() {
+        let _ = auth::ensure_cloud_admin(&prpls)?;
+    }
+
+    let range = query.range.unwrap_or_else(|| "7d".to_string());
+    let duration = analytics_service::parse_range(&range).map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+    let bucket = analytics_service::bucket_for(duration);
+
+    let rows = analytics_service::usage(&org_id, duration, bucket).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+
+    let sample_minutes = analytics_service::sample_minutes();
+    let buckets: Vec<OrgUsageBucket> = rows
+        .into_iter()
+        .map(|row| OrgUsageBucket {
+            bucket_start: row.bucket_start,
+            peak_rooms: row.peak_rooms,
+            peak_doc_rooms: row.peak_doc_rooms,
+            peak_conn: row.peak_conn,
+            avg_conn: row.avg_conn,
+            connection_minutes: row.conn_samples as f64 * sample_minutes,
+            updates: row.n_updates,
+        })
+        .collect();
+
+    let to = Utc::now();
+    Ok((
+        StatusCode::OK,
+        Json(OrgAnalyticsResponse {
+            org_id,
+            range,
+            from: to - duration,
+            to,
+            bucket_secs: bucket.num_seconds(),
+            peak_conn: buckets.iter().map(|b| b.peak_conn).max().unwrap_or(0),
+            peak_doc_rooms: buckets.iter().map(|b| b.peak_doc_rooms).max().unwrap_or(0),
+            connection_minutes: buckets.iter().map(|b| b.connection_minutes).sum(),
+            updates: buckets.iter().map(|b| b.updates).sum(),
+            buckets,
+        }),
+    ))
+}
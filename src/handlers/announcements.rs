@@ -0,0 +1,75 @@
+use crate::{auth::auth, models::{api_error, ApiError, ListResponse, OrgAnnouncement, OrgAnnouncementRequest, PageQuery, RequestId}, services::announcement_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Schedule an announcement for every connected client of an organization
+pub async fn org_announcement_create(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Json(request): Json<OrgAnnouncementRequest>,
+) -> Result<(StatusCode, Json<OrgAnnouncement>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let by_prpl = auth::ensure_cloud_admin(&prpls)?;
+
+    match announcement_service::create(&registry, &org_id, &request, &by_prpl).await {
+        Ok(Ok(announcement)) => Ok((StatusCode::CREATED, Json(announcement))),
+        Ok(Err(e)) => Err(api_error(StatusCode::BAD_REQUEST, e)),
+        Err(e) => {
+            error!("Failed to create an announcement in organization '{}': {}", org_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create an announcement in organization '{}'", org_id)))
+        }
+    }
+}
+
+/// List the running and scheduled announcements of an organization
+pub async fn org_announcements(
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path(org_id): Path<String>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<OrgAnnouncement>>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    match announcement_service::list(&org_id).await {
+        Ok(announcements) => Ok((StatusCode::OK, Json(ListResponse::page(announcements, &page, &request_id)))),
+        Err(e) => {
+            error!("Failed to list the announcements of organization '{}': {}", org_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to list the announcements of organization '{}'", org_id)))
+        }
+    }
+}
+
+/// Withdraw an announcement before it expires
+pub async fn org_announcement_delete(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, announcement_id)): Path<(String, String)>,
+) -> Result<StatusCode, ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    let announcement_uuid = match Uuid::parse_str(&announcement_id) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            warn!("Invalid announcement UUID '{}': {}", announcement_id, e);
+            return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid announcement UUID '{}'", announcement_id)));
+        }
+    };
+
+    match announcement_service::delete(&registry, &org_id, announcement_uuid).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(api_error(StatusCode::NOT_FOUND, format!("Announcement '{}' not found", announcement_id))),
+        Err(e) => {
+            error!("Failed to delete announcement '{}' of organization '{}': {}", announcement_id, org_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete announcement '{}'", announcement_id)))
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{auth::auth, models::{api_error, ApiError, ArchivalCandidate, ListResponse, PageQuery, RequestId}, services::archival_service};
+use axum::{extract::{Extension, Path, Query}, http::StatusCode, Json};
+use chrono::Duration;
+use tracing::error;
+
+/// Get the documents of an organization the archival job is going to archive
+pub async fn archival_candidates(
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path(org_id): Path<String>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<ArchivalCandidate>>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    let (policy, rows) = match archival_service::candidates(&org_id).await {
+        Ok(Some(res)) => res,
+        Ok(None) => return Err(api_error(StatusCode::NOT_FOUND, format!("Organization '{}' has no archival policy", org_id))),
+        Err(e) => {
+            error!("{}", e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+    };
+
+    let candidates = rows
+        .into_iter()
+        .map(|row| ArchivalCandidate {
+            doc_id: row.id.to_string(),
+            name: row.name,
+            workflow_state: row.workflow_state,
+            last_activity_at: row.last_activity_at,
+            flagged_at: row.archive_candidate_since,
+            archive_after: row.archive_candidate_since.map(|since| since + Duration::days(policy.grace_days as i64)),
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(ListResponse::page(candidates, &page, &request_id))))
+}
@@ -0,0 +1,45 @@
+use crate::{auth::auth, models::{api_error, ApiError}, services::{billing_service, csv_service}};
+use axum::{extract::{Extension, Path, Query}, http::StatusCode, response::{IntoResponse, Response}, Json};
+use serde::Deserialize;
+use tracing::{error, info};
+
+#[derive(Deserialize)]
+pub struct BillingQuery {
+    format: Option<String>,
+}
+
+/// Usage report of all organizations for a month, for invoicing
+pub async fn billing_report(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(period): Path<String>,
+    Query(query): Query<BillingQuery>,
+) -> Result<Response, ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let by_prpl = auth::ensure_cloud_admin(&prpls)?;
+
+    // Validate the output format
+    let as_csv = match query.format.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        None => false,
+        Some(value) => match value.to_lowercase().as_str() {
+            "json" => false,
+            "csv" => true,
+            other => {
+                return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid output format '{}'. Use 'json' or 'csv'.", other)));
+            }
+        },
+    };
+    billing_service::parse_period(&period).map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+
+    let report = billing_service::report(&period).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    info!("Billing report for '{}' with {} organizations built for '{}'", report.period, report.orgs.len(), by_prpl);
+
+    if as_csv {
+        let csv = csv_service::to_csv(&billing_service::CSV_HEADER, &billing_service::csv_rows(&report));
+        return Ok(csv_service::csv_response(&format!("billing-{}.csv", report.period), csv));
+    }
+    Ok((StatusCode::OK, Json(report)).into_response())
+}
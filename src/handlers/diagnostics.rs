@@ -1,8 +1,9 @@
-use crate::{auth::auth, models::{DiagnosticsResponse, ErrorResponse}, ws::{docctx::DocContext, userctx}};
-use axum::{extract::{State, Extension}, http::StatusCode, Json};
+use crate::{auth::auth, models::{DiagnosticsResponse, ErrorResponse, FailingSaveInfo, OrgDiagnosticsResponse}, ws::{docctx::DocContext, userctx}};
+use axum::{extract::{State, Extension, Query}, http::StatusCode, Json};
 use loro_websocket_server::{HubRegistry};
+use serde::Deserialize;
 use std::sync::Arc;
-use loro_protocol::protocol::CrdtType;
+use crate::services::{client_version_service, hub_service, org_diagnostics_service, quarantine_service, save_retry_service};
 use std::sync::{Mutex, OnceLock};
 use sysinfo::System;
 use tracing::info;
@@ -24,27 +25,43 @@ pub async fn diagnostics(
     let mut n_doc_rooms: u32 = 0;
     let mut n_ephemeral_rooms: u32 = 0;
     let mut n_dirty_docs: u32 = 0;
-    let hubs = registry.hubs().lock().await;
-    for (_, hub) in hubs.iter() {
-        let h = hub.lock().await;
-        for (room_key, doc_state) in h.docs.iter() {
-            n_rooms += 1;
-            if room_key.crdt == CrdtType::Loro {
-                n_doc_rooms += 1;
-            }
-            if room_key.crdt == CrdtType::LoroEphemeralStore {
-                n_ephemeral_rooms += 1;
-            }
-            if doc_state.dirty {
-                n_dirty_docs += 1;
-            }
-            n_conn += h.subs.get(room_key).map_or(0, |subs_set| subs_set.len()) as u32;
+    for room in hub_service::cached_rooms(&registry).await.iter() {
+        n_rooms += 1;
+        if room.is_doc {
+            n_doc_rooms += 1;
+        }
+        if room.is_ephemeral {
+            n_ephemeral_rooms += 1;
+        }
+        if room.dirty {
+            n_dirty_docs += 1;
         }
+        n_conn += room.subscribers as u32;
     }
 
     // Get the user contexts count
     let n_user_ctx = userctx::get_user_ctx_cache().entry_count() as u32;
 
+    // Get the documents quarantined by this instance
+    let n_quarantined_docs = quarantine_service::quarantined_total();
+
+    // Get the documents whose saves are failing
+    let n_stale_unsaved_docs = save_retry_service::stale_unsaved_count() as u32;
+    let failing_saves: Vec<FailingSaveInfo> = save_retry_service::failing_saves()
+        .into_iter()
+        .map(|save| FailingSaveInfo {
+            org_id: save.org,
+            doc_id: save.doc_id.to_string(),
+            attempts: save.attempts,
+            failing_since: save.failing_since,
+            next_retry_in_ms: save.next_retry_in_ms,
+            last_error: save.last_error,
+        })
+        .collect();
+
+    // Get the versions of the connected clients
+    let client_versions = client_version_service::distribution();
+
     // System stats
     let (cpu_usage, memory_alloc, memory_free, memory_total) = {
         let sys_lock = SYSTEM_MONITOR.get_or_init(|| {
@@ -84,6 +101,10 @@ pub async fn diagnostics(
             n_ephemeral_rooms,
             n_dirty_docs,
             n_user_ctx,
+            n_quarantined_docs,
+            n_stale_unsaved_docs,
+            failing_saves,
+            client_versions,
             cpu_usage,
             memory_alloc,
             memory_total,
@@ -91,3 +112,36 @@ pub async fn diagnostics(
         }),
     ));
 }
+
+#[derive(Deserialize)]
+pub struct OrgDiagnosticsQuery {
+    aggregate: Option<bool>,
+}
+
+/// Activity per organization, aggregated by default so it can be shared beyond the cloud admins
+pub async fn diagnostics_orgs(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Query(query): Query<OrgDiagnosticsQuery>,
+) -> Result<(StatusCode, Json<OrgDiagnosticsResponse>), (StatusCode, Json<ErrorResponse>)> {
+
+    // The aggregated view may be shown to the app, identifiers of small orgs only to cloud admins
+    let aggregate = query.aggregate.unwrap_or(true);
+    if aggregate {
+        let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    } else {
+        let _ = auth::ensure_cloud_admin(&prpls)?;
+    }
+
+    let orgs = org_diagnostics_service::collect(&registry, aggregate).await;
+    let min_org_conn = org_diagnostics_service::min_org_conn();
+
+    Ok((
+        StatusCode::OK,
+        Json(OrgDiagnosticsResponse {
+            aggregated: aggregate,
+            min_org_conn,
+            orgs,
+        }),
+    ))
+}
@@ -0,0 +1,93 @@
+use crate::{auth::auth, models::{api_error, ApiError, AccessReportEntry, DocumentAccessReportResponse}, services::{acl_service, csv_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, response::{IntoResponse, Response}, Json};
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct AccessReportQuery {
+    format: Option<String>,
+}
+
+/// List every principal with access to a document and their effective permissions per scope
+pub async fn doc_access_report(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<AccessReportQuery>,
+) -> Result<Response, ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    // Validate the output format
+    let as_csv = match query.format.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+        None => false,
+        Some(value) => match value.to_lowercase().as_str() {
+            "json" => false,
+            "csv" => true,
+            other => {
+                return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid output format '{}'. Use 'json' or 'csv'.", other)));
+            }
+        },
+    };
+
+    // Parse the doc_id as an UUID
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        error!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    // Get the database ACL rows and the Loro ACL scopes
+    let (access, scopes) = match acl_service::load_document_acls(&registry, &org_id, &doc_id).await {
+        Ok(Some(res)) => res,
+        Ok(None) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id)));
+        }
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}': {}", doc_id, e)));
+        }
+    };
+
+    // Flatten the grants per principal and scope
+    let mut entries = Vec::new();
+    for (idx, scope) in scopes.iter().enumerate() {
+        let mut by_prpl: BTreeMap<String, (BTreeSet<String>, BTreeSet<String>)> = BTreeMap::new();
+        for grant in acl_service::collect_scope_grants(&scopes, idx, &org_id, &access) {
+            let (permissions, sources) = by_prpl.entry(grant.prpl).or_default();
+            permissions.insert(grant.permission);
+            sources.insert(grant.source);
+        }
+        for (prpl, (permissions, sources)) in by_prpl {
+            entries.push(AccessReportEntry {
+                prpl,
+                path: scope.path.clone(),
+                level: scope.level.to_string(),
+                permissions: permissions.into_iter().collect(),
+                sources: sources.into_iter().collect(),
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.prpl.cmp(&b.prpl));
+
+    if as_csv {
+        let rows: Vec<Vec<String>> = entries
+            .iter()
+            .map(|e| vec![
+                e.prpl.clone(),
+                e.path.clone(),
+                e.level.clone(),
+                e.permissions.join(";"),
+                e.sources.join(";"),
+            ])
+            .collect();
+        let csv = csv_service::to_csv(&["prpl", "path", "level", "permissions", "sources"], &rows);
+        return Ok(csv_service::csv_response(&format!("access-report-{}.csv", doc_id), csv));
+    }
+
+    Ok((StatusCode::OK, Json(DocumentAccessReportResponse { entries })).into_response())
+}
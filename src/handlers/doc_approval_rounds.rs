@@ -0,0 +1,226 @@
+use crate::{auth::auth, models::{api_error, ApiError, ApprovalRoundView, ApproverResponseView, ColabApprovalRound, ColabApprovalRoundState, DocumentApprovalRoundCancelRequest, DocumentApprovalRoundResponse, DocumentApprovalRoundStartRequest, ListResponse, PageQuery, RequestId}, services::{acl_service, approval_round_service::{self, ApproverResponse}, doc_edit_service, doc_load_service, room_assignment_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use chrono::Utc;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// List the approval rounds of a document
+pub async fn doc_approval_rounds(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<ApprovalRoundView>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    parse_uuid("document", &doc_id)?;
+
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+
+    // Evaluate the responses, rounds with all responses in are completed
+    let mut views = Vec::new();
+    let mut completed = Vec::new();
+    for round in approval_round_service::read_rounds(&loro_doc) {
+        let responses = approval_round_service::approver_responses(&loro_doc, &round);
+        let effective = approval_round_service::effective_round(&round, &responses);
+        if effective.state != round.state {
+            completed.push(effective.clone());
+        }
+        views.push(round_view(&effective, &responses));
+    }
+
+    // Record the completion of rounds in the document
+    if !completed.is_empty() {
+        let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+            for round in &completed {
+                approval_round_service::end_round(doc, round)?;
+            }
+            doc.commit();
+            Ok(())
+        }, false).await;
+        if let Err(e) = result {
+            warn!("Failed to record completed approval rounds for document '{}': {}", doc_id, e);
+        }
+    }
+
+    Ok((StatusCode::OK, Json(ListResponse::page(views, &page, &request_id))))
+}
+
+/// Start an approval round, freezing the given blocks or languages for everyone but the approvers
+pub async fn doc_approval_round_start(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentApprovalRoundStartRequest>,
+) -> Result<(StatusCode, Json<DocumentApprovalRoundResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    parse_uuid("document", &doc_id)?;
+
+    // Only managers of the document can start a round
+    let permissions = document_permissions(&registry, &org_id, &doc_id, &request.by_prpl).await?;
+    if !permissions.contains("manage") {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("'{}' cannot start an approval round on document '{}'", request.by_prpl, doc_id)));
+    }
+
+    // Approvers respond with user approvals, so they must be users
+    if request.approvers.is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "An approval round needs at least one approver"));
+    }
+    let user_prefix = format!("{}/u/", org_id);
+    if let Some(invalid) = request.approvers.iter().find(|a| a.strip_prefix(&user_prefix).and_then(|uid| Uuid::parse_str(uid).ok()).is_none()) {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Approver '{}' is not a user of organization '{}'", invalid, org_id)));
+    }
+
+    // Resolve the paths to freeze
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let scopes = acl_service::collect_acl_scopes(&loro_doc).map_err(|e| {
+        error!("Failed to read document '{}': {}", doc_id, e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read document '{}': {}", doc_id, e))
+    })?;
+    let paths: Vec<String> = match request.paths {
+        Some(paths) => {
+            if let Some(unknown) = paths.iter().find(|p| p.as_str() == "/" || !scopes.iter().any(|s| &s.path == *p)) {
+                return Err(api_error(StatusCode::BAD_REQUEST, format!("No block or language found at path '{}'", unknown)));
+            }
+            paths
+        }
+        None => scopes.iter().filter(|s| s.map.is_some()).map(|s| s.path.clone()).collect(),
+    };
+    if paths.is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "The document has no blocks or languages to approve"));
+    }
+
+    // One round at a time
+    if !approval_round_service::frozen_rounds(&loro_doc).is_empty() {
+        return Err(api_error(StatusCode::CONFLICT, format!("An approval round is already active on document '{}'", doc_id)));
+    }
+
+    // The round refers to the current version of the document
+    let round = ColabApprovalRound {
+        id: Uuid::new_v4(),
+        state: ColabApprovalRoundState::Active,
+        outcome: None,
+        paths,
+        approvers: request.approvers,
+        version: ctx.doc_version,
+        version_v: loro_doc.state_vv().iter().map(|(peer, counter)| (*peer, *counter)).collect(),
+        started_by: request.by_prpl,
+        started_at: Utc::now(),
+        ended_by: None,
+        ended_at: None,
+    };
+    let responses = approval_round_service::approver_responses(&loro_doc, &round);
+    let view = round_view(&round, &responses);
+
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        approval_round_service::add_round(doc, &round)?;
+        doc.commit();
+        Ok(())
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to start approval round on document '{}': {}", doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to start approval round on document '{}': {}", doc_id, e)));
+    }
+
+    Ok((StatusCode::OK, Json(DocumentApprovalRoundResponse { round: view })))
+}
+
+/// Cancel an active approval round, unfreezing its blocks or languages
+pub async fn doc_approval_round_cancel(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id, round_id)): Path<(String, String, String)>,
+    Json(request): Json<DocumentApprovalRoundCancelRequest>,
+) -> Result<(StatusCode, Json<DocumentApprovalRoundResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    parse_uuid("document", &doc_id)?;
+    let round_uuid = parse_uuid("approval round", &round_id)?;
+
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let round = match approval_round_service::find_round(&loro_doc, &round_uuid) {
+        Some(round) => round,
+        None => return Err(api_error(StatusCode::NOT_FOUND, format!("Approval round '{}' not found", round_id))),
+    };
+    let responses = approval_round_service::approver_responses(&loro_doc, &round);
+    let mut round = approval_round_service::effective_round(&round, &responses);
+    if round.state != ColabApprovalRoundState::Active {
+        return Err(api_error(StatusCode::CONFLICT, format!("Approval round '{}' is already {}", round_id, round.state)));
+    }
+
+    // The starter of the round or a manager of the document can cancel it
+    if round.started_by != request.by_prpl {
+        let permissions = document_permissions(&registry, &org_id, &doc_id, &request.by_prpl).await?;
+        if !permissions.contains("manage") {
+            return Err(api_error(StatusCode::FORBIDDEN, format!("'{}' cannot cancel approval round '{}'", request.by_prpl, round_id)));
+        }
+    }
+
+    round.state = ColabApprovalRoundState::Cancelled;
+    round.ended_by = Some(request.by_prpl);
+    round.ended_at = Some(Utc::now());
+    let view = round_view(&round, &responses);
+
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        approval_round_service::end_round(doc, &round)?;
+        doc.commit();
+        Ok(())
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to cancel approval round '{}' on document '{}': {}", round_id, doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to cancel approval round '{}': {}", round_id, e)));
+    }
+
+    Ok((StatusCode::OK, Json(DocumentApprovalRoundResponse { round: view })))
+}
+
+fn parse_uuid(kind: &str, id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|e| {
+        error!("Invalid {} UUID '{}': {}", kind, id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid {} UUID '{}'", kind, id))
+    })
+}
+
+async fn document_permissions(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, by_prpl: &str) -> Result<std::collections::BTreeSet<String>, ApiError> {
+    match acl_service::permissions_on_path(registry, org_id, doc_id, "/", by_prpl).await {
+        Ok(Some(permissions)) => Ok(permissions),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id))),
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}': {}", doc_id, e)))
+        }
+    }
+}
+
+fn round_view(round: &ColabApprovalRound, responses: &[ApproverResponse]) -> ApprovalRoundView {
+    ApprovalRoundView {
+        id: round.id,
+        state: round.state.to_string(),
+        outcome: round.outcome.as_ref().map(|o| o.to_string()),
+        paths: round.paths.clone(),
+        approvers: round.approvers.clone(),
+        version: round.version,
+        version_v: round.version_v.clone(),
+        started_by: round.started_by.clone(),
+        started_at: round.started_at,
+        ended_by: round.ended_by.clone(),
+        ended_at: round.ended_at,
+        responses: responses
+            .iter()
+            .map(|r| ApproverResponseView {
+                approver: r.approver.clone(),
+                path: r.path.clone(),
+                state: r.state.to_string(),
+                date: r.date,
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentBlameResponse}, services::{blame_service, doc_load_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct BlameQuery {
+    version: Option<u32>,
+}
+
+/// Attribute every text run of a block, or statement language, to the change that inserted it
+pub async fn doc_blame(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id, block_id)): Path<(String, String, String)>,
+    Query(query): Query<BlameQuery>,
+) -> Result<(StatusCode, Json<DocumentBlameResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    // 1. Load the requested version, or the latest one
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_version_or_error(&registry, &org_id, &doc_id, query.version).await?;
+
+    // 2. Blame the block
+    let runs = blame_service::blame_block(&loro_doc, &block_id, &ctx.peer_map)
+        .map_err(|e| api_error(StatusCode::NOT_FOUND, e))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentBlameResponse {
+            block_id,
+            version: ctx.doc_version,
+            runs,
+        }),
+    ))
+}
@@ -0,0 +1,65 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentLazyBlocksResponse}, services::{lazy_block_service, room_assignment_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Switch a sheet to lazy block mode, the rows of its statement grids move to block rooms
+pub async fn doc_blocks_split(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentLazyBlocksResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let by_prpl = auth::ensure_service(&prpls, "colabri-app")?;
+    parse_doc_uuid(&doc_id)?;
+
+    let block_ids = lazy_block_service::split(registry, &org_id, &doc_id, &by_prpl).await.map_err(|e| {
+        error!("Failed to split the blocks of document '{}': {}", doc_id, e);
+        api_error(room_assignment_service::misdirected_or(&e, StatusCode::UNPROCESSABLE_ENTITY), format!("Failed to split the blocks of document '{}': {}", doc_id, e))
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentLazyBlocksResponse {
+            block_rooms: block_ids.iter().map(|block_id| lazy_block_service::block_room(&doc_id, block_id)).collect(),
+            doc_id,
+            lazy: true,
+        }),
+    ))
+}
+
+/// Leave lazy block mode, the rows of the block rooms move back into the sheet
+pub async fn doc_blocks_join(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentLazyBlocksResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    parse_doc_uuid(&doc_id)?;
+
+    let block_ids = lazy_block_service::join(registry, &org_id, &doc_id).await.map_err(|e| {
+        error!("Failed to join the blocks of document '{}': {}", doc_id, e);
+        api_error(room_assignment_service::misdirected_or(&e, StatusCode::UNPROCESSABLE_ENTITY), format!("Failed to join the blocks of document '{}': {}", doc_id, e))
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentLazyBlocksResponse {
+            block_rooms: block_ids.iter().map(|block_id| lazy_block_service::block_room(&doc_id, block_id)).collect(),
+            doc_id,
+            lazy: false,
+        }),
+    ))
+}
+
+fn parse_doc_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
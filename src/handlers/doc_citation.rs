@@ -0,0 +1,65 @@
+use crate::{auth::auth, models::{api_error, ApiError, ColabStatementElement, DocumentCitationResponse}, services::{citation_service, doc_load_service}, ws::docctx::DocContext};
+use crate::models::lorodoc::get_doc_type;
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro::ToJson;
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CitationQuery {
+    version: Option<u32>,
+    lang: String,
+}
+
+/// Render a statement language at a specific version for citation
+pub async fn doc_citation(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<CitationQuery>,
+) -> Result<(StatusCode, Json<DocumentCitationResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    // 1. Load the requested version, or the latest one
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_version_or_error(&registry, &org_id, &doc_id, query.version).await?;
+
+    // 2. Find the statement language
+    if get_doc_type(&loro_doc).as_deref() != Some("colab-statement") {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Document '{}' is not a statement", doc_id)));
+    }
+    let lang_json = match loro_doc.get_map("content").get_deep_value().to_json_value().get(&query.lang) {
+        Some(value) => value.clone(),
+        None => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("Language '{}' not found in statement '{}'", query.lang, doc_id)));
+        }
+    };
+    let element: ColabStatementElement = serde_json::from_value(lang_json).map_err(|e| {
+        error!("Failed to parse language '{}' of statement '{}': {}", query.lang, doc_id, e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse language '{}' of statement '{}': {}", query.lang, doc_id, e))
+    })?;
+
+    // 3. Render the text and collect the citation metadata
+    Ok((
+        StatusCode::OK,
+        Json(DocumentCitationResponse {
+            doc_id,
+            version: ctx.doc_version,
+            lang: query.lang,
+            html: citation_service::render_html(&element.text_element),
+            text: citation_service::render_text(&element.text_element),
+            approval_status: citation_service::approval_status(&element.approvals).to_string(),
+            approvers: citation_service::approvers(&org_id, &element.approvals),
+            date: citation_service::approval_date(&element.approvals),
+        }),
+    ))
+}
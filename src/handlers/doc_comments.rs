@@ -0,0 +1,277 @@
+use crate::{auth::auth, models::{api_error, ApiError, ColabComment, ColabCommentState, ColabCommentType, DocumentCommentAddRequest, DocumentCommentEditRequest, DocumentCommentResolveRequest, DocumentCommentResolveResponse, CommentThread, DocumentCommentResponse, ListResponse, PageQuery, RequestId, TextElement}, services::{acl_service, comment_service, doc_edit_service, doc_load_service, feature_service::{self, Feature}, reaction_service, room_assignment_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use chrono::Utc;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CommentsQuery {
+    path: Option<String>,
+}
+
+/// List the comment threads of a document
+pub async fn doc_comments(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<CommentsQuery>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<CommentThread>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Comments).await?;
+    parse_uuid("document", &doc_id)?;
+
+    // Load the latest state of the document
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let scopes = acl_service::collect_acl_scopes(&loro_doc).map_err(|e| {
+        error!("Failed to read document '{}': {}", doc_id, e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read document '{}': {}", doc_id, e))
+    })?;
+
+    // Group the comments of every block and language into threads
+    let mut threads = Vec::new();
+    for scope in &scopes {
+        if query.path.as_deref().is_some_and(|path| path != scope.path) {
+            continue;
+        }
+        if let Some(map) = &scope.map {
+            let mut comments = comment_service::read_comments(map);
+            comment_service::resolve_anchors(&loro_doc, map, &mut comments);
+            let mut scope_threads = comment_service::build_threads(&scope.path, &comments);
+
+            // Attach the reactions on every comment
+            let mut reactions = reaction_service::read_reactions(&scope.path, map);
+            for thread in &mut scope_threads {
+                for view in std::iter::once(&mut thread.root).chain(thread.replies.iter_mut()) {
+                    if let Some(summary) = reactions.iter_mut().find(|summary| summary.comment_id == Some(view.id)) {
+                        view.reactions = std::mem::take(&mut summary.reactions);
+                    }
+                }
+            }
+            threads.extend(scope_threads);
+        }
+    }
+
+    Ok((StatusCode::OK, Json(ListResponse::page(threads, &page, &request_id))))
+}
+
+/// Add a comment, or a reply to an existing comment
+pub async fn doc_comment_add(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentCommentAddRequest>,
+) -> Result<(StatusCode, Json<DocumentCommentResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Comments).await?;
+    parse_uuid("document", &doc_id)?;
+    let text = parse_text(request.text)?;
+
+    // Ensure the author may comment on the path
+    let author = ensure_can_comment(&registry, &org_id, &doc_id, &request.path, &request.by_prpl).await?;
+
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+
+    // A reply must point to a comment on the same path
+    if let Some(parent_id) = request.parent_id {
+        match comment_service::find_comment(&loro_doc, &parent_id) {
+            Ok(Some((path, _))) if path == request.path => {}
+            Ok(_) => {
+                return Err(api_error(StatusCode::NOT_FOUND, format!("Parent comment '{}' not found at path '{}'", parent_id, request.path)));
+            }
+            Err(e) => {
+                error!("Failed to read comments of document '{}': {}", doc_id, e);
+                return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read comments of document '{}': {}", doc_id, e)));
+            }
+        }
+    }
+
+    // Anchor the comment to a range of the text
+    let anchor = match &request.anchor {
+        Some(range) => match comment_service::create_anchor(&loro_doc, &request.path, range.start, range.end) {
+            Ok(anchor) => Some(anchor),
+            Err(e) => return Err(api_error(StatusCode::BAD_REQUEST, e)),
+        },
+        None => None,
+    };
+
+    let comment = ColabComment {
+        id: Uuid::new_v4(),
+        parent_id: request.parent_id,
+        r#type: ColabCommentType::User,
+        state: ColabCommentState::Open,
+        author,
+        text,
+        timestamp: Utc::now(),
+        edited_at: None,
+        anchor,
+    };
+    let view = comment_service::comment_view(&comment);
+
+    // Write the comment into the document
+    let path = request.path;
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        comment_service::add_comment(doc, &path, &comment)?;
+        doc.commit();
+        Ok(())
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to add comment to document '{}': {}", doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to add comment to document '{}': {}", doc_id, e)));
+    }
+
+    Ok((StatusCode::OK, Json(DocumentCommentResponse { comment: view })))
+}
+
+/// Edit the text of an own comment
+pub async fn doc_comment_edit(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id, comment_id)): Path<(String, String, String)>,
+    Json(request): Json<DocumentCommentEditRequest>,
+) -> Result<(StatusCode, Json<DocumentCommentResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Comments).await?;
+    parse_uuid("document", &doc_id)?;
+    let comment_uuid = parse_uuid("comment", &comment_id)?;
+    let text = parse_text(request.text)?;
+
+    // Find the comment and the path it lives on
+    let (path, comments) = find_comment_or_error(&registry, &org_id, &doc_id, &comment_uuid).await?;
+    let author = ensure_can_comment(&registry, &org_id, &doc_id, &path, &request.by_prpl).await?;
+
+    // Only the author can edit a comment
+    let mut comment = match comments.into_iter().find(|c| c.id == comment_uuid) {
+        Some(comment) => comment,
+        None => return Err(api_error(StatusCode::NOT_FOUND, format!("Comment '{}' not found", comment_id))),
+    };
+    if comment.author != author {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("Only the author can edit comment '{}'", comment_id)));
+    }
+
+    let edited_at = Utc::now();
+    comment.text = text.clone();
+    comment.edited_at = Some(edited_at);
+    let view = comment_service::comment_view(&comment);
+
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        comment_service::edit_comment_text(doc, &path, &comment_uuid, &text, &edited_at)?;
+        doc.commit();
+        Ok(())
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to edit comment '{}' of document '{}': {}", comment_id, doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to edit comment '{}': {}", comment_id, e)));
+    }
+
+    Ok((StatusCode::OK, Json(DocumentCommentResponse { comment: view })))
+}
+
+/// Resolve (or reopen) the thread a comment belongs to
+pub async fn doc_comment_resolve(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id, comment_id)): Path<(String, String, String)>,
+    Json(request): Json<DocumentCommentResolveRequest>,
+) -> Result<(StatusCode, Json<DocumentCommentResolveResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Comments).await?;
+    parse_uuid("document", &doc_id)?;
+    let comment_uuid = parse_uuid("comment", &comment_id)?;
+
+    // Find the comment and the path it lives on
+    let (path, comments) = find_comment_or_error(&registry, &org_id, &doc_id, &comment_uuid).await?;
+    let _ = ensure_can_comment(&registry, &org_id, &doc_id, &path, &request.by_prpl).await?;
+
+    // The state applies to the root and all its replies
+    let root_id = comment_service::thread_root_id(&comments, comment_uuid);
+    let state = if request.resolved.unwrap_or(true) { ColabCommentState::Resolved } else { ColabCommentState::Open };
+    let updated = comments
+        .iter()
+        .filter(|c| comment_service::thread_root_id(&comments, c.id) == root_id && c.state != state)
+        .count() as u32;
+
+    if updated > 0 {
+        let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+            comment_service::set_thread_state(doc, &path, &root_id, &state)?;
+            doc.commit();
+            Ok(())
+        }, false).await;
+        if let Err(e) = result {
+            error!("Failed to update thread of comment '{}' in document '{}': {}", comment_id, doc_id, e);
+            return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to update thread of comment '{}': {}", comment_id, e)));
+        }
+    }
+
+    Ok((StatusCode::OK, Json(DocumentCommentResolveResponse { success: true, updated })))
+}
+
+fn parse_uuid(kind: &str, id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|e| {
+        error!("Invalid {} UUID '{}': {}", kind, id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid {} UUID '{}'", kind, id))
+    })
+}
+
+fn parse_text(text: serde_json::Value) -> Result<TextElement, ApiError> {
+    serde_json::from_value(text).map_err(|e| {
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid comment text: {}", e))
+    })
+}
+
+async fn find_comment_or_error(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, comment_id: &Uuid) -> Result<(String, Vec<ColabComment>), ApiError> {
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(registry, org_id, doc_id).await?;
+    match comment_service::find_comment(&loro_doc, comment_id) {
+        Ok(Some(found)) => Ok(found),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Comment '{}' not found in document '{}'", comment_id, doc_id))),
+        Err(e) => {
+            error!("Failed to read comments of document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read comments of document '{}': {}", doc_id, e)))
+        }
+    }
+}
+
+// Ensure `by_prpl` is a user of the organization with at least some permission on `path`.
+// Returns the user ID, which is the author of the comment.
+async fn ensure_can_comment(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, path: &str, by_prpl: &str) -> Result<Uuid, ApiError> {
+
+    // Comments are always authored by a user
+    let author = by_prpl
+        .strip_prefix(&format!("{}/u/", org_id))
+        .and_then(|uid| Uuid::parse_str(uid).ok())
+        .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, format!("'{}' is not a user of organization '{}'", by_prpl, org_id)))?;
+
+    // Comments live on blocks and languages, not on the document itself
+    if path == "/" {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Comments can only be placed on a block or language"));
+    }
+
+    let permissions = match acl_service::permissions_on_path(registry, org_id, doc_id, path, by_prpl).await {
+        Ok(Some(permissions)) => permissions,
+        Ok(None) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("No block or language found at path '{}' in document '{}'", path, doc_id)));
+        }
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}': {}", doc_id, e)));
+        }
+    };
+    if permissions.is_empty() {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("'{}' has no access to '{}'", by_prpl, path)));
+    }
+
+    Ok(author)
+}
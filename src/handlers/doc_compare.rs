@@ -0,0 +1,43 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentCompareRequest, DocumentCompareResponse}, services::{compare_service, doc_load_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro::ToJson;
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Compare the latest state of a document with an external model of it
+pub async fn doc_compare(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentCompareRequest>,
+) -> Result<(StatusCode, Json<DocumentCompareResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    let doc_uuid = Uuid::parse_str(&doc_id).map_err(|e| {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })?;
+
+    // 1. Load the latest state
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let json = loro_doc.get_deep_value().to_json_value();
+
+    // 2. Compare the texts of the blocks and languages
+    let (unchanged, differences) = match compare_service::compare(&org_id, doc_uuid, json, &request.model).await {
+        Ok(Ok(compared)) => compared,
+        Ok(Err(e)) => return Err(api_error(StatusCode::BAD_REQUEST, e)),
+        Err(e) => {
+            error!("Failed to compare document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to compare document '{}': {}", doc_id, e)));
+        }
+    };
+    Ok((StatusCode::OK, Json(DocumentCompareResponse {
+        doc_id,
+        identical: differences.is_empty(),
+        unchanged,
+        differences,
+    })))
+}
@@ -0,0 +1,49 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentCreateRequest, DocumentCreateResponse}, services::doc_create_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Create a document and reserve its id before the first websocket connection
+pub async fn doc_create(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Json(request): Json<DocumentCreateRequest>,
+) -> Result<(StatusCode, Json<DocumentCreateResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    if request.name.trim().is_empty() || request.owner.trim().is_empty() || request.content_type.trim().is_empty() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "A document needs a name, an owner and a content type".to_string()));
+    }
+    let container = parse_uuid("container", request.container.as_deref())?;
+    let template = parse_uuid("template", request.template.as_deref())?;
+
+    let doc_uuid = Uuid::new_v4();
+    match doc_create_service::create(&registry, &org_id, doc_uuid, container, template, &request).await {
+        Ok(Ok(())) => Ok((StatusCode::CREATED, Json(DocumentCreateResponse {
+            id: doc_uuid.to_string(),
+            doc_type: request.doc_type,
+            content_type: request.content_type,
+            version: 1,
+        }))),
+        Ok(Err(e)) => {
+            warn!("Refusing to create document in organization '{}': {}", org_id, e);
+            Err(api_error(StatusCode::BAD_REQUEST, e))
+        }
+        Err(e) => {
+            error!("{}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+fn parse_uuid(field: &str, value: Option<&str>) -> Result<Option<Uuid>, ApiError> {
+    value.map(|value| Uuid::parse_str(value).map_err(|e| {
+        warn!("Invalid {} UUID '{}': {}", field, value, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid {} UUID '{}'", field, value))
+    })).transpose()
+}
@@ -0,0 +1,70 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentCsvImportRequest, DocumentCsvImportResponse}, services::{csv_service, doc_edit_service, limits_service, room_assignment_service, sheet_import_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Import a CSV as a new statement-grid or attributes block of a sheet
+pub async fn doc_csv_import(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentCsvImportRequest>,
+) -> Result<(StatusCode, Json<DocumentCsvImportResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    // The delimiter is a single character, anything else is refused instead of truncated
+    let delimiter = match request.delimiter.as_deref() {
+        None => ',',
+        Some(delimiter) => {
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid delimiter '{}', expected a single character", delimiter))),
+            }
+        }
+    };
+
+    // 1. Build the block from the CSV and the column mapping
+    let rows = csv_service::parse_csv(&request.csv, delimiter)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, format!("Invalid CSV: {}", e)))?;
+    let title = request.title.clone().unwrap_or_default();
+    let imported = sheet_import_service::build_block(&request.block_type, &title, &rows, &request.mapping)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+
+    // 2. Insert it into the sheet
+    let block_id = Uuid::new_v4().to_string();
+    let limits = limits_service::get_limits(&org_id).await;
+    let block = imported.block;
+    let position = request.position;
+    let new_block_id = block_id.clone();
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        sheet_import_service::insert_block(doc, &block, &new_block_id, position, &limits)?;
+        doc.commit();
+        Ok(())
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to import CSV into document '{}': {}", doc_id, e);
+        return Err(api_error(room_assignment_service::misdirected_or(&e, StatusCode::UNPROCESSABLE_ENTITY), format!("Failed to import CSV into document '{}': {}", doc_id, e)));
+    }
+    info!("Imported {} rows from CSV as {} block '{}' of document '{}'", imported.rows, request.block_type, block_id, doc_id);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(DocumentCsvImportResponse {
+            block_id,
+            block_type: request.block_type,
+            rows: imported.rows,
+            skipped: imported.skipped,
+        }),
+    ))
+}
@@ -1,7 +1,8 @@
 use crate::{
     auth::auth,
     db::dbcolab,
-    models::{DocumentDeleteRequest, DocumentDeleteResponse, ErrorResponse},
+    models::{DocumentDeleteRequest, DocumentDeleteResponse, DocumentLink, ErrorResponse},
+    services::{acl_cache_service, link_index_service},
     ws::docctx::DocContext,
 };
 use axum::{
@@ -12,7 +13,7 @@ use axum::{
 use loro_protocol::CrdtType;
 use loro_websocket_server::HubRegistry;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 /// Delete a document by marking it deleted in the DB and force closing the room
@@ -61,9 +62,47 @@ pub async fn doc_delete(
         }
     };
 
+    // Refuse to break the documents referencing this one, unless forced
+    if !request.force {
+        let backlinks = match link_index_service::incoming(&org_id, doc_uuid).await {
+            Ok(backlinks) => backlinks,
+            Err(e) => {
+                error!("{}", e);
+                let status = StatusCode::INTERNAL_SERVER_ERROR;
+                return Err((
+                    status,
+                    Json(ErrorResponse {
+                        code: status.as_u16(),
+                        status: status.to_string(),
+                        error: e,
+                    }),
+                ));
+            }
+        };
+        if !backlinks.is_empty() {
+            warn!("Not deleting document '{}', it is referenced by {} other document(s)", doc_id, backlinks.len());
+            let referenced_by = backlinks
+                .into_iter()
+                .map(|row| DocumentLink {
+                    doc_id: row.document.to_string(),
+                    name: row.name,
+                    kind: row.kind,
+                    path: row.path,
+                })
+                .collect();
+            return Ok((
+                StatusCode::CONFLICT,
+                Json(DocumentDeleteResponse { success: false, referenced_by }),
+            ));
+        }
+    }
+
     // Mark document as deleted
     match db.delete_colab_doc(&org_id, &doc_uuid, &by_prpl).await {
-        Ok(_) => info!("Document '{}' marked as deleted", doc_id),
+        Ok(_) => {
+            info!("Document '{}' marked as deleted", doc_id);
+            acl_cache_service::invalidate_document(&org_id, doc_uuid);
+        }
         Err(e) => {
             error!("Failed to delete document '{}': {}", doc_id, e);
             let status = StatusCode::INTERNAL_SERVER_ERROR;
@@ -89,6 +128,6 @@ pub async fn doc_delete(
 
     Ok((
         StatusCode::OK,
-        Json(DocumentDeleteResponse { success: true }),
+        Json(DocumentDeleteResponse { success: true, referenced_by: Vec::new() }),
     ))
 }
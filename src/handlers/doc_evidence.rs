@@ -0,0 +1,72 @@
+use crate::{auth::auth, db::dbcolab, models::{api_error, ApiError}, services::{analytics_service, doc_load_service, evidence_service, negotiation_service::{self, Representation}}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::{HeaderMap, StatusCode}, response::Response};
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct EvidenceQuery {
+    version: Option<u32>,
+    prpl: Option<String>,
+}
+
+/// Produce an evidence bundle (zip) of a document version
+pub async fn doc_evidence(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<EvidenceQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    negotiation_service::negotiate(&headers, &[Representation::Zip])?;
+
+    let doc_uuid = match Uuid::parse_str(&doc_id) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            error!("Invalid document UUID '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+        }
+    };
+
+    // 1. Load the requested version, or the latest one
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_version_or_error(&registry, &org_id, &doc_id, query.version).await?;
+
+    // 2. Get the owner and the ACL rows from the database
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, "Database not initialized")),
+    };
+    let access = match db.get_document_access(&org_id, doc_uuid).await {
+        Ok(Some(access)) => access,
+        Ok(None) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id)));
+        }
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}': {}", doc_id, e)));
+        }
+    };
+
+    // 3. Collect the evidence, write the manifest and pack everything
+    let by_prpl = query.prpl.unwrap_or_else(|| "s/colabri-app".to_string());
+    let bundle = evidence_service::collect_files(&org_id, &loro_doc, &ctx, &access).and_then(|files| {
+        let manifest = evidence_service::build_manifest(&org_id, &doc_id, &loro_doc, &ctx, &by_prpl, &files);
+        evidence_service::zip_bundle(&files, &manifest)
+    });
+    let bundle = match bundle {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            error!("Failed to build evidence bundle for document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to build evidence bundle for document '{}': {}", doc_id, e)));
+        }
+    };
+    info!("Built evidence bundle for document '{}' version {} for '{}'", doc_id, ctx.doc_version, by_prpl);
+    analytics_service::record_export(&org_id, doc_uuid, "evidence", &by_prpl);
+
+    Ok(evidence_service::zip_response(&format!("evidence-{}-v{}.zip", doc_id, ctx.doc_version), bundle))
+}
@@ -0,0 +1,60 @@
+use crate::{db::dbcolab, models::{api_error, ApiError, DocumentFollowRequest, DocumentFollowResponse}, services::{digest_service, doc_load_service}, ws::docctx::DocContext};
+use crate::handlers::doc_user_prefs::ensure_reader;
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::error;
+
+/// Follow a document, its changes are sent in the daily or weekly digest of the calling user
+pub async fn doc_follow(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentFollowRequest>,
+) -> Result<(StatusCode, Json<DocumentFollowResponse>), ApiError> {
+    let (doc_uuid, prpl) = ensure_reader(&prpls, &org_id, &doc_id).await?;
+
+    let frequency = request.frequency.unwrap_or_else(|| digest_service::FREQUENCIES[0].to_string());
+    if !digest_service::FREQUENCIES.contains(&frequency.as_str()) {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Unknown digest frequency '{}', expected one of {:?}", frequency, digest_service::FREQUENCIES)));
+    }
+
+    // The first digest lists the changes from now on
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, "Database not initialized")),
+    };
+    match db.follow_document(&org_id, doc_uuid, &prpl, &frequency, digest_service::state_vv(&loro_doc)).await {
+        Ok(row) => Ok((StatusCode::OK, Json(DocumentFollowResponse {
+            doc_id,
+            prpl,
+            following: true,
+            frequency: Some(row.frequency),
+        }))),
+        Err(e) => {
+            error!("Failed to follow document '{}' for '{}': {}", doc_id, prpl, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to follow document '{}'", doc_id)))
+        }
+    }
+}
+
+/// Stop following a document
+pub async fn doc_unfollow(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentFollowResponse>), ApiError> {
+    let (doc_uuid, prpl) = ensure_reader(&prpls, &org_id, &doc_id).await?;
+
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, "Database not initialized")),
+    };
+    match db.unfollow_document(&org_id, doc_uuid, &prpl).await {
+        Ok(_) => Ok((StatusCode::OK, Json(DocumentFollowResponse { doc_id, prpl, following: false, frequency: None }))),
+        Err(e) => {
+            error!("Failed to unfollow document '{}' for '{}': {}", doc_id, prpl, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to unfollow document '{}'", doc_id)))
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{auth::auth, models::{api_error, ApiError}, models::lorodoc::get_string, services::{acl_service, analytics_service, csv_service, doc_load_service, grid_export_service, lazy_block_service, locale_service, negotiation_service::{self, Representation}, statement_subdoc_service, transclusion_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::{HeaderMap, StatusCode}, response::Response};
+use loro::ToJson;
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use serde_json::json;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct GridExportQuery {
+    version: Option<u32>,
+}
+
+/// Export a statement-grid block as CSV, one row per statement language
+pub async fn doc_grid_export(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id, block_id)): Path<(String, String, String)>,
+    Query(query): Query<GridExportQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+
+    // Ensure the caller is a trusted service
+    let by_prpl = auth::ensure_service(&prpls, "colabri-app")?;
+    negotiation_service::negotiate(&headers, &[Representation::Csv])?;
+
+    let doc_uuid = Uuid::parse_str(&doc_id).map_err(|e| {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })?;
+
+    // 1. Load the requested version, or the latest one
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_version_or_error(&registry, &org_id, &doc_id, query.version).await?;
+
+    // 2. Find the statement grid
+    let mut block = acl_service::find_scope_map(&loro_doc, &format!("/content/{}", block_id))
+        .map_err(|e| api_error(StatusCode::NOT_FOUND, e))?
+        .get_deep_value()
+        .to_json_value();
+
+    // 3. Compose the rows of lazy blocks and the linked statements, which are stored apart.
+    // A transcluded grid is exported with the latest content of its source block, which comes composed.
+    let links = transclusion_service::links(&json!({ "content": [block.clone()] }));
+    if let Some((_, source, source_block)) = links.first() {
+        block = transclusion_service::source_block(&registry, &org_id, *source, source_block)
+            .await
+            .map_err(|e| {
+                error!("{}", e);
+                api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+            })?
+            .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("Source block '{}' of document '{}' not found", source_block, source)))?;
+    } else {
+        let mut sheet = json!({ "content": [block] });
+        let composed = match lazy_block_service::assemble_json(&org_id, doc_uuid, &mut sheet).await {
+            Ok(()) => statement_subdoc_service::compose_json(&org_id, &mut sheet).await,
+            Err(e) => Err(e),
+        };
+        composed.map_err(|e| {
+            error!("{}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+        })?;
+        block = sheet["content"][0].take();
+    }
+    if block.get("type").and_then(|t| t.as_str()) != Some("statement-grid") {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Block '{}' is not a statement grid", block_id)));
+    }
+
+    // 4. Flatten the rows, resolving the referenced statements, with the dates in the locale of the sheet
+    let content_type = get_string(&loro_doc.get_map("properties"), "contentType");
+    let locale = locale_service::resolve(&org_id, content_type.as_deref()).await;
+    let rows = grid_export_service::statement_grid_rows(&registry, &org_id, &doc_id, ctx.doc_version, &block_id, &block, &locale).await;
+    let csv = csv_service::to_csv(&grid_export_service::CSV_HEADER, &rows);
+    analytics_service::record_export(&org_id, doc_uuid, "statement-grid-csv", &by_prpl);
+    Ok(csv_service::csv_response(&format!("statement-grid-{}-{}.csv", doc_id, block_id), csv))
+}
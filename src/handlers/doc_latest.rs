@@ -1,9 +1,9 @@
-use crate::{auth::auth, models::{DocumentLatestResponse, ErrorResponse}, ws::docctx::DocContext};
-use axum::{extract::{State, Path, Extension, Query}, http::StatusCode, Json};
+use crate::{auth::auth, models::{api_error, DocumentChangesResponse, DocumentLatestResponse, ErrorResponse}, ws::docctx::DocContext};
+use axum::{extract::{State, Path, Extension, Query}, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}, Json};
 use base64::{engine::general_purpose, Engine as _};
-use loro_protocol::CrdtType;
-use loro_websocket_server::{HubRegistry, RoomKey};
-use std::sync::Arc;
+use loro_websocket_server::HubRegistry;
+use crate::services::{doc_changes_service::{self, Since}, doc_load_service, hub_service, negotiation_service::{self, Representation}, quarantine_service, range_service, render_service, version_vector_service};
+use std::{collections::HashMap, sync::Arc};
 use tracing::error;
 use loro::{ToJson, LoroDoc};
 use serde::{Deserialize, Serialize};
@@ -12,6 +12,9 @@ use uuid::Uuid;
 #[derive(Deserialize)]
 pub struct OutputFormatQuery {
     format: Option<String>,
+    since_version: Option<u32>,
+    // Version vector as JSON, keyed by peer id or principal
+    since_vv: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -49,7 +52,12 @@ pub async fn doc_latest(
     Extension(prpls): Extension<Vec<String>>,
     Path((org_id, doc_id)): Path<(String, String)>,
     Query(query): Query<OutputFormatQuery>,
-) -> Result<(StatusCode, Json<DocumentLatestResponse>), (StatusCode, Json<ErrorResponse>)> {
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+
+    // Pick the representation from the Accept header, the format param shapes the JSON envelope
+    let representation = negotiation_service::negotiate(&headers, &negotiation_service::DOCUMENT_REPRESENTATIONS)?;
+    negotiation_service::ensure_format_applies(representation, query.format.as_deref())?;
 
     let output_format = match OutputFormat::from_query(query.format) {
         Ok(format) => format,
@@ -67,7 +75,7 @@ pub async fn doc_latest(
     let _ = auth::ensure_service(&prpls, "colabri-app")?;
 
     // Parse the doc_id as an UUID
-    let _doc_uuid = match Uuid::parse_str(&doc_id) {
+    let doc_uuid = match Uuid::parse_str(&doc_id) {
         Ok(uuid) => uuid,
         Err(e) => {
             error!("Invalid document UUID '{}': {}", doc_id, e);
@@ -80,27 +88,40 @@ pub async fn doc_latest(
         }
     };
 
-    // Try to get data from memory (Hub)
-    let mem_data = {
-        let hubs = registry.hubs().lock().await;
-        if let Some(hub) = hubs.get(&org_id) {
-            let h = hub.lock().await;
-            if let Some(doc_state) = h.docs.get(&RoomKey {crdt: CrdtType::Loro, room: doc_id.clone()}) {
-                if let (Some(loro_doc), Some(ctx)) = (doc_state.doc.get_loro_doc(), &doc_state.ctx) {
-                    let (json, binary_str, version_v, peer_map) = build_doc_payload(&loro_doc, &ctx.peer_map, &doc_id, output_format)?;
-                    Some((json, binary_str, version_v, peer_map, ctx.doc_version.clone()))
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        } else {
-            None
+    // Polling integrations only get the blocks that changed since the state they know
+    let since = match (query.since_version, query.since_vv) {
+        (Some(_), Some(_)) => return Err(api_error(StatusCode::BAD_REQUEST, "Use either since_version or since_vv, not both")),
+        (Some(version), None) => Some(Since::Version(version)),
+        (None, Some(vv)) => match serde_json::from_str::<HashMap<String, i32>>(&vv) {
+            Ok(vv) => Some(Since::VersionVector(vv)),
+            Err(e) => return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid since_vv, expected a JSON object of counters: {}", e))),
+        },
+        (None, None) => None,
+    };
+    if let Some(since) = since {
+        if representation != Representation::Json {
+            return Err(api_error(StatusCode::NOT_ACCEPTABLE, "Changes since an earlier state are only answered as application/json"));
+        }
+        return changes_since(&registry, &org_id, &doc_id, &since).await;
+    }
+
+    // Try to get data from memory (Hub), the payload is built after the hub locks are released
+    // but before edits by this crate may touch the document again
+    let doc_read = hub_service::read_doc(&org_id, &doc_id).await;
+    let mem_data = match hub_service::get_open_doc_handle(&registry, &org_id, &doc_id).await {
+        Some((loro_doc, _)) if representation != Representation::Json => {
+            return other_representation(&registry, &org_id, doc_uuid, &loro_doc, representation, &headers).await;
+        }
+        Some((loro_doc, ctx)) => {
+            let (json, binary_str, version_v, peer_map) = build_doc_payload(&loro_doc, &ctx.peer_map, &doc_id, output_format)?;
+            let version_v_named = version_vector_service::named(&loro_doc.state_vv(), &ctx.peer_map);
+            Some((json, binary_str, version_v, version_v_named, peer_map, ctx.doc_version, ctx.tier))
         }
+        None => None,
     };
+    drop(doc_read);
 
-    if let Some((json, binary_str, version_v, peer_map, doc_version)) = mem_data {
+    if let Some((json, binary_str, version_v, version_v_named, peer_map, doc_version, tier)) = mem_data {
         return Ok((
             StatusCode::OK,
             Json(DocumentLatestResponse {
@@ -108,13 +129,15 @@ pub async fn doc_latest(
                 binary: binary_str,
                 version: doc_version,
                 version_v,
+                version_v_named,
                 peer_map,
+                tier,
             }),
-        ));
+        ).into_response());
     }
 
     // If not found in memory, try to load from database
-    let (snapshot, ctx) = match crate::services::doc_db_service::fetch_doc_snapshot_from_db(&org_id, &doc_id, None).await {
+    let (snapshot, ctx) = match quarantine_service::fetch_doc_snapshot(&org_id, &doc_id, None).await {
         Ok(Some(res)) => res,
         Ok(None) => {
             error!("Document '{}' not found in organization '{}'", doc_id, org_id);
@@ -127,7 +150,7 @@ pub async fn doc_latest(
         },
         Err(e) => {
             error!("Error loading document '{}' from database: {}", doc_id, e);
-            let status = StatusCode::INTERNAL_SERVER_ERROR;
+            let status = quarantine_service::load_error_status(&e);
             return Err((status, Json(ErrorResponse {
                 code: status.as_u16(),
                 status: status.to_string(),
@@ -148,7 +171,12 @@ pub async fn doc_latest(
         }))
     })?;
 
+    if representation != Representation::Json {
+        return other_representation(&registry, &org_id, doc_uuid, &loro_doc, representation, &headers).await;
+    }
+
     let (json, binary_str, state_vv_json, peer_map_json) = build_doc_payload(&loro_doc, &ctx.peer_map, &doc_id, output_format)?;
+    let version_v_named = version_vector_service::named(&loro_doc.state_vv(), &ctx.peer_map);
 
     Ok((
         StatusCode::OK,
@@ -157,9 +185,68 @@ pub async fn doc_latest(
             binary: binary_str,
             version: ctx.doc_version,
             version_v: state_vv_json,
+            version_v_named,
             peer_map: peer_map_json,
+            tier: ctx.tier,
+        }),
+    ).into_response())
+}
+
+// The blocks that changed since an earlier state with the latest version vector, 204 when nothing changed
+async fn changes_since(
+    registry: &Arc<HubRegistry<DocContext>>,
+    org_id: &str,
+    doc_id: &str,
+    since: &Since,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_or_error(registry, org_id, doc_id).await?;
+    let changes = match doc_changes_service::changes_since(registry, org_id, doc_id, &loro_doc, &ctx.peer_map, since).await {
+        Ok(Ok(Some(changes))) => changes,
+        Ok(Ok(None)) => return Ok(StatusCode::NO_CONTENT.into_response()),
+        Ok(Err(message)) => return Err(api_error(StatusCode::BAD_REQUEST, message)),
+        Err(e) => {
+            error!("Failed to compare document '{}' with an earlier state: {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to compare document '{}' with an earlier state", doc_id)));
+        }
+    };
+    let state_vv = loro_doc.state_vv();
+    let version_v = serde_json::to_value(&state_vv).map_err(|e| {
+        error!("Failed to serialize state_vv for document '{}': {}", doc_id, e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize state_vv for document '{}': {}", doc_id, e))
+    })?;
+    Ok((
+        StatusCode::OK,
+        Json(DocumentChangesResponse {
+            version: ctx.doc_version,
+            version_v,
+            version_v_named: version_vector_service::named(&state_vv, &ctx.peer_map),
+            changed_blocks: changes.changed,
+            removed_blocks: changes.removed,
         }),
-    ))
+    ).into_response())
+}
+
+// The latest state of a document as the raw snapshot or rendered for reading
+async fn other_representation(
+    registry: &Arc<HubRegistry<DocContext>>,
+    org_id: &str,
+    doc_uuid: Uuid,
+    loro_doc: &LoroDoc,
+    representation: Representation,
+    headers: &HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    if representation == Representation::Binary {
+        let snapshot = loro_doc.export(loro::ExportMode::state_only(None)).map_err(|e| {
+            error!("Failed to export latest state for document '{}' to binary: {}", doc_uuid, e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to export latest state for document '{}' to binary", doc_uuid))
+        })?;
+        return Ok(range_service::bytes_response(headers, snapshot, representation.content_type()));
+    }
+    let json = loro_doc.get_deep_value().to_json_value();
+    render_service::response(registry, org_id, doc_uuid, json, representation).await.map_err(|e| {
+        error!("Failed to render document '{}': {}", doc_uuid, e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to render document '{}' as {}", doc_uuid, representation.media_type()))
+    })
 }
 
 fn build_doc_payload<P>(
@@ -0,0 +1,60 @@
+use crate::{auth::auth, db::dbcolab::DocumentLinkRow, models::{api_error, ApiError, DocumentLink, ListResponse, PageQuery, RequestId}, services::link_index_service};
+use axum::{extract::{Extension, Path, Query}, http::StatusCode, Json};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// List the documents a document references
+pub async fn doc_links(
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<DocumentLink>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let rows = link_index_service::outgoing(&org_id, doc_uuid).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    let links: Vec<DocumentLink> = rows.into_iter().map(|row| to_link(row.target, row)).collect();
+    Ok((StatusCode::OK, Json(ListResponse::page(links, &page, &request_id))))
+}
+
+/// List the documents referencing a document
+pub async fn doc_backlinks(
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<DocumentLink>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let rows = link_index_service::incoming(&org_id, doc_uuid).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    let links: Vec<DocumentLink> = rows.into_iter().map(|row| to_link(row.document, row)).collect();
+    Ok((StatusCode::OK, Json(ListResponse::page(links, &page, &request_id))))
+}
+
+fn parse_doc_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
+
+fn to_link(other: Uuid, row: DocumentLinkRow) -> DocumentLink {
+    DocumentLink {
+        doc_id: other.to_string(),
+        name: row.name,
+        kind: row.kind,
+        path: row.path,
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{auth::auth, db::dbcolab, models::{api_error, ApiError, DocumentNumberResponse}, services::numbering_service};
+use axum::{extract::{Extension, Path}, http::StatusCode, Json};
+use tracing::error;
+
+/// Look up the document or block carrying a number
+pub async fn doc_number(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, number)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentNumberResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, "Database not initialized")),
+    };
+    let number = numbering_service::normalize(&number);
+    let row = match db.get_document_number(&org_id, &number).await {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("Number '{}' not found in organization '{}'", number, org_id)));
+        }
+        Err(e) => {
+            error!("Failed to look up number '{}': {}", number, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to look up number '{}': {}", number, e)));
+        }
+    };
+    Ok((StatusCode::OK, Json(DocumentNumberResponse {
+        number: row.number,
+        doc_id: row.document.to_string(),
+        block_id: Some(row.block_id).filter(|block_id| !block_id.is_empty()),
+        created_at: row.created_at,
+        created_by: row.created_by,
+    })))
+}
@@ -0,0 +1,81 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentPermissionsResponse, PermissionExplanation, PermissionGrant, ScopePermissions}, services::acl_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct PermissionsQuery {
+    prpl: String,
+}
+
+/// Explain the effective permissions of a principal on a document
+pub async fn doc_permissions(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<PermissionsQuery>,
+) -> Result<(StatusCode, Json<DocumentPermissionsResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    // Parse the doc_id as an UUID
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        error!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    // Get the database ACL rows and the Loro ACL scopes
+    let (access, scopes) = match acl_service::load_document_acls(&registry, &org_id, &doc_id).await {
+        Ok(Some(res)) => res,
+        Ok(None) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id)));
+        }
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}': {}", doc_id, e)));
+        }
+    };
+
+    // Expand the principal (e.g. a user gets its groups and roles)
+    let principals = acl_service::resolve_principals(&org_id, &query.prpl).await;
+
+    // Explain every permission on every scope
+    let mut scope_permissions = Vec::with_capacity(scopes.len());
+    for (idx, scope) in scopes.iter().enumerate() {
+        let grants = acl_service::collect_scope_grants(&scopes, idx, &org_id, &access);
+        let permissions = acl_service::ALL_PERMISSIONS
+            .iter()
+            .map(|permission| {
+                let permission = permission.to_string();
+                let matching: Vec<PermissionGrant> = grants
+                    .iter()
+                    .filter(|g| g.permission == permission && principals.contains(&g.prpl))
+                    .cloned()
+                    .collect();
+                PermissionExplanation {
+                    granted: !matching.is_empty(),
+                    permission,
+                    grants: matching,
+                }
+            })
+            .collect();
+        scope_permissions.push(ScopePermissions {
+            path: scope.path.clone(),
+            level: scope.level.to_string(),
+            permissions,
+        });
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentPermissionsResponse {
+            prpl: query.prpl,
+            principals,
+            scopes: scope_permissions,
+        }),
+    ))
+}
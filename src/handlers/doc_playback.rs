@@ -0,0 +1,72 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentPlaybackResponse}, services::{doc_load_service, playback_service::{self, PlaybackFormat, PlaybackRange}}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro::VersionVector;
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct PlaybackQuery {
+    version: Option<u32>,
+    // JSON encoded version vectors, e.g. {"123": 42}
+    from_vv: Option<String>,
+    to_vv: Option<String>,
+    step: Option<usize>,
+    format: Option<String>,
+}
+
+/// Replay the edits of a document as a sequence of intermediate states or diffs
+pub async fn doc_playback(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<PlaybackQuery>,
+) -> Result<(StatusCode, Json<DocumentPlaybackResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+    let format = PlaybackFormat::from_query(query.format.as_deref())
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+
+    // 1. Load the requested version, or the latest one
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_version_or_error(&registry, &org_id, &doc_id, query.version).await?;
+
+    // 2. Resolve the range, by default the whole history
+    let from = match &query.from_vv {
+        Some(vv) => parse_vv(vv)?,
+        None => VersionVector::default(),
+    };
+    let to = match &query.to_vv {
+        Some(vv) => parse_vv(vv)?,
+        None => loro_doc.oplog_vv(),
+    };
+    let range = PlaybackRange {
+        from,
+        to,
+        step: query.step.unwrap_or(1),
+        format,
+    };
+
+    // 3. Replay
+    let cache_key = format!("{}/{}@{}", org_id, doc_id, ctx.doc_version);
+    match playback_service::playback(&cache_key, &loro_doc, &ctx.peer_map, ctx.doc_version, &range) {
+        Ok(response) => Ok((StatusCode::OK, Json(response.as_ref().clone()))),
+        Err(e) => {
+            error!("Failed to replay document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::BAD_REQUEST, e))
+        }
+    }
+}
+
+fn parse_vv(vv: &str) -> Result<VersionVector, ApiError> {
+    let entries: HashMap<u64, i32> = serde_json::from_str(vv)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, format!("Invalid version vector '{}': {}", vv, e)))?;
+    Ok(VersionVector::from_iter(entries))
+}
@@ -0,0 +1,90 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentPolicyReviewRequest, DocumentPolicyReviewResponse, ListResponse, PageQuery, PolicyFinding, RequestId}, services::{doc_settings_service, policy_scan_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct PolicyFindingsQuery {
+    #[serde(rename = "includeResolved")]
+    include_resolved: Option<bool>,
+}
+
+/// List the policy findings of a document, its maintenance hold is part of its settings
+pub async fn doc_policy_findings(
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<PolicyFindingsQuery>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<PolicyFinding>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let internal_error = |e: String| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    };
+
+    // Unknown documents have no settings
+    doc_settings_service::get_settings(&org_id, doc_uuid)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found", doc_id)))?;
+    let rows = policy_scan_service::findings(&org_id, doc_uuid, query.include_resolved.unwrap_or(false))
+        .await
+        .map_err(internal_error)?;
+
+    let findings = rows
+        .into_iter()
+        .map(|row| PolicyFinding {
+            id: row.id.to_string(),
+            block_path: row.block_path,
+            category: row.category,
+            severity: row.severity,
+            rule: row.rule,
+            excerpt: row.excerpt,
+            created_at: row.created_at,
+            resolved_at: row.resolved_at,
+            resolved_by: row.resolved_by,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(ListResponse::page(findings, &page, &request_id))))
+}
+
+/// Resolve the open policy findings of a document and lift its maintenance hold
+pub async fn doc_policy_review(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentPolicyReviewRequest>,
+) -> Result<(StatusCode, Json<DocumentPolicyReviewResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    match policy_scan_service::review(&registry, &org_id, doc_uuid, &request.by_prpl).await {
+        Ok(Some(resolved)) => {
+            info!("Policy findings of document '{}' reviewed by '{}', {} resolved", doc_id, request.by_prpl, resolved);
+            Ok((StatusCode::OK, Json(DocumentPolicyReviewResponse { doc_id, resolved })))
+        }
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found", doc_id))),
+        Err(e) => {
+            error!("{}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+fn parse_doc_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
@@ -0,0 +1,93 @@
+use crate::{auth::auth, db::dbcolab::{self, DocumentSignatureRow}, models::{api_error, ApiError, DocumentSignatureResponse, DocumentSignatureVerifyRequest, DocumentSignatureVerifyResponse}, services::{feature_service::{self, Feature}, signing_service}};
+use axum::{extract::{Extension, Path}, http::StatusCode, Json};
+use base64::{engine::general_purpose, Engine as _};
+use tracing::error;
+use uuid::Uuid;
+
+/// Get the signature of the published version of a document
+pub async fn doc_published_signature(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentSignatureResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Publishing).await?;
+
+    let row = load_signature(&org_id, &doc_id).await?;
+    let message = signing_service::signed_message(&row.org, &row.document, row.version, &row.snapshot_sha256, &row.json_sha256);
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentSignatureResponse {
+            doc_id,
+            version: row.version,
+            version_v: row.version_v.0,
+            snapshot_sha256: row.snapshot_sha256,
+            json_sha256: row.json_sha256,
+            message,
+            algorithm: row.algorithm,
+            key_id: row.key_id,
+            public_key: row.public_key,
+            signature: row.signature,
+            signed_at: row.created_at,
+            signed_by: row.created_by,
+        }),
+    ))
+}
+
+/// Verify a snapshot and/or JSON against the signature of the published version of a document
+pub async fn doc_published_verify(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentSignatureVerifyRequest>,
+) -> Result<(StatusCode, Json<DocumentSignatureVerifyResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Publishing).await?;
+
+    let snapshot = match &request.snapshot {
+        Some(encoded) => match general_purpose::STANDARD.decode(encoded) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid base64 snapshot: {}", e))),
+        },
+        None => None,
+    };
+
+    let row = load_signature(&org_id, &doc_id).await?;
+    let (signature_valid, snapshot_matches, json_matches) = signing_service::verify_row(&row, snapshot.as_deref(), request.json.as_ref())
+        .map_err(|e| {
+            error!("Failed to verify signature of document '{}': {}", doc_id, e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to verify signature of document '{}': {}", doc_id, e))
+        })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentSignatureVerifyResponse {
+            valid: signature_valid && snapshot_matches != Some(false) && json_matches != Some(false),
+            signature_valid,
+            snapshot_matches,
+            json_matches,
+        }),
+    ))
+}
+
+async fn load_signature(org_id: &str, doc_id: &str) -> Result<DocumentSignatureRow, ApiError> {
+    let doc_uuid = Uuid::parse_str(doc_id).map_err(|e| {
+        error!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })?;
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, "Database not initialized")),
+    };
+    match db.get_latest_document_signature(org_id, doc_uuid).await {
+        Ok(Some(row)) => Ok(row),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' has no published signature", doc_id))),
+        Err(e) => {
+            error!("Failed to load signature of document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load signature of document '{}': {}", doc_id, e)))
+        }
+    }
+}
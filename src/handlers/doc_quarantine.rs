@@ -0,0 +1,76 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentQuarantineResponse}, services::quarantine_service};
+use axum::{extract::{Extension, Path}, http::StatusCode, Json};
+use tracing::error;
+use uuid::Uuid;
+
+/// Get the quarantine status of a document
+pub async fn doc_quarantine(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentQuarantineResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    Ok((StatusCode::OK, Json(load_status(&org_id, doc_uuid).await?)))
+}
+
+/// Lift the quarantine of a document and try to load it again
+pub async fn doc_quarantine_retry(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentQuarantineResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    // A failing retry counts as a load failure, the status tells whether the document is quarantined again
+    if let Err(e) = quarantine_service::retry(&org_id, doc_uuid).await {
+        error!("Retrying load of document '{}' failed: {}", doc_id, e);
+    }
+
+    Ok((StatusCode::OK, Json(load_status(&org_id, doc_uuid).await?)))
+}
+
+/// Rebuild a document from its JSON representation and lift its quarantine
+pub async fn doc_quarantine_repair(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentQuarantineResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let by_prpl = auth::ensure_cloud_admin(&prpls)?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    if let Err(e) = quarantine_service::repair(&org_id, doc_uuid, &by_prpl).await {
+        error!("Repairing document '{}' failed: {}", doc_id, e);
+        return Err(api_error(StatusCode::UNPROCESSABLE_ENTITY, e));
+    }
+
+    Ok((StatusCode::OK, Json(load_status(&org_id, doc_uuid).await?)))
+}
+
+fn parse_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        error!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
+
+async fn load_status(org_id: &str, doc_uuid: Uuid) -> Result<DocumentQuarantineResponse, ApiError> {
+    match quarantine_service::get_status(org_id, doc_uuid).await {
+        Ok(Some(status)) => Ok(DocumentQuarantineResponse {
+            quarantined: status.quarantined_at.is_some(),
+            load_failures: status.load_failures,
+            quarantined_at: status.quarantined_at,
+            reason: status.quarantine_reason,
+        }),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_uuid, org_id))),
+        Err(e) => {
+            error!("{}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentReactionRequest, DocumentReactionResponse, ListResponse, PageQuery, ReactionSummary, RequestId}, services::{acl_service, comment_service, doc_edit_service, doc_load_service, feature_service::{self, Feature}, reaction_service, room_assignment_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct ReactionsQuery {
+    path: Option<String>,
+}
+
+/// List the reactions on the blocks, languages and comments of a document, counted per emoji
+pub async fn doc_reactions(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<ReactionsQuery>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<ReactionSummary>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Comments).await?;
+    parse_uuid("document", &doc_id)?;
+
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let scopes = acl_service::collect_acl_scopes(&loro_doc).map_err(|e| {
+        error!("Failed to read document '{}': {}", doc_id, e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read document '{}': {}", doc_id, e))
+    })?;
+
+    let mut summaries = Vec::new();
+    for scope in &scopes {
+        if query.path.as_deref().is_some_and(|path| path != scope.path) {
+            continue;
+        }
+        if let Some(map) = &scope.map {
+            summaries.extend(reaction_service::read_reactions(&scope.path, map));
+        }
+    }
+
+    Ok((StatusCode::OK, Json(ListResponse::page(summaries, &page, &request_id))))
+}
+
+/// React with an emoji to a block, a language or a comment
+pub async fn doc_reaction_add(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentReactionRequest>,
+) -> Result<(StatusCode, Json<DocumentReactionResponse>), ApiError> {
+    edit_reaction(registry, prpls, org_id, doc_id, request, true).await
+}
+
+/// Withdraw a reaction
+pub async fn doc_reaction_remove(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentReactionRequest>,
+) -> Result<(StatusCode, Json<DocumentReactionResponse>), ApiError> {
+    edit_reaction(registry, prpls, org_id, doc_id, request, false).await
+}
+
+async fn edit_reaction(
+    registry: Arc<HubRegistry<DocContext>>,
+    prpls: Vec<String>,
+    org_id: String,
+    doc_id: String,
+    request: DocumentReactionRequest,
+    add: bool,
+) -> Result<(StatusCode, Json<DocumentReactionResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Comments).await?;
+    parse_uuid("document", &doc_id)?;
+    if !reaction_service::is_emoji(&request.emoji) {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("'{}' is not an emoji", request.emoji)));
+    }
+    ensure_can_react(&registry, &org_id, &doc_id, &request.path, &request.by_prpl).await?;
+
+    // A reaction to a comment needs the comment on the same path
+    if let Some(comment_id) = request.comment_id {
+        let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+        match comment_service::find_comment(&loro_doc, &comment_id) {
+            Ok(Some((path, _))) if path == request.path => {}
+            Ok(_) => {
+                return Err(api_error(StatusCode::NOT_FOUND, format!("Comment '{}' not found at path '{}'", comment_id, request.path)));
+            }
+            Err(e) => {
+                error!("Failed to read comments of document '{}': {}", doc_id, e);
+                return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read comments of document '{}': {}", doc_id, e)));
+            }
+        }
+    }
+
+    // Write the reaction and read back the counts of its target
+    let mut outcome = None;
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, |doc: &LoroDoc| {
+        let comment_id = request.comment_id.as_ref();
+        let changed = if add {
+            reaction_service::add_reaction(doc, &request.path, comment_id, &request.emoji, &request.by_prpl)?
+        } else {
+            reaction_service::remove_reaction(doc, &request.path, comment_id, &request.emoji, &request.by_prpl)?
+        };
+        if changed {
+            doc.commit();
+        }
+        outcome = Some((changed, reaction_service::summary(doc, &request.path, comment_id)?));
+        Ok(())
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to update the reactions of document '{}': {}", doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to update the reactions of document '{}': {}", doc_id, e)));
+    }
+    let (changed, summary) = outcome.ok_or_else(|| {
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update the reactions of document '{}'", doc_id))
+    })?;
+
+    Ok((StatusCode::OK, Json(DocumentReactionResponse { changed, summary })))
+}
+
+fn parse_uuid(kind: &str, id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|e| {
+        error!("Invalid {} UUID '{}': {}", kind, id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid {} UUID '{}'", kind, id))
+    })
+}
+
+// Ensure `by_prpl` has at least some permission on the block or language at `path`
+async fn ensure_can_react(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, path: &str, by_prpl: &str) -> Result<(), ApiError> {
+
+    // Reactions live on blocks and languages, not on the document itself
+    if path == "/" {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Reactions can only be placed on a block or language"));
+    }
+
+    let permissions = match acl_service::permissions_on_path(registry, org_id, doc_id, path, by_prpl).await {
+        Ok(Some(permissions)) => permissions,
+        Ok(None) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("No block or language found at path '{}' in document '{}'", path, doc_id)));
+        }
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}': {}", doc_id, e)));
+        }
+    };
+    if permissions.is_empty() {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("'{}' has no access to '{}'", by_prpl, path)));
+    }
+    Ok(())
+}
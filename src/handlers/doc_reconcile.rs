@@ -0,0 +1,100 @@
+use crate::{auth::auth, db::dbcolab::DocumentMergeRow, models::{api_error, ApiError, DocumentMerge, DocumentMergeRequest, DocumentMergeResponse, DocumentReconcileResponse, ReconcileVersion}, services::reconcile_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_protocol::CrdtType;
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+/// Get the versions of a document and whether they diverged
+pub async fn doc_reconcile(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentReconcileResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    // 1. Load the versions and find the heads
+    let versions = match reconcile_service::load_versions(&org_id, doc_uuid).await {
+        Ok(Some(versions)) => versions,
+        Ok(None) => return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id))),
+        Err(e) => {
+            error!("Failed to load versions of document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+    };
+
+    // 2. Load the earlier merges
+    let merges = reconcile_service::merges(&org_id, doc_uuid).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+
+    let versions: Vec<ReconcileVersion> = versions
+        .iter()
+        .map(|v| ReconcileVersion {
+            version: v.version,
+            version_v: serde_json::to_value(&v.vv).unwrap_or_default(),
+            head: v.head,
+        })
+        .collect();
+    Ok((
+        StatusCode::OK,
+        Json(DocumentReconcileResponse {
+            diverged: versions.iter().filter(|v| v.head).count() > 1,
+            versions,
+            merges: merges.into_iter().map(to_merge).collect(),
+        }),
+    ))
+}
+
+/// Merge two diverged versions of a document into a new version
+pub async fn doc_reconcile_merge(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentMergeRequest>,
+) -> Result<(StatusCode, Json<DocumentMergeResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let by_prpl = auth::ensure_cloud_admin(&prpls)?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+    let versions = match (request.version_a, request.version_b) {
+        (Some(a), Some(b)) => Some((a, b)),
+        (None, None) => None,
+        _ => return Err(api_error(StatusCode::BAD_REQUEST, "Either both versions or none are required")),
+    };
+
+    // 1. Close the room so pending edits are saved, the next load opens the merged version
+    registry.close_room(&org_id, CrdtType::Loro, &doc_id, true).await;
+
+    // 2. Merge
+    match reconcile_service::merge(&org_id, doc_uuid, versions, &by_prpl).await {
+        Ok(merge) => Ok((StatusCode::OK, Json(DocumentMergeResponse { merge: to_merge(merge) }))),
+        Err(e) => {
+            error!("Failed to merge versions of document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::CONFLICT, e))
+        }
+    }
+}
+
+fn to_merge(row: DocumentMergeRow) -> DocumentMerge {
+    DocumentMerge {
+        id: row.id.to_string(),
+        version_a: row.version_a as u32,
+        version_b: row.version_b as u32,
+        merged_version: row.merged_version as u32,
+        version_v: row.version_v.0,
+        created_at: row.created_at,
+        created_by: row.created_by,
+    }
+}
+
+fn parse_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        error!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
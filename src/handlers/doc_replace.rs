@@ -0,0 +1,40 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentReplaceRequest, DocumentReplaceResponse}, services::{replace_service, room_assignment_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Find and replace text across a document
+pub async fn doc_replace(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentReplaceRequest>,
+) -> Result<(StatusCode, Json<DocumentReplaceResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+    let options = replace_service::options(&request).map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+
+    let blocks = match replace_service::replace(&registry, &org_id, &doc_id, options, request.dry_run).await {
+        Ok(Some(replacement)) => replacement.blocks,
+        Ok(None) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id)));
+        }
+        Err(e) => {
+            error!("Failed to replace in document '{}': {}", doc_id, e);
+            return Err(api_error(room_assignment_service::misdirected_or(&e, StatusCode::UNPROCESSABLE_ENTITY), format!("Failed to replace in document '{}': {}", doc_id, e)));
+        }
+    };
+    Ok((StatusCode::OK, Json(DocumentReplaceResponse {
+        doc_id,
+        dry_run: request.dry_run,
+        matches: blocks.iter().map(|block| block.matches).sum(),
+        blocks,
+    })))
+}
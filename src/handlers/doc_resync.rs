@@ -0,0 +1,42 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentResyncRequest, DocumentResyncResponse}, services::{resync_service, room_assignment_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Re-sync a document from an external model of it, resolving conflicts by a strategy
+pub async fn doc_resync(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentResyncRequest>,
+) -> Result<(StatusCode, Json<DocumentResyncResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let by_prpl = auth::ensure_service(&prpls, "colabri-app")?;
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    // Plan the changes per block or language and apply them unless dry running
+    let strategy = request.strategy.clone();
+    let dry_run = request.dry_run;
+    let resynced = match resync_service::resync(&registry, &org_id, &doc_id, request, &by_prpl).await {
+        Ok(Ok(Some(resynced))) => resynced,
+        Ok(Ok(None)) => return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found", doc_id))),
+        Ok(Err(e)) => return Err(api_error(StatusCode::BAD_REQUEST, e)),
+        Err(e) => {
+            error!("Failed to re-sync document '{}': {}", doc_id, e);
+            return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to re-sync document '{}': {}", doc_id, e)));
+        }
+    };
+    Ok((StatusCode::OK, Json(DocumentResyncResponse {
+        doc_id,
+        strategy,
+        dry_run,
+        changes: resynced.changes,
+        version_tag: resynced.version_tag,
+    })))
+}
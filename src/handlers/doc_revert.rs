@@ -0,0 +1,84 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentRevertRequest, DocumentRevertResponse, RevertedChange}, services::{doc_edit_service, doc_load_service, revert_service, room_assignment_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use loro::{LoroDoc, VersionVector};
+use loro_websocket_server::HubRegistry;
+use std::{collections::HashMap, sync::Arc};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Revert all changes of a principal or peer after a version vector, e.g. of a compromised account or a misbehaving bot
+pub async fn doc_revert_author(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentRevertRequest>,
+) -> Result<(StatusCode, Json<DocumentRevertResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let admin = auth::ensure_cloud_admin(&prpls)?;
+
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+    let peer = match &request.peer {
+        Some(peer) => Some(peer.parse::<u64>().map_err(|_| api_error(StatusCode::BAD_REQUEST, format!("Invalid peer '{}'", peer)))?),
+        None => None,
+    };
+    let after = match &request.after_v {
+        Some(vv) => {
+            let entries: HashMap<u64, i32> = serde_json::from_value(vv.clone())
+                .map_err(|e| api_error(StatusCode::BAD_REQUEST, format!("Invalid version vector: {}", e)))?;
+            VersionVector::from_iter(entries)
+        }
+        None => VersionVector::default(),
+    };
+
+    // 1. Load the document and resolve the peers of the author
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let peers = revert_service::resolve_peers(&ctx.peer_map, request.by_prpl.as_deref(), peer)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+
+    // 2. Plan what gets reverted
+    let changes = revert_service::plan(&loro_doc, &peers, &after);
+    let reverted = revert_service::reverted_updates(&loro_doc, &peers, &after)
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let mut response = DocumentRevertResponse {
+        dry_run: request.dry_run,
+        peers: peers.iter().map(|p| p.to_string()).collect(),
+        changes: changes.iter().map(|c| RevertedChange {
+            peer: c.meta.id.peer.to_string(),
+            counter: c.start,
+            len: (c.meta.id.counter + c.meta.len as i32 - c.start) as usize,
+            lamport: c.meta.lamport,
+            timestamp: if c.meta.timestamp > 0 { DateTime::<Utc>::from_timestamp(c.meta.timestamp, 0) } else { None },
+        }).collect(),
+        reverted,
+        preview: None,
+    };
+
+    // 3. With a dry run, only show the result
+    if request.dry_run {
+        let preview = revert_service::preview(&loro_doc, &changes)
+            .map_err(|e| api_error(StatusCode::CONFLICT, e))?;
+        response.preview = Some(preview);
+        return Ok((StatusCode::OK, Json(response)));
+    }
+    if changes.is_empty() {
+        return Ok((StatusCode::OK, Json(response)));
+    }
+
+    // 4. Revert on the live document, the plan is made again in case the author made more changes meanwhile
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        let changes = revert_service::plan(doc, &peers, &after);
+        revert_service::apply(doc, &changes)
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to revert changes in document '{}': {}", doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to revert changes in document '{}': {}", doc_id, e)));
+    }
+    info!("Reverted {} changes of peers {:?} in document '{}' by '{}'", response.changes.len(), response.peers, doc_id, admin);
+
+    Ok((StatusCode::OK, Json(response)))
+}
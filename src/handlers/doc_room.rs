@@ -0,0 +1,43 @@
+use crate::{auth::{auth, is_org_member}, models::{api_error, ApiError, DocumentRoomResponse}, services::room_assignment_service};
+use axum::{extract::{Extension, Path, Query}, http::{header, StatusCode}, response::{IntoResponse, Response}, Json};
+use serde::Deserialize;
+use tracing::warn;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct RoomQuery {
+    redirect: Option<bool>,
+}
+
+/// Get the pod owning the room of a document, or redirect to it
+pub async fn doc_room(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<RoomQuery>,
+) -> Result<Response, ApiError> {
+
+    // Users connecting to the room and services may ask, as long as they belong to the organization
+    if !is_org_member(&prpls, &org_id) && auth::ensure_service(&prpls, "colabri-app").is_err() {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("No access to organization '{}'", org_id)));
+    }
+
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    let assignment = room_assignment_service::assign(&doc_id);
+    if query.redirect.unwrap_or(false) && !assignment.local {
+        return Ok((StatusCode::TEMPORARY_REDIRECT, [(header::LOCATION, assignment.ws_url)]).into_response());
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentRoomResponse {
+            doc_id,
+            pod: assignment.pod,
+            ws_url: assignment.ws_url,
+            local: assignment.local,
+        }),
+    ).into_response())
+}
@@ -0,0 +1,61 @@
+use crate::{auth::{auth, is_org_member}, models::{api_error, ApiError, DocumentSaveStatusResponse}, services::{hub_service, room_assignment_service, save_policy_service, save_status_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Get whether the changes in the room of a document are persisted
+pub async fn doc_save_status(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentSaveStatusResponse>), ApiError> {
+
+    // Editors in the room and services may ask, as long as they belong to the organization
+    if !is_org_member(&prpls, &org_id) && auth::ensure_service(&prpls, "colabri-app").is_err() {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("No access to organization '{}'", org_id)));
+    }
+
+    let doc_uuid = match Uuid::parse_str(&doc_id) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            warn!("Invalid document UUID '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+        }
+    };
+
+    // 1. The room as it is now
+    let local = room_assignment_service::assign(&doc_id).local;
+    let dirty = hub_service::is_doc_dirty(&registry, &org_id, &doc_id).await.unwrap_or(false);
+    let current_vv = hub_service::get_open_doc_handle(&registry, &org_id, &doc_id)
+        .await
+        .map(|(loro_doc, _)| loro_doc.oplog_vv());
+
+    // 2. The latest saves
+    let status = save_status_service::get(doc_uuid);
+    let deferred = save_policy_service::is_deferred(doc_uuid);
+    let all_saved = match (&current_vv, &status.last_saved_vv) {
+        (Some(current), Some(saved)) => !deferred && saved.includes_vv(current),
+        // A room that is not open has nothing unsaved
+        (None, _) => true,
+        (Some(_), None) => !dirty && !deferred,
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentSaveStatusResponse {
+            doc_id,
+            local,
+            open: current_vv.is_some(),
+            dirty,
+            deferred,
+            all_saved,
+            last_saved_at: status.last_saved_at,
+            last_saved_v: status.last_saved_vv.as_ref().and_then(|vv| serde_json::to_value(vv).ok()),
+            current_v: current_vv.as_ref().and_then(|vv| serde_json::to_value(vv).ok()),
+            last_error: status.last_error,
+            last_error_at: status.last_error_at,
+        }),
+    ))
+}
@@ -0,0 +1,65 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentSettingsPatchRequest, DocumentSettingsResponse}, services::doc_settings_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Get the settings of a document
+pub async fn doc_settings(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentSettingsResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin or the app service
+    if auth::ensure_service(&prpls, "colabri-app").is_err() {
+        let _ = auth::ensure_cloud_admin(&prpls)?;
+    }
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    match doc_settings_service::get_settings(&org_id, doc_uuid).await {
+        Ok(Some(settings)) => Ok((StatusCode::OK, Json(DocumentSettingsResponse { doc_id, settings }))),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id))),
+        Err(e) => {
+            error!("{}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+/// Change the settings of a document, they apply to its live room right away
+pub async fn doc_settings_patch(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentSettingsPatchRequest>,
+) -> Result<(StatusCode, Json<DocumentSettingsResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let by_prpl = auth::ensure_cloud_admin(&prpls)?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    if !request.settings.is_object() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "The settings must be a JSON merge patch object"));
+    }
+
+    match doc_settings_service::patch_settings(&registry, &org_id, doc_uuid, &request.settings, &by_prpl).await {
+        Ok(Some(settings)) => {
+            info!("Settings of document '{}' changed by '{}': {}", doc_id, by_prpl, request.settings);
+            Ok((StatusCode::OK, Json(DocumentSettingsResponse { doc_id, settings })))
+        }
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id))),
+        Err(e) if e.starts_with("Invalid settings") => Err(api_error(StatusCode::BAD_REQUEST, e)),
+        Err(e) => {
+            error!("{}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+fn parse_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        error!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
@@ -0,0 +1,103 @@
+use crate::{auth::auth, db::dbcolab::DocumentShareTokenRow, models::{api_error, ApiError, DocumentShareToken, DocumentShareTokenCreateRequest, ListResponse, PageQuery, RequestId}, services::{feature_service::{self, Feature}, public_view_service}};
+use axum::{extract::{Extension, Path, Query}, http::StatusCode, Json};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Create a share token giving unauthenticated read access to the published version of a document
+pub async fn doc_share_token_create(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentShareTokenCreateRequest>,
+) -> Result<(StatusCode, Json<DocumentShareToken>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Publishing).await?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    if let Some(expires_at) = request.expires_at {
+        if expires_at <= chrono::Utc::now() {
+            return Err(api_error(StatusCode::BAD_REQUEST, "The expiry of a share token must be in the future".to_string()));
+        }
+    }
+
+    let (token, row) = public_view_service::create_token(&org_id, doc_uuid, request.branding.as_ref(), request.expires_at, &request.by_prpl)
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+        })?;
+    info!("Share token '{}' of document '{}' created by '{}'", row.id, doc_id, request.by_prpl);
+
+    let mut share_token = to_share_token(row);
+    share_token.path = Some(format!("/public/{}", token));
+    share_token.token = Some(token);
+    Ok((StatusCode::CREATED, Json(share_token)))
+}
+
+/// List the share tokens of a document
+pub async fn doc_share_tokens(
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<DocumentShareToken>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let rows = public_view_service::list_tokens(&org_id, doc_uuid).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+
+    let tokens = rows.into_iter().map(to_share_token).collect();
+    Ok((StatusCode::OK, Json(ListResponse::page(tokens, &page, &request_id))))
+}
+
+/// Revoke a share token of a document
+pub async fn doc_share_token_revoke(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id, token_id)): Path<(String, String, String)>,
+) -> Result<StatusCode, ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let token_uuid = Uuid::parse_str(&token_id)
+        .map_err(|_| api_error(StatusCode::BAD_REQUEST, format!("Invalid share token id '{}'", token_id)))?;
+
+    match public_view_service::revoke_token(&org_id, doc_uuid, token_uuid).await {
+        Ok(true) => {
+            info!("Share token '{}' of document '{}' revoked", token_id, doc_id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err(api_error(StatusCode::NOT_FOUND, format!("No active share token '{}' for document '{}'", token_id, doc_id))),
+        Err(e) => {
+            error!("{}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+fn parse_doc_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
+
+fn to_share_token(row: DocumentShareTokenRow) -> DocumentShareToken {
+    DocumentShareToken {
+        id: row.id.to_string(),
+        token: None,
+        path: None,
+        branding: public_view_service::branding_of(&row),
+        expires_at: row.expires_at,
+        revoked_at: row.revoked_at,
+        created_at: row.created_at,
+        created_by: row.created_by,
+    }
+}
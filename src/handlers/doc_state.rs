@@ -0,0 +1,186 @@
+use crate::{auth::auth, models::{api_error, ApiError, AvailableTransition, DocumentStateResponse, DocumentStateTransitionRequest, DocumentStateTransitionResponse, WorkflowDefinition}, services::{acl_cache_service, acl_service, doc_edit_service, room_assignment_service, signing_service, summary_service, workflow_service::{self, TransitionEvent}}, ws::docctx::DocContext, db::dbcolab};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use chrono::Utc;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct StateQuery {
+    prpl: Option<String>,
+}
+
+/// Get the workflow state of a document and the transitions that can be made from it
+pub async fn doc_state(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<StateQuery>,
+) -> Result<(StatusCode, Json<DocumentStateResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    let workflow = load_workflow(&org_id).await?;
+    let state = load_state(&org_id, doc_uuid).await?;
+
+    // Tell whether the principal can make each transition
+    let permissions = match &query.prpl {
+        Some(prpl) => Some(document_permissions(&registry, &org_id, &doc_id, prpl).await?),
+        None => None,
+    };
+    let transitions = workflow
+        .transitions_from(&state)
+        .map(|t| AvailableTransition {
+            to: t.to.clone(),
+            permission: t.permission.clone(),
+            allowed: permissions.as_ref().map(|p| p.contains(&t.permission)),
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(DocumentStateResponse { state, transitions })))
+}
+
+/// Move a document to another workflow state
+pub async fn doc_state_transition(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentStateTransitionRequest>,
+) -> Result<(StatusCode, Json<DocumentStateTransitionResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    // 1. Validate the transition against the workflow of the organization
+    let workflow = load_workflow(&org_id).await?;
+    let current = load_state(&org_id, doc_uuid).await?;
+    let target = match workflow.state(&request.state) {
+        Some(state) => state.clone(),
+        None => return Err(api_error(StatusCode::BAD_REQUEST, format!("Unknown workflow state '{}'", request.state))),
+    };
+    let transition = match workflow.transition(&current, &target.name) {
+        Some(transition) => transition,
+        None => return Err(api_error(StatusCode::CONFLICT, format!("Transition from '{}' to '{}' is not allowed", current, target.name))),
+    };
+
+    // 2. Ensure the principal holds the permission of the transition
+    let permissions = document_permissions(&registry, &org_id, &doc_id, &request.by_prpl).await?;
+    if !permissions.contains(&transition.permission) {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("'{}' needs the '{}' permission to move document '{}' to '{}'", request.by_prpl, transition.permission, doc_id, target.name)));
+    }
+
+    // The document is edited after the state moved, only the pod owning its room may move it
+    if let Err(reason) = room_assignment_service::ensure_local(&doc_id) {
+        return Err(api_error(StatusCode::MISDIRECTED_REQUEST, format!("Document '{}' is served by another pod: {}", doc_id, reason)));
+    }
+
+    // 3. Update the state in the database, this fails if someone else moved the document in the meantime
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, "Database not initialized")),
+    };
+    match db.update_document_workflow_state(&org_id, doc_uuid, &current, &target.name, request.comment.as_deref(), &request.by_prpl).await {
+        Ok(true) => acl_cache_service::invalidate_document(&org_id, doc_uuid),
+        Ok(false) => {
+            return Err(api_error(StatusCode::CONFLICT, format!("Document '{}' is no longer in state '{}'", doc_id, current)));
+        }
+        Err(e) => {
+            error!("Failed to update workflow state of document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update workflow state of document '{}': {}", doc_id, e)));
+        }
+    }
+
+    // 4. Mirror the state in the document properties and apply the ACL template.
+    // Archived documents are force closed so connected editors reconnect with read access only.
+    let archiving = workflow_service::is_archived(&target.name);
+    let target_state = target.clone();
+    let result = doc_edit_service::edit_doc(registry.clone(), &org_id, &doc_id, move |doc: &LoroDoc| {
+        workflow_service::apply_state(doc, &target_state)?;
+        doc.commit();
+        Ok(())
+    }, archiving).await;
+    if let Err(e) = result {
+        error!("Document '{}' moved to '{}' but the document could not be updated: {}", doc_id, target.name, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Document '{}' moved to '{}' but the document could not be updated: {}", doc_id, target.name, e)));
+    }
+    if archiving {
+        if let Err(e) = workflow_service::revoke_archived_db_acls(&org_id, doc_uuid).await {
+            error!("{}", e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+    }
+
+    if workflow_service::is_published(&target.name) {
+        match signing_service::sign_publication(&registry, &org_id, doc_uuid, &request.by_prpl).await {
+            Ok(signature) => summary_service::spawn_generate(registry.clone(), org_id.clone(), doc_uuid, signature.version as u32),
+            Err(e) => {
+                error!("Document '{}' was published but could not be signed: {}", doc_id, e);
+                return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Document '{}' was published but could not be signed: {}", doc_id, e)));
+            }
+        }
+    }
+
+    // 5. Run the transition hooks
+    let event = TransitionEvent {
+        org_id: org_id.clone(),
+        doc_uuid,
+        from: current,
+        to: target.name,
+        by_prpl: request.by_prpl,
+        comment: request.comment,
+        timestamp: Utc::now(),
+    };
+    workflow_service::run_transition_hooks(&workflow, &event);
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentStateTransitionResponse {
+            success: true,
+            from: event.from,
+            to: event.to,
+            timestamp: event.timestamp,
+        }),
+    ))
+}
+
+fn parse_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        error!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
+
+async fn load_workflow(org_id: &str) -> Result<WorkflowDefinition, ApiError> {
+    workflow_service::get_workflow(org_id).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })
+}
+
+async fn load_state(org_id: &str, doc_uuid: Uuid) -> Result<String, ApiError> {
+    match workflow_service::get_state(org_id, doc_uuid).await {
+        Ok(Some(state)) => Ok(state),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_uuid, org_id))),
+        Err(e) => {
+            error!("{}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+async fn document_permissions(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, prpl: &str) -> Result<std::collections::BTreeSet<String>, ApiError> {
+    match acl_service::permissions_on_path(registry, org_id, doc_id, "/", prpl).await {
+        Ok(Some(permissions)) => Ok(permissions),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id))),
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}': {}", doc_id, e)))
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentHistoryBudgetRequest, DocumentStorageResponse, ListResponse, PageQuery, RequestId, VersionStorage}, services::storage_service};
+use axum::{extract::{Extension, Path, Query}, http::StatusCode, Json};
+use tracing::error;
+use uuid::Uuid;
+
+/// Get the storage used by the versions of a document
+pub async fn doc_storage(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentStorageResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    Ok((StatusCode::OK, Json(load_report(&org_id, doc_uuid).await?)))
+}
+
+/// List the saved versions of a document, oldest first
+pub async fn doc_versions(
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<VersionStorage>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    let report = load_report(&org_id, doc_uuid).await?;
+    Ok((StatusCode::OK, Json(ListResponse::page(report.versions, &page, &request_id))))
+}
+
+/// Set the number of versions of a document to keep
+pub async fn doc_storage_budget(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentHistoryBudgetRequest>,
+) -> Result<(StatusCode, Json<DocumentStorageResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    let doc_uuid = parse_uuid(&doc_id)?;
+
+    if request.history_budget == Some(0) {
+        return Err(api_error(StatusCode::BAD_REQUEST, "The history budget must keep at least one version"));
+    }
+
+    match storage_service::set_history_budget(&org_id, doc_uuid, request.history_budget, &request.by_prpl).await {
+        Ok(true) => {}
+        Ok(false) => return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id))),
+        Err(e) => {
+            error!("{}", e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+    }
+
+    // Answer with the preview under the new budget
+    Ok((StatusCode::OK, Json(load_report(&org_id, doc_uuid).await?)))
+}
+
+fn parse_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        error!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
+
+async fn load_report(org_id: &str, doc_uuid: Uuid) -> Result<DocumentStorageResponse, ApiError> {
+    match storage_service::storage_report(org_id, doc_uuid).await {
+        Ok(Some(report)) => Ok(report),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_uuid, org_id))),
+        Err(e) => {
+            error!("{}", e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentLinkStatementsResponse, LinkedStatement}, services::{room_assignment_service, statement_subdoc_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Move the local statements of a sheet into linked subdocuments
+pub async fn doc_statements_link(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentLinkStatementsResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let by_prpl = auth::ensure_service(&prpls, "colabri-app")?;
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    let extracted = statement_subdoc_service::extract(registry, &org_id, &doc_id, &by_prpl).await.map_err(|e| {
+        error!("Failed to link the statements of document '{}': {}", doc_id, e);
+        api_error(room_assignment_service::misdirected_or(&e, StatusCode::UNPROCESSABLE_ENTITY), format!("Failed to link the statements of document '{}': {}", doc_id, e))
+    })?;
+
+    let statements = extracted
+        .into_iter()
+        .map(|statement| LinkedStatement {
+            room: statement_subdoc_service::subdoc_room(&doc_id, statement.subdoc_id),
+            block_id: statement.block_id,
+            row: statement.row,
+            subdoc_id: statement.subdoc_id.to_string(),
+        })
+        .collect();
+    Ok((StatusCode::OK, Json(DocumentLinkStatementsResponse { doc_id, statements })))
+}
@@ -0,0 +1,242 @@
+use crate::{auth::auth, models::{api_error, ApiError, ColabSuggestion, ColabSuggestionState, DocumentSuggestionAddRequest, DocumentSuggestionDecisionRequest, DocumentSuggestionResponse, ListResponse, PageQuery, RequestId, SuggestionView}, services::{acl_service, doc_edit_service, doc_load_service, feature_service::{self, Feature}, room_assignment_service, suggestion_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct SuggestionsQuery {
+    path: Option<String>,
+    state: Option<String>,
+}
+
+/// List the suggestions of a document
+pub async fn doc_suggestions(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<SuggestionsQuery>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<SuggestionView>>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Suggestions).await?;
+    parse_uuid("document", &doc_id)?;
+
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let suggestions = suggestion_service::read_suggestions(&loro_doc)
+        .into_iter()
+        .filter(|s| query.path.as_deref().map_or(true, |path| path == s.path))
+        .filter(|s| query.state.as_deref().map_or(true, |state| state == s.state.to_string()))
+        .map(|s| suggestion_view(&loro_doc, &s))
+        .collect();
+
+    Ok((StatusCode::OK, Json(ListResponse::page(suggestions, &page, &request_id))))
+}
+
+/// Suggest an edit to a block or language
+pub async fn doc_suggestion_add(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentSuggestionAddRequest>,
+) -> Result<(StatusCode, Json<DocumentSuggestionResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Suggestions).await?;
+    parse_uuid("document", &doc_id)?;
+
+    // The author must be allowed to suggest (or edit) the block or language
+    let permissions = permissions_on_path(&registry, &org_id, &doc_id, &request.path, &request.by_prpl).await?;
+    if !permissions.contains("suggest") && !permissions.contains("edit") {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("'{}' cannot suggest edits on '{}'", request.by_prpl, request.path)));
+    }
+
+    // Validate the update against the current state
+    let update = general_purpose::STANDARD
+        .decode(&request.update)
+        .map_err(|e| api_error(StatusCode::BAD_REQUEST, format!("Invalid base64 update: {}", e)))?;
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let peers = match suggestion_service::apply_to_fork(&loro_doc, &request.path, &update) {
+        Ok((_, peers)) => peers,
+        Err(e) => return Err(api_error(StatusCode::BAD_REQUEST, e)),
+    };
+    if let Err(e) = suggestion_service::check_peers(&ctx.peer_map, &peers, &request.by_prpl) {
+        return Err(api_error(StatusCode::BAD_REQUEST, e));
+    }
+
+    let suggestion = ColabSuggestion {
+        id: Uuid::new_v4(),
+        path: request.path,
+        author: request.by_prpl,
+        state: ColabSuggestionState::Pending,
+        update: request.update,
+        timestamp: Utc::now(),
+        decided_by: None,
+        decided_at: None,
+    };
+    let view = suggestion_view(&loro_doc, &suggestion);
+
+    // Store the suggestion next to the content, the content itself is left untouched
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        suggestion_service::add_suggestion(doc, &suggestion)?;
+        doc.commit();
+        Ok(())
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to add suggestion to document '{}': {}", doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to add suggestion to document '{}': {}", doc_id, e)));
+    }
+
+    Ok((StatusCode::OK, Json(DocumentSuggestionResponse { suggestion: view })))
+}
+
+/// Accept a suggestion, importing its changes into the document
+pub async fn doc_suggestion_accept(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id, suggestion_id)): Path<(String, String, String)>,
+    Json(request): Json<DocumentSuggestionDecisionRequest>,
+) -> Result<(StatusCode, Json<DocumentSuggestionResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Suggestions).await?;
+    parse_uuid("document", &doc_id)?;
+    let suggestion_uuid = parse_uuid("suggestion", &suggestion_id)?;
+
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let mut suggestion = find_pending_suggestion(&loro_doc, &suggestion_uuid)?;
+    ensure_can_decide(&registry, &org_id, &doc_id, &suggestion, &request.by_prpl).await?;
+
+    // Make sure it still applies cleanly
+    let update = suggestion_service::decode_update(&suggestion)
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let peers = match suggestion_service::apply_to_fork(&loro_doc, &suggestion.path, &update) {
+        Ok((_, peers)) => peers,
+        Err(e) => return Err(api_error(StatusCode::CONFLICT, e)),
+    };
+    if let Err(e) = suggestion_service::check_peers(&ctx.peer_map, &peers, &suggestion.author) {
+        return Err(api_error(StatusCode::CONFLICT, e));
+    }
+
+    let decided_at = Utc::now();
+    let by_prpl = request.by_prpl.clone();
+    let accepted = suggestion.clone();
+    // The imported changes are attributed to the author of the suggestion
+    let peers = peers.into_iter().map(|peer| (peer, suggestion.author.clone())).collect();
+    let result = doc_edit_service::edit_doc_as(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        suggestion_service::accept_suggestion(doc, &accepted, &by_prpl, &decided_at)?;
+        doc.commit();
+        Ok(())
+    }, peers, false).await;
+    if let Err(e) = result {
+        error!("Failed to accept suggestion '{}' in document '{}': {}", suggestion_id, doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to accept suggestion '{}': {}", suggestion_id, e)));
+    }
+
+    suggestion.state = ColabSuggestionState::Accepted;
+    suggestion.decided_by = Some(request.by_prpl);
+    suggestion.decided_at = Some(decided_at);
+    Ok((StatusCode::OK, Json(DocumentSuggestionResponse { suggestion: suggestion_view(&loro_doc, &suggestion) })))
+}
+
+/// Reject a suggestion, its changes are never applied
+pub async fn doc_suggestion_reject(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id, suggestion_id)): Path<(String, String, String)>,
+    Json(request): Json<DocumentSuggestionDecisionRequest>,
+) -> Result<(StatusCode, Json<DocumentSuggestionResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    feature_service::ensure_enabled(&org_id, Feature::Suggestions).await?;
+    parse_uuid("document", &doc_id)?;
+    let suggestion_uuid = parse_uuid("suggestion", &suggestion_id)?;
+
+    let (loro_doc, _) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let mut suggestion = find_pending_suggestion(&loro_doc, &suggestion_uuid)?;
+    ensure_can_decide(&registry, &org_id, &doc_id, &suggestion, &request.by_prpl).await?;
+
+    let decided_at = Utc::now();
+    let by_prpl = request.by_prpl.clone();
+    let result = doc_edit_service::edit_doc(registry, &org_id, &doc_id, move |doc: &LoroDoc| {
+        suggestion_service::decide_suggestion(doc, &suggestion_uuid, &ColabSuggestionState::Rejected, &by_prpl, &decided_at)?;
+        doc.commit();
+        Ok(())
+    }, false).await;
+    if let Err(e) = result {
+        error!("Failed to reject suggestion '{}' in document '{}': {}", suggestion_id, doc_id, e);
+        return Err(api_error(room_assignment_service::edit_error_status(&e), format!("Failed to reject suggestion '{}': {}", suggestion_id, e)));
+    }
+
+    suggestion.state = ColabSuggestionState::Rejected;
+    suggestion.decided_by = Some(request.by_prpl);
+    suggestion.decided_at = Some(decided_at);
+    Ok((StatusCode::OK, Json(DocumentSuggestionResponse { suggestion: suggestion_view(&loro_doc, &suggestion) })))
+}
+
+fn parse_uuid(kind: &str, id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(id).map_err(|e| {
+        error!("Invalid {} UUID '{}': {}", kind, id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid {} UUID '{}'", kind, id))
+    })
+}
+
+fn suggestion_view(loro_doc: &LoroDoc, suggestion: &ColabSuggestion) -> SuggestionView {
+    let preview = match suggestion.state {
+        ColabSuggestionState::Pending => suggestion_service::preview(loro_doc, suggestion).ok(),
+        _ => None,
+    };
+    SuggestionView {
+        id: suggestion.id,
+        path: suggestion.path.clone(),
+        author: suggestion.author.clone(),
+        state: suggestion.state.to_string(),
+        timestamp: suggestion.timestamp,
+        decided_by: suggestion.decided_by.clone(),
+        decided_at: suggestion.decided_at,
+        preview,
+    }
+}
+
+fn find_pending_suggestion(loro_doc: &LoroDoc, suggestion_id: &Uuid) -> Result<ColabSuggestion, ApiError> {
+    match suggestion_service::find_suggestion(loro_doc, suggestion_id) {
+        Some(suggestion) if suggestion.state == ColabSuggestionState::Pending => Ok(suggestion),
+        Some(suggestion) => Err(api_error(StatusCode::CONFLICT, format!("Suggestion '{}' is already {}", suggestion_id, suggestion.state))),
+        None => Err(api_error(StatusCode::NOT_FOUND, format!("Suggestion '{}' not found", suggestion_id))),
+    }
+}
+
+async fn permissions_on_path(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, path: &str, by_prpl: &str) -> Result<std::collections::BTreeSet<String>, ApiError> {
+    // Suggestions target blocks and languages, not the document itself
+    if path == "/" {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Suggestions can only target a block or language"));
+    }
+    match acl_service::permissions_on_path(registry, org_id, doc_id, path, by_prpl).await {
+        Ok(Some(permissions)) => Ok(permissions),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("No block or language found at path '{}' in document '{}'", path, doc_id))),
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}': {}", doc_id, e)))
+        }
+    }
+}
+
+// Deciding on a suggestion requires edit permission on its block or language
+async fn ensure_can_decide(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, suggestion: &ColabSuggestion, by_prpl: &str) -> Result<(), ApiError> {
+    let permissions = permissions_on_path(registry, org_id, doc_id, &suggestion.path, by_prpl).await?;
+    if !permissions.contains("edit") {
+        return Err(api_error(StatusCode::FORBIDDEN, format!("'{}' cannot decide on suggestions for '{}'", by_prpl, suggestion.path)));
+    }
+    Ok(())
+}
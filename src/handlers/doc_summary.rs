@@ -0,0 +1,109 @@
+use crate::{auth::auth, db::dbcolab::DocumentSummaryRow, models::{api_error, ApiError, DocumentSummariesRequest, DocumentSummariesResponse, DocumentSummary}, services::summary_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+// Keeps a single request to the summaries cheap
+const MAX_SUMMARIES: usize = 500;
+
+/// Get the summary of the published version of a document
+pub async fn doc_summary(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentSummary>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let row = summary_service::get(&org_id, &[doc_uuid])
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+        })?
+        .into_iter()
+        .next()
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("No summary for document '{}'", doc_id)))?;
+
+    Ok((StatusCode::OK, Json(to_summary(row))))
+}
+
+/// Summarize the published version of a document again, e.g. after the provider was unavailable
+pub async fn doc_summary_regenerate(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentSummary>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let internal_error = |e: String| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    };
+
+    let version = summary_service::published_version(&org_id, doc_uuid)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("Document '{}' was never published", doc_id)))?;
+    summary_service::generate(&registry, &org_id, doc_uuid, version)
+        .await
+        .map_err(|e| {
+            error!("Failed to summarize document '{}' version {}: {}", doc_id, version, e);
+            api_error(StatusCode::BAD_GATEWAY, format!("Failed to summarize document '{}': {}", doc_id, e))
+        })?;
+
+    let row = summary_service::get(&org_id, &[doc_uuid])
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| internal_error(format!("Summary of document '{}' not found after generating it", doc_id)))?;
+    Ok((StatusCode::OK, Json(to_summary(row))))
+}
+
+/// Get the summaries of several documents, for library views
+pub async fn doc_summaries(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Json(request): Json<DocumentSummariesRequest>,
+) -> Result<(StatusCode, Json<DocumentSummariesResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    if request.doc_ids.len() > MAX_SUMMARIES {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("At most {} documents can be requested at once", MAX_SUMMARIES)));
+    }
+    let doc_uuids = request.doc_ids.iter().map(|doc_id| parse_doc_uuid(doc_id)).collect::<Result<Vec<Uuid>, ApiError>>()?;
+
+    let rows = summary_service::get(&org_id, &doc_uuids).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    Ok((StatusCode::OK, Json(DocumentSummariesResponse {
+        summaries: rows.into_iter().map(to_summary).collect(),
+    })))
+}
+
+fn parse_doc_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
+
+fn to_summary(row: DocumentSummaryRow) -> DocumentSummary {
+    DocumentSummary {
+        doc_id: row.document.to_string(),
+        version: row.version as u32,
+        summary: row.summary,
+        provider: row.provider,
+        generated_at: row.generated_at,
+    }
+}
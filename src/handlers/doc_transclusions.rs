@@ -0,0 +1,41 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentTransclusionsResponse}, services::transclusion_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct TransclusionsQuery {
+    prpl: String,
+}
+
+/// Resolve the transcluded blocks of a sheet with the latest content of their source
+pub async fn doc_transclusions(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<TransclusionsQuery>,
+) -> Result<(StatusCode, Json<DocumentTransclusionsResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    if let Err(e) = Uuid::parse_str(&doc_id) {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+    }
+
+    // Every source block is checked against the ACLs of its own document
+    let transclusions = match transclusion_service::resolve(&registry, &org_id, &doc_id, &query.prpl).await {
+        Ok(Some(transclusions)) => transclusions,
+        Ok(None) => {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found in organization '{}'", doc_id, org_id)));
+        }
+        Err(e) => {
+            error!("Failed to resolve the transclusions of document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to resolve the transclusions of document '{}': {}", doc_id, e)));
+        }
+    };
+    Ok((StatusCode::OK, Json(DocumentTransclusionsResponse { doc_id, transclusions })))
+}
@@ -0,0 +1,64 @@
+use crate::{auth::auth, db::dbcolab::OrgDocumentTypesRow, models::{api_error, ApiError, OrgDocumentTypesRequest, OrgDocumentTypesResponse}, services::doc_type_service};
+use axum::{extract::{Extension, Path}, http::StatusCode, Json};
+use std::collections::HashMap;
+use tracing::{error, info};
+
+/// Get the document types registered by an organization
+pub async fn org_document_types(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+) -> Result<(StatusCode, Json<OrgDocumentTypesResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin or the app service
+    if auth::ensure_service(&prpls, "colabri-app").is_err() {
+        let _ = auth::ensure_cloud_admin(&prpls)?;
+    }
+
+    let row = doc_type_service::get(&org_id).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    Ok((StatusCode::OK, Json(to_response(org_id, row))))
+}
+
+/// Register the document types of an organization
+pub async fn org_document_types_set(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Json(request): Json<OrgDocumentTypesRequest>,
+) -> Result<(StatusCode, Json<OrgDocumentTypesResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let by_prpl = auth::ensure_cloud_admin(&prpls)?;
+
+    let row = match doc_type_service::set(&org_id, request.strict, &request.types, &by_prpl).await {
+        Ok(Ok(row)) => row,
+        Ok(Err(e)) => return Err(api_error(StatusCode::BAD_REQUEST, e)),
+        Err(e) => {
+            error!("{}", e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+    };
+    info!("Document types of organization '{}' set by '{}', strict: {}", org_id, by_prpl, row.strict);
+
+    Ok((StatusCode::OK, Json(to_response(org_id, Some(row)))))
+}
+
+fn to_response(org_id: String, row: Option<OrgDocumentTypesRow>) -> OrgDocumentTypesResponse {
+    match row {
+        Some(row) => OrgDocumentTypesResponse {
+            org_id,
+            strict: row.strict,
+            types: doc_type_service::types_of(&row),
+            updated_at: Some(row.updated_at),
+            updated_by: Some(row.updated_by),
+        },
+        None => OrgDocumentTypesResponse {
+            org_id,
+            strict: false,
+            types: HashMap::new(),
+            updated_at: None,
+            updated_by: None,
+        },
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{auth::auth, handlers::doc_user_prefs::ensure_reader, models::{api_error, ApiError, DocumentUnreadResponse}, services::{doc_load_service, unread_service, user_prefs_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, Query, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct UnreadQuery {
+    // JSON object of counters keyed by peer id or principal
+    since_vv: Option<String>,
+}
+
+/// Digest of the changes to a document since the read marker of the caller, or since a version vector
+pub async fn doc_unread(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Query(query): Query<UnreadQuery>,
+) -> Result<(StatusCode, Json<DocumentUnreadResponse>), ApiError> {
+
+    // Services pass the earlier state, users default to their read marker
+    let (since, since_vv) = match &query.since_vv {
+        Some(since_vv) => {
+            if auth::ensure_service(&prpls, "colabri-app").is_err() {
+                ensure_reader(&prpls, &org_id, &doc_id).await?;
+            } else if let Err(e) = Uuid::parse_str(&doc_id) {
+                warn!("Invalid document UUID '{}': {}", doc_id, e);
+                return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+            }
+            let vv: HashMap<String, i32> = serde_json::from_str(since_vv).map_err(|e| {
+                api_error(StatusCode::BAD_REQUEST, format!("Invalid since_vv, expected a JSON object of counters: {}", e))
+            })?;
+            ("versionVector", vv)
+        }
+        None => {
+            let (doc_uuid, prpl) = ensure_reader(&prpls, &org_id, &doc_id).await?;
+            match user_prefs_service::read_marker(&org_id, doc_uuid, &prpl).await {
+                Ok(Some(vv)) => ("readMarker", vv),
+                Ok(None) => {
+                    return Err(api_error(StatusCode::NOT_FOUND, format!("'{}' never marked document '{}' read, pass since_vv instead", prpl, doc_id)));
+                }
+                Err(e) => {
+                    error!("Failed to load the read marker of '{}' on document '{}': {}", prpl, doc_id, e);
+                    return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load the read marker on document '{}'", doc_id)));
+                }
+            }
+        }
+    };
+
+    let (loro_doc, ctx) = doc_load_service::load_loro_doc_or_error(&registry, &org_id, &doc_id).await?;
+    let digest = match unread_service::digest(&loro_doc, &ctx.peer_map, &since_vv) {
+        Ok(Ok(digest)) => digest,
+        Ok(Err(message)) => return Err(api_error(StatusCode::BAD_REQUEST, message)),
+        Err(e) => {
+            error!("Failed to compare document '{}' with an earlier state: {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to compare document '{}' with an earlier state", doc_id)));
+        }
+    };
+    let version_v = serde_json::to_value(loro_doc.state_vv()).map_err(|e| {
+        error!("Failed to serialize state_vv for document '{}': {}", doc_id, e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize state_vv for document '{}': {}", doc_id, e))
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(DocumentUnreadResponse {
+            doc_id,
+            since: since.to_string(),
+            version: ctx.doc_version,
+            version_v,
+            authors: digest.authors,
+            blocks: digest.blocks,
+        }),
+    ))
+}
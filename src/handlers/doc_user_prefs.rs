@@ -0,0 +1,68 @@
+use crate::{auth::auth, models::{api_error, ApiError, DocumentUserPrefsRequest, DocumentUserPrefsResponse}, services::{acl_service, user_prefs_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Get the preferences of the calling user on a document, with the changes since the user last read it
+pub async fn doc_user_prefs(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentUserPrefsResponse>), ApiError> {
+    let (doc_uuid, prpl) = ensure_reader(&prpls, &org_id, &doc_id).await?;
+
+    match user_prefs_service::get(&registry, &org_id, doc_uuid, &prpl).await {
+        Ok(prefs) => Ok((StatusCode::OK, Json(prefs))),
+        Err(e) => {
+            error!("Failed to get the preferences of '{}' on document '{}': {}", prpl, doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get the preferences on document '{}'", doc_id)))
+        }
+    }
+}
+
+/// Update the preferences of the calling user on a document, or mark it read
+pub async fn doc_user_prefs_put(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<DocumentUserPrefsRequest>,
+) -> Result<(StatusCode, Json<DocumentUserPrefsResponse>), ApiError> {
+    let (doc_uuid, prpl) = ensure_reader(&prpls, &org_id, &doc_id).await?;
+
+    match user_prefs_service::update(&registry, &org_id, doc_uuid, &prpl, &request).await {
+        Ok(prefs) => Ok((StatusCode::OK, Json(prefs))),
+        Err(e) => {
+            error!("Failed to update the preferences of '{}' on document '{}': {}", prpl, doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update the preferences on document '{}'", doc_id)))
+        }
+    }
+}
+
+// Ensure the caller is a user of the organization with access to the document.
+// Returns the document UUID and the principal of the user.
+pub(crate) async fn ensure_reader(prpls: &Vec<String>, org_id: &str, doc_id: &str) -> Result<(Uuid, String), ApiError> {
+    let (user, prpl) = auth::ensure_org_member(prpls, org_id)?;
+    if user.is_none() {
+        return Err(api_error(StatusCode::BAD_REQUEST, "Preferences are kept for users only"));
+    }
+
+    let doc_uuid = match Uuid::parse_str(doc_id) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            warn!("Invalid document UUID '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id)));
+        }
+    };
+
+    match acl_service::document_db_permissions(org_id, doc_uuid, prpls).await {
+        Ok(Some(permissions)) if !permissions.is_empty() => Ok((doc_uuid, prpl)),
+        Ok(Some(_)) => Err(api_error(StatusCode::FORBIDDEN, format!("No access to document '{}'", doc_id))),
+        Ok(None) => Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' not found", doc_id))),
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load ACLs for document '{}'", doc_id)))
+        }
+    }
+}
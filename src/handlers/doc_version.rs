@@ -1,13 +1,12 @@
-use crate::{auth::auth, models::{DocumentVersionResponse, DocumentVersionRequest, ErrorResponse}, ws::docctx::DocContext};
-use axum::{extract::{State, Path, Extension}, http::StatusCode, Json};
+use crate::{auth::auth, models::{api_error, DocumentVersionResponse, DocumentVersionRequest, ErrorResponse, StorageTier}, ws::docctx::DocContext};
+use axum::{extract::{State, Path, Extension}, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}, Json};
 use base64::{engine::general_purpose, Engine as _};
-use loro_protocol::CrdtType;
-use loro_websocket_server::{HubRegistry, RoomKey};
+use loro_websocket_server::HubRegistry;
 use std::{collections::HashMap, sync::Arc};
 use tracing::{error, warn};
 use loro::{LoroDoc, ToJson, VersionVector};
 use uuid::Uuid;
-use crate::services::doc_db_service;
+use crate::services::{doc_load_service, negotiation_service::{self, Representation}, quarantine_service, range_service, render_service, version_vector_service};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum OutputFormat {
@@ -44,8 +43,13 @@ pub async fn doc_version(
     State(registry): State<Arc<HubRegistry<DocContext>>>,
     Extension(prpls): Extension<Vec<String>>,
     Path((org_id, doc_id)): Path<(String, String)>,
+    headers: HeaderMap,
     Json(request): Json<DocumentVersionRequest>,
-) -> Result<(StatusCode, Json<DocumentVersionResponse>), (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+
+    // Pick the representation from the Accept header, the format field shapes the JSON envelope
+    let representation = negotiation_service::negotiate(&headers, &negotiation_service::DOCUMENT_REPRESENTATIONS)?;
+    negotiation_service::ensure_format_applies(representation, request.format.as_deref())?;
 
     let output_format = match OutputFormat::from_query(request.format.clone()) {
         Ok(format) => format,
@@ -63,7 +67,7 @@ pub async fn doc_version(
     let _ = auth::ensure_service(&prpls, "colabri-app")?;
 
     // Parse the doc_id as an UUID
-    let _doc_uuid = match Uuid::parse_str(&doc_id) {
+    let doc_uuid = match Uuid::parse_str(&doc_id) {
         Ok(uuid) => uuid,
         Err(e) => {
             warn!("Invalid document UUID '{}': {}", doc_id, e);
@@ -83,24 +87,21 @@ pub async fn doc_version(
     // We need the loro_doc of the specified version
     let mut target_loro_doc: Option<LoroDoc> = None;
     let mut target_peer_map: Option<HashMap<u64, String>> = None;
+    let mut target_tier = StorageTier::Hot;
 
     // 1. Check if the document of that targeted version is currently open in the Hub.
-    let hubs = registry.hubs().lock().await;
-    if let Some(hub) = hubs.get(&org_id) {
-        let h = hub.lock().await;
-        if let Some(doc_state) = h.docs.get(&RoomKey {crdt: CrdtType::Loro, room: doc_id.clone()}) {
-            if let (Some(doc), Some(ctx)) = (doc_state.doc.get_loro_doc(), &doc_state.ctx) {
-                if ctx.doc_version == version {
-                    target_loro_doc = Some(doc.clone());
-                    target_peer_map = Some(ctx.peer_map.clone());
-                }
-            }
+    // A fork is used because the document gets checked out below.
+    if let Some((doc, ctx)) = doc_load_service::get_open_loro_doc(&registry, &org_id, &doc_id).await {
+        if ctx.doc_version == version {
+            target_loro_doc = Some(doc);
+            target_peer_map = Some(ctx.peer_map);
+            target_tier = ctx.tier;
         }
     }
     
     // 2. If not currently loaded, we try to load the document of that version from the database.
     if target_loro_doc.is_none() {
-        let (snapshot, ctx) = match doc_db_service::fetch_doc_snapshot_from_db(&org_id, &doc_id, Some(version)).await {
+        let (snapshot, ctx) = match quarantine_service::fetch_doc_snapshot(&org_id, &doc_id, Some(version)).await {
             Ok(Some(res)) => res,
             Ok(None) => {
                 warn!("Document '{}' with version {} not found in organization '{}'", doc_id, version, org_id);
@@ -113,7 +114,7 @@ pub async fn doc_version(
             },
             Err(e) => {
                 error!("Error loading document '{}' in org '{}' with version {} from database: {}", doc_id, org_id, version, e);
-                let status = StatusCode::INTERNAL_SERVER_ERROR;
+                let status = quarantine_service::load_error_status(&e);
                 return Err((status, Json(ErrorResponse {
                     code: status.as_u16(),
                     status: status.to_string(),
@@ -135,6 +136,7 @@ pub async fn doc_version(
         })?;
         target_loro_doc = Some(loro_doc);
         target_peer_map = Some(ctx.peer_map.clone());
+        target_tier = ctx.tier;
     }
 
 
@@ -153,25 +155,31 @@ pub async fn doc_version(
     };
 
 
+    // Resolve the principals of the version vector through the peer map of the version
+    let target_peer_map = target_peer_map.unwrap_or_default();
+    let version_v = match version_v {
+        Some(vv) => match version_vector_service::resolve(&vv, &target_peer_map) {
+            Ok(vv) => Some(vv),
+            Err(problems) => {
+                warn!("Unresolvable version vector for document '{}' with version {}: {}", doc_id, version, problems.join("; "));
+                return Err(api_error(StatusCode::BAD_REQUEST, format!("Version vector can't be resolved for document '{}' with version {}: {}", doc_id, version, problems.join("; "))));
+            }
+        },
+        None => None,
+    };
+
     // Now we have the target_loro_doc, if a version vector is specified ...
     let frontiers = match &version_v {
         Some(vv) => {
-            // go back to the specific point in time specified by version_v. 
-            let loro_version_v = VersionVector::from_iter(vv.clone());
-            let frontier_result = std::panic::catch_unwind(|| loro_doc.vv_to_frontiers(&loro_version_v));
-            let frontiers = match frontier_result {
-                Ok(frontiers) => frontiers,
-                Err(e) => {
-                    error!("Failed to compute frontiers for version vector: {:?}", e);
-                    let status = StatusCode::INTERNAL_SERVER_ERROR;
-                    return Err((status, Json(ErrorResponse {
-                        code: status.as_u16(),
-                        status: status.to_string(),
-                        error: format!("Failed to compute frontiers for specified version vector"),
-                    })));
+            // go back to the specific point in time specified by version_v, once it is known to be within the document
+            let loro_version_v = match version_vector_service::validate(&loro_doc, vv) {
+                Ok(loro_version_v) => loro_version_v,
+                Err(problems) => {
+                    warn!("Invalid version vector for document '{}' with version {}: {}", doc_id, version, problems.join("; "));
+                    return Err(api_error(StatusCode::BAD_REQUEST, format!("Version vector out of range for document '{}' with version {}: {}", doc_id, version, problems.join("; "))));
                 }
             };
-            frontiers
+            loro_doc.vv_to_frontiers(&loro_version_v)
         },
         None => {
             // If no version vector is specified, use the current state of the document
@@ -192,7 +200,22 @@ pub async fn doc_version(
             })));
         }
     };
-    
+
+    // The version as the raw snapshot or rendered for reading
+    if representation == Representation::Binary {
+        let snapshot = loro_doc.export(loro::ExportMode::state_only(Some(&frontiers))).map_err(|e| {
+            error!("Failed to export document '{}' with version '{}' to binary: {}", doc_id, version, e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to export document '{}' with version '{}' to binary", doc_id, version))
+        })?;
+        return Ok(range_service::bytes_response(&headers, snapshot, representation.content_type()));
+    }
+    if representation != Representation::Json {
+        let json = loro_doc.get_deep_value().to_json_value();
+        return render_service::response(&registry, &org_id, doc_uuid, json, representation).await.map_err(|e| {
+            error!("Failed to render document '{}' with version '{}': {}", doc_id, version, e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to render document '{}' with version '{}' as {}", doc_id, version, representation.media_type()))
+        });
+    }
 
     let binary_str = if output_format.include_binary() {
         let binary_snapshot = loro_doc.export(loro::ExportMode::state_only(Some(&frontiers))).map_err(|e| {
@@ -217,7 +240,7 @@ pub async fn doc_version(
     };
 
     // Serialize the peer map
-    let peer_map = serde_json::to_value(target_peer_map.unwrap_or_default()).map_err(|e| {
+    let peer_map = serde_json::to_value(&target_peer_map).map_err(|e| {
         error!("Failed to serialize peer_map for document '{}' and version '{}': {}", &doc_id, version, e);
         let status = StatusCode::INTERNAL_SERVER_ERROR;
         (status, Json(ErrorResponse {
@@ -227,29 +250,20 @@ pub async fn doc_version(
         }))
     })?;
 
-    let version_v_json = match &version_v {
-        Some(vv) => {
-            let loro_version_v = VersionVector::from_iter(vv.clone());
-            serde_json::to_value(&loro_version_v).map_err(|e| {
-                error!("Failed to serialize specified version_v: {}", e);
-                let status = StatusCode::INTERNAL_SERVER_ERROR;
-                (status, Json(ErrorResponse {
-                    code: status.as_u16(),
-                    status: status.to_string(),
-                    error: format!("Failed to serialize specified version_v: {}", e),
-                }))
-            })?
-        },
-        None => {serde_json::to_value(loro_doc.state_vv()).map_err(|e| {
-                error!("Failed to serialize version_v for document '{}': {}", &doc_id, e);
-                let status = StatusCode::INTERNAL_SERVER_ERROR;
-                (status, Json(ErrorResponse {
-                    code: status.as_u16(),
-                    status: status.to_string(),
-                    error: format!("Failed to serialize version_v for document '{}': {}", &doc_id, e),
-                }))
-            })?},
+    let loro_version_v = match &version_v {
+        Some(vv) => VersionVector::from_iter(vv.clone()),
+        None => loro_doc.state_vv(),
     };
+    let version_v_json = serde_json::to_value(&loro_version_v).map_err(|e| {
+        error!("Failed to serialize version_v for document '{}': {}", &doc_id, e);
+        let status = StatusCode::INTERNAL_SERVER_ERROR;
+        (status, Json(ErrorResponse {
+            code: status.as_u16(),
+            status: status.to_string(),
+            error: format!("Failed to serialize version_v for document '{}': {}", &doc_id, e),
+        }))
+    })?;
+    let version_v_named = version_vector_service::named(&loro_version_v, &target_peer_map);
 
 
     // Return the result
@@ -260,9 +274,11 @@ pub async fn doc_version(
             binary: binary_str,
             version: version,
             version_v: version_v_json,
+            version_v_named,
             peer_map,
+            tier: target_tier,
         }),
-    ));
+    ).into_response());
     
 
 }
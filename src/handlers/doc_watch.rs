@@ -0,0 +1,35 @@
+use crate::{handlers::doc_user_prefs::ensure_reader, models::{api_error, ApiError, DocumentWatchResponse}, services::watch_service};
+use axum::{extract::{Extension, Path}, http::StatusCode, Json};
+use tracing::error;
+
+/// Watch a document, the calling user gets notified about its events
+pub async fn doc_watch(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentWatchResponse>), ApiError> {
+    let (doc_uuid, prpl) = ensure_reader(&prpls, &org_id, &doc_id).await?;
+
+    match watch_service::watch(&org_id, doc_uuid, &prpl).await {
+        Ok(watch) => Ok((StatusCode::OK, Json(watch))),
+        Err(e) => {
+            error!("Failed to watch document '{}' for '{}': {}", doc_id, prpl, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to watch document '{}'", doc_id)))
+        }
+    }
+}
+
+/// Stop watching a document
+pub async fn doc_unwatch(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<DocumentWatchResponse>), ApiError> {
+    let (doc_uuid, prpl) = ensure_reader(&prpls, &org_id, &doc_id).await?;
+
+    match watch_service::unwatch(&org_id, doc_uuid, &prpl).await {
+        Ok(watch) => Ok((StatusCode::OK, Json(watch))),
+        Err(e) => {
+            error!("Failed to unwatch document '{}' for '{}': {}", doc_id, prpl, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to unwatch document '{}'", doc_id)))
+        }
+    }
+}
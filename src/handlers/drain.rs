@@ -0,0 +1,46 @@
+use crate::{auth::auth, models::{ApiError, DrainStatusResponse}, services::{drain_service, hub_service}, ws::docctx::DocContext};
+use axum::{extract::{Extension, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+
+/// Start draining the pod, stop accepting rooms and save all dirty documents
+pub async fn drain_start(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+) -> Result<(StatusCode, Json<DrainStatusResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    // A repeated call just reports the progress of the running drain
+    let status = if drain_service::start(registry.clone()) { StatusCode::ACCEPTED } else { StatusCode::OK };
+    Ok((status, Json(load_status(&registry).await)))
+}
+
+/// Get the progress of draining the pod
+pub async fn drain_status(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+) -> Result<(StatusCode, Json<DrainStatusResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    Ok((StatusCode::OK, Json(load_status(&registry).await)))
+}
+
+async fn load_status(registry: &Arc<HubRegistry<DocContext>>) -> DrainStatusResponse {
+    let progress = drain_service::progress();
+    let rooms_open = hub_service::snapshot_rooms(registry).await.iter().filter(|room| room.is_doc).count();
+    DrainStatusResponse {
+        state: progress.state.as_str().to_string(),
+        started_at: progress.started_at,
+        finished_at: progress.finished_at,
+        deferred_saved: progress.deferred_saved,
+        rooms_total: progress.rooms_total,
+        rooms_saved: progress.rooms_saved,
+        rooms_closed: progress.rooms_closed,
+        rooms_open,
+        errors: progress.errors,
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{auth::auth, models::{api_error, ApiError, StatementDuplicatesAnalyzeResponse, StatementDuplicatesReport}, services::dedup_service};
+use axum::{extract::{Extension, Path, Query}, http::StatusCode, Json};
+use serde::Deserialize;
+use tracing::{error, info};
+
+#[derive(Deserialize)]
+pub struct DuplicatesAnalyzeQuery {
+    threshold: Option<f64>,
+}
+
+/// Get the latest report of the duplicated statements of an organization
+pub async fn statement_duplicates(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+) -> Result<(StatusCode, Json<StatementDuplicatesReport>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let report = dedup_service::report(&org_id)
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+        })?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("The statements of organization '{}' were never analyzed", org_id)))?;
+    Ok((StatusCode::OK, Json(report)))
+}
+
+/// Start analyzing the statements of an organization for duplicates
+pub async fn statement_duplicates_analyze(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Query(query): Query<DuplicatesAnalyzeQuery>,
+) -> Result<(StatusCode, Json<StatementDuplicatesAnalyzeResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let by_prpl = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let threshold = query.threshold.unwrap_or(dedup_service::DEFAULT_THRESHOLD);
+    if !(0.5..=1.0).contains(&threshold) {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid threshold {}. Use a similarity between 0.5 and 1.", threshold)));
+    }
+
+    let started = dedup_service::start(&org_id, threshold);
+    if started {
+        info!("Deduplication analysis of org '{}' started by '{}'", org_id, by_prpl);
+    }
+    Ok((StatusCode::ACCEPTED, Json(StatementDuplicatesAnalyzeResponse { org_id, threshold, started })))
+}
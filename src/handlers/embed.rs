@@ -0,0 +1,61 @@
+use crate::{auth::auth, db::dbcolab::OrgEmbedSettingsRow, models::{api_error, ApiError, OrgEmbedSettingsRequest, OrgEmbedSettingsResponse}, services::embed_service};
+use axum::{extract::{Extension, Path}, http::StatusCode, Json};
+use tracing::{error, info};
+
+/// Get the embed settings of an organization
+pub async fn org_embed_settings(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+) -> Result<(StatusCode, Json<OrgEmbedSettingsResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin or the app service
+    if auth::ensure_service(&prpls, "colabri-app").is_err() {
+        let _ = auth::ensure_cloud_admin(&prpls)?;
+    }
+
+    let row = embed_service::get(&org_id).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    Ok((StatusCode::OK, Json(to_response(org_id, row))))
+}
+
+/// Set the origins allowed to frame the embedded views of an organization
+pub async fn org_embed_settings_set(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Json(request): Json<OrgEmbedSettingsRequest>,
+) -> Result<(StatusCode, Json<OrgEmbedSettingsResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let by_prpl = auth::ensure_cloud_admin(&prpls)?;
+
+    for origin in &request.frame_ancestors {
+        embed_service::normalize_origin(origin).map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+    }
+
+    let row = embed_service::set(&org_id, &request.frame_ancestors, &by_prpl).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    info!("Embed origins of organization '{}' set to {:?} by '{}'", org_id, row.frame_ancestors, by_prpl);
+
+    Ok((StatusCode::OK, Json(to_response(org_id, Some(row)))))
+}
+
+fn to_response(org_id: String, row: Option<OrgEmbedSettingsRow>) -> OrgEmbedSettingsResponse {
+    match row {
+        Some(row) => OrgEmbedSettingsResponse {
+            org_id,
+            frame_ancestors: row.frame_ancestors,
+            updated_at: Some(row.updated_at),
+            updated_by: Some(row.updated_by),
+        },
+        None => OrgEmbedSettingsResponse {
+            org_id,
+            frame_ancestors: Vec::new(),
+            updated_at: None,
+            updated_by: None,
+        },
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{auth::auth, models::{api_error, ApiError, ListResponse, OrgFeature, OrgFeatureSetRequest, PageQuery, RequestId}, services::feature_service::{self, Feature}};
+use axum::{extract::{Extension, Path, Query}, http::StatusCode, Json};
+use tracing::{error, info};
+
+/// List the features of an organization
+pub async fn org_features(
+    Extension(prpls): Extension<Vec<String>>,
+    Extension(request_id): Extension<RequestId>,
+    Path(org_id): Path<String>,
+    Query(page): Query<PageQuery>,
+) -> Result<(StatusCode, Json<ListResponse<OrgFeature>>), ApiError> {
+
+    // Ensure the caller is a cloud admin or the app service
+    if auth::ensure_service(&prpls, "colabri-app").is_err() {
+        let _ = auth::ensure_cloud_admin(&prpls)?;
+    }
+
+    Ok((StatusCode::OK, Json(ListResponse::page(load_features(&org_id).await?, &page, &request_id))))
+}
+
+/// Enable or disable a feature for an organization
+pub async fn org_feature_set(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, feature)): Path<(String, String)>,
+    Json(request): Json<OrgFeatureSetRequest>,
+) -> Result<(StatusCode, Json<OrgFeature>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let by_prpl = auth::ensure_cloud_admin(&prpls)?;
+
+    let feature = Feature::parse(&feature).ok_or_else(|| {
+        api_error(StatusCode::BAD_REQUEST, format!("Unknown feature '{}'", feature))
+    })?;
+
+    if let Err(e) = feature_service::set(&org_id, feature, request.enabled, &by_prpl).await {
+        error!("{}", e);
+        return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+    }
+    info!("Feature '{}' of organization '{}' set to {:?} by '{}'", feature, org_id, request.enabled, by_prpl);
+
+    // Answer with the feature as it applies now, the flag may have been removed
+    load_features(&org_id)
+        .await?
+        .into_iter()
+        .find(|state| state.feature == feature.as_str())
+        .map(|state| (StatusCode::OK, Json(state)))
+        .ok_or_else(|| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Feature '{}' missing from the features of organization '{}'", feature, org_id)))
+}
+
+async fn load_features(org_id: &str) -> Result<Vec<OrgFeature>, ApiError> {
+    let states = feature_service::list(org_id).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    Ok(states
+        .into_iter()
+        .map(|state| OrgFeature {
+            feature: state.feature.as_str().to_string(),
+            enabled: state.enabled,
+            default_enabled: state.default_enabled,
+            updated_at: state.updated_at,
+            updated_by: state.updated_by,
+        })
+        .collect())
+}
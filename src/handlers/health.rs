@@ -1,23 +1,47 @@
 use crate::models::{HealthResponse, ReadyResponse};
-use axum::Json;
+use crate::services::{drain_service, watchdog_service};
+use axum::{http::StatusCode, Json};
+use serde_json::Value;
 use tracing::debug;
 
-/// Health check endpoint
-pub async fn health_check() -> Json<HealthResponse> {
+/// Health check endpoint, fails when the watchdog detected a wedged hub lock
+pub async fn health_check() -> (StatusCode, Json<HealthResponse>) {
     debug!("Health check requested");
-    Json(HealthResponse {
+    if !watchdog_service::is_healthy() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(HealthResponse {
+            status: "failing".to_string(),
+            message: "Watchdog lock probes are failing".to_string(),
+        }));
+    }
+    (StatusCode::OK, Json(HealthResponse {
         status: "ok".to_string(),
         message: "Server is running".to_string(),
-    })
+    }))
 }
 
-/// Readiness check endpoint
-pub async fn ready_check() -> Json<ReadyResponse> {
+/// Readiness check endpoint, fails while the pod is draining or the database is unreachable,
+/// so no new traffic is routed to it
+pub async fn ready_check() -> (StatusCode, Json<ReadyResponse>) {
     debug!("Readiness check requested");
-    // In a real application, you might check database connectivity,
-    // cache availability, or other dependencies here.
-    Json(ReadyResponse {
+    if drain_service::is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ReadyResponse {
+            status: "draining".to_string(),
+            message: "Pod is draining".to_string(),
+        }));
+    }
+    if !watchdog_service::is_db_reachable() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(ReadyResponse {
+            status: "failing".to_string(),
+            message: "Database probes are failing".to_string(),
+        }));
+    }
+    (StatusCode::OK, Json(ReadyResponse {
         status: "ok".to_string(),
         message: "Service is ready".to_string(),
-    })
+    }))
+}
+
+/// Schema of the webhook events, so integrators can generate consumers
+pub async fn events_schema() -> Json<Value> {
+    Json(crate::docs::events::events_schema())
 }
@@ -0,0 +1,62 @@
+use crate::{auth::auth, db::dbcolab::OrgLocaleSettingsRow, models::{api_error, ApiError, OrgLocaleSettingsRequest, OrgLocaleSettingsResponse}, services::locale_service};
+use axum::{extract::{Extension, Path}, http::StatusCode, Json};
+use tracing::{error, info};
+
+/// Get the locale settings of an organization
+pub async fn org_locale_settings(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+) -> Result<(StatusCode, Json<OrgLocaleSettingsResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin or the app service
+    if auth::ensure_service(&prpls, "colabri-app").is_err() {
+        let _ = auth::ensure_cloud_admin(&prpls)?;
+    }
+
+    let row = locale_service::get(&org_id).await.map_err(|e| {
+        error!("{}", e);
+        api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+    })?;
+    Ok((StatusCode::OK, Json(to_response(org_id, row))))
+}
+
+/// Set the locale settings of an organization
+pub async fn org_locale_settings_set(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Json(request): Json<OrgLocaleSettingsRequest>,
+) -> Result<(StatusCode, Json<OrgLocaleSettingsResponse>), ApiError> {
+
+    // Ensure the caller is a cloud admin or the app service
+    let by_prpl = match auth::ensure_service(&prpls, "colabri-app") {
+        Ok(prpl) => prpl,
+        Err(_) => auth::ensure_cloud_admin(&prpls)?,
+    };
+
+    let row = match locale_service::set(&org_id, &request.settings, &request.content_types, &by_prpl).await {
+        Ok(Ok(row)) => row,
+        Ok(Err(e)) => return Err(api_error(StatusCode::BAD_REQUEST, e)),
+        Err(e) => {
+            error!("{}", e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+    };
+    info!("Locale settings of organization '{}' set by '{}'", org_id, by_prpl);
+
+    Ok((StatusCode::OK, Json(to_response(org_id, Some(row)))))
+}
+
+fn to_response(org_id: String, row: Option<OrgLocaleSettingsRow>) -> OrgLocaleSettingsResponse {
+    let org_locale = match &row {
+        Some(row) => locale_service::OrgLocale { settings: locale_service::settings_of(row), content_types: locale_service::content_types_of(row) },
+        None => locale_service::OrgLocale::default(),
+    };
+    OrgLocaleSettingsResponse {
+        org_id,
+        resolved: org_locale.resolve(None).to_resolved(),
+        settings: org_locale.settings,
+        content_types: org_locale.content_types,
+        updated_at: row.as_ref().map(|row| row.updated_at),
+        updated_by: row.map(|row| row.updated_by),
+    }
+}
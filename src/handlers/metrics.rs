@@ -0,0 +1,90 @@
+use crate::services::{acl_cache_service, drain_service, hub_service, journal_service, load_limit_service, org_diagnostics_service, quarantine_service, save_retry_service, save_verification_service, watchdog_service};
+use crate::models::OrgDiagnostics;
+use crate::ws::{docctx::DocContext, userctx};
+use crate::ws_deflate;
+use axum::{extract::State, http::{header, StatusCode}, response::{IntoResponse, Response}};
+use loro_websocket_server::HubRegistry;
+use std::fmt::Write;
+use std::sync::Arc;
+
+// Append a gauge in the Prometheus text exposition format
+fn gauge(body: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(body, "# HELP {} {}", name, help);
+    let _ = writeln!(body, "# TYPE {} gauge", name);
+    let _ = writeln!(body, "{} {}", name, value);
+}
+
+// Append a gauge with a value per org. The metrics are scraped into shared dashboards, so the orgs
+// are always aggregated like the org diagnostics: small orgs are only counted under org="other".
+fn org_gauge(body: &mut String, name: &str, help: &str, orgs: &[OrgDiagnostics], value: impl Fn(&OrgDiagnostics) -> u32) {
+    let _ = writeln!(body, "# HELP {} {}", name, help);
+    let _ = writeln!(body, "# TYPE {} gauge", name);
+    for org in orgs {
+        let label = org.org_id.as_deref().unwrap_or("other").replace('\\', "\\\\").replace('"', "\\\"");
+        let _ = writeln!(body, "{}{{org=\"{}\"}} {}", name, label, value(org));
+    }
+}
+
+/// Metrics of the instance in the Prometheus text format, served on the admin port only
+pub async fn metrics(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+) -> Response {
+    let mut n_conn = 0;
+    let mut n_rooms = 0;
+    let mut n_doc_rooms = 0;
+    let mut n_ephemeral_rooms = 0;
+    let mut n_dirty_docs = 0;
+    for room in hub_service::cached_rooms(&registry).await.iter() {
+        n_rooms += 1;
+        if room.is_doc {
+            n_doc_rooms += 1;
+        }
+        if room.is_ephemeral {
+            n_ephemeral_rooms += 1;
+        }
+        if room.dirty {
+            n_dirty_docs += 1;
+        }
+        n_conn += room.subscribers;
+    }
+
+    let mut body = String::new();
+    gauge(&mut body, "colabri_doc_connections", "Open WebSocket subscriptions", n_conn as f64);
+    gauge(&mut body, "colabri_doc_rooms", "Open rooms", n_rooms as f64);
+    gauge(&mut body, "colabri_doc_doc_rooms", "Open document rooms", n_doc_rooms as f64);
+    gauge(&mut body, "colabri_doc_ephemeral_rooms", "Open ephemeral rooms", n_ephemeral_rooms as f64);
+    gauge(&mut body, "colabri_doc_dirty_docs", "Open documents with unsaved changes", n_dirty_docs as f64);
+    gauge(&mut body, "colabri_doc_user_contexts", "Cached user contexts", userctx::get_user_ctx_cache().entry_count() as f64);
+    gauge(&mut body, "colabri_doc_quarantined_docs", "Documents quarantined by this instance", quarantine_service::quarantined_total() as f64);
+    gauge(&mut body, "colabri_doc_failing_saves", "Documents whose saves are failing", save_retry_service::failing_saves().len() as f64);
+    gauge(&mut body, "colabri_doc_verified_saves", "Saves whose JSON was verified against their snapshot by this instance", save_verification_service::verified_total() as f64);
+    gauge(&mut body, "colabri_doc_diverging_saves", "Saves whose JSON diverged from their snapshot on this instance", save_verification_service::divergences_total() as f64);
+    gauge(&mut body, "colabri_doc_stale_unsaved_docs", "Documents with unsaved changes older than the alert threshold", save_retry_service::stale_unsaved_count() as f64);
+    gauge(&mut body, "colabri_doc_journal_append_failures", "Updates refused by this instance because they could not be journaled", journal_service::append_failures_total() as f64);
+    let (ws_sent, ws_received) = (ws_deflate::sent_bytes_total(), ws_deflate::received_bytes_total());
+    gauge(&mut body, "colabri_doc_ws_compressed_connections", "WebSocket connections that negotiated permessage-deflate on this instance", ws_deflate::compressed_connections_total() as f64);
+    gauge(&mut body, "colabri_doc_ws_sent_bytes", "Payload bytes sent to the compressed WebSocket connections before compression", ws_sent.raw as f64);
+    gauge(&mut body, "colabri_doc_ws_sent_wire_bytes", "Payload bytes sent to the compressed WebSocket connections after compression", ws_sent.wire as f64);
+    gauge(&mut body, "colabri_doc_ws_received_bytes", "Payload bytes received from the compressed WebSocket connections after decompression", ws_received.raw as f64);
+    gauge(&mut body, "colabri_doc_ws_received_wire_bytes", "Payload bytes received from the compressed WebSocket connections before decompression", ws_received.wire as f64);
+    gauge(&mut body, "colabri_doc_ws_compression_saved_bytes", "Payload bytes kept off the wire by permessage-deflate on this instance", (ws_sent.saved() + ws_received.saved()) as f64);
+    gauge(&mut body, "colabri_doc_loads_running", "Document loads running", load_limit_service::running() as f64);
+    gauge(&mut body, "colabri_doc_load_queue_depth", "Document loads waiting for a free slot", load_limit_service::queue_depth() as f64);
+    gauge(&mut body, "colabri_doc_loads_waited", "Document loads that waited for a free slot on this instance", load_limit_service::waited_total() as f64);
+    gauge(&mut body, "colabri_doc_load_wait_seconds", "Time document loads spent waiting for a free slot on this instance", load_limit_service::wait_seconds_total());
+    gauge(&mut body, "colabri_doc_loads_rejected", "Document loads refused because too many were running and waiting", load_limit_service::rejected_total() as f64);
+    gauge(&mut body, "colabri_doc_acl_cache_hits", "Access decisions of room joins answered from the cache on this instance", acl_cache_service::hits_total() as f64);
+    gauge(&mut body, "colabri_doc_acl_cache_misses", "Access decisions of room joins taken against the database on this instance", acl_cache_service::misses_total() as f64);
+    gauge(&mut body, "colabri_doc_healthy", "Whether the watchdog lock probes succeed", if watchdog_service::is_healthy() { 1.0 } else { 0.0 });
+    gauge(&mut body, "colabri_doc_db_reachable", "Whether the watchdog database probes succeed", if watchdog_service::is_db_reachable() { 1.0 } else { 0.0 });
+    gauge(&mut body, "colabri_doc_draining", "Whether the pod is draining", if drain_service::is_draining() { 1.0 } else { 0.0 });
+
+    let orgs = org_diagnostics_service::collect(&registry, true).await;
+    org_gauge(&mut body, "colabri_doc_org_connections", "Connections per org", &orgs, |org| org.n_conn);
+    org_gauge(&mut body, "colabri_doc_org_rooms", "Open rooms per org", &orgs, |org| org.n_rooms);
+    org_gauge(&mut body, "colabri_doc_org_doc_rooms", "Open document rooms per org", &orgs, |org| org.n_doc_rooms);
+    org_gauge(&mut body, "colabri_doc_org_dirty_docs", "Open documents with unsaved changes per org", &orgs, |org| org.n_dirty_docs);
+    org_gauge(&mut body, "colabri_doc_org_failing_saves", "Documents whose saves are failing per org", &orgs, |org| org.n_failing_saves);
+    org_gauge(&mut body, "colabri_doc_org_orgs", "Orgs counted per entry, more than one for the bucket of small orgs", &orgs, |org| org.n_orgs);
+    (StatusCode::OK, [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")], body).into_response()
+}
@@ -0,0 +1,30 @@
+use crate::{auth::auth, models::{ApiError, UserPrincipalsRequest, UserPrincipalsResponse}, services::membership_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+
+/// Apply the principals of a user pushed by the app service, e.g. after an org membership change
+pub async fn user_principals_push(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path(uid): Path<String>,
+    Json(request): Json<UserPrincipalsRequest>,
+) -> Result<(StatusCode, Json<UserPrincipalsResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let outcome = membership_service::apply_principals(&registry, &uid, request.prpls).await;
+    Ok((
+        StatusCode::OK,
+        Json(UserPrincipalsResponse {
+            uid,
+            cached: outcome.cached,
+            connections: outcome.connections,
+            rooms: outcome.rooms,
+            downgraded: outcome.downgraded,
+            revoked: outcome.revoked,
+            closed_rooms: outcome.closed_rooms,
+        }),
+    ))
+}
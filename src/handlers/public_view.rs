@@ -0,0 +1,150 @@
+use crate::{config, db::dbcolab::DocumentShareTokenRow, models::PublicViewCard, services::{embed_service, feature_service::{self, Feature}, public_view_service::{self, PublishedDoc}, summary_service}, ws::docctx::DocContext};
+use axum::{extract::{Path, State}, http::{header, HeaderMap, StatusCode}, response::{IntoResponse, Response}, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::error;
+
+// Size of the iframe in the embed snippet
+const EMBED_WIDTH: u32 = 640;
+const EMBED_HEIGHT: u32 = 480;
+
+/// Public read-only rendering of the published version of a shared document
+pub async fn public_view(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Path(share_token): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    render_view(&registry, &share_token, &headers, false).await
+}
+
+/// Embed mode of the public view, may only be framed by the origins allowed for the organization
+pub async fn public_embed(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Path(share_token): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    render_view(&registry, &share_token, &headers, true).await
+}
+
+/// Link preview of a shared document, for unfurling in chat tools
+pub async fn public_view_card(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Path(share_token): Path<String>,
+) -> Response {
+    let (row, published) = match load(&registry, &share_token).await {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+
+    // Prefer the stored summary when it belongs to the published version
+    let excerpt = match summary_service::get(&row.org, &[row.document]).await {
+        Ok(summaries) => summaries.into_iter().find(|summary| summary.version as u32 == published.version).map(|summary| summary.summary),
+        Err(e) => {
+            error!("{}", e);
+            None
+        }
+    }
+    .unwrap_or_else(|| public_view_service::excerpt(&published.model, 280));
+
+    let base_url = format!("https://{}/public/{}", config::get_config().cloud_service_domain, share_token);
+    let card = PublicViewCard {
+        r#type: "rich".to_string(),
+        version: "1.0".to_string(),
+        title: public_view_service::title(&row),
+        excerpt,
+        updated_at: published.published_at,
+        provider_name: "Colabri".to_string(),
+        html: format!(
+            "<iframe src=\"{}/embed\" width=\"{}\" height=\"{}\" frameborder=\"0\" loading=\"lazy\"></iframe>",
+            base_url, EMBED_WIDTH, EMBED_HEIGHT
+        ),
+        url: base_url,
+        width: EMBED_WIDTH,
+        height: EMBED_HEIGHT,
+    };
+    (
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(card),
+    )
+        .into_response()
+}
+
+async fn render_view(registry: &Arc<HubRegistry<DocContext>>, share_token: &str, headers: &HeaderMap, embed: bool) -> Response {
+
+    // 1. Resolve the share token and load the published version
+    let (row, published) = match load(registry, share_token).await {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+
+    // 2. Render it, only the embed mode may be framed
+    let view = public_view_service::render(registry, &row, &published, embed).await;
+    let frame_ancestors = if embed { embed_service::frame_ancestors(&row.org).await } else { Vec::new() };
+
+    // 3. Let caches revalidate cheaply
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(',').any(|tag| tag.trim() == view.etag))
+        .unwrap_or(false);
+    let cache_headers = [
+        (header::CACHE_CONTROL, "public, max-age=300".to_string()),
+        (header::ETAG, view.etag),
+        (header::CONTENT_SECURITY_POLICY, embed_service::content_security_policy(&frame_ancestors)),
+    ];
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (
+        StatusCode::OK,
+        cache_headers,
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        view.html,
+    )
+        .into_response()
+}
+
+// Unknown, revoked and expired tokens all look the same
+async fn load(registry: &Arc<HubRegistry<DocContext>>, share_token: &str) -> Result<(DocumentShareTokenRow, PublishedDoc), Response> {
+    let row = match public_view_service::resolve_token(share_token).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return Err(not_found()),
+        Err(e) => {
+            error!("{}", e);
+            return Err(unavailable());
+        }
+    };
+    if !feature_service::is_enabled(&row.org, Feature::Publishing).await {
+        return Err(not_found());
+    }
+
+    match public_view_service::load_published(registry, &row).await {
+        Ok(Some(published)) => Ok((row, published)),
+        Ok(None) => Err(not_found()),
+        Err(e) => {
+            error!("{}", e);
+            Err(unavailable())
+        }
+    }
+}
+
+fn not_found() -> Response {
+    page(StatusCode::NOT_FOUND, "This link is invalid or has expired.")
+}
+
+fn unavailable() -> Response {
+    page(StatusCode::SERVICE_UNAVAILABLE, "This document is temporarily unavailable.")
+}
+
+fn page(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        format!("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><meta name=\"robots\" content=\"noindex\"><title>{}</title></head><body><p>{}</p></body></html>", status, message),
+    )
+        .into_response()
+}
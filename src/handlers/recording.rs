@@ -0,0 +1,94 @@
+use crate::{auth::auth, models::{api_error, ApiError, RecordingStartRequest, RecordingStatus}, services::recording_service};
+use axum::{extract::{Extension, Path}, http::{header, StatusCode}, response::{IntoResponse, Response}, Json};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+fn parse_doc_uuid(doc_id: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(doc_id).map_err(|e| {
+        warn!("Invalid document UUID '{}': {}", doc_id, e);
+        api_error(StatusCode::BAD_REQUEST, format!("Invalid document UUID '{}'", doc_id))
+    })
+}
+
+// The latest recording of a document, 404 when it was never recorded in the organization
+fn recording_of(org_id: &str, doc_id: &str, status: Option<RecordingStatus>) -> Result<RecordingStatus, ApiError> {
+    status
+        .filter(|status| status.org_id == org_id)
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("Document '{}' was not recorded", doc_id)))
+}
+
+/// Start recording the sync traffic of a document
+pub async fn doc_recording_start(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+    Json(request): Json<RecordingStartRequest>,
+) -> Result<(StatusCode, Json<RecordingStatus>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    match recording_service::start(&org_id, doc_uuid, &request).await {
+        Ok(Ok(status)) => Ok((StatusCode::CREATED, Json(status))),
+        Ok(Err(e)) => Err(api_error(StatusCode::CONFLICT, e)),
+        Err(e) => {
+            error!("Failed to start recording document '{}': {}", doc_id, e);
+            Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+/// Stop recording the sync traffic of a document
+pub async fn doc_recording_stop(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<RecordingStatus>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    recording_of(&org_id, &doc_id, recording_service::status(doc_uuid))?;
+    let status = recording_of(&org_id, &doc_id, recording_service::stop(doc_uuid))?;
+    Ok((StatusCode::OK, Json(status)))
+}
+
+/// Get the state of the latest recording of a document
+pub async fn doc_recording_status(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<RecordingStatus>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let status = recording_of(&org_id, &doc_id, recording_service::status(doc_uuid))?;
+    Ok((StatusCode::OK, Json(status)))
+}
+
+/// Download the frames of the latest recording of a document
+pub async fn doc_recording_download(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, doc_id)): Path<(String, String)>,
+) -> Result<Response, ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    let doc_uuid = parse_doc_uuid(&doc_id)?;
+    let (status, bytes) = match recording_service::download(doc_uuid).await {
+        Ok(Some((status, bytes))) if status.org_id == org_id => (status, bytes),
+        Ok(_) => return Err(api_error(StatusCode::NOT_FOUND, format!("Document '{}' was not recorded", doc_id))),
+        Err(e) => {
+            error!("Failed to read the recording of document '{}': {}", doc_id, e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+    };
+    let disposition = format!("attachment; filename=\"{}-{}.ndjson\"", doc_id, status.id);
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/x-ndjson".to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        bytes,
+    ).into_response())
+}
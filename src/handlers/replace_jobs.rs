@@ -0,0 +1,51 @@
+use crate::{auth::auth, models::{api_error, ApiError, BulkReplaceJobResponse, BulkReplaceRequest, BulkReplaceStartResponse}, services::bulk_replace_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Start finding and replacing text across the documents of an organization
+pub async fn replace_job_start(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+    Json(request): Json<BulkReplaceRequest>,
+) -> Result<(StatusCode, Json<BulkReplaceStartResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let by_prpl = auth::ensure_service(&prpls, "colabri-app")?;
+
+    let dry_run = request.dry_run;
+    let job_id = match bulk_replace_service::start(registry, &org_id, request, &by_prpl).await {
+        Ok(Ok(job_id)) => job_id,
+        Ok(Err(e)) => return Err(api_error(StatusCode::BAD_REQUEST, e)),
+        Err(e) => {
+            error!("{}", e);
+            return Err(api_error(StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+    };
+    info!("Find-and-replace job {} of org '{}' started by '{}', dry run: {}", job_id, org_id, by_prpl, dry_run);
+    Ok((StatusCode::ACCEPTED, Json(BulkReplaceStartResponse { job_id: job_id.to_string(), org_id, dry_run })))
+}
+
+/// Get a find-and-replace job with the matches per document
+pub async fn replace_job(
+    Extension(prpls): Extension<Vec<String>>,
+    Path((org_id, job_id)): Path<(String, String)>,
+) -> Result<(StatusCode, Json<BulkReplaceJobResponse>), ApiError> {
+
+    // Ensure the caller is a trusted service
+    let _ = auth::ensure_service(&prpls, "colabri-app")?;
+    let job_uuid = Uuid::parse_str(&job_id)
+        .map_err(|_| api_error(StatusCode::BAD_REQUEST, format!("Invalid job UUID '{}'", job_id)))?;
+
+    let job = bulk_replace_service::get(&org_id, job_uuid)
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+        })?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("Find-and-replace job '{}' not found in organization '{}'", job_id, org_id)))?;
+    Ok((StatusCode::OK, Json(job)))
+}
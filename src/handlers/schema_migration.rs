@@ -0,0 +1,36 @@
+use crate::{auth::auth, models::{api_error, ApiError, SchemaMigrationStatus}, services::schema_migration_service, ws::docctx::DocContext};
+use axum::{extract::{Extension, Path, State}, http::StatusCode, Json};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+
+/// Start migrating every document of an organization to the current schema version
+pub async fn org_schema_migration_start(
+    State(registry): State<Arc<HubRegistry<DocContext>>>,
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+) -> Result<(StatusCode, Json<SchemaMigrationStatus>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    // A repeated call just reports the progress of the running migration
+    let status = if schema_migration_service::start(registry, &org_id) { StatusCode::ACCEPTED } else { StatusCode::OK };
+    let progress = schema_migration_service::status(&org_id)
+        .ok_or_else(|| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("No schema migration of organization '{}'", org_id)))?;
+    Ok((status, Json(progress)))
+}
+
+/// Get the progress of the latest schema migration of an organization
+pub async fn org_schema_migration_status(
+    Extension(prpls): Extension<Vec<String>>,
+    Path(org_id): Path<String>,
+) -> Result<(StatusCode, Json<SchemaMigrationStatus>), ApiError> {
+
+    // Ensure the caller is a cloud admin
+    let _ = auth::ensure_cloud_admin(&prpls)?;
+
+    match schema_migration_service::status(&org_id) {
+        Some(progress) => Ok((StatusCode::OK, Json(progress))),
+        None => Err(api_error(StatusCode::NOT_FOUND, format!("No schema migration of organization '{}' ran on this pod", org_id))),
+    }
+}
@@ -0,0 +1,14 @@
+pub mod docs;
+pub mod handlers;
+pub mod models;
+pub mod routes;
+pub mod services;
+pub mod auth;
+pub mod cli;
+pub mod clients;
+pub mod config;
+pub mod db;
+pub mod doctypes;
+pub mod ws;
+pub mod ws_bridge;
+pub mod ws_deflate;
@@ -1,37 +1,38 @@
-mod docs;
-mod handlers;
-mod models;
-mod routes;
-mod services;
-mod auth;
-// mod websocket; // No longer needed - using loro-websocket-server directly
-mod clients;
-mod config;
-mod db;
-mod ws;
+// The modules live in the library crate, so benchmarks can reach them too
+use colabri_doc::{cli, clients, config, db, handlers, routes, services, ws, ws_bridge};
 
 use axum::Router;
+use clap::Parser;
 use config::Config;
-use docs::ApiDoc;
+use colabri_doc::docs::ApiDoc;
 use loro_websocket_server::{HubRegistry, ServerConfig};
-use routes::create_api_routes;
+use routes::{create_admin_routes, create_api_routes, create_public_routes};
 use std::{panic, sync::Arc};
 use tower_http::trace::TraceLayer;
 use tracing::{error, info, warn};
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use tracing_subscriber::{fmt, fmt::writer::BoxMakeWriter, prelude::*, EnvFilter};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-#[tokio::main]
+// Snapshot imports and exports are CPU bound, the worker threads keep them from stalling the other rooms
+#[tokio::main(flavor = "multi_thread")]
 async fn main() {
+    // Parse the command line, without subcommand the servers are started
+    let args = cli::Cli::parse();
+
     // Set panic hook for better error messages
     panic::set_hook(Box::new(|info| {
         eprintln!("PANIC: {info}");
     }));
 
-    // Initialize tracing
+    // Initialize tracing, subcommands log to stderr so their output can be piped
+    let log_writer = if args.command.is_some() {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     tracing_subscriber::registry()
-        .with(fmt::layer())
+        .with(fmt::layer().with_writer(log_writer))
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| {
             // Default to info level, but allow debug for our app
             "colabri_doc=debug,tower_http=debug,axum::rejection=trace,info".into()
@@ -41,9 +42,11 @@ async fn main() {
     info!("Starting server...");
 
     // Load configuration
+    let mut config_loaded = true;
     let app_config = Config::load().unwrap_or_else(|e| {
         error!("Failed to load configuration: {}", e);
         warn!("Using default configuration");
+        config_loaded = false;
         Config::default()
     });
 
@@ -55,6 +58,13 @@ async fn main() {
 
     let config = config::get_config();
 
+    // Check the dependencies and exit instead of serving
+    if args.selftest {
+        let report = services::selftest_service::run(config_loaded).await;
+        println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+        std::process::exit(if report.ok { 0 } else { 1 });
+    }
+
     // Initialize database connection if URL is provided
     if let Some(db_url) = &config.db_url {
         match db::dbcolab::init_db(db_url).await {
@@ -68,14 +78,37 @@ async fn main() {
         warn!("No database URL configured - WebSocket document loading will not be available");
     }
 
+    // Run a maintenance subcommand and exit instead of serving
+    if let Some(command) = args.command {
+        let registry = Arc::new(HubRegistry::new(ServerConfig::default()));
+        if let Err(e) = cli::run(command, registry).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Initialize user context cache
     ws::userctx::init_user_ctx_cache();
+    ws::userctx::prune_persisted_prpls().await;
 
     // Initialize connection context cache
     ws::connctx::init_conn_ctx_cache();
 
-    // Initialize App Service Client
-    if let Some(secret) = &config.cloud_auth_jwt_secret {
+    // Initialize App Service Client, or its mock for local development
+    if let Some(path) = &args.mock_app_service {
+        if config.environment != "development" {
+            error!("The mock app service is only available in the development environment");
+            std::process::exit(1);
+        }
+        match clients::app_service_client::init_mock_app_service_client(&path.to_string_lossy()) {
+            Ok(users) => warn!("Using the mock app service with the principals of {} users from {}", users, path.display()),
+            Err(e) => {
+                error!("Failed to initialize the mock app service: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(secret) = &config.cloud_auth_jwt_secret {
         if let Err(e) = clients::app_service_client::init_app_service_client(
             config.app_service_url(),
             secret.clone(),
@@ -95,7 +128,7 @@ async fn main() {
     let ws_config = ServerConfig {
         on_load_document: Some(std::sync::Arc::new(ws::wscolab::on_load_document)),
         on_save_document: Some(std::sync::Arc::new(ws::wscolab::on_save_document)),
-        save_interval_ms: config.doc_save_interval_ms,
+        save_interval_ms: services::save_policy_service::scheduler_tick_ms(),
         default_permission: loro_websocket_server::protocol::Permission::Write,
         authenticate: Some(std::sync::Arc::new(ws::wscolab::on_authenticate)),
         handshake_auth: Some(std::sync::Arc::new(ws::wscolab::on_auth_handshake)),
@@ -105,39 +138,79 @@ async fn main() {
     };
     let registry = Arc::new(HubRegistry::new(ws_config));
 
+    // Start the liveness watchdog
+    services::watchdog_service::spawn(registry.clone());
+
+    // Start the job archiving inactive documents
+    services::archival_service::spawn(registry.clone());
+
+    // Start persisting the saves deferred by the save policies
+    services::save_policy_service::spawn(ws::wscolab::save_deferred_document);
+
+    // Start retrying the saves that failed
+    services::save_retry_service::spawn(ws::wscolab::save_deferred_document);
+
+    // Start scanning saved documents for policy violations
+    services::policy_scan_service::spawn(registry.clone());
+
+    // Start notifying sheets about changes of the blocks they transclude
+    services::transclusion_service::spawn(registry.clone());
+
+    // Start numbering the new blocks of saved documents
+    services::numbering_service::spawn(registry.clone());
+
+    // Start sampling the usage per org
+    services::analytics_service::spawn(registry.clone());
+
+    // Start sending the digests of followed documents
+    services::digest_service::spawn(registry.clone());
+
+    // Start pushing the running announcements to the open rooms
+    services::announcement_service::spawn(registry.clone());
+
     // Start WebSocket server
     let ws_listener = tokio::net::TcpListener::bind(&ws_addr)
         .await
         .unwrap_or_else(|_| panic!("Failed to bind WebSocket server to {}", ws_addr));
 
     info!("📡 WebSocket server starting on ws://{}", ws_addr);
-    info!("⏱️ Document save interval set to {} ms", config.doc_save_interval_ms.unwrap_or(30_000));
+    info!("⏱️ Document save interval set to {} ms, save scheduler tick {} ms", config.doc_save_interval_ms.unwrap_or(30_000), services::save_policy_service::scheduler_tick_ms().unwrap_or(30_000));
 
     // Create API routes
     let api_routes = create_api_routes(registry.clone());
 
     // Combine all routes
     let app_routes = Router::new()
-        .route("/health", axum::routing::get(handlers::health_check))
-        .route("/ready", axum::routing::get(handlers::ready_check))
-        // Mount API routes
-        .nest("/api", api_routes)
+        .route("/api-docs/events.json", axum::routing::get(handlers::events_schema))
+        // Mount API routes, browsers may call them from the configured origins
+        .nest("/api", api_routes.layer(services::cors_service::cors_layer()))
+        // Mount the public views
+        .merge(create_public_routes(registry.clone()))
+        // Mount the WebSocket bridge, tunneling to the WebSocket server
+        .merge(ws_bridge::create_ws_bridge_routes())
         // Mount Swagger UI
         .merge(SwaggerUi::new("/swagger").url("/api-docs/openapi.json", ApiDoc::openapi()))
         // Add tracing layer
         .layer(TraceLayer::new_for_http());
 
-    
+    // Probes, metrics and admin endpoints are only served on the internal listener
+    let admin_routes = create_admin_routes(registry.clone())
+        .layer(TraceLayer::new_for_http());
+
 
     // Spawn WebSocket server task
-    tokio::spawn(async move {
+    let ws_registry = registry.clone();
+    let ws_server = tokio::spawn(async move {
         if let Err(e) =
-            loro_websocket_server::serve_incoming_with_registry(ws_listener, registry.clone()).await
+            loro_websocket_server::serve_incoming_with_registry(ws_listener, ws_registry).await
         {
             error!("WebSocket server error: {}", e);
         }
     });
 
+    // Flush the dirty documents before exiting on SIGTERM or SIGINT
+    services::shutdown_service::spawn(registry.clone(), ws_server);
+
     // Start the HTTP/API server
     let listener = tokio::net::TcpListener::bind(config.server_address())
         .await
@@ -150,7 +223,19 @@ async fn main() {
         config.server_address()
     );
 
+    // Start the internal admin server
+    let admin_listener = tokio::net::TcpListener::bind(config.admin_address())
+        .await
+        .unwrap_or_else(|_| panic!("Failed to bind the admin server to {}", config.admin_address()));
+    info!("🩺 Health, metrics and admin endpoints available at http://{}", config.admin_address());
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(admin_listener, admin_routes).await {
+            error!("Admin server error: {}", e);
+        }
+    });
+
     axum::serve(listener, app_routes)
+        .with_graceful_shutdown(services::shutdown_service::flushed())
         .await
         .expect("Server failed to start");
 
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Usage of an organization in a time bucket, summed over all instances
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgUsageBucket {
+    #[serde(rename = "bucketStart")]
+    pub bucket_start: DateTime<Utc>,
+    #[serde(rename = "peakRooms")]
+    pub peak_rooms: i64,
+    #[serde(rename = "peakDocRooms")]
+    pub peak_doc_rooms: i64,
+    #[serde(rename = "peakConn")]
+    pub peak_conn: i64,
+    #[serde(rename = "avgConn")]
+    pub avg_conn: f64,
+    #[serde(rename = "connectionMinutes")]
+    pub connection_minutes: f64,
+    // Accepted document updates
+    pub updates: i64,
+}
+
+/// Response with the usage of an organization over a range
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgAnalyticsResponse {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    pub range: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    #[serde(rename = "bucketSecs")]
+    pub bucket_secs: i64,
+    #[serde(rename = "peakConn")]
+    pub peak_conn: i64,
+    #[serde(rename = "peakDocRooms")]
+    pub peak_doc_rooms: i64,
+    #[serde(rename = "connectionMinutes")]
+    pub connection_minutes: f64,
+    pub updates: i64,
+    // Buckets without samples are left out
+    pub buckets: Vec<OrgUsageBucket>,
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Schedule an announcement for the connected clients of an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgAnnouncementRequest {
+    pub message: String,
+    // "info" (default), "warning" or "critical"
+    #[serde(default)]
+    pub level: Option<String>,
+    // Defaults to now
+    #[serde(rename = "startsAt", default)]
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+}
+
+/// An announcement broadcast to the connected clients of an organization
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct OrgAnnouncement {
+    pub id: Uuid,
+    pub message: String,
+    pub level: String,
+    #[serde(rename = "startsAt")]
+    pub starts_at: DateTime<Utc>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+}
@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A document that is up for archival
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ArchivalCandidate {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub name: String,
+    #[serde(rename = "workflowState")]
+    pub workflow_state: String,
+    #[serde(rename = "lastActivityAt")]
+    pub last_activity_at: DateTime<Utc>,
+    // When the archival job flagged the document, not set until the next run
+    #[serde(rename = "flaggedAt")]
+    pub flagged_at: Option<DateTime<Utc>>,
+    // When the document gets archived, unless there is activity before
+    #[serde(rename = "archiveAfter")]
+    pub archive_after: Option<DateTime<Utc>>,
+}
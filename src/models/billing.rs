@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Usage of an organization in a billing period
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgBillingUsage {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    // Documents that received at least one update
+    #[serde(rename = "activeDocuments")]
+    pub active_documents: i64,
+    // Sampled connection time of editors
+    #[serde(rename = "editorMinutes")]
+    pub editor_minutes: f64,
+    // Average of the daily storage samples
+    #[serde(rename = "storageBytes")]
+    pub storage_bytes: i64,
+    #[serde(rename = "peakStorageBytes")]
+    pub peak_storage_bytes: i64,
+    pub exports: i64,
+}
+
+/// Usage report of all organizations for a billing period
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BillingReportResponse {
+    // Month of the report, e.g. 2026-09
+    pub period: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    // Whether the period is still running
+    pub partial: bool,
+    pub orgs: Vec<OrgBillingUsage>,
+}
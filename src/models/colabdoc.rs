@@ -38,6 +38,7 @@ pub enum ColabModelPermission {
     #[serde(rename = "add-remove")]
     AddRemove,
     Delete,
+    Suggest,
 }
 
 impl fmt::Display for ColabModelPermission {
@@ -48,6 +49,7 @@ impl fmt::Display for ColabModelPermission {
             ColabModelPermission::Manage => write!(f, "manage"),
             ColabModelPermission::AddRemove => write!(f, "add-remove"),
             ColabModelPermission::Delete => write!(f, "delete"),
+            ColabModelPermission::Suggest => write!(f, "suggest"),
         }
     }
 }
@@ -77,6 +79,12 @@ pub struct ColabModelProperties {
         skip_serializing_if = "Option::is_none"
     )]
     pub lang_codes: Option<Vec<String>>,
+    #[serde(
+        rename = "workflowState",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub workflow_state: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -104,6 +112,10 @@ pub enum ColabSheetBlock {
     Barcode(ColabSheetBarcodeBlock),
     #[serde(rename = "symbol-grid")]
     Symbol(ColabSheetSymbolBlock),
+    #[serde(rename = "transclusion")]
+    Transclusion(ColabSheetTransclusionBlock),
+    #[serde(rename = "board")]
+    Board(ColabSheetBoardBlock),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -181,6 +193,45 @@ pub struct ColabSymbolModel {
     pub r#type: String,
 }
 
+/// A planning board: columns of cards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColabSheetBoardBlock {
+    pub title: TextElement,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    pub acls: HashMap<ColabModelPermission, Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    pub columns: Vec<ColabBoardColumn>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColabBoardColumn {
+    pub id: String,
+    pub title: String,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    pub cards: Vec<ColabBoardCard>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColabBoardCard {
+    pub id: String,
+    pub title: TextElement,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+    pub status: String,
+}
+
+/// A block showing a block of another document, the server resolves its content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColabSheetTransclusionBlock {
+    #[serde(rename = "sourceDocId")]
+    pub source_doc_id: uuid::Uuid,
+    #[serde(rename = "sourceBlockId")]
+    pub source_block_id: String,
+    // Hash of the source block the sheet was last notified about
+    #[serde(rename = "sourceHash", default, skip_serializing_if = "Option::is_none")]
+    pub source_hash: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColabSheetStatementGridBlock {
     pub title: TextElement,
@@ -229,7 +280,7 @@ pub struct ColabStatementElement {
     pub approvals: HashMap<String, ColabUserApproval>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ColabApprovalState {
     Draft,
@@ -284,7 +335,7 @@ impl fmt::Display for ColabCommentType {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ColabCommentState {
     Open,
@@ -302,12 +353,112 @@ impl fmt::Display for ColabCommentState {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColabComment {
+    #[serde(default = "uuid::Uuid::new_v4")]
+    pub id: uuid::Uuid,
+    #[serde(rename = "parentId", default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<uuid::Uuid>,
     #[serde(rename = "type")]
     pub r#type: ColabCommentType,
     pub state: ColabCommentState,
     pub author: uuid::Uuid,
     pub text: TextElement,
     pub timestamp: DateTime<Utc>,
+    #[serde(rename = "editedAt", default, skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<ColabCommentAnchor>,
+}
+
+/// A range in the text of a block or language.
+/// `start` and `end` are encoded Loro cursors, the offsets are the last known resolved positions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColabCommentAnchor {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub quote: String,
+    #[serde(rename = "startOffset", default)]
+    pub start_offset: usize,
+    #[serde(rename = "endOffset", default)]
+    pub end_offset: usize,
+    #[serde(default)]
+    pub orphaned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColabSuggestionState {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+impl fmt::Display for ColabSuggestionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColabSuggestionState::Pending => write!(f, "pending"),
+            ColabSuggestionState::Accepted => write!(f, "accepted"),
+            ColabSuggestionState::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+/// A suggested edit to a block or language.
+/// `update` is a base64 encoded Loro update that is only imported into the document when accepted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColabSuggestion {
+    pub id: uuid::Uuid,
+    pub path: String,
+    pub author: String,
+    pub state: ColabSuggestionState,
+    pub update: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(rename = "decidedBy", default, skip_serializing_if = "Option::is_none")]
+    pub decided_by: Option<String>,
+    #[serde(rename = "decidedAt", default, skip_serializing_if = "Option::is_none")]
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColabApprovalRoundState {
+    Active,
+    Completed,
+    Cancelled,
+}
+
+impl fmt::Display for ColabApprovalRoundState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColabApprovalRoundState::Active => write!(f, "active"),
+            ColabApprovalRoundState::Completed => write!(f, "completed"),
+            ColabApprovalRoundState::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// An approval round freezes blocks or languages for everyone but the approvers,
+/// until all approvers responded or the round is cancelled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColabApprovalRound {
+    pub id: uuid::Uuid,
+    pub state: ColabApprovalRoundState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<ColabApprovalState>,
+    pub paths: Vec<String>,
+    pub approvers: Vec<String>,
+    // The version of the document when the round started
+    pub version: u32,
+    #[serde(rename = "versionV")]
+    pub version_v: HashMap<u64, i32>,
+    #[serde(rename = "startedBy")]
+    pub started_by: String,
+    #[serde(rename = "startedAt")]
+    pub started_at: DateTime<Utc>,
+    #[serde(rename = "endedBy", default, skip_serializing_if = "Option::is_none")]
+    pub ended_by: Option<String>,
+    #[serde(rename = "endedAt", default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,4 +1,5 @@
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -11,8 +12,57 @@ pub struct DiagnosticsResponse {
     pub n_ephemeral_rooms: u32,
     pub n_dirty_docs: u32,
     pub n_user_ctx: u32,
+    pub n_quarantined_docs: u64,
+    pub n_stale_unsaved_docs: u32,
+    pub failing_saves: Vec<FailingSaveInfo>,
+    pub client_versions: Vec<ClientVersionCount>,
     pub cpu_usage: f32,
     pub memory_alloc: u64,
     pub memory_total: u64,
     pub memory_free: u64,
-}
\ No newline at end of file
+}
+
+/// A document whose saves are failing and waiting for a retry
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct FailingSaveInfo {
+    pub org_id: String,
+    pub doc_id: String,
+    pub attempts: u32,
+    pub failing_since: DateTime<Utc>,
+    pub next_retry_in_ms: u64,
+    pub last_error: String,
+}
+
+/// Connections of the clients announcing a version
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ClientVersionCount {
+    pub client_version: Option<String>,
+    pub schema_version: Option<String>,
+    pub n_conn: u32,
+    // Whether the clients should upgrade, or would be refused when they reconnect
+    pub deprecated: bool,
+}
+
+/// Activity of an organization on this instance, or of a bucket of small organizations
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgDiagnostics {
+    // None for the bucket of organizations below the threshold
+    pub org_id: Option<String>,
+    // Number of organizations counted in this entry
+    pub n_orgs: u32,
+    pub n_conn: u32,
+    pub n_rooms: u32,
+    pub n_doc_rooms: u32,
+    pub n_dirty_docs: u32,
+    pub n_failing_saves: u32,
+}
+
+/// Response for the diagnostics per organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgDiagnosticsResponse {
+    // Whether small organizations were bucketed together without their identifiers
+    pub aggregated: bool,
+    // Connections an organization needs to be listed on its own when aggregated
+    pub min_org_conn: u32,
+    pub orgs: Vec<OrgDiagnostics>,
+}
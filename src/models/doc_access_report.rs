@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The effective permissions of one principal on one scope of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AccessReportEntry {
+    pub prpl: String,
+    pub path: String,
+    pub level: String,
+    pub permissions: Vec<String>,
+    pub sources: Vec<String>,
+}
+
+/// Response for the access report of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentAccessReportResponse {
+    pub entries: Vec<AccessReportEntry>,
+}
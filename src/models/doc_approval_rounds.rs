@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The response of one approver on one block or language
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ApproverResponseView {
+    pub approver: String,
+    pub path: String,
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<DateTime<Utc>>,
+}
+
+/// An approval round and the responses of its approvers
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ApprovalRoundView {
+    pub id: Uuid,
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+    pub paths: Vec<String>,
+    pub approvers: Vec<String>,
+    pub version: u32,
+    #[serde(rename = "versionV")]
+    pub version_v: HashMap<u64, i32>,
+    #[serde(rename = "startedBy")]
+    pub started_by: String,
+    #[serde(rename = "startedAt")]
+    pub started_at: DateTime<Utc>,
+    #[serde(rename = "endedBy", skip_serializing_if = "Option::is_none")]
+    pub ended_by: Option<String>,
+    #[serde(rename = "endedAt", skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<DateTime<Utc>>,
+    pub responses: Vec<ApproverResponseView>,
+}
+
+/// Request for starting an approval round
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentApprovalRoundStartRequest {
+    // The blocks or languages to freeze, all of them when omitted
+    pub paths: Option<Vec<String>>,
+    pub approvers: Vec<String>,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Request for cancelling an approval round
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentApprovalRoundCancelRequest {
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Response returned after starting or cancelling an approval round
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentApprovalRoundResponse {
+    pub round: ApprovalRoundView,
+}
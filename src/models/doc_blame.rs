@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A run of characters that were inserted by the same change
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BlameRun {
+    // The text field of the block, "title" or "textElement"
+    pub field: String,
+    pub text: String,
+    // Unicode offsets within the field
+    pub start: usize,
+    pub end: usize,
+    // Peer that made the change, as string since peer ids exceed the JSON safe integer range
+    pub peer: Option<String>,
+    // Principal behind the peer, from the peer registry of the document
+    pub prpl: Option<String>,
+    // Time of the change, only known when timestamps were recorded
+    pub timestamp: Option<DateTime<Utc>>,
+    pub lamport: Option<u32>,
+}
+
+/// Response with the blame of a block
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentBlameResponse {
+    #[serde(rename = "blockId")]
+    pub block_id: String,
+    pub version: u32,
+    pub runs: Vec<BlameRun>,
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Response of switching the lazy block mode of a sheet
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentLazyBlocksResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    // Whether the sheet now loads its statement grids lazily
+    pub lazy: bool,
+    // Rooms syncing the bodies of the statement grids, `<docId>~<blockId>`
+    #[serde(rename = "blockRooms")]
+    pub block_rooms: Vec<String>,
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// An approver of the cited statement language
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CitationApprover {
+    pub prpl: String,
+    pub state: String,
+    pub date: DateTime<Utc>,
+}
+
+/// A statement language at a specific version, rendered for citation
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCitationResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub version: u32,
+    pub lang: String,
+    pub html: String,
+    pub text: String,
+    #[serde(rename = "approvalStatus")]
+    pub approval_status: String,
+    pub approvers: Vec<CitationApprover>,
+    // The date of the latest approval decision, omitted when nobody responded yet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<DateTime<Utc>>,
+}
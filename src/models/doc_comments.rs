@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use super::ReactionCount;
+
+/// A single comment
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CommentView {
+    pub id: Uuid,
+    #[serde(rename = "parentId", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Uuid>,
+    pub author: Uuid,
+    pub state: String,
+    pub text: serde_json::value::Value,
+    pub timestamp: DateTime<Utc>,
+    #[serde(rename = "editedAt", skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<CommentAnchorView>,
+    // Reactions on the comment, the most used emoji first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reactions: Vec<ReactionCount>,
+}
+
+/// The text range a comment is anchored to, as character offsets in the current text.
+/// An orphaned anchor lost its range (e.g. the text was deleted) and keeps its last known offsets.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CommentAnchorView {
+    pub start: usize,
+    pub end: usize,
+    pub quote: String,
+    pub orphaned: bool,
+}
+
+/// Character range to anchor a new comment to
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CommentAnchorRequest {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A root comment with its replies ordered by timestamp
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CommentThread {
+    pub path: String,
+    pub state: String,
+    pub root: CommentView,
+    pub replies: Vec<CommentView>,
+}
+
+/// Request for adding a comment or a reply
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCommentAddRequest {
+    pub path: String,
+    pub text: serde_json::value::Value,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<Uuid>,
+    pub anchor: Option<CommentAnchorRequest>,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Request for editing the text of an own comment
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCommentEditRequest {
+    pub text: serde_json::value::Value,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Request for resolving (or reopening) a whole thread
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCommentResolveRequest {
+    pub resolved: Option<bool>,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Response returned after adding or editing a comment
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCommentResponse {
+    pub comment: CommentView,
+}
+
+/// Response returned after resolving a thread
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCommentResolveResponse {
+    pub success: bool,
+    pub updated: u32,
+}
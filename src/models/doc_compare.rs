@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Request for comparing a document with an external model of it
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCompareRequest {
+    // The JSON of a statement or sheet, e.g. the regulatory source the document was copied from
+    pub model: serde_json::Value,
+}
+
+/// A block or language whose text differs between the document and the external model
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ContentDiff {
+    // /content/<lang> of statements, /content/<blockId> of sheets or a language of a local statement
+    pub path: String,
+    // missing: only in the external model, extra: only in the document, changed: in both with different text
+    pub status: String,
+    // The text in the document
+    pub current: Option<String>,
+    // The text in the external model
+    pub source: Option<String>,
+}
+
+/// Differences between a document and an external model
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCompareResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    // Whether every block and language has the same text
+    pub identical: bool,
+    // Blocks and languages with the same text
+    pub unchanged: usize,
+    pub differences: Vec<ContentDiff>,
+}
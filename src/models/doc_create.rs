@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use super::ColabModelPermission;
+
+/// Request to create a document before its first websocket connection
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCreateRequest {
+    // "colab-statement" or "colab-sheet"
+    #[serde(rename = "type")]
+    pub doc_type: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub name: String,
+    pub owner: String,
+    // Library or folder holding the document
+    #[serde(default)]
+    pub container: Option<String>,
+    #[serde(rename = "containerType", default)]
+    pub container_type: Option<String>,
+    #[serde(rename = "masterLangCode", default)]
+    pub master_lang_code: Option<String>,
+    // Document of the same type whose latest content the new document starts from
+    #[serde(default)]
+    pub template: Option<String>,
+    // ACLs of the new document, the owner gets view, edit and manage when absent
+    #[serde(default)]
+    #[schema(value_type = Option<HashMap<String, Vec<String>>>)]
+    pub acls: Option<HashMap<ColabModelPermission, Vec<String>>>,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Response with the id of a created document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCreateResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub doc_type: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub version: u32,
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// How the columns of a CSV map onto a sheet block, columns are referred to by their header
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CsvColumnMapping {
+    // statement-grid: the column holding the text of every language, keyed by language code
+    #[serde(default)]
+    pub languages: HashMap<String, String>,
+    // statement-grid: the contentType of the local statements
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+    // attributes: the column holding the attribute keys
+    #[serde(rename = "keyColumn")]
+    pub key_column: Option<String>,
+    // attributes: the column holding the attribute values
+    #[serde(rename = "valueColumn")]
+    pub value_column: Option<String>,
+    // attributes: the column holding the display values, the value is displayed without it
+    #[serde(rename = "displayColumn")]
+    pub display_column: Option<String>,
+}
+
+/// Request for importing a CSV as a new block of a sheet
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCsvImportRequest {
+    // The CSV, with a header row
+    pub csv: String,
+    // "statement-grid" or "attributes"
+    #[serde(rename = "blockType")]
+    pub block_type: String,
+    pub title: Option<String>,
+    pub mapping: CsvColumnMapping,
+    // Field delimiter, a single character, a comma by default
+    pub delimiter: Option<String>,
+    // Position of the new block in the sheet, appended when omitted
+    pub position: Option<usize>,
+}
+
+/// Response after importing a CSV as a new block of a sheet
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentCsvImportResponse {
+    #[serde(rename = "blockId")]
+    pub block_id: String,
+    #[serde(rename = "blockType")]
+    pub block_type: String,
+    // Number of statement rows or attributes in the new block
+    pub rows: usize,
+    // CSV rows that were skipped because they had no content
+    pub skipped: usize,
+}
@@ -1,15 +1,22 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use crate::models::DocumentLink;
 
 /// Request payload for deleting a document
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct DocumentDeleteRequest {
     #[serde(rename = "byPrpl")]
     pub by_prpl: String,
+    // Delete the document even when other documents reference it
+    #[serde(default)]
+    pub force: bool,
 }
 
 /// Response returned after deleting a document
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct DocumentDeleteResponse {
     pub success: bool,
+    // The references of other documents that kept the document from being deleted
+    #[serde(rename = "referencedBy", default, skip_serializing_if = "Vec::is_empty")]
+    pub referenced_by: Vec<DocumentLink>,
 }
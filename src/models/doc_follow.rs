@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Follow a document, the digest lists its changes, new comments and pending approvals
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentFollowRequest {
+    // "daily" (default) or "weekly"
+    #[serde(default)]
+    pub frequency: Option<String>,
+}
+
+/// Whether the calling user follows a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentFollowResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub prpl: String,
+    pub following: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<String>,
+}
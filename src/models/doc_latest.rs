@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use super::{NamedVersionEntry, StorageTier};
 
 /// Response for exporting a document
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -11,6 +12,24 @@ pub struct DocumentLatestResponse {
     pub version: u32,
     #[serde(rename = "versionV")]
     pub version_v: serde_json::value::Value,
+    #[serde(rename = "versionVNamed")]
+    pub version_v_named: Vec<NamedVersionEntry>,
     #[serde(rename = "peerMap")]
     pub peer_map: serde_json::value::Value,
+    pub tier: StorageTier,
+}
+
+/// Response for exporting a document that changed since an earlier state, with the blocks that differ
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentChangesResponse {
+    pub version: u32,
+    #[serde(rename = "versionV")]
+    pub version_v: serde_json::value::Value,
+    #[serde(rename = "versionVNamed")]
+    pub version_v_named: Vec<NamedVersionEntry>,
+    // Ids of the blocks of a sheet, or language codes of a statement, that were added or changed
+    #[serde(rename = "changedBlocks")]
+    pub changed_blocks: Vec<String>,
+    #[serde(rename = "removedBlocks")]
+    pub removed_blocks: Vec<String>,
 }
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A reference between two documents
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentLink {
+    // The document on the other end of the reference
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // "statementRef", "transclusion" or "mention"
+    pub kind: String,
+    // Location of the reference in the referencing document
+    pub path: String,
+}
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The document or block carrying a stable number
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentNumberResponse {
+    pub number: String,
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    // The numbered block, null when the number is the number of the document
+    #[serde(rename = "blockId")]
+    pub block_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A single ACL entry or implicit rule granting a permission
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct PermissionGrant {
+    pub permission: String,
+    pub source: String,
+    pub prpl: String,
+    pub path: String,
+    pub inherited: bool,
+}
+
+/// Whether a permission is granted, and by which entries
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PermissionExplanation {
+    pub permission: String,
+    pub granted: bool,
+    pub grants: Vec<PermissionGrant>,
+}
+
+/// The explained permissions on one scope (document, block or language) of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ScopePermissions {
+    pub path: String,
+    pub level: String,
+    pub permissions: Vec<PermissionExplanation>,
+}
+
+/// Response for explaining the effective permissions of a principal on a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentPermissionsResponse {
+    pub prpl: String,
+    pub principals: Vec<String>,
+    pub scopes: Vec<ScopePermissions>,
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A single step in the replay of a document history
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct PlaybackFrame {
+    pub index: usize,
+    #[serde(rename = "versionV")]
+    pub version_v: serde_json::Value,
+    // Time of the last change in the frame
+    pub timestamp: Option<DateTime<Utc>>,
+    // Principals that made the changes in the frame
+    pub authors: Vec<String>,
+    #[serde(rename = "nChanges")]
+    pub n_changes: usize,
+    // The document state after the frame, with format=state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<serde_json::Value>,
+    // The updates of the frame, with format=diff
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<serde_json::Value>,
+}
+
+/// Response with the replay of a document history
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct DocumentPlaybackResponse {
+    pub version: u32,
+    #[serde(rename = "fromV")]
+    pub from_v: serde_json::Value,
+    #[serde(rename = "toV")]
+    pub to_v: serde_json::Value,
+    pub step: usize,
+    pub frames: Vec<PlaybackFrame>,
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Finding of the policy scanner in a block or language of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PolicyFinding {
+    pub id: String,
+    // e.g. /content/<blockId> or /content/<blockId>/rows/0/statement/content/en
+    #[serde(rename = "blockPath")]
+    pub block_path: String,
+    // e.g. "pii", "banned-claim" or "classified"
+    pub category: String,
+    // "low", "medium", "high" or "critical"
+    pub severity: String,
+    pub rule: Option<String>,
+    pub excerpt: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "resolvedAt")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    #[serde(rename = "resolvedBy")]
+    pub resolved_by: Option<String>,
+}
+
+/// Request for reviewing the policy findings of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentPolicyReviewRequest {
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Response after reviewing the policy findings of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentPolicyReviewResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    // Number of findings that were resolved
+    pub resolved: u64,
+}
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Detached signature of the published version of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSignatureResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub version: i32,
+    #[serde(rename = "versionV")]
+    pub version_v: serde_json::Value,
+    #[serde(rename = "snapshotSha256")]
+    pub snapshot_sha256: String,
+    #[serde(rename = "jsonSha256")]
+    pub json_sha256: String,
+    // The exact message that was signed
+    pub message: String,
+    pub algorithm: String,
+    #[serde(rename = "keyId")]
+    pub key_id: String,
+    #[serde(rename = "publicKey")]
+    pub public_key: String,
+    pub signature: String,
+    #[serde(rename = "signedAt")]
+    pub signed_at: DateTime<Utc>,
+    #[serde(rename = "signedBy")]
+    pub signed_by: String,
+}
+
+/// Request for verifying content against the published signature
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSignatureVerifyRequest {
+    // Base64 encoded snapshot
+    pub snapshot: Option<String>,
+    pub json: Option<serde_json::Value>,
+}
+
+/// Result of a verification against the published signature
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSignatureVerifyResponse {
+    pub valid: bool,
+    #[serde(rename = "signatureValid")]
+    pub signature_valid: bool,
+    #[serde(rename = "snapshotMatches", skip_serializing_if = "Option::is_none")]
+    pub snapshot_matches: Option<bool>,
+    #[serde(rename = "jsonMatches", skip_serializing_if = "Option::is_none")]
+    pub json_matches: Option<bool>,
+}
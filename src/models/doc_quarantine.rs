@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Response with the load failures and quarantine status of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentQuarantineResponse {
+    pub quarantined: bool,
+    #[serde(rename = "loadFailures")]
+    pub load_failures: i32,
+    #[serde(rename = "quarantinedAt")]
+    pub quarantined_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// An emoji with the principals that reacted with it
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: usize,
+    pub prpls: Vec<String>,
+}
+
+/// The reactions on a block or language, or on one of its comments
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReactionSummary {
+    pub path: String,
+    // Null for reactions on the block or language itself
+    #[serde(rename = "commentId")]
+    pub comment_id: Option<Uuid>,
+    pub reactions: Vec<ReactionCount>,
+}
+
+/// Request for adding or removing a reaction
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentReactionRequest {
+    pub path: String,
+    // React to a comment on the path instead of the block or language
+    #[serde(rename = "commentId")]
+    pub comment_id: Option<Uuid>,
+    pub emoji: String,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Response returned after adding or removing a reaction
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentReactionResponse {
+    // False when the reaction was already there, or wasn't there to remove
+    pub changed: bool,
+    pub summary: ReactionSummary,
+}
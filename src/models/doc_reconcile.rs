@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A stored version of a document and whether it is a head
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReconcileVersion {
+    pub version: u32,
+    #[serde(rename = "versionV")]
+    pub version_v: serde_json::Value,
+    // Whether no other version contains this one
+    pub head: bool,
+}
+
+/// A merge of two diverged versions
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentMerge {
+    pub id: String,
+    #[serde(rename = "versionA")]
+    pub version_a: u32,
+    #[serde(rename = "versionB")]
+    pub version_b: u32,
+    #[serde(rename = "mergedVersion")]
+    pub merged_version: u32,
+    #[serde(rename = "versionV")]
+    pub version_v: serde_json::Value,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+}
+
+/// Response with the versions of a document and whether they diverged
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentReconcileResponse {
+    pub diverged: bool,
+    pub versions: Vec<ReconcileVersion>,
+    pub merges: Vec<DocumentMerge>,
+}
+
+/// Request to merge two diverged versions, the two heads by default
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentMergeRequest {
+    #[serde(rename = "versionA")]
+    pub version_a: Option<u32>,
+    #[serde(rename = "versionB")]
+    pub version_b: Option<u32>,
+}
+
+/// Response with the merge that was done
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentMergeResponse {
+    pub merge: DocumentMerge,
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Request for finding and replacing text across a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentReplaceRequest {
+    pub pattern: String,
+    pub replacement: String,
+    // Whether the pattern is a regular expression, the replacement may then refer to groups as $1 or ${name}
+    #[serde(default)]
+    pub regex: bool,
+    // Only replace in these blocks, all blocks when absent
+    #[serde(rename = "blockIds")]
+    pub block_ids: Option<Vec<String>>,
+    // Only replace in these languages, all languages when absent
+    #[serde(rename = "langCodes")]
+    pub lang_codes: Option<Vec<String>>,
+    // Only count the matches, without changing the document
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+}
+
+/// Matches of the pattern in a block or language
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReplaceMatches {
+    // Null for the languages of a statement document
+    #[serde(rename = "blockId")]
+    pub block_id: Option<String>,
+    #[serde(rename = "langCode")]
+    pub lang_code: Option<String>,
+    pub matches: usize,
+}
+
+/// Response of finding and replacing text across a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentReplaceResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    // Matches in the whole document
+    pub matches: usize,
+    // Blocks and languages with at least one match
+    pub blocks: Vec<ReplaceMatches>,
+}
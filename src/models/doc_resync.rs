@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use super::DocumentVersionTag;
+
+/// Request for re-syncing a document from an external model of it
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentResyncRequest {
+    // The JSON of a statement or sheet, the source of truth of the document
+    pub model: serde_json::Value,
+    // external-wins, doc-wins or manual
+    pub strategy: String,
+    // With the manual strategy: `external` or `document` per block id, or language of a statement
+    #[serde(default)]
+    pub decisions: HashMap<String, String>,
+    // Only report what would change, without changing the document
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+}
+
+/// A block, or language of a statement, that differs from the external model and what was done with it
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResyncChange {
+    // Block id, or language of a statement
+    pub part: String,
+    // missing: only in the external model, extra: only in the document, changed: in both but different
+    pub status: String,
+    // added, replaced, removed, kept or unresolved
+    pub action: String,
+}
+
+/// Report of re-syncing a document from an external model
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentResyncResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub strategy: String,
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    pub changes: Vec<ResyncChange>,
+    // The state before the re-sync, absent on a dry run or when nothing changed
+    #[serde(rename = "versionTag", skip_serializing_if = "Option::is_none")]
+    pub version_tag: Option<DocumentVersionTag>,
+}
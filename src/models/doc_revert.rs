@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Request to revert the changes of an author
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentRevertRequest {
+    // Principal whose changes are reverted, all its peers in the peer registry are included
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: Option<String>,
+    // A single peer whose changes are reverted, as string since peer ids exceed the JSON safe integer range
+    pub peer: Option<String>,
+    // Only changes after this version vector are reverted, the whole history by default
+    #[serde(rename = "afterV")]
+    pub after_v: Option<serde_json::Value>,
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+}
+
+/// A change that is reverted
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct RevertedChange {
+    pub peer: String,
+    pub counter: i32,
+    pub len: usize,
+    pub lamport: u32,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// Response with the changes that are, or with dryRun would be, reverted
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentRevertResponse {
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    pub peers: Vec<String>,
+    pub changes: Vec<RevertedChange>,
+    // The updates that are reverted
+    pub reverted: serde_json::Value,
+    // The document state with the changes reverted, with dryRun
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<serde_json::Value>,
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The pod owning the room of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentRoomResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub pod: String,
+    #[serde(rename = "wsUrl")]
+    pub ws_url: String,
+    // Whether the room is owned by the pod that answered
+    pub local: bool,
+}
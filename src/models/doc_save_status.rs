@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Save status of the room of a document, as known by the pod owning the room
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSaveStatusResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    // Whether the room is owned by the pod that answered, only that pod knows the save status
+    pub local: bool,
+    // Whether the room is open on this pod
+    pub open: bool,
+    // Whether the room has changes that were not saved yet
+    pub dirty: bool,
+    // Whether a save is waiting for the save policy of the document
+    pub deferred: bool,
+    // Whether every change in the room is persisted
+    #[serde(rename = "allSaved")]
+    pub all_saved: bool,
+    #[serde(rename = "lastSavedAt")]
+    pub last_saved_at: Option<DateTime<Utc>>,
+    #[serde(rename = "lastSavedV")]
+    pub last_saved_v: Option<serde_json::Value>,
+    #[serde(rename = "currentV")]
+    pub current_v: Option<serde_json::Value>,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    #[serde(rename = "lastErrorAt")]
+    pub last_error_at: Option<DateTime<Utc>>,
+}
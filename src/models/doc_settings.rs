@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Save policy of a single document, missing keys fall back to the policy of the org
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSavePolicySettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debounce_ms: Option<u64>,
+    // "snapshot" or "updates"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistence: Option<String>,
+}
+
+/// Limits of a single document, missing keys fall back to the limits of the org
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentLimitSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_blocks: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_text_depth: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_snapshot_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_languages: Option<usize>,
+}
+
+/// Maintenance hold of a document, set when the policy scanner finds a severe violation
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentMaintenance {
+    pub reason: String,
+    pub since: DateTime<Utc>,
+}
+
+/// Overrides of the behavior of a single document
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DocumentSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub save_policy: Option<DocumentSavePolicySettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<DocumentLimitSettings>,
+    // Rejects all updates by users, the document can still be read
+    #[serde(default)]
+    pub read_only: bool,
+    // Rejects all updates by users until the hold is reviewed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<DocumentMaintenance>,
+}
+
+/// Response with the settings of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSettingsResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub settings: DocumentSettings,
+}
+
+/// Request for changing the settings of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSettingsPatchRequest {
+    // JSON merge patch of the settings, null removes a setting
+    pub settings: serde_json::Value,
+}
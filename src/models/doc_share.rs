@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Branding of the public view of a document
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ShareBranding {
+    // Shown above the document, a generic title is used without it
+    pub title: Option<String>,
+    #[serde(rename = "logoUrl")]
+    pub logo_url: Option<String>,
+    // Hex color, e.g. #0a7cff
+    #[serde(rename = "accentColor")]
+    pub accent_color: Option<String>,
+    pub footer: Option<String>,
+}
+
+/// Request for creating a share token of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentShareTokenCreateRequest {
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub branding: Option<ShareBranding>,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// A share token of a document, the token itself is only returned once when it is created
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentShareToken {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    // Path of the public view, relative to the service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub branding: Option<ShareBranding>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A transition that can be made from the current state
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct AvailableTransition {
+    pub to: String,
+    pub permission: String,
+    // Whether the principal of the request holds the permission, omitted without principal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<bool>,
+}
+
+/// Response with the workflow state of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentStateResponse {
+    pub state: String,
+    pub transitions: Vec<AvailableTransition>,
+}
+
+/// Request for moving a document to another workflow state
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentStateTransitionRequest {
+    pub state: String,
+    pub comment: Option<String>,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Response returned after a workflow transition
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentStateTransitionResponse {
+    pub success: bool,
+    pub from: String,
+    pub to: String,
+    pub timestamp: DateTime<Utc>,
+}
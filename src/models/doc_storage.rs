@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Where the content of a document version is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageTier {
+    // Inline in the database
+    Hot,
+    // In object storage, rehydrated on read
+    Cold,
+}
+
+/// Storage used by a single version of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct VersionStorage {
+    pub version: u32,
+    #[serde(rename = "streamId")]
+    pub stream_id: String,
+    pub bytes: u64,
+    pub tier: StorageTier,
+    // Bytes of this version and all versions before it
+    #[serde(rename = "cumulativeBytes")]
+    pub cumulative_bytes: u64,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+    // Hash of the content without metadata, unchanged when only ACLs or properties changed.
+    // Null for versions saved before the hash was recorded.
+    #[serde(rename = "contentHash")]
+    pub content_hash: Option<String>,
+    // Whether the compaction job trims this version under the current history budget
+    pub prunable: bool,
+}
+
+/// Response with the storage used by the versions of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentStorageResponse {
+    #[serde(rename = "historyBudget")]
+    pub history_budget: Option<u32>,
+    #[serde(rename = "totalBytes")]
+    pub total_bytes: u64,
+    #[serde(rename = "prunableBytes")]
+    pub prunable_bytes: u64,
+    pub versions: Vec<VersionStorage>,
+}
+
+/// Request for setting the history budget of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentHistoryBudgetRequest {
+    // Number of most recent versions to keep, null keeps the full history
+    #[serde(rename = "historyBudget")]
+    pub history_budget: Option<u32>,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A statement of a sheet that was moved into a linked subdocument
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LinkedStatement {
+    #[serde(rename = "blockId")]
+    pub block_id: String,
+    // Position of the row in the statement grid
+    pub row: usize,
+    #[serde(rename = "subdocId")]
+    pub subdoc_id: String,
+    // Room syncing the subdocument, `<docId>~stmt-<subdocId>`
+    pub room: String,
+}
+
+/// Response of linking the local statements of a sheet as subdocuments
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentLinkStatementsResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub statements: Vec<LinkedStatement>,
+}
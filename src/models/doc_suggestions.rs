@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A suggested edit to a block or language
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SuggestionView {
+    pub id: Uuid,
+    pub path: String,
+    pub author: String,
+    pub state: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(rename = "decidedBy", skip_serializing_if = "Option::is_none")]
+    pub decided_by: Option<String>,
+    #[serde(rename = "decidedAt", skip_serializing_if = "Option::is_none")]
+    pub decided_at: Option<DateTime<Utc>>,
+    // The block or language as it would look like when accepted, only for pending suggestions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<serde_json::value::Value>,
+}
+
+/// Request for suggesting an edit
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSuggestionAddRequest {
+    pub path: String,
+    // Base64 encoded Loro update, made on top of the current document state
+    pub update: String,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Request for accepting or rejecting a suggestion
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSuggestionDecisionRequest {
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+}
+
+/// Response returned after suggesting an edit or deciding on a suggestion
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSuggestionResponse {
+    pub suggestion: SuggestionView,
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Summary of the published version of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSummary {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    // Published version the summary was generated from
+    pub version: u32,
+    pub summary: String,
+    // URL of the summary provider, or "extract" for the start of the text
+    pub provider: String,
+    #[serde(rename = "generatedAt")]
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Request for the summaries of several documents
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSummariesRequest {
+    #[serde(rename = "docIds")]
+    pub doc_ids: Vec<String>,
+}
+
+/// Response listing document summaries, documents without a summary are left out
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentSummariesResponse {
+    pub summaries: Vec<DocumentSummary>,
+}
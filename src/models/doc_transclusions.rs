@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A transcluded block of a sheet, resolved for a principal
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResolvedTransclusion {
+    #[serde(rename = "blockId")]
+    pub block_id: String,
+    #[serde(rename = "sourceDocId")]
+    pub source_doc_id: String,
+    #[serde(rename = "sourceBlockId")]
+    pub source_block_id: String,
+    // "resolved", "forbidden", "missing" or "nested"
+    pub status: String,
+    // The latest content of the source block, only when resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<serde_json::Value>,
+}
+
+/// Response of resolving the transclusions of a sheet
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentTransclusionsResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub transclusions: Vec<ResolvedTransclusion>,
+}
@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Document types registered by an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgDocumentTypesResponse {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    // Documents of unregistered types are rejected when loaded, imported or saved
+    pub strict: bool,
+    // Content types registered per document type, e.g. {"colab-statement": ["claim"]}
+    pub types: HashMap<String, Vec<String>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedBy")]
+    pub updated_by: Option<String>,
+}
+
+/// Request for registering the document types of an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgDocumentTypesRequest {
+    pub strict: bool,
+    #[serde(default)]
+    pub types: HashMap<String, Vec<String>>,
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A block or language that changed since the earlier state
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UnreadBlock {
+    pub path: String,
+    // "added", "changed" or "removed"
+    pub status: String,
+    // Principals that changed the block, peers without principal as "peer:<id>"
+    pub authors: Vec<String>,
+    // What happened to the text, one line per text of the block
+    pub summary: Vec<String>,
+}
+
+/// Catch-up digest of the changes to a document since an earlier state
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentUnreadResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    // "readMarker" for the stored read marker of the caller, "versionVector" for `since_vv`
+    pub since: String,
+    pub version: u32,
+    #[serde(rename = "versionV")]
+    pub version_v: serde_json::Value,
+    // Everyone who changed the document since the earlier state
+    pub authors: Vec<String>,
+    pub blocks: Vec<UnreadBlock>,
+}
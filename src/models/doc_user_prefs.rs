@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Preferences and read marker of the calling user on a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentUserPrefsResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub prpl: String,
+    // Latest saved version when the user last marked the document read
+    #[serde(rename = "lastReadVersion")]
+    pub last_read_version: Option<u32>,
+    #[serde(rename = "lastReadAt")]
+    pub last_read_at: Option<DateTime<Utc>>,
+    #[serde(rename = "pinnedBlocks")]
+    pub pinned_blocks: Vec<String>,
+    #[serde(rename = "collapsedSections")]
+    pub collapsed_sections: Vec<String>,
+    // Changes since the read marker, null when the user never read the document or it can't be compared
+    pub unread: Option<UnreadChanges>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// The blocks or languages that changed since the user last read the document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UnreadChanges {
+    #[serde(rename = "hasUnread")]
+    pub has_unread: bool,
+    #[serde(rename = "changedBlocks")]
+    pub changed_blocks: Vec<String>,
+    #[serde(rename = "removedBlocks")]
+    pub removed_blocks: Vec<String>,
+}
+
+/// Request for updating the preferences of the calling user, fields left out keep their value
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentUserPrefsRequest {
+    // Move the read marker to the latest state of the document
+    #[serde(rename = "markRead", default)]
+    pub mark_read: bool,
+    #[serde(rename = "pinnedBlocks")]
+    pub pinned_blocks: Option<Vec<String>>,
+    #[serde(rename = "collapsedSections")]
+    pub collapsed_sections: Option<Vec<String>>,
+}
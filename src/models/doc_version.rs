@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use super::StorageTier;
 
 
 /// Request for getting a specific document version
@@ -9,8 +10,9 @@ use utoipa::ToSchema;
 pub struct DocumentVersionRequest {
     #[serde(rename = "version")]
     pub version: u32,
+    /// Counters keyed by peer id (`{"1234": 42}`) or by principal (`{"s/colabri-doc": 42}`), principals are resolved through the peer map
     #[serde(rename = "versionV")]
-    pub version_v: Option<HashMap<u64, i32>>,
+    pub version_v: Option<HashMap<String, i32>>,
     #[serde(rename = "format")]
     pub format: Option<String>,
 }
@@ -26,6 +28,18 @@ pub struct DocumentVersionResponse {
     pub version: u32,
     #[serde(rename = "versionV")]
     pub version_v: serde_json::value::Value,
+    #[serde(rename = "versionVNamed")]
+    pub version_v_named: Vec<NamedVersionEntry>,
     #[serde(rename = "peerMap")]
     pub peer_map: serde_json::value::Value,
+    pub tier: StorageTier,
+}
+
+/// Counter of a peer in a version vector, with the principal the peer belongs to
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct NamedVersionEntry {
+    pub peer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prpl: Option<String>,
+    pub counter: i32,
 }
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Whether the calling user watches a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentWatchResponse {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub prpl: String,
+    pub watching: bool,
+    // When the user started watching the document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+}
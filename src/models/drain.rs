@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Progress of draining the pod before a deploy
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DrainStatusResponse {
+    // "idle", "draining" or "drained"
+    pub state: String,
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+    // Saves deferred by the save policies that were persisted
+    #[serde(rename = "deferredSaved")]
+    pub deferred_saved: usize,
+    // Document rooms that were open when the drain started
+    #[serde(rename = "roomsTotal")]
+    pub rooms_total: usize,
+    #[serde(rename = "roomsSaved")]
+    pub rooms_saved: usize,
+    #[serde(rename = "roomsClosed")]
+    pub rooms_closed: usize,
+    // Document rooms that are still open, with clients connected
+    #[serde(rename = "roomsOpen")]
+    pub rooms_open: usize,
+    pub errors: Vec<String>,
+}
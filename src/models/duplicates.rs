@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A statement in a cluster of duplicates
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct DuplicateOccurrence {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub name: String,
+    // Location of the statement in a sheet, empty for statement documents
+    pub path: String,
+    pub excerpt: String,
+}
+
+/// Statements with the same or nearly the same text
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct DuplicateCluster {
+    // Every statement has the same normalized text
+    pub exact: bool,
+    // Lowest similarity that joined the statements into the cluster
+    pub similarity: f64,
+    pub occurrences: Vec<DuplicateOccurrence>,
+}
+
+/// Latest report of the duplicated statements of an org
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct StatementDuplicatesReport {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    pub threshold: f64,
+    // Number of analyzed statements
+    pub statements: i32,
+    pub clusters: Vec<DuplicateCluster>,
+    #[serde(rename = "generatedAt")]
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Response of starting the deduplication analysis
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct StatementDuplicatesAnalyzeResponse {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    pub threshold: f64,
+    // False when an analysis of the org was already running
+    pub started: bool,
+}
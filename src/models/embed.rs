@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Embed settings of an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgEmbedSettingsResponse {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    // Origins allowed to frame the embedded views, empty when embedding is not allowed
+    #[serde(rename = "frameAncestors")]
+    pub frame_ancestors: Vec<String>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedBy")]
+    pub updated_by: Option<String>,
+}
+
+/// Request for setting the origins allowed to frame the embedded views of an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgEmbedSettingsRequest {
+    // https origins, e.g. https://intranet.example.com or https://*.example.com
+    #[serde(rename = "frameAncestors")]
+    pub frame_ancestors: Vec<String>,
+}
+
+/// Link preview of a shared document, modelled after oEmbed
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct PublicViewCard {
+    // Always "rich"
+    #[serde(rename = "type")]
+    pub r#type: String,
+    // Always "1.0"
+    pub version: String,
+    pub title: String,
+    pub excerpt: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(rename = "providerName")]
+    pub provider_name: String,
+    pub url: String,
+    // Iframe snippet of the embed mode
+    pub html: String,
+    pub width: u32,
+    pub height: u32,
+}
@@ -1,3 +1,4 @@
+use axum::{http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -7,4 +8,16 @@ pub struct ErrorResponse {
     pub code: u16,
     pub status: String,
     pub error: String,
-}
\ No newline at end of file
+}
+
+/// The error half of a handler result
+pub type ApiError = (StatusCode, Json<ErrorResponse>);
+
+/// Build an error tuple with the given status and message
+pub fn api_error(status: StatusCode, error: impl Into<String>) -> ApiError {
+    (status, Json(ErrorResponse {
+        code: status.as_u16(),
+        status: status.to_string(),
+        error: error.into(),
+    }))
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::models::UnreadBlock;
+
+/// Data of the "document.state-changed" event, sent when a document moves to another workflow state
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DocumentStateChangedEvent {
+    pub from: String,
+    pub to: String,
+    #[serde(rename = "byPrpl")]
+    pub by_prpl: String,
+    pub comment: Option<String>,
+    // Principals watching the document, to notify next to the principals in its ACLs
+    #[serde(default)]
+    pub watchers: Vec<String>,
+}
+
+/// Data of the "document.quarantined" event, sent when a document is quarantined after repeated failed loads
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DocumentQuarantinedEvent {
+    #[serde(rename = "loadFailures")]
+    pub load_failures: i32,
+    pub reason: String,
+}
+
+/// Data of the "user.digest" event, the digest of the documents a user follows that changed since the last digest
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserDigestEvent {
+    pub prpl: String,
+    // "daily" or "weekly"
+    pub frequency: String,
+    // Locale and timezone of the organization, documents format their dates with those of their content type
+    #[serde(default)]
+    pub locale: String,
+    #[serde(default)]
+    pub timezone: String,
+    pub documents: Vec<DigestDocument>,
+}
+
+/// A followed document in a digest
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DigestDocument {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub name: String,
+    pub since: DateTime<Utc>,
+    // `since` in the locale settings of the organization for the content type of the document
+    #[serde(rename = "sinceFormatted", default)]
+    pub since_formatted: String,
+    // Everyone who changed the document since the last digest
+    pub authors: Vec<String>,
+    // Blocks and languages that changed, with the summary of what happened to their text
+    pub blocks: Vec<UnreadBlock>,
+    // Comments of others since the last digest
+    #[serde(rename = "newComments")]
+    pub new_comments: Vec<DigestComment>,
+    // Paths waiting for the approval of the user in an active approval round
+    #[serde(rename = "pendingApprovals")]
+    pub pending_approvals: Vec<String>,
+}
+
+/// A comment placed since the last digest
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DigestComment {
+    pub path: String,
+    pub id: Uuid,
+    pub author: Uuid,
+    pub timestamp: DateTime<Utc>,
+    #[serde(rename = "timestampFormatted", default)]
+    pub timestamp_formatted: String,
+}
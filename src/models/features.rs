@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// State of a feature for an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgFeature {
+    // "comments", "suggestions", "translation", "publishing" or "policy-scanning"
+    pub feature: String,
+    pub enabled: bool,
+    // Whether the feature is enabled for organizations without a flag
+    #[serde(rename = "defaultEnabled")]
+    pub default_enabled: bool,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedBy")]
+    pub updated_by: Option<String>,
+}
+
+/// Request for enabling or disabling a feature for an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgFeatureSetRequest {
+    // null removes the flag, so the default applies again
+    pub enabled: Option<bool>,
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use super::{ApprovalRoundView, ArchivalCandidate, CommentThread, DocumentLink, DocumentShareToken, ErrorResponse, OrgAnnouncement, OrgFeature, PolicyFinding, ReactionSummary, SuggestionView, VersionStorage};
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// Id of the request, set by the API middleware and echoed in the x-request-id header
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// Page of a list endpoint requested with `?limit=&offset=`
+#[derive(Deserialize, Default)]
+pub struct PageQuery {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// Position of a page in the full list
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Pagination {
+    pub limit: usize,
+    pub offset: usize,
+    pub total: usize,
+    // Offset of the next page, null on the last page
+    #[serde(rename = "nextOffset")]
+    pub next_offset: Option<usize>,
+}
+
+/// Metadata of a list response
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ListMeta {
+    pub pagination: Pagination,
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+}
+
+/// Envelope of every list endpoint: a page of items, its position and the items that failed to load
+#[derive(Serialize, Deserialize, ToSchema)]
+#[aliases(
+    VersionStorageList = ListResponse<VersionStorage>,
+    CommentThreadList = ListResponse<CommentThread>,
+    ReactionSummaryList = ListResponse<ReactionSummary>,
+    SuggestionList = ListResponse<SuggestionView>,
+    ApprovalRoundList = ListResponse<ApprovalRoundView>,
+    ShareTokenList = ListResponse<DocumentShareToken>,
+    DocumentLinkList = ListResponse<DocumentLink>,
+    OrgAnnouncementList = ListResponse<OrgAnnouncement>,
+    ArchivalCandidateList = ListResponse<ArchivalCandidate>,
+    OrgFeatureList = ListResponse<OrgFeature>,
+    PolicyFindingList = ListResponse<PolicyFinding>
+)]
+pub struct ListResponse<T> {
+    pub data: Vec<T>,
+    pub meta: ListMeta,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ErrorResponse>,
+}
+
+impl<T> ListResponse<T> {
+    /// Cut the requested page out of the full list
+    pub fn page(items: Vec<T>, page: &PageQuery, request_id: &RequestId) -> Self {
+        let total = items.len();
+        let limit = page.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let offset = page.offset.unwrap_or(0);
+        let data: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+        let next_offset = Some(offset + data.len()).filter(|next| *next < total);
+        ListResponse {
+            data,
+            meta: ListMeta {
+                pagination: Pagination { limit, offset, total, next_offset },
+                request_id: request_id.0.clone(),
+            },
+            errors: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Locale settings, unset fields fall back to the next level
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct LocaleSettings {
+    // BCP 47 language tag, e.g. "en-US"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    // IANA timezone, e.g. "Europe/Brussels"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    // strftime format of dates, e.g. "%d/%m/%Y"
+    #[serde(rename = "dateFormat", default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+    // strftime format of timestamps, e.g. "%d/%m/%Y %H:%M"
+    #[serde(rename = "datetimeFormat", default, skip_serializing_if = "Option::is_none")]
+    pub datetime_format: Option<String>,
+}
+
+/// Locale settings after falling back to the organization and the defaults of the service
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResolvedLocaleSettings {
+    pub locale: String,
+    pub timezone: String,
+    #[serde(rename = "dateFormat")]
+    pub date_format: String,
+    #[serde(rename = "datetimeFormat")]
+    pub datetime_format: String,
+}
+
+/// Locale settings of an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgLocaleSettingsResponse {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    pub settings: LocaleSettings,
+    // Settings overridden per content type
+    #[serde(rename = "contentTypes")]
+    pub content_types: HashMap<String, LocaleSettings>,
+    // The settings of the organization with the defaults filled in
+    pub resolved: ResolvedLocaleSettings,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedBy")]
+    pub updated_by: Option<String>,
+}
+
+/// Request for setting the locale settings of an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct OrgLocaleSettingsRequest {
+    #[serde(default)]
+    pub settings: LocaleSettings,
+    #[serde(rename = "contentTypes", default)]
+    pub content_types: HashMap<String, LocaleSettings>,
+}
@@ -1,10 +1,10 @@
-use loro::{LoroDoc, LoroList, LoroMap, LoroMovableList, LoroText};
+use loro::{ContainerID, LoroDoc, LoroList, LoroMap, LoroMovableList, LoroText, VersionVector};
 use std::option::Option;
-use tracing::{info};
+use tracing::{info, warn};
 
 
 use crate::models::{
-    ColabApproval, ColabModel, ColabModelPermission, ColabSheetBlock, ColabSheetModel,
+    ColabApproval, ColabComment, ColabCommentAnchor, ColabModel, ColabModelPermission, ColabSheetBlock, ColabSheetModel, ColabSheetStatementGridRow,
     ColabStatementModel, ColabUserApproval, TextElement, TextElementChild, TextElementChildrenOrString,
 };
 
@@ -185,7 +185,8 @@ pub fn stmt_to_loro_doc(stmt_model: &ColabStatementModel) -> Option<LoroDoc> {
             }
         }
 
-        // Let's ignore comments for now.
+        // Let's set the comments
+        comments_to_loro_map(&block.comments, &block_loro_map);
 
         // Let's set the TextElement
         let text_element_loro_map = block_loro_map
@@ -251,7 +252,58 @@ fn colab_user_approval_to_loro_map(user_approval: &ColabUserApproval, loro_map:
     let _ = loro_map.insert("date", date_str.as_str());
 }
 
-fn txtelem_to_loro_doc(text_element: &TextElement, loro_map: &LoroMap) {
+// Store the comments in a map keyed by comment id, replies refer to their parent by id.
+fn comments_to_loro_map(comments: &[ColabComment], block_loro_map: &LoroMap) {
+    if comments.is_empty() {
+        return;
+    }
+    let comments_loro_map = block_loro_map
+        .get_or_create_container("comments", LoroMap::new())
+        .unwrap();
+    for comment in comments {
+        let comment_loro_map = comments_loro_map
+            .get_or_create_container(comment.id.to_string().as_str(), LoroMap::new())
+            .unwrap();
+        colab_comment_to_loro_map(comment, &comment_loro_map);
+    }
+}
+
+pub fn colab_comment_to_loro_map(comment: &ColabComment, loro_map: &LoroMap) {
+    let _ = loro_map.insert("id", comment.id.to_string().as_str());
+    if let Some(parent_id) = &comment.parent_id {
+        let _ = loro_map.insert("parentId", parent_id.to_string().as_str());
+    }
+    let _ = loro_map.insert("type", comment.r#type.to_string().as_str());
+    let _ = loro_map.insert("state", comment.state.to_string().as_str());
+    let _ = loro_map.insert("author", comment.author.to_string().as_str());
+    let _ = loro_map.insert("timestamp", comment.timestamp.to_rfc3339().as_str());
+    if let Some(edited_at) = &comment.edited_at {
+        let _ = loro_map.insert("editedAt", edited_at.to_rfc3339().as_str());
+    }
+    if let Some(anchor) = &comment.anchor {
+        colab_comment_anchor_to_loro_map(anchor, loro_map);
+    }
+
+    // Replace the text as a whole, so edits don't append to the previous children
+    let text_loro_map = loro_map
+        .insert_container("text", LoroMap::new())
+        .unwrap();
+    txtelem_to_loro_doc(&comment.text, &text_loro_map);
+}
+
+pub fn colab_comment_anchor_to_loro_map(anchor: &ColabCommentAnchor, comment_loro_map: &LoroMap) {
+    let anchor_loro_map = comment_loro_map
+        .get_or_create_container("anchor", LoroMap::new())
+        .unwrap();
+    let _ = anchor_loro_map.insert("start", anchor.start.as_str());
+    let _ = anchor_loro_map.insert("end", anchor.end.as_str());
+    let _ = anchor_loro_map.insert("quote", anchor.quote.as_str());
+    let _ = anchor_loro_map.insert("startOffset", anchor.start_offset as i64);
+    let _ = anchor_loro_map.insert("endOffset", anchor.end_offset as i64);
+    let _ = anchor_loro_map.insert("orphaned", anchor.orphaned);
+}
+
+pub fn txtelem_to_loro_doc(text_element: &TextElement, loro_map: &LoroMap) {
     const MAX_DEPTH: usize = 100; // Prevent stack overflow
 
     // Set the nodeName
@@ -302,8 +354,9 @@ fn txtelem_child_to_loro_map(
     depth: usize,
     max_depth: usize,
 ) {
-    // Prevent stack overflow by limiting recursion depth
+    // Prevent stack overflow by limiting recursion depth, the document limits reject such documents before they get here
     if depth >= max_depth {
+        warn!("Truncating text element '{}' at depth {}", child.node_name, depth);
         let _ = loro_map.insert("nodeName", "truncated");
         let _ = loro_map.insert("children", "[Max depth exceeded]");
         return;
@@ -351,7 +404,7 @@ fn txtelem_child_to_loro_map(
     }
 }
 
-fn colab_sheet_block_to_loro_map(block: &ColabSheetBlock) -> LoroMap {
+pub fn colab_sheet_block_to_loro_map(block: &ColabSheetBlock) -> LoroMap {
     let loro_map = LoroMap::new();
     match block {
         ColabSheetBlock::Properties(_properties_block) => {
@@ -464,37 +517,52 @@ fn colab_sheet_block_to_loro_map(block: &ColabSheetBlock) -> LoroMap {
             let rows_list = loro_map
                 .insert_container("rows", LoroMovableList::new())
                 .unwrap();
-            
-            for (idx, row) in grid_block.rows.iter().enumerate() {
-                let row_map = LoroMap::new();
-                let _ = row_map.insert("type", row.r#type.as_str());
-                
-                if let Some(s) = &row.statement_ref {
-                    let statement_ref_map = row_map
-                        .insert_container("statementRef", LoroMap::new())
-                        .unwrap();
-                    let _ = statement_ref_map.insert(
-                        "docId",
-                        s.doc_id.to_string().as_str(),
-                    );
-                    let _ = statement_ref_map.insert(
-                        "version",
-                        s.version,
-                    );
-                    let _ = statement_ref_map.insert(
-                        "versionV",
-                        s.version_v.as_str(),
-                    );
-                }
+            statement_grid_rows_to_loro_list(&grid_block.rows, &rows_list);
+        }
+        ColabSheetBlock::Transclusion(transclusion_block) => {
+            let _ = loro_map.insert("type", "transclusion");
+            let _ = loro_map.insert("sourceDocId", transclusion_block.source_doc_id.to_string().as_str());
+            let _ = loro_map.insert("sourceBlockId", transclusion_block.source_block_id.as_str());
+        }
+        ColabSheetBlock::Board(board_block) => {
+            let _ = loro_map.insert("type", "board");
+            // ACLs
+            let acls_map = loro_map
+                .insert_container("acls", LoroMap::new())
+                .unwrap();
+            populate_acls(&acls_map, &board_block.acls);
+
+            // Title
+            let title_element_map = loro_map
+                .insert_container("title", LoroMap::new())
+                .unwrap();
+            txtelem_to_loro_doc(&board_block.title, &title_element_map);
 
-                if let Some(stmt) = &row.statement {
-                    let statement_map = row_map
-                        .insert_container("statement", LoroMap::new())
+            // Columns, each with its cards, both movable so cards can be dragged around
+            let columns_list = loro_map
+                .insert_container("columns", LoroMovableList::new())
+                .unwrap();
+            for (idx, column) in board_block.columns.iter().enumerate() {
+                let column_map = LoroMap::new();
+                let _ = column_map.insert("id", column.id.as_str());
+                let _ = column_map.insert("title", column.title.as_str());
+                let column_map = columns_list.insert_container(idx, column_map).unwrap();
+                let cards_list = column_map
+                    .insert_container("cards", LoroMovableList::new())
+                    .unwrap();
+                for (card_idx, card) in column.cards.iter().enumerate() {
+                    let card_map = LoroMap::new();
+                    let _ = card_map.insert("id", card.id.as_str());
+                    let _ = card_map.insert("status", card.status.as_str());
+                    if let Some(assignee) = &card.assignee {
+                        let _ = card_map.insert("assignee", assignee.as_str());
+                    }
+                    let card_map = cards_list.insert_container(card_idx, card_map).unwrap();
+                    let card_title_map = card_map
+                        .insert_container("title", LoroMap::new())
                         .unwrap();
-                    stmt_to_loro_map(stmt, &statement_map);
+                    txtelem_to_loro_doc(&card.title, &card_title_map);
                 }
-
-                let _ = rows_list.insert_container(idx, row_map);
             }
         }
         ColabSheetBlock::Attributes(attribute_block) => {
@@ -561,6 +629,9 @@ fn stmt_to_loro_map(stmt_model: &ColabStatementModel, loro_map: &LoroMap) {
             }
         }
 
+        // Comments
+        comments_to_loro_map(&block.comments, &block_loro_map);
+
         // TextElement
         let text_element_loro_map = block_loro_map
             .get_or_create_container("textElement", LoroMap::new())
@@ -580,3 +651,92 @@ fn populate_acls(acls_map: &LoroMap, acls: &std::collections::HashMap<ColabModel
         }
     }
 }
+
+/// Fill a movable list with the rows of a statement grid
+pub fn statement_grid_rows_to_loro_list(rows: &[ColabSheetStatementGridRow], rows_list: &LoroMovableList) {
+    for (idx, row) in rows.iter().enumerate() {
+        let row_map = LoroMap::new();
+        let _ = row_map.insert("type", row.r#type.as_str());
+        
+        if let Some(s) = &row.statement_ref {
+            let statement_ref_map = row_map
+                .insert_container("statementRef", LoroMap::new())
+                .unwrap();
+            let _ = statement_ref_map.insert(
+                "docId",
+                s.doc_id.to_string().as_str(),
+            );
+            let _ = statement_ref_map.insert(
+                "version",
+                s.version,
+            );
+            let _ = statement_ref_map.insert(
+                "versionV",
+                s.version_v.as_str(),
+            );
+        }
+
+        if let Some(stmt) = &row.statement {
+            let statement_map = row_map
+                .insert_container("statement", LoroMap::new())
+                .unwrap();
+            stmt_to_loro_map(stmt, &statement_map);
+        }
+
+        let _ = rows_list.insert_container(idx, row_map);
+    }
+}
+
+// Helpers to read the Loro structure of a colab document
+
+/// Get a nested map stored under `key`
+pub fn get_child_map(map: &LoroMap, key: &str) -> Option<LoroMap> {
+    map.get(key)?.as_container()?.as_map().cloned()
+}
+
+/// Get a nested movable list stored under `key`
+pub fn get_child_movable_list(map: &LoroMap, key: &str) -> Option<LoroMovableList> {
+    map.get(key)?.as_container()?.as_movable_list().cloned()
+}
+
+/// Get a string value stored under `key`
+pub fn get_string(map: &LoroMap, key: &str) -> Option<String> {
+    map.get(key)?.as_value()?.as_string().map(|s| s.to_string())
+}
+
+/// Get the map stored at `idx` in a movable list
+pub fn get_list_map(list: &LoroMovableList, idx: usize) -> Option<LoroMap> {
+    list.get(idx)?.as_container()?.as_map().cloned()
+}
+
+/// Get the `properties.type` of a document
+pub fn get_doc_type(doc: &LoroDoc) -> Option<String> {
+    get_string(&doc.get_map("properties"), "type")
+}
+
+/// Get the identifier of a sheet block, falling back to its position when no `id` is stored
+pub fn get_block_id(block: &LoroMap, idx: usize) -> String {
+    get_string(block, "id").unwrap_or_else(|| idx.to_string())
+}
+
+/// Whether `container` is `scope_id` itself or nested somewhere inside it
+pub fn is_inside_container(doc: &LoroDoc, container: &ContainerID, scope_id: &ContainerID) -> bool {
+    container == scope_id
+        || doc
+            .get_path_to_container(container)
+            .is_some_and(|path| path.iter().any(|(id, _)| id == scope_id))
+}
+
+/// The containers targeted by the operations between two versions of a document
+pub fn changed_containers(doc: &LoroDoc, from: &VersionVector, to: &VersionVector) -> Vec<ContainerID> {
+    let mut containers: Vec<ContainerID> = Vec::new();
+    let changes = doc.export_json_updates_without_peer_compression(from, to);
+    for change in &changes.changes {
+        for op in &change.ops {
+            if !containers.contains(&op.container) {
+                containers.push(op.container.clone());
+            }
+        }
+    }
+    containers
+}
@@ -8,6 +8,58 @@ pub mod doc_delete;
 pub mod diagnostics;
 pub mod lorodoc;
 pub mod error;
+pub mod doc_permissions;
+pub mod doc_access_report;
+pub mod doc_comments;
+pub mod doc_suggestions;
+pub mod doc_approval_rounds;
+pub mod workflow;
+pub mod doc_state;
+pub mod doc_citation;
+pub mod doc_published;
+pub mod doc_room;
+pub mod doc_quarantine;
+pub mod doc_storage;
+pub mod archival;
+pub mod doc_playback;
+pub mod doc_blame;
+pub mod doc_revert;
+pub mod doc_reconcile;
+pub mod drain;
+pub mod principals;
+pub mod doc_save_status;
+pub mod features;
+pub mod doc_settings;
+pub mod events;
+pub mod doc_csv_import;
+pub mod doc_share;
+pub mod embed;
+pub mod doc_summary;
+pub mod doc_policy;
+pub mod analytics;
+pub mod billing;
+pub mod doc_blocks;
+pub mod doc_subdocs;
+pub mod doc_transclusions;
+pub mod doc_links;
+pub mod duplicates;
+pub mod list;
+pub mod doc_create;
+pub mod doc_types;
+pub mod doc_numbers;
+pub mod doc_replace;
+pub mod replace_jobs;
+pub mod doc_compare;
+pub mod doc_resync;
+pub mod recording;
+pub mod schema_migration;
+pub mod doc_reactions;
+pub mod doc_user_prefs;
+pub mod doc_unread;
+pub mod doc_follow;
+pub mod doc_watch;
+pub mod announcements;
+pub mod locale;
 
 pub use colabdoc::*;
 pub use health::*;
@@ -18,3 +70,55 @@ pub use doc_move_lib::*;
 pub use doc_delete::*;
 pub use diagnostics::*;
 pub use error::*;
+pub use doc_permissions::*;
+pub use doc_access_report::*;
+pub use doc_comments::*;
+pub use doc_suggestions::*;
+pub use doc_approval_rounds::*;
+pub use workflow::*;
+pub use doc_state::*;
+pub use doc_citation::*;
+pub use doc_published::*;
+pub use doc_room::*;
+pub use doc_quarantine::*;
+pub use doc_storage::*;
+pub use archival::*;
+pub use doc_playback::*;
+pub use doc_blame::*;
+pub use doc_revert::*;
+pub use doc_reconcile::*;
+pub use drain::*;
+pub use principals::*;
+pub use doc_save_status::*;
+pub use features::*;
+pub use doc_settings::*;
+pub use events::*;
+pub use doc_csv_import::*;
+pub use doc_share::*;
+pub use embed::*;
+pub use doc_summary::*;
+pub use doc_policy::*;
+pub use analytics::*;
+pub use billing::*;
+pub use doc_blocks::*;
+pub use doc_subdocs::*;
+pub use doc_transclusions::*;
+pub use doc_links::*;
+pub use duplicates::*;
+pub use list::*;
+pub use doc_create::*;
+pub use doc_types::*;
+pub use doc_numbers::*;
+pub use doc_replace::*;
+pub use replace_jobs::*;
+pub use doc_compare::*;
+pub use doc_resync::*;
+pub use recording::*;
+pub use schema_migration::*;
+pub use doc_reactions::*;
+pub use doc_user_prefs::*;
+pub use doc_unread::*;
+pub use doc_follow::*;
+pub use doc_watch::*;
+pub use announcements::*;
+pub use locale::*;
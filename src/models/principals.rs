@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Updated principals of a user, pushed by the app service
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UserPrincipalsRequest {
+    pub prpls: Vec<String>,
+}
+
+/// Outcome of applying the principals to the live connections of the user
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UserPrincipalsResponse {
+    pub uid: String,
+    // Whether the user was cached, users that are not cached have no live connections
+    pub cached: bool,
+    pub connections: usize,
+    // Rooms joined by the connections that were re-evaluated
+    pub rooms: usize,
+    // Rooms where a connection lost the write permission
+    pub downgraded: usize,
+    // Rooms where a connection lost access altogether
+    pub revoked: usize,
+    // Rooms that were closed to drop the lost access
+    #[serde(rename = "closedRooms")]
+    pub closed_rooms: Vec<String>,
+}
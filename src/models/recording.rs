@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Request to start recording the sync traffic of a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct RecordingStartRequest {
+    // Duration of the recording in seconds, capped by the configured maximum
+    #[serde(rename = "durationSecs")]
+    pub duration_secs: Option<u64>,
+    // Whether the full payloads are written next to their sizes and hashes
+    #[serde(rename = "includePayloads", default)]
+    pub include_payloads: bool,
+}
+
+/// State of the latest recording of a document
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct RecordingStatus {
+    pub id: String,
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    // "recording" or "stopped"
+    pub state: String,
+    #[serde(rename = "includePayloads")]
+    pub include_payloads: bool,
+    #[serde(rename = "startedAt")]
+    pub started_at: DateTime<Utc>,
+    #[serde(rename = "endsAt")]
+    pub ends_at: DateTime<Utc>,
+    #[serde(rename = "stoppedAt")]
+    pub stopped_at: Option<DateTime<Utc>>,
+    // Frames written so far and the size of the recording in bytes
+    pub frames: u64,
+    pub bytes: u64,
+}
@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use super::ReplaceMatches;
+
+/// Request for finding and replacing text across the documents of an organization
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BulkReplaceRequest {
+    pub pattern: String,
+    pub replacement: String,
+    // Whether the pattern is a regular expression, the replacement may then refer to groups as $1 or ${name}
+    #[serde(default)]
+    pub regex: bool,
+    // Only replace in these languages, all languages when absent
+    #[serde(rename = "langCodes")]
+    pub lang_codes: Option<Vec<String>>,
+    // Only replace in the documents of this library, all documents of the organization when absent
+    pub library: Option<String>,
+    // Only report the matches per document, without changing any document
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+}
+
+/// The state of a document before a bulk job changed it.
+/// Reverting the changes of `peer` after `stateVv` (revert-author) rolls the change back.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DocumentVersionTag {
+    pub tag: String,
+    // Latest saved version when the document was tagged
+    pub version: Option<i32>,
+    #[serde(rename = "stateVv")]
+    pub state_vv: serde_json::Value,
+    // As string since peer ids exceed the JSON safe integer range
+    pub peer: String,
+}
+
+/// Matches of a bulk job in a document
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BulkReplaceDocumentResult {
+    #[serde(rename = "docId")]
+    pub doc_id: String,
+    pub name: String,
+    pub matches: usize,
+    pub blocks: Vec<ReplaceMatches>,
+    #[serde(rename = "versionTag", skip_serializing_if = "Option::is_none")]
+    pub version_tag: Option<DocumentVersionTag>,
+    // Why the document couldn't be searched or changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A bulk find-and-replace job and the documents it found matches in so far
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BulkReplaceJobResponse {
+    #[serde(rename = "jobId")]
+    pub job_id: String,
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    // running, done or failed
+    pub status: String,
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    pub request: BulkReplaceRequest,
+    // Documents the job goes through and how many of them are handled
+    pub documents: i32,
+    pub processed: i32,
+    // Matches in all documents so far
+    pub matches: usize,
+    pub results: Vec<BulkReplaceDocumentResult>,
+    pub error: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "createdBy")]
+    pub created_by: String,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Response of starting a bulk find-and-replace job
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct BulkReplaceStartResponse {
+    #[serde(rename = "jobId")]
+    pub job_id: String,
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+}
@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Progress of migrating the documents of an organization to the current schema version
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct SchemaMigrationStatus {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    // "running", "done" or "failed"
+    pub state: String,
+    #[serde(rename = "targetVersion")]
+    pub target_version: u32,
+    #[serde(rename = "startedAt")]
+    pub started_at: DateTime<Utc>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(rename = "documentsTotal")]
+    pub documents_total: usize,
+    #[serde(rename = "documentsChecked")]
+    pub documents_checked: usize,
+    #[serde(rename = "documentsMigrated")]
+    pub documents_migrated: usize,
+    pub errors: Vec<String>,
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A state of the document lifecycle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WorkflowStateDefinition {
+    pub name: String,
+    // Document level ACLs (permission -> principals) applied when entering the state
+    #[serde(rename = "aclTemplate", default, skip_serializing_if = "Option::is_none")]
+    pub acl_template: Option<HashMap<String, Vec<String>>>,
+}
+
+/// An allowed move between two states
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WorkflowTransitionDefinition {
+    pub from: String,
+    pub to: String,
+    // The document permission needed to make the transition
+    #[serde(default = "default_transition_permission")]
+    pub permission: String,
+}
+
+fn default_transition_permission() -> String {
+    "manage".to_string()
+}
+
+/// The document lifecycle of an organization
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WorkflowDefinition {
+    pub initial: String,
+    pub states: Vec<WorkflowStateDefinition>,
+    pub transitions: Vec<WorkflowTransitionDefinition>,
+    // Called with every transition of a document in the organization
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+}
+
+impl WorkflowDefinition {
+    pub fn state(&self, name: &str) -> Option<&WorkflowStateDefinition> {
+        self.states.iter().find(|s| s.name == name)
+    }
+
+    pub fn transition(&self, from: &str, to: &str) -> Option<&WorkflowTransitionDefinition> {
+        self.transitions.iter().find(|t| t.from == from && t.to == to)
+    }
+
+    pub fn transitions_from<'a>(&'a self, from: &'a str) -> impl Iterator<Item = &'a WorkflowTransitionDefinition> {
+        self.transitions.iter().filter(move |t| t.from == from)
+    }
+}
+
+impl Default for WorkflowDefinition {
+    // draft -> in-review -> approved -> published -> archived
+    fn default() -> Self {
+        let state = |name: &str| WorkflowStateDefinition { name: name.to_string(), acl_template: None };
+        let transition = |from: &str, to: &str, permission: &str| WorkflowTransitionDefinition {
+            from: from.to_string(),
+            to: to.to_string(),
+            permission: permission.to_string(),
+        };
+        Self {
+            initial: "draft".to_string(),
+            states: vec![state("draft"), state("in-review"), state("approved"), state("published"), state("archived")],
+            transitions: vec![
+                transition("draft", "in-review", "edit"),
+                transition("in-review", "draft", "edit"),
+                transition("in-review", "approved", "manage"),
+                transition("approved", "draft", "manage"),
+                transition("approved", "published", "manage"),
+                transition("published", "draft", "manage"),
+                transition("published", "archived", "manage"),
+                transition("draft", "archived", "manage"),
+                transition("archived", "draft", "manage"),
+            ],
+            webhooks: Vec::new(),
+        }
+    }
+}
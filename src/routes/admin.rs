@@ -0,0 +1,42 @@
+use crate::{handlers::{health_check, ready_check, metrics, diagnostics, diagnostics_orgs, billing_report, drain_start, drain_status, user_principals_push, org_features, org_feature_set, org_embed_settings, org_embed_settings_set, org_document_types, org_document_types_set, doc_recording_start, doc_recording_stop, doc_recording_status, doc_recording_download, org_schema_migration_start, org_schema_migration_status, org_announcement_create, org_announcements, org_announcement_delete, org_locale_settings, org_locale_settings_set}, ws::docctx::DocContext, routes::auth_middleware::auth_middleware, routes::timeout_middleware::{timeout_middleware, Budget}};
+use axum::{routing::{get, post, put, delete}, Router, middleware};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+
+/// Create the routes of the internal admin listener.
+/// The probes and metrics are unauthenticated, the admin endpoints keep the /api prefix and the
+/// middleware stack of the API.
+pub fn create_admin_routes(registry: Arc<HubRegistry<DocContext>>) -> Router {
+    // The billing report covers every org of the month and gets the longer budget
+    let long_admin_api = Router::<Arc<HubRegistry<DocContext>>>::new()
+        .route("/admin/billing/:period", get(billing_report))
+        .route_layer(middleware::from_fn(auth_middleware))
+        .route_layer(middleware::from_fn_with_state(Budget::Long, timeout_middleware));
+
+    let admin_api = Router::<Arc<HubRegistry<DocContext>>>::new()
+        .route("/v1/diagnostics", get(diagnostics))
+        .route("/v1/diagnostics/orgs", get(diagnostics_orgs))
+        .route("/admin/drain", post(drain_start))
+        .route("/admin/drain/status", get(drain_status))
+        .route("/internal/users/:uid/principals", post(user_principals_push))
+        .route("/admin/:org_id/features", get(org_features))
+        .route("/admin/:org_id/features/:feature", put(org_feature_set))
+        .route("/admin/:org_id/embed", get(org_embed_settings).put(org_embed_settings_set))
+        .route("/admin/:org_id/document-types", get(org_document_types).put(org_document_types_set))
+        .route("/admin/:org_id/documents/:doc_id/recording", post(doc_recording_start).get(doc_recording_status).delete(doc_recording_stop))
+        .route("/admin/:org_id/documents/:doc_id/recording/download", get(doc_recording_download))
+        .route("/admin/:org_id/schema-migration", post(org_schema_migration_start).get(org_schema_migration_status))
+        .route("/admin/:org_id/announcements", post(org_announcement_create).get(org_announcements))
+        .route("/admin/:org_id/announcements/:announcement_id", delete(org_announcement_delete))
+        .route("/admin/:org_id/locale", get(org_locale_settings).put(org_locale_settings_set))
+        .route_layer(middleware::from_fn(auth_middleware)) // Applies to all routes added above
+        .route_layer(middleware::from_fn_with_state(Budget::Default, timeout_middleware)) // Wraps the auth as well, it may fetch the user context
+        .merge(long_admin_api);
+
+    Router::<Arc<HubRegistry<DocContext>>>::new()
+        .route("/health", get(health_check))
+        .route("/ready", get(ready_check))
+        .route("/metrics", get(metrics))
+        .nest("/api", admin_api)
+        .with_state(registry)
+}
@@ -1,16 +1,83 @@
-use crate::{handlers::{doc_latest, doc_version, doc_move_lib, doc_delete, diagnostics}, ws::docctx::DocContext, routes::auth_middleware::auth_middleware};
-use axum::{routing::{get, post, delete}, Router, middleware};
+use crate::{handlers::{doc_latest, doc_version, doc_move_lib, doc_delete, doc_permissions, doc_access_report, doc_comments, doc_comment_add, doc_comment_edit, doc_comment_resolve, doc_reactions, doc_reaction_add, doc_reaction_remove, doc_user_prefs, doc_user_prefs_put, doc_unread, doc_follow, doc_unfollow, doc_watch, doc_unwatch, doc_suggestions, doc_suggestion_add, doc_suggestion_accept, doc_suggestion_reject, doc_approval_rounds, doc_approval_round_start, doc_approval_round_cancel, doc_state, doc_state_transition, doc_citation, doc_evidence, doc_published_signature, doc_published_verify, doc_room, doc_quarantine, doc_quarantine_retry, doc_quarantine_repair, doc_storage, doc_versions, doc_storage_budget, archival_candidates, doc_playback, doc_blame, doc_revert_author, doc_reconcile, doc_reconcile_merge, doc_save_status, doc_settings, doc_settings_patch, doc_grid_export, doc_csv_import, doc_share_token_create, doc_share_tokens, doc_share_token_revoke, doc_summary, doc_summary_regenerate, doc_summaries, doc_policy_findings, doc_policy_review, org_analytics, doc_blocks_split, doc_blocks_join, doc_statements_link, doc_transclusions, doc_links, doc_backlinks, statement_duplicates, statement_duplicates_analyze, doc_create, doc_number, doc_replace, replace_job_start, replace_job, doc_compare, doc_resync}, ws::docctx::DocContext, routes::auth_middleware::auth_middleware, routes::timeout_middleware::{timeout_middleware, Budget}};
+use axum::{routing::{get, post, put, patch, delete}, Router, middleware};
 use loro_websocket_server::HubRegistry;
 use std::sync::Arc;
 
 /// Create API routes
 pub fn create_api_routes(registry: Arc<HubRegistry<DocContext>>) -> Router {
-    Router::<Arc<HubRegistry<DocContext>>>::new()
-        .route("/v1/diagnostics", get(diagnostics))
+    // Routes that export, import or rework whole documents get the longer budget.
+    // The document reads export by the Accept header, so they are long as a whole.
+    let long_routes = Router::<Arc<HubRegistry<DocContext>>>::new()
         .route("/v1/:org_id/documents/:doc_id", get(doc_latest))
         .route("/v1/:org_id/documents/:doc_id/version", post(doc_version))
+        .route("/v1/:org_id/documents/:doc_id/access-report", get(doc_access_report))
+        .route("/v1/:org_id/documents/:doc_id/citation", get(doc_citation))
+        .route("/v1/:org_id/documents/:doc_id/evidence", get(doc_evidence))
+        .route("/v1/:org_id/documents/:doc_id/quarantine/repair", post(doc_quarantine_repair))
+        .route("/v1/:org_id/documents/:doc_id/playback", get(doc_playback))
+        .route("/v1/:org_id/documents/:doc_id/blocks/:block_id/blame", get(doc_blame))
+        .route("/v1/:org_id/documents/:doc_id/revert-author", post(doc_revert_author))
+        .route("/v1/:org_id/documents/:doc_id/reconcile", get(doc_reconcile).post(doc_reconcile_merge))
+        .route("/v1/:org_id/documents/:doc_id/blocks/:block_id/export.csv", get(doc_grid_export))
+        .route("/v1/:org_id/documents/:doc_id/blocks/import-csv", post(doc_csv_import))
+        .route("/v1/:org_id/documents/:doc_id/blocks/lazy", post(doc_blocks_split).delete(doc_blocks_join))
+        .route("/v1/:org_id/documents/:doc_id/statements/link", post(doc_statements_link))
+        .route("/v1/:org_id/documents/:doc_id/replace", post(doc_replace))
+        .route("/v1/:org_id/documents/:doc_id/compare", post(doc_compare))
+        .route("/v1/:org_id/documents/:doc_id/resync", post(doc_resync))
+        .route("/v1/:org_id/documents/:doc_id/transclusions", get(doc_transclusions))
+        .route("/v1/:org_id/documents/:doc_id/summary", get(doc_summary).post(doc_summary_regenerate))
+        .route("/v1/:org_id/documents/summaries", post(doc_summaries))
+        .route("/v1/:org_id/documents/:doc_id/policy-findings/review", post(doc_policy_review))
+        .route("/orgs/:org_id/analytics", get(org_analytics))
+        .route_layer(middleware::from_fn(auth_middleware))
+        .route_layer(middleware::from_fn_with_state(Budget::Long, timeout_middleware));
+
+    Router::<Arc<HubRegistry<DocContext>>>::new()
         .route("/v1/:org_id/documents/:doc_id/move-lib", post(doc_move_lib))
         .route("/v1/:org_id/documents/:doc_id", delete(doc_delete))
+        .route("/v1/:org_id/documents/:doc_id/permissions", get(doc_permissions))
+        .route("/v1/:org_id/documents/:doc_id/comments", get(doc_comments))
+        .route("/v1/:org_id/documents/:doc_id/comments", post(doc_comment_add))
+        .route("/v1/:org_id/documents/:doc_id/comments/:comment_id", patch(doc_comment_edit))
+        .route("/v1/:org_id/documents/:doc_id/comments/:comment_id/resolve", post(doc_comment_resolve))
+        .route("/v1/:org_id/documents/:doc_id/reactions", get(doc_reactions).post(doc_reaction_add).delete(doc_reaction_remove))
+        .route("/v1/:org_id/documents/:doc_id/suggestions", get(doc_suggestions))
+        .route("/v1/:org_id/documents/:doc_id/suggestions", post(doc_suggestion_add))
+        .route("/v1/:org_id/documents/:doc_id/suggestions/:suggestion_id/accept", post(doc_suggestion_accept))
+        .route("/v1/:org_id/documents/:doc_id/suggestions/:suggestion_id/reject", post(doc_suggestion_reject))
+        .route("/v1/:org_id/documents/:doc_id/approval-rounds", get(doc_approval_rounds))
+        .route("/v1/:org_id/documents/:doc_id/approval-rounds", post(doc_approval_round_start))
+        .route("/v1/:org_id/documents/:doc_id/approval-rounds/:round_id/cancel", post(doc_approval_round_cancel))
+        .route("/v1/:org_id/documents/:doc_id/state", get(doc_state))
+        .route("/v1/:org_id/documents/:doc_id/state", post(doc_state_transition))
+        .route("/v1/:org_id/documents/:doc_id/published/signature", get(doc_published_signature))
+        .route("/v1/:org_id/documents/:doc_id/published/verify", post(doc_published_verify))
+        .route("/v1/:org_id/documents/:doc_id/room", get(doc_room))
+        .route("/v1/:org_id/documents/:doc_id/quarantine", get(doc_quarantine))
+        .route("/v1/:org_id/documents/:doc_id/quarantine/retry", post(doc_quarantine_retry))
+        .route("/v1/:org_id/documents/:doc_id/storage", get(doc_storage))
+        .route("/v1/:org_id/documents/:doc_id/storage/budget", put(doc_storage_budget))
+        .route("/v1/:org_id/documents/:doc_id/versions", get(doc_versions))
+        .route("/v1/:org_id/archival/candidates", get(archival_candidates))
+        .route("/v1/:org_id/documents/:doc_id/save-status", get(doc_save_status))
+        .route("/v1/:org_id/documents/:doc_id/me/prefs", get(doc_user_prefs).put(doc_user_prefs_put))
+        .route("/v1/:org_id/documents/:doc_id/unread", get(doc_unread))
+        .route("/v1/:org_id/documents/:doc_id/me/follow", put(doc_follow).delete(doc_unfollow))
+        .route("/v1/:org_id/documents/:doc_id/watch", put(doc_watch).delete(doc_unwatch))
+        .route("/v1/:org_id/documents/:doc_id/settings", get(doc_settings).patch(doc_settings_patch))
+        .route("/v1/:org_id/documents/:doc_id/links", get(doc_links))
+        .route("/v1/:org_id/documents/:doc_id/backlinks", get(doc_backlinks))
+        .route("/v1/:org_id/numbers/:number", get(doc_number))
+        .route("/v1/:org_id/replace-jobs", post(replace_job_start))
+        .route("/v1/:org_id/replace-jobs/:job_id", get(replace_job))
+        .route("/v1/:org_id/statements/duplicates", get(statement_duplicates).post(statement_duplicates_analyze))
+        .route("/v1/:org_id/documents/:doc_id/share-tokens", get(doc_share_tokens).post(doc_share_token_create))
+        .route("/v1/:org_id/documents/:doc_id/share-tokens/:token_id", delete(doc_share_token_revoke))
+        .route("/v1/:org_id/documents/:doc_id/policy-findings", get(doc_policy_findings))
+        .route("/orgs/:org_id/docs", post(doc_create))
         .route_layer(middleware::from_fn(auth_middleware)) // Applies to all routes added above
+        .route_layer(middleware::from_fn_with_state(Budget::Default, timeout_middleware)) // Wraps the auth as well, it may fetch the user context
+        .merge(long_routes)
         .with_state(registry)
 }
@@ -1,4 +1,5 @@
 use axum::{
+    body::{self, Body},
     extract::Request,
     http::{StatusCode},
     middleware::Next,
@@ -9,6 +10,9 @@ use crate::config;
 use crate::ws::userctx;
 use crate::services::auth_service::{validate_jwt, get_auth_token};
 
+// Largest request body that is kept around to retry a request after refreshing the principals
+const MAX_RETRY_BODY_BYTES: usize = 64 * 1024 * 1024;
+
 pub async fn auth_middleware(
     mut req: Request,
     next: Next,
@@ -63,9 +67,11 @@ pub async fn auth_middleware(
             None => Vec::new(),
         };
 
-        // 8A. Load User Context and the prpls for the user
-        let user_ctx = match userctx::get_or_fetch_user_ctx_blocking(&user_uid, roles, true) {
-            Ok(user_ctx) => {
+        // 8A. Load User Context and the prpls for the user, from the cache when possible.
+        // The cached context may come from another token of the user, the roles are those of this token.
+        let user_ctx = match userctx::get_or_fetch_user_ctx_async(&user_uid, roles.clone(), false).await {
+            Ok(mut user_ctx) => {
+                user_ctx.token_roles = roles;
                 user_ctx
             }
             Err(e) => {
@@ -79,8 +85,11 @@ pub async fn auth_middleware(
         {
             let extensions = req.extensions_mut();
             extensions.insert(prpls);
-            extensions.insert(user_uid);
+            extensions.insert(user_uid.clone());
         }
+
+        // 10A. When the handler denies access, the principals may be stale. Refresh them and retry once.
+        return Ok(run_with_refresh(req, next, &user_uid, &user_ctx).await);
     }
     // 5B. If this is a service token, just extract the service name as prpl
     else if token_type == "service" {
@@ -115,3 +124,50 @@ pub async fn auth_middleware(
     Ok(next.run(req).await)
 
 }
+
+// Run a user request, and when it is denied with stale principals, run it once more with refreshed ones
+async fn run_with_refresh(req: Request, next: Next, user_uid: &str, user_ctx: &userctx::UserCtx) -> Response {
+    let (parts, req_body) = req.into_parts();
+    let bytes = match body::to_bytes(req_body, MAX_RETRY_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to read request body: {}", e);
+            return Response::builder()
+                .status(StatusCode::PAYLOAD_TOO_LARGE)
+                .body(Body::empty())
+                .unwrap_or_default();
+        }
+    };
+
+    // Keep what is needed to rebuild the request
+    let (method, uri, version, headers) = (parts.method.clone(), parts.uri.clone(), parts.version, parts.headers.clone());
+    let response = next.clone().run(Request::from_parts(parts, Body::from(bytes.clone()))).await;
+    if response.status() != StatusCode::FORBIDDEN {
+        return response;
+    }
+
+    let refreshed = match userctx::refresh_user_ctx(user_uid).await {
+        Ok(Some(refreshed)) if refreshed.principals != user_ctx.principals => userctx::UserCtx {
+            principals: refreshed.principals,
+            token_roles: user_ctx.token_roles.clone(),
+        },
+        Ok(_) => return response,
+        Err(e) => {
+            error!("Failed to refresh principals of user {}: {}", user_uid, e);
+            return response;
+        }
+    };
+    info!("Principals of user {} changed, retrying {} {}", user_uid, method, uri);
+
+    let mut retry = Request::new(Body::from(bytes));
+    *retry.method_mut() = method;
+    *retry.uri_mut() = uri;
+    *retry.version_mut() = version;
+    *retry.headers_mut() = headers;
+    {
+        let extensions = retry.extensions_mut();
+        extensions.insert(refreshed.get_all_prpls());
+        extensions.insert(user_uid.to_string());
+    }
+    next.run(retry).await
+}
@@ -1,4 +1,9 @@
+pub mod admin;
 pub mod api;
 pub mod auth_middleware;
+pub mod timeout_middleware;
+pub mod public;
 
+pub use admin::*;
 pub use api::*;
+pub use public::*;
@@ -0,0 +1,13 @@
+use crate::{handlers::{public_view, public_embed, public_view_card}, ws::docctx::DocContext};
+use axum::{routing::get, Router};
+use loro_websocket_server::HubRegistry;
+use std::sync::Arc;
+
+/// Create the unauthenticated public routes
+pub fn create_public_routes(registry: Arc<HubRegistry<DocContext>>) -> Router {
+    Router::<Arc<HubRegistry<DocContext>>>::new()
+        .route("/public/:share_token", get(public_view))
+        .route("/public/:share_token/embed", get(public_embed))
+        .route("/public/:share_token/card.json", get(public_view_card))
+        .with_state(registry)
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+use uuid::Uuid;
+use crate::config;
+use crate::models::{api_error, RequestId};
+
+static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Budget of the handlers of a route, set when the routes are built
+#[derive(Clone, Copy, Debug)]
+pub enum Budget {
+    Default,
+    // Routes that export, import or rework whole documents
+    Long,
+}
+
+impl Budget {
+    fn duration(self) -> Duration {
+        let config = config::get_config();
+        let ms = match self {
+            Budget::Default => config.api_timeout_ms.unwrap_or(5_000),
+            Budget::Long => config.api_long_timeout_ms.unwrap_or(30_000),
+        };
+        Duration::from_millis(ms)
+    }
+}
+
+// Cancel handlers that run over the budget of their route and answer 504. The budget is the state
+// of the layer, see create_api_routes for the routes layered with Budget::Long.
+// Dropping the handler future releases the hub locks it holds, edits in progress close their room
+// on the way out. The request id of the caller, or a new one, is passed to the handlers as a
+// RequestId extension and echoed in the x-request-id header.
+pub async fn timeout_middleware(
+    State(budget): State<Budget>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let request_id = req.headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let path = req.extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+    let method = req.method().clone();
+    let budget = budget.duration();
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let mut response = match tokio::time::timeout(budget, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("Request {} {} {} cancelled after {} ms", request_id, method, path, budget.as_millis());
+            api_error(StatusCode::GATEWAY_TIMEOUT, format!("Request '{}' timed out after {} ms", request_id, budget.as_millis())).into_response()
+        }
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER.clone(), value);
+    }
+    response
+}
@@ -0,0 +1,97 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use moka::sync::Cache;
+use tracing::warn;
+use uuid::Uuid;
+use crate::config;
+
+// Cache of the access decisions taken when a user joins the room of a document.
+// Every join otherwise queries the ACL rows of the document, the library holding it and its workflow
+// state. A decision is keyed by the document and a hash of the principals it was taken for, so users
+// whose principals changed are decided again. Decisions expire after a short time, for ACLs the app
+// service changes, and the endpoints changing ACLs or the state of a document forget its decisions.
+
+/// The access decided for a set of principals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessDecision {
+    Denied,
+    Read,
+    Write,
+}
+
+// Document and hash of the principals
+type DecisionKey = (String, Uuid, u64);
+
+static DECISIONS: OnceLock<Option<Cache<DecisionKey, AccessDecision>>> = OnceLock::new();
+static HITS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static MISSES_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+fn get_decisions() -> Option<&'static Cache<DecisionKey, AccessDecision>> {
+    DECISIONS
+        .get_or_init(|| {
+            let ttl_ms = config::get_config().acl_cache_ttl_ms.unwrap_or(10 * 1000);
+            if ttl_ms == 0 {
+                return None;
+            }
+            Some(
+                Cache::builder()
+                    .max_capacity(100_000)
+                    .time_to_live(Duration::from_millis(ttl_ms))
+                    .support_invalidation_closures()
+                    .build(),
+            )
+        })
+        .as_ref()
+}
+
+// The principals in any order and with duplicates hash the same
+fn principals_hash(principals: &[String]) -> u64 {
+    let mut sorted: Vec<&String> = principals.iter().collect();
+    sorted.sort();
+    sorted.dedup();
+    let mut hasher = DefaultHasher::new();
+    sorted.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Decisions answered from the cache on this instance
+pub fn hits_total() -> u64 {
+    HITS_TOTAL.load(Ordering::Relaxed)
+}
+
+/// Decisions taken against the database on this instance
+pub fn misses_total() -> u64 {
+    MISSES_TOTAL.load(Ordering::Relaxed)
+}
+
+/// The cached decision on a document for a set of principals
+pub fn get(org_id: &str, doc_uuid: Uuid, principals: &[String]) -> Option<AccessDecision> {
+    let decisions = get_decisions()?;
+    let decision = decisions.get(&(org_id.to_string(), doc_uuid, principals_hash(principals)));
+    match decision {
+        Some(_) => HITS_TOTAL.fetch_add(1, Ordering::Relaxed),
+        None => MISSES_TOTAL.fetch_add(1, Ordering::Relaxed),
+    };
+    decision
+}
+
+/// Remember the decision on a document for a set of principals
+pub fn insert(org_id: &str, doc_uuid: Uuid, principals: &[String], decision: AccessDecision) {
+    if let Some(decisions) = get_decisions() {
+        decisions.insert((org_id.to_string(), doc_uuid, principals_hash(principals)), decision);
+    }
+}
+
+/// Forget the decisions on a document, after its ACLs, library or state changed
+pub fn invalidate_document(org_id: &str, doc_uuid: Uuid) {
+    let Some(decisions) = get_decisions() else {
+        return;
+    };
+    let org_id = org_id.to_string();
+    if let Err(e) = decisions.invalidate_entries_if(move |(org, doc, _), _| *org == org_id && *doc == doc_uuid) {
+        warn!("Failed to invalidate the access decisions of document {}: {}", doc_uuid, e);
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use loro::{LoroDoc, LoroMap, ToJson};
+use loro_websocket_server::HubRegistry;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::auth::CLOUD_ADMIN_PRPL;
+use crate::db::dbcolab::{self, DocumentAccessRows};
+use crate::doctypes;
+use crate::services::doc_load_service;
+use crate::ws::docctx::DocContext;
+use crate::models::{ColabModelPermission, PermissionGrant};
+use crate::models::lorodoc::{get_block_id, get_child_map, get_child_movable_list, get_doc_type, get_list_map, get_string};
+use crate::ws::userctx;
+
+pub const ALL_PERMISSIONS: [ColabModelPermission; 6] = [
+    ColabModelPermission::View,
+    ColabModelPermission::Edit,
+    ColabModelPermission::Manage,
+    ColabModelPermission::AddRemove,
+    ColabModelPermission::Delete,
+    ColabModelPermission::Suggest,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AclLevel {
+    Document,
+    Block,
+    Language,
+}
+
+impl fmt::Display for AclLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AclLevel::Document => write!(f, "document"),
+            AclLevel::Block => write!(f, "block"),
+            AclLevel::Language => write!(f, "language"),
+        }
+    }
+}
+
+/// A place in the document that can carry an `acls` map
+pub struct AclScope {
+    pub path: String,
+    pub level: AclLevel,
+    pub parent: Option<usize>,
+    pub acls: Option<LoroMap>,
+    // The map owning the acls, None for the document scope
+    pub map: Option<LoroMap>,
+}
+
+impl AclScope {
+    /// The ACL entries of this scope, keyed by permission
+    pub fn entries(&self) -> HashMap<String, Vec<String>> {
+        match &self.acls {
+            Some(acls) => read_acl_map(acls),
+            None => HashMap::new(),
+        }
+    }
+}
+
+// Read an ACL map (permission -> list of principals) into a HashMap
+pub fn read_acl_map(acls: &LoroMap) -> HashMap<String, Vec<String>> {
+    let mut entries = HashMap::new();
+    if let serde_json::Value::Object(obj) = acls.get_deep_value().to_json_value() {
+        for (permission, prpls) in obj {
+            let prpls: Vec<String> = prpls
+                .as_array()
+                .map(|arr| arr.iter().filter_map(|p| p.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default();
+            entries.insert(permission, prpls);
+        }
+    }
+    entries
+}
+
+// Load everything needed to evaluate access on a document: the database access rows and the ACL scopes
+// of the latest document state. Returns None if the document does not exist.
+pub async fn load_document_acls(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str) -> Result<Option<(DocumentAccessRows, Vec<AclScope>)>, String> {
+    let doc_uuid = Uuid::parse_str(doc_id)
+        .map_err(|e| format!("Invalid document UUID '{}': {}", doc_id, e))?;
+
+    // Get the owner and the ACL rows from the database
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let access = match db.get_document_access(org_id, doc_uuid).await {
+        Ok(Some(access)) => access,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            error!("Failed to load ACLs for document '{}': {}", doc_id, e);
+            return Err(format!("Failed to load ACLs for document '{}': {}", doc_id, e));
+        }
+    };
+
+    // Get the latest state of the document for the Loro ACLs
+    let loro_doc = match doc_load_service::load_loro_doc(registry, org_id, doc_id).await? {
+        Some((loro_doc, _ctx)) => loro_doc,
+        None => return Ok(None),
+    };
+    let scopes = collect_acl_scopes(&loro_doc)?;
+
+    Ok(Some((access, scopes)))
+}
+
+// The permissions a principal holds on the scope at `path`, including the ones inherited from the document.
+// Returns None if the document or the path does not exist.
+pub async fn permissions_on_path(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, path: &str, prpl: &str) -> Result<Option<BTreeSet<String>>, String> {
+    let (access, scopes) = match load_document_acls(registry, org_id, doc_id).await? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+    let idx = match scopes.iter().position(|s| s.path == path) {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+    let principals = resolve_principals(org_id, prpl).await;
+    let grants = collect_scope_grants(&scopes, idx, org_id, &access);
+    Ok(Some(granted_permissions(&grants, &principals)))
+}
+
+// The permissions a set of principals holds on a document according to the database only
+// (document ACLs, library ACLs and implicit grants). Loro ACLs can only narrow this down to blocks.
+pub async fn document_db_permissions(org_id: &str, doc_uuid: Uuid, principals: &[String]) -> Result<Option<BTreeSet<String>>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    match db.get_document_access(org_id, doc_uuid).await {
+        Ok(Some(access)) => Ok(Some(granted_permissions(&document_level_grants(org_id, &access, false), principals))),
+        Ok(None) => Ok(None),
+        Err(e) => Err(format!("Failed to load ACLs for document '{}': {}", doc_uuid, e)),
+    }
+}
+
+// Find the map of the block or language at `path`
+pub fn find_scope_map(doc: &LoroDoc, path: &str) -> Result<LoroMap, String> {
+    collect_acl_scopes(doc)?
+        .into_iter()
+        .find(|scope| scope.path == path)
+        .and_then(|scope| scope.map)
+        .ok_or_else(|| format!("No block or language found at path '{}'", path))
+}
+
+// Remove the given permissions from every ACL scope of the document (document, blocks and languages).
+// Returns the number of ACL entries that were removed.
+pub fn strip_loro_permissions(doc: &LoroDoc, permissions: &[ColabModelPermission]) -> Result<usize, String> {
+    let mut removed = 0;
+    for scope in collect_acl_scopes(doc)? {
+        let acls = match &scope.acls {
+            Some(acls) => acls,
+            None => continue,
+        };
+        for permission in permissions {
+            let key = permission.to_string();
+            if acls.get(&key).is_some() {
+                acls.delete(&key)
+                    .map_err(|e| format!("Failed to remove '{}' ACL at '{}': {}", key, scope.path, e))?;
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+// Walk the document and collect all ACL scopes: the document itself, the blocks (sheets),
+// the local statements in statement-grid rows and the languages of statements.
+// The first scope is always the document scope, parents always come before their children.
+pub fn collect_acl_scopes(doc: &LoroDoc) -> Result<Vec<AclScope>, String> {
+    let doc_type = get_doc_type(doc)
+        .ok_or_else(|| "Document type property not found".to_string())?;
+
+    let mut scopes = vec![AclScope {
+        path: "/".to_string(),
+        level: AclLevel::Document,
+        parent: None,
+        acls: Some(doc.get_map("acls")),
+        map: None,
+    }];
+
+    doctypes::get_or_error(&doc_type)?.collect_acl_scopes(doc, &mut scopes);
+    Ok(scopes)
+}
+
+pub(crate) fn collect_language_scopes(content: &LoroMap, base_path: &str, parent: usize, scopes: &mut Vec<AclScope>) {
+    let mut lang_codes: Vec<String> = content.keys().map(|k| k.to_string()).collect();
+    lang_codes.sort();
+    for lang_code in lang_codes {
+        if let Some(lang_map) = get_child_map(content, &lang_code) {
+            scopes.push(AclScope {
+                path: format!("{}/content/{}", base_path, lang_code),
+                level: AclLevel::Language,
+                parent: Some(parent),
+                acls: get_child_map(&lang_map, "acls"),
+                map: Some(lang_map),
+            });
+        }
+    }
+}
+
+pub(crate) fn collect_sheet_scopes(doc: &LoroDoc, scopes: &mut Vec<AclScope>) {
+    let content = doc.get_movable_list("content");
+    for i in 0..content.len() {
+        let block = match get_list_map(&content, i) {
+            Some(block) => block,
+            None => continue,
+        };
+
+        let block_path = format!("/content/{}", get_block_id(&block, i));
+        let block_idx = scopes.len();
+        scopes.push(AclScope {
+            path: block_path.clone(),
+            level: AclLevel::Block,
+            parent: Some(0),
+            acls: get_child_map(&block, "acls"),
+            map: Some(block.clone()),
+        });
+
+        // Local statements in a statement grid carry their own ACLs
+        if get_string(&block, "type").as_deref() != Some("statement-grid") {
+            continue;
+        }
+        let rows = match get_child_movable_list(&block, "rows") {
+            Some(rows) => rows,
+            None => continue,
+        };
+        for r in 0..rows.len() {
+            let row = match get_list_map(&rows, r) {
+                Some(row) => row,
+                None => continue,
+            };
+            if get_string(&row, "type").as_deref() != Some("local") {
+                continue;
+            }
+            if let Some(statement) = get_child_map(&row, "statement") {
+                let statement_path = format!("{}/rows/{}/statement", block_path, r);
+                let statement_idx = scopes.len();
+                scopes.push(AclScope {
+                    path: statement_path.clone(),
+                    level: AclLevel::Block,
+                    parent: Some(block_idx),
+                    acls: get_child_map(&statement, "acls"),
+                    map: Some(statement.clone()),
+                });
+                if let Some(statement_content) = get_child_map(&statement, "content") {
+                    collect_language_scopes(&statement_content, &statement_path, statement_idx, scopes);
+                }
+            }
+        }
+    }
+}
+
+// Collect every grant that applies to the scope at `idx`: the database ACL rows, the implicit
+// owner and admin grants, and the Loro ACL entries of the scope and all of its ancestors.
+pub fn collect_scope_grants(scopes: &[AclScope], idx: usize, org_id: &str, access: &DocumentAccessRows) -> Vec<PermissionGrant> {
+    let mut grants = document_level_grants(org_id, access, idx != 0);
+
+    let mut current = Some(idx);
+    while let Some(i) = current {
+        let scope = &scopes[i];
+        let mut entries: Vec<(String, Vec<String>)> = scope.entries().into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (permission, prpls) in entries {
+            for prpl in prpls {
+                grants.push(PermissionGrant {
+                    permission: permission.clone(),
+                    source: "loro-acl".to_string(),
+                    prpl,
+                    path: scope.path.clone(),
+                    inherited: i != idx,
+                });
+            }
+        }
+        current = scope.parent;
+    }
+    grants
+}
+
+// The permissions a set of principals holds according to a list of grants
+pub fn granted_permissions(grants: &[PermissionGrant], principals: &[String]) -> BTreeSet<String> {
+    grants
+        .iter()
+        .filter(|g| principals.contains(&g.prpl))
+        .map(|g| g.permission.clone())
+        .collect()
+}
+
+fn document_level_grants(org_id: &str, access: &DocumentAccessRows, inherited: bool) -> Vec<PermissionGrant> {
+    let mut grants = Vec::new();
+
+    for row in &access.acls {
+        grants.push(PermissionGrant {
+            permission: row.permission.clone(),
+            source: "document-acl".to_string(),
+            prpl: row.prpl.clone(),
+            path: "/".to_string(),
+            inherited,
+        });
+    }
+
+    for row in &access.library_acls {
+        grants.push(PermissionGrant {
+            permission: row.permission.clone(),
+            source: "library-acl".to_string(),
+            prpl: row.prpl.clone(),
+            path: format!("library/{}", row.library),
+            inherited,
+        });
+    }
+
+    // The owner, the org admins and the cloud admins implicitly hold every permission
+    let implicit = [
+        ("owner", access.owner.clone()),
+        ("org-admin", format!("{}/f/admin", org_id)),
+        ("cloud-admin", CLOUD_ADMIN_PRPL.to_string()),
+    ];
+    for (source, prpl) in implicit {
+        for permission in ALL_PERMISSIONS.iter() {
+            grants.push(PermissionGrant {
+                permission: permission.to_string(),
+                source: source.to_string(),
+                prpl: prpl.clone(),
+                path: "/".to_string(),
+                inherited,
+            });
+        }
+    }
+    grants
+}
+
+// Expand a principal into the full set of principals used for access checks.
+// User principals (`{org}/u/{uid}`) are expanded through the user context, others are taken as is.
+pub async fn resolve_principals(org_id: &str, prpl: &str) -> Vec<String> {
+    let user_prefix = format!("{}/u/", org_id);
+    if let Some(uid) = prpl.strip_prefix(&user_prefix) {
+        match userctx::get_or_fetch_user_ctx_async(uid, Vec::new(), false).await {
+            Ok(user_ctx) => {
+                let mut principals = user_ctx.get_all_prpls();
+                if !principals.iter().any(|p| p == prpl) {
+                    principals.push(prpl.to_string());
+                }
+                return principals;
+            }
+            Err(e) => {
+                warn!("Failed to expand principals for '{}', using it as is: {}", prpl, e);
+            }
+        }
+    }
+    vec![prpl.to_string()]
+}
+
+// Remove all ACLs from a document: the document ACLs and the ACLs of every block, local statement and language
+pub fn reset_acls(doc: &LoroDoc) -> Result<(), String> {
+    let props = doc.get_map("properties");
+
+    // Use if let to safely get the type string without panicking unwrap()
+    if let Some(type_val) = props.get("type") {
+        // Safely convert to value value then string
+        let type_str = type_val.as_value()
+            .and_then(|v| v.as_string().map(|s| s.to_string()))
+            .ok_or_else(|| format!("Document type property is not a string"))?;
+
+        doctypes::get_or_error(&type_str)?.reset_acls(doc)?;
+    } else {
+         return Err("Document type property not found".to_string());
+    }
+    Ok(())
+}
+
+pub(crate) fn reset_acls_statement_doc(doc: &LoroDoc) -> Result<(), String> {
+    let acls = doc.get_map("acls");
+    acls.clear().map_err(|e| format!("Failed to clear ACLs: {}", e))?;
+
+    // Iterate over the languages
+    let content = doc.get_map("content");
+    let keys: Vec<String> = content.keys().map(|k| k.to_string()).collect();
+    
+    // Iterate over all keys in content
+    for lang_code in keys {
+        if let Some(val) = content.get(&lang_code) {
+            if let Some(container) = val.as_container() {
+                if let Some(map) = container.as_map() {
+                // Clear the ACLs for the language
+                    if let Some(acls_val) = map.get("acls") {
+                        if let Some(acls_container) = acls_val.as_container() {
+                            if let Some(acls_map) = acls_container.as_map() {
+                                acls_map.clear().map_err(|e| format!("Failed to clear ACLs for language '{}': {}", lang_code, e))?;
+                                info!("Cleared ACLs for language '{}'", lang_code);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    info!("Cleared ACLs for statement document");
+    Ok(())
+}
+
+pub(crate) fn reset_acls_sheet_doc(doc: &LoroDoc) -> Result<(), String> {
+    
+    info!("Resetting ACLs for sheet document");
+    
+    let acls = doc.get_map("acls");
+    acls.clear().map_err(|e| format!("Failed to clear ACLs: {}", e))?;
+    info!("Cleared top-level ACLs for sheet document");
+
+    // Iterate over the blocks
+    let content: loro::LoroMovableList = doc.get_movable_list("content");
+    
+
+    // Iterate over all keys in content
+    for i in 0..content.len() {
+        if let Some(val) = content.get(i) {
+            if let Some(container) = val.as_container() {
+                if let Some(block) = container.as_map() {
+
+                    // Clear the ACLs for the block
+                    if let Some(acls_val) = block.get("acls") {
+                        if let Some(acls_container) = acls_val.as_container() {
+                            if let Some(acls_map) = acls_container.as_map() {
+                                acls_map.clear().map_err(|e| format!("Failed to clear ACLs for block '{}': {}", i, e))?;
+                            }
+                        }
+                    }
+
+                    let block_type_str = block.get("type")
+                        .ok_or_else(|| "Block missing 'type' field".to_string())?
+                        .as_value()
+                        .ok_or_else(|| "'type' is not a value".to_string())?
+                        .as_string()
+                        .map(|v| v.to_string())
+                        .ok_or_else(|| "'type' is not a string".to_string())?;
+
+                    if block_type_str == "statement-grid" {
+                        // Safely get rows list
+                        let rows_val = block.get("rows")
+                            .ok_or_else(|| "Rows not found in statement-grid".to_string())?;
+
+                        let rows_container = rows_val.as_container()
+                            .ok_or_else(|| "Rows is not a container".to_string())?;
+                        let rows = rows_container.as_movable_list()
+                            .ok_or_else(|| "Rows is not a movable list".to_string())?;
+
+                        for r in 0..rows.len() {
+                            let row_val = rows.get(r)
+                                .ok_or_else(|| "No row found on this index".to_string())?;
+                            let row_container = row_val.as_container()
+                                .ok_or_else(|| "The row is not persisted as a container".to_string())?;
+                            let row = row_container.as_map()
+                                .ok_or_else(|| "The row is not persisted as a map".to_string())?;
+
+                            let row_type_val = row.get("type")
+                                .ok_or_else(|| "Row missing 'type' field".to_string())?;
+                            let row_type_value = row_type_val.as_value()
+                                .ok_or_else(|| "'type' is not a value".to_string())?;
+                            let row_type = row_type_value.as_string()
+                                .map(|v| v.to_string())
+                                .ok_or_else(|| "'type' is not a string".to_string())?;
+
+                            if row_type != "local" {
+                                continue;
+                            } else {
+                                let statement_val = row.get("statement")
+                                    .ok_or_else(|| "Row missing 'statement' field".to_string())?;
+                                let statement_container = statement_val.as_container()
+                                    .ok_or_else(|| "'statement' is not a container".to_string())?;
+                                let statement = statement_container.as_map()
+                                    .ok_or_else(|| "'statement' is not a map".to_string())?;
+
+                                reset_acls_statement(statement)?;
+                            }
+                        }
+                    }
+
+                    // Log cleared block ACLs
+                    info!("Cleared ACLs for block '{}'", i);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+fn reset_acls_statement(map: &LoroMap) -> Result<(), String> {
+    
+    // Get the statement top acls
+    let acls_val = map.get("acls")
+        .ok_or_else(|| "Could not find top acls on the statement".to_string())?;
+    let acls_container = acls_val.as_container()
+        .ok_or_else(|| "Top acls on statement is not a container".to_string())?;
+    let acls = acls_container.as_map()
+        .ok_or_else(|| "Top acls on statement is not a map".to_string())?;
+
+    let properties_val = map.get("properties")
+        .ok_or_else(|| "Could not find properties map on the statement".to_string())?;
+    let properties_container = properties_val.as_container()
+        .ok_or_else(|| "Properties on statement is not a container".to_string())?;
+    let properties = properties_container.as_map()
+        .ok_or_else(|| "Properties on statement is not a map".to_string())?;
+
+    let content_type_val = properties.get("contentType")
+        .ok_or_else(|| "Could not find content type property on the statement".to_string())?;
+    let content_type = content_type_val.as_value()
+        .ok_or_else(|| "Content type property on statement is not a value".to_string())?;
+    let content_type_str = content_type.as_string()
+        .ok_or_else(|| "Content type property on statement is not a string".to_string())?;
+    let content_type = content_type_str.to_string();
+
+    // Clear them
+    acls.clear().map_err(|e| format!("Failed to clear ACLs: {}", e))?;
+
+    // Get the content map
+    let content_val = map.get("content")
+        .ok_or_else(|| "Could not find content map on the statement".to_string())?;
+    let content_container = content_val.as_container()
+        .ok_or_else(|| "Content on statement is not a container".to_string())?;
+    let content = content_container.as_map()
+        .ok_or_else(|| "Content on statement is not a map".to_string())?;
+
+
+    // Iterate over the languages
+    let keys: Vec<String> = content.keys().map(|k| k.to_string()).collect();
+    
+    // Iterate over all keys in content
+    for lang_code in keys {
+        if let Some(val) = content.get(&lang_code) {
+            if let Some(container) = val.as_container() {
+                if let Some(map) = container.as_map() {
+                // Clear the ACLs for the language
+                    if let Some(acls_val) = map.get("acls") {
+                        if let Some(acls_container) = acls_val.as_container() {
+                            if let Some(acls_map) = acls_container.as_map() {
+                                acls_map.clear().map_err(|e| format!("Failed to clear ACLs for language '{}': {}", lang_code, e))?;
+                                info!("Cleared ACLs for language '{}'", lang_code);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    info!("Cleared ACLs for statement document with content type '{}'", content_type);
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use chrono::{Datelike, DurationRound, Utc};
+use loro_websocket_server::HubRegistry;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab::{self, OrgUsageBucketRow, OrgUsageSampleRow};
+use crate::services::hub_service;
+use crate::ws::docctx::DocContext;
+
+// Longest range that can be queried
+pub const MAX_RANGE_DAYS: i64 = 90;
+
+// Accepted updates per org and the updated documents since the previous sample
+static UPDATE_COUNTS: OnceLock<Mutex<HashMap<String, i64>>> = OnceLock::new();
+static UPDATED_DOCS: OnceLock<Mutex<HashSet<(String, Uuid)>>> = OnceLock::new();
+
+fn get_update_counts() -> &'static Mutex<HashMap<String, i64>> {
+    UPDATE_COUNTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn get_updated_docs() -> &'static Mutex<HashSet<(String, Uuid)>> {
+    UPDATED_DOCS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+// Count an accepted update for the edit rate of an org and the active documents of the month
+pub fn record_update(org_id: &str, doc_uuid: Uuid) {
+    let mut counts = get_update_counts().lock().unwrap();
+    *counts.entry(org_id.to_string()).or_insert(0) += 1;
+    drop(counts);
+    get_updated_docs().lock().unwrap().insert((org_id.to_string(), doc_uuid));
+}
+
+// Log an export of a document for billing, in the background
+pub fn record_export(org_id: &str, doc_uuid: Uuid, kind: &'static str, by_prpl: &str) {
+    let org_id = org_id.to_string();
+    let by_prpl = by_prpl.to_string();
+    tokio::spawn(async move {
+        if let Some(db) = dbcolab::get_db() {
+            if let Err(e) = db.insert_export_event(&org_id, doc_uuid, kind, &by_prpl).await {
+                warn!("Failed to record '{}' export of document '{}': {}", kind, doc_uuid, e);
+            }
+        }
+    });
+}
+
+// Start sampling the usage per org
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>) {
+    let interval_ms = config::get_config().analytics_sample_interval_ms.unwrap_or(60_000);
+    if interval_ms == 0 {
+        info!("Usage analytics disabled");
+        return;
+    }
+    let interval = Duration::from_millis(interval_ms);
+    info!("Starting usage analytics, sample interval: {:?}", interval);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut n_samples: u64 = 0;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = sample(&registry).await {
+                error!("Usage sample failed: {}", e);
+            }
+            // Sample the storage and trim the old samples about once an hour
+            if n_samples % (3_600_000 / interval_ms).max(1) == 0 {
+                if let Err(e) = sample_storage().await {
+                    error!("Storage sample failed: {}", e);
+                }
+                if let Err(e) = prune().await {
+                    error!("Pruning usage samples failed: {}", e);
+                }
+            }
+            n_samples += 1;
+        }
+    });
+}
+
+async fn sample(registry: &Arc<HubRegistry<DocContext>>) -> Result<(), String> {
+    let sampled_at = Utc::now().duration_trunc(chrono::Duration::minutes(1)).map_err(|e| e.to_string())?;
+
+    // 1. Count the rooms and connections per org, with the updates since the previous sample
+    let mut samples: HashMap<String, OrgUsageSampleRow> = HashMap::new();
+    let empty = |org: &str| OrgUsageSampleRow { org: org.to_string(), n_rooms: 0, n_doc_rooms: 0, n_conn: 0, n_updates: 0 };
+    for room in hub_service::cached_rooms(registry).await.iter() {
+        let sample = samples.entry(room.org.clone()).or_insert_with(|| empty(&room.org));
+        sample.n_rooms += 1;
+        if room.is_doc {
+            sample.n_doc_rooms += 1;
+        }
+        sample.n_conn += room.subscribers as i32;
+    }
+    let updates = std::mem::take(&mut *get_update_counts().lock().unwrap());
+    for (org, n_updates) in updates {
+        samples.entry(org.clone()).or_insert_with(|| empty(&org)).n_updates = n_updates;
+    }
+    let updated_docs: Vec<(String, Uuid)> = std::mem::take(&mut *get_updated_docs().lock().unwrap()).into_iter().collect();
+    if samples.is_empty() {
+        return Ok(());
+    }
+
+    // 2. Store them
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    if !updated_docs.is_empty() {
+        let month = sampled_at.date_naive().with_day(1).unwrap_or_else(|| sampled_at.date_naive());
+        db.insert_org_active_documents(month, &updated_docs)
+            .await
+            .map_err(|e| format!("Failed to store active documents: {}", e))?;
+    }
+    let pod = config::get_config().cloud_pod.clone().unwrap_or_else(|| "local".to_string());
+    let samples: Vec<OrgUsageSampleRow> = samples.into_values().collect();
+    db.insert_org_usage_samples(&pod, sampled_at, &samples)
+        .await
+        .map_err(|e| format!("Failed to store usage samples: {}", e))
+}
+
+async fn sample_storage() -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.sample_org_storage()
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to sample the storage per org: {}", e))
+}
+
+async fn prune() -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let retention_days = config::get_config().analytics_retention_days.unwrap_or(90) as i64;
+    let deleted = db.delete_org_usage_samples_before(Utc::now() - chrono::Duration::days(retention_days))
+        .await
+        .map_err(|e| format!("Failed to delete old usage samples: {}", e))?;
+    if deleted > 0 {
+        info!("Deleted {} usage samples older than {} days", deleted, retention_days);
+    }
+    Ok(())
+}
+
+// Parse a range like 90m, 24h or 7d
+pub fn parse_range(range: &str) -> Result<chrono::Duration, String> {
+    let invalid = || format!("Invalid range '{}', expected e.g. 60m, 24h or 7d", range);
+    let range = range.trim();
+    let unit = range.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = range[..range.len() - unit.len_utf8()].parse().map_err(|_| invalid())?;
+    let duration = match unit {
+        'm' => chrono::Duration::minutes(amount),
+        'h' => chrono::Duration::hours(amount),
+        'd' => chrono::Duration::days(amount),
+        _ => return Err(invalid()),
+    };
+    if amount <= 0 || duration > chrono::Duration::days(MAX_RANGE_DAYS) {
+        return Err(format!("Range '{}' must be positive and at most {} days", range, MAX_RANGE_DAYS));
+    }
+    Ok(duration)
+}
+
+// Buckets of 5 minutes up to a day, hours up to a week and days beyond
+pub fn bucket_for(range: chrono::Duration) -> chrono::Duration {
+    if range <= chrono::Duration::days(1) {
+        chrono::Duration::minutes(5)
+    } else if range <= chrono::Duration::days(7) {
+        chrono::Duration::hours(1)
+    } else {
+        chrono::Duration::days(1)
+    }
+}
+
+pub async fn usage(org_id: &str, range: chrono::Duration, bucket: chrono::Duration) -> Result<Vec<OrgUsageBucketRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_org_usage(org_id, Utc::now() - range, bucket.num_seconds())
+        .await
+        .map_err(|e| format!("Failed to load usage of organization '{}': {}", org_id, e))
+}
+
+// Minutes between samples, to turn sampled connections into connection time
+pub fn sample_minutes() -> f64 {
+    config::get_config().analytics_sample_interval_ms.unwrap_or(60_000) as f64 / 60_000.0
+}
@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use chrono::Utc;
+use loro::LoroValue;
+use loro::awareness::EphemeralStore;
+use loro_websocket_server::HubRegistry;
+use tracing::{debug, error, info};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab::{self, OrgAnnouncementRow};
+use crate::models::{OrgAnnouncement, OrgAnnouncementRequest};
+use crate::services::hub_service;
+use crate::ws::docctx::DocContext;
+
+// Announcements pushed to every connected client of an org through the ephemeral rooms.
+// Every running announcement is an entry "announcement:<id>" of the ephemeral store, the job pushes the
+// entries again at every interval so clients that joined since get them too. Clients drop an entry that
+// wasn't pushed for two intervals, and the pod withdraws the entries it pushed once their announcement
+// expired or was deleted. The content of documents is never touched.
+
+pub const LEVELS: [&str; 3] = ["info", "warning", "critical"];
+
+const KEY_PREFIX: &str = "announcement:";
+
+// The announcements pushed by this pod per org
+static PUSHED: OnceLock<Mutex<HashMap<String, HashSet<Uuid>>>> = OnceLock::new();
+
+fn get_pushed() -> &'static Mutex<HashMap<String, HashSet<Uuid>>> {
+    PUSHED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn interval_ms() -> u64 {
+    config::get_config().announcement_interval_ms.unwrap_or(30 * 1000)
+}
+
+// Start the job pushing the running announcements
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>) {
+    let interval_ms = interval_ms();
+    if interval_ms == 0 {
+        info!("Announcement job disabled");
+        return;
+    }
+    let interval = Duration::from_millis(interval_ms);
+    info!("Starting announcement job, interval: {:?}", interval);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run(&registry).await {
+                error!("Announcement run failed: {}", e);
+            }
+        }
+    });
+}
+
+pub fn to_announcement(row: OrgAnnouncementRow) -> OrgAnnouncement {
+    OrgAnnouncement {
+        id: row.id,
+        message: row.message,
+        level: row.level,
+        starts_at: row.starts_at,
+        expires_at: row.expires_at,
+        created_at: row.created_at,
+        created_by: row.created_by,
+    }
+}
+
+/// Schedule an announcement, it is pushed right away when it already started
+pub async fn create(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, request: &OrgAnnouncementRequest, by_prpl: &str) -> Result<Result<OrgAnnouncement, String>, String> {
+    let level = request.level.clone().unwrap_or_else(|| LEVELS[0].to_string());
+    let starts_at = request.starts_at.unwrap_or_else(Utc::now);
+    if request.message.trim().is_empty() {
+        return Ok(Err("The message of an announcement can't be empty".to_string()));
+    }
+    if !LEVELS.contains(&level.as_str()) {
+        return Ok(Err(format!("Unknown level '{}', expected one of {:?}", level, LEVELS)));
+    }
+    if request.expires_at <= starts_at.max(Utc::now()) {
+        return Ok(Err("An announcement must expire after it starts and in the future".to_string()));
+    }
+
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let row = db.insert_org_announcement(org_id, &request.message, &level, starts_at, request.expires_at, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to store the announcement: {}", e))?;
+    if row.starts_at <= Utc::now() {
+        publish(registry, org_id).await?;
+    }
+    Ok(Ok(to_announcement(row)))
+}
+
+/// The running and scheduled announcements of an org
+pub async fn list(org_id: &str) -> Result<Vec<OrgAnnouncement>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let rows = db.list_org_announcements(org_id)
+        .await
+        .map_err(|e| format!("Failed to list the announcements: {}", e))?;
+    Ok(rows.into_iter().map(to_announcement).collect())
+}
+
+/// Withdraw an announcement, false when it doesn't exist
+pub async fn delete(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, announcement_id: Uuid) -> Result<bool, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let deleted = db.delete_org_announcement(org_id, announcement_id)
+        .await
+        .map_err(|e| format!("Failed to delete the announcement: {}", e))?;
+    if deleted {
+        publish(registry, org_id).await?;
+    }
+    Ok(deleted)
+}
+
+// Push the running announcements of one org now
+async fn publish(registry: &Arc<HubRegistry<DocContext>>, org_id: &str) -> Result<(), String> {
+    let now = Utc::now();
+    let running = list(org_id).await?.into_iter().filter(|announcement| announcement.starts_at <= now).collect();
+    push(registry, org_id, running).await;
+    Ok(())
+}
+
+async fn run(registry: &Arc<HubRegistry<DocContext>>) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let rows = db.get_running_org_announcements()
+        .await
+        .map_err(|e| format!("Failed to load the running announcements: {}", e))?;
+
+    // The orgs with running announcements, and those with announcements to withdraw
+    let mut running: HashMap<String, Vec<OrgAnnouncement>> = get_pushed()
+        .lock()
+        .unwrap()
+        .keys()
+        .map(|org_id| (org_id.clone(), Vec::new()))
+        .collect();
+    for row in rows {
+        running.entry(row.org.clone()).or_default().push(to_announcement(row));
+    }
+    for (org_id, announcements) in running {
+        push(registry, &org_id, announcements).await;
+    }
+    Ok(())
+}
+
+// Push the running announcements of an org and withdraw the ones this pod pushed before that stopped running
+async fn push(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, running: Vec<OrgAnnouncement>) {
+    let store = EphemeralStore::new(2 * interval_ms() as i64);
+    let mut keys = Vec::with_capacity(running.len());
+    let running_ids: HashSet<Uuid> = running.iter().map(|announcement| announcement.id).collect();
+    for announcement in running {
+        let key = format!("{}{}", KEY_PREFIX, announcement.id);
+        let value: HashMap<String, LoroValue> = HashMap::from([
+            ("id".to_string(), announcement.id.to_string().into()),
+            ("message".to_string(), announcement.message.into()),
+            ("level".to_string(), announcement.level.into()),
+            ("startsAt".to_string(), announcement.starts_at.to_rfc3339().into()),
+            ("expiresAt".to_string(), announcement.expires_at.to_rfc3339().into()),
+        ]);
+        store.set(&key, LoroValue::from(value));
+        keys.push(key);
+    }
+
+    let withdrawn: Vec<Uuid> = {
+        let mut pushed = get_pushed().lock().unwrap();
+        let previous = pushed.remove(org_id).unwrap_or_default();
+        if !running_ids.is_empty() {
+            pushed.insert(org_id.to_string(), running_ids.clone());
+        }
+        previous.difference(&running_ids).copied().collect()
+    };
+    for id in withdrawn {
+        let key = format!("{}{}", KEY_PREFIX, id);
+        store.delete(&key);
+        keys.push(key);
+    }
+    if keys.is_empty() {
+        return;
+    }
+
+    let updates: Vec<Vec<u8>> = keys.iter().map(|key| store.encode(key)).collect();
+    let rooms = hub_service::broadcast_ephemeral(registry, org_id, updates).await;
+    debug!("Pushed {} announcement changes of organization '{}' to {} rooms", keys.len(), org_id, rooms);
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use loro::{LoroDoc, LoroList, LoroMap, ToJson};
+use tracing::warn;
+use uuid::Uuid;
+use crate::models::{ColabApprovalRound, ColabApprovalRoundState, ColabApprovalState, ColabUserApproval};
+use crate::models::lorodoc::{changed_containers, get_child_map, is_inside_container};
+use crate::services::acl_service;
+
+// Approval rounds are stored in the root "approvalRounds" map of the document, keyed by round id.
+// Approvers respond through the regular approvals of the frozen blocks or languages.
+
+pub struct ApproverResponse {
+    pub approver: String,
+    pub path: String,
+    pub state: ColabApprovalState,
+    pub date: Option<DateTime<Utc>>,
+}
+
+pub fn read_rounds(doc: &LoroDoc) -> Vec<ColabApprovalRound> {
+    let rounds_map = doc.get_map("approvalRounds");
+    let keys: Vec<String> = rounds_map.keys().map(|k| k.to_string()).collect();
+    let mut rounds = Vec::with_capacity(keys.len());
+    for key in keys {
+        if let Some(round_map) = get_child_map(&rounds_map, &key) {
+            match serde_json::from_value::<ColabApprovalRound>(round_map.get_deep_value().to_json_value()) {
+                Ok(round) => rounds.push(round),
+                Err(e) => warn!("Skipping malformed approval round '{}': {}", key, e),
+            }
+        }
+    }
+    rounds.sort_by_key(|r| r.started_at);
+    rounds
+}
+
+pub fn find_round(doc: &LoroDoc, round_id: &Uuid) -> Option<ColabApprovalRound> {
+    read_rounds(doc).into_iter().find(|r| &r.id == round_id)
+}
+
+// Approvers are user principals, approvals refer to the user ID
+fn approver_uid(approver: &str) -> Option<Uuid> {
+    approver.split_once("/u/").and_then(|(_, uid)| Uuid::parse_str(uid).ok())
+}
+
+fn read_user_approvals(container: &LoroMap) -> Vec<ColabUserApproval> {
+    let approvals_map = match get_child_map(container, "approvals") {
+        Some(map) => map,
+        None => return Vec::new(),
+    };
+    let mut approvals = Vec::new();
+    let keys: Vec<String> = approvals_map.keys().map(|k| k.to_string()).collect();
+    for key in keys {
+        if let Some(approval_map) = get_child_map(&approvals_map, &key) {
+            if let Ok(approval) = serde_json::from_value::<ColabUserApproval>(approval_map.get_deep_value().to_json_value()) {
+                approvals.push(approval);
+            }
+        }
+    }
+    approvals
+}
+
+// The latest response of every approver on every path of the round.
+// Only approvals given after the start of the round count.
+pub fn approver_responses(doc: &LoroDoc, round: &ColabApprovalRound) -> Vec<ApproverResponse> {
+    let scopes = acl_service::collect_acl_scopes(doc).unwrap_or_default();
+    let mut responses = Vec::with_capacity(round.paths.len() * round.approvers.len());
+    for path in &round.paths {
+        let approvals = scopes
+            .iter()
+            .find(|s| &s.path == path)
+            .and_then(|s| s.map.as_ref())
+            .map(read_user_approvals)
+            .unwrap_or_default();
+        for approver in &round.approvers {
+            let uid = approver_uid(approver);
+            let latest = approvals
+                .iter()
+                .filter(|a| Some(a.user) == uid && a.date >= round.started_at)
+                .max_by_key(|a| a.date);
+            responses.push(ApproverResponse {
+                approver: approver.clone(),
+                path: path.clone(),
+                state: latest.map(|a| a.state.clone()).unwrap_or(ColabApprovalState::Pending),
+                date: latest.map(|a| a.date),
+            });
+        }
+    }
+    responses
+}
+
+// A round is rejected as soon as one approver rejects, and approved when every approver approved every path
+pub fn outcome(responses: &[ApproverResponse]) -> Option<ColabApprovalState> {
+    if responses.iter().any(|r| r.state == ColabApprovalState::Rejected) {
+        Some(ColabApprovalState::Rejected)
+    } else if !responses.is_empty() && responses.iter().all(|r| r.state == ColabApprovalState::Approved) {
+        Some(ColabApprovalState::Approved)
+    } else {
+        None
+    }
+}
+
+// The round as it currently stands: an active round with all responses in is completed
+pub fn effective_round(round: &ColabApprovalRound, responses: &[ApproverResponse]) -> ColabApprovalRound {
+    let mut round = round.clone();
+    if round.state == ColabApprovalRoundState::Active {
+        if let Some(outcome) = outcome(responses) {
+            round.state = ColabApprovalRoundState::Completed;
+            round.outcome = Some(outcome);
+            round.ended_at = responses.iter().filter_map(|r| r.date).max();
+        }
+    }
+    round
+}
+
+// The rounds that currently freeze part of the document
+pub fn frozen_rounds(doc: &LoroDoc) -> Vec<ColabApprovalRound> {
+    read_rounds(doc)
+        .into_iter()
+        .filter(|r| r.state == ColabApprovalRoundState::Active && outcome(&approver_responses(doc, r)).is_none())
+        .collect()
+}
+
+// Check that incoming updates don't touch blocks or languages frozen for the given principals.
+// The updates are tried on a fork, so the live document is never modified.
+pub fn check_updates_allowed(doc: &LoroDoc, updates: &[Vec<u8>], principals: &[String]) -> Result<(), String> {
+
+    // Approvers can keep editing the blocks they need to approve
+    let frozen: Vec<ColabApprovalRound> = frozen_rounds(doc)
+        .into_iter()
+        .filter(|r| !r.approvers.iter().any(|a| principals.contains(a)))
+        .collect();
+    if frozen.is_empty() {
+        return Ok(());
+    }
+
+    let fork = doc.fork();
+    let before = fork.oplog_vv();
+    fork.import_batch(updates)
+        .map_err(|e| format!("Failed to import updates: {}", e))?;
+    let after = fork.oplog_vv();
+    let containers = changed_containers(&fork, &before, &after);
+
+    for round in &frozen {
+        for path in &round.paths {
+            let scope_id = match acl_service::find_scope_map(doc, path) {
+                Ok(scope_map) => scope_map.id(),
+                Err(_) => continue,
+            };
+            if containers.iter().any(|c| is_inside_container(&fork, c, &scope_id)) {
+                return Err(format!("'{}' is frozen by approval round '{}'", path, round.id));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn add_round(doc: &LoroDoc, round: &ColabApprovalRound) -> Result<(), String> {
+    let map_err = |e: loro::LoroError| format!("Failed to write approval round '{}': {}", round.id, e);
+    let round_map = doc
+        .get_map("approvalRounds")
+        .get_or_create_container(round.id.to_string().as_str(), LoroMap::new())
+        .map_err(map_err)?;
+    round_map.insert("id", round.id.to_string().as_str()).map_err(map_err)?;
+    round_map.insert("state", round.state.to_string().as_str()).map_err(map_err)?;
+    round_map.insert("version", round.version as i64).map_err(map_err)?;
+    round_map.insert("startedBy", round.started_by.as_str()).map_err(map_err)?;
+    round_map.insert("startedAt", round.started_at.to_rfc3339().as_str()).map_err(map_err)?;
+
+    let paths_list = round_map.insert_container("paths", LoroList::new()).map_err(map_err)?;
+    for path in &round.paths {
+        paths_list.push(path.as_str()).map_err(map_err)?;
+    }
+    let approvers_list = round_map.insert_container("approvers", LoroList::new()).map_err(map_err)?;
+    for approver in &round.approvers {
+        approvers_list.push(approver.as_str()).map_err(map_err)?;
+    }
+    let version_v_map = round_map.insert_container("versionV", LoroMap::new()).map_err(map_err)?;
+    for (peer, counter) in &round.version_v {
+        version_v_map.insert(peer.to_string().as_str(), *counter as i64).map_err(map_err)?;
+    }
+    Ok(())
+}
+
+// Close a round, either because all responses are in or because it was cancelled
+pub fn end_round(doc: &LoroDoc, round: &ColabApprovalRound) -> Result<(), String> {
+    let map_err = |e: loro::LoroError| format!("Failed to update approval round '{}': {}", round.id, e);
+    let round_map = get_child_map(&doc.get_map("approvalRounds"), &round.id.to_string())
+        .ok_or_else(|| format!("Approval round '{}' not found", round.id))?;
+    round_map.insert("state", round.state.to_string().as_str()).map_err(map_err)?;
+    if let Some(outcome) = &round.outcome {
+        round_map.insert("outcome", outcome.to_string().as_str()).map_err(map_err)?;
+    }
+    if let Some(ended_by) = &round.ended_by {
+        round_map.insert("endedBy", ended_by.as_str()).map_err(map_err)?;
+    }
+    if let Some(ended_at) = &round.ended_at {
+        round_map.insert("endedAt", ended_at.to_rfc3339().as_str()).map_err(map_err)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{Duration as ChronoDuration, Utc};
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab::{self, ArchivalCandidateRow, ArchivalPolicyRow};
+use crate::services::{acl_cache_service, doc_edit_service, room_assignment_service, workflow_service::{self, TransitionEvent, ARCHIVED_STATE}};
+use crate::ws::docctx::DocContext;
+
+/// The principal making the archival transitions
+const ARCHIVAL_PRPL: &str = "s/colabri-doc";
+
+// Start the archival job. Each run flags the documents that have been inactive for too long
+// and archives the candidates whose grace period has passed.
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>) {
+    let interval_ms = config::get_config().archival_interval_ms.unwrap_or(60 * 60 * 1000);
+    if interval_ms == 0 {
+        info!("Archival job disabled");
+        return;
+    }
+    let interval = Duration::from_millis(interval_ms);
+    info!("Starting archival job, interval: {:?}", interval);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run(&registry).await {
+                error!("Archival run failed: {}", e);
+            }
+        }
+    });
+}
+
+// Record that a document was opened or saved, in the background
+pub fn touch(org_id: &str, doc_uuid: Uuid) {
+    let org_id = org_id.to_string();
+    tokio::spawn(async move {
+        if let Some(db) = dbcolab::get_db() {
+            if let Err(e) = db.touch_document_activity(&org_id, doc_uuid).await {
+                warn!("Failed to record activity on document '{}': {}", doc_uuid, e);
+            }
+        }
+    });
+}
+
+// Get the archival policy of an organization and its current candidates, None without an enabled policy
+pub async fn candidates(org_id: &str) -> Result<Option<(ArchivalPolicyRow, Vec<ArchivalCandidateRow>)>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let policy = match db.get_archival_policy(org_id).await {
+        Ok(Some(policy)) => policy,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(format!("Failed to load archival policy of organization '{}': {}", org_id, e)),
+    };
+    let candidates = db.list_archival_candidates(org_id, policy.inactive_days)
+        .await
+        .map_err(|e| format!("Failed to list archival candidates of organization '{}': {}", org_id, e))?;
+    Ok(Some((policy, candidates)))
+}
+
+// Whether the grace period of a flagged candidate has passed
+pub fn is_due(candidate: &ArchivalCandidateRow, policy: &ArchivalPolicyRow) -> bool {
+    match candidate.archive_candidate_since {
+        Some(since) => since + ChronoDuration::days(policy.grace_days as i64) <= Utc::now(),
+        None => false,
+    }
+}
+
+async fn run(registry: &Arc<HubRegistry<DocContext>>) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let policies = db.get_archival_policies()
+        .await
+        .map_err(|e| format!("Failed to load archival policies: {}", e))?;
+
+    for policy in policies {
+        // 1. Flag the documents that became inactive since the last run
+        match db.flag_archival_candidates(&policy.org, policy.inactive_days).await {
+            Ok(0) => {}
+            Ok(flagged) => info!("Flagged {} inactive documents of organization '{}' for archival", flagged, policy.org),
+            Err(e) => {
+                error!("Failed to flag archival candidates of organization '{}': {}", policy.org, e);
+                continue;
+            }
+        }
+
+        // 2. Archive the candidates that are due. Every pod archives the documents whose room it owns.
+        let candidates = match candidates(&policy.org).await {
+            Ok(Some((_, candidates))) => candidates,
+            Ok(None) => continue,
+            Err(e) => {
+                error!("{}", e);
+                continue;
+            }
+        };
+        for candidate in candidates.iter().filter(|c| is_due(c, &policy)) {
+            if !room_assignment_service::assign(&candidate.id.to_string()).local {
+                continue;
+            }
+            if let Err(e) = archive(registry, &policy, candidate).await {
+                error!("Failed to archive inactive document '{}': {}", candidate.id, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Move a document to the archived state, the same way a workflow transition does
+async fn archive(registry: &Arc<HubRegistry<DocContext>>, policy: &ArchivalPolicyRow, candidate: &ArchivalCandidateRow) -> Result<(), String> {
+    let org_id = &policy.org;
+    let doc_id = candidate.id.to_string();
+    let workflow = workflow_service::get_workflow(org_id).await?;
+    let target = workflow.state(ARCHIVED_STATE)
+        .cloned()
+        .ok_or_else(|| format!("The workflow of organization '{}' has no '{}' state", org_id, ARCHIVED_STATE))?;
+
+    // 1. Update the state in the database, this fails if the document was moved in the meantime
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let comment = format!("Archived after {} days of inactivity", policy.inactive_days);
+    let moved = db.update_document_workflow_state(org_id, candidate.id, &candidate.workflow_state, ARCHIVED_STATE, Some(&comment), ARCHIVAL_PRPL)
+        .await
+        .map_err(|e| format!("Failed to update workflow state: {}", e))?;
+    if !moved {
+        return Err(format!("Document is no longer in state '{}'", candidate.workflow_state));
+    }
+    acl_cache_service::invalidate_document(org_id, candidate.id);
+
+    // 2. Mirror the state in the document and revoke the write permissions
+    doc_edit_service::edit_doc(registry.clone(), org_id, &doc_id, move |doc: &LoroDoc| {
+        workflow_service::apply_state(doc, &target)?;
+        doc.commit();
+        Ok(())
+    }, true).await?;
+    workflow_service::revoke_archived_db_acls(org_id, candidate.id).await?;
+
+    // 3. Run the transition hooks
+    workflow_service::run_transition_hooks(&workflow, &TransitionEvent {
+        org_id: org_id.clone(),
+        doc_uuid: candidate.id,
+        from: candidate.workflow_state.clone(),
+        to: ARCHIVED_STATE.to_string(),
+        by_prpl: ARCHIVAL_PRPL.to_string(),
+        comment: Some(comment),
+        timestamp: Utc::now(),
+    });
+    info!("Archived inactive document '{}' of organization '{}'", doc_id, org_id);
+    Ok(())
+}
@@ -1,8 +1,44 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 use tracing::info;
 use axum::http::{self};
-use crate::ws::userctx;
+use moka::{future::Cache, Expiry};
+use crate::ws::userctx::{self, UserCtx};
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation, TokenData};
 
+// Loads of user contexts are shared per user and token roles, so a client opening many documents at once
+// loads its principals once, while a token with other roles gets a load of its own.
+// Failures are kept briefly too, so a bad user can't hammer the app service.
+const USER_CTX_LOAD_TTL: Duration = Duration::from_secs(30);
+const USER_CTX_LOAD_NEGATIVE_TTL: Duration = Duration::from_secs(5);
+
+type UserCtxLoad = Result<UserCtx, String>;
+
+// The uid and the sorted roles of the token
+type UserCtxLoadKey = (String, Vec<String>);
+
+static USER_CTX_LOADS: OnceLock<Cache<UserCtxLoadKey, UserCtxLoad>> = OnceLock::new();
+
+struct UserCtxLoadExpiry;
+
+impl Expiry<UserCtxLoadKey, UserCtxLoad> for UserCtxLoadExpiry {
+    fn expire_after_create(&self, _key: &UserCtxLoadKey, value: &UserCtxLoad, _created_at: Instant) -> Option<Duration> {
+        match value {
+            Ok(_) => Some(USER_CTX_LOAD_TTL),
+            Err(_) => Some(USER_CTX_LOAD_NEGATIVE_TTL),
+        }
+    }
+}
+
+fn get_user_ctx_loads() -> &'static Cache<UserCtxLoadKey, UserCtxLoad> {
+    USER_CTX_LOADS.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(100_000)
+            .expire_after(UserCtxLoadExpiry)
+            .build()
+    })
+}
+
 // Get the auth token from a request
 pub fn get_auth_token<B>(req: &http::Request<B>) -> Result<String, String> {
     // 1. Try to get token from Authorization header
@@ -31,47 +67,38 @@ pub fn get_auth_token<B>(req: &http::Request<B>) -> Result<String, String> {
     }
 }
 
-// Get the user principals from a JWT token
-pub fn get_user_prpls(token: &str, force_refresh: bool) -> Result<(String, Vec<String>), String> {
-   
-    // Validate the auth_token as a JWT token
+// Validate the JWT token of a user and return the UID and the roles it carries.
+// Only checks the token, it never waits on the app service.
+pub fn validate_user_token(token: &str) -> Result<(String, Vec<String>), String> {
     let config = crate::config::get_config();
-    if let Some(secret) = &config.cloud_auth_jwt_secret {
-        match validate_jwt(token, secret) {
+    let secret = config.cloud_auth_jwt_secret.as_ref().ok_or_else(|| "No JWT secret configured!".to_string())?;
+    let token_data = validate_jwt(token, secret).map_err(|e| format!("JWT validation failed: {}", e))?;
+    let uid = token_data.claims.get("sub")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "Can't extract a UID from the JWT token".to_string())?;
+    info!("JWT token validated successfully for user: {}", uid);
 
-            // When a valid token is found, get the UID
-            Ok(token_data) => {
-                if let Some(uid) = token_data.claims.get("sub").and_then(|v| v.as_str()) {
-                    info!("JWT token validated successfully for user: {}", uid);
-
-                    // Get roles from the token claims
-                    let roles = match token_data.claims.get("roles").and_then(|v| v.as_array()) {
-                        Some(roles_array) => roles_array.iter().filter_map(|r| r.as_str().map(|s| s.to_string())).collect::<Vec<String>>(),
-                        None => Vec::new(),
-                    };
+    // Get roles from the token claims
+    let roles = match token_data.claims.get("roles").and_then(|v| v.as_array()) {
+        Some(roles_array) => roles_array.iter().filter_map(|r| r.as_str().map(|s| s.to_string())).collect::<Vec<String>>(),
+        None => Vec::new(),
+    };
+    Ok((uid.to_string(), roles))
+}
 
-                    // When we have the UID, fetch the user context
-                    return match userctx::get_or_fetch_user_ctx_blocking(uid, roles, force_refresh) {
-                        Ok(user_ctx) => {
-                            // Get all the principals for the user
-                            let prpls = user_ctx.get_all_prpls();
-                            return Ok((uid.to_string(), prpls));
-                        }
-                        Err(e) => {
-                            Err(format!("Failed to load user context for {}: {}", uid, e))
-                        }
-                    };
-                } else {
-                    Err(format!("Can't extract a UID from the JWT token"))
-                }
-            },
-            Err(e) => {
-                Err(format!("JWT validation failed: {}", e))
-            }
-        }
-    } else {
-        Err(format!("No JWT secret configured!"))
-    }
+// Load fresh principals of a user connecting, once per user and token roles while the load is kept.
+// Concurrent loads of the same user and roles wait for a single lookup.
+pub async fn load_user_ctx(uid: &str, roles: Vec<String>) -> UserCtxLoad {
+    let uid_owned = uid.to_string();
+    let mut key_roles = roles.clone();
+    key_roles.sort();
+    get_user_ctx_loads()
+        .get_with((uid.to_string(), key_roles), async move {
+            userctx::get_or_fetch_user_ctx_async(&uid_owned, roles, true)
+                .await
+                .map_err(|e| format!("Failed to load user context for {}: {}", uid_owned, e))
+        })
+        .await
 }
 
 // Validate a JWT token and return the token data
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use crate::db::dbcolab;
+use crate::models::{BillingReportResponse, OrgBillingUsage};
+use crate::services::analytics_service;
+
+pub const CSV_HEADER: [&str; 6] = ["org_id", "active_documents", "editor_minutes", "storage_bytes", "peak_storage_bytes", "exports"];
+
+// Parse a period like 2026-09 into the first day of the month and of the next month
+pub fn parse_period(period: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let invalid = || format!("Invalid period '{}', expected a month like 2026-09", period);
+    let (year, month) = period.split_once('-').ok_or_else(invalid)?;
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+    let month: u32 = month.parse().map_err(|_| invalid())?;
+    let from = NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(invalid)?;
+    let to = if month == 12 { NaiveDate::from_ymd_opt(year + 1, 1, 1) } else { NaiveDate::from_ymd_opt(year, month + 1, 1) }
+        .ok_or_else(invalid)?;
+    Ok((from, to))
+}
+
+fn start_of(day: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap_or_default())
+}
+
+// The usage of every organization in a month, from the usage samples, the active documents and the export log
+pub async fn report(period: &str) -> Result<BillingReportResponse, String> {
+    let (from_day, to_day) = parse_period(period)?;
+    let (from, to) = (start_of(from_day), start_of(to_day));
+    if from > Utc::now() {
+        return Err(format!("Period '{}' has not started yet", period));
+    }
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    let mut orgs: BTreeMap<String, OrgBillingUsage> = BTreeMap::new();
+    let empty = |org: &str| OrgBillingUsage {
+        org_id: org.to_string(),
+        active_documents: 0,
+        editor_minutes: 0.0,
+        storage_bytes: 0,
+        peak_storage_bytes: 0,
+        exports: 0,
+    };
+
+    let active = db.get_billing_active_documents(from_day).await
+        .map_err(|e| format!("Failed to count active documents: {}", e))?;
+    for row in active {
+        orgs.entry(row.org.clone()).or_insert_with(|| empty(&row.org)).active_documents = row.value;
+    }
+    let sample_minutes = analytics_service::sample_minutes();
+    let connections = db.get_billing_connection_samples(from, to).await
+        .map_err(|e| format!("Failed to sum connections: {}", e))?;
+    for row in connections {
+        orgs.entry(row.org.clone()).or_insert_with(|| empty(&row.org)).editor_minutes = row.value as f64 * sample_minutes;
+    }
+    let exports = db.get_billing_exports(from, to).await
+        .map_err(|e| format!("Failed to count exports: {}", e))?;
+    for row in exports {
+        orgs.entry(row.org.clone()).or_insert_with(|| empty(&row.org)).exports = row.value;
+    }
+    let storage = db.get_billing_storage(from_day, to_day).await
+        .map_err(|e| format!("Failed to load storage samples: {}", e))?;
+    for row in storage {
+        let usage = orgs.entry(row.org.clone()).or_insert_with(|| empty(&row.org));
+        usage.storage_bytes = row.avg_bytes;
+        usage.peak_storage_bytes = row.peak_bytes;
+    }
+
+    Ok(BillingReportResponse {
+        period: format!("{:04}-{:02}", from_day.year(), from_day.month()),
+        from,
+        to,
+        partial: to > Utc::now(),
+        orgs: orgs.into_values().collect(),
+    })
+}
+
+pub fn csv_rows(report: &BillingReportResponse) -> Vec<Vec<String>> {
+    report.orgs
+        .iter()
+        .map(|usage| vec![
+            usage.org_id.clone(),
+            usage.active_documents.to_string(),
+            format!("{:.1}", usage.editor_minutes),
+            usage.storage_bytes.to_string(),
+            usage.peak_storage_bytes.to_string(),
+            usage.exports.to_string(),
+        ])
+        .collect()
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use loro::cursor::Side;
+use loro::{Container, LoroDoc, LoroMap, LoroText, ValueOrContainer, ID};
+use crate::models::BlameRun;
+use crate::models::lorodoc::get_child_map;
+use crate::services::acl_service;
+
+// The text fields of a block or language that are blamed, in display order
+const BLAMED_FIELDS: [&str; 2] = ["title", "textElement"];
+
+// Blame the text of the block or language with the given id.
+// Every run of characters inserted by the same change is attributed to the peer of that change,
+// resolved to a principal through the peer map of the document. Formatting changes are not tracked.
+pub fn blame_block(doc: &LoroDoc, block_id: &str, peer_map: &HashMap<u64, String>) -> Result<Vec<BlameRun>, String> {
+    let block = acl_service::find_scope_map(doc, &format!("/content/{}", block_id))?;
+
+    let mut runs: Vec<BlameRun> = Vec::new();
+    for field in BLAMED_FIELDS {
+        let element = match get_child_map(&block, field) {
+            Some(element) => element,
+            None => continue,
+        };
+        let mut leaves = Vec::new();
+        collect_texts(&element, &mut leaves, 0);
+
+        let mut offset = 0;
+        for text in leaves {
+            blame_text(doc, &text, field, offset, peer_map, &mut runs);
+            offset += text.len_unicode();
+        }
+    }
+    Ok(runs)
+}
+
+fn blame_text(doc: &LoroDoc, text: &LoroText, field: &str, offset: usize, peer_map: &HashMap<u64, String>, runs: &mut Vec<BlameRun>) {
+    let mut current: Option<(Option<ID>, BlameRun)> = None;
+
+    for (pos, ch) in text.to_string().chars().enumerate() {
+        // The cursor of a character carries the id of the operation that inserted it
+        let change = text
+            .get_cursor(pos, Side::Middle)
+            .and_then(|cursor| cursor.id)
+            .and_then(|id| doc.get_change(id));
+        let change_id = change.as_ref().map(|c| c.id);
+
+        if let Some((run_change, run)) = &mut current {
+            if *run_change == change_id {
+                run.text.push(ch);
+                run.end = offset + pos + 1;
+                continue;
+            }
+        }
+        if let Some((_, run)) = current.take() {
+            runs.push(run);
+        }
+        let peer = change.as_ref().map(|c| c.id.peer);
+        current = Some((change_id, BlameRun {
+            field: field.to_string(),
+            text: ch.to_string(),
+            start: offset + pos,
+            end: offset + pos + 1,
+            peer: peer.map(|p| p.to_string()),
+            prpl: peer.and_then(|p| peer_map.get(&p).cloned()),
+            timestamp: change
+                .as_ref()
+                .filter(|c| c.timestamp > 0)
+                .and_then(|c| DateTime::<Utc>::from_timestamp(c.timestamp, 0)),
+            lamport: change.as_ref().map(|c| c.lamport),
+        }));
+    }
+    if let Some((_, run)) = current {
+        runs.push(run);
+    }
+}
+
+fn collect_texts(element: &LoroMap, leaves: &mut Vec<LoroText>, depth: usize) {
+    const MAX_DEPTH: usize = 100; // Prevent stack overflow
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let children = match element.get("children") {
+        Some(ValueOrContainer::Container(Container::List(list))) => list,
+        _ => return,
+    };
+    for idx in 0..children.len() {
+        match children.get(idx) {
+            Some(ValueOrContainer::Container(Container::Text(text))) => leaves.push(text),
+            Some(ValueOrContainer::Container(Container::Map(child))) => collect_texts(&child, leaves, depth + 1),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use loro_websocket_server::HubRegistry;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab::{self, DocumentRefRow};
+use crate::models::{BulkReplaceDocumentResult, BulkReplaceJobResponse, BulkReplaceRequest, DocumentReplaceRequest, DocumentVersionTag};
+use crate::services::replace_service::{self, ReplaceOptions};
+use crate::ws::docctx::DocContext;
+
+// Find and replace across the documents of an org or library as a background job.
+// A dry run reports the matches per document without changing anything. Otherwise every changed
+// document is tagged with the state the replacement was applied to and the peer applying it,
+// reverting the changes of that peer after that state rolls the document back. Documents are
+// handled one at a time with a pause in between, so the job doesn't hold up the rooms of the org.
+// One job runs per org at a time.
+
+static RUNNING: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn get_running() -> &'static Mutex<HashSet<String>> {
+    RUNNING.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+// The tag of the documents changed by a job
+fn job_tag(job_id: Uuid) -> String {
+    format!("replace-{}", job_id)
+}
+
+// Start a job, returns its id.
+// The inner error is a message for the caller about the request, the outer error a failure to store the job.
+pub async fn start(registry: Arc<HubRegistry<DocContext>>, org_id: &str, request: BulkReplaceRequest, by_prpl: &str) -> Result<Result<Uuid, String>, String> {
+    let options = match replace_service::options(&DocumentReplaceRequest {
+        pattern: request.pattern.clone(),
+        replacement: request.replacement.clone(),
+        regex: request.regex,
+        block_ids: None,
+        lang_codes: request.lang_codes.clone(),
+        dry_run: request.dry_run,
+    }) {
+        Ok(options) => options,
+        Err(e) => return Ok(Err(e)),
+    };
+    let library = match request.library.as_deref().map(Uuid::parse_str).transpose() {
+        Ok(library) => library,
+        Err(_) => return Ok(Err(format!("Invalid library UUID '{}'", request.library.unwrap_or_default()))),
+    };
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let json = serde_json::to_value(&request).map_err(|e| format!("Failed to serialize the request: {}", e))?;
+    if !get_running().lock().unwrap().insert(org_id.to_string()) {
+        return Ok(Err(format!("A find-and-replace job of organization '{}' is already running", org_id)));
+    }
+
+    let job_id = Uuid::new_v4();
+    if let Err(e) = db.insert_replace_job(org_id, job_id, json, request.dry_run, by_prpl).await {
+        get_running().lock().unwrap().remove(org_id);
+        return Err(format!("Failed to store find-and-replace job: {}", e));
+    }
+
+    let (org_id, by_prpl) = (org_id.to_string(), by_prpl.to_string());
+    tokio::spawn(async move {
+        if let Err(e) = run(&registry, &org_id, job_id, options, library, request.dry_run, &by_prpl).await {
+            error!("Find-and-replace job {} of org '{}' failed: {}", job_id, org_id, e);
+            if let Some(db) = dbcolab::get_db() {
+                if let Err(e) = db.update_replace_job(&org_id, job_id, "failed", 0, 0, serde_json::json!([]), Some(&e)).await {
+                    error!("Failed to record the failure of find-and-replace job {}: {}", job_id, e);
+                }
+            }
+        }
+        get_running().lock().unwrap().remove(&org_id);
+    });
+    Ok(Ok(job_id))
+}
+
+async fn run(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, job_id: Uuid, options: ReplaceOptions, library: Option<Uuid>, dry_run: bool, by_prpl: &str) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let documents = db.get_org_documents(org_id, library)
+        .await
+        .map_err(|e| format!("Failed to list the documents: {}", e))?;
+    let pause = Duration::from_millis(config::get_config().bulk_replace_pause_ms.unwrap_or(250));
+    let total = documents.len() as i32;
+
+    let mut results: Vec<BulkReplaceDocumentResult> = Vec::new();
+    for (i, document) in documents.iter().enumerate() {
+        if let Some(result) = replace_document(registry, org_id, job_id, document, &options, dry_run, by_prpl).await {
+            results.push(result);
+        }
+        let status = if i + 1 == documents.len() { "done" } else { "running" };
+        let json = serde_json::to_value(&results).map_err(|e| format!("Failed to serialize the results: {}", e))?;
+        db.update_replace_job(org_id, job_id, status, total, i as i32 + 1, json, None)
+            .await
+            .map_err(|e| format!("Failed to record the progress: {}", e))?;
+        tokio::time::sleep(pause).await;
+    }
+    if documents.is_empty() {
+        db.update_replace_job(org_id, job_id, "done", 0, 0, serde_json::json!([]), None)
+            .await
+            .map_err(|e| format!("Failed to record the progress: {}", e))?;
+    }
+    info!("Find-and-replace job {} of org '{}' found matches in {} of {} documents", job_id, org_id, results.len(), total);
+    Ok(())
+}
+
+// The result of a document, None when it has no matches
+async fn replace_document(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, job_id: Uuid, document: &DocumentRefRow, options: &ReplaceOptions, dry_run: bool, by_prpl: &str) -> Option<BulkReplaceDocumentResult> {
+    let doc_id = document.id.to_string();
+    let mut result = BulkReplaceDocumentResult {
+        doc_id: doc_id.clone(),
+        name: document.name.clone(),
+        matches: 0,
+        blocks: Vec::new(),
+        version_tag: None,
+        error: None,
+    };
+    let replacement = match replace_service::replace(registry, org_id, &doc_id, options.clone(), dry_run).await {
+        Ok(Some(replacement)) => replacement,
+        Ok(None) => return None,
+        Err(e) => {
+            warn!("Find-and-replace job {} skips document {}: {}", job_id, doc_id, e);
+            result.error = Some(e);
+            return Some(result);
+        }
+    };
+    if replacement.blocks.is_empty() {
+        return None;
+    }
+    result.matches = replacement.blocks.iter().map(|block| block.matches).sum();
+    result.blocks = replacement.blocks;
+
+    // Tag the state the replacement was applied to
+    if let Some((state_vv, peer)) = replacement.applied {
+        let tag = job_tag(job_id);
+        let state_vv = serde_json::to_value(&state_vv).unwrap_or_default();
+        let peer = peer.to_string();
+        let tagged = match dbcolab::get_db() {
+            Some(db) => db.insert_document_version_tag(org_id, document.id, &tag, state_vv.clone(), Some(&peer), by_prpl).await.map_err(|e| e.to_string()),
+            None => Err("Database not initialized".to_string()),
+        };
+        match tagged {
+            Ok(version) => result.version_tag = Some(DocumentVersionTag { tag, version, state_vv, peer }),
+            Err(e) => {
+                error!("Failed to tag document {} changed by find-and-replace job {}: {}", doc_id, job_id, e);
+                result.error = Some(format!("Changed, but failed to record the version tag: {}", e));
+            }
+        }
+    }
+    Some(result)
+}
+
+// A job of an org, None when it doesn't exist
+pub async fn get(org_id: &str, job_id: Uuid) -> Result<Option<BulkReplaceJobResponse>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let row = match db.get_replace_job(org_id, job_id)
+        .await
+        .map_err(|e| format!("Failed to load find-and-replace job '{}': {}", job_id, e))? {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let request: BulkReplaceRequest = serde_json::from_value(row.request.0)
+        .map_err(|e| format!("Failed to parse the request of find-and-replace job '{}': {}", job_id, e))?;
+    let results: Vec<BulkReplaceDocumentResult> = serde_json::from_value(row.results.0)
+        .map_err(|e| format!("Failed to parse the results of find-and-replace job '{}': {}", job_id, e))?;
+    Ok(Some(BulkReplaceJobResponse {
+        job_id: row.id.to_string(),
+        org_id: org_id.to_string(),
+        status: row.status,
+        dry_run: row.dry_run,
+        request,
+        documents: row.documents,
+        processed: row.processed,
+        matches: results.iter().map(|result| result.matches).sum(),
+        results,
+        error: row.error,
+        created_at: row.created_at,
+        created_by: row.created_by,
+        finished_at: row.finished_at,
+    }))
+}
@@ -0,0 +1,230 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use crate::models::{CitationApprover, ColabApprovalState, ColabUserApproval, TextElementChild, TextElement, TextElementChildrenOrString};
+use crate::services::script_service;
+
+const MAX_DEPTH: usize = 100; // Prevent stack overflow
+
+// How a node of the text element is rendered
+enum NodeKind {
+    Block(&'static str),
+    Inline(&'static str),
+    Break,
+    // Unknown nodes only render their children
+    Transparent,
+}
+
+fn node_kind(node_name: &str) -> NodeKind {
+    match node_name {
+        "paragraph" | "p" => NodeKind::Block("p"),
+        "heading" | "h" => NodeKind::Block("h3"),
+        "bullet_list" | "bulletList" | "ul" => NodeKind::Block("ul"),
+        "ordered_list" | "orderedList" | "ol" => NodeKind::Block("ol"),
+        "list_item" | "listItem" | "li" => NodeKind::Block("li"),
+        "strong" | "bold" | "b" => NodeKind::Inline("strong"),
+        "em" | "italic" | "i" => NodeKind::Inline("em"),
+        "underline" | "u" => NodeKind::Inline("u"),
+        "sub" | "subscript" => NodeKind::Inline("sub"),
+        "sup" | "superscript" => NodeKind::Inline("sup"),
+        "hard_break" | "hardBreak" | "br" => NodeKind::Break,
+        _ => NodeKind::Transparent,
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Render a text element as clean HTML: known nodes become tags without attributes but their direction, everything else is dropped
+pub fn render_html(element: &TextElement) -> String {
+    let mut html = String::new();
+    render_html_children(&element.children, &mut html, 0);
+    html
+}
+
+fn render_html_children(children: &TextElementChildrenOrString, html: &mut String, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    match children {
+        TextElementChildrenOrString::AsStringArray(strings) => {
+            for s in strings {
+                html.push_str(&escape_html(s));
+            }
+        }
+        TextElementChildrenOrString::AsChildren(nodes) => {
+            for node in nodes {
+                render_html_node(node, html, depth + 1);
+            }
+        }
+    }
+}
+
+fn render_html_node(node: &TextElementChild, html: &mut String, depth: usize) {
+    match node_kind(&node.node_name) {
+        NodeKind::Block(tag) => {
+            // The direction of a block set by the editor is kept, it changes how the block reads
+            let dir = node.attributes.get("dir")
+                .or_else(|| node.attributes.get("textDirection"))
+                .and_then(|dir| script_service::direction_attribute(dir));
+            match dir {
+                Some(dir) => html.push_str(&format!("<{} dir=\"{}\">", tag, dir)),
+                None => html.push_str(&format!("<{}>", tag)),
+            }
+            render_html_children(&node.children, html, depth);
+            html.push_str(&format!("</{}>", tag));
+        }
+        NodeKind::Inline(tag) => {
+            html.push_str(&format!("<{}>", tag));
+            render_html_children(&node.children, html, depth);
+            html.push_str(&format!("</{}>", tag));
+        }
+        NodeKind::Break => html.push_str("<br>"),
+        NodeKind::Transparent => render_html_children(&node.children, html, depth),
+    }
+}
+
+// Render a text element as plain text, block nodes end with a line break
+pub fn render_text(element: &TextElement) -> String {
+    let mut text = String::new();
+    render_text_children(&element.children, &mut text, 0);
+    text.trim().to_string()
+}
+
+fn render_text_children(children: &TextElementChildrenOrString, text: &mut String, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    match children {
+        TextElementChildrenOrString::AsStringArray(strings) => {
+            for s in strings {
+                text.push_str(s);
+            }
+        }
+        TextElementChildrenOrString::AsChildren(nodes) => {
+            for node in nodes {
+                match node_kind(&node.node_name) {
+                    NodeKind::Block(_) => {
+                        render_text_children(&node.children, text, depth + 1);
+                        if !text.ends_with('\n') {
+                            text.push('\n');
+                        }
+                    }
+                    NodeKind::Break => text.push('\n'),
+                    NodeKind::Inline(_) | NodeKind::Transparent => render_text_children(&node.children, text, depth + 1),
+                }
+            }
+        }
+    }
+}
+
+// Render a text element as Markdown, marks without a Markdown equivalent keep only their text
+pub fn render_markdown(element: &TextElement) -> String {
+    let mut markdown = String::new();
+    render_markdown_children(&element.children, &mut markdown, 0, "");
+    markdown.trim().to_string()
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '#' | '[' | ']' | '|' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// The list item marker of the enclosing list is passed down to its items
+fn render_markdown_children(children: &TextElementChildrenOrString, markdown: &mut String, depth: usize, marker: &str) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    match children {
+        TextElementChildrenOrString::AsStringArray(strings) => {
+            for s in strings {
+                markdown.push_str(&escape_markdown(s));
+            }
+        }
+        TextElementChildrenOrString::AsChildren(nodes) => {
+            for node in nodes {
+                match node_kind(&node.node_name) {
+                    NodeKind::Block("ul") => render_markdown_children(&node.children, markdown, depth + 1, "- "),
+                    NodeKind::Block("ol") => render_markdown_children(&node.children, markdown, depth + 1, "1. "),
+                    NodeKind::Block(tag) => {
+                        match tag {
+                            "h3" => markdown.push_str("### "),
+                            "li" => markdown.push_str(marker),
+                            _ => {}
+                        }
+                        render_markdown_children(&node.children, markdown, depth + 1, marker);
+                        if !markdown.ends_with('\n') {
+                            markdown.push('\n');
+                        }
+                        if tag != "li" {
+                            markdown.push('\n');
+                        }
+                    }
+                    NodeKind::Inline(tag) => {
+                        let mark = match tag {
+                            "strong" => "**",
+                            "em" => "*",
+                            _ => "",
+                        };
+                        markdown.push_str(mark);
+                        render_markdown_children(&node.children, markdown, depth + 1, marker);
+                        markdown.push_str(mark);
+                    }
+                    NodeKind::Break => markdown.push_str("  \n"),
+                    NodeKind::Transparent => render_markdown_children(&node.children, markdown, depth + 1, marker),
+                }
+            }
+        }
+    }
+}
+
+// The overall approval status of a statement language:
+// rejected if anyone rejected, approved if everyone approved, pending if anyone is still to respond
+pub fn approval_status(approvals: &HashMap<String, ColabUserApproval>) -> ColabApprovalState {
+    let states: Vec<&ColabApprovalState> = approvals.values().map(|a| &a.state).collect();
+    if states.iter().any(|s| **s == ColabApprovalState::Rejected) {
+        ColabApprovalState::Rejected
+    } else if !states.is_empty() && states.iter().all(|s| **s == ColabApprovalState::Approved) {
+        ColabApprovalState::Approved
+    } else if states.iter().any(|s| **s == ColabApprovalState::Pending) {
+        ColabApprovalState::Pending
+    } else {
+        ColabApprovalState::Draft
+    }
+}
+
+// The approvers of a statement language, oldest decision first
+pub fn approvers(org_id: &str, approvals: &HashMap<String, ColabUserApproval>) -> Vec<CitationApprover> {
+    let mut approvers: Vec<CitationApprover> = approvals
+        .values()
+        .map(|a| CitationApprover {
+            prpl: format!("{}/u/{}", org_id, a.user),
+            state: a.state.to_string(),
+            date: a.date,
+        })
+        .collect();
+    approvers.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.prpl.cmp(&b.prpl)));
+    approvers
+}
+
+// The date of the latest approval decision
+pub fn approval_date(approvals: &HashMap<String, ColabUserApproval>) -> Option<DateTime<Utc>> {
+    approvals.values().map(|a| a.date).max()
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::cmp::Ordering;
+use axum::http;
+use crate::config;
+use crate::models::ClientVersionCount;
+use crate::ws::connctx::{self, ClientVersion};
+
+// Versions of the clients, to roll out breaking protocol and schema changes.
+// Clients announce the version of their library and of the document schema in the WebSocket handshake,
+// as headers or, for browsers that can't set them, as query params. Handshakes below the minimum
+// versions are refused, clients below the warning version are accepted and reported as deprecated.
+// A client that doesn't announce a version is below every configured version.
+
+const CLIENT_VERSION_HEADER: &str = "x-colabri-client-version";
+const SCHEMA_VERSION_HEADER: &str = "x-colabri-schema-version";
+const CLIENT_VERSION_PARAM: &str = "clientVersion";
+const SCHEMA_VERSION_PARAM: &str = "schemaVersion";
+
+/// Whether a client may connect
+pub enum Compatibility {
+    Supported,
+    // Accepted, with the reason it should upgrade
+    Deprecated(String),
+    // Refused, with the reason
+    Refused(String),
+}
+
+// A header of the request, or else a query param
+fn announced<B>(req: &http::Request<B>, header: &str, param: &str) -> Option<String> {
+    let from_header = req.headers().get(header).and_then(|value| value.to_str().ok()).map(|value| value.to_string());
+    let from_query = || {
+        req.uri().query().and_then(|query| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == param)
+                .map(|(_, value)| value.to_string())
+        })
+    };
+    from_header.or_else(from_query).map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// The versions a client announced in its handshake request
+pub fn from_request<B>(req: &http::Request<B>) -> ClientVersion {
+    ClientVersion {
+        client: announced(req, CLIENT_VERSION_HEADER, CLIENT_VERSION_PARAM),
+        schema: announced(req, SCHEMA_VERSION_HEADER, SCHEMA_VERSION_PARAM),
+    }
+}
+
+// Compare dotted versions numerically, a pre-release suffix is ignored and missing parts are 0
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (parts(a), parts(b));
+    (0..a.len().max(b.len()))
+        .map(|idx| a.get(idx).unwrap_or(&0).cmp(b.get(idx).unwrap_or(&0)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+// Whether an announced version is below a configured one, unconfigured versions allow everything
+fn is_below(version: Option<&str>, configured: Option<&str>) -> bool {
+    match configured.map(str::trim).filter(|configured| !configured.is_empty()) {
+        Some(configured) => version.map_or(true, |version| compare_versions(version, configured).is_lt()),
+        None => false,
+    }
+}
+
+fn describe(version: Option<&str>) -> &str {
+    version.unwrap_or("unknown")
+}
+
+/// Whether a client with the announced versions may connect
+pub fn check(version: &ClientVersion) -> Compatibility {
+    let config = config::get_config();
+    if is_below(version.client.as_deref(), config.client_min_version.as_deref()) {
+        return Compatibility::Refused(format!(
+            "Client version {} is below the minimum {}", describe(version.client.as_deref()), config.client_min_version.as_deref().unwrap_or_default()
+        ));
+    }
+    if is_below(version.schema.as_deref(), config.client_min_schema_version.as_deref()) {
+        return Compatibility::Refused(format!(
+            "Schema version {} is below the minimum {}", describe(version.schema.as_deref()), config.client_min_schema_version.as_deref().unwrap_or_default()
+        ));
+    }
+    if is_below(version.client.as_deref(), config.client_warn_version.as_deref()) {
+        return Compatibility::Deprecated(format!(
+            "Client version {} is deprecated, upgrade to {} or later", describe(version.client.as_deref()), config.client_warn_version.as_deref().unwrap_or_default()
+        ));
+    }
+    Compatibility::Supported
+}
+
+/// The connections per announced client and schema version, most used first
+pub fn distribution() -> Vec<ClientVersionCount> {
+    let mut counts: HashMap<ClientVersion, u32> = HashMap::new();
+    for (_, conn_ctx) in connctx::get_conn_ctx_cache().iter() {
+        *counts.entry(conn_ctx.client_version.clone()).or_default() += 1;
+    }
+    let mut distribution: Vec<ClientVersionCount> = counts
+        .into_iter()
+        .map(|(version, n_conn)| ClientVersionCount {
+            deprecated: !matches!(check(&version), Compatibility::Supported),
+            client_version: version.client,
+            schema_version: version.schema,
+            n_conn,
+        })
+        .collect();
+    distribution.sort_by(|a, b| b.n_conn.cmp(&a.n_conn).then(a.client_version.cmp(&b.client_version)));
+    distribution
+}
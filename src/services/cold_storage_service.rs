@@ -0,0 +1,180 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use moka::sync::Cache;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab;
+
+const DEFAULT_API_URL: &str = "https://storage.googleapis.com";
+const METADATA_TOKEN_URL: &str = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+// Window in which the reads of a cold stream are counted for promotion
+const READ_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+static STORAGE_CLIENT: OnceLock<Client> = OnceLock::new();
+static ACCESS_TOKEN: Mutex<Option<(String, Instant)>> = Mutex::const_new(None);
+static READ_COUNTS: OnceLock<Cache<Uuid, u32>> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct MetadataToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+fn get_client() -> &'static Client {
+    STORAGE_CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .expect("Failed to build object storage client")
+    })
+}
+
+fn get_read_counts() -> &'static Cache<Uuid, u32> {
+    READ_COUNTS.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(100_000)
+            .time_to_live(READ_WINDOW)
+            .build()
+    })
+}
+
+// The bucket and object of a pointer of the form gs://<bucket>/<object>
+fn parse_pointer(pointer: &str) -> Result<(&str, &str), String> {
+    pointer
+        .strip_prefix("gs://")
+        .and_then(|rest| rest.split_once('/'))
+        .ok_or_else(|| format!("Unsupported cold storage pointer '{}'", pointer))
+}
+
+// URL of the object storage API with the given path segments
+fn api_url(segments: &[&str]) -> Result<Url, String> {
+    let base = config::get_config().cold_storage_api_url.clone().unwrap_or_else(|| DEFAULT_API_URL.to_string());
+    let mut url = Url::parse(&base).map_err(|e| format!("Invalid cold storage API URL '{}': {}", base, e))?;
+    url.path_segments_mut()
+        .map_err(|_| format!("Invalid cold storage API URL '{}'", base))?
+        .extend(segments);
+    Ok(url)
+}
+
+// Fetch the content of a cold tier stream, the pointer has the form gs://<bucket>/<object>
+pub async fn fetch(pointer: &str) -> Result<Vec<u8>, String> {
+    let (bucket, object) = parse_pointer(pointer)?;
+
+    // 1. Build the download URL, the object name is encoded as a single path segment
+    let mut url = api_url(&["storage", "v1", "b", bucket, "o", object])?;
+    url.query_pairs_mut().append_pair("alt", "media");
+
+    // 2. Download the object, anonymously when no token is available (e.g. a local emulator)
+    let mut request = get_client().get(url);
+    if let Some(token) = access_token().await {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.map_err(|e| format!("Failed to fetch '{}': {}", pointer, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch '{}': {}", pointer, response.status()));
+    }
+    let bytes = response.bytes().await.map_err(|e| format!("Failed to read '{}': {}", pointer, e))?;
+    Ok(bytes.to_vec())
+}
+
+// Write an object to the cold tier, used by the self-test
+pub async fn store(pointer: &str, content: Vec<u8>) -> Result<(), String> {
+    let (bucket, object) = parse_pointer(pointer)?;
+    let mut url = api_url(&["upload", "storage", "v1", "b", bucket, "o"])?;
+    url.query_pairs_mut().append_pair("uploadType", "media").append_pair("name", object);
+    let mut request = get_client().post(url).header("Content-Type", "application/octet-stream").body(content);
+    if let Some(token) = access_token().await {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.map_err(|e| format!("Failed to store '{}': {}", pointer, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to store '{}': {}", pointer, response.status()));
+    }
+    Ok(())
+}
+
+// Delete an object from the cold tier, used by the self-test
+pub async fn delete(pointer: &str) -> Result<(), String> {
+    let (bucket, object) = parse_pointer(pointer)?;
+    let url = api_url(&["storage", "v1", "b", bucket, "o", object])?;
+    let mut request = get_client().delete(url);
+    if let Some(token) = access_token().await {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.map_err(|e| format!("Failed to delete '{}': {}", pointer, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to delete '{}': {}", pointer, response.status()));
+    }
+    Ok(())
+}
+
+// Count a read of a cold stream and promote it back to the hot tier once it is read often enough
+pub fn record_read(org_id: &str, stream_id: Uuid, content: &[u8]) {
+    let threshold = config::get_config().cold_promote_after_reads.unwrap_or(3);
+    if threshold == 0 {
+        return;
+    }
+    let reads = get_read_counts().get(&stream_id).unwrap_or(0) + 1;
+    if reads < threshold {
+        get_read_counts().insert(stream_id, reads);
+        return;
+    }
+    get_read_counts().invalidate(&stream_id);
+
+    let org_id = org_id.to_string();
+    let content = content.to_vec();
+    tokio::spawn(async move {
+        let db = match dbcolab::get_db() {
+            Some(db) => db,
+            None => return,
+        };
+        match db.promote_doc_stream(&org_id, stream_id, content).await {
+            Ok(true) => info!("Promoted stream {} to the hot tier after {} reads", stream_id, reads),
+            Ok(false) => {}
+            Err(e) => error!("Failed to promote stream {} to the hot tier: {}", stream_id, e),
+        }
+    });
+}
+
+// Get an access token of the service account from the metadata server, cached until shortly before it expires
+async fn access_token() -> Option<String> {
+    let mut cached = ACCESS_TOKEN.lock().await;
+    if let Some((token, expires_at)) = cached.as_ref() {
+        if Instant::now() < *expires_at {
+            return Some(token.clone());
+        }
+    }
+
+    let response = get_client()
+        .get(METADATA_TOKEN_URL)
+        .header("Metadata-Flavor", "Google")
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await;
+    let token: MetadataToken = match response {
+        Ok(response) if response.status().is_success() => match response.json().await {
+            Ok(token) => token,
+            Err(e) => {
+                warn!("Invalid access token from the metadata server: {}", e);
+                return None;
+            }
+        },
+        Ok(response) => {
+            warn!("Failed to get an access token from the metadata server: {}", response.status());
+            return None;
+        }
+        Err(e) => {
+            warn!("Failed to reach the metadata server: {}", e);
+            return None;
+        }
+    };
+
+    let expires_at = Instant::now() + Duration::from_secs(token.expires_in.saturating_sub(60));
+    *cached = Some((token.access_token.clone(), expires_at));
+    Some(token.access_token)
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use loro::cursor::{Cursor, Side};
+use loro::{Container, LoroDoc, LoroMap, LoroText, ToJson, ValueOrContainer};
+use tracing::{info, warn};
+use uuid::Uuid;
+use crate::models::{ColabComment, ColabCommentAnchor, ColabCommentState, CommentAnchorView, CommentThread, CommentView, TextElement};
+use crate::models::lorodoc::{colab_comment_anchor_to_loro_map, colab_comment_to_loro_map, get_child_map, txtelem_to_loro_doc};
+use crate::services::acl_service;
+use crate::ws::docctx::DocContext;
+
+// Read all comments stored on a block or language map
+pub fn read_comments(container: &LoroMap) -> Vec<ColabComment> {
+    let comments_map = match get_child_map(container, "comments") {
+        Some(map) => map,
+        None => return Vec::new(),
+    };
+
+    let keys: Vec<String> = comments_map.keys().map(|k| k.to_string()).collect();
+    let mut comments = Vec::with_capacity(keys.len());
+    for key in keys {
+        if let Some(comment_map) = get_child_map(&comments_map, &key) {
+            match serde_json::from_value::<ColabComment>(comment_map.get_deep_value().to_json_value()) {
+                Ok(comment) => comments.push(comment),
+                Err(e) => warn!("Skipping malformed comment '{}': {}", key, e),
+            }
+        }
+    }
+    comments
+}
+
+// Find a comment anywhere in the document.
+// Returns the path it is stored on together with all comments on that path.
+pub fn find_comment(doc: &LoroDoc, comment_id: &Uuid) -> Result<Option<(String, Vec<ColabComment>)>, String> {
+    for scope in acl_service::collect_acl_scopes(doc)? {
+        if let Some(map) = &scope.map {
+            let comments = read_comments(map);
+            if comments.iter().any(|c| &c.id == comment_id) {
+                return Ok(Some((scope.path.clone(), comments)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Follow the parent links of a comment up to the root of its thread
+pub fn thread_root_id(comments: &[ColabComment], comment_id: Uuid) -> Uuid {
+    let by_id: HashMap<Uuid, &ColabComment> = comments.iter().map(|c| (c.id, c)).collect();
+    let mut current = comment_id;
+    // Bounded by the number of comments to guard against cycles
+    for _ in 0..comments.len() {
+        match by_id.get(&current).and_then(|c| c.parent_id) {
+            Some(parent_id) if by_id.contains_key(&parent_id) => current = parent_id,
+            _ => break,
+        }
+    }
+    current
+}
+
+// Group the comments on a path into threads, replies ordered by timestamp
+pub fn build_threads(path: &str, comments: &[ColabComment]) -> Vec<CommentThread> {
+    let mut roots: Vec<&ColabComment> = comments
+        .iter()
+        .filter(|c| thread_root_id(comments, c.id) == c.id)
+        .collect();
+    roots.sort_by_key(|c| c.timestamp);
+
+    roots
+        .into_iter()
+        .map(|root| {
+            let mut replies: Vec<&ColabComment> = comments
+                .iter()
+                .filter(|c| c.id != root.id && thread_root_id(comments, c.id) == root.id)
+                .collect();
+            replies.sort_by_key(|c| c.timestamp);
+            CommentThread {
+                path: path.to_string(),
+                state: root.state.to_string(),
+                root: comment_view(root),
+                replies: replies.into_iter().map(comment_view).collect(),
+            }
+        })
+        .collect()
+}
+
+pub fn comment_view(comment: &ColabComment) -> CommentView {
+    CommentView {
+        id: comment.id,
+        parent_id: comment.parent_id,
+        author: comment.author,
+        state: comment.state.to_string(),
+        text: serde_json::to_value(&comment.text).unwrap_or_default(),
+        timestamp: comment.timestamp,
+        edited_at: comment.edited_at,
+        anchor: comment.anchor.as_ref().map(|anchor| CommentAnchorView {
+            start: anchor.start_offset,
+            end: anchor.end_offset,
+            quote: anchor.quote.clone(),
+            orphaned: anchor.orphaned,
+        }),
+        reactions: Vec::new(),
+    }
+}
+
+// Add a comment (or reply) to the block or language at `path`
+pub fn add_comment(doc: &LoroDoc, path: &str, comment: &ColabComment) -> Result<(), String> {
+    let container = acl_service::find_scope_map(doc, path)?;
+    let comments_map = container
+        .get_or_create_container("comments", LoroMap::new())
+        .map_err(|e| format!("Failed to create comments map: {}", e))?;
+    let comment_map = comments_map
+        .get_or_create_container(comment.id.to_string().as_str(), LoroMap::new())
+        .map_err(|e| format!("Failed to create comment '{}': {}", comment.id, e))?;
+    colab_comment_to_loro_map(comment, &comment_map);
+    Ok(())
+}
+
+// Replace the text of a comment
+pub fn edit_comment_text(doc: &LoroDoc, path: &str, comment_id: &Uuid, text: &TextElement, edited_at: &DateTime<Utc>) -> Result<(), String> {
+    let container = acl_service::find_scope_map(doc, path)?;
+    let comment_map = get_child_map(&container, "comments")
+        .and_then(|comments_map| get_child_map(&comments_map, &comment_id.to_string()))
+        .ok_or_else(|| format!("Comment '{}' not found at path '{}'", comment_id, path))?;
+    let text_map = comment_map
+        .insert_container("text", LoroMap::new())
+        .map_err(|e| format!("Failed to replace text of comment '{}': {}", comment_id, e))?;
+    txtelem_to_loro_doc(text, &text_map);
+    comment_map
+        .insert("editedAt", edited_at.to_rfc3339().as_str())
+        .map_err(|e| format!("Failed to update comment '{}': {}", comment_id, e))?;
+    Ok(())
+}
+
+// Set the state of every comment in the thread with the given root
+pub fn set_thread_state(doc: &LoroDoc, path: &str, root_id: &Uuid, state: &ColabCommentState) -> Result<(), String> {
+    let container = acl_service::find_scope_map(doc, path)?;
+    let comments = read_comments(&container);
+    let comments_map = get_child_map(&container, "comments")
+        .ok_or_else(|| format!("No comments found at path '{}'", path))?;
+    for comment in &comments {
+        if &thread_root_id(&comments, comment.id) != root_id || &comment.state == state {
+            continue;
+        }
+        if let Some(comment_map) = get_child_map(&comments_map, &comment.id.to_string()) {
+            comment_map
+                .insert("state", state.to_string().as_str())
+                .map_err(|e| format!("Failed to update comment '{}': {}", comment.id, e))?;
+        }
+    }
+    Ok(())
+}
+
+// Anchoring comments to text ranges.
+// The text of a block or language is a tree of elements with LoroText leaves, offsets count
+// unicode characters over the concatenation of all leaves. The range ends are stored as Loro
+// cursors, so they keep pointing to the same characters while the text is edited concurrently.
+
+struct TextLeaf {
+    text: LoroText,
+    offset: usize,
+}
+
+fn collect_text_leaves(element: &LoroMap, leaves: &mut Vec<TextLeaf>, offset: &mut usize, depth: usize) {
+    const MAX_DEPTH: usize = 100; // Prevent stack overflow
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let children = match element.get("children") {
+        Some(ValueOrContainer::Container(Container::List(list))) => list,
+        _ => return,
+    };
+    for idx in 0..children.len() {
+        match children.get(idx) {
+            Some(ValueOrContainer::Container(Container::Text(text))) => {
+                let len = text.len_unicode();
+                leaves.push(TextLeaf { text, offset: *offset });
+                *offset += len;
+            }
+            Some(ValueOrContainer::Container(Container::Map(child))) => {
+                collect_text_leaves(&child, leaves, offset, depth + 1);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Get the text leaves of the block or language map, together with the full text
+fn text_leaves(container: &LoroMap) -> Option<(Vec<TextLeaf>, Vec<char>)> {
+    let text_element = get_child_map(container, "textElement")?;
+    let mut leaves = Vec::new();
+    let mut offset = 0;
+    collect_text_leaves(&text_element, &mut leaves, &mut offset, 0);
+    let text = leaves.iter().flat_map(|leaf| leaf.text.to_string().chars().collect::<Vec<_>>()).collect();
+    Some((leaves, text))
+}
+
+fn encode_cursor_at(leaves: &[TextLeaf], pos: usize) -> Option<String> {
+    // A position on the boundary of two leaves belongs to the start of the next leaf
+    let leaf = leaves.iter().rev().find(|leaf| leaf.offset <= pos)?;
+    let cursor = leaf.text.get_cursor(pos - leaf.offset, Side::Left)?;
+    Some(general_purpose::STANDARD.encode(cursor.encode()))
+}
+
+// Resolve an encoded cursor to its current offset, and the updated cursor if Loro had to move it
+fn resolve_cursor(doc: &LoroDoc, leaves: &[TextLeaf], encoded: &str) -> Option<(usize, Option<String>)> {
+    let bytes = general_purpose::STANDARD.decode(encoded).ok()?;
+    let cursor = Cursor::decode(&bytes).ok()?;
+    let leaf = leaves.iter().find(|leaf| leaf.text.id() == cursor.container)?;
+    let result = doc.get_cursor_pos(&cursor).ok()?;
+    let updated = result.update.map(|c| general_purpose::STANDARD.encode(c.encode()));
+    Some((leaf.offset + result.current.pos, updated))
+}
+
+fn anchor_range(leaves: &[TextLeaf], text: &[char], start: usize, end: usize) -> Option<ColabCommentAnchor> {
+    Some(ColabCommentAnchor {
+        start: encode_cursor_at(leaves, start)?,
+        end: encode_cursor_at(leaves, end)?,
+        quote: text[start..end].iter().collect(),
+        start_offset: start,
+        end_offset: end,
+        orphaned: false,
+    })
+}
+
+// Create an anchor for the character range [start, end) of the text of the block or language at `path`
+pub fn create_anchor(doc: &LoroDoc, path: &str, start: usize, end: usize) -> Result<ColabCommentAnchor, String> {
+    let container = acl_service::find_scope_map(doc, path)?;
+    let (leaves, text) = text_leaves(&container)
+        .filter(|(leaves, _)| !leaves.is_empty())
+        .ok_or_else(|| format!("No text found at path '{}'", path))?;
+    if start > end || end > text.len() {
+        return Err(format!("Invalid range [{}, {}) for a text of {} characters", start, end, text.len()));
+    }
+    anchor_range(&leaves, &text, start, end)
+        .ok_or_else(|| format!("Failed to create an anchor for range [{}, {})", start, end))
+}
+
+// Find the current range of an anchor.
+// When the cursors can't be resolved anymore, the quote is searched for near the last known offset.
+fn resolve_anchor(doc: &LoroDoc, leaves: &[TextLeaf], text: &[char], anchor: &ColabCommentAnchor) -> ColabCommentAnchor {
+
+    // 1. Resolve the cursors
+    if let (Some((start, start_update)), Some((end, end_update))) =
+        (resolve_cursor(doc, leaves, &anchor.start), resolve_cursor(doc, leaves, &anchor.end))
+    {
+        if start <= end && end <= text.len() {
+            return ColabCommentAnchor {
+                start: start_update.unwrap_or_else(|| anchor.start.clone()),
+                end: end_update.unwrap_or_else(|| anchor.end.clone()),
+                quote: text[start..end].iter().collect(),
+                start_offset: start,
+                end_offset: end,
+                orphaned: false,
+            };
+        }
+    }
+
+    // 2. Re-anchor on the occurrence of the quote closest to the last known offset
+    let quote: Vec<char> = anchor.quote.chars().collect();
+    if !quote.is_empty() && quote.len() <= text.len() {
+        let closest = (0..=text.len() - quote.len())
+            .filter(|&pos| text[pos..pos + quote.len()] == quote[..])
+            .min_by_key(|&pos| pos.abs_diff(anchor.start_offset));
+        if let Some(reanchored) = closest.and_then(|pos| anchor_range(leaves, text, pos, pos + quote.len())) {
+            return reanchored;
+        }
+    }
+
+    // 3. Give up, keep the last known state
+    ColabCommentAnchor { orphaned: true, ..anchor.clone() }
+}
+
+fn anchor_changed(before: &ColabCommentAnchor, after: &ColabCommentAnchor) -> bool {
+    before.start != after.start
+        || before.end != after.end
+        || before.quote != after.quote
+        || before.start_offset != after.start_offset
+        || before.end_offset != after.end_offset
+        || before.orphaned != after.orphaned
+}
+
+// Update the anchors of the given comments (stored on `container`) to their current range
+pub fn resolve_anchors(doc: &LoroDoc, container: &LoroMap, comments: &mut [ColabComment]) {
+    if comments.iter().all(|c| c.anchor.is_none()) {
+        return;
+    }
+    let (leaves, text) = text_leaves(container).unwrap_or_default();
+    for comment in comments.iter_mut() {
+        if let Some(anchor) = &comment.anchor {
+            comment.anchor = Some(resolve_anchor(doc, &leaves, &text, anchor));
+        }
+    }
+}
+
+// Resolve all anchors in the document and write back the ones that changed.
+// Returns the number of updated anchors, the caller is responsible for committing.
+pub fn reanchor_comments(doc: &LoroDoc) -> Result<usize, String> {
+    let mut updated = 0;
+    for scope in acl_service::collect_acl_scopes(doc)? {
+        let container = match &scope.map {
+            Some(map) => map,
+            None => continue,
+        };
+        let comments_map = match get_child_map(container, "comments") {
+            Some(map) => map,
+            None => continue,
+        };
+        let mut comments = read_comments(container);
+        let before: HashMap<Uuid, ColabCommentAnchor> = comments
+            .iter()
+            .filter_map(|c| c.anchor.clone().map(|anchor| (c.id, anchor)))
+            .collect();
+        resolve_anchors(doc, container, &mut comments);
+        for comment in &comments {
+            let (old, new) = match (before.get(&comment.id), &comment.anchor) {
+                (Some(old), Some(new)) => (old, new),
+                _ => continue,
+            };
+            if !anchor_changed(old, new) {
+                continue;
+            }
+            if let Some(comment_map) = get_child_map(&comments_map, &comment.id.to_string()) {
+                colab_comment_anchor_to_loro_map(new, &comment_map);
+                updated += 1;
+            }
+        }
+    }
+    Ok(updated)
+}
+
+// Re-anchor the comments of a snapshot that is being loaded.
+// Anchoring problems never block loading, the original snapshot is returned on any error.
+pub fn reanchor_snapshot(doc_id: &str, snapshot: Vec<u8>, ctx: &mut DocContext) -> Vec<u8> {
+    let loro_doc = LoroDoc::new();
+    if let Err(e) = loro_doc.import(&snapshot) {
+        warn!("Skipping re-anchoring of comments for document '{}', failed to import snapshot: {}", doc_id, e);
+        return snapshot;
+    }
+    match reanchor_comments(&loro_doc) {
+        Ok(0) => snapshot,
+        Ok(updated) => {
+            loro_doc.commit();
+            match loro_doc.export(loro::ExportMode::Snapshot) {
+                Ok(reanchored) => {
+                    // Attribute the change to the colabri-doc service
+                    ctx.peer_map.insert(loro_doc.peer_id(), "s/colabri-doc".to_string());
+                    info!("Re-anchored {} comment(s) in document '{}'", updated, doc_id);
+                    reanchored
+                }
+                Err(e) => {
+                    warn!("Failed to export re-anchored document '{}': {}", doc_id, e);
+                    snapshot
+                }
+            }
+        }
+        Err(e) => {
+            warn!("Failed to re-anchor comments for document '{}': {}", doc_id, e);
+            snapshot
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+use serde_json::Value;
+use uuid::Uuid;
+use crate::models::{ColabModel, ContentDiff};
+use crate::services::{lazy_block_service, policy_scan_service, statement_subdoc_service};
+
+// Comparison of a document with an external model, e.g. the regulatory source it was copied from.
+// Both sides are reduced to the plain text of every block and language, keyed by path as in the
+// policy scan: /content/<lang> of statements, /content/<blockId> of sheets and the languages of their
+// local statements. Blocks are matched by id, blocks without id by position. Formatting, ACLs and
+// approvals aren't compared. Rows stored apart from the document are composed in first.
+
+// The text per path of a document in its JSON form
+fn texts(model: &ColabModel, json: &Value) -> BTreeMap<String, String> {
+    policy_scan_service::scan_blocks(model, json)
+        .into_iter()
+        .map(|block| (block.path, block.text.split_whitespace().collect::<Vec<_>>().join(" ")))
+        .collect()
+}
+
+pub(crate) fn doc_type(model: &ColabModel) -> String {
+    match model {
+        ColabModel::Statement(statement) => statement.properties.r#type.to_string(),
+        ColabModel::Sheet(sheet) => sheet.properties.r#type.to_string(),
+    }
+}
+
+// Compare the JSON of the latest state of a document with an external model.
+// Returns the unchanged count and the differences. The inner error is a message for the caller about
+// the external model, the outer error a failure to compose the document.
+pub async fn compare(org_id: &str, doc_uuid: Uuid, mut json: Value, source: &Value) -> Result<Result<(usize, Vec<ContentDiff>), String>, String> {
+    let source_model: ColabModel = match serde_json::from_value(source.clone()) {
+        Ok(model) => model,
+        Err(e) => return Ok(Err(format!("Invalid external model: {}", e))),
+    };
+    lazy_block_service::assemble_json(org_id, doc_uuid, &mut json).await?;
+    statement_subdoc_service::compose_json(org_id, &mut json).await?;
+    let current_model: ColabModel = serde_json::from_value(json.clone())
+        .map_err(|e| format!("Failed to parse document '{}': {}", doc_uuid, e))?;
+    if doc_type(&current_model) != doc_type(&source_model) {
+        return Ok(Err(format!("The external model is a {}, the document a {}", doc_type(&source_model), doc_type(&current_model))));
+    }
+
+    let current = texts(&current_model, &json);
+    let source = texts(&source_model, source);
+    let mut unchanged = 0;
+    let mut differences = Vec::new();
+    for (path, source_text) in &source {
+        match current.get(path) {
+            Some(current_text) if current_text == source_text => unchanged += 1,
+            Some(current_text) => differences.push(ContentDiff {
+                path: path.clone(),
+                status: "changed".to_string(),
+                current: Some(current_text.clone()),
+                source: Some(source_text.clone()),
+            }),
+            None => differences.push(ContentDiff {
+                path: path.clone(),
+                status: "missing".to_string(),
+                current: None,
+                source: Some(source_text.clone()),
+            }),
+        }
+    }
+    for (path, current_text) in current.iter().filter(|(path, _)| !source.contains_key(*path)) {
+        differences.push(ContentDiff {
+            path: path.clone(),
+            status: "extra".to_string(),
+            current: Some(current_text.clone()),
+            source: None,
+        });
+    }
+    Ok(Ok((unchanged, differences)))
+}
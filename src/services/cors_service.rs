@@ -0,0 +1,62 @@
+use std::time::Duration;
+use axum::http::{self, header, HeaderValue, Method};
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+use crate::config;
+
+// Origins allowed to call the API from a browser and to open a WebSocket, from CLOUD_CORS_ORIGINS.
+// The comma separated patterns are origins where `*` stands for any subdomain or any port, e.g.
+// "https://*.colabri.cloud" or "http://localhost:*". A `*` never spans a `/` or a `:`, so a pattern
+// can't be satisfied by smuggling another host or port into the wildcard.
+
+// How long browsers may cache the answer to a preflight request
+const PREFLIGHT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Whether the origin matches one of the configured patterns
+pub fn origin_allowed(origin: &str) -> bool {
+    config::get_config()
+        .cloud_cors_origins
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .any(|pattern| matches_pattern(pattern, origin))
+}
+
+// Match an origin against a pattern, every `*` matches a non-empty run without `/` or `:`
+fn matches_pattern(pattern: &str, origin: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern.eq_ignore_ascii_case(origin);
+    };
+    if origin.len() < prefix.len() || !origin[..prefix.len()].eq_ignore_ascii_case(prefix) {
+        return false;
+    }
+    let remainder = &origin[prefix.len()..];
+    remainder
+        .char_indices()
+        .skip(1)
+        .map(|(index, _)| index)
+        .chain(std::iter::once(remainder.len()))
+        .take_while(|&end| !remainder[..end].contains(['/', ':']))
+        .any(|end| matches_pattern(rest, &remainder[end..]))
+}
+
+/// The CORS layer of the API routes
+pub fn cors_layer() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(|origin: &HeaderValue, _| {
+            origin.to_str().map(origin_allowed).unwrap_or(false)
+        }))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS])
+        .allow_headers(AllowHeaders::mirror_request())
+        .expose_headers([header::CONTENT_DISPOSITION])
+        .allow_credentials(true)
+        .max_age(PREFLIGHT_MAX_AGE)
+}
+
+/// Whether a WebSocket handshake may proceed. Browsers always send their origin, clients that don't
+/// aren't subject to the same-origin policy and are authenticated by their token alone.
+pub fn handshake_allowed<B>(req: &http::Request<B>) -> bool {
+    match req.headers().get(header::ORIGIN) {
+        Some(origin) => origin.to_str().map(origin_allowed).unwrap_or(false),
+        None => true,
+    }
+}
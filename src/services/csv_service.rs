@@ -0,0 +1,77 @@
+use axum::{http::{header, StatusCode}, response::{IntoResponse, Response}};
+
+// Escape a single CSV field (RFC 4180)
+pub fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Render a header and rows as a CSV document
+pub fn to_csv(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut csv = String::new();
+    csv.push_str(&header.iter().map(|h| escape_csv_field(h)).collect::<Vec<String>>().join(","));
+    csv.push_str("\r\n");
+    for row in rows {
+        csv.push_str(&row.iter().map(|f| escape_csv_field(f)).collect::<Vec<String>>().join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+// Parse a CSV document (RFC 4180) into rows of fields, quoted fields may contain delimiters, quotes and line breaks
+pub fn parse_csv(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("Unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    // Blank lines carry no data
+    rows.retain(|row| !(row.len() == 1 && row[0].is_empty()));
+    Ok(rows)
+}
+
+// Wrap a CSV document in a downloadable response
+pub fn csv_response(filename: &str, csv: String) -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        csv,
+    )
+        .into_response()
+}
@@ -0,0 +1,220 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use tracing::{error, info, warn};
+use crate::db::dbcolab::{self, DocumentJsonRow};
+use crate::models::{ColabModel, ColabSheetBlock, DuplicateCluster, DuplicateOccurrence, StatementDuplicatesReport};
+use crate::services::summary_service;
+
+// Deduplication analysis of the statements of an org.
+// The text of every statement document and of every statement embedded in a sheet is normalized,
+// statements with the same text are clustered by their hash and statements with nearly the same text
+// by the Jaccard similarity of their words. The clusters show which claims can be consolidated into
+// one shared, referenced statement.
+
+pub const DEFAULT_THRESHOLD: f64 = 0.85;
+// Short statements like "Yes" are duplicates by nature
+const MIN_WORDS: usize = 3;
+// Above this many distinct texts only the exact duplicates are clustered
+const MAX_NEAR_COMPARISONS: usize = 5000;
+const EXCERPT_CHARS: usize = 160;
+
+static RUNNING: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn get_running() -> &'static Mutex<HashSet<String>> {
+    RUNNING.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+struct Statement {
+    occurrence: DuplicateOccurrence,
+    text: String,
+}
+
+// Lowercase words without punctuation
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn push_statement(row: &DocumentJsonRow, path: String, text: String, statements: &mut Vec<Statement>) {
+    let normalized = normalize(&text);
+    if normalized.split(' ').count() < MIN_WORDS {
+        return;
+    }
+    statements.push(Statement {
+        occurrence: DuplicateOccurrence {
+            doc_id: row.id.to_string(),
+            name: row.name.clone(),
+            path,
+            excerpt: summary_service::extract(&text, EXCERPT_CHARS),
+        },
+        text: normalized,
+    });
+}
+
+// The statements of the documents of an org, with the text of their master language
+fn collect_statements(rows: &[DocumentJsonRow]) -> Vec<Statement> {
+    let mut statements = Vec::new();
+    for row in rows {
+        let model: ColabModel = match serde_json::from_value(row.json.0.clone()) {
+            Ok(model) => model,
+            Err(e) => {
+                warn!("Skipping document '{}' in the deduplication analysis: {}", row.id, e);
+                continue;
+            }
+        };
+        match model {
+            ColabModel::Statement(statement) => push_statement(row, String::new(), summary_service::statement_text(&statement), &mut statements),
+            ColabModel::Sheet(sheet) => {
+                let raw_blocks = row.json.0.get("content").and_then(|content| content.as_array());
+                for (i, block) in sheet.content.iter().enumerate() {
+                    let grid = match block {
+                        ColabSheetBlock::StatementGrid(grid) => grid,
+                        _ => continue,
+                    };
+                    let block_id = raw_blocks
+                        .and_then(|raw| raw.get(i))
+                        .and_then(|raw| raw.get("id"))
+                        .and_then(|id| id.as_str())
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| i.to_string());
+                    for (r, grid_row) in grid.rows.iter().enumerate() {
+                        if let Some(statement) = &grid_row.statement {
+                            let path = format!("/content/{}/rows/{}/statement", block_id, r);
+                            push_statement(row, path, summary_service::statement_text(statement), &mut statements);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    statements
+}
+
+fn jaccard(a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+    let intersection = a.intersection(b).count();
+    let union = a.len() + b.len() - intersection;
+    if union == 0 { 1.0 } else { intersection as f64 / union as f64 }
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[i] = root;
+    root
+}
+
+// Cluster the statements by their text, largest clusters first
+fn cluster(statements: Vec<Statement>, threshold: f64) -> Vec<DuplicateCluster> {
+    // 1. Exact duplicates share their normalized text
+    let mut by_text: HashMap<String, Vec<DuplicateOccurrence>> = HashMap::new();
+    for statement in statements {
+        by_text.entry(statement.text).or_default().push(statement.occurrence);
+    }
+    let texts: Vec<(String, Vec<DuplicateOccurrence>)> = by_text.into_iter().collect();
+
+    // 2. Near duplicates are joined by the similarity of their words
+    let mut parents: Vec<usize> = (0..texts.len()).collect();
+    let mut similarity: Vec<f64> = vec![1.0; texts.len()];
+    if texts.len() <= MAX_NEAR_COMPARISONS {
+        let words: Vec<HashSet<&str>> = texts.iter().map(|(text, _)| text.split(' ').collect()).collect();
+        for a in 0..texts.len() {
+            for b in (a + 1)..texts.len() {
+                // The similarity can't exceed the ratio of the word counts
+                let (short, long) = (words[a].len().min(words[b].len()), words[a].len().max(words[b].len()));
+                if (short as f64) < threshold * long as f64 {
+                    continue;
+                }
+                let score = jaccard(&words[a], &words[b]);
+                if score < threshold {
+                    continue;
+                }
+                let (root_a, root_b) = (find(&mut parents, a), find(&mut parents, b));
+                let lowest = similarity[root_a].min(similarity[root_b]).min(score);
+                if root_a != root_b {
+                    parents[root_b] = root_a;
+                }
+                similarity[root_a] = lowest;
+            }
+        }
+    } else {
+        warn!("Only clustering exact duplicates of {} distinct statements", texts.len());
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..texts.len() {
+        let root = find(&mut parents, i);
+        groups.entry(root).or_default().push(i);
+    }
+    let mut clusters: Vec<DuplicateCluster> = groups
+        .into_iter()
+        .map(|(root, members)| DuplicateCluster {
+            exact: members.len() == 1,
+            similarity: similarity[root],
+            occurrences: members.iter().flat_map(|&i| texts[i].1.iter().cloned()).collect(),
+        })
+        .filter(|cluster| cluster.occurrences.len() > 1)
+        .collect();
+    clusters.sort_by(|a, b| b.occurrences.len().cmp(&a.occurrences.len()).then(a.occurrences[0].excerpt.cmp(&b.occurrences[0].excerpt)));
+    clusters
+}
+
+async fn analyze(org_id: &str, threshold: f64) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let rows = db.get_org_document_json(org_id)
+        .await
+        .map_err(|e| format!("Failed to load the documents of org '{}': {}", org_id, e))?;
+    let statements = collect_statements(&rows);
+    let count = statements.len() as i32;
+    let clusters = tokio::task::spawn_blocking(move || cluster(statements, threshold))
+        .await
+        .map_err(|e| format!("Failed to cluster the statements: {}", e))?;
+    let json = serde_json::to_value(&clusters).map_err(|e| format!("Failed to serialize the clusters: {}", e))?;
+    db.upsert_statement_duplicates(org_id, threshold, count, json)
+        .await
+        .map_err(|e| format!("Failed to store the duplicates of org '{}': {}", org_id, e))?;
+    info!("Found {} clusters of duplicates among {} statements of org '{}'", clusters.len(), count, org_id);
+    Ok(())
+}
+
+// Start analyzing the statements of an org in the background.
+// Returns false when an analysis of the org is already running.
+pub fn start(org_id: &str, threshold: f64) -> bool {
+    if !get_running().lock().unwrap().insert(org_id.to_string()) {
+        return false;
+    }
+    let org_id = org_id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = analyze(&org_id, threshold).await {
+            error!("Deduplication analysis of org '{}' failed: {}", org_id, e);
+        }
+        get_running().lock().unwrap().remove(&org_id);
+    });
+    true
+}
+
+// The latest report of an org, None when it was never analyzed
+pub async fn report(org_id: &str) -> Result<Option<StatementDuplicatesReport>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let row = match db.get_statement_duplicates(org_id)
+        .await
+        .map_err(|e| format!("Failed to load the duplicates of org '{}': {}", org_id, e))? {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let clusters: Vec<DuplicateCluster> = serde_json::from_value(row.clusters.0)
+        .map_err(|e| format!("Failed to parse the duplicates of org '{}': {}", org_id, e))?;
+    Ok(Some(StatementDuplicatesReport {
+        org_id: org_id.to_string(),
+        threshold: row.threshold,
+        statements: row.statements,
+        clusters,
+        generated_at: row.generated_at,
+    }))
+}
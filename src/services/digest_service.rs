@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::clients::app_service_client;
+use crate::config;
+use crate::db::dbcolab::{self, DocumentFollowerRow};
+use crate::models::{ColabApprovalState, DigestComment, DigestDocument, UserDigestEvent};
+use crate::models::lorodoc::get_string;
+use crate::services::{acl_service, approval_round_service, comment_service, doc_load_service, locale_service, unread_service, webhook_service::{self, WebhookEvent}, workflow_service};
+use crate::ws::docctx::DocContext;
+
+// Daily or weekly digests of the documents a user follows.
+// Each follow remembers the version vector of the document and the time of its last digest. A digest
+// groups the follows of a user that are due and lists per document the blocks that changed since that
+// version vector, the comments of others since that time and the paths waiting for the approval of the
+// user. Claiming a follow moves it to the current state, only the pod that claims it sends the digest.
+// Dates come formatted in the locale settings of the org as well, for notifications that show them as is.
+
+pub const FREQUENCIES: [&str; 2] = ["daily", "weekly"];
+
+// Start the digest job
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>) {
+    let interval_ms = config::get_config().digest_interval_ms.unwrap_or(60 * 60 * 1000);
+    if interval_ms == 0 {
+        info!("Digest job disabled");
+        return;
+    }
+    let interval = Duration::from_millis(interval_ms);
+    info!("Starting digest job, interval: {:?}", interval);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run(&registry).await {
+                error!("Digest run failed: {}", e);
+            }
+        }
+    });
+}
+
+/// The version vector of a document keyed by peer id, as stored with follows and read markers
+pub fn state_vv(doc: &LoroDoc) -> serde_json::Value {
+    let vv: HashMap<String, i32> = doc
+        .state_vv()
+        .iter()
+        .map(|(peer, counter)| (peer.to_string(), *counter))
+        .collect();
+    serde_json::to_value(vv).unwrap_or_default()
+}
+
+// Users are user principals, comments refer to the user ID
+fn user_uid(prpl: &str) -> Option<Uuid> {
+    prpl.split_once("/u/").and_then(|(_, uid)| Uuid::parse_str(uid).ok())
+}
+
+async fn run(registry: &Arc<HubRegistry<DocContext>>) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let follows = db.get_due_document_followers()
+        .await
+        .map_err(|e| format!("Failed to load the due digests: {}", e))?;
+
+    // One digest per user and frequency
+    let mut digests: BTreeMap<(String, String, String), Vec<DocumentFollowerRow>> = BTreeMap::new();
+    for follow in follows {
+        digests.entry((follow.org.clone(), follow.prpl.clone(), follow.frequency.clone())).or_default().push(follow);
+    }
+
+    let mut names: HashMap<String, HashMap<Uuid, String>> = HashMap::new();
+    for ((org_id, prpl, frequency), follows) in digests {
+        if !names.contains_key(&org_id) {
+            let documents = db.get_org_documents(&org_id, None)
+                .await
+                .map_err(|e| format!("Failed to list the documents of organization '{}': {}", org_id, e))?;
+            names.insert(org_id.clone(), documents.into_iter().map(|document| (document.id, document.name)).collect());
+        }
+        let org_names = &names[&org_id];
+
+        let mut documents = Vec::new();
+        for follow in &follows {
+            match compile(registry, follow, org_names).await {
+                Ok(Some(document)) => documents.push(document),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to compile the digest of document '{}' for '{}': {}", follow.document, prpl, e),
+            }
+        }
+        if documents.is_empty() {
+            continue;
+        }
+
+        let locale = locale_service::resolve(&org_id, None).await;
+        let digest = UserDigestEvent {
+            prpl: prpl.clone(),
+            frequency,
+            locale: locale.locale,
+            timezone: locale.timezone.name().to_string(),
+            documents,
+        };
+        deliver(&org_id, &digest).await;
+        info!("Sent the digest of {} documents to '{}'", digest.documents.len(), prpl);
+    }
+    Ok(())
+}
+
+// The digest of one followed document, None when nothing happened or another pod claimed it
+async fn compile(registry: &Arc<HubRegistry<DocContext>>, follow: &DocumentFollowerRow, names: &HashMap<Uuid, String>) -> Result<Option<DigestDocument>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let doc_id = follow.document.to_string();
+    let Some((latest, ctx)) = doc_load_service::load_loro_doc(registry, &follow.org, &doc_id).await? else {
+        return Ok(None);
+    };
+
+    // 1. The changes since the last digest
+    let since_vv = follow
+        .last_vv
+        .as_ref()
+        .and_then(|vv| serde_json::from_value::<HashMap<String, i32>>(vv.0.clone()).ok());
+    let (authors, blocks) = match since_vv {
+        Some(since_vv) => match unread_service::digest(&latest, &ctx.peer_map, &since_vv)? {
+            Ok(digest) => (digest.authors, digest.blocks),
+            Err(e) => {
+                warn!("Skipping the changes of document '{}' in the digest: {}", doc_id, e);
+                (Vec::new(), Vec::new())
+            }
+        },
+        None => (Vec::new(), Vec::new()),
+    };
+
+    // 2. The comments of others since the last digest, dated in the locale of the document
+    let content_type = get_string(&latest.get_map("properties"), "contentType");
+    let locale = locale_service::resolve(&follow.org, content_type.as_deref()).await;
+    let uid = user_uid(&follow.prpl);
+    let mut new_comments = Vec::new();
+    for scope in acl_service::collect_acl_scopes(&latest)? {
+        let Some(map) = &scope.map else {
+            continue;
+        };
+        new_comments.extend(
+            comment_service::read_comments(map)
+                .into_iter()
+                .filter(|comment| comment.timestamp > follow.last_digest_at && Some(comment.author) != uid)
+                .map(|comment| DigestComment {
+                    path: scope.path.clone(),
+                    id: comment.id,
+                    author: comment.author,
+                    timestamp_formatted: locale.format_datetime(&comment.timestamp),
+                    timestamp: comment.timestamp,
+                }),
+        );
+    }
+    new_comments.sort_by_key(|comment| comment.timestamp);
+
+    // 3. The paths waiting for the approval of the user
+    let mut pending_approvals: Vec<String> = approval_round_service::frozen_rounds(&latest)
+        .iter()
+        .flat_map(|round| approval_round_service::approver_responses(&latest, round))
+        .filter(|response| response.approver == follow.prpl && response.state == ColabApprovalState::Pending)
+        .map(|response| response.path)
+        .collect();
+    pending_approvals.sort();
+    pending_approvals.dedup();
+
+    // Claim the follow, even without news, so the next digest starts from here
+    let claimed = db.claim_document_follower_digest(follow, state_vv(&latest))
+        .await
+        .map_err(|e| format!("Failed to claim the digest: {}", e))?;
+    if !claimed || (blocks.is_empty() && new_comments.is_empty() && pending_approvals.is_empty()) {
+        return Ok(None);
+    }
+
+    Ok(Some(DigestDocument {
+        name: names.get(&follow.document).cloned().unwrap_or_default(),
+        doc_id,
+        since: follow.last_digest_at,
+        since_formatted: locale.format_datetime(&follow.last_digest_at),
+        authors,
+        blocks,
+        new_comments,
+        pending_approvals,
+    }))
+}
+
+// Hand the digest to the app service to notify the user, and to the webhooks of the workflow
+async fn deliver(org_id: &str, digest: &UserDigestEvent) {
+    if let Some(client) = app_service_client::get_app_service_client() {
+        match serde_json::to_value(digest) {
+            Ok(payload) => match client.send_digest(org_id, &payload).await {
+                Ok(status) if status.is_success() => {}
+                Ok(status) => warn!("App service answered {} to the digest of '{}'", status, digest.prpl),
+                Err(e) => warn!("Failed to send the digest of '{}' to the app service: {}", digest.prpl, e),
+            },
+            Err(e) => error!("Failed to serialize the digest of '{}': {}", digest.prpl, e),
+        }
+    }
+    match workflow_service::get_workflow(org_id).await {
+        Ok(workflow) => webhook_service::dispatch(workflow.webhooks, WebhookEvent::new(org_id, "", Utc::now(), digest)),
+        Err(e) => error!("{}", e),
+    }
+}
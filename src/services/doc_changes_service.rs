@@ -0,0 +1,87 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use loro::{LoroDoc, ToJson};
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use crate::services::{doc_load_service, version_vector_service};
+use crate::services::evidence_service::sha256_hex;
+use crate::ws::docctx::DocContext;
+
+// Changes of a document since an earlier state, for polling integrations.
+// The earlier state is a saved version or a version vector. Blocks of sheets are compared by id,
+// statements by language. Rows of lazy blocks and linked statements are stored apart from the
+// document and aren't compared, the block holding them only changes when its own fields change.
+
+/// The earlier state to compare the latest state of a document with
+pub enum Since {
+    Version(u32),
+    VersionVector(HashMap<String, i32>),
+}
+
+/// The blocks that differ between the earlier and the latest state
+pub struct Changes {
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+// Hash of every block of a sheet, or of every language of a statement
+pub(crate) fn block_hashes(json: &Value) -> BTreeMap<String, String> {
+    match json.get("content") {
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let id = block.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()).unwrap_or_else(|| i.to_string());
+                (id, sha256_hex(block.to_string().as_bytes()))
+            })
+            .collect(),
+        Some(Value::Object(languages)) => languages
+            .iter()
+            .map(|(lang_code, element)| (lang_code.clone(), sha256_hex(element.to_string().as_bytes())))
+            .collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+// The state of a document at a version vector, keyed by peer id or principal
+pub(crate) fn checkout_vv(doc: &LoroDoc, peer_map: &HashMap<u64, String>, vv: &HashMap<String, i32>) -> Result<LoroDoc, String> {
+    let vv = version_vector_service::resolve(vv, peer_map).map_err(|problems| problems.join("; "))?;
+    let vv = version_vector_service::validate(doc, &vv).map_err(|problems| problems.join("; "))?;
+    let fork = doc.fork();
+    fork.checkout(&fork.vv_to_frontiers(&vv))
+        .map_err(|e| format!("Failed to checkout the version vector: {}", e))?;
+    Ok(fork)
+}
+
+// Compare the latest state of a document with an earlier one.
+// The inner result is Ok(None) when the document didn't change at all, and Err with a message for the
+// caller when the earlier state doesn't exist. The outer error is a failure to load the earlier state.
+pub async fn changes_since(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, latest: &LoroDoc, peer_map: &HashMap<u64, String>, since: &Since) -> Result<Result<Option<Changes>, String>, String> {
+    let base = match since {
+        Since::Version(version) => match doc_load_service::load_loro_doc_version(registry, org_id, doc_id, *version).await? {
+            Some((base, _)) => base,
+            None => return Ok(Err(format!("Version {} of document '{}' not found", version, doc_id))),
+        },
+        Since::VersionVector(vv) => match checkout_vv(latest, peer_map, vv) {
+            Ok(base) => base,
+            Err(e) => return Ok(Err(e)),
+        },
+    };
+    if base.state_vv() == latest.state_vv() {
+        return Ok(Ok(None));
+    }
+
+    let before = block_hashes(&base.get_deep_value().to_json_value());
+    let after = block_hashes(&latest.get_deep_value().to_json_value());
+    let changed = after
+        .iter()
+        .filter(|(id, hash)| before.get(*id) != Some(*hash))
+        .map(|(id, _)| id.clone())
+        .collect();
+    let removed = before
+        .keys()
+        .filter(|id| !after.contains_key(*id))
+        .cloned()
+        .collect();
+    Ok(Ok(Some(Changes { changed, removed })))
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use loro::ToJson;
+use loro_websocket_server::HubRegistry;
+use serde_json::{json, Value};
+use tracing::info;
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::doctypes;
+use crate::models::{ColabModel, ColabModelPermission, ColabPackage, DocumentCreateRequest};
+use crate::services::{doc_load_service, doc_type_service, json_projection_service, limits_service, numbering_service, schema_migration_service, storage_service};
+use crate::ws::docctx::DocContext;
+
+// Creation of documents through the API.
+// The document row, its model and the first stream are stored together, so the id can be handed out
+// before anyone connects and the first websocket connection loads a regular stream. A document starts
+// empty or from the latest content of a template of the same type, without the approvals of the template.
+// Documents exported from another environment are imported the same way, from their JSON.
+
+// The JSON of an empty document of a type
+fn empty_json(request: &DocumentCreateRequest) -> Result<Value, String> {
+    let mut properties = json!({ "type": request.doc_type, "contentType": request.content_type });
+    if let Some(master_lang_code) = &request.master_lang_code {
+        properties["masterLangCode"] = json!(master_lang_code);
+    }
+    doctypes::get(&request.doc_type)
+        .map(|doc_type| doc_type.empty_json(properties))
+        .ok_or_else(|| format!("Unsupported document type '{}'", request.doc_type))
+}
+
+// The JSON of a document started from the latest content of a template
+async fn template_json(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, template_id: Uuid, request: &DocumentCreateRequest) -> Result<Result<Value, String>, String> {
+    let mut json = match doc_load_service::load_loro_doc(registry, org_id, &template_id.to_string()).await? {
+        Some((template, _)) => template.get_deep_value().to_json_value(),
+        None => return Ok(Err(format!("Template '{}' not found", template_id))),
+    };
+    let template_type = json.pointer("/properties/type").and_then(|t| t.as_str()).unwrap_or_default();
+    if template_type != request.doc_type {
+        return Ok(Err(format!("Template '{}' is a {}, not a {}", template_id, template_type, request.doc_type)));
+    }
+    json["properties"]["contentType"] = json!(request.content_type);
+    if let Some(master_lang_code) = &request.master_lang_code {
+        json["properties"]["masterLangCode"] = json!(master_lang_code);
+    }
+    json["properties"].as_object_mut().map(|properties| properties.remove("workflowState"));
+    if json.get("approvals").is_some() {
+        json["approvals"] = json!({});
+    }
+    if let Some(Value::Array(blocks)) = json.get_mut("content") {
+        for block in blocks.iter_mut().filter(|block| block.get("approvals").is_some()) {
+            block["approvals"] = json!({});
+        }
+    }
+    Ok(Ok(json))
+}
+
+// Create a document with the reserved id.
+// The inner error is a message for the caller about the request, the outer error a failure to store it.
+pub async fn create(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, container: Option<Uuid>, template: Option<Uuid>, request: &DocumentCreateRequest) -> Result<Result<(), String>, String> {
+    if let Err(e) = doc_type_service::ensure_registered(org_id, &request.doc_type, Some(&request.content_type)).await {
+        return Ok(Err(e));
+    }
+
+    // 1. The JSON of the initial state
+    let mut json = match template {
+        Some(template_id) => match template_json(registry, org_id, template_id, request).await? {
+            Ok(json) => json,
+            Err(e) => return Ok(Err(e)),
+        },
+        None => match empty_json(request) {
+            Ok(json) => json,
+            Err(e) => return Ok(Err(e)),
+        },
+    };
+    json["acls"] = acls_json(request)?;
+    store(org_id, doc_uuid, container, json, request).await
+}
+
+// Create a document with the reserved id from the JSON of its initial state, keeping the ACLs of the JSON
+pub async fn import(org_id: &str, doc_uuid: Uuid, container: Option<Uuid>, mut json: Value, request: &DocumentCreateRequest) -> Result<Result<(), String>, String> {
+    if let Err(e) = doc_type_service::ensure_registered(org_id, &request.doc_type, Some(&request.content_type)).await {
+        return Ok(Err(e));
+    }
+    let doc_type = json.pointer("/properties/type").and_then(|t| t.as_str()).unwrap_or_default();
+    if doc_type != request.doc_type {
+        return Ok(Err(format!("The JSON is a {}, not a {}", doc_type, request.doc_type)));
+    }
+    if json.get("acls").map_or(true, |acls| acls.is_null()) {
+        json["acls"] = acls_json(request)?;
+    }
+    store(org_id, doc_uuid, container, json, request).await
+}
+
+// The ACLs of the request, the owner gets view, edit and manage when absent
+fn acls_json(request: &DocumentCreateRequest) -> Result<Value, String> {
+    let acls = request.acls.clone().unwrap_or_else(|| {
+        [ColabModelPermission::View, ColabModelPermission::Edit, ColabModelPermission::Manage]
+            .into_iter()
+            .map(|permission| (permission, vec![request.owner.clone()]))
+            .collect()
+    });
+    serde_json::to_value(&acls).map_err(|e| format!("Failed to serialize ACLs: {}", e))
+}
+
+// Store the JSON of the initial state as a new document
+async fn store(org_id: &str, doc_uuid: Uuid, container: Option<Uuid>, json: Value, request: &DocumentCreateRequest) -> Result<Result<(), String>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    // 2. Build the LoroDoc within the limits of the organization
+    if let Err(e) = doctypes::get_or_error(&request.doc_type).and_then(|doc_type| doc_type.validate(&json)) {
+        return Ok(Err(e));
+    }
+    let limits = limits_service::get_limits(org_id).await;
+    if let Err(violation) = limits_service::check_json(&json, &limits) {
+        return Ok(Err(violation.to_string()));
+    }
+    let doc_model: ColabModel = match serde_json::from_value(json) {
+        Ok(model) => model,
+        Err(e) => return Ok(Err(format!("Invalid initial content: {}", e))),
+    };
+    let loro_doc = crate::models::lorodoc::colab_to_loro_doc(&doc_model)
+        .ok_or_else(|| format!("Failed to convert the initial content of document '{}' to a LoroDoc", doc_uuid))?;
+    loro_doc.get_map("properties")
+        .insert(schema_migration_service::SCHEMA_VERSION_FIELD, schema_migration_service::CURRENT_SCHEMA_VERSION as i64)
+        .map_err(|e| format!("Failed to set the schema version of document '{}': {}", doc_uuid, e))?;
+    loro_doc.commit();
+    numbering_service::number_new_doc(org_id, doc_uuid, &loro_doc, &request.by_prpl).await?;
+    let snapshot = loro_doc.export(loro::ExportMode::Snapshot)
+        .map_err(|e| format!("Failed to export snapshot: {}", e))?;
+    if let Err(violation) = limits_service::check_snapshot_size(&snapshot, &limits) {
+        return Ok(Err(violation.to_string()));
+    }
+
+    // 3. Store the document with its first stream
+    let mut peer_map: HashMap<u64, String> = HashMap::new();
+    peer_map.insert(loro_doc.peer_id(), "s/colabri-doc".to_string());
+    let blob = serde_cbor::to_vec(&ColabPackage { snapshot, peer_map: peer_map.clone() })
+        .map_err(|e| format!("Failed to serialize ColabPackage: {}", e))?;
+    let state_vv_json = serde_json::to_value(loro_doc.state_vv())
+        .map_err(|e| format!("Failed to serialize state_vv: {}", e))?;
+    let peer_map_json = serde_json::to_value(&peer_map)
+        .map_err(|e| format!("Failed to serialize peer_map: {}", e))?;
+    let json = json_projection_service::project(loro_doc.get_deep_value().to_json_value());
+    let content_sha256 = storage_service::content_hash(&json);
+    db.create_colab_doc(
+        org_id,
+        doc_uuid,
+        &request.name,
+        &request.doc_type,
+        &request.owner,
+        container,
+        request.container_type.as_deref(),
+        json,
+        blob,
+        state_vv_json,
+        peer_map_json,
+        &content_sha256,
+        &request.by_prpl,
+    )
+    .await
+    .map_err(|e| format!("Failed to create document '{}': {}", doc_uuid, e))?;
+    info!("Document '{}' of type {} created by '{}'", doc_uuid, request.doc_type, request.by_prpl);
+    Ok(Ok(()))
+}
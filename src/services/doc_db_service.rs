@@ -1,9 +1,11 @@
 use std::collections::HashMap;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 use loro::LoroDoc;
-use crate::models::{ColabModel, ColabPackage};
+use crate::models::{ColabModel, ColabPackage, StorageTier};
 use crate::db::dbcolab::{self, DocumentStreamRow};
+use crate::doctypes;
+use crate::services::{cold_storage_service, doc_settings_service, doc_type_service, limits_service};
 use crate::ws::docctx::DocContext;
 
 pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Option<u32>) -> Result<Option<(Vec<u8>, DocContext)>, String> {
@@ -40,20 +42,20 @@ pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Opt
             }
         };
         
+        // Load the overrides of the document
+        let settings = doc_settings_service::get_settings(org_id, doc_uuid).await?.unwrap_or_default();
+
         // Iterate over the streams and search for the stream with name "main" and the highest version.
+        // Streams in the cold tier have no content but a pointer to object storage.
         let mut main_stream: Option<&DocumentStreamRow> = None;
-        let mut main_stream_bytes: Option<&Vec<u8>> = None;
         
         // If a version is specified, we look for that specific version of the main stream. If not, we look for the main stream with the highest version.
         let stream_version = match version {
             Some(v) => {
                 for stream in &doc_data.streams {
-                    if stream.name == "main" && stream.version == v {
-                        if let Some(content) = &stream.content {
-                            main_stream_bytes = Some(content);
-                            main_stream = Some(stream);
-                            break;
-                        }
+                    if stream.name == "main" && stream.version == v && has_content(stream) {
+                        main_stream = Some(stream);
+                        break;
                     }
                 }
                 v
@@ -61,12 +63,9 @@ pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Opt
             None => {
                 let mut highest_version: u32 = 0;
                 for stream in &doc_data.streams {
-                    if stream.name == "main" && stream.version > highest_version {
-                        if let Some(content) = &stream.content {
-                            main_stream_bytes = Some(content);
-                            main_stream = Some(stream);
-                            highest_version = stream.version;
-                        }
+                    if stream.name == "main" && stream.version > highest_version && has_content(stream) {
+                        main_stream = Some(stream);
+                        highest_version = stream.version;
                     }
                 }
                 highest_version
@@ -75,9 +74,12 @@ pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Opt
 
 
         // Check if we found content for the highest main stream
-        if main_stream_bytes.is_none() || main_stream.is_none() {
+        if main_stream.is_none() {
             if let Some(ref json_value) = doc_data.json {
                 // We need to generate the loro doc from the json in the statement.
+                // Deprecated: documents are created through POST /api/orgs/{org_id}/docs with their first
+                // stream, this only covers documents the app created before that endpoint existed.
+                warn!("Document '{}' has no stream, importing it from its JSON is deprecated", doc_uuid);
                 
                 // Parse the json as ColabModel
                 let doc_model: ColabModel = match serde_json::from_value(json_value.clone()) {
@@ -89,6 +91,26 @@ pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Opt
                     }
                 };
 
+                // Refuse to import documents of types the organization doesn't register
+                let content_type = json_value.pointer("/properties/contentType").and_then(|t| t.as_str());
+                if let Err(e) = doc_type_service::ensure_registered(org_id, &doc_data.doc_type, content_type).await {
+                    error!("Refusing to import document '{}': {}", doc_uuid.to_string(), e);
+                    return Err(e);
+                }
+
+                // Refuse to import documents with an invalid structure
+                if let Some(Err(e)) = doctypes::get(&doc_data.doc_type).map(|doc_type| doc_type.validate(json_value)) {
+                    error!("Refusing to import document '{}': {}", doc_uuid.to_string(), e);
+                    return Err(e);
+                }
+
+                // Refuse to import documents beyond the limits of the organization
+                let limits = limits_service::with_document_settings(limits_service::get_limits(org_id).await, &settings);
+                if let Err(violation) = limits_service::check_json(json_value, &limits) {
+                    error!("Refusing to import document '{}': {}", doc_uuid.to_string(), violation);
+                    return Err(violation.to_error_string());
+                }
+
                 // Convert ColabModel to LoroDoc
                 let loro_doc: LoroDoc = match crate::models::lorodoc::colab_to_loro_doc(&doc_model) {
                     Some(doc) => doc,
@@ -100,6 +122,10 @@ pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Opt
 
                 // Export the LoroDoc as a byte stream
                 let snapshot = loro_doc.export(loro::ExportMode::Snapshot).unwrap();
+                if let Err(violation) = limits_service::check_snapshot_size(&snapshot, &limits) {
+                    error!("Refusing to import document '{}': {}", doc_uuid.to_string(), violation);
+                    return Err(violation.to_error_string());
+                }
 
                 // Create the peer map with the current peer
                 let mut peer_map: HashMap<u64, String> = HashMap::new();
@@ -143,6 +169,10 @@ pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Opt
                     doc_owner: doc_data.owner.clone(),
                     peer_map: peer_map.clone(),
                     last_updating_peer: Some(loro_doc.peer_id()),
+                    tier: StorageTier::Hot,
+                    settings,
+                    state_only: false,
+                    block_id: None,
                 };
 
                 return Ok(Some((snapshot, context)));
@@ -155,9 +185,25 @@ pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Opt
         }
         // Import the content into the LoroDoc
         else {
+            let stream = main_stream.unwrap();
+
+            // Rehydrate the content from object storage when the stream is in the cold tier
+            let (main_stream_bytes, tier) = match &stream.content {
+                Some(content) => (content.clone(), StorageTier::Hot),
+                None => {
+                    let pointer = stream.pointer.as_deref().unwrap_or_default();
+                    info!("Rehydrating document '{}' version {} from cold storage: {}", doc_uuid.to_string(), stream.version, pointer);
+                    let content = cold_storage_service::fetch(pointer).await.map_err(|e| {
+                        error!("Failed to rehydrate document '{}' from cold storage: {}", doc_uuid.to_string(), e);
+                        format!("Failed to rehydrate document from cold storage: {}", e)
+                    })?;
+                    cold_storage_service::record_read(org_id, stream.id, &content);
+                    (content, StorageTier::Cold)
+                }
+            };
 
             // Deserialize the CBOR formatted "main_stream_bytes" into a ColabPackage
-            let colab_package : ColabPackage = match serde_cbor::from_slice(&main_stream_bytes.unwrap()) {
+            let colab_package : ColabPackage = match serde_cbor::from_slice(&main_stream_bytes) {
                 Ok(pkg) => pkg,
                 Err(e) => {
                     error!("Failed to deserialize ColabPackage for document '{}': {}", doc_uuid.to_string(), e);
@@ -173,14 +219,23 @@ pub async fn fetch_doc_snapshot_from_db(org_id: &str, doc_id: &str, version: Opt
             let context = DocContext {
                 org: org_id.to_string(),
                 doc_id: doc_uuid.clone(),
-                doc_stream_id: main_stream.unwrap().id.clone(),
+                doc_stream_id: stream.id.clone(),
                 doc_version: stream_version,
                 doc_owner: doc_data.owner.clone(),
                 peer_map: peer_map,
                 last_updating_peer: None,
+                tier,
+                settings,
+                state_only: false,
+                block_id: None,
             };
 
-            info!("Successfully loaded document: {} ({} bytes)", doc_uuid.to_string(), main_stream_bytes.unwrap().len());
+            info!("Successfully loaded document: {} ({} bytes)", doc_uuid.to_string(), main_stream_bytes.len());
             return Ok(Some((loro_snapshot, context)));
         }
 }
+
+// Whether a stream has content, either stored inline or in the cold tier
+fn has_content(stream: &DocumentStreamRow) -> bool {
+    stream.content.is_some() || stream.pointer.is_some()
+}
@@ -1,40 +1,110 @@
 use std::sync::Arc;
 use loro_protocol::CrdtType;
-use loro_websocket_server::{HubRegistry, RoomKey};
+use loro_websocket_server::HubRegistry;
 use loro::LoroDoc;
+use crate::services::{hub_service, lazy_block_service, panic_guard_service, room_assignment_service};
 use crate::ws::docctx::DocContext;
-use tracing::{info};
+use tracing::{info, warn};
 
+// Closes the room of an edit when the edit is dropped halfway, e.g. when its request timed out
+struct CloseOnDrop {
+    registry: Arc<HubRegistry<DocContext>>,
+    org_id: String,
+    doc_id: String,
+    force_close: bool,
+    armed: bool,
+}
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        warn!("Edit of document {} in org {} was cancelled, closing its room", self.doc_id, self.org_id);
+        let registry = self.registry.clone();
+        let (org_id, doc_id, force_close) = (self.org_id.clone(), self.doc_id.clone(), self.force_close);
+        tokio::spawn(async move {
+            registry.close_room(&org_id, CrdtType::Loro, &doc_id, force_close).await;
+        });
+    }
+}
 
 // Edit a document by opening it in the Hub, applying the edit_callback, and then making sure to close it
 pub async fn edit_doc(registry: Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, edit_callback: impl FnOnce(&LoroDoc) -> Result<(), String> + Send, force_close: bool) -> Result<(), String> {
+    edit_doc_as(registry, org_id, doc_id, edit_callback, Vec::new(), force_close).await
+}
+
+// Same as edit_doc, but also registers the given peers in the peer_map.
+// Used when the edit imports changes that were authored by someone else (e.g. an accepted suggestion).
+pub async fn edit_doc_as(registry: Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, edit_callback: impl FnOnce(&LoroDoc) -> Result<(), String> + Send, peers: Vec<(u64, String)>, force_close: bool) -> Result<(), String> {
+
+    // Rooms are only opened on the pod owning them, block rooms live with their sheet
+    room_assignment_service::ensure_local(lazy_block_service::parse_room(doc_id).0)?;
+
+    let mut guard = CloseOnDrop {
+        registry: registry.clone(),
+        org_id: org_id.to_string(),
+        doc_id: doc_id.to_string(),
+        force_close,
+        armed: true,
+    };
+
+    // Readers of the document wait until the edit and the peer map agree again
+    let _doc_write = hub_service::write_doc(org_id, doc_id).await;
 
-    // Do the edit
-    let edit_result = registry.edit_loro_doc(org_id, doc_id, edit_callback, Some(true)).await;
+    // Do the edit, a panicking edit fails instead of unwinding through the hub
+    let (org, room) = (org_id.to_string(), doc_id.to_string());
+    let guarded_callback = move |doc: &LoroDoc| {
+        panic_guard_service::run_sync("edit_doc", &org, &room, || edit_callback(doc)).unwrap_or_else(|panic| Err(panic.to_string()))
+    };
+    let edit_result = registry.edit_loro_doc(org_id, doc_id, guarded_callback, Some(true)).await;
     let peer_id = match edit_result {
         Ok(peer_id) => peer_id,
-        Err(e) => return Err(format!("Failed to edit document: {}", e)),
+        Err(e) => {
+            guard.armed = false;
+            return Err(format!("Failed to edit document: {}", e));
+        }
     };
     info!("Edited document {} in org {}, peer_id: {}", doc_id, org_id, peer_id);
 
     // Add the peer_id to the DocContext's peer_map with a value of "colabri-doc" to indicate that this edit was made by the colabri-doc service.
     // This way, when we look at the peer_map in the future, we can see which edits were made by the service and which were made by real users.
-    {
-        let hubs = registry.hubs().lock().await;
-        if let Some(hub) = hubs.get(org_id) {
-            let mut h = hub.lock().await;
-            if let Some(doc_state) = h.docs.get_mut(&RoomKey { crdt: CrdtType::Loro, room: doc_id.to_string() }) {
-                if let Some(ctx) = doc_state.ctx.as_mut() {
-                    ctx.peer_map.insert(peer_id, "s/colabri-doc".to_string());
-                }
-            }
+    hub_service::update_doc_ctx(&registry, org_id, doc_id, |ctx| {
+        ctx.peer_map.insert(peer_id, "s/colabri-doc".to_string());
+        for (peer, prpl) in &peers {
+            ctx.peer_map.entry(*peer).or_insert_with(|| prpl.clone());
         }
-    }
+    }).await;
     info!("Updated the peer map for document {} in org {}, peer_id: {}, prpl: {}", doc_id, org_id, peer_id, "s/colabri-doc");
 
     // Close the room.
     registry.close_room(&org_id,  CrdtType::Loro, &doc_id, force_close).await;
+    guard.armed = false;
     info!("Closed room for document {} in org {}, force_close: {}", doc_id, org_id, force_close);
     
     return Ok(());
-}
\ No newline at end of file
+}
+
+// Edit a document that isn't open in a room, e.g. while its room is loading.
+// The edit is a single commit of the service, a failing edit leaves the snapshot untouched.
+// Returns the edited snapshot, or None when the edit changed nothing.
+pub fn edit_snapshot(snapshot: &[u8], ctx: &mut DocContext, edit_callback: impl FnOnce(&LoroDoc) -> Result<(), String>) -> Result<Option<Vec<u8>>, String> {
+    let doc = LoroDoc::new();
+    doc.import(snapshot).map_err(|e| format!("Failed to import snapshot of document {}: {}", ctx.doc_id, e))?;
+    let before = doc.oplog_vv();
+
+    let (org, room) = (ctx.org.clone(), ctx.doc_id.to_string());
+    panic_guard_service::run_sync("edit_snapshot", &org, &room, || edit_callback(&doc)).unwrap_or_else(|panic| Err(panic.to_string()))?;
+    doc.commit();
+    if doc.oplog_vv() == before {
+        return Ok(None);
+    }
+
+    // The edit is unsaved, attribute it to the service so the next save picks it up
+    ctx.peer_map.insert(doc.peer_id(), "s/colabri-doc".to_string());
+    ctx.last_updating_peer = Some(doc.peer_id());
+    let edited = doc.export(loro::ExportMode::Snapshot)
+        .map_err(|e| format!("Failed to export edited document {}: {}", ctx.doc_id, e))?;
+    info!("Edited unopened document {} in org {}, peer_id: {}", ctx.doc_id, ctx.org, doc.peer_id());
+    Ok(Some(edited))
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use axum::http::StatusCode;
+use loro::LoroDoc;
+use loro_websocket_server::HubRegistry;
+use tracing::error;
+use crate::models::{api_error, ApiError};
+use crate::services::{hub_service, quarantine_service};
+use crate::ws::docctx::DocContext;
+
+// Get an independent copy of the latest state of a document.
+// The live room in the Hub is preferred, otherwise the document is loaded from the database.
+pub async fn load_loro_doc(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str) -> Result<Option<(LoroDoc, DocContext)>, String> {
+
+    // 1. Check if the document is currently open in the Hub
+    if let Some(found) = get_open_loro_doc(registry, org_id, doc_id).await {
+        return Ok(Some(found));
+    }
+
+    // 2. If not, load the latest version from the database
+    let (snapshot, ctx) = match quarantine_service::fetch_doc_snapshot(org_id, doc_id, None).await? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+
+    // Reconstruct LoroDoc from snapshot
+    let loro_doc = LoroDoc::new();
+    loro_doc.import(&snapshot).map_err(|e| {
+        error!("Failed to import snapshot for document '{}': {}", doc_id, e);
+        format!("Failed to import snapshot for document '{}': {}", doc_id, e)
+    })?;
+
+    Ok(Some((loro_doc, ctx)))
+}
+
+// Get a fork of a document that is currently open in the Hub, together with its context.
+// Forking makes sure callers can checkout or mutate the copy without touching the live room.
+// Rooms loaded from a state-only snapshot lack the history, those are loaded from the database instead.
+pub async fn get_open_loro_doc(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str) -> Option<(LoroDoc, DocContext)> {
+    let (loro_doc, ctx) = hub_service::get_open_doc_handle(registry, org_id, doc_id).await?;
+    if ctx.state_only {
+        return None;
+    }
+    Some((loro_doc.fork(), ctx))
+}
+
+// Same as load_loro_doc, but with the errors already mapped to API errors (404, 422 when quarantined or 500)
+pub async fn load_loro_doc_or_error(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str) -> Result<(LoroDoc, DocContext), ApiError> {
+    match load_loro_doc(registry, org_id, doc_id).await {
+        Ok(Some(res)) => Ok(res),
+        Ok(None) => Err(api_error(
+            StatusCode::NOT_FOUND,
+            format!("Document '{}' not found in organization '{}'", doc_id, org_id),
+        )),
+        Err(e) => {
+            error!("Error loading document '{}': {}", doc_id, e);
+            Err(api_error(
+                quarantine_service::load_error_status(&e),
+                format!("Error loading document '{}': {}", doc_id, e),
+            ))
+        }
+    }
+}
+
+// Get an independent copy of a specific version of a document.
+// The live room is used when it is still on that version, otherwise the version is loaded from the database.
+pub async fn load_loro_doc_version(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, version: u32) -> Result<Option<(LoroDoc, DocContext)>, String> {
+    if let Some((loro_doc, ctx)) = get_open_loro_doc(registry, org_id, doc_id).await {
+        if ctx.doc_version == version {
+            return Ok(Some((loro_doc, ctx)));
+        }
+    }
+
+    let (snapshot, ctx) = match quarantine_service::fetch_doc_snapshot(org_id, doc_id, Some(version)).await? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+    let loro_doc = LoroDoc::new();
+    loro_doc.import(&snapshot).map_err(|e| {
+        error!("Failed to import snapshot for document '{}' version {}: {}", doc_id, version, e);
+        format!("Failed to import snapshot for document '{}' version {}: {}", doc_id, version, e)
+    })?;
+    Ok(Some((loro_doc, ctx)))
+}
+
+// Load a specific version of a document, or the latest one when no version is given,
+// with the errors already mapped to API errors (404, 422 when quarantined or 500)
+pub async fn load_loro_doc_version_or_error(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, version: Option<u32>) -> Result<(LoroDoc, DocContext), ApiError> {
+    let version = match version {
+        Some(version) => version,
+        None => return load_loro_doc_or_error(registry, org_id, doc_id).await,
+    };
+    match load_loro_doc_version(registry, org_id, doc_id, version).await {
+        Ok(Some(res)) => Ok(res),
+        Ok(None) => Err(api_error(
+            StatusCode::NOT_FOUND,
+            format!("Document '{}' with version {} not found in organization '{}'", doc_id, version, org_id),
+        )),
+        Err(e) => {
+            error!("Error loading document '{}' with version {}: {}", doc_id, version, e);
+            Err(api_error(
+                quarantine_service::load_error_status(&e),
+                format!("Error loading document '{}' with version {}: {}", doc_id, version, e),
+            ))
+        }
+    }
+}
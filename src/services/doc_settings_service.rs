@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use tracing::error;
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::models::DocumentSettings;
+use crate::services::hub_service;
+use crate::ws::docctx::DocContext;
+
+// Get the settings of a document, the defaults when it has none.
+// Invalid settings are logged and ignored, settings should never make documents unavailable.
+pub async fn get_settings(org_id: &str, doc_uuid: Uuid) -> Result<Option<DocumentSettings>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    match db.get_document_settings(org_id, doc_uuid).await {
+        Ok(Some(Some(value))) => Ok(Some(parse(doc_uuid, value))),
+        Ok(Some(None)) => Ok(Some(DocumentSettings::default())),
+        Ok(None) => Ok(None),
+        Err(e) => Err(format!("Failed to load settings of document '{}': {}", doc_uuid, e)),
+    }
+}
+
+fn parse(doc_uuid: Uuid, value: Value) -> DocumentSettings {
+    serde_json::from_value(value).unwrap_or_else(|e| {
+        error!("Invalid settings for document '{}': {}", doc_uuid, e);
+        DocumentSettings::default()
+    })
+}
+
+// Apply a JSON merge patch to the settings of a document and push them to its live room.
+// Returns the new settings, or None when the document doesn't exist.
+pub async fn patch_settings(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, patch: &Value, by_prpl: &str) -> Result<Option<DocumentSettings>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    // 1. Merge the patch into the stored settings
+    let mut value = match db.get_document_settings(org_id, doc_uuid).await {
+        Ok(Some(value)) => value.unwrap_or_else(|| Value::Object(Default::default())),
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(format!("Failed to load settings of document '{}': {}", doc_uuid, e)),
+    };
+    merge_patch(&mut value, patch);
+
+    // 2. Only store settings that make sense
+    let settings: DocumentSettings = serde_json::from_value(value.clone())
+        .map_err(|e| format!("Invalid settings: {}", e))?;
+    if let Some(persistence) = settings.save_policy.as_ref().and_then(|policy| policy.persistence.as_deref()) {
+        if persistence != "snapshot" && persistence != "updates" {
+            return Err(format!("Invalid settings: unknown persistence '{}'", persistence));
+        }
+    }
+
+    // 3. Store them and push them to the live room
+    let stored = if value.as_object().is_some_and(|map| map.is_empty()) { None } else { Some(value) };
+    match db.set_document_settings(org_id, doc_uuid, stored, by_prpl).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(None),
+        Err(e) => return Err(format!("Failed to store settings of document '{}': {}", doc_uuid, e)),
+    }
+    let live = settings.clone();
+    hub_service::update_doc_ctx(registry, org_id, &doc_uuid.to_string(), move |ctx| ctx.settings = live).await;
+    Ok(Some(settings))
+}
+
+// RFC 7396 JSON merge patch, null removes a key
+fn merge_patch(target: &mut Value, patch: &Value) {
+    match patch {
+        Value::Object(patch_map) => {
+            if !target.is_object() {
+                *target = Value::Object(Default::default());
+            }
+            let target_map = target.as_object_mut().unwrap();
+            for (key, patch_value) in patch_map {
+                if patch_value.is_null() {
+                    target_map.remove(key);
+                } else {
+                    merge_patch(target_map.entry(key.clone()).or_insert(Value::Null), patch_value);
+                }
+            }
+        }
+        _ => *target = patch.clone(),
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use loro::LoroDoc;
+use moka::sync::Cache;
+use tracing::{error, warn};
+use crate::db::dbcolab::{self, OrgDocumentTypesRow};
+use crate::doctypes;
+use crate::models::lorodoc::get_string;
+
+// Document types registered per organization.
+// Every org knows the document types of the doctypes registry. Orgs in strict mode also register the content
+// types of every document type, documents of other types or content types can't be loaded, imported
+// or saved. Without strict mode unknown document types are only logged.
+
+// How long the registered types of an org are cached before they are read from the database again
+const TYPES_CACHE_TTL: Duration = Duration::from_secs(60);
+
+static TYPES_CACHE: OnceLock<Cache<String, TypeRegistry>> = OnceLock::new();
+
+/// The document types registered by an org
+#[derive(Debug, Clone, Default)]
+pub struct TypeRegistry {
+    pub strict: bool,
+    pub types: HashMap<String, Vec<String>>,
+}
+
+fn get_cache() -> &'static Cache<String, TypeRegistry> {
+    TYPES_CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(TYPES_CACHE_TTL)
+            .build()
+    })
+}
+
+// The content types per document type of a stored registration
+pub fn types_of(row: &OrgDocumentTypesRow) -> HashMap<String, Vec<String>> {
+    serde_json::from_value(row.types.clone()).unwrap_or_else(|e| {
+        error!("Invalid document types registration: {}", e);
+        HashMap::new()
+    })
+}
+
+// The registry of an org. When it can't be loaded the org isn't strict, the registry should never
+// make documents unavailable by itself.
+pub async fn get_registry(org_id: &str) -> TypeRegistry {
+    if let Some(registry) = get_cache().get(org_id) {
+        return registry;
+    }
+
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return TypeRegistry::default(),
+    };
+    let registry = match db.get_org_document_types(org_id).await {
+        Ok(Some(row)) => TypeRegistry { strict: row.strict, types: types_of(&row) },
+        Ok(None) => TypeRegistry::default(),
+        Err(e) => {
+            error!("Failed to load document types of organization '{}': {}", org_id, e);
+            return TypeRegistry::default();
+        }
+    };
+    get_cache().insert(org_id.to_string(), registry.clone());
+    registry
+}
+
+impl TypeRegistry {
+    // Check a document type and its content type, None when the content type isn't known yet
+    pub fn check(&self, doc_type: &str, content_type: Option<&str>) -> Result<(), String> {
+        let known = doctypes::get(doc_type).is_some();
+        if !self.strict {
+            if !known {
+                warn!("Document of unknown type '{}'", doc_type);
+            }
+            return Ok(());
+        }
+        if !known {
+            return Err(format!("Unknown document type '{}'", doc_type));
+        }
+        let registered = self.types.get(doc_type).map(Vec::as_slice).unwrap_or_default();
+        match content_type {
+            Some(content_type) if !registered.iter().any(|registered| registered == content_type) => Err(format!(
+                "Content type '{}' isn't registered for documents of type '{}'", content_type, doc_type
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Reject a document type and content type the org doesn't register
+pub async fn ensure_registered(org_id: &str, doc_type: &str, content_type: Option<&str>) -> Result<(), String> {
+    get_registry(org_id).await.check(doc_type, content_type)
+}
+
+// Reject a document whose properties hold a type or content type the org doesn't register
+pub async fn ensure_registered_doc(org_id: &str, doc: &LoroDoc) -> Result<(), String> {
+    let properties = doc.get_map("properties");
+    let doc_type = get_string(&properties, "type").unwrap_or_default();
+    let content_type = get_string(&properties, "contentType").unwrap_or_default();
+    ensure_registered(org_id, &doc_type, Some(&content_type)).await
+}
+
+// Same as ensure_registered_doc for a snapshot, only imported when the org is strict
+pub async fn ensure_registered_snapshot(org_id: &str, snapshot: &[u8]) -> Result<(), String> {
+    if !get_registry(org_id).await.strict {
+        return Ok(());
+    }
+    let doc = LoroDoc::new();
+    doc.import(snapshot).map_err(|e| format!("Failed to import snapshot to check its type: {}", e))?;
+    ensure_registered_doc(org_id, &doc).await
+}
+
+pub async fn get(org_id: &str) -> Result<Option<OrgDocumentTypesRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_org_document_types(org_id)
+        .await
+        .map_err(|e| format!("Failed to load document types of organization '{}': {}", org_id, e))
+}
+
+// Replace the registered types of an org, only the document types of this service can be registered
+pub async fn set(org_id: &str, strict: bool, types: &HashMap<String, Vec<String>>, by_prpl: &str) -> Result<Result<OrgDocumentTypesRow, String>, String> {
+    if let Some(doc_type) = types.keys().find(|doc_type| doctypes::get(doc_type).is_none()) {
+        return Ok(Err(format!("Unknown document type '{}', known are: {}", doc_type, doctypes::names().join(", "))));
+    }
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let types_json = serde_json::to_value(types).map_err(|e| format!("Failed to serialize document types: {}", e))?;
+    let row = db.set_org_document_types(org_id, strict, types_json, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to set document types of organization '{}': {}", org_id, e))?;
+    get_cache().invalidate(org_id);
+    Ok(Ok(row))
+}
@@ -0,0 +1,164 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use loro_protocol::CrdtType;
+use loro_websocket_server::HubRegistry;
+use tracing::{error, info};
+use crate::services::{hub_service, save_policy_service};
+use crate::ws::{docctx::DocContext, wscolab};
+
+/// Error of a document load refused while draining
+pub const DRAINING_ERROR: &str = "Pod is draining, no new rooms are accepted";
+
+// How often a shutdown checks whether the drain finished
+const DRAINED_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static DRAINING: AtomicBool = AtomicBool::new(false);
+static PROGRESS: OnceLock<Mutex<DrainProgress>> = OnceLock::new();
+
+/// Phase of a drain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrainState {
+    Idle,
+    Draining,
+    Drained,
+}
+
+impl DrainState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DrainState::Idle => "idle",
+            DrainState::Draining => "draining",
+            DrainState::Drained => "drained",
+        }
+    }
+}
+
+/// Progress of a drain
+#[derive(Debug, Clone)]
+pub struct DrainProgress {
+    pub state: DrainState,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub deferred_saved: usize,
+    pub rooms_total: usize,
+    pub rooms_saved: usize,
+    pub rooms_closed: usize,
+    pub errors: Vec<String>,
+}
+
+fn get_progress() -> &'static Mutex<DrainProgress> {
+    PROGRESS.get_or_init(|| Mutex::new(DrainProgress {
+        state: DrainState::Idle,
+        started_at: None,
+        finished_at: None,
+        deferred_saved: 0,
+        rooms_total: 0,
+        rooms_saved: 0,
+        rooms_closed: 0,
+        errors: Vec::new(),
+    }))
+}
+
+// Whether the pod is draining, new rooms are refused while it is
+pub fn is_draining() -> bool {
+    DRAINING.load(Ordering::Relaxed)
+}
+
+pub fn progress() -> DrainProgress {
+    get_progress().lock().unwrap().clone()
+}
+
+// Wait for the drain to finish, false when it didn't within the timeout
+pub async fn wait_drained(timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, async {
+        while progress().state != DrainState::Drained {
+            tokio::time::sleep(DRAINED_POLL_INTERVAL).await;
+        }
+    })
+    .await
+    .is_ok()
+}
+
+// Start draining the pod, returns false if it is already draining or drained.
+// There is no way back, the pod is expected to be rolled once it is drained.
+pub fn start(registry: Arc<HubRegistry<DocContext>>) -> bool {
+    if DRAINING.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    {
+        let mut progress = get_progress().lock().unwrap();
+        progress.state = DrainState::Draining;
+        progress.started_at = Some(Utc::now());
+    }
+    info!("Draining pod, no new rooms are accepted");
+    tokio::spawn(async move {
+        run(&registry).await;
+    });
+    true
+}
+
+async fn run(registry: &Arc<HubRegistry<DocContext>>) {
+
+    // 1. Persist the saves that were deferred by the save policies
+    for save in save_policy_service::take_all() {
+        let room = save.room.clone();
+        match wscolab::save_deferred_document(save).await {
+            Ok(()) => get_progress().lock().unwrap().deferred_saved += 1,
+            Err(e) => record_error(format!("Failed to persist deferred save of '{}': {}", room, e)),
+        }
+    }
+
+    // 2. Save the dirty documents and close the rooms nobody is connected to
+    let rooms: Vec<_> = hub_service::snapshot_rooms(registry)
+        .await
+        .into_iter()
+        .filter(|room| room.is_doc)
+        .collect();
+    get_progress().lock().unwrap().rooms_total = rooms.len();
+    for room in rooms {
+        if room.subscribers == 0 {
+            // Closing a room saves it when it is dirty
+            registry.close_room(&room.org, CrdtType::Loro, &room.room, false).await;
+            let mut progress = get_progress().lock().unwrap();
+            progress.rooms_closed += 1;
+            if room.dirty {
+                progress.rooms_saved += 1;
+            }
+            continue;
+        }
+        if !room.dirty {
+            continue;
+        }
+        let (loro_doc, ctx) = match hub_service::get_open_doc_handle(registry, &room.org, &room.room).await {
+            Some(open) => open,
+            None => continue,
+        };
+        let snapshot = match loro_doc.export(loro::ExportMode::Snapshot) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                record_error(format!("Failed to export '{}': {}", room.room, e));
+                continue;
+            }
+        };
+        let save = save_policy_service::DeferredSave { room: room.room.clone(), snapshot, ctx };
+        match wscolab::save_deferred_document(save).await {
+            Ok(()) => get_progress().lock().unwrap().rooms_saved += 1,
+            Err(e) => record_error(format!("Failed to save '{}': {}", room.room, e)),
+        }
+    }
+
+    let mut progress = get_progress().lock().unwrap();
+    progress.state = DrainState::Drained;
+    progress.finished_at = Some(Utc::now());
+    info!(
+        "Pod drained: {} deferred saves persisted, {} of {} rooms saved, {} closed, {} errors",
+        progress.deferred_saved, progress.rooms_saved, progress.rooms_total, progress.rooms_closed, progress.errors.len()
+    );
+}
+
+fn record_error(error: String) {
+    error!("{}", error);
+    get_progress().lock().unwrap().errors.push(error);
+}
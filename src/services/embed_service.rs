@@ -0,0 +1,92 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+use moka::sync::Cache;
+use tracing::error;
+use crate::db::dbcolab::{self, OrgEmbedSettingsRow};
+
+// How long the embed origins of an org are cached before they are read from the database again
+const ORIGINS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+static ORIGINS_CACHE: OnceLock<Cache<String, Vec<String>>> = OnceLock::new();
+
+fn get_cache() -> &'static Cache<String, Vec<String>> {
+    ORIGINS_CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(ORIGINS_CACHE_TTL)
+            .build()
+    })
+}
+
+// The origins allowed to frame the embedded views of an organization.
+// When they can't be loaded nothing is allowed.
+pub async fn frame_ancestors(org_id: &str) -> Vec<String> {
+    if let Some(origins) = get_cache().get(org_id) {
+        return origins;
+    }
+
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return Vec::new(),
+    };
+    let origins = match db.get_org_embed_settings(org_id).await {
+        Ok(row) => row.map(|row| row.frame_ancestors).unwrap_or_default(),
+        Err(e) => {
+            error!("Failed to load embed settings of organization '{}': {}", org_id, e);
+            return Vec::new();
+        }
+    };
+    get_cache().insert(org_id.to_string(), origins.clone());
+    origins
+}
+
+pub async fn get(org_id: &str) -> Result<Option<OrgEmbedSettingsRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_org_embed_settings(org_id)
+        .await
+        .map_err(|e| format!("Failed to load embed settings of organization '{}': {}", org_id, e))
+}
+
+// Replace the allowed origins of an organization, after normalizing them
+pub async fn set(org_id: &str, origins: &[String], by_prpl: &str) -> Result<OrgEmbedSettingsRow, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let mut normalized: Vec<String> = Vec::new();
+    for origin in origins {
+        let origin = normalize_origin(origin)?;
+        if !normalized.contains(&origin) {
+            normalized.push(origin);
+        }
+    }
+    let row = db.set_org_embed_frame_ancestors(org_id, &normalized, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to set embed settings of organization '{}': {}", org_id, e))?;
+    get_cache().invalidate(org_id);
+    Ok(row)
+}
+
+// An https origin without path, optionally with a leading wildcard label, e.g. https://*.example.com
+pub fn normalize_origin(origin: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid embed origin '{}', expected e.g. https://app.example.com", origin);
+    let origin = origin.trim().trim_end_matches('/').to_lowercase();
+    let host_port = origin.strip_prefix("https://").ok_or_else(invalid)?;
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (host_port, None),
+    };
+    let host = host.strip_prefix("*.").unwrap_or(host);
+    let valid_host = !host.is_empty()
+        && host.split('.').all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+    let valid_port = port.map_or(true, |port| !port.is_empty() && port.len() <= 5 && port.chars().all(|c| c.is_ascii_digit()));
+    if !valid_host || !valid_port {
+        return Err(invalid());
+    }
+    Ok(origin)
+}
+
+// The Content-Security-Policy of a view, only embedded views may be framed by the allowed origins
+pub fn content_security_policy(frame_ancestors: &[String]) -> String {
+    if frame_ancestors.is_empty() {
+        return "frame-ancestors 'none'".to_string();
+    }
+    format!("frame-ancestors {}", frame_ancestors.join(" "))
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Write};
+use axum::{http::{header, StatusCode}, response::{IntoResponse, Response}};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use loro::{LoroDoc, ToJson};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+use crate::config;
+use crate::db::dbcolab::DocumentAccessRows;
+use crate::models::ColabStatementElement;
+use crate::models::lorodoc::{get_child_map, get_doc_type};
+use crate::services::{acl_service, approval_round_service, citation_service};
+use crate::ws::docctx::DocContext;
+
+/// A file of an evidence bundle
+pub struct EvidenceFile {
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn json_file(name: &str, value: &Value) -> Result<EvidenceFile, String> {
+    let content = serde_json::to_vec_pretty(value)
+        .map_err(|e| format!("Failed to serialize '{}': {}", name, e))?;
+    Ok(EvidenceFile { name: name.to_string(), content })
+}
+
+// Add the principal of the approving user next to every user id in an approvals value
+fn with_identities(org_id: &str, value: &mut Value) {
+    match value {
+        Value::Object(obj) => {
+            if let Some(user) = obj.get("user").and_then(|u| u.as_str()).map(|u| u.to_string()) {
+                obj.entry("prpl").or_insert_with(|| Value::String(format!("{}/u/{}", org_id, user)));
+            }
+            for child in obj.values_mut() {
+                with_identities(org_id, child);
+            }
+        }
+        Value::Array(arr) => {
+            for child in arr {
+                with_identities(org_id, child);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Collect the evidence of a document version: the content, the approval chain and the ACLs.
+// The files are sorted by name.
+pub fn collect_files(org_id: &str, doc: &LoroDoc, ctx: &DocContext, access: &DocumentAccessRows) -> Result<Vec<EvidenceFile>, String> {
+    let mut files = Vec::new();
+
+    // 1. The full content of the version
+    files.push(json_file("content/document.json", &doc.get_deep_value().to_json_value())?);
+
+    // 2. The rendered languages of a statement
+    if get_doc_type(doc).as_deref() == Some("colab-statement") {
+        if let Value::Object(langs) = doc.get_map("content").get_deep_value().to_json_value() {
+            for (lang, value) in langs {
+                let element: ColabStatementElement = serde_json::from_value(value)
+                    .map_err(|e| format!("Failed to parse language '{}': {}", lang, e))?;
+                files.push(EvidenceFile {
+                    name: format!("content/{}.html", lang),
+                    content: citation_service::render_html(&element.text_element).into_bytes(),
+                });
+                files.push(EvidenceFile {
+                    name: format!("content/{}.txt", lang),
+                    content: citation_service::render_text(&element.text_element).into_bytes(),
+                });
+            }
+        }
+    }
+
+    // 3. The approval chain: the approvals per block or language, the approval rounds and who edited the document
+    let scopes = acl_service::collect_acl_scopes(doc)?;
+    let mut approvals = Vec::new();
+    for scope in &scopes {
+        let approvals_map = match scope.map.as_ref().and_then(|map| get_child_map(map, "approvals")) {
+            Some(approvals_map) => approvals_map,
+            None => continue,
+        };
+        let mut value = approvals_map.get_deep_value().to_json_value();
+        with_identities(org_id, &mut value);
+        approvals.push(json!({
+            "path": scope.path,
+            "level": scope.level.to_string(),
+            "approvals": value,
+        }));
+    }
+    let editors: BTreeMap<String, String> = ctx.peer_map
+        .iter()
+        .map(|(peer, prpl)| (peer.to_string(), prpl.clone()))
+        .collect();
+    files.push(json_file("approvals.json", &json!({
+        "approvals": approvals,
+        "approvalRounds": approval_round_service::read_rounds(doc),
+        "editors": editors,
+    }))?);
+
+    // 4. The ACLs inside the document at this version, and the database ACLs as they are now
+    let document_acls: Vec<Value> = scopes
+        .iter()
+        .filter(|scope| scope.acls.is_some())
+        .map(|scope| json!({
+            "path": scope.path,
+            "level": scope.level.to_string(),
+            "acls": scope.entries(),
+        }))
+        .collect();
+    files.push(json_file("acls.json", &json!({
+        "document": document_acls,
+        "database": access,
+    }))?);
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+// The manifest lists the hash of every file. The bundle hash is the SHA-256 of the lines
+// "<sha256>  <name>\n" of all files (sha256sum format), and is signed when a signing key is configured.
+pub fn build_manifest(org_id: &str, doc_id: &str, doc: &LoroDoc, ctx: &DocContext, by_prpl: &str, files: &[EvidenceFile]) -> Value {
+    let mut checksums = String::new();
+    let mut entries = Vec::with_capacity(files.len());
+    for file in files {
+        let hash = sha256_hex(&file.content);
+        checksums.push_str(&format!("{}  {}\n", hash, file.name));
+        entries.push(json!({
+            "name": file.name,
+            "sha256": hash,
+            "size": file.content.len(),
+        }));
+    }
+    let bundle_hash = sha256_hex(checksums.as_bytes());
+    let signature = sign(&bundle_hash).map(|value| json!({
+        "algorithm": "HMAC-SHA256",
+        "value": value,
+    }));
+
+    json!({
+        "org": org_id,
+        "docId": doc_id,
+        "version": ctx.doc_version,
+        "versionV": serde_json::to_value(doc.state_vv()).unwrap_or(Value::Null),
+        "generatedAt": Utc::now(),
+        "generatedBy": by_prpl,
+        "files": entries,
+        "hash": bundle_hash,
+        "signature": signature,
+    })
+}
+
+// Sign a hash with the evidence signing key, None when no key is configured
+fn sign(hash: &str) -> Option<String> {
+    let key = config::get_config().evidence_signing_key.as_ref()?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).ok()?;
+    mac.update(hash.as_bytes());
+    Some(mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// Pack the files and the manifest into a zip archive
+pub fn zip_bundle(files: &[EvidenceFile], manifest: &Value) -> Result<Vec<u8>, String> {
+    let manifest = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    let entries = files
+        .iter()
+        .map(|f| (f.name.as_str(), f.content.as_slice()))
+        .chain(std::iter::once(("manifest.json", manifest.as_slice())));
+    for (name, content) in entries {
+        zip.start_file(name, options)
+            .map_err(|e| format!("Failed to add '{}' to bundle: {}", name, e))?;
+        zip.write_all(content)
+            .map_err(|e| format!("Failed to add '{}' to bundle: {}", name, e))?;
+    }
+    let cursor = zip.finish().map_err(|e| format!("Failed to finish bundle: {}", e))?;
+    Ok(cursor.into_inner())
+}
+
+// Wrap a zip archive in a downloadable response
+pub fn zip_response(filename: &str, bytes: Vec<u8>) -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        bytes,
+    )
+        .into_response()
+}
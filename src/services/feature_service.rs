@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Duration;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use moka::sync::Cache;
+use tracing::error;
+use crate::config;
+use crate::db::dbcolab;
+use crate::models::{api_error, ApiError};
+
+// How long the flags of an org are cached before they are read from the database again
+const FLAGS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+static FLAGS_CACHE: OnceLock<Cache<String, HashMap<String, bool>>> = OnceLock::new();
+
+/// A capability that can be enabled per organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Comments,
+    Suggestions,
+    Translation,
+    Publishing,
+    PolicyScanning,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 5] = [Feature::Comments, Feature::Suggestions, Feature::Translation, Feature::Publishing, Feature::PolicyScanning];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Feature::Comments => "comments",
+            Feature::Suggestions => "suggestions",
+            Feature::Translation => "translation",
+            Feature::Publishing => "publishing",
+            Feature::PolicyScanning => "policy-scanning",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Feature> {
+        Feature::ALL.into_iter().find(|feature| feature.as_str() == name)
+    }
+
+    // Whether the feature is enabled for orgs without a flag, from the comma separated FEATURES_ENABLED_BY_DEFAULT
+    pub fn default_enabled(&self) -> bool {
+        config::get_config()
+            .features_enabled_by_default
+            .as_deref()
+            .unwrap_or("comments,suggestions,publishing")
+            .split(',')
+            .any(|name| name.trim() == self.as_str())
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The state of a feature for an organization
+pub struct FeatureState {
+    pub feature: Feature,
+    pub enabled: bool,
+    pub default_enabled: bool,
+    // Only set when the org has a flag for the feature
+    pub updated_at: Option<DateTime<Utc>>,
+    pub updated_by: Option<String>,
+}
+
+fn get_cache() -> &'static Cache<String, HashMap<String, bool>> {
+    FLAGS_CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(FLAGS_CACHE_TTL)
+            .build()
+    })
+}
+
+// Whether a feature is enabled for an organization.
+// When the flags can't be loaded the defaults apply.
+pub async fn is_enabled(org_id: &str, feature: Feature) -> bool {
+    if let Some(flags) = get_cache().get(org_id) {
+        return flags.get(feature.as_str()).copied().unwrap_or_else(|| feature.default_enabled());
+    }
+
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return feature.default_enabled(),
+    };
+    let flags: HashMap<String, bool> = match db.get_org_feature_flags(org_id).await {
+        Ok(rows) => rows.into_iter().map(|row| (row.feature, row.enabled)).collect(),
+        Err(e) => {
+            error!("Failed to load feature flags of organization '{}': {}", org_id, e);
+            return feature.default_enabled();
+        }
+    };
+    let enabled = flags.get(feature.as_str()).copied().unwrap_or_else(|| feature.default_enabled());
+    get_cache().insert(org_id.to_string(), flags);
+    enabled
+}
+
+// Refuse a request when a feature is not enabled for the organization
+pub async fn ensure_enabled(org_id: &str, feature: Feature) -> Result<(), ApiError> {
+    if is_enabled(org_id, feature).await {
+        return Ok(());
+    }
+    Err(api_error(
+        StatusCode::FORBIDDEN,
+        format!("Feature '{}' is not enabled for organization '{}'", feature, org_id),
+    ))
+}
+
+// The state of every feature for an organization, as read from the database
+pub async fn list(org_id: &str) -> Result<Vec<FeatureState>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let rows = db.get_org_feature_flags(org_id).await
+        .map_err(|e| format!("Failed to load feature flags of organization '{}': {}", org_id, e))?;
+
+    Ok(Feature::ALL
+        .into_iter()
+        .map(|feature| {
+            let row = rows.iter().find(|row| row.feature == feature.as_str());
+            FeatureState {
+                feature,
+                enabled: row.map(|row| row.enabled).unwrap_or_else(|| feature.default_enabled()),
+                default_enabled: feature.default_enabled(),
+                updated_at: row.map(|row| row.updated_at),
+                updated_by: row.map(|row| row.updated_by.clone()),
+            }
+        })
+        .collect())
+}
+
+// Enable or disable a feature for an organization, None resets it to the default
+pub async fn set(org_id: &str, feature: Feature, enabled: Option<bool>, by_prpl: &str) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.set_org_feature_flag(org_id, feature.as_str(), enabled, by_prpl).await
+        .map_err(|e| format!("Failed to set feature '{}' of organization '{}': {}", feature, org_id, e))?;
+    get_cache().invalidate(org_id);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use loro::ToJson;
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use tracing::warn;
+use crate::models::ColabStatementElement;
+use crate::services::{citation_service, doc_load_service, statement_subdoc_service};
+use crate::services::locale_service::Locale;
+use crate::ws::docctx::DocContext;
+
+pub const CSV_HEADER: [&str; 8] = ["row", "source", "statementId", "version", "language", "text", "approvalState", "approvedAt"];
+
+// Flatten the rows of a statement grid into CSV rows, one per statement language.
+// Local statements are identified by their path in the sheet, referenced statements are loaded at their pinned version.
+// Approval dates are formatted with the locale settings of the sheet.
+pub async fn statement_grid_rows(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, doc_version: u32, block_id: &str, block: &Value, locale: &Locale) -> Vec<Vec<String>> {
+    let mut csv_rows = Vec::new();
+    let rows = block.get("rows").and_then(|rows| rows.as_array()).cloned().unwrap_or_default();
+    for (r, row) in rows.iter().enumerate() {
+        let row_type = row.get("type").and_then(|t| t.as_str()).unwrap_or_default();
+        match row_type {
+            "local" => {
+                let statement_id = format!("{}/content/{}/rows/{}/statement", doc_id, block_id, r);
+                let content = row.get("statement").and_then(|statement| statement.get("content"));
+                csv_rows.extend(language_rows(r, "local", &statement_id, doc_version, content, locale));
+            }
+            // Linked statements are composed into the block before exporting
+            statement_subdoc_service::LINKED_ROW => {
+                let statement_id = row.get("subdocId").and_then(|id| id.as_str()).unwrap_or_default();
+                let content = row.get("statement").and_then(|statement| statement.get("content"));
+                csv_rows.extend(language_rows(r, "linked", statement_id, doc_version, content, locale));
+            }
+            _ => {
+                let statement_ref = row.get("statementRef");
+                let ref_doc_id = statement_ref.and_then(|s| s.get("docId")).and_then(|d| d.as_str()).unwrap_or_default().to_string();
+                let ref_version = statement_ref.and_then(|s| s.get("version")).and_then(|v| v.as_u64()).map(|v| v as u32);
+                match load_statement_content(registry, org_id, &ref_doc_id, ref_version).await {
+                    Ok((version, content)) => {
+                        csv_rows.extend(language_rows(r, "ref", &ref_doc_id, version, Some(&content), locale));
+                    }
+                    Err(e) => {
+                        warn!("Failed to resolve statement '{}' in row {} of block '{}' of document '{}': {}", ref_doc_id, r, block_id, doc_id, e);
+                        csv_rows.push(vec![
+                            r.to_string(),
+                            "ref".to_string(),
+                            ref_doc_id,
+                            ref_version.map(|v| v.to_string()).unwrap_or_default(),
+                            String::new(),
+                            String::new(),
+                            "unresolved".to_string(),
+                            String::new(),
+                        ]);
+                    }
+                }
+            }
+        }
+    }
+    csv_rows
+}
+
+// Load the content of a referenced statement at its pinned version, or the latest one
+async fn load_statement_content(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, version: Option<u32>) -> Result<(u32, Value), String> {
+    let loaded = match version {
+        Some(version) => doc_load_service::load_loro_doc_version(registry, org_id, doc_id, version).await?,
+        None => doc_load_service::load_loro_doc(registry, org_id, doc_id).await?,
+    };
+    let (loro_doc, ctx) = loaded.ok_or_else(|| "Statement not found".to_string())?;
+    Ok((ctx.doc_version, loro_doc.get_map("content").get_deep_value().to_json_value()))
+}
+
+// One CSV row per language of a statement, sorted by language code
+fn language_rows(r: usize, source: &str, statement_id: &str, version: u32, content: Option<&Value>, locale: &Locale) -> Vec<Vec<String>> {
+    let mut csv_rows = Vec::new();
+    let languages = match content.and_then(|c| c.as_object()) {
+        Some(languages) => languages,
+        None => return csv_rows,
+    };
+    let mut lang_codes: Vec<&String> = languages.keys().collect();
+    lang_codes.sort();
+    for lang_code in lang_codes {
+        let element: ColabStatementElement = match serde_json::from_value(languages[lang_code].clone()) {
+            Ok(element) => element,
+            Err(e) => {
+                warn!("Failed to parse language '{}' of statement '{}': {}", lang_code, statement_id, e);
+                continue;
+            }
+        };
+        csv_rows.push(vec![
+            r.to_string(),
+            source.to_string(),
+            statement_id.to_string(),
+            version.to_string(),
+            lang_code.clone(),
+            citation_service::render_text(&element.text_element),
+            citation_service::approval_status(&element.approvals).to_string(),
+            citation_service::approval_date(&element.approvals).map(|date| locale.format_date(&date)).unwrap_or_default(),
+        ]);
+    }
+    csv_rows
+}
@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use loro_websocket_server::protocol::Permission;
+use tracing::{info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::ws::connctx::{self, ClientVersion, ConnCtx};
+use crate::ws::userctx::{self, UserCtx};
+
+// Guest collaboration for development and demos.
+// When enabled, a handshake without a token gets a generated guest user of the organization it
+// connects to. Guests may only join the whitelisted documents, with the configured permission, and
+// are denied every other document. Their principals never come from the app service, the user
+// context is created here and only lives in the cache.
+
+// Prefix of the uids of generated guests
+const GUEST_UID_PREFIX: &str = "guest-";
+
+struct GuestConfig {
+    writable: bool,
+    docs: HashSet<String>,
+}
+
+static GUEST_CONFIG: OnceLock<Option<GuestConfig>> = OnceLock::new();
+
+// The guest configuration, None when guests are refused
+fn get_guest_config() -> Option<&'static GuestConfig> {
+    GUEST_CONFIG.get_or_init(|| {
+        let config = config::get_config();
+        let writable = match config.guest_access.as_deref().map(|access| access.trim().to_lowercase()) {
+            None => return None,
+            Some(access) if access.is_empty() => return None,
+            Some(access) if access == "view" => false,
+            Some(access) if access == "write" => true,
+            Some(access) => {
+                warn!("Unknown guest access '{}', guests are refused", access);
+                return None;
+            }
+        };
+        let docs: HashSet<String> = config.guest_docs
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|doc_id| doc_id.trim())
+            .filter(|doc_id| !doc_id.is_empty())
+            .filter_map(|doc_id| match Uuid::parse_str(doc_id) {
+                Ok(doc_uuid) => Some(doc_uuid.to_string()),
+                Err(e) => {
+                    warn!("Ignoring invalid guest document '{}': {}", doc_id, e);
+                    None
+                }
+            })
+            .collect();
+        warn!("Guest access is enabled with {} permission on {} documents", if writable { "write" } else { "view" }, docs.len());
+        Some(GuestConfig { writable, docs })
+    }).as_ref()
+}
+
+pub fn is_enabled() -> bool {
+    get_guest_config().is_some()
+}
+
+pub fn is_guest(uid: &str) -> bool {
+    uid.starts_with(GUEST_UID_PREFIX)
+}
+
+// Register a new guest user on a connection, its principal makes it a user of the organization
+pub fn admit_guest(conn_id: u64, org_id: &str, client_version: ClientVersion) -> String {
+    let uid = format!("{}{}", GUEST_UID_PREFIX, Uuid::new_v4().simple());
+    let user_ctx = UserCtx {
+        principals: vec![format!("{}/u/{}", org_id, uid)],
+        token_roles: Vec::new(),
+    };
+    userctx::get_user_ctx_cache().insert(uid.clone(), user_ctx);
+    connctx::get_conn_ctx_cache().insert(conn_id, ConnCtx { uid: uid.clone(), org_id: org_id.to_string(), client_version, roles: Vec::new() });
+    info!("Admitted guest {} to organization {}", uid, org_id);
+    uid
+}
+
+// The permission of a guest on a document, None denies access
+pub fn guest_permission(doc_id: &str) -> Option<Permission> {
+    let guest_config = get_guest_config()?;
+    if !guest_config.docs.contains(doc_id) {
+        return None;
+    }
+    Some(if guest_config.writable { Permission::Write } else { Permission::Read })
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
+use std::time::{Duration, Instant};
+use loro::LoroDoc;
+use loro_protocol::CrdtType;
+use loro_websocket_server::{HubRegistry, RoomKey};
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock as DocLock};
+use crate::ws::docctx::DocContext;
+
+// Access to the rooms in the HubRegistry with the shortest possible lock scopes.
+// The registry lock is only held to clone the hub handle of an organization, and a hub lock
+// is only held to copy out what is needed. Reading a document happens after all locks are released,
+// through the document's own synchronization.
+//
+// Two read-mostly structures keep readers from queueing behind the hub locks:
+// - The metadata of all rooms is shared as a copy that is at most ROOM_SNAPSHOT_MAX_AGE old. Whichever
+//   reader finds it expired refreshes it, the others keep reading the previous copy meanwhile.
+// - Edits by this crate take the write side of a per-document RwLock, readers that need the document
+//   and its context to agree take the read side. Readers of different documents never wait on each other.
+
+// How old the shared copy of the room metadata may get
+const ROOM_SNAPSHOT_MAX_AGE: Duration = Duration::from_secs(1);
+
+struct RoomSnapshot {
+    taken_at: Instant,
+    rooms: Arc<Vec<RoomMeta>>,
+}
+
+static ROOM_SNAPSHOT: RwLock<Option<RoomSnapshot>> = RwLock::new(None);
+static ROOM_SNAPSHOT_REFRESHING: AtomicBool = AtomicBool::new(false);
+
+type DocKey = (String, String);
+static DOC_LOCKS: OnceLock<Mutex<HashMap<DocKey, Weak<DocLock<()>>>>> = OnceLock::new();
+
+/// Metadata of an open room, copied out of its hub
+#[derive(Clone)]
+pub struct RoomMeta {
+    pub org: String,
+    pub room: String,
+    pub is_doc: bool,
+    pub is_ephemeral: bool,
+    pub dirty: bool,
+    pub subscribers: usize,
+}
+
+// Get a handle to the live document of an open room, together with a copy of its context.
+// Mutating the handle mutates the room, use get_open_loro_doc for an independent copy.
+pub async fn get_open_doc_handle(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str) -> Option<(LoroDoc, DocContext)> {
+    let hub = registry.hubs().lock().await.get(org_id).cloned()?;
+    let h = hub.lock().await;
+    let doc_state = h.docs.get(&RoomKey { crdt: CrdtType::Loro, room: doc_id.to_string() })?;
+    match (doc_state.doc.get_loro_doc(), &doc_state.ctx) {
+        (Some(loro_doc), Some(ctx)) => Some((loro_doc, ctx.clone())),
+        _ => None,
+    }
+}
+
+// Update the context of an open room, returns false if the room is not open
+pub async fn update_doc_ctx(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, update: impl FnOnce(&mut DocContext)) -> bool {
+    let hub = match registry.hubs().lock().await.get(org_id).cloned() {
+        Some(hub) => hub,
+        None => return false,
+    };
+    let mut h = hub.lock().await;
+    match h.docs.get_mut(&RoomKey { crdt: CrdtType::Loro, room: doc_id.to_string() }).and_then(|doc_state| doc_state.ctx.as_mut()) {
+        Some(ctx) => {
+            update(ctx);
+            true
+        }
+        None => false,
+    }
+}
+
+// Copy the metadata of all open rooms, locking one hub at a time
+pub async fn snapshot_rooms(registry: &Arc<HubRegistry<DocContext>>) -> Vec<RoomMeta> {
+    let hubs: Vec<_> = registry
+        .hubs()
+        .lock()
+        .await
+        .iter()
+        .map(|(org, hub)| (org.clone(), hub.clone()))
+        .collect();
+
+    let mut rooms = Vec::new();
+    for (org, hub) in hubs {
+        let h = hub.lock().await;
+        for (room_key, doc_state) in h.docs.iter() {
+            rooms.push(RoomMeta {
+                org: org.clone(),
+                room: room_key.room.clone(),
+                is_doc: room_key.crdt == CrdtType::Loro,
+                is_ephemeral: room_key.crdt == CrdtType::LoroEphemeralStore,
+                dirty: doc_state.dirty,
+                subscribers: h.subs.get(room_key).map_or(0, |subs_set| subs_set.len()),
+            });
+        }
+    }
+    rooms
+}
+
+// Releases the refresh of the shared room metadata, also when the refreshing reader is cancelled
+struct RefreshGuard;
+
+impl Drop for RefreshGuard {
+    fn drop(&mut self) {
+        ROOM_SNAPSHOT_REFRESHING.store(false, Ordering::Release);
+    }
+}
+
+fn shared_rooms(max_age: Option<Duration>) -> Option<Arc<Vec<RoomMeta>>> {
+    let snapshot = ROOM_SNAPSHOT.read().unwrap_or_else(|e| e.into_inner());
+    snapshot
+        .as_ref()
+        .filter(|snapshot| match max_age {
+            Some(max_age) => snapshot.taken_at.elapsed() < max_age,
+            None => true,
+        })
+        .map(|snapshot| snapshot.rooms.clone())
+}
+
+// The metadata of all open rooms as of at most ROOM_SNAPSHOT_MAX_AGE ago, for the metrics and
+// dashboards that can live with that delay. Use snapshot_rooms when the current state matters.
+pub async fn cached_rooms(registry: &Arc<HubRegistry<DocContext>>) -> Arc<Vec<RoomMeta>> {
+    if let Some(rooms) = shared_rooms(Some(ROOM_SNAPSHOT_MAX_AGE)) {
+        return rooms;
+    }
+
+    // Another reader is refreshing, take the expired copy instead of locking the hubs as well
+    if ROOM_SNAPSHOT_REFRESHING.swap(true, Ordering::AcqRel) {
+        if let Some(rooms) = shared_rooms(None) {
+            return rooms;
+        }
+        return Arc::new(snapshot_rooms(registry).await);
+    }
+    let _refresh = RefreshGuard;
+    let rooms = Arc::new(snapshot_rooms(registry).await);
+    *ROOM_SNAPSHOT.write().unwrap_or_else(|e| e.into_inner()) = Some(RoomSnapshot {
+        taken_at: Instant::now(),
+        rooms: rooms.clone(),
+    });
+    rooms
+}
+
+// Whether an open document has unsaved changes, None if it is not open. Only locks the hub of its org.
+pub async fn is_doc_dirty(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str) -> Option<bool> {
+    let hub = registry.hubs().lock().await.get(org_id).cloned()?;
+    let h = hub.lock().await;
+    h.docs
+        .get(&RoomKey { crdt: CrdtType::Loro, room: doc_id.to_string() })
+        .map(|doc_state| doc_state.dirty)
+}
+
+// The RwLock of a document, shared by everyone holding it and dropped once nobody does
+fn doc_lock(org_id: &str, doc_id: &str) -> Arc<DocLock<()>> {
+    let mut locks = DOC_LOCKS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let key = (org_id.to_string(), doc_id.to_string());
+    if let Some(lock) = locks.get(&key).and_then(Weak::upgrade) {
+        return lock;
+    }
+    locks.retain(|_, lock| lock.strong_count() > 0);
+    let lock = Arc::new(DocLock::new(()));
+    locks.insert(key, Arc::downgrade(&lock));
+    lock
+}
+
+// Hold while reading a document together with its context, edits by this crate wait until it is dropped
+pub async fn read_doc(org_id: &str, doc_id: &str) -> OwnedRwLockReadGuard<()> {
+    doc_lock(org_id, doc_id).read_owned().await
+}
+
+// Hold while editing a document and updating its context, readers wait until it is dropped
+pub async fn write_doc(org_id: &str, doc_id: &str) -> OwnedRwLockWriteGuard<()> {
+    doc_lock(org_id, doc_id).write_owned().await
+}
+
+// Push updates to the subscribers of every open ephemeral room of an org, without touching any document.
+// Returns the number of rooms the updates were pushed to.
+pub async fn broadcast_ephemeral(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, updates: Vec<Vec<u8>>) -> usize {
+    let hub = match registry.hubs().lock().await.get(org_id).cloned() {
+        Some(hub) => hub,
+        None => return 0,
+    };
+    let rooms: Vec<String> = hub
+        .lock()
+        .await
+        .docs
+        .keys()
+        .filter(|room_key| room_key.crdt == CrdtType::LoroEphemeralStore)
+        .map(|room_key| room_key.room.clone())
+        .collect();
+
+    for room in &rooms {
+        registry.broadcast(org_id, CrdtType::LoroEphemeralStore, room, updates.clone()).await;
+    }
+    rooms.len()
+}
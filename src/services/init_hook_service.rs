@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use loro::{LoroDoc, LoroMap, ToJson};
+use serde_json::Value;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab;
+use crate::models::{ColabSheetBlock, ColabSheetPropertiesBlock, TextElement, TextElementChild, TextElementChildrenOrString};
+use crate::models::lorodoc::{colab_sheet_block_to_loro_map, get_list_map, get_string, txtelem_to_loro_doc};
+use crate::services::doc_edit_service;
+use crate::ws::docctx::DocContext;
+
+// First-open initialization of documents.
+// Hooks are registered per content type with DOC_INIT_HOOKS, e.g.
+// `claim=ensure-languages;datasheet=properties-block,ensure-languages`. They run in one edit the first
+// time a document of the content type is loaded into a room, the initialization is recorded with the
+// hooks that ran. Hooks only add what is missing, running them on a document again changes nothing.
+
+/// A hook preparing a document of a content type on its first load
+pub struct InitHook {
+    pub name: &'static str,
+    apply: fn(&LoroDoc) -> Result<(), String>,
+}
+
+const HOOKS: [InitHook; 2] = [
+    InitHook { name: "ensure-languages", apply: ensure_languages },
+    InitHook { name: "properties-block", apply: properties_block },
+];
+
+static REGISTRY: OnceLock<HashMap<String, Vec<&'static InitHook>>> = OnceLock::new();
+
+// The hooks per content type, as configured
+fn registry() -> &'static HashMap<String, Vec<&'static InitHook>> {
+    REGISTRY.get_or_init(|| {
+        let mut registry: HashMap<String, Vec<&'static InitHook>> = HashMap::new();
+        let configured = config::get_config().doc_init_hooks.clone().unwrap_or_default();
+        for entry in configured.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let Some((content_type, names)) = entry.split_once('=') else {
+                warn!("Ignoring initialization hooks '{}' without a content type", entry);
+                continue;
+            };
+            let hooks = registry.entry(content_type.trim().to_string()).or_default();
+            for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                match HOOKS.iter().find(|hook| hook.name == name) {
+                    Some(hook) => hooks.push(hook),
+                    None => warn!("Ignoring unknown initialization hook '{}' of content type '{}'", name, content_type.trim()),
+                }
+            }
+        }
+        registry
+    })
+}
+
+/// The hooks of a content type, in the configured order
+pub fn hooks_for(content_type: &str) -> &'static [&'static InitHook] {
+    registry().get(content_type).map(Vec::as_slice).unwrap_or_default()
+}
+
+// Run the hooks of the content type of a document that was never initialized.
+// Returns the initialized snapshot, its changes are attributed to the service and still need to be saved.
+pub async fn initialize(org_id: &str, snapshot: &[u8], ctx: &mut DocContext) -> Option<Vec<u8>> {
+    if registry().is_empty() {
+        return None;
+    }
+    let doc = LoroDoc::new();
+    if let Err(e) = doc.import(snapshot) {
+        error!("Failed to import snapshot of document {} for initialization: {}", ctx.doc_id, e);
+        return None;
+    }
+    let content_type = get_string(&doc.get_map("properties"), "contentType").unwrap_or_default();
+    let hooks = hooks_for(&content_type);
+    if hooks.is_empty() {
+        return None;
+    }
+
+    let db = dbcolab::get_db()?;
+    match db.is_document_initialized(org_id, ctx.doc_id).await {
+        Ok(false) => {}
+        Ok(true) => return None,
+        Err(e) => {
+            error!("Failed to check the initialization of document {}: {}", ctx.doc_id, e);
+            return None;
+        }
+    }
+
+    let initialized = match doc_edit_service::edit_snapshot(snapshot, ctx, |doc| {
+        hooks.iter().try_for_each(|hook| (hook.apply)(doc).map_err(|e| format!("Hook '{}' failed: {}", hook.name, e)))
+    }) {
+        Ok(initialized) => initialized,
+        Err(e) => {
+            error!("Failed to initialize document {}: {}", ctx.doc_id, e);
+            return None;
+        }
+    };
+
+    let names: Vec<String> = hooks.iter().map(|hook| hook.name.to_string()).collect();
+    if let Err(e) = db.insert_document_initialization(org_id, ctx.doc_id, &content_type, &names, "s/colabri-doc").await {
+        error!("Failed to record the initialization of document {}: {}", ctx.doc_id, e);
+    }
+    info!("Initialized document {} of content type '{}' with hooks {}", ctx.doc_id, content_type, names.join(", "));
+    initialized
+}
+
+// Statements get an empty element for the master language and every language of the document
+fn ensure_languages(doc: &LoroDoc) -> Result<(), String> {
+    let properties = doc.get_map("properties").get_deep_value().to_json_value();
+    if properties.get("type").and_then(Value::as_str) != Some("colab-statement") {
+        return Ok(());
+    }
+    let master = properties.get("masterLangCode").and_then(Value::as_str);
+    let lang_codes = properties.get("langCodes").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str);
+    let content = doc.get_map("content");
+    for lang_code in master.into_iter().chain(lang_codes) {
+        if content.get(lang_code).is_some() {
+            continue;
+        }
+        let element = content
+            .get_or_create_container(lang_code, LoroMap::new())
+            .map_err(|e| format!("Failed to add language '{}': {}", lang_code, e))?;
+        element
+            .get_or_create_container("acls", LoroMap::new())
+            .map_err(|e| format!("Failed to add the ACLs of language '{}': {}", lang_code, e))?;
+        let text_element = element
+            .get_or_create_container("textElement", LoroMap::new())
+            .map_err(|e| format!("Failed to add the text of language '{}': {}", lang_code, e))?;
+        txtelem_to_loro_doc(&empty_text_element(), &text_element);
+    }
+    Ok(())
+}
+
+// Sheets start with a properties block
+fn properties_block(doc: &LoroDoc) -> Result<(), String> {
+    if get_string(&doc.get_map("properties"), "type").as_deref() != Some("colab-sheet") {
+        return Ok(());
+    }
+    let content = doc.get_movable_list("content");
+    let has_properties = (0..content.len())
+        .filter_map(|idx| get_list_map(&content, idx))
+        .any(|block| get_string(&block, "type").as_deref() == Some("properties"));
+    if has_properties {
+        return Ok(());
+    }
+    let block_map = colab_sheet_block_to_loro_map(&ColabSheetBlock::Properties(ColabSheetPropertiesBlock {}));
+    block_map.insert("id", Uuid::new_v4().to_string()).map_err(|e| format!("Failed to set the block id: {}", e))?;
+    content.insert_container(0, block_map).map_err(|e| format!("Failed to insert the properties block: {}", e))?;
+    Ok(())
+}
+
+// A single empty paragraph
+fn empty_text_element() -> TextElement {
+    TextElement {
+        node_name: "doc".to_string(),
+        attributes: HashMap::new(),
+        children: TextElementChildrenOrString::AsChildren(vec![TextElementChild {
+            node_name: "paragraph".to_string(),
+            attributes: HashMap::new(),
+            children: TextElementChildrenOrString::AsStringArray(Vec::new()),
+        }]),
+    }
+}
@@ -0,0 +1,55 @@
+use loro::{ExportMode, LoroDoc};
+use tracing::error;
+use crate::config;
+use crate::services::{quarantine_service, workflow_service};
+use crate::ws::docctx::DocContext;
+
+// Rooms that only admit readers are loaded from a state-only snapshot, so joining viewers
+// don't download the op history. The initial sync is sent per room by the websocket server,
+// so this applies to rooms where every client is read-only: archived and read-only documents.
+
+pub fn is_enabled() -> bool {
+    config::get_config().state_only_read_rooms.unwrap_or(true)
+}
+
+// Whether every client joining the room of the document is granted read access only
+pub async fn is_read_only_room(org_id: &str, ctx: &DocContext) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    if ctx.settings.read_only {
+        return true;
+    }
+    match workflow_service::get_state(org_id, ctx.doc_id).await {
+        Ok(Some(state)) => workflow_service::is_archived(&state),
+        Ok(None) => false,
+        Err(e) => {
+            error!("{}", e);
+            false
+        }
+    }
+}
+
+// Export the current state of a snapshot without its op history
+pub fn to_state_only(snapshot: &[u8]) -> Result<Vec<u8>, String> {
+    let doc = LoroDoc::new();
+    doc.import(snapshot).map_err(|e| format!("Failed to import snapshot: {}", e))?;
+    doc.export(ExportMode::state_only(None))
+        .map_err(|e| format!("Failed to export state-only snapshot: {}", e))
+}
+
+// Merge the changes made in a room loaded from a state-only snapshot into the stored history.
+// The state-only document starts at the stored version, so the changes since that version are imported.
+pub async fn merge_into_history(org_id: &str, doc_id: &str, state_only_doc: &LoroDoc) -> Result<LoroDoc, String> {
+    let (snapshot, _) = quarantine_service::fetch_doc_snapshot(org_id, doc_id, None)
+        .await?
+        .ok_or_else(|| format!("Document '{}' not found in organization '{}'", doc_id, org_id))?;
+    let history = LoroDoc::new();
+    history.import(&snapshot)
+        .map_err(|e| format!("Failed to import stored history of document '{}': {}", doc_id, e))?;
+    let updates = state_only_doc.export(ExportMode::updates(&history.oplog_vv()))
+        .map_err(|e| format!("Failed to export changes of document '{}': {}", doc_id, e))?;
+    history.import(&updates)
+        .map_err(|e| format!("Failed to merge changes into the history of document '{}': {}", doc_id, e))?;
+    Ok(history)
+}
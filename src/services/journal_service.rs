@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use loro::{LoroDoc, VersionVector};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::ws::docctx::DocContext;
+
+static JOURNAL_LOCKS: OnceLock<Mutex<HashMap<Uuid, Arc<tokio::sync::Mutex<()>>>>> = OnceLock::new();
+static APPEND_FAILURES_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// An accepted update as written to the journal of a document
+#[derive(Serialize, Deserialize)]
+struct JournalRecord {
+    // The peer that made the update and its counter after the update
+    peer: u64,
+    counter_end: i32,
+    prpl: String,
+    updates: Vec<Vec<u8>>,
+}
+
+// Whether updates are journaled, they are when a journal directory is configured
+pub fn is_enabled() -> bool {
+    journal_dir().is_some()
+}
+
+// Updates refused by this instance because they couldn't be journaled
+pub fn append_failures_total() -> u64 {
+    APPEND_FAILURES_TOTAL.load(Ordering::Relaxed)
+}
+
+fn journal_dir() -> Option<PathBuf> {
+    config::get_config().update_journal_dir.as_ref().filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+fn journal_path(org_id: &str, doc_uuid: Uuid) -> Option<PathBuf> {
+    journal_dir().map(|dir| dir.join(org_id).join(format!("{}.journal", doc_uuid)))
+}
+
+// One lock per document, appends and compactions of a journal must not interleave
+fn journal_lock(doc_uuid: Uuid) -> Arc<tokio::sync::Mutex<()>> {
+    let mut locks = JOURNAL_LOCKS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+    locks.entry(doc_uuid).or_default().clone()
+}
+
+// Forget the lock of a document without journal, unless another task holds or waits for it.
+// Clones are only handed out under the map lock, so the count can't grow during the check.
+fn release_journal_lock(doc_uuid: Uuid, lock: Arc<tokio::sync::Mutex<()>>) {
+    let mut locks = JOURNAL_LOCKS.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+    if locks.get(&doc_uuid).is_some_and(|held| Arc::ptr_eq(held, &lock)) && Arc::strong_count(&lock) == 2 {
+        locks.remove(&doc_uuid);
+    }
+}
+
+// Encode a record, prefixed with its length so a torn write at the tail can be detected
+fn encode(record: &JournalRecord) -> Result<Vec<u8>, String> {
+    let body = serde_cbor::to_vec(record).map_err(|e| format!("Failed to encode journal record: {}", e))?;
+    let mut bytes = Vec::with_capacity(body.len() + 4);
+    bytes.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+// Decode the records of a journal, a truncated or corrupt tail ends the journal
+fn decode(bytes: &[u8]) -> Vec<JournalRecord> {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= bytes.len() {
+        let len = u32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]) as usize;
+        let end = offset + 4 + len;
+        if end > bytes.len() {
+            warn!("Ignoring a truncated journal record of {} bytes", len);
+            break;
+        }
+        match serde_cbor::from_slice::<JournalRecord>(&bytes[offset + 4..end]) {
+            Ok(record) => records.push(record),
+            Err(e) => {
+                warn!("Ignoring the tail of a journal after a corrupt record: {}", e);
+                break;
+            }
+        }
+        offset = end;
+    }
+    records
+}
+
+// Append accepted updates to the journal of a document and flush them to disk.
+// A failed append is counted, the caller refuses the updates instead of acknowledging them.
+pub async fn append(org_id: &str, doc_uuid: Uuid, peer: u64, counter_end: i32, prpl: &str, updates: &[Vec<u8>]) -> Result<(), String> {
+    let path = match journal_path(org_id, doc_uuid) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let result = append_to(&path, doc_uuid, peer, counter_end, prpl, updates).await;
+    if result.is_err() {
+        APPEND_FAILURES_TOTAL.fetch_add(1, Ordering::Relaxed);
+    }
+    result
+}
+
+async fn append_to(path: &Path, doc_uuid: Uuid, peer: u64, counter_end: i32, prpl: &str, updates: &[Vec<u8>]) -> Result<(), String> {
+    let bytes = encode(&JournalRecord { peer, counter_end, prpl: prpl.to_string(), updates: updates.to_vec() })?;
+
+    let lock = journal_lock(doc_uuid);
+    let _guard = lock.lock().await;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| format!("Failed to create journal directory {:?}: {}", parent, e))?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(|e| format!("Failed to open journal {:?}: {}", path, e))?;
+    file.write_all(&bytes).await.map_err(|e| format!("Failed to append to journal {:?}: {}", path, e))?;
+    file.sync_data().await.map_err(|e| format!("Failed to flush journal {:?}: {}", path, e))?;
+    Ok(())
+}
+
+// Drop the records that are part of a saved version, the journal is removed once it is empty
+pub async fn compact(org_id: &str, doc_uuid: Uuid, saved_vv: &VersionVector) {
+    let path = match journal_path(org_id, doc_uuid) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let lock = journal_lock(doc_uuid);
+    let guard = lock.lock().await;
+    let bytes = match tokio::fs::read(&path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            drop(guard);
+            release_journal_lock(doc_uuid, lock);
+            return;
+        }
+        Err(e) => {
+            error!("Failed to read journal {:?} for compaction: {}", path, e);
+            return;
+        }
+    };
+
+    let remaining: Vec<JournalRecord> = decode(&bytes)
+        .into_iter()
+        .filter(|record| saved_vv.get(&record.peer).copied().unwrap_or(0) < record.counter_end)
+        .collect();
+    if remaining.is_empty() {
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            error!("Failed to remove journal {:?}: {}", path, e);
+            return;
+        }
+        drop(guard);
+        release_journal_lock(doc_uuid, lock);
+        return;
+    }
+
+    // Write the remaining records next to the journal and swap them in
+    let mut compacted = Vec::new();
+    for record in &remaining {
+        match encode(record) {
+            Ok(bytes) => compacted.extend_from_slice(&bytes),
+            Err(e) => {
+                error!("Failed to compact journal {:?}: {}", path, e);
+                return;
+            }
+        }
+    }
+    let tmp_path = path.with_extension("journal.tmp");
+    if let Err(e) = tokio::fs::write(&tmp_path, &compacted).await {
+        error!("Failed to write compacted journal {:?}: {}", tmp_path, e);
+        return;
+    }
+    if let Err(e) = tokio::fs::rename(&tmp_path, &path).await {
+        error!("Failed to replace journal {:?}: {}", path, e);
+    }
+}
+
+// Replay the journal of a document on top of its persisted snapshot.
+// Returns the snapshot with the journaled updates, or None when the journal holds nothing new.
+pub async fn replay(org_id: &str, snapshot: &[u8], ctx: &mut DocContext) -> Option<Vec<u8>> {
+    let path = journal_path(org_id, ctx.doc_id)?;
+
+    let lock = journal_lock(ctx.doc_id);
+    let guard = lock.lock().await;
+    let bytes = match tokio::fs::read(&path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            drop(guard);
+            release_journal_lock(ctx.doc_id, lock);
+            return None;
+        }
+        Err(e) => {
+            error!("Failed to read journal {:?} for replay: {}", path, e);
+            return None;
+        }
+    };
+    let records = decode(&bytes);
+    if records.is_empty() {
+        return None;
+    }
+
+    // Import the snapshot and every journaled update on top of it
+    let loro_doc = LoroDoc::new();
+    if let Err(e) = loro_doc.import(snapshot) {
+        error!("Failed to import snapshot of document {} for journal replay: {}", ctx.doc_id, e);
+        return None;
+    }
+    let before = loro_doc.oplog_vv();
+    let mut last_peer = None;
+    let mut n_replayed = 0;
+    for record in &records {
+        if before.get(&record.peer).copied().unwrap_or(0) >= record.counter_end {
+            continue;
+        }
+        if let Err(e) = loro_doc.import_batch(&record.updates) {
+            warn!("Failed to replay a journaled update of document {}: {}", ctx.doc_id, e);
+            continue;
+        }
+        ctx.peer_map.entry(record.peer).or_insert_with(|| record.prpl.clone());
+        last_peer = Some(record.peer);
+        n_replayed += 1;
+    }
+    if loro_doc.oplog_vv() == before {
+        return None;
+    }
+
+    // The replayed changes are unsaved, attribute them to the last journaled peer so the next save picks them up
+    let replayed = match loro_doc.export(loro::ExportMode::Snapshot) {
+        Ok(replayed) => replayed,
+        Err(e) => {
+            error!("Failed to export replayed document {}: {}", ctx.doc_id, e);
+            return None;
+        }
+    };
+    ctx.last_updating_peer = last_peer;
+    info!("Replayed {} of {} journaled update(s) into document {}", n_replayed, records.len(), ctx.doc_id);
+    Some(replayed)
+}
@@ -0,0 +1,142 @@
+use loro::{LoroDoc, ToJson};
+use serde_json::{Map, Number, Value};
+use tracing::warn;
+use uuid::Uuid;
+use crate::config;
+use crate::services::{lazy_block_service, statement_subdoc_service};
+
+// Projection of the deep value of a document onto the canonical ColabModel JSON stored for the app.
+// The Loro structure keeps things in the shape that merges best, which isn't the shape the app queries:
+// comments are a map keyed by comment id instead of a list, reactions live next to the content, edits
+// leave empty text nodes behind and numbers set by clients arrive as floats. The canonical projection
+// undoes that, so the stored JSON keeps its shape while the Loro structure evolves. The raw projection
+// stores the deep value as is.
+
+// Node name of a text wrapped into an element, when a text element mixes texts and elements
+const TEXT_NODE_NAME: &str = "#text";
+
+// Keys holding state of the service next to the content, they have their own endpoints
+const SERVICE_KEYS: [&str; 1] = ["reactions"];
+
+/// How the JSON of a saved document is stored in the app database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    Raw,
+    Canonical,
+}
+
+/// The configured projection, canonical unless configured otherwise
+pub fn configured() -> Projection {
+    match config::get_config().save_json_projection.as_deref() {
+        Some("raw") => Projection::Raw,
+        _ => Projection::Canonical,
+    }
+}
+
+/// The JSON stored for a document: its deep value with the rows of its lazy blocks and its linked
+/// statements, projected with the configured projection
+pub async fn document_json(org_id: &str, doc_uuid: Uuid, loro_doc: &LoroDoc) -> Value {
+    let mut json = loro_doc.get_deep_value().to_json_value();
+
+    // The bodies of lazily loaded blocks are stored apart, the stored JSON holds the whole sheet
+    if lazy_block_service::is_lazy(loro_doc) {
+        if let Err(e) = lazy_block_service::assemble_json(org_id, doc_uuid, &mut json).await {
+            warn!("Saving document {} without the rows of its lazy blocks: {}", doc_uuid, e);
+        }
+    }
+    if let Err(e) = statement_subdoc_service::compose_json(org_id, &mut json).await {
+        warn!("Saving document {} without its linked statements: {}", doc_uuid, e);
+    }
+    project(json)
+}
+
+/// Project the deep value of a document with the configured projection
+pub fn project(json: Value) -> Value {
+    match configured() {
+        Projection::Raw => json,
+        Projection::Canonical => canonical(json),
+    }
+}
+
+/// Project the deep value of a document onto the canonical ColabModel JSON
+pub fn canonical(json: Value) -> Value {
+    match json {
+        Value::Object(map) => Value::Object(canonical_object(map)),
+        Value::Array(items) => Value::Array(items.into_iter().map(canonical).collect()),
+        Value::Number(number) => Value::Number(canonical_number(number)),
+        other => other,
+    }
+}
+
+fn canonical_object(map: Map<String, Value>) -> Map<String, Value> {
+    let is_text_element = map.contains_key("nodeName") && map.contains_key("children");
+    let mut projected = Map::with_capacity(map.len());
+    for (key, value) in map {
+        if SERVICE_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let value = match (key.as_str(), value) {
+            ("comments", Value::Object(comments)) => comments_as_list(comments),
+            ("children", Value::Array(children)) if is_text_element => normalize_children(children),
+            ("attributes", Value::Null) if is_text_element => Value::Object(Map::new()),
+            (_, value) => canonical(value),
+        };
+        projected.insert(key, value);
+    }
+    projected
+}
+
+// Numbers set by clients are floats, whole ones are stored as integers
+fn canonical_number(number: Number) -> Number {
+    match number.as_f64() {
+        Some(float) if !number.is_i64() && !number.is_u64() && float.fract() == 0.0 && float.abs() < i64::MAX as f64 => Number::from(float as i64),
+        _ => number,
+    }
+}
+
+// Comments are keyed by id in the document, a list ordered by time in the model
+fn comments_as_list(comments: Map<String, Value>) -> Value {
+    let mut list: Vec<Value> = comments.into_values().map(canonical).collect();
+    list.sort_by(|a, b| {
+        let key = |comment: &Value| (
+            comment.get("timestamp").and_then(Value::as_str).unwrap_or_default().to_string(),
+            comment.get("id").and_then(Value::as_str).unwrap_or_default().to_string(),
+        );
+        key(a).cmp(&key(b))
+    });
+    Value::Array(list)
+}
+
+// The children of a text element are either all texts or all elements.
+// Empty texts left behind by edits are dropped, texts next to elements are wrapped into text nodes.
+fn normalize_children(children: Vec<Value>) -> Value {
+    let only_empty_text = !children.is_empty() && children.iter().all(|child| child.as_str() == Some(""));
+    if only_empty_text {
+        return Value::Array(vec![Value::String(String::new())]);
+    }
+    let mut normalized: Vec<Value> = Vec::with_capacity(children.len());
+    for child in children {
+        match child {
+            Value::String(text) if text.is_empty() => {}
+            Value::String(text) => normalized.push(Value::String(text)),
+            other => normalized.push(canonical(other)),
+        }
+    }
+
+    let has_texts = normalized.iter().any(Value::is_string);
+    let has_elements = normalized.iter().any(|child| !child.is_string());
+    if has_texts && has_elements {
+        normalized = normalized
+            .into_iter()
+            .map(|child| match child {
+                Value::String(text) => serde_json::json!({
+                    "nodeName": TEXT_NODE_NAME,
+                    "attributes": {},
+                    "children": [text],
+                }),
+                element => element,
+            })
+            .collect();
+    }
+    Value::Array(normalized)
+}
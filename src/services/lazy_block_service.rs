@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use loro::{ExportMode, LoroDoc, LoroMovableList, LoroValue, ToJson};
+use loro_protocol::CrdtType;
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use tracing::{info, warn};
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::models::ColabPackage;
+use crate::models::ColabSheetStatementGridRow;
+use crate::models::lorodoc::{get_block_id, get_child_movable_list, get_doc_type, get_list_map, get_string, statement_grid_rows_to_loro_list};
+use crate::services::{doc_edit_service, doc_load_service, quarantine_service, room_assignment_service};
+use crate::ws::docctx::DocContext;
+
+// Lazy block mode of large sheets.
+// The sheet room keeps the skeleton of its statement grids (type, id, title, acls), the rows of every
+// grid are a Loro document of their own in the room `<document>~<block_id>`. Clients open a block room
+// when they need the body of the block, so the initial sync of the sheet stays small.
+// Services reading the Loro document of a split sheet see the skeletons, joining folds the bodies back.
+
+pub const ROOM_SEPARATOR: char = '~';
+const LAZY_PROPERTY: &str = "lazyBlocks";
+
+/// Room that syncs the body of a block
+pub fn block_room(doc_id: &str, block_id: &str) -> String {
+    format!("{}{}{}", doc_id, ROOM_SEPARATOR, block_id)
+}
+
+/// Split a room into the document and, for block rooms, the block
+pub fn parse_room(room: &str) -> (&str, Option<&str>) {
+    match room.split_once(ROOM_SEPARATOR) {
+        Some((doc_id, block_id)) => (doc_id, Some(block_id)),
+        None => (room, None),
+    }
+}
+
+/// Whether the sheet is in lazy block mode
+pub fn is_lazy(doc: &LoroDoc) -> bool {
+    doc.get_map("properties")
+        .get(LAZY_PROPERTY)
+        .is_some_and(|value| matches!(value.as_value(), Some(LoroValue::Bool(true))))
+}
+
+// Load the body of a block as the snapshot of its room, with the context of the sheet
+pub async fn load_block(org_id: &str, doc_id: &str, block_id: &str) -> Result<Option<(Vec<u8>, DocContext)>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let doc_uuid = Uuid::parse_str(doc_id).map_err(|e| format!("Invalid document UUID '{}': {}", doc_id, e))?;
+    let row = match db.get_document_block(org_id, doc_uuid, block_id).await
+        .map_err(|e| format!("Failed to load block '{}' of document '{}': {}", block_id, doc_id, e))? {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let (_, mut ctx) = match quarantine_service::fetch_doc_snapshot(org_id, doc_id, None).await? {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let package: ColabPackage = serde_cbor::from_slice(&row.package)
+        .map_err(|e| format!("Failed to decode block '{}' of document '{}': {}", block_id, doc_id, e))?;
+    ctx.peer_map = package.peer_map;
+    ctx.last_updating_peer = None;
+    ctx.state_only = false;
+    ctx.block_id = Some(block_id.to_string());
+    Ok(Some((package.snapshot, ctx)))
+}
+
+// Persist the body of a block
+pub async fn save_block(snapshot: Vec<u8>, ctx: &DocContext, block_id: &str, by_prpl: &str) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let loro_doc = LoroDoc::new();
+    loro_doc.import(&snapshot)
+        .map_err(|e| format!("Failed to import block '{}' of document '{}': {}", block_id, ctx.doc_id, e))?;
+    let json = loro_doc.get_movable_list("rows").get_deep_value().to_json_value();
+    let package = serde_cbor::to_vec(&ColabPackage { snapshot, peer_map: ctx.peer_map.clone() })
+        .map_err(|e| format!("Failed to serialize block '{}' of document '{}': {}", block_id, ctx.doc_id, e))?;
+    db.upsert_document_block(&ctx.org, ctx.doc_id, block_id, package, json, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to save block '{}' of document '{}': {}", block_id, ctx.doc_id, e))?;
+    info!("Saved block '{}' of document {}", block_id, ctx.doc_id);
+    Ok(())
+}
+
+// Fill the rows of the lazy blocks into the JSON of a sheet
+pub async fn assemble_json(org_id: &str, doc_uuid: Uuid, json: &mut Value) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let mut bodies: HashMap<String, Value> = db.get_document_blocks(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load the blocks of document '{}': {}", doc_uuid, e))?
+        .into_iter()
+        .map(|row| (row.block_id, row.json))
+        .collect();
+    if let Some(blocks) = json.get_mut("content").and_then(|content| content.as_array_mut()) {
+        for block in blocks.iter_mut() {
+            let block_id = match block.get("id").and_then(|id| id.as_str()) {
+                Some(block_id) => block_id.to_string(),
+                None => continue,
+            };
+            if let (Some(rows), Some(block)) = (bodies.remove(&block_id), block.as_object_mut()) {
+                block.insert("rows".to_string(), rows);
+            }
+        }
+    }
+    Ok(())
+}
+
+// The statement grids of a sheet with their id and position
+fn grid_blocks(doc: &LoroDoc) -> Vec<(usize, String)> {
+    let content = doc.get_movable_list("content");
+    (0..content.len())
+        .filter_map(|i| {
+            let block = get_list_map(&content, i)?;
+            (get_string(&block, "type").as_deref() == Some("statement-grid")).then(|| (i, get_block_id(&block, i)))
+        })
+        .collect()
+}
+
+fn rows_json(doc: &LoroDoc, idx: usize) -> Value {
+    get_list_map(&doc.get_movable_list("content"), idx)
+        .and_then(|block| get_child_movable_list(&block, "rows"))
+        .map(|rows| rows.get_deep_value().to_json_value())
+        .unwrap_or(Value::Array(Vec::new()))
+}
+
+// Move the rows of every statement grid of a sheet into their own block document.
+// The bodies are stored first, the sheet is only stripped when its rows didn't change in the meantime.
+pub async fn split(registry: Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, by_prpl: &str) -> Result<Vec<String>, String> {
+    let doc_uuid = Uuid::parse_str(doc_id).map_err(|e| format!("Invalid document UUID '{}': {}", doc_id, e))?;
+    // The rooms of the sheet and its blocks live on the pod owning the sheet
+    room_assignment_service::ensure_local(doc_id)?;
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    // 1. Store the rows of every statement grid as a block document
+    let (doc, _) = doc_load_service::load_loro_doc(&registry, org_id, doc_id)
+        .await?
+        .ok_or_else(|| format!("Document '{}' not found in organization '{}'", doc_id, org_id))?;
+    if get_doc_type(&doc).as_deref() != Some("colab-sheet") {
+        return Err("Only sheets can load their blocks lazily".to_string());
+    }
+    if is_lazy(&doc) {
+        return Err(format!("Document '{}' already loads its blocks lazily", doc_id));
+    }
+    let mut stored: HashMap<String, Value> = HashMap::new();
+    for (idx, block_id) in grid_blocks(&doc) {
+        let json = rows_json(&doc, idx);
+        let rows: Vec<ColabSheetStatementGridRow> = serde_json::from_value(json.clone())
+            .map_err(|e| format!("Failed to read the rows of block '{}': {}", block_id, e))?;
+        let body = LoroDoc::new();
+        statement_grid_rows_to_loro_list(&rows, &body.get_movable_list("rows"));
+        body.commit();
+        let snapshot = body.export(ExportMode::Snapshot)
+            .map_err(|e| format!("Failed to export block '{}': {}", block_id, e))?;
+        let peer_map = HashMap::from([(body.peer_id(), "s/colabri-doc".to_string())]);
+        let package = serde_cbor::to_vec(&ColabPackage { snapshot, peer_map })
+            .map_err(|e| format!("Failed to serialize block '{}': {}", block_id, e))?;
+        db.upsert_document_block(org_id, doc_uuid, &block_id, package, body.get_movable_list("rows").get_deep_value().to_json_value(), by_prpl)
+            .await
+            .map_err(|e| format!("Failed to store block '{}': {}", block_id, e))?;
+        stored.insert(block_id, json);
+    }
+
+    // 2. Replace the rows in the sheet by a reference to the block room, connected clients reconnect
+    let block_ids: Vec<String> = stored.keys().cloned().collect();
+    let sheet_id = doc_id.to_string();
+    doc_edit_service::edit_doc(registry, org_id, doc_id, move |doc: &LoroDoc| {
+        let grids = grid_blocks(doc);
+        if grids.len() != stored.len() || grids.iter().any(|(idx, block_id)| stored.get(block_id) != Some(&rows_json(doc, *idx))) {
+            return Err("The statement grids changed while splitting, try again".to_string());
+        }
+        let content = doc.get_movable_list("content");
+        for (idx, block_id) in grids {
+            let block = get_list_map(&content, idx).ok_or_else(|| format!("Block '{}' disappeared", block_id))?;
+            let set = |key: &str, value: LoroValue| block.insert(key, value).map_err(|e| format!("Failed to update block '{}': {}", block_id, e));
+            set("id", block_id.as_str().into())?;
+            set("lazy", true.into())?;
+            set("bodyRoom", block_room(&sheet_id, &block_id).into())?;
+            block.delete("rows").map_err(|e| format!("Failed to remove the rows of block '{}': {}", block_id, e))?;
+        }
+        doc.get_map("properties").insert(LAZY_PROPERTY, true).map_err(|e| format!("Failed to enable lazy blocks: {}", e))?;
+        doc.commit();
+        Ok(())
+    }, true).await?;
+    info!("Split {} statement grids of document {} into block rooms", block_ids.len(), doc_id);
+    Ok(block_ids)
+}
+
+// Fold the block documents of a sheet back into the sheet and leave lazy block mode
+pub async fn join(registry: Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str) -> Result<Vec<String>, String> {
+    let doc_uuid = Uuid::parse_str(doc_id).map_err(|e| format!("Invalid document UUID '{}': {}", doc_id, e))?;
+    // The rooms of the sheet and its blocks live on the pod owning the sheet
+    room_assignment_service::ensure_local(doc_id)?;
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    // 1. Close the open block rooms, so their changes are saved
+    let rows = db.get_document_blocks(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load the blocks of document '{}': {}", doc_id, e))?;
+    for row in &rows {
+        registry.close_room(org_id, CrdtType::Loro, &block_room(doc_id, &row.block_id), true).await;
+    }
+
+    // 2. Load the saved bodies
+    let rows = db.get_document_blocks(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load the blocks of document '{}': {}", doc_id, e))?;
+    let mut bodies: HashMap<String, Vec<ColabSheetStatementGridRow>> = HashMap::new();
+    for row in rows {
+        let grid_rows: Vec<ColabSheetStatementGridRow> = serde_json::from_value(row.json)
+            .map_err(|e| format!("Failed to read the rows of block '{}': {}", row.block_id, e))?;
+        bodies.insert(row.block_id, grid_rows);
+    }
+
+    // 3. Put the rows back into the sheet
+    let block_ids: Vec<String> = bodies.keys().cloned().collect();
+    doc_edit_service::edit_doc(registry, org_id, doc_id, move |doc: &LoroDoc| {
+        let content = doc.get_movable_list("content");
+        for (idx, block_id) in grid_blocks(doc) {
+            let block = match get_list_map(&content, idx) {
+                Some(block) => block,
+                None => continue,
+            };
+            match bodies.remove(&block_id) {
+                Some(grid_rows) => {
+                    let rows_list = block.insert_container("rows", LoroMovableList::new())
+                        .map_err(|e| format!("Failed to restore the rows of block '{}': {}", block_id, e))?;
+                    statement_grid_rows_to_loro_list(&grid_rows, &rows_list);
+                }
+                None => warn!("No stored body for block '{}', it stays empty", block_id),
+            }
+            let _ = block.delete("lazy");
+            let _ = block.delete("bodyRoom");
+        }
+        doc.get_map("properties").delete(LAZY_PROPERTY).map_err(|e| format!("Failed to disable lazy blocks: {}", e))?;
+        doc.commit();
+        Ok(())
+    }, true).await?;
+
+    // 4. The bodies are part of the sheet again
+    db.delete_document_blocks(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to delete the blocks of document '{}': {}", doc_id, e))?;
+    info!("Joined {} block rooms back into document {}", block_ids.len(), doc_id);
+    Ok(block_ids)
+}
@@ -0,0 +1,129 @@
+use loro::{LoroDoc, LoroList, LoroMap, LoroValue, ToJson, ValueOrContainer};
+use serde_json::Value;
+use crate::models::lorodoc::{get_list_map, get_string};
+
+// Approvals held in the legacy list format.
+// Approvals of statement languages and sheet blocks are a map keyed by the approving user or group.
+// Documents written before that hold them as a list. `legacy_paths` finds the lists in the JSON of a
+// document, the validators of the document types flag them. `normalize` rewrites them as maps, it runs
+// as the "approvals-as-map" schema migration and can be called on any document, one without lists is
+// left untouched.
+
+/// The places of a document in its JSON form holding approvals as a list.
+/// Languages of statements are named by their code, blocks of sheets by their id or index.
+pub fn legacy_paths(json: &Value) -> Vec<String> {
+    match json.get("content") {
+        Some(Value::Object(languages)) => languages
+            .iter()
+            .filter(|(_, element)| element.get("approvals").is_some_and(Value::is_array))
+            .map(|(lang_code, _)| format!("content/{}/approvals", lang_code))
+            .collect(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.get("approvals").is_some_and(Value::is_array))
+            .map(|(i, block)| {
+                let id = block.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()).unwrap_or_else(|| i.to_string());
+                format!("content/{}/approvals", id)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Fail when a document in its JSON form still holds approvals as a list
+pub fn validate(json: &Value) -> Result<(), String> {
+    let paths = legacy_paths(json);
+    if paths.is_empty() {
+        return Ok(());
+    }
+    Err(format!("Approvals in the legacy list format at {}", paths.join(", ")))
+}
+
+/// Rewrite every approval list of a document as a map keeping every approval.
+/// Returns the number of lists that were rewritten, the caller commits the changes.
+pub fn normalize(doc: &LoroDoc) -> Result<usize, String> {
+    let mut holders: Vec<LoroMap> = Vec::new();
+    match get_string(&doc.get_map("properties"), "type").as_deref() {
+        Some("colab-statement") => {
+            let content = doc.get_map("content");
+            content.for_each(|_, value| {
+                if let ValueOrContainer::Container(loro::Container::Map(element)) = value {
+                    holders.push(element);
+                }
+            });
+        }
+        Some("colab-sheet") => {
+            let content = doc.get_movable_list("content");
+            holders.extend((0..content.len()).filter_map(|idx| get_list_map(&content, idx)));
+        }
+        _ => {}
+    }
+
+    let mut normalized = 0;
+    for holder in holders {
+        let Some(ValueOrContainer::Container(loro::Container::List(list))) = holder.get("approvals") else {
+            continue;
+        };
+        let approvals = list.get_deep_value().to_json_value();
+        holder.delete("approvals").map_err(|e| format!("Failed to remove the approval list: {}", e))?;
+        let map = holder
+            .insert_container("approvals", LoroMap::new())
+            .map_err(|e| format!("Failed to add the approval map: {}", e))?;
+        for (idx, approval) in approvals.as_array().into_iter().flatten().enumerate() {
+            let key = approval
+                .get("user")
+                .or_else(|| approval.get("group"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| idx.to_string());
+            insert_json(&map, &key, approval).map_err(|e| format!("Failed to move approval '{}': {}", key, e))?;
+        }
+        normalized += 1;
+    }
+    Ok(normalized)
+}
+
+// Write a JSON value into a map
+fn insert_json(map: &LoroMap, key: &str, value: &Value) -> Result<(), String> {
+    let inserted = match value {
+        Value::Object(object) => {
+            let child = map.insert_container(key, LoroMap::new()).map_err(|e| e.to_string())?;
+            return object.iter().try_for_each(|(key, value)| insert_json(&child, key, value));
+        }
+        Value::Array(items) => {
+            let child = map.insert_container(key, LoroList::new()).map_err(|e| e.to_string())?;
+            return items.iter().try_for_each(|item| push_json(&child, item));
+        }
+        Value::String(text) => map.insert(key, text.as_str()),
+        Value::Bool(flag) => map.insert(key, *flag),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => map.insert(key, number),
+            None => map.insert(key, number.as_f64().unwrap_or_default()),
+        },
+        Value::Null => map.insert(key, LoroValue::Null),
+    };
+    inserted.map_err(|e| e.to_string())
+}
+
+// Append a JSON value to a list
+fn push_json(list: &LoroList, value: &Value) -> Result<(), String> {
+    let pushed = match value {
+        Value::Object(object) => {
+            let child = list.insert_container(list.len(), LoroMap::new()).map_err(|e| e.to_string())?;
+            return object.iter().try_for_each(|(key, value)| insert_json(&child, key, value));
+        }
+        Value::Array(items) => {
+            let child = list.insert_container(list.len(), LoroList::new()).map_err(|e| e.to_string())?;
+            return items.iter().try_for_each(|item| push_json(&child, item));
+        }
+        Value::String(text) => list.push(text.as_str()),
+        Value::Bool(flag) => list.push(*flag),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => list.push(number),
+            None => list.push(number.as_f64().unwrap_or_default()),
+        },
+        Value::Null => list.push(LoroValue::Null),
+    };
+    pushed.map_err(|e| e.to_string())
+}
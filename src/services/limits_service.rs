@@ -0,0 +1,205 @@
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::Duration;
+use loro::{LoroDoc, ToJson};
+use moka::sync::Cache;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, warn};
+use crate::config;
+use crate::db::dbcolab;
+use crate::doctypes;
+use crate::models::DocumentSettings;
+
+// How long per-org limits are cached before they are read from the database again
+const LIMITS_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+static LIMITS_CACHE: OnceLock<Cache<String, DocumentLimits>> = OnceLock::new();
+
+/// The size and complexity limits of a single document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentLimits {
+    pub max_blocks: usize,
+    pub max_text_depth: usize,
+    pub max_snapshot_bytes: usize,
+    pub max_languages: usize,
+}
+
+/// Per-org overrides of the limits, as stored in the database
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentLimitOverrides {
+    max_blocks: Option<usize>,
+    max_text_depth: Option<usize>,
+    max_snapshot_bytes: Option<usize>,
+    max_languages: Option<usize>,
+}
+
+/// A limit that a document exceeds
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitViolation {
+    pub limit: String,
+    pub max: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Document limit '{}' exceeded: {} > {}", self.limit, self.actual, self.max)
+    }
+}
+
+impl LimitViolation {
+    fn new(limit: &str, max: usize, actual: usize) -> Self {
+        Self { limit: limit.to_string(), max, actual }
+    }
+
+    // The violation as a JSON error string, for callbacks that can only return a String
+    pub fn to_error_string(&self) -> String {
+        serde_json::to_string(&serde_json::json!({ "error": "document_limit_exceeded", "violation": self }))
+            .unwrap_or_else(|_| self.to_string())
+    }
+}
+
+impl Default for DocumentLimits {
+    fn default() -> Self {
+        let config = config::get_config();
+        Self {
+            max_blocks: config.doc_max_blocks.unwrap_or(500),
+            max_text_depth: config.doc_max_text_depth.unwrap_or(32),
+            max_snapshot_bytes: config.doc_max_snapshot_bytes.unwrap_or(16 * 1024 * 1024),
+            max_languages: config.doc_max_languages.unwrap_or(50),
+        }
+    }
+}
+
+fn get_cache() -> &'static Cache<String, DocumentLimits> {
+    LIMITS_CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(LIMITS_CACHE_TTL)
+            .build()
+    })
+}
+
+// Get the limits of an organization, the defaults overridden by the org specific limits.
+// When the overrides can't be loaded the defaults apply, limits should never make documents unavailable.
+pub async fn get_limits(org_id: &str) -> DocumentLimits {
+    if let Some(limits) = get_cache().get(org_id) {
+        return limits;
+    }
+
+    let overrides = match dbcolab::get_db() {
+        Some(db) => match db.get_org_document_limits(org_id).await {
+            Ok(Some(value)) => serde_json::from_value::<DocumentLimitOverrides>(value).unwrap_or_else(|e| {
+                error!("Invalid document limits for organization '{}': {}", org_id, e);
+                DocumentLimitOverrides::default()
+            }),
+            Ok(None) => DocumentLimitOverrides::default(),
+            Err(e) => {
+                error!("Failed to load document limits of organization '{}': {}", org_id, e);
+                return DocumentLimits::default();
+            }
+        },
+        None => return DocumentLimits::default(),
+    };
+
+    let defaults = DocumentLimits::default();
+    let limits = DocumentLimits {
+        max_blocks: overrides.max_blocks.unwrap_or(defaults.max_blocks),
+        max_text_depth: overrides.max_text_depth.unwrap_or(defaults.max_text_depth),
+        max_snapshot_bytes: overrides.max_snapshot_bytes.unwrap_or(defaults.max_snapshot_bytes),
+        max_languages: overrides.max_languages.unwrap_or(defaults.max_languages),
+    };
+    get_cache().insert(org_id.to_string(), limits.clone());
+    limits
+}
+
+// Apply the overrides of a single document to the limits of its organization
+pub fn with_document_settings(mut limits: DocumentLimits, settings: &DocumentSettings) -> DocumentLimits {
+    if let Some(overrides) = &settings.limits {
+        limits.max_blocks = overrides.max_blocks.unwrap_or(limits.max_blocks);
+        limits.max_text_depth = overrides.max_text_depth.unwrap_or(limits.max_text_depth);
+        limits.max_snapshot_bytes = overrides.max_snapshot_bytes.unwrap_or(limits.max_snapshot_bytes);
+        limits.max_languages = overrides.max_languages.unwrap_or(limits.max_languages);
+    }
+    limits
+}
+
+// Check the size of an exported snapshot
+pub fn check_snapshot_size(snapshot: &[u8], limits: &DocumentLimits) -> Result<(), LimitViolation> {
+    if snapshot.len() > limits.max_snapshot_bytes {
+        return Err(LimitViolation::new("maxSnapshotBytes", limits.max_snapshot_bytes, snapshot.len()));
+    }
+    Ok(())
+}
+
+// Check the structure of a document in its JSON form, either a ColabModel or the deep value of a LoroDoc
+pub fn check_json(json: &Value, limits: &DocumentLimits) -> Result<(), LimitViolation> {
+    let properties = json.get("properties");
+    let doc_type = properties.and_then(|p| p.get("type")).and_then(|t| t.as_str()).unwrap_or_default();
+    let content = json.get("content");
+
+    let stats = doctypes::get(doc_type).map(|doc_type| doc_type.stats(json));
+
+    // 1. Blocks of the document
+    if let Some(blocks) = stats.as_ref().and_then(|stats| stats.blocks) {
+        if blocks > limits.max_blocks {
+            return Err(LimitViolation::new("maxBlocks", limits.max_blocks, blocks));
+        }
+    }
+
+    // 2. Languages, the declared ones and the ones a statement has content for
+    let declared = properties
+        .and_then(|p| p.get("langCodes"))
+        .and_then(|l| l.as_array())
+        .map(|l| l.len())
+        .unwrap_or(0);
+    let present = stats.map(|stats| stats.languages).unwrap_or(0);
+    let languages = declared.max(present);
+    if languages > limits.max_languages {
+        return Err(LimitViolation::new("maxLanguages", limits.max_languages, languages));
+    }
+
+    // 3. Nesting of text elements
+    if let Some(content) = content {
+        if let Some(depth) = exceeding_text_depth(content, 0, limits.max_text_depth) {
+            return Err(LimitViolation::new("maxTextDepth", limits.max_text_depth, depth));
+        }
+    }
+
+    Ok(())
+}
+
+// Check the structure of a LoroDoc
+pub fn check_doc(doc: &LoroDoc, limits: &DocumentLimits) -> Result<(), LimitViolation> {
+    check_json(&doc.get_deep_value().to_json_value(), limits)
+}
+
+// Check what a document would look like with the updates applied, without touching the document itself
+pub fn check_updates(doc: &LoroDoc, updates: &[Vec<u8>], limits: &DocumentLimits) -> Result<(), LimitViolation> {
+    let fork = doc.fork();
+    if let Err(e) = fork.import_batch(updates) {
+        // Invalid updates are rejected when they get applied, not here
+        warn!("Failed to import updates while checking document limits: {}", e);
+        return Ok(());
+    }
+    check_doc(&fork, limits)
+}
+
+// The text element depth reached when it exceeds the maximum, the walk stops at the first element beyond it
+fn exceeding_text_depth(value: &Value, depth: usize, max_depth: usize) -> Option<usize> {
+    match value {
+        Value::Object(map) => {
+            let depth = if map.contains_key("nodeName") { depth + 1 } else { depth };
+            if depth > max_depth {
+                return Some(depth);
+            }
+            map.values().find_map(|v| exceeding_text_depth(v, depth, max_depth))
+        }
+        Value::Array(items) => items.iter().find_map(|v| exceeding_text_depth(v, depth, max_depth)),
+        _ => None,
+    }
+}
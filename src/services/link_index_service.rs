@@ -0,0 +1,91 @@
+use serde_json::Value;
+use tracing::warn;
+use uuid::Uuid;
+use crate::db::dbcolab::{self, DocumentLinkRow};
+use crate::services::transclusion_service;
+
+// Index of the references between documents.
+// Saved documents index the statements referenced by their statement grids, their transcluded
+// blocks and the document ids mentioned in attribute values, so the documents using a statement
+// can be listed before it is edited or deleted.
+
+pub const KIND_STATEMENT_REF: &str = "statementRef";
+pub const KIND_TRANSCLUSION: &str = "transclusion";
+pub const KIND_MENTION: &str = "mention";
+
+// Document ids in a JSON value, at any depth
+fn collect_mentions(value: &Value, mentions: &mut Vec<Uuid>) {
+    match value {
+        Value::String(s) => {
+            if let Ok(id) = Uuid::parse_str(s.trim()) {
+                mentions.push(id);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_mentions(item, mentions)),
+        Value::Object(map) => map.values().for_each(|item| collect_mentions(item, mentions)),
+        _ => {}
+    }
+}
+
+// The references in the JSON of a document: target document, kind and path of the reference
+pub fn links(doc_uuid: Uuid, json: &Value) -> Vec<(Uuid, String, String)> {
+    let mut links = Vec::new();
+    let blocks = match json.get("content").and_then(|content| content.as_array()) {
+        Some(blocks) => blocks,
+        None => return links,
+    };
+    for (i, block) in blocks.iter().enumerate() {
+        let block_id = block.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()).unwrap_or_else(|| i.to_string());
+        if block.get("type").and_then(|t| t.as_str()) == Some(transclusion_service::BLOCK_TYPE) {
+            if let Some(source) = block.get("sourceDocId").and_then(|id| id.as_str()).and_then(|id| Uuid::parse_str(id).ok()) {
+                links.push((source, KIND_TRANSCLUSION.to_string(), format!("/content/{}", block_id)));
+            }
+        }
+        if let Some(rows) = block.get("rows").and_then(|rows| rows.as_array()) {
+            for (r, row) in rows.iter().enumerate() {
+                let target = row.get("statementRef").and_then(|r| r.get("docId")).and_then(|id| id.as_str()).and_then(|id| Uuid::parse_str(id).ok());
+                if let Some(target) = target {
+                    links.push((target, KIND_STATEMENT_REF.to_string(), format!("/content/{}/rows/{}", block_id, r)));
+                }
+            }
+        }
+        if let Some(attributes) = block.get("attributes").and_then(|attributes| attributes.as_object()) {
+            for (key, attribute) in attributes {
+                let mut mentions = Vec::new();
+                collect_mentions(attribute.get("value").unwrap_or(&Value::Null), &mut mentions);
+                for target in mentions {
+                    links.push((target, KIND_MENTION.to_string(), format!("/content/{}/attributes/{}", block_id, key)));
+                }
+            }
+        }
+    }
+    links.retain(|(target, _, _)| *target != doc_uuid);
+    links
+}
+
+// Index the references in the saved JSON of a document, failures leave the previous index in place
+pub async fn index(org_id: &str, doc_uuid: Uuid, json: &Value) {
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return,
+    };
+    if let Err(e) = db.replace_document_links(org_id, doc_uuid, &links(doc_uuid, json)).await {
+        warn!("Failed to index the references of document '{}': {}", doc_uuid, e);
+    }
+}
+
+// The references of a document to other documents
+pub async fn outgoing(org_id: &str, doc_uuid: Uuid) -> Result<Vec<DocumentLinkRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_document_links(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load the references of document '{}': {}", doc_uuid, e))
+}
+
+// The references of other documents to a document
+pub async fn incoming(org_id: &str, doc_uuid: Uuid) -> Result<Vec<DocumentLinkRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_document_backlinks(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load the references to document '{}': {}", doc_uuid, e))
+}
@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+use crate::config;
+
+// Limit on the document loads running at once.
+// Every load reads a snapshot and often writes back a migration or a replayed journal, a burst of rooms
+// opening after a deploy would otherwise take every connection of the database pool. Loads above the
+// limit wait in a bounded queue for a while, loads that find the queue full or wait too long fail right
+// away and the client retries joining the room.
+
+/// Error of a load refused because too many loads are running and waiting
+pub const SATURATED_ERROR: &str = "Too many documents are being loaded, try again later";
+
+static PERMITS: OnceLock<Option<Arc<Semaphore>>> = OnceLock::new();
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+static WAITED_TOTAL: AtomicU64 = AtomicU64::new(0);
+static WAIT_MS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static REJECTED_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+// The permits of the loads, None when loads aren't limited
+fn get_permits() -> Option<&'static Arc<Semaphore>> {
+    PERMITS
+        .get_or_init(|| match config::get_config().max_concurrent_loads.unwrap_or(8) {
+            0 => None,
+            max => Some(Arc::new(Semaphore::new(max))),
+        })
+        .as_ref()
+}
+
+fn queue_size() -> usize {
+    config::get_config().load_queue_size.unwrap_or(256)
+}
+
+fn queue_timeout() -> Duration {
+    Duration::from_millis(config::get_config().load_queue_timeout_ms.unwrap_or(10 * 1000))
+}
+
+/// A running load, the next one in the queue starts when it is dropped
+pub struct LoadPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for LoadPermit {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Wait for a load to be allowed to start, fails when the queue is full or the wait took too long
+pub async fn acquire(doc_id: &str) -> Result<LoadPermit, String> {
+    let Some(permits) = get_permits() else {
+        RUNNING.fetch_add(1, Ordering::Relaxed);
+        return Ok(LoadPermit { _permit: None });
+    };
+    if let Ok(permit) = permits.clone().try_acquire_owned() {
+        RUNNING.fetch_add(1, Ordering::Relaxed);
+        return Ok(LoadPermit { _permit: Some(permit) });
+    }
+
+    // Join the queue unless it is full
+    if QUEUED.fetch_add(1, Ordering::SeqCst) >= queue_size() {
+        QUEUED.fetch_sub(1, Ordering::SeqCst);
+        REJECTED_TOTAL.fetch_add(1, Ordering::Relaxed);
+        warn!("Refusing to load document {}, the load queue is full", doc_id);
+        return Err(SATURATED_ERROR.to_string());
+    }
+    let started = Instant::now();
+    let acquired = tokio::time::timeout(queue_timeout(), permits.clone().acquire_owned()).await;
+    QUEUED.fetch_sub(1, Ordering::SeqCst);
+    WAITED_TOTAL.fetch_add(1, Ordering::Relaxed);
+    WAIT_MS_TOTAL.fetch_add(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+
+    match acquired {
+        Ok(Ok(permit)) => {
+            RUNNING.fetch_add(1, Ordering::Relaxed);
+            Ok(LoadPermit { _permit: Some(permit) })
+        }
+        Ok(Err(e)) => Err(format!("Load limit closed: {}", e)),
+        Err(_) => {
+            REJECTED_TOTAL.fetch_add(1, Ordering::Relaxed);
+            warn!("Refusing to load document {}, it waited {:?} in the load queue", doc_id, queue_timeout());
+            Err(SATURATED_ERROR.to_string())
+        }
+    }
+}
+
+/// Loads waiting in the queue
+pub fn queue_depth() -> usize {
+    QUEUED.load(Ordering::SeqCst)
+}
+
+/// Loads running
+pub fn running() -> usize {
+    RUNNING.load(Ordering::Relaxed)
+}
+
+/// Loads that waited in the queue, whether they started or not
+pub fn waited_total() -> u64 {
+    WAITED_TOTAL.load(Ordering::Relaxed)
+}
+
+/// Total time loads spent in the queue, in seconds
+pub fn wait_seconds_total() -> f64 {
+    WAIT_MS_TOTAL.load(Ordering::Relaxed) as f64 / 1000.0
+}
+
+/// Loads refused because the queue was full or they waited too long
+pub fn rejected_total() -> u64 {
+    REJECTED_TOTAL.load(Ordering::Relaxed)
+}
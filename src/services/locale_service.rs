@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use moka::sync::Cache;
+use regex::Regex;
+use tracing::{error, warn};
+use crate::config;
+use crate::db::dbcolab::{self, OrgLocaleSettingsRow};
+use crate::models::{LocaleSettings, ResolvedLocaleSettings};
+use crate::services::doc_type_service;
+
+// Locale settings per organization, used to render dates in exports and digests.
+// Settings are resolved per content type: the settings of the content type, then those of the org,
+// then the defaults of the service. Without a date format the locale picks one. Content types an org
+// registers with its document types are the only ones it can override, any when it registers none.
+
+// How long the settings of an org are cached before they are read from the database again
+const SETTINGS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+const DEFAULT_LOCALE: &str = "en-US";
+const DEFAULT_TIMEZONE: &str = "UTC";
+
+static SETTINGS_CACHE: OnceLock<Cache<String, OrgLocale>> = OnceLock::new();
+static LOCALE_TAG: OnceLock<Regex> = OnceLock::new();
+
+/// The locale settings stored by an org
+#[derive(Debug, Clone, Default)]
+pub struct OrgLocale {
+    pub settings: LocaleSettings,
+    pub content_types: HashMap<String, LocaleSettings>,
+}
+
+/// Resolved settings, ready to format dates
+#[derive(Debug, Clone)]
+pub struct Locale {
+    pub locale: String,
+    pub timezone: Tz,
+    pub date_format: String,
+    pub datetime_format: String,
+}
+
+fn get_cache() -> &'static Cache<String, OrgLocale> {
+    SETTINGS_CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(SETTINGS_CACHE_TTL)
+            .build()
+    })
+}
+
+// The content type overrides of a stored row
+pub fn content_types_of(row: &OrgLocaleSettingsRow) -> HashMap<String, LocaleSettings> {
+    serde_json::from_value(row.content_types.clone()).unwrap_or_else(|e| {
+        error!("Invalid locale settings per content type: {}", e);
+        HashMap::new()
+    })
+}
+
+pub fn settings_of(row: &OrgLocaleSettingsRow) -> LocaleSettings {
+    LocaleSettings {
+        locale: row.locale.clone(),
+        timezone: row.timezone.clone(),
+        date_format: row.date_format.clone(),
+        datetime_format: row.datetime_format.clone(),
+    }
+}
+
+// The settings of an org. When they can't be loaded the defaults apply, exports never fail on them.
+pub async fn get_org_locale(org_id: &str) -> OrgLocale {
+    if let Some(org_locale) = get_cache().get(org_id) {
+        return org_locale;
+    }
+
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return OrgLocale::default(),
+    };
+    let org_locale = match db.get_org_locale_settings(org_id).await {
+        Ok(Some(row)) => OrgLocale { settings: settings_of(&row), content_types: content_types_of(&row) },
+        Ok(None) => OrgLocale::default(),
+        Err(e) => {
+            error!("Failed to load locale settings of organization '{}': {}", org_id, e);
+            return OrgLocale::default();
+        }
+    };
+    get_cache().insert(org_id.to_string(), org_locale.clone());
+    org_locale
+}
+
+/// The settings for the documents of a content type in an org, or for the org without a content type
+pub async fn resolve(org_id: &str, content_type: Option<&str>) -> Locale {
+    get_org_locale(org_id).await.resolve(content_type)
+}
+
+impl OrgLocale {
+    pub fn resolve(&self, content_type: Option<&str>) -> Locale {
+        let overrides = content_type.and_then(|content_type| self.content_types.get(content_type));
+        let pick = |field: fn(&LocaleSettings) -> &Option<String>| {
+            overrides.and_then(|settings| field(settings).clone()).or_else(|| field(&self.settings).clone())
+        };
+
+        let service = config::get_config();
+        let locale = pick(|settings| &settings.locale)
+            .or_else(|| service.default_locale.clone())
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+        let timezone_name = pick(|settings| &settings.timezone)
+            .or_else(|| service.default_timezone.clone())
+            .unwrap_or_else(|| DEFAULT_TIMEZONE.to_string());
+        let timezone = timezone_name.parse::<Tz>().unwrap_or_else(|_| {
+            warn!("Unknown timezone '{}', using UTC", timezone_name);
+            Tz::UTC
+        });
+
+        let (locale_date, locale_datetime) = locale_formats(&locale);
+        let date_format = pick(|settings| &settings.date_format)
+            .filter(|format| is_valid_format(format))
+            .unwrap_or_else(|| locale_date.to_string());
+        let datetime_format = pick(|settings| &settings.datetime_format)
+            .filter(|format| is_valid_format(format))
+            .unwrap_or_else(|| locale_datetime.to_string());
+        Locale { locale, timezone, date_format, datetime_format }
+    }
+}
+
+impl Locale {
+    pub fn format_date(&self, date: &DateTime<Utc>) -> String {
+        date.with_timezone(&self.timezone).format(&self.date_format).to_string()
+    }
+
+    pub fn format_datetime(&self, date: &DateTime<Utc>) -> String {
+        date.with_timezone(&self.timezone).format(&self.datetime_format).to_string()
+    }
+
+    pub fn to_resolved(&self) -> ResolvedLocaleSettings {
+        ResolvedLocaleSettings {
+            locale: self.locale.clone(),
+            timezone: self.timezone.name().to_string(),
+            date_format: self.date_format.clone(),
+            datetime_format: self.datetime_format.clone(),
+        }
+    }
+}
+
+// The date and timestamp formats customary for a locale
+fn locale_formats(locale: &str) -> (&'static str, &'static str) {
+    let mut parts = locale.split(['-', '_']);
+    let language = parts.next().unwrap_or_default().to_lowercase();
+    let region = parts.next().unwrap_or_default().to_uppercase();
+    match (language.as_str(), region.as_str()) {
+        ("en", "US") => ("%m/%d/%Y", "%m/%d/%Y %I:%M %p %Z"),
+        ("en", _) | ("fr", _) | ("es", _) | ("it", _) | ("pt", _) => ("%d/%m/%Y", "%d/%m/%Y %H:%M %Z"),
+        ("de", _) | ("pl", _) | ("ru", _) => ("%d.%m.%Y", "%d.%m.%Y %H:%M %Z"),
+        ("nl", _) => ("%d-%m-%Y", "%d-%m-%Y %H:%M %Z"),
+        ("ja", _) | ("zh", _) | ("ko", _) => ("%Y/%m/%d", "%Y/%m/%d %H:%M %Z"),
+        _ => ("%Y-%m-%d", "%Y-%m-%d %H:%M %Z"),
+    }
+}
+
+// Formatting with an invalid strftime format fails, those are never used
+fn is_valid_format(format: &str) -> bool {
+    !format.trim().is_empty() && !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+// Check the settings of one level
+fn validate(settings: &LocaleSettings, level: &str) -> Result<(), String> {
+    let locale_tag = LOCALE_TAG.get_or_init(|| Regex::new(r"^[A-Za-z]{2,3}([-_][A-Za-z0-9]{2,8})*$").unwrap());
+    if let Some(locale) = settings.locale.as_deref().filter(|locale| !locale_tag.is_match(locale)) {
+        return Err(format!("Invalid locale '{}' {}, expected a language tag like 'en-US'", locale, level));
+    }
+    if let Some(timezone) = settings.timezone.as_deref().filter(|timezone| timezone.parse::<Tz>().is_err()) {
+        return Err(format!("Unknown timezone '{}' {}, expected an IANA timezone like 'Europe/Brussels'", timezone, level));
+    }
+    for format in [&settings.date_format, &settings.datetime_format].into_iter().flatten() {
+        if !is_valid_format(format) {
+            return Err(format!("Invalid date format '{}' {}", format, level));
+        }
+    }
+    Ok(())
+}
+
+pub async fn get(org_id: &str) -> Result<Option<OrgLocaleSettingsRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_org_locale_settings(org_id)
+        .await
+        .map_err(|e| format!("Failed to load locale settings of organization '{}': {}", org_id, e))
+}
+
+// Replace the locale settings of an org
+pub async fn set(org_id: &str, settings: &LocaleSettings, content_types: &HashMap<String, LocaleSettings>, by_prpl: &str) -> Result<Result<OrgLocaleSettingsRow, String>, String> {
+    if let Err(e) = validate(settings, "for the organization") {
+        return Ok(Err(e));
+    }
+    let registered: Vec<String> = doc_type_service::get_registry(org_id).await.types.into_values().flatten().collect();
+    for (content_type, overrides) in content_types {
+        if !registered.is_empty() && !registered.contains(content_type) {
+            return Ok(Err(format!("Content type '{}' isn't registered with the document types of the organization", content_type)));
+        }
+        if let Err(e) = validate(overrides, &format!("for content type '{}'", content_type)) {
+            return Ok(Err(e));
+        }
+    }
+
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let content_types_json = serde_json::to_value(content_types).map_err(|e| format!("Failed to serialize locale settings: {}", e))?;
+    let row = db.set_org_locale_settings(
+        org_id,
+        settings.locale.as_deref(),
+        settings.timezone.as_deref(),
+        settings.date_format.as_deref(),
+        settings.datetime_format.as_deref(),
+        content_types_json,
+        by_prpl,
+    )
+    .await
+    .map_err(|e| format!("Failed to set locale settings of organization '{}': {}", org_id, e))?;
+    get_cache().invalidate(org_id);
+    Ok(Ok(row))
+}
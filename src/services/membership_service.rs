@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use loro_protocol::CrdtType;
+use loro_websocket_server::protocol::Permission;
+use loro_websocket_server::HubRegistry;
+use tracing::info;
+use crate::services::lazy_block_service;
+use crate::ws::{connctx, docctx::DocContext, userctx, wscolab};
+
+/// Outcome of re-evaluating the live connections of a user
+#[derive(Debug, Default)]
+pub struct Reevaluation {
+    pub cached: bool,
+    pub connections: usize,
+    pub rooms: usize,
+    pub downgraded: usize,
+    pub revoked: usize,
+    pub closed_rooms: Vec<String>,
+}
+
+// Apply principals pushed by the app service: update the cached user context and re-evaluate the permissions
+// of every live connection of the user on the rooms it joined. Connections that may no longer write are
+// downgraded to read. Rooms a connection may no longer view are closed, every client reconnects and is
+// authorized again, so only the connections that lost access stay out.
+pub async fn apply_principals(registry: &Arc<HubRegistry<DocContext>>, uid: &str, principals: Vec<String>) -> Reevaluation {
+    let mut outcome = Reevaluation::default();
+    let user_ctx = match userctx::update_principals(uid, principals) {
+        Some(ctx) => ctx,
+        None => {
+            info!("User {} is not cached, nothing to re-evaluate", uid);
+            return outcome;
+        }
+    };
+    outcome.cached = true;
+
+    // 1. Find the live connections of the user
+    let connections: Vec<(u64, connctx::ConnCtx)> = connctx::get_conn_ctx_cache()
+        .iter()
+        .filter(|(_, ctx)| ctx.uid == uid)
+        .map(|(conn_id, ctx)| (*conn_id, ctx))
+        .collect();
+    outcome.connections = connections.len();
+
+    // 2. Re-evaluate every room they joined
+    let mut to_close: HashSet<(String, String)> = HashSet::new();
+    for (conn_id, conn_ctx) in &connections {
+        // The pushed principals with the roles of the token of the connection
+        let conn_user_ctx = userctx::UserCtx { principals: user_ctx.principals.clone(), token_roles: conn_ctx.roles.clone() };
+        for room in connctx::joined_rooms(*conn_id) {
+            outcome.rooms += 1;
+            // Block rooms of lazily loaded sheets are authorized on their sheet
+            let sheet_id = lazy_block_service::parse_room(&room).0;
+            match wscolab::authorize_user(conn_ctx, sheet_id, &conn_user_ctx).await {
+                Ok(Some(Permission::Write)) => connctx::set_writable(*conn_id, &room, true),
+                Ok(Some(_)) => {
+                    if connctx::is_writable(*conn_id, &room) {
+                        outcome.downgraded += 1;
+                    }
+                    connctx::set_writable(*conn_id, &room, false);
+                }
+                Ok(None) | Err(_) => {
+                    outcome.revoked += 1;
+                    connctx::set_writable(*conn_id, &room, false);
+                    to_close.insert((conn_ctx.org_id.clone(), room));
+                }
+            }
+        }
+    }
+
+    // 3. Drop the access that was lost
+    for (org_id, room) in to_close {
+        info!("User {} lost access to document {}, closing its room", uid, room);
+        registry.close_room(&org_id, CrdtType::Loro, &room, true).await;
+        outcome.closed_rooms.push(room);
+    }
+    if outcome.revoked > 0 || outcome.downgraded > 0 {
+        info!(
+            "Principals of user {} changed: {} rooms downgraded to read, {} revoked",
+            uid, outcome.downgraded, outcome.revoked
+        );
+    }
+    outcome
+}
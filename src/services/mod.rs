@@ -1,4 +1,84 @@
 pub mod doc_db_service;
 pub mod doc_edit_service;
+pub mod doc_load_service;
+pub mod hub_service;
+pub mod initial_sync_service;
+pub mod lazy_block_service;
+pub mod acl_service;
+pub mod csv_service;
+pub mod comment_service;
+pub mod suggestion_service;
+pub mod approval_round_service;
+pub mod webhook_service;
+pub mod workflow_service;
+pub mod citation_service;
+pub mod evidence_service;
+pub mod signing_service;
+pub mod statement_subdoc_service;
+pub mod transclusion_service;
+pub mod link_index_service;
+pub mod dedup_service;
+pub mod panic_guard_service;
+pub mod version_vector_service;
+pub mod negotiation_service;
+pub mod render_service;
+pub mod doc_changes_service;
+pub mod range_service;
+pub mod doc_create_service;
+pub mod init_hook_service;
+pub mod doc_type_service;
+pub mod numbering_service;
+pub mod replace_service;
+pub mod bulk_replace_service;
+pub mod compare_service;
+pub mod resync_service;
+pub mod room_assignment_service;
+pub mod watchdog_service;
+pub mod limits_service;
+pub mod quarantine_service;
+pub mod storage_service;
+pub mod archival_service;
+pub mod cold_storage_service;
+pub mod playback_service;
+pub mod blame_service;
+pub mod revert_service;
+pub mod reconcile_service;
+pub mod save_policy_service;
+pub mod drain_service;
+pub mod membership_service;
+pub mod save_status_service;
+pub mod save_retry_service;
+pub mod journal_service;
+pub mod feature_service;
+pub mod doc_settings_service;
+pub mod grid_export_service;
+pub mod sheet_import_service;
+pub mod public_view_service;
+pub mod embed_service;
+pub mod summary_service;
+pub mod policy_scan_service;
+pub mod analytics_service;
+pub mod org_diagnostics_service;
+pub mod billing_service;
+pub mod selftest_service;
 
 pub mod auth_service;
+pub mod guest_service;
+pub mod recording_service;
+pub mod client_version_service;
+pub mod schema_migration_service;
+pub mod legacy_approvals_service;
+pub mod reaction_service;
+pub mod user_prefs_service;
+pub mod unread_service;
+pub mod digest_service;
+pub mod watch_service;
+pub mod announcement_service;
+pub mod json_projection_service;
+pub mod save_verification_service;
+pub mod locale_service;
+pub mod shutdown_service;
+pub mod script_service;
+pub mod load_limit_service;
+pub mod acl_cache_service;
+pub mod cors_service;
@@ -0,0 +1,121 @@
+use axum::http::{header, HeaderMap, StatusCode};
+use crate::models::{api_error, ApiError};
+
+// Content negotiation of the document and export endpoints.
+// The Accept header picks the representation, media ranges and q-values are honored and the order
+// of the supported representations breaks ties. A request without Accept, or accepting anything,
+// gets the first supported representation, which keeps the `format` query param of the JSON
+// endpoints working as before.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Representation {
+    // The JSON envelope, with the JSON and/or the base64 snapshot picked by `format`
+    Json,
+    // The raw snapshot
+    Binary,
+    Markdown,
+    Html,
+    Pdf,
+    Csv,
+    Zip,
+}
+
+// Representations of a document, the JSON envelope first as the answer to requests without Accept header
+pub const DOCUMENT_REPRESENTATIONS: [Representation; 5] = [
+    Representation::Json,
+    Representation::Binary,
+    Representation::Markdown,
+    Representation::Html,
+    Representation::Pdf,
+];
+
+impl Representation {
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Representation::Json => "application/json",
+            Representation::Binary => "application/octet-stream",
+            Representation::Markdown => "text/markdown",
+            Representation::Html => "text/html",
+            Representation::Pdf => "application/pdf",
+            Representation::Csv => "text/csv",
+            Representation::Zip => "application/zip",
+        }
+    }
+
+    // Value of the Content-Type header
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Representation::Markdown => "text/markdown; charset=utf-8",
+            Representation::Html => "text/html; charset=utf-8",
+            Representation::Csv => "text/csv; charset=utf-8",
+            other => other.media_type(),
+        }
+    }
+}
+
+// A media range of the Accept header with its quality
+struct MediaRange {
+    media_type: String,
+    quality: f32,
+}
+
+fn parse_accept(accept: &str) -> Vec<MediaRange> {
+    accept
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let media_type = params.next()?.trim().to_lowercase();
+            if media_type.is_empty() {
+                return None;
+            }
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q=").map(|q| q.trim().to_string()))
+                .next()
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some(MediaRange { media_type, quality })
+        })
+        .collect()
+}
+
+// Quality of a representation under the media ranges, the most specific matching range wins
+fn quality_of(representation: Representation, ranges: &[MediaRange]) -> f32 {
+    let media_type = representation.media_type();
+    let main_type = media_type.split('/').next().unwrap_or_default();
+    let exact = ranges.iter().find(|range| range.media_type == media_type);
+    let main = ranges.iter().find(|range| range.media_type.strip_suffix("/*") == Some(main_type));
+    let any = ranges.iter().find(|range| range.media_type == "*/*");
+    exact.or(main).or(any).map(|range| range.quality).unwrap_or(0.0)
+}
+
+// Pick the representation of a response, 406 when the Accept header allows none of the supported ones
+pub fn negotiate(headers: &HeaderMap, supported: &[Representation]) -> Result<Representation, ApiError> {
+    let default = *supported.first().expect("an endpoint supports at least one representation");
+    let accept = match headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()).map(str::trim) {
+        Some(accept) if !accept.is_empty() => accept,
+        _ => return Ok(default),
+    };
+    let ranges = parse_accept(accept);
+    let mut best: Option<(Representation, f32)> = None;
+    for representation in supported {
+        let quality = quality_of(*representation, &ranges);
+        if quality > 0.0 && best.map_or(true, |(_, q)| quality > q) {
+            best = Some((*representation, quality));
+        }
+    }
+    best.map(|(representation, _)| representation).ok_or_else(|| {
+        let media_types: Vec<&str> = supported.iter().map(|representation| representation.media_type()).collect();
+        api_error(StatusCode::NOT_ACCEPTABLE, format!("None of '{}' can be produced, supported are: {}", accept, media_types.join(", ")))
+    })
+}
+
+// The `format` query param only shapes the JSON envelope, other representations don't combine with it
+pub fn ensure_format_applies(representation: Representation, format: Option<&str>) -> Result<(), ApiError> {
+    match format.map(str::trim).filter(|format| !format.is_empty()) {
+        Some(format) if representation != Representation::Json => Err(api_error(
+            StatusCode::NOT_ACCEPTABLE,
+            format!("The format '{}' only applies to application/json, not to {}", format, representation.media_type()),
+        )),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use loro::{LoroDoc, ToJson};
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab;
+use crate::doctypes;
+use crate::services::{acl_service, doc_edit_service};
+use crate::ws::docctx::DocContext;
+
+// Stable numbers of documents and blocks, e.g. CLM-0042.
+// The prefix is configured per content type with DOC_NUMBER_PREFIXES, e.g. `claim=CLM;datasheet=DS`,
+// other content types use the first letters of their name. Statements are numbered when they are
+// created, blocks of sheets when the sheet is saved. The number is stored in the document as
+// `number` and recorded in the database, which is the reference: a number is never handed out twice,
+// and a number that is changed or removed in the document is put back after the next save.
+
+pub const NUMBER_FIELD: &str = "number";
+
+// Block id, number, prefix and sequence number of a number to record
+type NumberEntry = (String, String, String, i32);
+
+static PREFIXES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+// The latest saved JSON per document waiting to be numbered, saves in between are skipped
+static PENDING: OnceLock<Mutex<HashMap<Uuid, (String, Value)>>> = OnceLock::new();
+static PENDING_NOTIFY: Notify = Notify::const_new();
+
+fn get_pending() -> &'static Mutex<HashMap<Uuid, (String, Value)>> {
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// The prefixes per content type, as configured
+fn prefixes() -> &'static HashMap<String, String> {
+    PREFIXES.get_or_init(|| {
+        let configured = config::get_config().doc_number_prefixes.clone().unwrap_or_default();
+        configured
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| match entry.split_once('=') {
+                Some((content_type, prefix)) if !prefix.trim().is_empty() => Some((content_type.trim().to_string(), prefix.trim().to_uppercase())),
+                _ => {
+                    warn!("Ignoring number prefix '{}' without a content type or prefix", entry);
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+/// The prefix of the numbers of a content type
+pub fn prefix_for(content_type: &str) -> String {
+    if let Some(prefix) = prefixes().get(content_type) {
+        return prefix.clone();
+    }
+    let prefix: String = content_type.chars().filter(|c| c.is_ascii_alphanumeric()).take(3).collect::<String>().to_uppercase();
+    if prefix.is_empty() { "DOC".to_string() } else { prefix }
+}
+
+fn format_number(prefix: &str, seq: i32) -> String {
+    format!("{}-{:04}", prefix, seq)
+}
+
+/// A number as it is looked up, numbers are stored in upper case
+pub fn normalize(number: &str) -> String {
+    number.trim().to_uppercase()
+}
+
+// Reserve and record numbers for the given parts of a document
+async fn assign(org_id: &str, doc_uuid: Uuid, content_type: &str, blocks: Vec<String>, by_prpl: &str) -> Result<Vec<NumberEntry>, String> {
+    if blocks.is_empty() {
+        return Ok(Vec::new());
+    }
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let prefix = prefix_for(content_type);
+    let seqs = db.reserve_number_sequence(org_id, &prefix, blocks.len() as i32)
+        .await
+        .map_err(|e| format!("Failed to reserve numbers with prefix '{}': {}", prefix, e))?;
+    let entries: Vec<NumberEntry> = blocks
+        .into_iter()
+        .zip(seqs)
+        .map(|(block_id, seq)| (block_id, format_number(&prefix, seq), prefix.clone(), seq))
+        .collect();
+    db.insert_document_numbers(org_id, doc_uuid, &entries, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to record the numbers of document '{}': {}", doc_uuid, e))?;
+    Ok(entries)
+}
+
+// Set the number of the document, or of one of its blocks
+fn write_number(doc: &LoroDoc, block_id: &str, number: &str) -> Result<(), String> {
+    let map = if block_id.is_empty() {
+        doc.get_map("properties")
+    } else {
+        acl_service::find_scope_map(doc, &format!("/content/{}", block_id))?
+    };
+    map.insert(NUMBER_FIELD, number).map_err(|e| format!("Failed to set the number: {}", e))
+}
+
+// Number a document being created, numbers copied from a template are replaced.
+// The numbers are recorded before the document is stored, a failed creation leaves them unused.
+pub async fn number_new_doc(org_id: &str, doc_uuid: Uuid, doc: &LoroDoc, by_prpl: &str) -> Result<(), String> {
+    let json = doc.get_deep_value().to_json_value();
+    let doc_type = json.pointer("/properties/type").and_then(|t| t.as_str()).unwrap_or_default();
+    let content_type = json.pointer("/properties/contentType").and_then(|t| t.as_str()).unwrap_or_default();
+    let blocks = doctypes::get_or_error(doc_type)?
+        .numbered_parts(&json)
+        .into_iter()
+        .map(|part| part.block.unwrap_or_default())
+        .collect();
+    for (block_id, number, _, _) in assign(org_id, doc_uuid, content_type, blocks, by_prpl).await? {
+        write_number(doc, &block_id, &number)?;
+    }
+    doc.commit();
+    Ok(())
+}
+
+// Queue the JSON of a saved document, to number its new blocks and put back changed numbers
+pub fn schedule(org_id: &str, doc_uuid: Uuid, json: Value) {
+    get_pending().lock().unwrap().insert(doc_uuid, (org_id.to_string(), json));
+    PENDING_NOTIFY.notify_one();
+}
+
+// Start the worker handling the queued documents one at a time
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>) {
+    tokio::spawn(async move {
+        loop {
+            PENDING_NOTIFY.notified().await;
+            loop {
+                let next = {
+                    let mut pending = get_pending().lock().unwrap();
+                    let doc_uuid = pending.keys().next().copied();
+                    doc_uuid.and_then(|doc_uuid| pending.remove(&doc_uuid).map(|(org_id, json)| (doc_uuid, org_id, json)))
+                };
+                let (doc_uuid, org_id, json) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                if let Err(e) = process(&registry, &org_id, doc_uuid, &json).await {
+                    error!("Failed to number document '{}': {}", doc_uuid, e);
+                }
+            }
+        }
+    });
+}
+
+async fn process(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, json: &Value) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let doc_type = match json.pointer("/properties/type").and_then(|t| t.as_str()).and_then(doctypes::get) {
+        Some(doc_type) => doc_type,
+        None => return Ok(()),
+    };
+    let content_type = json.pointer("/properties/contentType").and_then(|t| t.as_str()).unwrap_or_default();
+
+    // 1. Compare the numbers in the document with the recorded ones
+    let recorded: HashMap<String, String> = db.get_document_numbers(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load the numbers: {}", e))?
+        .into_iter()
+        .map(|row| (row.block_id, row.number))
+        .collect();
+    let mut writes: Vec<(String, String)> = Vec::new();
+    let mut unnumbered: Vec<String> = Vec::new();
+    for part in doc_type.numbered_parts(json) {
+        let block_id = part.block.unwrap_or_default();
+        match recorded.get(&block_id) {
+            Some(number) if part.number.as_ref() == Some(number) => {}
+            Some(number) => {
+                warn!("Number of '{}' in document {} was changed to {:?}, restoring {}", block_id, doc_uuid, part.number, number);
+                writes.push((block_id, number.clone()));
+            }
+            None => unnumbered.push(block_id),
+        }
+    }
+
+    // 2. Number the new parts, numbers typed or copied in are replaced
+    for (block_id, number, _, _) in assign(org_id, doc_uuid, content_type, unnumbered, "s/colabri-doc").await? {
+        writes.push((block_id, number));
+    }
+    if writes.is_empty() {
+        return Ok(());
+    }
+
+    // 3. Write the numbers into the document, blocks removed in the meantime keep their recorded number
+    let count = writes.len();
+    doc_edit_service::edit_doc(registry.clone(), org_id, &doc_uuid.to_string(), move |doc: &LoroDoc| {
+        for (block_id, number) in &writes {
+            if let Err(e) = write_number(doc, block_id, number) {
+                warn!("Failed to number '{}' as {}: {}", block_id, number, e);
+            }
+        }
+        doc.commit();
+        Ok(())
+    }, false).await?;
+    info!("Numbered {} parts of document {}", count, doc_uuid);
+    Ok(())
+}
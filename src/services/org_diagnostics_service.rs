@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use loro_websocket_server::HubRegistry;
+use crate::config;
+use crate::models::OrgDiagnostics;
+use crate::services::{hub_service, save_retry_service};
+use crate::ws::docctx::DocContext;
+
+// Activity per organization, shared by the org diagnostics and the metrics. When aggregated, the orgs
+// with fewer connections than DIAGNOSTICS_ORG_MIN_CONN are merged into a single entry without identifier.
+
+/// Connections an organization needs to be listed on its own when aggregated
+pub fn min_org_conn() -> u32 {
+    config::get_config().diagnostics_org_min_conn.unwrap_or(5)
+}
+
+/// The activity per organization, busiest first. The bucket of small orgs comes last.
+pub async fn collect(registry: &Arc<HubRegistry<DocContext>>, aggregate: bool) -> Vec<OrgDiagnostics> {
+
+    // 1. Count the activity per org
+    let mut per_org: BTreeMap<String, OrgDiagnostics> = BTreeMap::new();
+    for room in hub_service::cached_rooms(registry).await.iter() {
+        let stats = org_entry(&mut per_org, room.org.clone());
+        stats.n_rooms += 1;
+        if room.is_doc {
+            stats.n_doc_rooms += 1;
+        }
+        if room.dirty {
+            stats.n_dirty_docs += 1;
+        }
+        stats.n_conn += room.subscribers as u32;
+    }
+    for save in save_retry_service::failing_saves() {
+        org_entry(&mut per_org, save.org).n_failing_saves += 1;
+    }
+
+    // 2. Merge the orgs below the threshold into a single bucket without identifier
+    let min_org_conn = min_org_conn();
+    let mut orgs: Vec<OrgDiagnostics> = Vec::new();
+    let mut other = OrgDiagnostics {
+        org_id: None,
+        n_orgs: 0,
+        n_conn: 0,
+        n_rooms: 0,
+        n_doc_rooms: 0,
+        n_dirty_docs: 0,
+        n_failing_saves: 0,
+    };
+    for (_, stats) in per_org {
+        if !aggregate || stats.n_conn >= min_org_conn {
+            orgs.push(stats);
+            continue;
+        }
+        other.n_orgs += 1;
+        other.n_conn += stats.n_conn;
+        other.n_rooms += stats.n_rooms;
+        other.n_doc_rooms += stats.n_doc_rooms;
+        other.n_dirty_docs += stats.n_dirty_docs;
+        other.n_failing_saves += stats.n_failing_saves;
+    }
+    orgs.sort_by(|a, b| b.n_conn.cmp(&a.n_conn));
+    if other.n_orgs > 0 {
+        orgs.push(other);
+    }
+    orgs
+}
+
+fn org_entry(per_org: &mut BTreeMap<String, OrgDiagnostics>, org: String) -> &mut OrgDiagnostics {
+    per_org.entry(org.clone()).or_insert_with(|| OrgDiagnostics {
+        org_id: Some(org),
+        n_orgs: 1,
+        n_conn: 0,
+        n_rooms: 0,
+        n_doc_rooms: 0,
+        n_dirty_docs: 0,
+        n_failing_saves: 0,
+    })
+}
@@ -0,0 +1,74 @@
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use futures_util::FutureExt;
+use tracing::error;
+use uuid::Uuid;
+use crate::services::{lazy_block_service, quarantine_service};
+
+// Isolation of panics in the CRDT callbacks.
+// The load, save and update callbacks of the websocket server and the edit closures run through this
+// guard. A panic is turned into a CallbackPanic error for the caller instead of unwinding into the
+// room, it is logged with the document and the document is quarantined so the panic doesn't repeat
+// on every load.
+
+/// A panic caught in a CRDT callback
+#[derive(Debug, Clone)]
+pub struct CallbackPanic {
+    pub callback: &'static str,
+    pub org_id: String,
+    pub room: String,
+    pub message: String,
+}
+
+impl fmt::Display for CallbackPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Panic in {} of room '{}' in organization '{}': {}", self.callback, self.room, self.org_id, self.message)
+    }
+}
+
+impl std::error::Error for CallbackPanic {}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+fn caught(callback: &'static str, org_id: &str, room: &str, payload: &(dyn Any + Send)) -> CallbackPanic {
+    let panic = CallbackPanic {
+        callback,
+        org_id: org_id.to_string(),
+        room: room.to_string(),
+        message: panic_message(payload),
+    };
+    error!("{}", panic);
+
+    // Quarantine the document the room belongs to, block rooms quarantine their sheet
+    let (doc_id, _) = lazy_block_service::parse_room(room);
+    if let Ok(doc_uuid) = Uuid::parse_str(doc_id) {
+        let (org_id, reason) = (panic.org_id.clone(), panic.to_string());
+        tokio::spawn(async move {
+            quarantine_service::quarantine(&org_id, doc_uuid, &reason).await;
+        });
+    }
+    panic
+}
+
+// Run the future of a callback, a panic is returned as error
+pub async fn run<T>(callback: &'static str, org_id: &str, room: &str, fut: impl Future<Output = T>) -> Result<T, CallbackPanic> {
+    AssertUnwindSafe(fut)
+        .catch_unwind()
+        .await
+        .map_err(|payload| caught(callback, org_id, room, payload.as_ref()))
+}
+
+// Run a synchronous callback, like the closure of an edit, a panic is returned as error
+pub fn run_sync<T>(callback: &'static str, org_id: &str, room: &str, f: impl FnOnce() -> T) -> Result<T, CallbackPanic> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| caught(callback, org_id, room, payload.as_ref()))
+}
@@ -0,0 +1,160 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use loro::{ChangeMeta, LoroDoc, ToJson, VersionVector, ID};
+use moka::sync::Cache;
+use crate::models::{DocumentPlaybackResponse, PlaybackFrame};
+
+/// The maximum number of frames in a single playback, larger histories need a larger step
+pub const MAX_FRAMES: usize = 500;
+
+// Playbacks are cached by document, version and range, a UI scrubbing back and forth reuses them
+static PLAYBACK_CACHE: OnceLock<Cache<String, Arc<DocumentPlaybackResponse>>> = OnceLock::new();
+
+/// What every frame of a playback contains
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlaybackFormat {
+    State,
+    Diff,
+}
+
+impl PlaybackFormat {
+    pub fn from_query(format: Option<&str>) -> Result<Self, String> {
+        match format.unwrap_or("state") {
+            "state" => Ok(PlaybackFormat::State),
+            "diff" => Ok(PlaybackFormat::Diff),
+            other => Err(format!("Invalid format '{}', expected 'state' or 'diff'", other)),
+        }
+    }
+}
+
+/// The part of the history to replay
+pub struct PlaybackRange {
+    pub from: VersionVector,
+    pub to: VersionVector,
+    // Number of changes per frame
+    pub step: usize,
+    pub format: PlaybackFormat,
+}
+
+fn get_cache() -> &'static Cache<String, Arc<DocumentPlaybackResponse>> {
+    PLAYBACK_CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(100)
+            .time_to_live(Duration::from_secs(10 * 60))
+            .build()
+    })
+}
+
+// Replay the history of a document between two version vectors in frames of `step` changes.
+// The document is checked out frame after frame in causal order, so each checkout only moves forward a little.
+pub fn playback(
+    cache_key: &str,
+    doc: &LoroDoc,
+    peer_map: &HashMap<u64, String>,
+    version: u32,
+    range: &PlaybackRange,
+) -> Result<Arc<DocumentPlaybackResponse>, String> {
+    let PlaybackRange { from, to, step, format } = range;
+    let (step, format) = (*step, *format);
+    let key = format!("{}|{}|{}|{}|{:?}", cache_key, vv_key(from), vv_key(to), step, format);
+    if let Some(cached) = get_cache().get(&key) {
+        return Ok(cached);
+    }
+
+    // 1. Make sure the range exists in the history
+    let oplog_vv = doc.oplog_vv();
+    for (peer, counter) in to.iter() {
+        if oplog_vv.get(peer).copied().unwrap_or(0) < *counter {
+            return Err(format!("Version vector goes beyond the history of the document for peer {}", peer));
+        }
+        if from.get(peer).copied().unwrap_or(0) > *counter {
+            return Err(format!("The start of the range is after its end for peer {}", peer));
+        }
+    }
+
+    // 2. Collect the changes in the range in causal order
+    let changes = collect_changes(doc, from, to);
+    let step = step.max(1);
+    let n_frames = changes.len().div_ceil(step);
+    if n_frames > MAX_FRAMES {
+        return Err(format!("The range has {} changes, which gives more than {} frames with step {}", changes.len(), MAX_FRAMES, step));
+    }
+
+    // 3. Walk over the frames
+    let mut frames = Vec::with_capacity(n_frames);
+    let mut vv = from.clone();
+    for (index, chunk) in changes.chunks(step).enumerate() {
+        let prev_vv = vv.clone();
+        let mut authors: BTreeSet<String> = BTreeSet::new();
+        for change in chunk {
+            let peer = change.id.peer;
+            let end = (change.id.counter + change.len as i32).min(to.get(&peer).copied().unwrap_or(0));
+            if vv.get(&peer).copied().unwrap_or(0) < end {
+                vv.insert(peer, end);
+            }
+            authors.insert(peer_map.get(&peer).cloned().unwrap_or_else(|| format!("peer:{}", peer)));
+        }
+
+        let (state, diff) = match format {
+            PlaybackFormat::State => {
+                let frontiers = doc.vv_to_frontiers(&vv);
+                doc.checkout(&frontiers).map_err(|e| format!("Failed to checkout frame {}: {}", index, e))?;
+                (Some(doc.get_deep_value().to_json_value()), None)
+            }
+            PlaybackFormat::Diff => {
+                let updates = doc.export_json_updates_without_peer_compression(&prev_vv, &vv);
+                let diff = serde_json::to_value(&updates).map_err(|e| format!("Failed to serialize frame {}: {}", index, e))?;
+                (None, Some(diff))
+            }
+        };
+
+        frames.push(PlaybackFrame {
+            index,
+            version_v: serde_json::to_value(&vv).map_err(|e| format!("Failed to serialize version vector: {}", e))?,
+            timestamp: chunk.iter().map(|c| c.timestamp).max().and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0)),
+            authors: authors.into_iter().collect(),
+            n_changes: chunk.len(),
+            state,
+            diff,
+        });
+    }
+    doc.checkout_to_latest();
+
+    let response = Arc::new(DocumentPlaybackResponse {
+        version,
+        from_v: serde_json::to_value(from).map_err(|e| format!("Failed to serialize version vector: {}", e))?,
+        to_v: serde_json::to_value(to).map_err(|e| format!("Failed to serialize version vector: {}", e))?,
+        step,
+        frames,
+    });
+    get_cache().insert(key, response.clone());
+    Ok(response)
+}
+
+// The changes between two version vectors, sorted by lamport timestamp.
+// A change only depends on changes with a lower lamport, so every prefix is a valid version.
+fn collect_changes(doc: &LoroDoc, from: &VersionVector, to: &VersionVector) -> Vec<ChangeMeta> {
+    let mut changes: Vec<ChangeMeta> = Vec::new();
+    for (peer, end) in to.iter() {
+        let mut counter = from.get(peer).copied().unwrap_or(0);
+        while counter < *end {
+            match doc.get_change(ID::new(*peer, counter)) {
+                Some(change) => {
+                    counter = change.id.counter + change.len as i32;
+                    changes.push(change);
+                }
+                None => break,
+            }
+        }
+    }
+    changes.sort_by_key(|c| (c.lamport, c.id.peer));
+    changes
+}
+
+fn vv_key(vv: &VersionVector) -> String {
+    let mut entries: Vec<(u64, i32)> = vv.iter().map(|(p, c)| (*p, *c)).collect();
+    entries.sort();
+    entries.iter().map(|(p, c)| format!("{}:{}", p, c)).collect::<Vec<_>>().join(",")
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use chrono::Utc;
+use loro_websocket_server::HubRegistry;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab::{self, DocumentPolicyFindingRow};
+use crate::models::{ColabModel, ColabSheetBlock, ColabStatementModel, DocumentMaintenance};
+use crate::services::citation_service::render_text;
+use crate::services::doc_settings_service;
+use crate::services::feature_service::{self, Feature};
+use crate::ws::docctx::DocContext;
+
+// Principal recorded when the scanner puts a document in maintenance
+const SCANNER_PRPL: &str = "s/colabri-doc";
+
+pub const SEVERITIES: [&str; 4] = ["low", "medium", "high", "critical"];
+
+static SCANNER_CLIENT: OnceLock<Client> = OnceLock::new();
+// The latest saved JSON per document waiting for a scan, saves in between are skipped
+static PENDING: OnceLock<Mutex<HashMap<Uuid, (String, Value)>>> = OnceLock::new();
+static PENDING_NOTIFY: Notify = Notify::const_new();
+
+/// Text of a block or language sent to the scanner
+#[derive(Serialize)]
+pub(crate) struct ScanBlock {
+    pub(crate) path: String,
+    pub(crate) text: String,
+}
+
+#[derive(Serialize)]
+struct ScanRequest<'a> {
+    #[serde(rename = "orgId")]
+    org_id: &'a str,
+    #[serde(rename = "docId")]
+    doc_id: String,
+    blocks: Vec<ScanBlock>,
+}
+
+#[derive(Deserialize)]
+struct ScanResponse {
+    #[serde(default)]
+    findings: Vec<ScanFinding>,
+}
+
+#[derive(Deserialize)]
+struct ScanFinding {
+    path: String,
+    // e.g. "pii", "banned-claim" or "classified"
+    category: String,
+    severity: String,
+    rule: Option<String>,
+    excerpt: Option<String>,
+}
+
+fn get_client() -> &'static Client {
+    SCANNER_CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to build policy scanner client")
+    })
+}
+
+fn get_pending() -> &'static Mutex<HashMap<Uuid, (String, Value)>> {
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Rank of a severity, unknown severities rank lowest
+pub fn severity_rank(severity: &str) -> usize {
+    SEVERITIES.iter().position(|s| *s == severity).unwrap_or(0)
+}
+
+fn maintenance_rank() -> usize {
+    let severity = config::get_config().policy_maintenance_severity.as_deref().unwrap_or("high");
+    SEVERITIES.iter().position(|s| *s == severity).unwrap_or_else(|| {
+        warn!("Unknown policy maintenance severity '{}', using 'high'", severity);
+        2
+    })
+}
+
+pub fn is_configured() -> bool {
+    config::get_config().policy_scanner_url.is_some()
+}
+
+// Queue the JSON of a saved document for a scan
+pub fn schedule(org_id: &str, doc_uuid: Uuid, json: Value) {
+    get_pending().lock().unwrap().insert(doc_uuid, (org_id.to_string(), json));
+    PENDING_NOTIFY.notify_one();
+}
+
+// Start the worker scanning the queued documents one at a time
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>) {
+    let url = match config::get_config().policy_scanner_url.clone() {
+        Some(url) => url,
+        None => return,
+    };
+    info!("🔎 Policy scanner enabled at {}", url);
+
+    tokio::spawn(async move {
+        loop {
+            PENDING_NOTIFY.notified().await;
+            loop {
+                let next = {
+                    let mut pending = get_pending().lock().unwrap();
+                    let doc_uuid = pending.keys().next().copied();
+                    doc_uuid.and_then(|doc_uuid| pending.remove(&doc_uuid).map(|(org_id, json)| (doc_uuid, org_id, json)))
+                };
+                let (doc_uuid, org_id, json) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                if !feature_service::is_enabled(&org_id, Feature::PolicyScanning).await {
+                    continue;
+                }
+                if let Err(e) = scan(&registry, &url, &org_id, doc_uuid, &json).await {
+                    error!("Failed to scan document '{}': {}", doc_uuid, e);
+                }
+            }
+        }
+    });
+}
+
+async fn scan(registry: &Arc<HubRegistry<DocContext>>, url: &str, org_id: &str, doc_uuid: Uuid, json: &Value) -> Result<(), String> {
+
+    // 1. Collect the text per block and language
+    let model: ColabModel = serde_json::from_value(json.clone()).map_err(|e| format!("Failed to parse document: {}", e))?;
+    let blocks = scan_blocks(&model, json);
+    if blocks.is_empty() {
+        return Ok(());
+    }
+
+    // 2. Ask the scanner
+    let mut request = get_client().post(url).json(&ScanRequest { org_id, doc_id: doc_uuid.to_string(), blocks });
+    if let Some(token) = config::get_config().policy_scanner_token.as_deref() {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.map_err(|e| format!("Policy scanner unreachable: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Policy scanner returned {}", response.status()));
+    }
+    let response: ScanResponse = response.json().await.map_err(|e| format!("Invalid policy scanner response: {}", e))?;
+
+    // 3. Record the findings, they replace the open findings of earlier scans
+    let now = Utc::now();
+    let findings: Vec<DocumentPolicyFindingRow> = response.findings
+        .into_iter()
+        .map(|finding| DocumentPolicyFindingRow {
+            id: Uuid::nil(),
+            block_path: finding.path,
+            category: finding.category,
+            severity: finding.severity.to_lowercase(),
+            rule: finding.rule,
+            excerpt: finding.excerpt,
+            created_at: now,
+            resolved_at: None,
+            resolved_by: None,
+        })
+        .collect();
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.replace_policy_findings(org_id, doc_uuid, &findings)
+        .await
+        .map_err(|e| format!("Failed to store policy findings: {}", e))?;
+
+    // 4. Hold the document for review when a finding is severe enough
+    let threshold = maintenance_rank();
+    let severe: Vec<&DocumentPolicyFindingRow> = findings.iter().filter(|finding| severity_rank(&finding.severity) >= threshold).collect();
+    if severe.is_empty() {
+        return Ok(());
+    }
+    let settings = doc_settings_service::get_settings(org_id, doc_uuid).await?.unwrap_or_default();
+    if settings.maintenance.is_some() {
+        return Ok(());
+    }
+    let maintenance = DocumentMaintenance {
+        reason: format!(
+            "Policy scan found {} finding(s) of severity {} or higher in {}",
+            severe.len(),
+            SEVERITIES[threshold],
+            severe.iter().map(|finding| finding.block_path.as_str()).collect::<Vec<_>>().join(", ")
+        ),
+        since: now,
+    };
+    warn!("Document '{}' put in maintenance: {}", doc_uuid, maintenance.reason);
+    doc_settings_service::patch_settings(registry, org_id, doc_uuid, &json!({ "maintenance": maintenance }), SCANNER_PRPL).await?;
+    Ok(())
+}
+
+// The scanned texts: every language of a statement, and every block of a sheet with its local statements.
+// Block ids are read from the JSON since the model doesn't keep them.
+pub(crate) fn scan_blocks(model: &ColabModel, json: &Value) -> Vec<ScanBlock> {
+    let mut blocks = Vec::new();
+    match model {
+        ColabModel::Statement(statement) => push_statement(statement, "", &mut blocks),
+        ColabModel::Sheet(sheet) => {
+            let raw_blocks = json.get("content").and_then(|content| content.as_array());
+            for (i, block) in sheet.content.iter().enumerate() {
+                let block_id = raw_blocks
+                    .and_then(|raw| raw.get(i))
+                    .and_then(|raw| raw.get("id"))
+                    .and_then(|id| id.as_str())
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| i.to_string());
+                let path = format!("/content/{}", block_id);
+                match block {
+                    ColabSheetBlock::Text(text) => {
+                        let text = format!("{}\n{}", render_text(&text.title), render_text(&text.text_element));
+                        blocks.push(ScanBlock { path, text: text.trim().to_string() });
+                    }
+                    ColabSheetBlock::Attributes(attributes) => {
+                        let mut lines: Vec<String> = attributes.attributes.iter().map(|(key, value)| format!("{}: {}", key, value.display)).collect();
+                        lines.sort();
+                        blocks.push(ScanBlock { path, text: lines.join("\n") });
+                    }
+                    ColabSheetBlock::StatementGrid(grid) => {
+                        for (r, row) in grid.rows.iter().enumerate() {
+                            if let Some(statement) = &row.statement {
+                                push_statement(statement, &format!("{}/rows/{}/statement", path, r), &mut blocks);
+                            }
+                        }
+                    }
+                    ColabSheetBlock::Board(board) => {
+                        let mut lines = vec![render_text(&board.title)];
+                        lines.extend(board.columns.iter().flat_map(|column| &column.cards).map(|card| render_text(&card.title)));
+                        blocks.push(ScanBlock { path, text: lines.join("\n").trim().to_string() });
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    blocks.retain(|block| !block.text.is_empty());
+    blocks
+}
+
+fn push_statement(statement: &ColabStatementModel, base_path: &str, blocks: &mut Vec<ScanBlock>) {
+    let mut lang_codes: Vec<&String> = statement.content.keys().collect();
+    lang_codes.sort();
+    for lang_code in lang_codes {
+        blocks.push(ScanBlock {
+            path: format!("{}/content/{}", base_path, lang_code),
+            text: render_text(&statement.content[lang_code].text_element),
+        });
+    }
+}
+
+pub async fn findings(org_id: &str, doc_uuid: Uuid, include_resolved: bool) -> Result<Vec<DocumentPolicyFindingRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_policy_findings(org_id, doc_uuid, include_resolved)
+        .await
+        .map_err(|e| format!("Failed to load policy findings of document '{}': {}", doc_uuid, e))
+}
+
+// Resolve the open findings of a document and lift its maintenance hold.
+// Returns the number of resolved findings, or None when the document doesn't exist.
+pub async fn review(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, by_prpl: &str) -> Result<Option<u64>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let resolved = db.resolve_policy_findings(org_id, doc_uuid, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to resolve policy findings of document '{}': {}", doc_uuid, e))?;
+    let settings = doc_settings_service::patch_settings(registry, org_id, doc_uuid, &json!({ "maintenance": null }), by_prpl).await?;
+    Ok(settings.map(|_| resolved))
+}
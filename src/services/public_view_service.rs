@@ -0,0 +1,307 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use loro::ToJson;
+use loro_websocket_server::HubRegistry;
+use tracing::warn;
+use uuid::Uuid;
+use crate::db::dbcolab::{self, DocumentShareTokenRow};
+use crate::models::{ColabModel, ColabSheetBlock, ColabStatementModel, ShareBranding, TextElement};
+use crate::services::citation_service::{escape_html, render_html, render_text};
+use crate::services::{doc_load_service, script_service, summary_service, transclusion_service};
+use crate::services::evidence_service::sha256_hex;
+use crate::ws::docctx::DocContext;
+
+const DEFAULT_TITLE: &str = "Published document";
+
+/// The rendered public view of a document
+pub struct RenderedView {
+    pub html: String,
+    // Changes when the published version or the branding changes
+    pub etag: String,
+}
+
+// Create a share token, returns the token itself together with its row
+pub async fn create_token(org_id: &str, doc_uuid: Uuid, branding: Option<&ShareBranding>, expires_at: Option<DateTime<Utc>>, by_prpl: &str) -> Result<(String, DocumentShareTokenRow), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let branding = branding.map(serde_json::to_value).transpose().map_err(|e| format!("Invalid branding: {}", e))?;
+    let row = db.insert_share_token(org_id, doc_uuid, &sha256_hex(token.as_bytes()), branding, expires_at, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to create share token for document '{}': {}", doc_uuid, e))?;
+    Ok((token, row))
+}
+
+pub async fn list_tokens(org_id: &str, doc_uuid: Uuid) -> Result<Vec<DocumentShareTokenRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.list_share_tokens(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to list share tokens of document '{}': {}", doc_uuid, e))
+}
+
+// Revoke a share token, returns false when there was no active token to revoke
+pub async fn revoke_token(org_id: &str, doc_uuid: Uuid, token_id: Uuid) -> Result<bool, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.revoke_share_token(org_id, doc_uuid, token_id)
+        .await
+        .map_err(|e| format!("Failed to revoke share token '{}' of document '{}': {}", token_id, doc_uuid, e))
+}
+
+// Find the active share token behind a token
+pub async fn resolve_token(token: &str) -> Result<Option<DocumentShareTokenRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_active_share_token(&sha256_hex(token.as_bytes()))
+        .await
+        .map_err(|e| format!("Failed to resolve share token: {}", e))
+}
+
+pub fn branding_of(row: &DocumentShareTokenRow) -> Option<ShareBranding> {
+    row.branding.as_ref().and_then(|branding| serde_json::from_value(branding.0.clone()).ok())
+}
+
+/// The published version of a shared document
+pub struct PublishedDoc {
+    pub model: ColabModel,
+    pub version: u32,
+    pub published_at: DateTime<Utc>,
+    json_sha256: String,
+}
+
+// Load the published version of the document behind a share token, the latest signed one.
+// Returns None when the document was never published.
+pub async fn load_published(registry: &Arc<HubRegistry<DocContext>>, row: &DocumentShareTokenRow) -> Result<Option<PublishedDoc>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let doc_id = row.document.to_string();
+
+    let signature = match db.get_latest_document_signature(&row.org, row.document).await
+        .map_err(|e| format!("Failed to load the published version of document '{}': {}", doc_id, e))?
+    {
+        Some(signature) => signature,
+        None => return Ok(None),
+    };
+    let (loro_doc, _) = doc_load_service::load_loro_doc_version(registry, &row.org, &doc_id, signature.version as u32)
+        .await?
+        .ok_or_else(|| format!("Published version {} of document '{}' not found", signature.version, doc_id))?;
+    let model: ColabModel = serde_json::from_value(loro_doc.get_deep_value().to_json_value())
+        .map_err(|e| format!("Failed to parse document '{}': {}", doc_id, e))?;
+
+    Ok(Some(PublishedDoc {
+        model,
+        version: signature.version as u32,
+        published_at: signature.created_at,
+        json_sha256: signature.json_sha256,
+    }))
+}
+
+// Render the published version of a shared document.
+// The embed mode leaves out the header and footer so it fits in an iframe.
+pub async fn render(registry: &Arc<HubRegistry<DocContext>>, row: &DocumentShareTokenRow, published: &PublishedDoc, embed: bool) -> RenderedView {
+    let branding = branding_of(row).unwrap_or_default();
+    // The transcluded sources are part of the etag
+    let (body, sources) = render_content(registry, &row.org, &published.model).await;
+
+    let branding_json = row.branding.as_ref().map(|branding| branding.0.to_string()).unwrap_or_default();
+    let tag = format!("{}:{}:{}:{}:{}", published.json_sha256, published.version, branding_json, embed, sources.join(","));
+    RenderedView {
+        html: page(&branding, &body, embed, None),
+        etag: format!("\"{}\"", &sha256_hex(tag.as_bytes())[..32]),
+    }
+}
+
+// Render the content of a document as HTML sections.
+// Statement languages carry their direction, other sections take the direction of their first words.
+// Transcluded blocks show the latest published version of their source, returned as `<doc>:<version>`.
+pub async fn render_content(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, model: &ColabModel) -> (String, Vec<String>) {
+    let mut body = String::new();
+    let mut sources = Vec::new();
+    match model {
+        ColabModel::Statement(statement) => render_statement(statement, &mut body),
+        ColabModel::Sheet(sheet) => {
+            for block in &sheet.content {
+                let ColabSheetBlock::Transclusion(transclusion) = block else {
+                    render_block(registry, org_id, block, &mut body).await;
+                    continue;
+                };
+                match transclusion_service::published_block(registry, org_id, transclusion).await {
+                    Ok(Some((version, source_block))) => {
+                        sources.push(format!("{}:{}", transclusion.source_doc_id, version));
+                        render_block(registry, org_id, &source_block, &mut body).await;
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to render transcluded block '{}' of document '{}': {}", transclusion.source_block_id, transclusion.source_doc_id, e),
+                }
+            }
+        }
+    }
+    (body, sources)
+}
+
+// A standalone HTML page with the rendered content of a document, without branding
+pub fn document_page(title: &str, body: &str, lang: &str) -> String {
+    let branding = ShareBranding { title: Some(title.to_string()), ..ShareBranding::default() };
+    page(&branding, body, true, Some(lang))
+}
+
+pub fn title(row: &DocumentShareTokenRow) -> String {
+    branding_of(row)
+        .and_then(|branding| branding.title)
+        .unwrap_or_else(|| DEFAULT_TITLE.to_string())
+}
+
+// Plain text of the start of a document, for link previews
+pub fn excerpt(model: &ColabModel, max_chars: usize) -> String {
+    summary_service::extract(&summary_service::document_text(model), max_chars)
+}
+
+async fn render_block(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, block: &ColabSheetBlock, html: &mut String) {
+    match block {
+        ColabSheetBlock::Properties(_) | ColabSheetBlock::Transclusion(_) => {}
+        ColabSheetBlock::Text(text) => {
+            html.push_str("<section dir=\"auto\">");
+            heading(&text.title, html);
+            html.push_str(&render_html(&text.text_element));
+            html.push_str("</section>");
+        }
+        ColabSheetBlock::Attributes(attributes) => {
+            html.push_str("<section dir=\"auto\">");
+            heading(&attributes.title, html);
+            let mut keys: Vec<&String> = attributes.attributes.keys().collect();
+            keys.sort();
+            html.push_str("<table>");
+            for key in keys {
+                html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>", escape_html(key), escape_html(&attributes.attributes[key].display)));
+            }
+            html.push_str("</table></section>");
+        }
+        ColabSheetBlock::StatementGrid(grid) => {
+            html.push_str("<section dir=\"auto\">");
+            heading(&grid.title, html);
+            for row in &grid.rows {
+                if let Some(statement) = &row.statement {
+                    render_statement(statement, html);
+                } else if let Some(statement_ref) = &row.statement_ref {
+                    match load_statement(registry, org_id, statement_ref.doc_id, statement_ref.version).await {
+                        Ok(statement) => render_statement(&statement, html),
+                        Err(e) => warn!("Failed to render referenced statement '{}': {}", statement_ref.doc_id, e),
+                    }
+                }
+            }
+            html.push_str("</section>");
+        }
+        ColabSheetBlock::Barcode(barcode) => {
+            html.push_str("<section dir=\"auto\">");
+            heading(&barcode.title, html);
+            html.push_str("<ul>");
+            for row in &barcode.rows {
+                html.push_str(&format!("<li>{}: {}</li>", escape_html(&row.barcode.r#type), escape_html(&row.barcode.data)));
+            }
+            html.push_str("</ul></section>");
+        }
+        ColabSheetBlock::Symbol(symbol) => {
+            html.push_str("<section dir=\"auto\">");
+            heading(&symbol.title, html);
+            html.push_str("<ul>");
+            for row in &symbol.rows {
+                html.push_str(&format!("<li>{}</li>", escape_html(&row.symbol.r#type)));
+            }
+            html.push_str("</ul></section>");
+        }
+        ColabSheetBlock::Board(board) => {
+            html.push_str("<section dir=\"auto\">");
+            heading(&board.title, html);
+            for column in &board.columns {
+                html.push_str(&format!("<h3>{}</h3><ul>", escape_html(&column.title)));
+                for card in &column.cards {
+                    html.push_str(&format!("<li><strong>{}</strong> {}", escape_html(&card.status), escape_html(&render_text(&card.title))));
+                    if let Some(assignee) = &card.assignee {
+                        html.push_str(&format!(" ({})", escape_html(assignee)));
+                    }
+                    html.push_str("</li>");
+                }
+                html.push_str("</ul>");
+            }
+            html.push_str("</section>");
+        }
+    }
+}
+
+pub async fn load_statement(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, version: u32) -> Result<ColabStatementModel, String> {
+    let (loro_doc, _) = doc_load_service::load_loro_doc_version(registry, org_id, &doc_uuid.to_string(), version)
+        .await?
+        .ok_or_else(|| format!("Version {} not found", version))?;
+    serde_json::from_value(loro_doc.get_deep_value().to_json_value()).map_err(|e| format!("Failed to parse statement: {}", e))
+}
+
+// Every language of a statement, the master language first
+fn render_statement(statement: &ColabStatementModel, html: &mut String) {
+    let master = statement.properties.master_lang_code.as_deref();
+    let mut lang_codes: Vec<&String> = statement.content.keys().collect();
+    lang_codes.sort_by(|a, b| (Some(a.as_str()) != master).cmp(&(Some(b.as_str()) != master)).then(a.cmp(b)));
+    html.push_str("<div class=\"statement\">");
+    for lang_code in lang_codes {
+        html.push_str(&format!(
+            "<div class=\"lang\" lang=\"{}\" dir=\"{}\"><span class=\"code\">{}</span>{}</div>",
+            escape_html(lang_code),
+            script_service::direction(lang_code),
+            escape_html(&lang_code.to_uppercase()),
+            render_html(&statement.content[lang_code].text_element)
+        ));
+    }
+    html.push_str("</div>");
+}
+
+fn heading(title: &TextElement, html: &mut String) {
+    let title = render_text(title);
+    if !title.is_empty() {
+        html.push_str(&format!("<h2>{}</h2>", escape_html(&title)));
+    }
+}
+
+// Only plain hex colors make it into the stylesheet
+fn accent_color(branding: &ShareBranding) -> &str {
+    match branding.accent_color.as_deref() {
+        Some(color) if color.starts_with('#') && matches!(color.len(), 4 | 7) && color[1..].chars().all(|c| c.is_ascii_hexdigit()) => color,
+        _ => "#1f2937",
+    }
+}
+
+fn page(branding: &ShareBranding, body: &str, embed: bool, lang: Option<&str>) -> String {
+    let title = escape_html(branding.title.as_deref().unwrap_or(DEFAULT_TITLE));
+    let lang = lang.map(|lang| format!(" lang=\"{}\"", escape_html(lang))).unwrap_or_default();
+    if embed {
+        return format!(
+            "<!DOCTYPE html>\n<html{lang}><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+<meta name=\"robots\" content=\"noindex\"><base target=\"_blank\"><title>{title}</title><style>\
+body{{font-family:system-ui,sans-serif;margin:0;padding:.75rem;color:#111827}}h2{{font-size:1rem}}table{{border-collapse:collapse}}\
+th,td{{text-align:left;padding:.25rem .75rem .25rem 0}}.statement{{border-left:3px solid {accent};padding-left:.75rem;margin:.75rem 0}}\
+.code{{font-size:.75rem;font-weight:600;color:#6b7280;margin-right:.5rem}}\
+</style></head><body><main>{body}</main></body></html>",
+            lang = lang,
+            title = title,
+            accent = accent_color(branding),
+            body = body,
+        );
+    }
+    let logo = branding.logo_url.as_deref()
+        .filter(|url| url.starts_with("https://"))
+        .map(|url| format!("<img class=\"logo\" src=\"{}\" alt=\"\">", escape_html(url)))
+        .unwrap_or_default();
+    let footer = branding.footer.as_deref()
+        .map(|footer| format!("<footer>{}</footer>", escape_html(footer)))
+        .unwrap_or_default();
+    format!(
+        "<!DOCTYPE html>\n<html{lang}><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+<meta name=\"robots\" content=\"noindex\"><title>{title}</title><style>\
+body{{font-family:system-ui,sans-serif;max-width:50rem;margin:2rem auto;padding:0 1rem;color:#111827}}\
+header{{display:flex;align-items:center;gap:1rem;border-bottom:3px solid {accent};margin-bottom:1.5rem}}\
+.logo{{max-height:3rem}}h1{{color:{accent}}}table{{border-collapse:collapse}}th,td{{text-align:left;padding:.25rem .75rem .25rem 0}}\
+.statement{{border-left:3px solid {accent};padding-left:.75rem;margin:1rem 0}}.code{{font-size:.75rem;font-weight:600;color:#6b7280;margin-right:.5rem}}\
+footer{{margin-top:2rem;font-size:.875rem;color:#6b7280}}\
+</style></head><body><header>{logo}<h1>{title}</h1></header><main>{body}</main>{footer}</body></html>",
+        lang = lang,
+        title = title,
+        accent = accent_color(branding),
+        logo = logo,
+        body = body,
+        footer = footer,
+    )
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use axum::http::StatusCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::Utc;
+use loro::{LoroDoc, ToJson};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab::{self, DocumentQuarantineRow};
+use crate::models::{ColabModel, ColabPackage, DocumentQuarantinedEvent};
+use crate::services::{doc_db_service, json_projection_service, storage_service};
+use crate::services::webhook_service::{self, WebhookEvent};
+use crate::services::workflow_service;
+use crate::ws::docctx::DocContext;
+
+/// Prefix of the load error of a quarantined document
+pub const QUARANTINED_ERROR: &str = "Document is quarantined";
+
+// Documents quarantined by this instance since it started
+static QUARANTINED_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+pub fn quarantined_total() -> u64 {
+    QUARANTINED_TOTAL.load(Ordering::Relaxed)
+}
+
+pub fn is_quarantined_error(error: &str) -> bool {
+    error.starts_with(QUARANTINED_ERROR)
+}
+
+// The HTTP status of a load error, 422 for quarantined documents so clients stop retrying
+pub fn load_error_status(error: &str) -> StatusCode {
+    if is_quarantined_error(error) {
+        StatusCode::UNPROCESSABLE_ENTITY
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+// Load the latest snapshot of a document, or a specific version, like doc_db_service does.
+// Quarantined documents are refused right away. Failures, including snapshots that can't be imported,
+// are counted and quarantine the document once they reach the threshold, a successful load resets them.
+pub async fn fetch_doc_snapshot(org_id: &str, doc_id: &str, version: Option<u32>) -> Result<Option<(Vec<u8>, DocContext)>, String> {
+    let doc_uuid = match Uuid::parse_str(doc_id) {
+        Ok(uuid) => uuid,
+        Err(_) => return doc_db_service::fetch_doc_snapshot_from_db(org_id, doc_id, version).await,
+    };
+
+    // 1. Refuse quarantined documents
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    match db.get_document_quarantine(org_id, doc_uuid).await {
+        Ok(Some(status)) if status.quarantined_at.is_some() => {
+            return Err(quarantined_error(&status));
+        }
+        Ok(_) => {}
+        Err(e) => return Err(format!("Failed to load quarantine status of document '{}': {}", doc_uuid, e)),
+    }
+
+    // 2. Load the document and make sure the snapshot can be imported
+    let result = doc_db_service::fetch_doc_snapshot_from_db(org_id, doc_id, version)
+        .await
+        .and_then(|loaded| match loaded {
+            Some((snapshot, ctx)) => {
+                LoroDoc::new()
+                    .import(&snapshot)
+                    .map_err(|e| format!("Failed to import snapshot for document '{}': {}", doc_uuid, e))?;
+                Ok(Some((snapshot, ctx)))
+            }
+            None => Ok(None),
+        });
+
+    // 3. Keep track of the outcome
+    match &result {
+        Ok(Some(_)) => {
+            if let Err(e) = db.reset_document_load_failures(org_id, doc_uuid).await {
+                error!("Failed to reset load failures of document '{}': {}", doc_uuid, e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            record_failure(org_id, doc_uuid, e).await;
+        }
+    }
+    result
+}
+
+// Count a failed load, and quarantine and alert when the threshold is reached
+async fn record_failure(org_id: &str, doc_uuid: Uuid, reason: &str) {
+    let threshold = config::get_config().doc_quarantine_threshold.unwrap_or(3).max(1) as i32;
+    count_failure(org_id, doc_uuid, reason, threshold).await;
+}
+
+// Quarantine a document right away, e.g. after it made a callback panic
+pub async fn quarantine(org_id: &str, doc_uuid: Uuid, reason: &str) {
+    count_failure(org_id, doc_uuid, reason, 1).await;
+}
+
+async fn count_failure(org_id: &str, doc_uuid: Uuid, reason: &str, threshold: i32) {
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return,
+    };
+    let status = match db.record_document_load_failure(org_id, doc_uuid, reason, threshold).await {
+        Ok(Some(status)) => status,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Failed to record load failure of document '{}': {}", doc_uuid, e);
+            return;
+        }
+    };
+    warn!("Load of document '{}' failed ({} consecutive failures): {}", doc_uuid, status.load_failures, reason);
+
+    // Only the failure that crosses the threshold raises the alert, an immediate quarantine always does
+    if threshold > 1 && status.load_failures != threshold {
+        return;
+    }
+    QUARANTINED_TOTAL.fetch_add(1, Ordering::Relaxed);
+    error!("Quarantined document '{}' in organization '{}' after {} failed loads", doc_uuid, org_id, threshold);
+    match workflow_service::get_workflow(org_id).await {
+        Ok(workflow) => webhook_service::dispatch(workflow.webhooks, WebhookEvent::new(
+            org_id,
+            &doc_uuid.to_string(),
+            Utc::now(),
+            &DocumentQuarantinedEvent {
+                load_failures: status.load_failures,
+                reason: reason.to_string(),
+            },
+        )),
+        Err(e) => error!("{}", e),
+    }
+}
+
+fn quarantined_error(status: &DocumentQuarantineRow) -> String {
+    format!("{}: {}", QUARANTINED_ERROR, status.quarantine_reason.as_deref().unwrap_or("unknown reason"))
+}
+
+// Get the quarantine status of a document, None if the document does not exist
+pub async fn get_status(org_id: &str, doc_uuid: Uuid) -> Result<Option<DocumentQuarantineRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_document_quarantine(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load quarantine status of document '{}': {}", doc_uuid, e))
+}
+
+// Lift the quarantine and load the document again, it gets quarantined again if it still fails
+pub async fn retry(org_id: &str, doc_uuid: Uuid) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.reset_document_load_failures(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to lift quarantine of document '{}': {}", doc_uuid, e))?;
+    match fetch_doc_snapshot(org_id, &doc_uuid.to_string(), None).await? {
+        Some(_) => Ok(()),
+        None => Err(format!("Document '{}' not found in organization '{}'", doc_uuid, org_id)),
+    }
+}
+
+// Rebuild the latest stream of a document from its JSON representation and lift the quarantine.
+// The edit history in the stream is lost, the content as of the last save is kept.
+pub async fn repair(org_id: &str, doc_uuid: Uuid, by_prpl: &str) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    // 1. Find the latest main stream and the JSON of the document
+    let doc_data = db.load_colab_doc(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .ok_or_else(|| format!("Document '{}' not found in organization '{}'", doc_uuid, org_id))?;
+    let stream = doc_data.streams
+        .iter()
+        .filter(|s| s.name == "main")
+        .max_by_key(|s| s.version)
+        .ok_or_else(|| format!("Document '{}' has no main stream to repair", doc_uuid))?;
+    let json_value = doc_data.json
+        .clone()
+        .ok_or_else(|| format!("Document '{}' has no JSON to repair from", doc_uuid))?;
+
+    // 2. Rebuild the LoroDoc from the JSON
+    let doc_model: ColabModel = serde_json::from_value(json_value)
+        .map_err(|e| format!("The JSON of document '{}' is invalid too, it can't be repaired: {}", doc_uuid, e))?;
+    let loro_doc = crate::models::lorodoc::colab_to_loro_doc(&doc_model)
+        .ok_or_else(|| format!("Failed to convert the JSON of document '{}' to a LoroDoc", doc_uuid))?;
+    let snapshot = loro_doc.export(loro::ExportMode::Snapshot)
+        .map_err(|e| format!("Failed to export snapshot: {}", e))?;
+
+    // 3. Overwrite the stream with the rebuilt document
+    let mut peer_map: HashMap<u64, String> = HashMap::new();
+    peer_map.insert(loro_doc.peer_id(), "s/colabri-doc".to_string());
+    let blob = serde_cbor::to_vec(&ColabPackage { snapshot, peer_map: peer_map.clone() })
+        .map_err(|e| format!("Failed to serialize ColabPackage: {}", e))?;
+    let state_vv_json = serde_json::to_value(loro_doc.state_vv())
+        .map_err(|e| format!("Failed to serialize state_vv: {}", e))?;
+    let peer_map_json = serde_json::to_value(&peer_map)
+        .map_err(|e| format!("Failed to serialize peer_map: {}", e))?;
+    let json = json_projection_service::project(loro_doc.get_deep_value().to_json_value());
+    let doc_type = match doc_model {
+        ColabModel::Statement(_) => "colab-statement",
+        ColabModel::Sheet(_) => "colab-sheet",
+    };
+    let content_sha256 = storage_service::content_hash(&json);
+    db.update_colab_doc(org_id, doc_uuid, doc_type, stream.id, blob, json, &content_sha256, state_vv_json, peer_map_json, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to store repaired document '{}': {}", doc_uuid, e))?;
+    info!("Repaired document '{}' from its JSON, stream {} rebuilt by '{}'", doc_uuid, stream.id, by_prpl);
+
+    // 4. Lift the quarantine
+    db.reset_document_load_failures(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to lift quarantine of document '{}': {}", doc_uuid, e))?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use axum::{http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode}, response::{IntoResponse, Response}};
+use base64::{engine::general_purpose, Engine as _};
+use sha2::{Digest, Sha256};
+
+// Resumable downloads of binary snapshots.
+// Snapshots are exported in full, a Range request answers a single byte range of them with 206. The
+// ETag is the SHA-256 of the whole snapshot, a client resuming with If-Range gets the full snapshot
+// again when the document changed in between. Repr-Digest carries the checksum of the whole snapshot
+// so the reassembled download can be verified. Content-Length is set for every answer.
+
+static REPR_DIGEST: HeaderName = HeaderName::from_static("repr-digest");
+
+// The byte range requested by a Range header, relative to a body of `len` bytes.
+// None when the header asks for something else than a single byte range, which is answered in full.
+fn parse_range(range: &str, len: usize) -> Option<Result<(usize, usize), ()>> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+    let range = if start.is_empty() {
+        // The last `end` bytes
+        let suffix: usize = end.parse().ok()?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let start: usize = start.parse().ok()?;
+        let end: usize = if end.is_empty() { len.saturating_sub(1) } else { end.parse().ok()? };
+        if start >= len || end < start {
+            return Some(Err(()));
+        }
+        (start, end.min(len - 1))
+    };
+    Some(Ok(range))
+}
+
+// Answer a binary body in full or the byte range requested by the Range header
+pub fn bytes_response(headers: &HeaderMap, bytes: Vec<u8>, content_type: &'static str) -> Response {
+    let digest = Sha256::digest(&bytes);
+    let etag = format!("\"{}\"", digest[..16].iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    let repr_digest = format!("sha-256=:{}:", general_purpose::STANDARD.encode(digest));
+    let len = bytes.len();
+
+    // A range only applies to the snapshot the client started downloading
+    let if_range_matches = headers
+        .get(header::IF_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(true, |if_range| if_range.trim() == etag);
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| if_range_matches)
+        .and_then(|range| parse_range(range, len));
+
+    let mut response = match range {
+        None => (StatusCode::OK, bytes).into_response(),
+        Some(Err(())) => {
+            let mut response = StatusCode::RANGE_NOT_SATISFIABLE.into_response();
+            if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                response.headers_mut().insert(header::CONTENT_RANGE, value);
+            }
+            return response;
+        }
+        Some(Ok((start, end))) => {
+            let mut response = (StatusCode::PARTIAL_CONTENT, bytes[start..=end].to_vec()).into_response();
+            if let Ok(value) = HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len)) {
+                response.headers_mut().insert(header::CONTENT_RANGE, value);
+            }
+            response
+        }
+    };
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response_headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&repr_digest) {
+        response_headers.insert(REPR_DIGEST.clone(), value);
+    }
+    response
+}
@@ -0,0 +1,109 @@
+use chrono::Utc;
+use loro::{LoroDoc, LoroMap};
+use uuid::Uuid;
+use crate::models::{ReactionCount, ReactionSummary};
+use crate::models::lorodoc::get_child_map;
+use crate::services::acl_service;
+
+// Emoji reactions on blocks, languages and comments.
+// Every block or language keeps its reactions in a "reactions" map apart from its comments:
+// target -> emoji -> principal -> time of the reaction. The target is "block" for the block or
+// language itself and the id of the comment otherwise. A principal reacts at most once per emoji,
+// concurrent reactions of different principals merge without conflicts.
+
+const BLOCK_TARGET: &str = "block";
+
+// Longest emoji accepted, in characters. Sequences joined with ZWJ and modifiers stay well below it.
+const MAX_EMOJI_CHARS: usize = 16;
+
+fn target_key(comment_id: Option<&Uuid>) -> String {
+    comment_id.map_or_else(|| BLOCK_TARGET.to_string(), |id| id.to_string())
+}
+
+/// Whether a string is a single emoji and not text: short, without whitespace and without ASCII
+pub fn is_emoji(emoji: &str) -> bool {
+    let chars = emoji.chars().count();
+    chars > 0 && chars <= MAX_EMOJI_CHARS && emoji.chars().all(|c| !c.is_ascii() && !c.is_whitespace() && !c.is_control())
+}
+
+// The reactions of one target, the most used emoji first
+fn counts(target_map: &LoroMap) -> Vec<ReactionCount> {
+    let mut counts: Vec<ReactionCount> = target_map
+        .keys()
+        .map(|emoji| emoji.to_string())
+        .filter_map(|emoji| {
+            let prpls_map = get_child_map(target_map, &emoji)?;
+            let mut prpls: Vec<String> = prpls_map.keys().map(|prpl| prpl.to_string()).collect();
+            prpls.sort();
+            Some(ReactionCount { emoji, count: prpls.len(), prpls })
+        })
+        .filter(|count| count.count > 0)
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.emoji.cmp(&b.emoji)));
+    counts
+}
+
+/// The reactions stored on a block or language map, per target with reactions
+pub fn read_reactions(path: &str, container: &LoroMap) -> Vec<ReactionSummary> {
+    let Some(reactions_map) = get_child_map(container, "reactions") else {
+        return Vec::new();
+    };
+    let mut summaries: Vec<ReactionSummary> = reactions_map
+        .keys()
+        .map(|target| target.to_string())
+        .filter_map(|target| {
+            let comment_id = match target.as_str() {
+                BLOCK_TARGET => None,
+                id => Some(Uuid::parse_str(id).ok()?),
+            };
+            let reactions = counts(&get_child_map(&reactions_map, &target)?);
+            (!reactions.is_empty()).then(|| ReactionSummary { path: path.to_string(), comment_id, reactions })
+        })
+        .collect();
+    summaries.sort_by_key(|summary| summary.comment_id);
+    summaries
+}
+
+/// The reactions on the block or language at `path`, or on one of its comments
+pub fn summary(doc: &LoroDoc, path: &str, comment_id: Option<&Uuid>) -> Result<ReactionSummary, String> {
+    let container = acl_service::find_scope_map(doc, path)?;
+    let reactions = get_child_map(&container, "reactions")
+        .and_then(|reactions_map| get_child_map(&reactions_map, &target_key(comment_id)))
+        .map(|target_map| counts(&target_map))
+        .unwrap_or_default();
+    Ok(ReactionSummary { path: path.to_string(), comment_id: comment_id.copied(), reactions })
+}
+
+/// Add the reaction of a principal, false when it already reacted with the emoji.
+/// The caller commits the changes.
+pub fn add_reaction(doc: &LoroDoc, path: &str, comment_id: Option<&Uuid>, emoji: &str, prpl: &str) -> Result<bool, String> {
+    let container = acl_service::find_scope_map(doc, path)?;
+    let prpls_map = container
+        .get_or_create_container("reactions", LoroMap::new())
+        .and_then(|reactions_map| reactions_map.get_or_create_container(target_key(comment_id).as_str(), LoroMap::new()))
+        .and_then(|target_map| target_map.get_or_create_container(emoji, LoroMap::new()))
+        .map_err(|e| format!("Failed to create the reactions map: {}", e))?;
+    if prpls_map.get(prpl).is_some() {
+        return Ok(false);
+    }
+    prpls_map
+        .insert(prpl, Utc::now().to_rfc3339().as_str())
+        .map_err(|e| format!("Failed to add the reaction: {}", e))?;
+    Ok(true)
+}
+
+/// Remove the reaction of a principal, false when it didn't react with the emoji.
+/// The caller commits the changes.
+pub fn remove_reaction(doc: &LoroDoc, path: &str, comment_id: Option<&Uuid>, emoji: &str, prpl: &str) -> Result<bool, String> {
+    let container = acl_service::find_scope_map(doc, path)?;
+    let prpls_map = get_child_map(&container, "reactions")
+        .and_then(|reactions_map| get_child_map(&reactions_map, &target_key(comment_id)))
+        .and_then(|target_map| get_child_map(&target_map, emoji));
+    let Some(prpls_map) = prpls_map.filter(|prpls_map| prpls_map.get(prpl).is_some()) else {
+        return Ok(false);
+    };
+    prpls_map
+        .delete(prpl)
+        .map_err(|e| format!("Failed to remove the reaction: {}", e))?;
+    Ok(true)
+}
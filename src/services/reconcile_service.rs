@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use loro::{LoroDoc, ToJson, VersionVector};
+use tracing::info;
+use uuid::Uuid;
+use crate::db::dbcolab::{self, DocumentMergeRow};
+use crate::models::ColabPackage;
+use crate::services::quarantine_service;
+
+/// A stored version of a document with its version vector
+pub struct LoadedVersion {
+    pub version: u32,
+    pub snapshot: Vec<u8>,
+    pub peer_map: HashMap<u64, String>,
+    pub vv: VersionVector,
+    // Whether no other version contains this one, more than one head means the versions diverged
+    pub head: bool,
+}
+
+// Load all versions of the main stream of a document and find the heads
+pub async fn load_versions(org_id: &str, doc_uuid: Uuid) -> Result<Option<Vec<LoadedVersion>>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let doc_data = match db.load_colab_doc(org_id, doc_uuid).await {
+        Ok(Some(doc)) => doc,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(format!("Database error: {}", e)),
+    };
+    let mut numbers: Vec<u32> = doc_data.streams
+        .iter()
+        .filter(|s| s.name == "main" && (s.content.is_some() || s.pointer.is_some()))
+        .map(|s| s.version)
+        .collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+
+    // 1. Load every version
+    let mut versions: Vec<LoadedVersion> = Vec::with_capacity(numbers.len());
+    for version in numbers {
+        let (snapshot, ctx) = match quarantine_service::fetch_doc_snapshot(org_id, &doc_uuid.to_string(), Some(version)).await? {
+            Some(loaded) => loaded,
+            None => continue,
+        };
+        let doc = LoroDoc::new();
+        doc.import(&snapshot).map_err(|e| format!("Failed to import version {}: {}", version, e))?;
+        versions.push(LoadedVersion {
+            version,
+            snapshot,
+            peer_map: ctx.peer_map,
+            vv: doc.oplog_vv(),
+            head: true,
+        });
+    }
+
+    // 2. A version is no head when a later version contains it, or any version contains it and more
+    let heads: Vec<bool> = versions
+        .iter()
+        .map(|v| !versions.iter().any(|other| {
+            other.version != v.version && other.vv.includes_vv(&v.vv) && (other.vv != v.vv || other.version > v.version)
+        }))
+        .collect();
+    for (version, head) in versions.iter_mut().zip(heads) {
+        version.head = head;
+    }
+    Ok(Some(versions))
+}
+
+// Get the merges that were done on a document
+pub async fn merges(org_id: &str, doc_uuid: Uuid) -> Result<Vec<DocumentMergeRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_document_merges(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load merges of document '{}': {}", doc_uuid, e))
+}
+
+// Import two versions into one LoroDoc and save the merged state as a new version.
+// Without explicit versions the two heads are merged, which requires exactly two heads.
+pub async fn merge(org_id: &str, doc_uuid: Uuid, versions: Option<(u32, u32)>, by_prpl: &str) -> Result<DocumentMergeRow, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let doc_type = db.load_colab_doc(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?
+        .map(|doc| doc.doc_type)
+        .ok_or_else(|| format!("Document '{}' not found in organization '{}'", doc_uuid, org_id))?;
+    let loaded = load_versions(org_id, doc_uuid)
+        .await?
+        .ok_or_else(|| format!("Document '{}' not found in organization '{}'", doc_uuid, org_id))?;
+
+    // 1. Pick the two versions
+    let (a, b) = match versions {
+        Some((a, b)) => (
+            loaded.iter().find(|v| v.version == a).ok_or_else(|| format!("Version {} not found", a))?,
+            loaded.iter().find(|v| v.version == b).ok_or_else(|| format!("Version {} not found", b))?,
+        ),
+        None => {
+            let heads: Vec<&LoadedVersion> = loaded.iter().filter(|v| v.head).collect();
+            match heads.as_slice() {
+                [a, b] => (*a, *b),
+                [_] => return Err("The versions of the document did not diverge, there is nothing to merge".to_string()),
+                _ => return Err(format!("The document has {} heads, specify the two versions to merge", heads.len())),
+            }
+        }
+    };
+    if a.version == b.version {
+        return Err("Can't merge a version with itself".to_string());
+    }
+
+    // 2. Merge both heads, the CRDT resolves concurrent edits
+    let doc = LoroDoc::new();
+    doc.import(&a.snapshot).map_err(|e| format!("Failed to import version {}: {}", a.version, e))?;
+    doc.import(&b.snapshot).map_err(|e| format!("Failed to import version {}: {}", b.version, e))?;
+    let mut peer_map = b.peer_map.clone();
+    peer_map.extend(a.peer_map.iter().map(|(peer, prpl)| (*peer, prpl.clone())));
+
+    // 3. Store the merge as a new version
+    let snapshot = doc.export(loro::ExportMode::Snapshot)
+        .map_err(|e| format!("Failed to export snapshot: {}", e))?;
+    let blob = serde_cbor::to_vec(&ColabPackage { snapshot, peer_map: peer_map.clone() })
+        .map_err(|e| format!("Failed to serialize ColabPackage: {}", e))?;
+    let state_vv_json = serde_json::to_value(doc.state_vv())
+        .map_err(|e| format!("Failed to serialize state_vv: {}", e))?;
+    let peer_map_json = serde_json::to_value(&peer_map)
+        .map_err(|e| format!("Failed to serialize peer_map: {}", e))?;
+    let json = doc.get_deep_value().to_json_value();
+    let merge = db.insert_merged_doc_stream(org_id, doc_uuid, &doc_type, blob, json, state_vv_json, peer_map_json, (a.version, b.version), by_prpl)
+        .await
+        .map_err(|e| format!("Failed to store merged document '{}': {}", doc_uuid, e))?;
+    info!("Merged versions {} and {} of document '{}' into version {} by '{}'", a.version, b.version, doc_uuid, merge.merged_version, by_prpl);
+    Ok(merge)
+}
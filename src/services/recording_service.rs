@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use loro_protocol::UpdateStatusCode;
+use loro_websocket_server::protocol::Permission;
+use loro::{LoroDoc, ToJson};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::models::{RecordingStartRequest, RecordingStatus};
+use crate::services::evidence_service::sha256_hex;
+use crate::services::{lazy_block_service, storage_service};
+
+// Recordings of the sync traffic of a document, to capture client sync bugs in production.
+// The traffic is recorded as the hooks of the WebSocket server see it: joins and the permission they
+// got, the updates of clients and the status they were answered with, loads, saves and closed
+// connections. Every frame is a JSON line with its size and SHA-256, and the payload in base64 when
+// asked for. A recording ends after its duration, only the latest recording of a document is kept.
+// A recording with payloads replays into a fresh document: the loaded snapshot, or a fixture, with
+// the updates the server accepted applied in order, to reproduce the state of an incident.
+
+const DEFAULT_DURATION_SECS: u64 = 300;
+
+struct Recording {
+    status: RecordingStatus,
+    path: PathBuf,
+}
+
+/// Direction of a recorded frame, as seen by the server
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    // Sent by a client
+    In,
+    // Answered to a client
+    Out,
+    // Done by the server itself, e.g. a load or a save
+    Server,
+}
+
+/// A frame as written to a recording
+#[derive(Serialize)]
+struct Frame<'a> {
+    at: DateTime<Utc>,
+    direction: Direction,
+    kind: &'a str,
+    room: &'a str,
+    #[serde(rename = "connId", skip_serializing_if = "Option::is_none")]
+    conn_id: Option<u64>,
+    size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+static RECORDINGS: OnceLock<Mutex<HashMap<String, Recording>>> = OnceLock::new();
+
+fn get_recordings() -> &'static Mutex<HashMap<String, Recording>> {
+    RECORDINGS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn recording_dir() -> PathBuf {
+    PathBuf::from(config::get_config().recording_dir.clone().unwrap_or_else(|| "/tmp/colabri-doc-recordings".to_string()))
+}
+
+// Stop a recording whose duration passed
+fn expire(recording: &mut Recording) {
+    if recording.status.stopped_at.is_none() && Utc::now() >= recording.status.ends_at {
+        recording.status.stopped_at = Some(recording.status.ends_at);
+        recording.status.state = "stopped".to_string();
+        info!("Recording {} of document {} ended", recording.status.id, recording.status.doc_id);
+    }
+}
+
+// The document of a room, block rooms of lazily loaded sheets are recorded with their sheet
+fn doc_of_room(room: &str) -> &str {
+    lazy_block_service::parse_room(room).0
+}
+
+/// Whether the traffic of a room is being recorded
+pub fn is_recording(room: &str) -> bool {
+    let mut recordings = get_recordings().lock().unwrap();
+    match recordings.get_mut(doc_of_room(room)) {
+        Some(recording) => {
+            expire(recording);
+            recording.status.stopped_at.is_none()
+        }
+        None => false,
+    }
+}
+
+/// Start recording the traffic of a document. A running recording is kept, the inner error says so.
+pub async fn start(org_id: &str, doc_uuid: Uuid, request: &RecordingStartRequest) -> Result<Result<RecordingStatus, String>, String> {
+    let doc_id = doc_uuid.to_string();
+    let max_secs = config::get_config().recording_max_secs.unwrap_or(900);
+    let duration_secs = request.duration_secs.unwrap_or(DEFAULT_DURATION_SECS).clamp(1, max_secs.max(1));
+    let previous_path = {
+        let mut recordings = get_recordings().lock().unwrap();
+        match recordings.get_mut(&doc_id) {
+            Some(recording) => {
+                expire(recording);
+                if recording.status.stopped_at.is_none() {
+                    return Ok(Err(format!("Document '{}' is already being recorded by recording {}", doc_id, recording.status.id)));
+                }
+                Some(recording.path.clone())
+            }
+            None => None,
+        }
+    };
+
+    // Replace the previous recording of the document
+    let dir = recording_dir().join(org_id);
+    tokio::fs::create_dir_all(&dir).await.map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    if let Some(previous_path) = previous_path {
+        if let Err(e) = tokio::fs::remove_file(&previous_path).await {
+            warn!("Failed to remove the previous recording {}: {}", previous_path.display(), e);
+        }
+    }
+    let id = Uuid::new_v4().to_string();
+    let path = dir.join(format!("{}-{}.ndjson", doc_id, id));
+    tokio::fs::write(&path, b"").await.map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+
+    let started_at = Utc::now();
+    let status = RecordingStatus {
+        id,
+        org_id: org_id.to_string(),
+        doc_id: doc_id.clone(),
+        state: "recording".to_string(),
+        include_payloads: request.include_payloads,
+        started_at,
+        ends_at: started_at + Duration::seconds(duration_secs as i64),
+        stopped_at: None,
+        frames: 0,
+        bytes: 0,
+    };
+    info!("Recording document {} for {} s as recording {}", doc_id, duration_secs, status.id);
+    get_recordings().lock().unwrap().insert(doc_id, Recording { status: status.clone(), path });
+    Ok(Ok(status))
+}
+
+/// Stop recording a document, None when it was never recorded
+pub fn stop(doc_uuid: Uuid) -> Option<RecordingStatus> {
+    let mut recordings = get_recordings().lock().unwrap();
+    let recording = recordings.get_mut(&doc_uuid.to_string())?;
+    expire(recording);
+    if recording.status.stopped_at.is_none() {
+        recording.status.stopped_at = Some(Utc::now());
+        recording.status.state = "stopped".to_string();
+        info!("Recording {} of document {} stopped", recording.status.id, doc_uuid);
+    }
+    Some(recording.status.clone())
+}
+
+/// The latest recording of a document, None when it was never recorded
+pub fn status(doc_uuid: Uuid) -> Option<RecordingStatus> {
+    let mut recordings = get_recordings().lock().unwrap();
+    let recording = recordings.get_mut(&doc_uuid.to_string())?;
+    expire(recording);
+    Some(recording.status.clone())
+}
+
+/// The frames of the latest recording of a document as JSON lines, with its status
+pub async fn download(doc_uuid: Uuid) -> Result<Option<(RecordingStatus, Vec<u8>)>, String> {
+    let (status, path) = {
+        let mut recordings = get_recordings().lock().unwrap();
+        match recordings.get_mut(&doc_uuid.to_string()) {
+            Some(recording) => {
+                expire(recording);
+                (recording.status.clone(), recording.path.clone())
+            }
+            None => return Ok(None),
+        }
+    };
+    let bytes = tokio::fs::read(&path).await.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(Some((status, bytes)))
+}
+
+/// Record a frame of a room, nothing is written when the room isn't recorded
+pub async fn record(room: &str, direction: Direction, kind: &str, conn_id: Option<u64>, payload: &[u8], detail: Option<String>) {
+    let (path, include_payloads) = {
+        let mut recordings = get_recordings().lock().unwrap();
+        match recordings.get_mut(doc_of_room(room)) {
+            Some(recording) => {
+                expire(recording);
+                if recording.status.stopped_at.is_some() {
+                    return;
+                }
+                (recording.path.clone(), recording.status.include_payloads)
+            }
+            None => return,
+        }
+    };
+
+    let frame = Frame {
+        at: Utc::now(),
+        direction,
+        kind,
+        room,
+        conn_id,
+        size: payload.len(),
+        sha256: Some(sha256_hex(payload)).filter(|_| !payload.is_empty()),
+        payload: Some(general_purpose::STANDARD.encode(payload)).filter(|_| include_payloads && !payload.is_empty()),
+        detail,
+    };
+    let mut line = match serde_json::to_vec(&frame) {
+        Ok(line) => line,
+        Err(e) => {
+            warn!("Failed to encode a recorded frame of room {}: {}", room, e);
+            return;
+        }
+    };
+    line.push(b'\n');
+
+    // Each frame is a single append, so concurrent frames don't interleave within a line
+    let written = async {
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(&path).await?;
+        file.write_all(&line).await
+    }.await;
+    if let Err(e) = written {
+        warn!("Failed to write a recorded frame to {}: {}", path.display(), e);
+        return;
+    }
+    let mut recordings = get_recordings().lock().unwrap();
+    if let Some(recording) = recordings.get_mut(doc_of_room(room)).filter(|recording| recording.path == path) {
+        recording.status.frames += 1;
+        recording.status.bytes += line.len() as u64;
+    }
+}
+
+/// Record the join of a room and the permission it was answered with
+pub async fn record_join(room: &str, conn_id: u64, result: &Result<Option<Permission>, String>) {
+    if !is_recording(room) {
+        return;
+    }
+    record(room, Direction::In, "join", Some(conn_id), &[], None).await;
+    let detail = match result {
+        Ok(Some(Permission::Write)) => "write".to_string(),
+        Ok(Some(_)) => "read".to_string(),
+        Ok(None) => "denied".to_string(),
+        Err(e) => format!("error: {}", e),
+    };
+    record(room, Direction::Out, "join-response", Some(conn_id), &[], Some(detail)).await;
+}
+
+/// Record the updates a client sent to a room
+pub async fn record_updates(room: &str, conn_id: u64, updates: &[Vec<u8>]) {
+    if !is_recording(room) {
+        return;
+    }
+    for update in updates {
+        record(room, Direction::In, "update", Some(conn_id), update, None).await;
+    }
+}
+
+/// Record the status the updates of a client were answered with
+pub async fn record_update_status(room: &str, conn_id: u64, status: &UpdateStatusCode) {
+    if !is_recording(room) {
+        return;
+    }
+    let detail = match status {
+        UpdateStatusCode::Ok => "ok",
+        UpdateStatusCode::PermissionDenied => "permission-denied",
+        UpdateStatusCode::PayloadTooLarge => "payload-too-large",
+        UpdateStatusCode::Unknown => "unknown",
+        _ => "other",
+    };
+    record(room, Direction::Out, "update-status", Some(conn_id), &[], Some(detail.to_string())).await;
+}
+
+/// A frame as read back from a recording
+#[derive(Deserialize)]
+pub struct RecordedFrame {
+    pub direction: String,
+    pub kind: String,
+    #[serde(rename = "connId")]
+    pub conn_id: Option<u64>,
+    pub size: usize,
+    pub sha256: Option<String>,
+    pub payload: Option<String>,
+    pub detail: Option<String>,
+}
+
+/// The state of a document after replaying a recording
+pub struct ReplayOutcome {
+    // Updates accepted by the server and applied, updates it rejected and updates without an answer
+    pub applied: usize,
+    pub rejected: usize,
+    pub unanswered: usize,
+    pub content_hash: String,
+    pub json: Value,
+}
+
+/// Read the frames of a recording, one JSON line each
+pub fn parse_frames(bytes: &[u8]) -> Result<Vec<RecordedFrame>, String> {
+    bytes
+        .split(|b| *b == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !line.iter().all(|b| b.is_ascii_whitespace()))
+        .map(|(idx, line)| serde_json::from_slice(line).map_err(|e| format!("Invalid frame on line {}: {}", idx + 1, e)))
+        .collect()
+}
+
+// The payload of a frame, checked against its recorded hash
+fn frame_payload(frame: &RecordedFrame, line: usize) -> Result<Vec<u8>, String> {
+    let payload = frame.payload.as_deref().ok_or_else(|| {
+        format!("The {} frame {} was recorded without its payload, record with includePayloads to replay", frame.kind, line)
+    })?;
+    let bytes = general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|e| format!("Invalid payload of frame {}: {}", line, e))?;
+    if frame.sha256.as_deref().is_some_and(|sha256| sha256 != sha256_hex(&bytes)) {
+        return Err(format!("The payload of frame {} doesn't match its hash", line));
+    }
+    Ok(bytes)
+}
+
+/// Replay a recording into a fresh document, starting from a fixture snapshot or the snapshot the
+/// recording loaded. The updates of a client are applied when the server answered them with ok.
+pub fn replay(fixture: Option<&[u8]>, frames: &[RecordedFrame]) -> Result<ReplayOutcome, String> {
+    let doc = LoroDoc::new();
+    let mut base_loaded = false;
+    if let Some(fixture) = fixture {
+        doc.import(fixture).map_err(|e| format!("Failed to import the fixture: {}", e))?;
+        base_loaded = true;
+    }
+
+    let mut pending: HashMap<u64, Vec<Vec<u8>>> = HashMap::new();
+    let (mut applied, mut rejected) = (0, 0);
+    for (idx, frame) in frames.iter().enumerate() {
+        let line = idx + 1;
+        match (frame.direction.as_str(), frame.kind.as_str()) {
+            ("server", "load") if !base_loaded => {
+                if frame.size > 0 {
+                    doc.import(&frame_payload(frame, line)?).map_err(|e| format!("Failed to import the snapshot of frame {}: {}", line, e))?;
+                }
+                base_loaded = true;
+            }
+            ("in", "update") => {
+                let update = frame_payload(frame, line)?;
+                pending.entry(frame.conn_id.unwrap_or_default()).or_default().push(update);
+            }
+            ("out", "update-status") => {
+                let updates = pending.remove(&frame.conn_id.unwrap_or_default()).unwrap_or_default();
+                if frame.detail.as_deref() != Some("ok") {
+                    rejected += updates.len();
+                    continue;
+                }
+                for update in updates {
+                    doc.import(&update).map_err(|e| format!("Failed to import an update answered by frame {}: {}", line, e))?;
+                    applied += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let json = doc.get_deep_value().to_json_value();
+    Ok(ReplayOutcome {
+        applied,
+        rejected,
+        unanswered: pending.values().map(|updates| updates.len()).sum(),
+        content_hash: storage_service::content_hash(&json),
+        json,
+    })
+}
@@ -0,0 +1,242 @@
+use std::sync::Arc;
+use axum::{http::{header, StatusCode}, response::{IntoResponse, Response}};
+use chrono::Utc;
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use tracing::warn;
+use uuid::Uuid;
+use crate::doctypes;
+use crate::models::{ColabModel, ColabSheetBlock, ColabStatementModel, TextElement};
+use crate::services::citation_service::{escape_html, render_markdown, render_text};
+use crate::services::negotiation_service::Representation;
+use crate::services::{lazy_block_service, locale_service, public_view_service, script_service, statement_subdoc_service};
+use crate::ws::docctx::DocContext;
+
+// Human readable renderings of a document: Markdown, HTML and PDF.
+// Rows and statements stored apart are composed in first. Transcluded blocks show the latest
+// published version of their source, as in the public view. The PDF is the Markdown laid out as
+// plain text pages in a standard font, graphemes outside of Latin-1 are replaced. HTML and PDF
+// carry the time of the export in the locale and timezone of the org for the content type.
+
+const PDF_FONT_SIZE: usize = 10;
+const PDF_LEADING: usize = 13;
+const PDF_LINES_PER_PAGE: usize = 58;
+const PDF_CHARS_PER_LINE: usize = 95;
+
+// Render the JSON of a document in a human readable representation
+pub async fn render(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, mut json: Value, representation: Representation) -> Result<Vec<u8>, String> {
+    lazy_block_service::assemble_json(org_id, doc_uuid, &mut json).await?;
+    statement_subdoc_service::compose_json(org_id, &mut json).await?;
+    let model: ColabModel = serde_json::from_value(json)
+        .map_err(|e| format!("Failed to parse document '{}': {}", doc_uuid, e))?;
+    let title = format!("Document {}", doc_uuid);
+    let content_type = match &model {
+        ColabModel::Statement(statement) => &statement.properties.content_type,
+        ColabModel::Sheet(sheet) => &sheet.properties.content_type,
+    };
+    let locale = locale_service::resolve(org_id, Some(content_type)).await;
+    let exported = format!("Exported {}", locale.format_datetime(&Utc::now()));
+    match representation {
+        Representation::Markdown => Ok(markdown(registry, org_id, &model).await.into_bytes()),
+        Representation::Html => {
+            let (body, _) = public_view_service::render_content(registry, org_id, &model).await;
+            let body = format!("<p class=\"code\">{}</p>{}", escape_html(&exported), body);
+            Ok(public_view_service::document_page(&title, &body, &locale.locale).into_bytes())
+        }
+        Representation::Pdf => Ok(pdf(&title, &format!("{}\n\n{}", exported, markdown(registry, org_id, &model).await))),
+        other => Err(format!("Documents aren't rendered as {}", other.media_type())),
+    }
+}
+
+// Answer a rendering of a document with its content type
+pub async fn response(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, json: Value, representation: Representation) -> Result<Response, String> {
+    let bytes = render(registry, org_id, doc_uuid, json, representation).await?;
+    Ok((StatusCode::OK, [(header::CONTENT_TYPE, representation.content_type())], bytes).into_response())
+}
+
+async fn markdown(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, model: &ColabModel) -> String {
+    let doc_type = match model {
+        ColabModel::Statement(statement) => statement.properties.r#type.to_string(),
+        ColabModel::Sheet(sheet) => sheet.properties.r#type.to_string(),
+    };
+    let markdown = match doctypes::get(&doc_type) {
+        Some(doc_type) => doc_type.render_markdown(registry, org_id, model).await,
+        None => String::new(),
+    };
+    markdown.trim_end().to_string() + "\n"
+}
+
+pub(crate) async fn markdown_block(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, block: &ColabSheetBlock, markdown: &mut String) {
+    match block {
+        ColabSheetBlock::Properties(_) | ColabSheetBlock::Transclusion(_) => {}
+        ColabSheetBlock::Text(text) => {
+            heading(&text.title, markdown);
+            markdown.push_str(&render_markdown(&text.text_element));
+            markdown.push_str("\n\n");
+        }
+        ColabSheetBlock::Attributes(attributes) => {
+            heading(&attributes.title, markdown);
+            let mut keys: Vec<&String> = attributes.attributes.keys().collect();
+            keys.sort();
+            markdown.push_str("| Attribute | Value |\n| --- | --- |\n");
+            for key in keys {
+                markdown.push_str(&format!("| {} | {} |\n", table_cell(key), table_cell(&attributes.attributes[key].display)));
+            }
+            markdown.push('\n');
+        }
+        ColabSheetBlock::StatementGrid(grid) => {
+            heading(&grid.title, markdown);
+            for row in &grid.rows {
+                if let Some(statement) = &row.statement {
+                    markdown_statement(statement, markdown);
+                } else if let Some(statement_ref) = &row.statement_ref {
+                    match public_view_service::load_statement(registry, org_id, statement_ref.doc_id, statement_ref.version).await {
+                        Ok(statement) => markdown_statement(&statement, markdown),
+                        Err(e) => warn!("Failed to render referenced statement '{}': {}", statement_ref.doc_id, e),
+                    }
+                }
+            }
+        }
+        ColabSheetBlock::Barcode(barcode) => {
+            heading(&barcode.title, markdown);
+            for row in &barcode.rows {
+                markdown.push_str(&format!("- {}: {}\n", row.barcode.r#type, row.barcode.data));
+            }
+            markdown.push('\n');
+        }
+        ColabSheetBlock::Symbol(symbol) => {
+            heading(&symbol.title, markdown);
+            for row in &symbol.rows {
+                markdown.push_str(&format!("- {}\n", row.symbol.r#type));
+            }
+            markdown.push('\n');
+        }
+        ColabSheetBlock::Board(board) => {
+            heading(&board.title, markdown);
+            for column in &board.columns {
+                markdown.push_str(&format!("### {}\n\n", column.title.replace('\n', " ")));
+                for card in &column.cards {
+                    let title = render_text(&card.title).replace('\n', " ");
+                    match &card.assignee {
+                        Some(assignee) => markdown.push_str(&format!("- [{}] {} ({})\n", card.status, title, assignee)),
+                        None => markdown.push_str(&format!("- [{}] {}\n", card.status, title)),
+                    }
+                }
+                markdown.push('\n');
+            }
+        }
+    }
+}
+
+// Every language of a statement as a quote, the master language first
+pub(crate) fn markdown_statement(statement: &ColabStatementModel, markdown: &mut String) {
+    let master = statement.properties.master_lang_code.as_deref();
+    let mut lang_codes: Vec<&String> = statement.content.keys().collect();
+    lang_codes.sort_by(|a, b| (Some(a.as_str()) != master).cmp(&(Some(b.as_str()) != master)).then(a.cmp(b)));
+    for lang_code in lang_codes {
+        let text = render_markdown(&statement.content[lang_code].text_element);
+        markdown.push_str(&format!("> **{}** {}\n", lang_code.to_uppercase(), text.replace('\n', "\n> ")));
+    }
+    markdown.push('\n');
+}
+
+fn heading(title: &TextElement, markdown: &mut String) {
+    let title = render_text(title);
+    if !title.is_empty() {
+        markdown.push_str(&format!("## {}\n\n", title.replace('\n', " ")));
+    }
+}
+
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+// Break a line at word boundaries to fit the width of a page, counting graphemes
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split(' ') {
+        if !current.is_empty() && script_service::grapheme_count(&current) + 1 + script_service::grapheme_count(word) > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+        while script_service::grapheme_count(&current) > width {
+            let head = script_service::take_graphemes(&current, width).to_string();
+            current = current[head.len()..].to_string();
+            lines.push(head);
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+// A line as a PDF string in WinAnsi encoding, one character per grapheme.
+// Combining marks are dropped, graphemes whose base isn't in Latin-1 are replaced.
+fn pdf_string(line: &str) -> Vec<u8> {
+    let mut bytes = vec![b'('];
+    for grapheme in script_service::graphemes(line) {
+        let Some(c) = grapheme.chars().next() else {
+            continue;
+        };
+        match c {
+            '(' | ')' | '\\' => bytes.extend([b'\\', c as u8]),
+            c if (c as u32) < 0x20 => {}
+            c if (c as u32) < 0x7f || ((c as u32) >= 0xa0 && (c as u32) <= 0xff) => bytes.push(c as u32 as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes.push(b')');
+    bytes
+}
+
+// Lay out a text as a PDF with one Helvetica text object per page
+fn pdf(title: &str, text: &str) -> Vec<u8> {
+    let lines: Vec<String> = text.lines().flat_map(|line| wrap(line, PDF_CHARS_PER_LINE)).collect();
+    let pages: Vec<&[String]> = if lines.is_empty() { vec![&[][..]] } else { lines.chunks(PDF_LINES_PER_PAGE).collect() };
+
+    // Objects: 1 catalog, 2 pages, 3 font, 4 info, then a page and its content per page
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 5 + i * 2)).collect();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes());
+    objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec());
+    let mut info = b"<< /Producer (colabri-doc) /Title ".to_vec();
+    info.extend(pdf_string(title));
+    info.extend(b" >>");
+    objects.push(info);
+    for (i, page_lines) in pages.iter().enumerate() {
+        let mut content = format!("BT /F1 {} Tf {} TL 50 800 Td\n", PDF_FONT_SIZE, PDF_LEADING).into_bytes();
+        for line in page_lines.iter() {
+            content.extend(pdf_string(line));
+            content.extend(b" Tj T*\n");
+        }
+        content.extend(b"ET");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            6 + i * 2
+        ).into_bytes());
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(format!("trailer\n<< /Size {} /Root 1 0 R /Info 4 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).into_bytes());
+    pdf
+}
@@ -0,0 +1,182 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use loro::{Container, LoroDoc, LoroMap, LoroText, ValueOrContainer};
+use loro_websocket_server::HubRegistry;
+use regex::{Regex, RegexBuilder};
+use tracing::info;
+use crate::models::{DocumentReplaceRequest, ReplaceMatches};
+use crate::models::lorodoc::{get_child_map, get_string};
+use crate::services::acl_service::{self, AclLevel};
+use crate::services::{doc_edit_service, doc_load_service};
+use crate::ws::docctx::DocContext;
+
+// Find and replace across the text of a document.
+// The titles and text elements of blocks and languages are searched, text blocks of sheets count as
+// the master language of the sheet. Every match is spliced into the run of text it was found in, so
+// concurrent edits elsewhere in the text merge. A match doesn't span two runs, e.g. a word that is
+// partly bold. Rows of lazy blocks and linked statements are stored apart and aren't searched.
+
+// The text fields of a block or language that are searched
+const REPLACED_FIELDS: [&str; 2] = ["title", "textElement"];
+
+// Bound on the compiled pattern, regexes run in linear time but can compile to large automata
+const MAX_PATTERN_SIZE: usize = 1 << 20;
+
+/// A validated find-and-replace request
+#[derive(Clone)]
+pub struct ReplaceOptions {
+    pattern: Regex,
+    replacement: String,
+    // Whether group references in the replacement are expanded
+    expand: bool,
+    block_ids: Option<HashSet<String>>,
+    lang_codes: Option<HashSet<String>>,
+}
+
+/// Compile the pattern of a request, the error is a message for the caller
+pub fn options(request: &DocumentReplaceRequest) -> Result<ReplaceOptions, String> {
+    if request.pattern.is_empty() {
+        return Err("The pattern is empty".to_string());
+    }
+    let source = if request.regex { request.pattern.clone() } else { regex::escape(&request.pattern) };
+    let pattern = RegexBuilder::new(&source)
+        .size_limit(MAX_PATTERN_SIZE)
+        .build()
+        .map_err(|e| format!("Invalid pattern '{}': {}", request.pattern, e))?;
+    Ok(ReplaceOptions {
+        pattern,
+        replacement: request.replacement.clone(),
+        expand: request.regex,
+        block_ids: request.block_ids.as_ref().map(|ids| ids.iter().cloned().collect()),
+        lang_codes: request.lang_codes.as_ref().map(|codes| codes.iter().cloned().collect()),
+    })
+}
+
+/// The outcome of a find-and-replace in a document
+pub struct Replacement {
+    pub blocks: Vec<ReplaceMatches>,
+    // The state the replacements were applied to and the peer applying them, None on a dry run or without matches.
+    // Reverting the changes of the peer after the state undoes the replacements.
+    pub applied: Option<(HashMap<String, i32>, u64)>,
+}
+
+// Find, or find and replace, the pattern in every block and language in scope
+fn replace_in_doc(doc: &LoroDoc, options: &ReplaceOptions, apply: bool) -> Result<Vec<ReplaceMatches>, String> {
+    let master_lang_code = get_string(&doc.get_map("properties"), "masterLangCode");
+    let mut counts = Vec::new();
+    for scope in acl_service::collect_acl_scopes(doc)? {
+        let Some(map) = &scope.map else {
+            continue;
+        };
+        // Paths are /content/<block>, /content/<lang> of statements, or a language of a local statement
+        let segments: Vec<&str> = scope.path.split('/').filter(|s| !s.is_empty()).collect();
+        let (block_id, lang_code) = match (&scope.level, segments.len()) {
+            (AclLevel::Block, 2) => (Some(segments[1].to_string()), master_lang_code.clone()),
+            (AclLevel::Language, 2) => (None, Some(segments[1].to_string())),
+            (AclLevel::Language, _) => (Some(segments[1].to_string()), segments.last().map(|s| s.to_string())),
+            _ => continue,
+        };
+        if let Some(block_ids) = &options.block_ids {
+            if !block_id.as_ref().is_some_and(|id| block_ids.contains(id)) {
+                continue;
+            }
+        }
+        if let Some(lang_codes) = &options.lang_codes {
+            if !lang_code.as_ref().is_some_and(|code| lang_codes.contains(code)) {
+                continue;
+            }
+        }
+
+        let mut matches = 0;
+        for field in REPLACED_FIELDS {
+            let Some(element) = get_child_map(map, field) else {
+                continue;
+            };
+            let mut leaves = Vec::new();
+            collect_texts(&element, &mut leaves, 0);
+            for text in leaves {
+                matches += replace_in_text(&text, options, apply)?;
+            }
+        }
+        if matches > 0 {
+            counts.push(ReplaceMatches { block_id, lang_code, matches });
+        }
+    }
+    Ok(counts)
+}
+
+// Replace the matches in a run of text from the last to the first, so earlier positions stay valid
+fn replace_in_text(text: &LoroText, options: &ReplaceOptions, apply: bool) -> Result<usize, String> {
+    let value = text.to_string();
+    let mut spans = Vec::new();
+    let (mut byte, mut chars) = (0, 0);
+    for captures in options.pattern.captures_iter(&value) {
+        let found = captures.get(0).expect("group 0 is the whole match");
+        // Empty matches, e.g. of `a*`, replace nothing
+        if found.start() == found.end() {
+            continue;
+        }
+        chars += value[byte..found.start()].chars().count();
+        let len = found.as_str().chars().count();
+        let replacement = if options.expand {
+            let mut expanded = String::new();
+            captures.expand(&options.replacement, &mut expanded);
+            expanded
+        } else {
+            options.replacement.clone()
+        };
+        spans.push((chars, len, replacement));
+        byte = found.start();
+    }
+    if apply {
+        for (pos, len, replacement) in spans.iter().rev() {
+            text.splice(*pos, *len, replacement).map_err(|e| format!("Failed to replace text: {}", e))?;
+        }
+    }
+    Ok(spans.len())
+}
+
+fn collect_texts(element: &LoroMap, leaves: &mut Vec<LoroText>, depth: usize) {
+    const MAX_DEPTH: usize = 100; // Prevent stack overflow
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let children = match element.get("children") {
+        Some(ValueOrContainer::Container(Container::List(list))) => list,
+        _ => return,
+    };
+    for idx in 0..children.len() {
+        match children.get(idx) {
+            Some(ValueOrContainer::Container(Container::Text(text))) => leaves.push(text),
+            Some(ValueOrContainer::Container(Container::Map(child))) => collect_texts(&child, leaves, depth + 1),
+            _ => {}
+        }
+    }
+}
+
+// Find and replace across the latest state of a document, counting only on a dry run.
+// Returns the matches per block and language, None when the document doesn't exist.
+pub async fn replace(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, options: ReplaceOptions, dry_run: bool) -> Result<Option<Replacement>, String> {
+    let doc = match doc_load_service::load_loro_doc(registry, org_id, doc_id).await? {
+        Some((doc, _)) => doc,
+        None => return Ok(None),
+    };
+    let blocks = replace_in_doc(&doc, &options, false)?;
+    if dry_run || blocks.is_empty() {
+        return Ok(Some(Replacement { blocks, applied: None }));
+    }
+
+    // Replace in the room, the counts of the edit are the ones that were applied
+    let replaced: Arc<Mutex<Option<Replacement>>> = Arc::new(Mutex::new(None));
+    let result = replaced.clone();
+    doc_edit_service::edit_doc(registry.clone(), org_id, doc_id, move |doc: &LoroDoc| {
+        let before = doc.state_vv().iter().map(|(peer, counter)| (peer.to_string(), *counter)).collect();
+        let blocks = replace_in_doc(doc, &options, true)?;
+        doc.commit();
+        *result.lock().unwrap() = Some(Replacement { blocks, applied: Some((before, doc.peer_id())) });
+        Ok(())
+    }, false).await?;
+    let replaced = replaced.lock().unwrap().take().ok_or_else(|| "The replacement wasn't applied".to_string())?;
+    info!("Replaced {} matches in document {}", replaced.blocks.iter().map(|count| count.matches).sum::<usize>(), doc_id);
+    Ok(Some(replaced))
+}
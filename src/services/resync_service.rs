@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use loro::{LoroDoc, LoroMap, ToJson};
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use tracing::{error, info};
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::models::{ColabModel, ColabSheetBlock, ColabStatementElement, DocumentResyncRequest, DocumentVersionTag, ResyncChange};
+use crate::models::lorodoc::{colab_sheet_block_to_loro_map, get_block_id, get_child_map, get_list_map, txtelem_to_loro_doc};
+use crate::services::numbering_service::NUMBER_FIELD;
+use crate::services::{compare_service, doc_edit_service, doc_load_service, lazy_block_service};
+use crate::ws::docctx::DocContext;
+
+// Re-sync of a document from an external model, its source of truth.
+// Statements are synced per language, sheets per block, blocks are matched by id and blocks without
+// id by position. Parts only in the external model are added. Parts that differ, and parts only in
+// the document, are resolved by the strategy:
+// - external-wins: the external part replaces the document part, parts only in the document are removed
+// - doc-wins: the document part is kept
+// - manual: as decided per part in the request, undecided parts are left alone and reported unresolved
+// Replaced parts keep their id, number, ACLs, comments and approvals, linked statements of a replaced
+// grid become local. Sheets with lazy blocks are joined first. The state before the re-sync is tagged
+// like the documents changed by a bulk find-and-replace.
+
+// Keys of a part that belong to the document and are neither compared nor replaced
+const KEPT_KEYS: [&str; 5] = ["id", NUMBER_FIELD, "acls", "comments", "approvals"];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    ExternalWins,
+    DocWins,
+    Manual,
+}
+
+impl Strategy {
+    pub fn parse(strategy: &str) -> Result<Self, String> {
+        match strategy {
+            "external-wins" => Ok(Strategy::ExternalWins),
+            "doc-wins" => Ok(Strategy::DocWins),
+            "manual" => Ok(Strategy::Manual),
+            other => Err(format!("Unknown conflict strategy '{}'. Use external-wins, doc-wins or manual.", other)),
+        }
+    }
+}
+
+/// The outcome of a re-sync
+pub struct Resynced {
+    pub changes: Vec<ResyncChange>,
+    pub version_tag: Option<DocumentVersionTag>,
+}
+
+// A block or language in its JSON form
+struct Part {
+    key: String,
+    index: usize,
+    json: Value,
+    // Without the kept keys, after a round trip through the model
+    normalized: Value,
+}
+
+fn strip_kept_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for key in KEPT_KEYS {
+                map.remove(key);
+            }
+            map.values_mut().for_each(strip_kept_keys);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_kept_keys),
+        _ => {}
+    }
+}
+
+// The parts of a document: the blocks of a sheet or the languages of a statement
+fn parts(json: &Value) -> Vec<Part> {
+    let normalize = |value: &Value, is_block: bool| {
+        let mut normalized = if is_block {
+            serde_json::from_value::<ColabSheetBlock>(value.clone()).ok().and_then(|block| serde_json::to_value(block).ok())
+        } else {
+            serde_json::from_value::<ColabStatementElement>(value.clone()).ok().and_then(|element| serde_json::to_value(element).ok())
+        }
+        .unwrap_or_else(|| value.clone());
+        strip_kept_keys(&mut normalized);
+        normalized
+    };
+    match json.get("content") {
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| Part {
+                key: block.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()).unwrap_or_else(|| i.to_string()),
+                index: i,
+                json: block.clone(),
+                normalized: normalize(block, true),
+            })
+            .collect(),
+        Some(Value::Object(languages)) => {
+            let mut lang_codes: Vec<&String> = languages.keys().collect();
+            lang_codes.sort();
+            lang_codes
+                .into_iter()
+                .enumerate()
+                .map(|(i, lang_code)| Part {
+                    key: lang_code.clone(),
+                    index: i,
+                    json: languages[lang_code].clone(),
+                    normalized: normalize(&languages[lang_code], false),
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// What to do with every part that differs
+fn plan(current: &[Part], source: &[Part], strategy: Strategy, decisions: &HashMap<String, String>) -> Vec<ResyncChange> {
+    let resolve = |key: &str, external: &str, document: &str| -> String {
+        match strategy {
+            Strategy::ExternalWins => external.to_string(),
+            Strategy::DocWins => document.to_string(),
+            Strategy::Manual => match decisions.get(key).map(String::as_str) {
+                Some("external") => external.to_string(),
+                Some("document") => document.to_string(),
+                _ => "unresolved".to_string(),
+            },
+        }
+    };
+    let mut changes = Vec::new();
+    for part in source {
+        match current.iter().find(|c| c.key == part.key) {
+            Some(c) if c.normalized == part.normalized => {}
+            Some(_) => changes.push(ResyncChange { part: part.key.clone(), status: "changed".to_string(), action: resolve(&part.key, "replaced", "kept") }),
+            None => changes.push(ResyncChange { part: part.key.clone(), status: "missing".to_string(), action: "added".to_string() }),
+        }
+    }
+    for part in current.iter().filter(|c| !source.iter().any(|s| s.key == c.key)) {
+        changes.push(ResyncChange { part: part.key.clone(), status: "extra".to_string(), action: resolve(&part.key, "removed", "kept") });
+    }
+    changes
+}
+
+// The external block in the document, with the kept keys of the document block
+fn block_map(source: &Part, current: Option<&Value>) -> Result<LoroMap, String> {
+    let mut json = source.json.clone();
+    if let (Some(json), Some(Value::Object(current))) = (json.as_object_mut(), current) {
+        for key in KEPT_KEYS {
+            match current.get(key) {
+                Some(value) => json.insert(key.to_string(), value.clone()),
+                None => json.remove(key),
+            };
+        }
+    }
+    let block: ColabSheetBlock = serde_json::from_value(json.clone())
+        .map_err(|e| format!("Invalid block '{}' in the external model: {}", source.key, e))?;
+    let map = colab_sheet_block_to_loro_map(&block);
+    let id = json.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()).unwrap_or_else(|| Uuid::new_v4().to_string());
+    map.insert("id", id.as_str()).map_err(|e| format!("Failed to set the block id: {}", e))?;
+    if let Some(number) = json.get(NUMBER_FIELD).and_then(|n| n.as_str()) {
+        map.insert(NUMBER_FIELD, number).map_err(|e| format!("Failed to set the block number: {}", e))?;
+    }
+    Ok(map)
+}
+
+// Apply the planned changes to a sheet
+fn apply_sheet(doc: &LoroDoc, changes: &[ResyncChange], source: &[Part]) -> Result<(), String> {
+    let content = doc.get_movable_list("content");
+    let find = |key: &str| (0..content.len()).find(|&i| get_list_map(&content, i).is_some_and(|block| get_block_id(&block, i) == key));
+    for change in changes {
+        let Some(idx) = find(&change.part) else {
+            continue;
+        };
+        match change.action.as_str() {
+            "removed" => content.delete(idx, 1).map_err(|e| format!("Failed to remove block '{}': {}", change.part, e))?,
+            "replaced" => {
+                let part = source.iter().find(|part| part.key == change.part).ok_or_else(|| format!("Block '{}' not found in the external model", change.part))?;
+                let current = get_list_map(&content, idx).map(|block| block.get_deep_value().to_json_value());
+                let map = block_map(part, current.as_ref())?;
+                content.delete(idx, 1).map_err(|e| format!("Failed to replace block '{}': {}", change.part, e))?;
+                content.insert_container(idx, map).map_err(|e| format!("Failed to replace block '{}': {}", change.part, e))?;
+            }
+            _ => {}
+        }
+    }
+    for change in changes.iter().filter(|change| change.action == "added") {
+        let part = source.iter().find(|part| part.key == change.part).ok_or_else(|| format!("Block '{}' not found in the external model", change.part))?;
+        let map = block_map(part, None)?;
+        content.insert_container(part.index.min(content.len()), map).map_err(|e| format!("Failed to add block '{}': {}", change.part, e))?;
+    }
+    Ok(())
+}
+
+// Apply the planned changes to a statement
+fn apply_statement(doc: &LoroDoc, changes: &[ResyncChange], source: &[Part]) -> Result<(), String> {
+    let content = doc.get_map("content");
+    for change in changes {
+        match change.action.as_str() {
+            "removed" => content.delete(&change.part).map_err(|e| format!("Failed to remove language '{}': {}", change.part, e))?,
+            "replaced" | "added" => {
+                let part = source.iter().find(|part| part.key == change.part).ok_or_else(|| format!("Language '{}' not found in the external model", change.part))?;
+                let element: ColabStatementElement = serde_json::from_value(part.json.clone())
+                    .map_err(|e| format!("Invalid language '{}' in the external model: {}", change.part, e))?;
+                let lang_map = match get_child_map(&content, &change.part) {
+                    Some(lang_map) => lang_map,
+                    None => {
+                        let lang_map = content.insert_container(&change.part, LoroMap::new())
+                            .map_err(|e| format!("Failed to add language '{}': {}", change.part, e))?;
+                        lang_map.insert_container("acls", LoroMap::new())
+                            .map_err(|e| format!("Failed to add language '{}': {}", change.part, e))?;
+                        lang_map
+                    }
+                };
+                let text_element = lang_map.insert_container("textElement", LoroMap::new())
+                    .map_err(|e| format!("Failed to replace language '{}': {}", change.part, e))?;
+                txtelem_to_loro_doc(&element.text_element, &text_element);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Re-sync the latest state of a document from an external model, only planning on a dry run.
+// The inner error is a message for the caller about the request, None when the document doesn't exist.
+pub async fn resync(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, request: DocumentResyncRequest, by_prpl: &str) -> Result<Result<Option<Resynced>, String>, String> {
+    // 1. Check the request against the document
+    let strategy = match Strategy::parse(&request.strategy) {
+        Ok(strategy) => strategy,
+        Err(e) => return Ok(Err(e)),
+    };
+    let source_model: ColabModel = match serde_json::from_value(request.model.clone()) {
+        Ok(model) => model,
+        Err(e) => return Ok(Err(format!("Invalid external model: {}", e))),
+    };
+    let doc = match doc_load_service::load_loro_doc(registry, org_id, doc_id).await? {
+        Some((doc, _)) => doc,
+        None => return Ok(Ok(None)),
+    };
+    if lazy_block_service::is_lazy(&doc) {
+        return Ok(Err(format!("Document '{}' has lazy blocks, join them before re-syncing", doc_id)));
+    }
+    let json = doc.get_deep_value().to_json_value();
+    let current_model: ColabModel = serde_json::from_value(json.clone())
+        .map_err(|e| format!("Failed to parse document '{}': {}", doc_id, e))?;
+    if compare_service::doc_type(&current_model) != compare_service::doc_type(&source_model) {
+        return Ok(Err(format!("The external model is a {}, the document a {}", compare_service::doc_type(&source_model), compare_service::doc_type(&current_model))));
+    }
+    let decisions = request.decisions;
+    let source = parts(&request.model);
+    let changes = plan(&parts(&json), &source, strategy, &decisions);
+    let changes_anything = changes.iter().any(|change| matches!(change.action.as_str(), "added" | "replaced" | "removed"));
+    if request.dry_run || !changes_anything {
+        return Ok(Ok(Some(Resynced { changes, version_tag: None })));
+    }
+
+    // 2. Apply the plan to the live document, which may have changed since it was loaded
+    let is_sheet = matches!(current_model, ColabModel::Sheet(_));
+    let applied: Arc<Mutex<Option<(Vec<ResyncChange>, HashMap<String, i32>, u64)>>> = Arc::new(Mutex::new(None));
+    let result = applied.clone();
+    doc_edit_service::edit_doc(registry.clone(), org_id, doc_id, move |doc: &LoroDoc| {
+        let before = doc.state_vv().iter().map(|(peer, counter)| (peer.to_string(), *counter)).collect();
+        let changes = plan(&parts(&doc.get_deep_value().to_json_value()), &source, strategy, &decisions);
+        if is_sheet {
+            apply_sheet(doc, &changes, &source)?;
+        } else {
+            apply_statement(doc, &changes, &source)?;
+        }
+        doc.commit();
+        *result.lock().unwrap() = Some((changes, before, doc.peer_id()));
+        Ok(())
+    }, false).await?;
+    let (changes, state_vv, peer) = applied.lock().unwrap().take().ok_or_else(|| "The re-sync wasn't applied".to_string())?;
+
+    // 3. Tag the state the re-sync was applied to
+    let tag = format!("resync-{}", Uuid::new_v4());
+    let state_vv = serde_json::to_value(&state_vv).unwrap_or_default();
+    let peer = peer.to_string();
+    let version_tag = match dbcolab::get_db() {
+        Some(db) => match db.insert_document_version_tag(org_id, Uuid::parse_str(doc_id).unwrap_or_default(), &tag, state_vv.clone(), Some(&peer), by_prpl).await {
+            Ok(version) => Some(DocumentVersionTag { tag, version, state_vv, peer }),
+            Err(e) => {
+                error!("Failed to tag document {} after re-syncing it: {}", doc_id, e);
+                None
+            }
+        },
+        None => None,
+    };
+    info!("Re-synced {} parts of document {} from an external model", changes.len(), doc_id);
+    Ok(Ok(Some(Resynced { changes, version_tag })))
+}
@@ -0,0 +1,91 @@
+use std::collections::{HashMap, HashSet};
+use loro::{ChangeMeta, Frontiers, LoroDoc, ToJson, VersionVector, ID};
+use serde_json::Value;
+
+/// A change of the author that gets reverted, clipped to the part after the version vector
+pub struct RevertChange {
+    pub meta: ChangeMeta,
+    // First counter of the change that is reverted
+    pub start: i32,
+}
+
+// The peers that belong to the author, either a single peer or all peers of a principal in the peer registry
+pub fn resolve_peers(peer_map: &HashMap<u64, String>, prpl: Option<&str>, peer: Option<u64>) -> Result<HashSet<u64>, String> {
+    let mut peers: HashSet<u64> = HashSet::new();
+    if let Some(prpl) = prpl {
+        peers.extend(peer_map.iter().filter(|(_, p)| p.as_str() == prpl).map(|(peer, _)| *peer));
+        if peers.is_empty() {
+            return Err(format!("Principal '{}' has no peers in this document", prpl));
+        }
+    }
+    if let Some(peer) = peer {
+        peers.insert(peer);
+    }
+    if peers.is_empty() {
+        return Err("Either a principal or a peer is required".to_string());
+    }
+    Ok(peers)
+}
+
+// The changes of the peers after the version vector, the latest first so they can be undone in order
+pub fn plan(doc: &LoroDoc, peers: &HashSet<u64>, after: &VersionVector) -> Vec<RevertChange> {
+    let oplog_vv = doc.oplog_vv();
+    let mut changes: Vec<RevertChange> = Vec::new();
+    for peer in peers {
+        let end = oplog_vv.get(peer).copied().unwrap_or(0);
+        let mut counter = after.get(peer).copied().unwrap_or(0);
+        while counter < end {
+            match doc.get_change(ID::new(*peer, counter)) {
+                Some(meta) => {
+                    let next = meta.id.counter + meta.len as i32;
+                    changes.push(RevertChange { meta, start: counter });
+                    counter = next;
+                }
+                None => break,
+            }
+        }
+    }
+    changes.sort_by_key(|c| std::cmp::Reverse((c.meta.lamport, c.meta.id.peer)));
+    changes
+}
+
+// Undo the planned changes on the current state of the document and commit the result.
+// Each change is inverted by diffing from its end back to the version right before it, the inverse
+// is applied on top of everything that happened since. Later edits by others are kept, text that
+// was edited by both sides is reverted on a best effort basis.
+pub fn apply(doc: &LoroDoc, changes: &[RevertChange]) -> Result<(), String> {
+    for change in changes {
+        let peer = change.meta.id.peer;
+        let end = change.meta.id.counter + change.meta.len as i32 - 1;
+        let after = Frontiers::from_id(ID::new(peer, end));
+        let before = if change.start > change.meta.id.counter {
+            Frontiers::from_id(ID::new(peer, change.start - 1))
+        } else {
+            change.meta.deps.clone()
+        };
+        let inverse = doc.diff(&after, &before)
+            .map_err(|e| format!("Failed to invert change {}@{}: {}", change.start, peer, e))?;
+        doc.apply_diff(inverse)
+            .map_err(|e| format!("Failed to revert change {}@{}: {}", change.start, peer, e))?;
+    }
+    doc.commit();
+    Ok(())
+}
+
+// The updates of the planned changes as JSON, this is what gets reverted
+pub fn reverted_updates(doc: &LoroDoc, peers: &HashSet<u64>, after: &VersionVector) -> Result<Value, String> {
+    let oplog_vv = doc.oplog_vv();
+    let mut from = oplog_vv.clone();
+    for peer in peers {
+        from.insert(*peer, after.get(peer).copied().unwrap_or(0));
+    }
+    let updates = doc.export_json_updates_without_peer_compression(&from, &oplog_vv);
+    serde_json::to_value(&updates).map_err(|e| format!("Failed to serialize updates: {}", e))
+}
+
+// The state of the document with the changes reverted, computed on a fork
+pub fn preview(doc: &LoroDoc, changes: &[RevertChange]) -> Result<Value, String> {
+    let fork = doc.fork();
+    apply(&fork, changes)?;
+    Ok(fork.get_deep_value().to_json_value())
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use axum::http::StatusCode;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use crate::config;
+
+// Points per pod on the ring, smooths out the distribution of rooms
+const VIRTUAL_NODES: usize = 64;
+
+static RING: OnceLock<Option<HashRing>> = OnceLock::new();
+
+/// A consistent hash ring over the pods. Adding or removing a pod only moves the rooms of that pod.
+pub struct HashRing {
+    nodes: BTreeMap<u64, String>,
+}
+
+impl HashRing {
+    pub fn new(pods: &[String]) -> Self {
+        let mut nodes = BTreeMap::new();
+        for pod in pods {
+            for idx in 0..VIRTUAL_NODES {
+                nodes.insert(hash(&format!("{}#{}", pod, idx)), pod.clone());
+            }
+        }
+        Self { nodes }
+    }
+
+    /// The pod owning a key: the first node clockwise from the hash of the key
+    pub fn owner(&self, key: &str) -> Option<&str> {
+        let h = hash(key);
+        self.nodes
+            .range(h..)
+            .next()
+            .or_else(|| self.nodes.iter().next())
+            .map(|(_, pod)| pod.as_str())
+    }
+}
+
+// A hash that is stable across pods and releases
+fn hash(key: &str) -> u64 {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+// The ring of the configured pods, None when room affinity is disabled (single pod)
+fn get_ring() -> Option<&'static HashRing> {
+    RING.get_or_init(|| {
+        let config = config::get_config();
+        config.cloud_pod.as_ref()?;
+        let pods: Vec<String> = config.cloud_pods
+            .as_deref()?
+            .split(',')
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        if pods.len() < 2 {
+            return None;
+        }
+        info!("Room affinity enabled over pods: {:?}", pods);
+        Some(HashRing::new(&pods))
+    })
+    .as_ref()
+}
+
+/// Where a room lives
+pub struct RoomAssignment {
+    pub pod: String,
+    pub ws_url: String,
+    pub local: bool,
+}
+
+// Get the pod owning the room of a document. Without room affinity every room is local.
+pub fn assign(doc_id: &str) -> RoomAssignment {
+    let config = config::get_config();
+    let local_pod = config.cloud_pod.clone().unwrap_or_default();
+    let pod = get_ring()
+        .and_then(|ring| ring.owner(doc_id))
+        .map(|pod| pod.to_string())
+        .unwrap_or_else(|| local_pod.clone());
+    RoomAssignment {
+        ws_url: config.pod_ws_url(&pod),
+        local: pod == local_pod,
+        pod,
+    }
+}
+
+// The reason a join is refused when the room is owned by another pod, None when the room is local.
+// Clients recognize the `redirect:` prefix and reconnect to the given WebSocket URL.
+pub fn redirect_reason(doc_id: &str) -> Option<String> {
+    let assignment = assign(doc_id);
+    if assignment.local {
+        None
+    } else {
+        Some(format!("{}{}", REDIRECT_PREFIX, assignment.ws_url))
+    }
+}
+
+const REDIRECT_PREFIX: &str = "redirect:";
+
+// Refuse to open the room of a document owned by another pod, a second room would diverge from the
+// one of the owner. The error is the redirect reason of the owner.
+pub fn ensure_local(doc_id: &str) -> Result<(), String> {
+    match redirect_reason(doc_id) {
+        Some(reason) => Err(reason),
+        None => Ok(()),
+    }
+}
+
+// The HTTP status of a failed edit, 421 when the room is owned by another pod so the caller retries there
+pub fn edit_error_status(error: &str) -> StatusCode {
+    misdirected_or(error, StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+// Same as edit_error_status, for edits whose other failures have a status of their own
+pub fn misdirected_or(error: &str, status: StatusCode) -> StatusCode {
+    if error.contains(REDIRECT_PREFIX) {
+        StatusCode::MISDIRECTED_REQUEST
+    } else {
+        status
+    }
+}
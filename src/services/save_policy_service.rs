@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use moka::sync::Cache;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab;
+use crate::models::DocumentSettings;
+use crate::ws::docctx::DocContext;
+
+// How long per-org policies are cached before they are read from the database again
+const POLICIES_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+// How often deferred saves are checked
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// How long the schedule of a document without saves or updates is kept
+const SCHEDULE_TTL: Duration = Duration::from_secs(60 * 60);
+
+static POLICIES_CACHE: OnceLock<Cache<String, SavePolicyOverrides>> = OnceLock::new();
+static SCHEDULE: OnceLock<Mutex<HashMap<Uuid, SaveSchedule>>> = OnceLock::new();
+
+/// How a document is written to the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Persistence {
+    // A full snapshot with the state and the history, fast to load
+    Snapshot,
+    // Only the history, smaller for large documents but the state is rebuilt on load
+    Updates,
+}
+
+/// The save policy of a document
+#[derive(Debug, Clone)]
+pub struct SavePolicy {
+    pub interval: Duration,
+    pub debounce: Duration,
+    pub persistence: Persistence,
+}
+
+/// Per-org overrides of a save policy, as stored in the database
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavePolicyEntry {
+    interval_ms: Option<u64>,
+    debounce_ms: Option<u64>,
+    persistence: Option<Persistence>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavePolicyOverrides {
+    #[serde(default)]
+    doc_types: HashMap<String, SavePolicyEntry>,
+    #[serde(default)]
+    content_types: HashMap<String, SavePolicyEntry>,
+}
+
+/// A save that was deferred by the policy, persisted by the flusher once it is due
+pub struct DeferredSave {
+    pub room: String,
+    pub snapshot: Vec<u8>,
+    pub ctx: DocContext,
+}
+
+#[derive(Default)]
+struct SaveSchedule {
+    last_saved: Option<Instant>,
+    last_update: Option<Instant>,
+    deferred_since: Option<Instant>,
+    due: Option<Instant>,
+    pending: Option<DeferredSave>,
+}
+
+impl Default for SavePolicy {
+    fn default() -> Self {
+        let config = config::get_config();
+        let persistence = match config.doc_save_persistence.as_deref() {
+            Some("updates") => Persistence::Updates,
+            _ => Persistence::Snapshot,
+        };
+        Self {
+            interval: Duration::from_millis(config.doc_save_interval_ms.unwrap_or(30_000)),
+            debounce: Duration::from_millis(config.doc_save_debounce_ms.unwrap_or(0)),
+            persistence,
+        }
+    }
+}
+
+fn get_cache() -> &'static Cache<String, SavePolicyOverrides> {
+    POLICIES_CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(POLICIES_CACHE_TTL)
+            .build()
+    })
+}
+
+fn get_schedule() -> &'static Mutex<HashMap<Uuid, SaveSchedule>> {
+    SCHEDULE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// The tick of the save scheduler of the websocket server
+pub fn scheduler_tick_ms() -> Option<u64> {
+    let config = config::get_config();
+    config.doc_save_tick_ms.or(config.doc_save_interval_ms)
+}
+
+// Resolve the save policy of a document from the JSON of the document.
+// A policy for its contentType wins over one for its document type, both over the defaults.
+pub async fn get_policy(org_id: &str, json: &Value) -> SavePolicy {
+    let properties = json.get("properties");
+    let doc_type = properties.and_then(|p| p.get("type")).and_then(|t| t.as_str()).unwrap_or_default();
+    let content_type = properties.and_then(|p| p.get("contentType")).and_then(|t| t.as_str()).unwrap_or_default();
+
+    let overrides = get_overrides(org_id).await;
+    let mut policy = SavePolicy::default();
+    for entry in [overrides.doc_types.get(doc_type), overrides.content_types.get(content_type)].into_iter().flatten() {
+        if let Some(interval_ms) = entry.interval_ms {
+            policy.interval = Duration::from_millis(interval_ms);
+        }
+        if let Some(debounce_ms) = entry.debounce_ms {
+            policy.debounce = Duration::from_millis(debounce_ms);
+        }
+        if let Some(persistence) = entry.persistence {
+            policy.persistence = persistence;
+        }
+    }
+    policy
+}
+
+// Apply the overrides of a single document to its resolved policy
+pub fn with_document_settings(mut policy: SavePolicy, settings: &DocumentSettings) -> SavePolicy {
+    if let Some(overrides) = &settings.save_policy {
+        if let Some(interval_ms) = overrides.interval_ms {
+            policy.interval = Duration::from_millis(interval_ms);
+        }
+        if let Some(debounce_ms) = overrides.debounce_ms {
+            policy.debounce = Duration::from_millis(debounce_ms);
+        }
+        match overrides.persistence.as_deref() {
+            Some("snapshot") => policy.persistence = Persistence::Snapshot,
+            Some("updates") => policy.persistence = Persistence::Updates,
+            _ => {}
+        }
+    }
+    policy
+}
+
+// When the overrides can't be loaded the defaults apply, a policy should never keep documents from being saved
+async fn get_overrides(org_id: &str) -> SavePolicyOverrides {
+    if let Some(overrides) = get_cache().get(org_id) {
+        return overrides;
+    }
+    let overrides = match dbcolab::get_db() {
+        Some(db) => match db.get_org_save_policies(org_id).await {
+            Ok(Some(value)) => serde_json::from_value::<SavePolicyOverrides>(value).unwrap_or_else(|e| {
+                error!("Invalid save policies for organization '{}': {}", org_id, e);
+                SavePolicyOverrides::default()
+            }),
+            Ok(None) => SavePolicyOverrides::default(),
+            Err(e) => {
+                error!("Failed to load save policies of organization '{}': {}", org_id, e);
+                return SavePolicyOverrides::default();
+            }
+        },
+        None => return SavePolicyOverrides::default(),
+    };
+    get_cache().insert(org_id.to_string(), overrides.clone());
+    overrides
+}
+
+// Record an update of a document, it postpones a debounced save
+pub fn record_update(doc_uuid: Uuid) {
+    let mut schedule = get_schedule().lock().unwrap();
+    schedule.entry(doc_uuid).or_default().last_update = Some(Instant::now());
+}
+
+// Decide whether a save requested by the scheduler happens now. When it is not due yet the save is
+// kept and the latest one is persisted by the flusher once it is. A save is never deferred for longer
+// than twice the larger of the interval and the debounce, so constant editing can't starve it.
+pub fn should_save_now(save: DeferredSave, policy: &SavePolicy) -> Option<DeferredSave> {
+    let now = Instant::now();
+    let mut schedule = get_schedule().lock().unwrap();
+    let entry = schedule.entry(save.ctx.doc_id).or_default();
+
+    let mut due = entry.last_saved.map(|t| t + policy.interval).unwrap_or(now);
+    if let Some(last_update) = entry.last_update {
+        due = due.max(last_update + policy.debounce);
+    }
+    let deferred_since = *entry.deferred_since.get_or_insert(now);
+    due = due.min(deferred_since + policy.interval.max(policy.debounce) * 2);
+
+    if due <= now {
+        entry.pending = None;
+        entry.due = None;
+        return Some(save);
+    }
+    entry.due = Some(due);
+    entry.pending = Some(save);
+    None
+}
+
+// Whether a save of the document is waiting for its policy
+pub fn is_deferred(doc_uuid: Uuid) -> bool {
+    let schedule = get_schedule().lock().unwrap();
+    schedule.get(&doc_uuid).map(|entry| entry.pending.is_some()).unwrap_or(false)
+}
+
+// Record that a document was saved
+pub fn record_saved(doc_uuid: Uuid) {
+    let mut schedule = get_schedule().lock().unwrap();
+    let entry = schedule.entry(doc_uuid).or_default();
+    entry.last_saved = Some(Instant::now());
+    entry.deferred_since = None;
+}
+
+// Take the deferred saves that are due, and drop the schedules of documents that went quiet
+fn take_due() -> Vec<DeferredSave> {
+    let now = Instant::now();
+    let mut schedule = get_schedule().lock().unwrap();
+    schedule.retain(|_, entry| {
+        entry.pending.is_some() || [entry.last_saved, entry.last_update].into_iter().flatten().any(|t| now.duration_since(t) < SCHEDULE_TTL)
+    });
+    schedule
+        .values_mut()
+        .filter(|entry| entry.due.map(|due| due <= now).unwrap_or(false))
+        .filter_map(|entry| {
+            entry.due = None;
+            entry.pending.take()
+        })
+        .collect()
+}
+
+// Take all deferred saves, due or not
+pub fn take_all() -> Vec<DeferredSave> {
+    let mut schedule = get_schedule().lock().unwrap();
+    schedule
+        .values_mut()
+        .filter_map(|entry| {
+            entry.due = None;
+            entry.pending.take()
+        })
+        .collect()
+}
+
+// Start the flusher persisting deferred saves once they are due
+pub fn spawn<F, Fut>(persist: F)
+where
+    F: Fn(DeferredSave) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<(), String>> + Send + 'static,
+{
+    info!("Starting save flusher, default save policy: {:?}", SavePolicy::default());
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for save in take_due() {
+                let room = save.room.clone();
+                if let Err(e) = persist(save).await {
+                    error!("Failed to persist deferred save of document '{}': {}", room, e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::config;
+use crate::services::save_policy_service::DeferredSave;
+
+// How often the retry queue is checked for due retries
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+static RETRY_QUEUE: OnceLock<Mutex<HashMap<Uuid, RetryEntry>>> = OnceLock::new();
+
+struct RetryEntry {
+    // The latest save that failed, taken out while it is being retried
+    save: Option<DeferredSave>,
+    org: String,
+    attempts: u32,
+    next_attempt: Instant,
+    failing_since: DateTime<Utc>,
+    last_error: String,
+    alerted: bool,
+}
+
+/// A document whose changes could not be persisted, as reported in the diagnostics
+#[derive(Debug, Clone)]
+pub struct FailingSave {
+    pub org: String,
+    pub doc_id: Uuid,
+    pub attempts: u32,
+    pub failing_since: DateTime<Utc>,
+    pub next_retry_in_ms: u64,
+    pub last_error: String,
+}
+
+fn get_queue() -> &'static Mutex<HashMap<Uuid, RetryEntry>> {
+    RETRY_QUEUE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// The delay before the next retry, doubling with every failed attempt
+fn backoff(attempts: u32) -> Duration {
+    let config = config::get_config();
+    let base = config.save_retry_base_ms.unwrap_or(1_000).max(1);
+    let max = config.save_retry_max_ms.unwrap_or(300_000).max(base);
+    Duration::from_millis(base.saturating_mul(1u64 << attempts.saturating_sub(1).min(20)).min(max))
+}
+
+// The age of unsaved changes after which an alert is raised
+fn alert_threshold() -> chrono::Duration {
+    let secs = config::get_config().save_alert_after_secs.unwrap_or(300);
+    chrono::Duration::seconds(secs as i64)
+}
+
+// Queue a failed save for a retry, replacing an older save of the same document
+pub fn schedule(doc_uuid: Uuid, save: DeferredSave, error: &str) {
+    let mut queue = get_queue().lock().unwrap();
+    let entry = queue.entry(doc_uuid).or_insert_with(|| RetryEntry {
+        save: None,
+        org: save.ctx.org.clone(),
+        attempts: 0,
+        next_attempt: Instant::now(),
+        failing_since: Utc::now(),
+        last_error: String::new(),
+        alerted: false,
+    });
+    entry.attempts += 1;
+    entry.next_attempt = Instant::now() + backoff(entry.attempts);
+    entry.last_error = error.to_string();
+    entry.save = Some(save);
+    warn!("Save of document {} failed {} time(s), retrying in {:?}: {}", doc_uuid, entry.attempts, backoff(entry.attempts), error);
+}
+
+// Forget the failures of a document once it is saved
+pub fn clear(doc_uuid: Uuid) {
+    if let Some(entry) = get_queue().lock().unwrap().remove(&doc_uuid) {
+        info!("Document {} saved again after {} failed attempt(s)", doc_uuid, entry.attempts);
+    }
+}
+
+// The documents with failing saves, oldest failure first
+pub fn failing_saves() -> Vec<FailingSave> {
+    let now = Instant::now();
+    let queue = get_queue().lock().unwrap();
+    let mut failing: Vec<FailingSave> = queue
+        .iter()
+        .map(|(doc_id, entry)| FailingSave {
+            org: entry.org.clone(),
+            doc_id: *doc_id,
+            attempts: entry.attempts,
+            failing_since: entry.failing_since,
+            next_retry_in_ms: entry.next_attempt.saturating_duration_since(now).as_millis() as u64,
+            last_error: entry.last_error.clone(),
+        })
+        .collect();
+    failing.sort_by_key(|save| save.failing_since);
+    failing
+}
+
+// The number of documents with unsaved changes older than the alert threshold
+pub fn stale_unsaved_count() -> usize {
+    let cutoff = Utc::now() - alert_threshold();
+    get_queue().lock().unwrap().values().filter(|entry| entry.failing_since <= cutoff).count()
+}
+
+// Take the retries that are due, and raise an alert for documents that stay unsaved too long
+fn take_due() -> Vec<DeferredSave> {
+    let now = Instant::now();
+    let cutoff = Utc::now() - alert_threshold();
+    let mut queue = get_queue().lock().unwrap();
+    for (doc_id, entry) in queue.iter_mut() {
+        if !entry.alerted && entry.failing_since <= cutoff {
+            entry.alerted = true;
+            error!(
+                "ALERT: document {} of organization '{}' has unsaved changes since {} after {} failed attempt(s). Last error: {}",
+                doc_id, entry.org, entry.failing_since, entry.attempts, entry.last_error
+            );
+        }
+    }
+    queue
+        .values_mut()
+        .filter(|entry| entry.next_attempt <= now)
+        .filter_map(|entry| entry.save.take())
+        .collect()
+}
+
+// Start retrying failed saves, `persist` queues the save again when it fails
+pub fn spawn<F, Fut>(persist: F)
+where
+    F: Fn(DeferredSave) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<(), String>> + Send + 'static,
+{
+    info!("Starting save retry queue, alerting after {}s of unsaved changes", alert_threshold().num_seconds());
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(RETRY_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for save in take_due() {
+                let room = save.room.clone();
+                if let Err(e) = persist(save).await {
+                    warn!("Retry of the save of document '{}' failed: {}", room, e);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,48 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use loro::VersionVector;
+use moka::sync::Cache;
+use uuid::Uuid;
+
+static SAVE_STATUS: OnceLock<Cache<Uuid, SaveStatus>> = OnceLock::new();
+
+/// The outcome of the latest saves of a document on this pod
+#[derive(Debug, Clone, Default)]
+pub struct SaveStatus {
+    pub last_saved_at: Option<DateTime<Utc>>,
+    pub last_saved_vv: Option<VersionVector>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+fn get_cache() -> &'static Cache<Uuid, SaveStatus> {
+    SAVE_STATUS.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(100_000)
+            .time_to_idle(Duration::from_secs(60 * 60))
+            .build()
+    })
+}
+
+pub fn get(doc_uuid: Uuid) -> SaveStatus {
+    get_cache().get(&doc_uuid).unwrap_or_default()
+}
+
+// Record a successful save, it clears an earlier error
+pub fn record_saved(doc_uuid: Uuid, vv: VersionVector) {
+    let mut status = get(doc_uuid);
+    status.last_saved_at = Some(Utc::now());
+    status.last_saved_vv = Some(vv);
+    status.last_error = None;
+    status.last_error_at = None;
+    get_cache().insert(doc_uuid, status);
+}
+
+// Record a failed save
+pub fn record_error(doc_uuid: Uuid, error: &str) {
+    let mut status = get(doc_uuid);
+    status.last_error = Some(error.to_string());
+    status.last_error_at = Some(Utc::now());
+    get_cache().insert(doc_uuid, status);
+}
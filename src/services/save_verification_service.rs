@@ -0,0 +1,82 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use loro::LoroDoc;
+use serde_json::Value;
+use tracing::error;
+use uuid::Uuid;
+use crate::config;
+use crate::services::json_projection_service;
+
+// Verification of the JSON written with a save.
+// The snapshot about to be stored is imported into a fresh document and its JSON is generated again,
+// the way a reader of the snapshot would. A JSON that differs from the one about to be written means
+// the app-facing representation no longer matches the document, e.g. a serialization bug. Divergences
+// are logged with the paths that differ and counted, the save itself goes ahead.
+
+// Most differing paths logged per divergence
+const MAX_LOGGED_PATHS: usize = 10;
+
+static VERIFIED_TOTAL: AtomicU64 = AtomicU64::new(0);
+static DIVERGENCES_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+pub fn is_enabled() -> bool {
+    config::get_config().save_verification.unwrap_or(false)
+}
+
+/// Saves verified by this instance
+pub fn verified_total() -> u64 {
+    VERIFIED_TOTAL.load(Ordering::Relaxed)
+}
+
+/// Saves whose JSON diverged from their snapshot on this instance
+pub fn divergences_total() -> u64 {
+    DIVERGENCES_TOTAL.load(Ordering::Relaxed)
+}
+
+/// Compare the JSON about to be written with the JSON generated from the snapshot about to be written.
+/// Returns false when they diverge.
+pub async fn verify(org_id: &str, doc_uuid: Uuid, snapshot: &[u8], json: &Value) -> bool {
+    VERIFIED_TOTAL.fetch_add(1, Ordering::Relaxed);
+    let reimported = LoroDoc::new();
+    if let Err(e) = reimported.import(snapshot) {
+        DIVERGENCES_TOTAL.fetch_add(1, Ordering::Relaxed);
+        error!("Save verification of document {} failed, its snapshot can't be imported: {}", doc_uuid, e);
+        return false;
+    }
+    let regenerated = json_projection_service::document_json(org_id, doc_uuid, &reimported).await;
+
+    let mut paths = Vec::new();
+    diff("", json, &regenerated, &mut paths);
+    if paths.is_empty() {
+        return true;
+    }
+    DIVERGENCES_TOTAL.fetch_add(1, Ordering::Relaxed);
+    let shown: Vec<&str> = paths.iter().take(MAX_LOGGED_PATHS).map(String::as_str).collect();
+    error!(
+        "Save verification of document {} in org {} failed, the JSON differs from its snapshot at {} paths: {}",
+        doc_uuid, org_id, paths.len(), shown.join(", ")
+    );
+    false
+}
+
+// Collect the paths where two JSON values differ
+fn diff(path: &str, written: &Value, regenerated: &Value, paths: &mut Vec<String>) {
+    match (written, regenerated) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                let child = format!("{}/{}", path, key);
+                match b.get(key) {
+                    Some(other) => diff(&child, value, other, paths),
+                    None => paths.push(child),
+                }
+            }
+            paths.extend(b.keys().filter(|key| !a.contains_key(*key)).map(|key| format!("{}/{}", path, key)));
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (index, (value, other)) in a.iter().zip(b).enumerate() {
+                diff(&format!("{}/{}", path, index), value, other, paths);
+            }
+        }
+        (a, b) if a == b => {}
+        _ => paths.push(if path.is_empty() { "/".to_string() } else { path.to_string() }),
+    }
+}
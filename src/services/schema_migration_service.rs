@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use chrono::Utc;
+use loro::{LoroDoc, LoroValue, ValueOrContainer};
+use loro_websocket_server::HubRegistry;
+use tracing::{error, info, warn};
+use crate::config;
+use crate::db::dbcolab;
+use crate::models::SchemaMigrationStatus;
+use crate::services::{doc_edit_service, doc_load_service, legacy_approvals_service};
+use crate::ws::docctx::DocContext;
+
+// Versions of the structure of documents and the migrations between them.
+// Every document carries the version of its structure as `properties.schemaVersion`, documents
+// created before it was introduced are version 1. A migration upgrades a document from one version to
+// the next, they run in order until the document is current. Documents are migrated lazily when their
+// room loads, or all documents of an org at once by a bulk job. Migrations only rewrite what is in
+// the old shape, running one on a migrated document changes nothing.
+
+pub const SCHEMA_VERSION_FIELD: &str = "schemaVersion";
+
+/// The version of the structure of new documents
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// An upgrade of documents from one schema version to the next
+pub struct Migration {
+    pub from: u32,
+    pub name: &'static str,
+    apply: fn(&LoroDoc) -> Result<(), String>,
+}
+
+const MIGRATIONS: [Migration; 1] = [
+    Migration { from: 1, name: "approvals-as-map", apply: approvals_as_map },
+];
+
+/// The schema version of a document, 1 when it has none
+pub fn schema_version(doc: &LoroDoc) -> u32 {
+    match doc.get_map("properties").get(SCHEMA_VERSION_FIELD) {
+        Some(ValueOrContainer::Value(LoroValue::I64(version))) => version.max(1) as u32,
+        Some(ValueOrContainer::Value(LoroValue::Double(version))) => (version as u32).max(1),
+        _ => 1,
+    }
+}
+
+pub fn needs_migration(doc: &LoroDoc) -> bool {
+    schema_version(doc) < CURRENT_SCHEMA_VERSION
+}
+
+/// Run the pending migrations of a document, returns the names of the migrations that ran.
+/// The caller commits the changes.
+pub fn migrate(doc: &LoroDoc) -> Result<Vec<&'static str>, String> {
+    let mut applied = Vec::new();
+    let mut version = schema_version(doc);
+    while version < CURRENT_SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or_else(|| format!("No migration from schema version {}", version))?;
+        (migration.apply)(doc).map_err(|e| format!("Migration '{}' failed: {}", migration.name, e))?;
+        version += 1;
+        doc.get_map("properties")
+            .insert(SCHEMA_VERSION_FIELD, version as i64)
+            .map_err(|e| format!("Failed to set the schema version: {}", e))?;
+        applied.push(migration.name);
+    }
+    Ok(applied)
+}
+
+// Migrate a document that is loading into a room.
+// Returns the migrated snapshot, its changes are attributed to the service and still need to be saved.
+pub fn migrate_snapshot(snapshot: &[u8], ctx: &mut DocContext) -> Option<Vec<u8>> {
+    let doc = LoroDoc::new();
+    if let Err(e) = doc.import(snapshot) {
+        error!("Failed to import snapshot of document {} for migration: {}", ctx.doc_id, e);
+        return None;
+    }
+    if !needs_migration(&doc) {
+        return None;
+    }
+    let from = schema_version(&doc);
+    match doc_edit_service::edit_snapshot(snapshot, ctx, |doc| migrate(doc).map(|_| ())) {
+        Ok(migrated) => {
+            info!("Migrated document {} from schema version {} to {}", ctx.doc_id, from, CURRENT_SCHEMA_VERSION);
+            migrated
+        }
+        Err(e) => {
+            error!("Failed to migrate document {}: {}", ctx.doc_id, e);
+            None
+        }
+    }
+}
+
+// Version 1 to 2: approvals of blocks and languages are a map keyed by the approving user or group
+fn approvals_as_map(doc: &LoroDoc) -> Result<(), String> {
+    legacy_approvals_service::normalize(doc).map(|_| ())
+}
+
+// Progress of the bulk migrations per org
+static PROGRESS: OnceLock<Mutex<HashMap<String, SchemaMigrationStatus>>> = OnceLock::new();
+
+fn get_progress() -> &'static Mutex<HashMap<String, SchemaMigrationStatus>> {
+    PROGRESS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn update_progress(org_id: &str, update: impl FnOnce(&mut SchemaMigrationStatus)) {
+    if let Some(status) = get_progress().lock().unwrap().get_mut(org_id) {
+        update(status);
+    }
+}
+
+/// The progress of the latest bulk migration of an org
+pub fn status(org_id: &str) -> Option<SchemaMigrationStatus> {
+    get_progress().lock().unwrap().get(org_id).cloned()
+}
+
+/// Start migrating every document of an org, false when a migration of the org is already running
+pub fn start(registry: Arc<HubRegistry<DocContext>>, org_id: &str) -> bool {
+    {
+        let mut progress = get_progress().lock().unwrap();
+        if progress.get(org_id).is_some_and(|status| status.state == "running") {
+            return false;
+        }
+        progress.insert(org_id.to_string(), SchemaMigrationStatus {
+            org_id: org_id.to_string(),
+            state: "running".to_string(),
+            target_version: CURRENT_SCHEMA_VERSION,
+            started_at: Utc::now(),
+            finished_at: None,
+            documents_total: 0,
+            documents_checked: 0,
+            documents_migrated: 0,
+            errors: Vec::new(),
+        });
+    }
+
+    let org_id = org_id.to_string();
+    tokio::spawn(async move {
+        let state = match run(&registry, &org_id).await {
+            Ok(()) => "done",
+            Err(e) => {
+                error!("Schema migration of org '{}' failed: {}", org_id, e);
+                update_progress(&org_id, |status| status.errors.push(e));
+                "failed"
+            }
+        };
+        update_progress(&org_id, |status| {
+            status.state = state.to_string();
+            status.finished_at = Some(Utc::now());
+        });
+    });
+    true
+}
+
+async fn run(registry: &Arc<HubRegistry<DocContext>>, org_id: &str) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let documents = db.get_org_documents(org_id, None)
+        .await
+        .map_err(|e| format!("Failed to list the documents: {}", e))?;
+    update_progress(org_id, |status| status.documents_total = documents.len());
+    let pause = Duration::from_millis(config::get_config().bulk_replace_pause_ms.unwrap_or(250));
+
+    for document in documents {
+        let doc_id = document.id.to_string();
+        let pending = match doc_load_service::load_loro_doc(registry, org_id, &doc_id).await {
+            Ok(Some((doc, _))) => needs_migration(&doc),
+            Ok(None) => false,
+            Err(e) => {
+                warn!("Failed to load document {} for migration: {}", doc_id, e);
+                update_progress(org_id, |status| status.errors.push(format!("{}: {}", doc_id, e)));
+                false
+            }
+        };
+        if pending {
+            // Loading the room migrates the document, the edit migrates it when the room was already open
+            let migrated = doc_edit_service::edit_doc(registry.clone(), org_id, &doc_id, |doc| {
+                migrate(doc)?;
+                doc.commit();
+                Ok(())
+            }, false).await;
+            match migrated {
+                Ok(()) => update_progress(org_id, |status| status.documents_migrated += 1),
+                Err(e) => {
+                    warn!("Failed to migrate document {}: {}", doc_id, e);
+                    update_progress(org_id, |status| status.errors.push(format!("{}: {}", doc_id, e)));
+                }
+            }
+            tokio::time::sleep(pause).await;
+        }
+        update_progress(org_id, |status| status.documents_checked += 1);
+    }
+    info!("Schema migration of org '{}' finished", org_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loro::{LoroList, LoroMap, ToJson};
+    use serde_json::json;
+
+    // A statement of schema version 1, its English version holds two approvals as a list
+    fn v1_statement() -> LoroDoc {
+        let doc = LoroDoc::new();
+        doc.get_map("properties").insert("type", "colab-statement").unwrap();
+        let en = doc.get_map("content").insert_container("en", LoroMap::new()).unwrap();
+        en.insert("text", "Keep out of reach of children").unwrap();
+        let approvals = en.insert_container("approvals", LoroList::new()).unwrap();
+        for (user, state) in [("u/alice", "approved"), ("u/bob", "rejected")] {
+            let approval = approvals.insert_container(approvals.len(), LoroMap::new()).unwrap();
+            approval.insert("user", user).unwrap();
+            approval.insert("state", state).unwrap();
+        }
+        doc.commit();
+        doc
+    }
+
+    fn json_of(doc: &LoroDoc) -> serde_json::Value {
+        doc.get_deep_value().to_json_value()
+    }
+
+    #[test]
+    fn migrate_turns_approval_lists_into_maps() {
+        let doc = v1_statement();
+        let applied = migrate(&doc).unwrap();
+        doc.commit();
+
+        assert_eq!(applied, vec!["approvals-as-map"]);
+        let json = json_of(&doc);
+        assert_eq!(json["content"]["en"]["approvals"], json!({
+            "u/alice": { "user": "u/alice", "state": "approved" },
+            "u/bob": { "user": "u/bob", "state": "rejected" },
+        }));
+        assert_eq!(json["content"]["en"]["text"], "Keep out of reach of children");
+    }
+
+    #[test]
+    fn migrate_twice_changes_nothing() {
+        let doc = v1_statement();
+        migrate(&doc).unwrap();
+        doc.commit();
+        let (vv, json) = (doc.oplog_vv(), json_of(&doc));
+
+        assert!(migrate(&doc).unwrap().is_empty());
+        doc.commit();
+        assert_eq!(doc.oplog_vv(), vv);
+        assert_eq!(json_of(&doc), json);
+    }
+
+    #[test]
+    fn migrate_sets_the_schema_version() {
+        let doc = v1_statement();
+        assert!(needs_migration(&doc));
+        migrate(&doc).unwrap();
+        doc.commit();
+
+        assert!(!needs_migration(&doc));
+        assert_eq!(json_of(&doc)["properties"][SCHEMA_VERSION_FIELD], json!(CURRENT_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn schema_version_of_a_v1_snapshot() {
+        let snapshot = v1_statement().export(loro::ExportMode::Snapshot).unwrap();
+        let doc = LoroDoc::new();
+        doc.import(&snapshot).unwrap();
+        assert_eq!(schema_version(&doc), 1);
+    }
+
+    #[test]
+    fn schema_version_of_a_v2_snapshot() {
+        let migrated = v1_statement();
+        migrate(&migrated).unwrap();
+        migrated.commit();
+        let snapshot = migrated.export(loro::ExportMode::Snapshot).unwrap();
+        let doc = LoroDoc::new();
+        doc.import(&snapshot).unwrap();
+        assert_eq!(schema_version(&doc), 2);
+    }
+}
@@ -0,0 +1,50 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+// Writing direction and counting of texts in any script.
+// Lengths are counted in grapheme clusters, what a reader sees as one character: a letter with its
+// combining marks, an Arabic letter with its harakat, a Hangul syllable or an emoji sequence. Cutting a
+// text between the code points of a cluster leaves a dangling mark or a broken emoji. Statements in
+// right-to-left languages are rendered with their direction, other texts take the direction of their
+// first strong character.
+
+// Languages written right to left, by their ISO 639 code
+const RTL_LANGUAGES: [&str; 12] = ["ar", "arc", "ckb", "dv", "fa", "he", "iw", "ks", "ps", "sd", "ug", "ur"];
+
+// Values of a direction attribute in a text element
+const DIRECTIONS: [&str; 3] = ["ltr", "rtl", "auto"];
+
+/// The direction of a text in a language: "rtl" for right-to-left languages, "auto" for the others
+pub fn direction(lang_code: &str) -> &'static str {
+    let mut subtags = lang_code.split(['-', '_']);
+    let language = subtags.next().unwrap_or_default().to_lowercase();
+    // A script subtag overrides the script customary for the language, e.g. "az-Arab" or "ar-Latn"
+    match subtags.find(|subtag| subtag.len() == 4).map(str::to_lowercase).as_deref() {
+        Some("arab" | "hebr" | "syrc" | "thaa" | "nkoo" | "adlm") => "rtl",
+        Some(_) => "auto",
+        None if RTL_LANGUAGES.contains(&language.as_str()) => "rtl",
+        None => "auto",
+    }
+}
+
+/// The direction set on a node of a text element, if it is a valid one
+pub fn direction_attribute(value: &str) -> Option<&'static str> {
+    DIRECTIONS.iter().copied().find(|direction| value.eq_ignore_ascii_case(direction))
+}
+
+/// The length of a text as a reader counts it
+pub fn grapheme_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// The first graphemes of a text
+pub fn take_graphemes(text: &str, count: usize) -> &str {
+    match text.grapheme_indices(true).nth(count) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+/// The graphemes of a text, to lay it out
+pub fn graphemes(text: &str) -> Vec<&str> {
+    text.graphemes(true).collect()
+}
@@ -0,0 +1,183 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use uuid::Uuid;
+use crate::clients::app_service_client::AppServiceClient;
+use crate::config::{self, Config};
+use crate::db::dbcolab::{self, DbColab};
+use crate::services::{auth_service, cold_storage_service};
+
+// Startup self-test, run with `--selftest` instead of serving.
+// Exercises the critical paths against the configured dependencies: the database with its policy
+// context, a service token minted and validated with the configured secret, a call to the app
+// service with it, a write and read of the cold tier and of the update journal. Checks of optional
+// dependencies that aren't configured are skipped. The report is printed as JSON and the process
+// exits with 1 when a check failed, so deployment smoke tests can gate on it.
+
+// Budget of every check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+// Org of the policy context check, it doesn't need to exist
+const SELFTEST_ORG: &str = "selftest";
+const DEFAULT_SELFTEST_UID: &str = "colabri-doc-selftest";
+
+/// Outcome of a single check
+#[derive(Serialize)]
+pub struct SelftestCheck {
+    pub name: String,
+    // pass, fail or skip
+    pub status: String,
+    pub detail: String,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u128,
+}
+
+/// Report of the self-test
+#[derive(Serialize)]
+pub struct SelftestReport {
+    pub ok: bool,
+    pub checks: Vec<SelftestCheck>,
+}
+
+// Run a check within the budget
+async fn check<F>(checks: &mut Vec<SelftestCheck>, name: &str, run: F) -> bool
+where
+    F: Future<Output = Result<String, String>>,
+{
+    let started = Instant::now();
+    let (status, detail) = match tokio::time::timeout(CHECK_TIMEOUT, run).await {
+        Ok(Ok(detail)) => ("pass", detail),
+        Ok(Err(e)) => ("fail", e),
+        Err(_) => ("fail", format!("Timed out after {} s", CHECK_TIMEOUT.as_secs())),
+    };
+    checks.push(SelftestCheck {
+        name: name.to_string(),
+        status: status.to_string(),
+        detail,
+        duration_ms: started.elapsed().as_millis(),
+    });
+    status == "pass"
+}
+
+fn skip(checks: &mut Vec<SelftestCheck>, name: &str, detail: &str) {
+    checks.push(SelftestCheck {
+        name: name.to_string(),
+        status: "skip".to_string(),
+        detail: detail.to_string(),
+        duration_ms: 0,
+    });
+}
+
+async fn check_db(checks: &mut Vec<SelftestCheck>, config: &Config) {
+    let db_url = match &config.db_url {
+        Some(db_url) => db_url,
+        None => {
+            check(checks, "db.connect", async { Err("DB_URL is not configured".to_string()) }).await;
+            return;
+        }
+    };
+    let connected = check(checks, "db.connect", async {
+        dbcolab::init_db(db_url).await.map_err(|e| e.to_string())?;
+        let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+        db.ping().await.map_err(|e| e.to_string())?;
+        let (idle, total) = db.pool_state();
+        Ok(format!("Connected, {} idle of {} connections", idle, total))
+    }).await;
+    if !connected {
+        skip(checks, "db.policy", "The database isn't reachable");
+        return;
+    }
+    check(checks, "db.policy", async {
+        let db: std::sync::Arc<DbColab> = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+        let orgs = db.check_policy_context(SELFTEST_ORG).await.map_err(|e| e.to_string())?;
+        if orgs != SELFTEST_ORG {
+            return Err(format!("The policy context reads back as '{}' instead of '{}'", orgs, SELFTEST_ORG));
+        }
+        Ok("SET LOCAL app.orgs applied".to_string())
+    }).await;
+}
+
+async fn check_auth(checks: &mut Vec<SelftestCheck>, config: &Config) {
+    let secret = match &config.cloud_auth_jwt_secret {
+        Some(secret) => secret.clone(),
+        None => {
+            check(checks, "jwt.validate", async { Err("CLOUD_AUTH_JWT_SECRET is not configured".to_string()) }).await;
+            skip(checks, "app_service.prpls", "No secret to mint a service token with");
+            return;
+        }
+    };
+    let client = AppServiceClient::new(config.app_service_url(), secret.clone(), config.cloud_service_name.clone());
+    check(checks, "jwt.validate", async {
+        let token = client.generate_token();
+        let token_data = auth_service::validate_jwt(&token, &secret).map_err(|e| format!("A minted service token doesn't validate: {}", e))?;
+        match token_data.claims.get("type").and_then(|v| v.as_str()) {
+            Some("service") => Ok("Minted and validated a service token".to_string()),
+            other => Err(format!("The minted token has type {:?} instead of 'service'", other)),
+        }
+    }).await;
+    let uid = config.selftest_user_uid.clone().unwrap_or_else(|| DEFAULT_SELFTEST_UID.to_string());
+    check(checks, "app_service.prpls", async {
+        let status = client.probe_prpls(&uid).await.map_err(|e| format!("Failed to reach {}: {}", config.app_service_url(), e))?;
+        // An unknown test user still proves the service is reachable and accepts the token
+        if status.is_success() || (status == reqwest::StatusCode::NOT_FOUND && config.selftest_user_uid.is_none()) {
+            Ok(format!("The app service answered {} for '{}'", status, uid))
+        } else {
+            Err(format!("The app service answered {} for '{}'", status, uid))
+        }
+    }).await;
+}
+
+async fn check_blob_store(checks: &mut Vec<SelftestCheck>, config: &Config) {
+    match config.selftest_blob_pointer.as_deref().filter(|pointer| !pointer.is_empty()) {
+        Some(pointer) => {
+            let pointer = format!("{}/{}", pointer.trim_end_matches('/'), Uuid::new_v4());
+            check(checks, "blob_store.write_read", async {
+                let content = Uuid::new_v4().to_string().into_bytes();
+                cold_storage_service::store(&pointer, content.clone()).await?;
+                let read = cold_storage_service::fetch(&pointer).await;
+                let deleted = cold_storage_service::delete(&pointer).await;
+                if read? != content {
+                    return Err(format!("'{}' reads back different content", pointer));
+                }
+                deleted?;
+                Ok(format!("Wrote, read and deleted '{}'", pointer))
+            }).await;
+        }
+        None => skip(checks, "blob_store.write_read", "SELFTEST_BLOB_POINTER is not configured"),
+    }
+    match config.update_journal_dir.as_deref().filter(|dir| !dir.is_empty()) {
+        Some(dir) => {
+            let path = std::path::Path::new(dir).join(format!(".selftest-{}", Uuid::new_v4()));
+            check(checks, "journal.write_read", async {
+                let content = Uuid::new_v4().to_string().into_bytes();
+                tokio::fs::write(&path, &content).await.map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                let read = tokio::fs::read(&path).await;
+                let _ = tokio::fs::remove_file(&path).await;
+                if read.map_err(|e| format!("Failed to read {}: {}", path.display(), e))? != content {
+                    return Err(format!("{} reads back different content", path.display()));
+                }
+                Ok(format!("Wrote and read {}", path.display()))
+            }).await;
+        }
+        None => skip(checks, "journal.write_read", "UPDATE_JOURNAL_DIR is not configured"),
+    }
+}
+
+// Run every check against the global configuration, `config_loaded` tells whether it came from the environment
+pub async fn run(config_loaded: bool) -> SelftestReport {
+    let config = config::get_config();
+    let mut checks = Vec::new();
+    check(&mut checks, "config.load", async {
+        if config_loaded {
+            Ok(format!("Loaded for environment '{}'", config.environment))
+        } else {
+            Err("The configuration failed to load, the defaults are in use".to_string())
+        }
+    }).await;
+    check_db(&mut checks, config).await;
+    check_auth(&mut checks, config).await;
+    check_blob_store(&mut checks, config).await;
+    SelftestReport {
+        ok: checks.iter().all(|check| check.status != "fail"),
+        checks,
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use loro::{LoroDoc, LoroMap, ToJson};
+use serde_json::Value;
+use crate::models::lorodoc::{colab_sheet_block_to_loro_map, get_doc_type};
+use crate::models::{
+    AttributeValue, ColabModelProperties, ColabModelType, ColabSheetAttributesBlock, ColabSheetBlock,
+    ColabSheetStatementGridBlock, ColabSheetStatementGridRow, ColabStatementElement, ColabStatementModel,
+    CsvColumnMapping, TextElement, TextElementChild, TextElementChildrenOrString,
+};
+use crate::services::limits_service::{self, DocumentLimits};
+
+/// A sheet block built from a CSV
+pub struct ImportedBlock {
+    pub block: ColabSheetBlock,
+    pub rows: usize,
+    pub skipped: usize,
+}
+
+// Build a statement-grid or attributes block from the rows of a CSV, the first row is the header
+pub fn build_block(block_type: &str, title: &str, rows: &[Vec<String>], mapping: &CsvColumnMapping) -> Result<ImportedBlock, String> {
+    let (header, records) = rows.split_first().ok_or_else(|| "The CSV is empty".to_string())?;
+    let column = |name: &str| -> Result<usize, String> {
+        header.iter().position(|h| h.trim() == name).ok_or_else(|| format!("Column '{}' not found in the CSV header", name))
+    };
+    let cell = |record: &Vec<String>, idx: usize| record.get(idx).map(|s| s.trim().to_string()).unwrap_or_default();
+
+    match block_type {
+        "statement-grid" => {
+            if mapping.languages.is_empty() {
+                return Err("The mapping needs at least one language column".to_string());
+            }
+            let mut languages: Vec<(String, usize)> = Vec::new();
+            for (lang_code, name) in &mapping.languages {
+                languages.push((lang_code.clone(), column(name)?));
+            }
+            languages.sort();
+
+            let mut grid_rows = Vec::new();
+            let mut skipped = 0;
+            for record in records {
+                let content: HashMap<String, ColabStatementElement> = languages
+                    .iter()
+                    .filter_map(|(lang_code, idx)| {
+                        let text = cell(record, *idx);
+                        (!text.is_empty()).then(|| (lang_code.clone(), statement_element(&text)))
+                    })
+                    .collect();
+                if content.is_empty() {
+                    skipped += 1;
+                    continue;
+                }
+                let lang_codes: Vec<String> = languages.iter().map(|(lang_code, _)| lang_code.clone()).filter(|l| content.contains_key(l)).collect();
+                grid_rows.push(ColabSheetStatementGridRow {
+                    r#type: "local".to_string(),
+                    statement_ref: None,
+                    statement: Some(ColabStatementModel {
+                        properties: ColabModelProperties {
+                            r#type: ColabModelType::ColabStatement,
+                            content_type: mapping.content_type.clone().unwrap_or_else(|| "claim".to_string()),
+                            master_lang_code: None,
+                            country_codes: None,
+                            lang_codes: Some(lang_codes),
+                            workflow_state: None,
+                        },
+                        acls: HashMap::new(),
+                        content,
+                    }),
+                });
+            }
+            let count = grid_rows.len();
+            Ok(ImportedBlock {
+                block: ColabSheetBlock::StatementGrid(ColabSheetStatementGridBlock {
+                    title: text_element(title),
+                    acls: HashMap::new(),
+                    rows: grid_rows,
+                }),
+                rows: count,
+                skipped,
+            })
+        }
+        "attributes" => {
+            let key_idx = column(mapping.key_column.as_deref().ok_or_else(|| "The mapping needs a keyColumn".to_string())?)?;
+            let value_idx = column(mapping.value_column.as_deref().ok_or_else(|| "The mapping needs a valueColumn".to_string())?)?;
+            let display_idx = mapping.display_column.as_deref().map(column).transpose()?;
+
+            let mut attributes = HashMap::new();
+            let mut skipped = 0;
+            for record in records {
+                let key = cell(record, key_idx);
+                if key.is_empty() {
+                    skipped += 1;
+                    continue;
+                }
+                let value = cell(record, value_idx);
+                let display = display_idx.map(|idx| cell(record, idx)).unwrap_or_else(|| value.clone());
+                attributes.insert(key, AttributeValue { display, value: Value::String(value) });
+            }
+            let count = attributes.len();
+            Ok(ImportedBlock {
+                block: ColabSheetBlock::Attributes(ColabSheetAttributesBlock {
+                    title: text_element(title),
+                    attributes,
+                    acls: HashMap::new(),
+                }),
+                rows: count,
+                skipped,
+            })
+        }
+        other => Err(format!("Unsupported block type '{}', use statement-grid or attributes", other)),
+    }
+}
+
+// Insert a block into a sheet under the given id, refusing sheets that would exceed their limits
+pub fn insert_block(doc: &LoroDoc, block: &ColabSheetBlock, block_id: &str, position: Option<usize>, limits: &DocumentLimits) -> Result<(), String> {
+    if get_doc_type(doc).as_deref() != Some("colab-sheet") {
+        return Err("Blocks can only be imported into sheets".to_string());
+    }
+    let content = doc.get_movable_list("content");
+    let position = position.unwrap_or(content.len()).min(content.len());
+
+    let block_map: LoroMap = colab_sheet_block_to_loro_map(block);
+    block_map.insert("id", block_id).map_err(|e| format!("Failed to set the block id: {}", e))?;
+    content.insert_container(position, block_map).map_err(|e| format!("Failed to insert the block: {}", e))?;
+
+    limits_service::check_json(&doc.get_deep_value().to_json_value(), limits).map_err(|violation| violation.to_string())
+}
+
+// A plain text as a single paragraph
+fn text_element(text: &str) -> TextElement {
+    TextElement {
+        node_name: "doc".to_string(),
+        attributes: HashMap::new(),
+        children: TextElementChildrenOrString::AsChildren(vec![TextElementChild {
+            node_name: "paragraph".to_string(),
+            attributes: HashMap::new(),
+            children: TextElementChildrenOrString::AsStringArray(vec![text.to_string()]),
+        }]),
+    }
+}
+
+fn statement_element(text: &str) -> ColabStatementElement {
+    ColabStatementElement {
+        text_element: text_element(text),
+        acls: HashMap::new(),
+        comments: Vec::new(),
+        approvals: HashMap::new(),
+    }
+}
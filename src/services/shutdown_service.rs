@@ -0,0 +1,82 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use loro_websocket_server::HubRegistry;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use crate::config;
+use crate::services::drain_service;
+use crate::ws::docctx::DocContext;
+
+// Graceful shutdown on SIGTERM or SIGINT.
+// The WebSocket listener stops accepting connections, then the pod drains as through the admin
+// endpoint: deferred saves are persisted and dirty documents saved. Only then the HTTP server stops
+// and the process exits, so a deploy doesn't lose the edits since the last save. A drain that takes
+// longer than the timeout is given up, the orchestrator kills the pod after its grace period anyway.
+
+static FLUSHED: OnceLock<Notify> = OnceLock::new();
+
+fn get_flushed() -> &'static Notify {
+    FLUSHED.get_or_init(Notify::new)
+}
+
+fn timeout() -> Duration {
+    Duration::from_millis(config::get_config().shutdown_timeout_ms.unwrap_or(25 * 1000))
+}
+
+// Start waiting for the signals to shut down
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>, ws_server: JoinHandle<()>) {
+    tokio::spawn(async move {
+        let signal = wait_for_signal().await;
+        info!("Received {}, shutting down", signal);
+
+        // 1. Stop accepting WebSocket connections, open connections keep their rooms until saved
+        ws_server.abort();
+
+        // 2. Flush the dirty documents, a drain started through the admin endpoint is awaited
+        drain_service::start(registry);
+        if drain_service::wait_drained(timeout()).await {
+            let progress = drain_service::progress();
+            info!("Flushed {} of {} rooms before shutting down, {} errors", progress.rooms_saved, progress.rooms_total, progress.errors.len());
+        } else {
+            warn!("Shutting down before all documents were flushed, the drain took longer than {:?}", timeout());
+        }
+
+        // 3. Let the HTTP server stop
+        get_flushed().notify_one();
+    });
+}
+
+/// Resolves once the documents were flushed after a shutdown signal, for the graceful shutdown of the HTTP server
+pub async fn flushed() {
+    get_flushed().notified().await;
+}
+
+async fn wait_for_signal() -> &'static str {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => "SIGINT",
+        _ = terminate => "SIGTERM",
+    }
+}
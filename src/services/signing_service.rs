@@ -0,0 +1,139 @@
+use std::sync::{Arc, OnceLock};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use loro::{ExportMode, LoroDoc, ToJson};
+use loro_websocket_server::HubRegistry;
+use sqlx::types::Json;
+use tracing::{error, info};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab::{self, DocumentSignatureRow};
+use crate::services::doc_load_service;
+use crate::services::evidence_service::sha256_hex;
+use crate::ws::docctx::DocContext;
+
+pub const SIGNATURE_ALGORITHM: &str = "Ed25519";
+
+static SIGNING_KEY: OnceLock<Option<SigningKey>> = OnceLock::new();
+
+// The publish signing key from the configuration, parsed once
+fn get_signing_key() -> Result<&'static SigningKey, String> {
+    SIGNING_KEY
+        .get_or_init(|| {
+            let encoded = config::get_config().publish_signing_key.as_ref()?;
+            let seed = match general_purpose::STANDARD.decode(encoded.trim()) {
+                Ok(seed) => seed,
+                Err(e) => {
+                    error!("Invalid publish signing key: {}", e);
+                    return None;
+                }
+            };
+            match <[u8; 32]>::try_from(seed.as_slice()) {
+                Ok(seed) => Some(SigningKey::from_bytes(&seed)),
+                Err(_) => {
+                    error!("Invalid publish signing key: expected 32 bytes, got {}", seed.len());
+                    None
+                }
+            }
+        })
+        .as_ref()
+        .ok_or_else(|| "No valid publish signing key configured".to_string())
+}
+
+fn key_id(verifying_key: &VerifyingKey) -> String {
+    match &config::get_config().publish_signing_key_id {
+        Some(id) => id.clone(),
+        None => sha256_hex(verifying_key.as_bytes())[..16].to_string(),
+    }
+}
+
+// The message that gets signed. It binds the hashes of the exported snapshot and JSON to the document and version.
+pub fn signed_message(org_id: &str, doc_id: &Uuid, version: i32, snapshot_sha256: &str, json_sha256: &str) -> String {
+    format!("colabri-doc/published/v1\n{}\n{}\n{}\n{}\n{}\n", org_id, doc_id, version, snapshot_sha256, json_sha256)
+}
+
+// The SHA-256 of the JSON of a document, keys are serialized in sorted order
+pub fn json_sha256(json: &serde_json::Value) -> Result<String, String> {
+    serde_json::to_vec(json)
+        .map(|bytes| sha256_hex(&bytes))
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))
+}
+
+// Sign the exported snapshot and JSON of a document version
+pub fn sign_doc(org_id: &str, doc_uuid: Uuid, doc: &LoroDoc, ctx: &DocContext, by_prpl: &str) -> Result<DocumentSignatureRow, String> {
+    let signing_key = get_signing_key()?;
+    let verifying_key = signing_key.verifying_key();
+
+    // 1. Hash the snapshot and the JSON
+    let snapshot = doc.export(ExportMode::Snapshot)
+        .map_err(|e| format!("Failed to export snapshot: {}", e))?;
+    let snapshot_sha256 = sha256_hex(&snapshot);
+    let json_sha256 = json_sha256(&doc.get_deep_value().to_json_value())?;
+
+    // 2. Sign the hashes
+    let version = ctx.doc_version as i32;
+    let message = signed_message(org_id, &doc_uuid, version, &snapshot_sha256, &json_sha256);
+    let signature = signing_key.sign(message.as_bytes());
+
+    let version_v = serde_json::to_value(doc.oplog_vv())
+        .map_err(|e| format!("Failed to serialize version vector: {}", e))?;
+    Ok(DocumentSignatureRow {
+        id: Uuid::nil(),
+        org: org_id.to_string(),
+        document: doc_uuid,
+        version,
+        version_v: Json(version_v),
+        snapshot_sha256,
+        json_sha256,
+        algorithm: SIGNATURE_ALGORITHM.to_string(),
+        key_id: key_id(&verifying_key),
+        public_key: general_purpose::STANDARD.encode(verifying_key.as_bytes()),
+        signature: general_purpose::STANDARD.encode(signature.to_bytes()),
+        created_at: Utc::now(),
+        created_by: by_prpl.to_string(),
+    })
+}
+
+// Sign the latest state of a document that just got published and store the signature
+pub async fn sign_publication(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, by_prpl: &str) -> Result<DocumentSignatureRow, String> {
+    let doc_id = doc_uuid.to_string();
+    let (doc, ctx) = doc_load_service::load_loro_doc(registry, org_id, &doc_id)
+        .await?
+        .ok_or_else(|| format!("Document '{}' not found in organization '{}'", doc_id, org_id))?;
+
+    let mut row = sign_doc(org_id, doc_uuid, &doc, &ctx, by_prpl)?;
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    row.id = db.insert_document_signature(&row)
+        .await
+        .map_err(|e| format!("Failed to store signature of document '{}': {}", doc_id, e))?;
+    info!("Signed published document '{}' version {} with key '{}'", doc_id, row.version, row.key_id);
+    Ok(row)
+}
+
+// Verify a detached Ed25519 signature over a message, all binary values are base64 encoded
+pub fn verify_signature(message: &str, signature: &str, public_key: &str) -> Result<bool, String> {
+    let public_key = general_purpose::STANDARD.decode(public_key)
+        .map_err(|e| format!("Invalid public key: {}", e))?;
+    let public_key = <[u8; 32]>::try_from(public_key.as_slice())
+        .map_err(|_| "Invalid public key: expected 32 bytes".to_string())?;
+    let verifying_key = VerifyingKey::from_bytes(&public_key)
+        .map_err(|e| format!("Invalid public key: {}", e))?;
+    let signature = general_purpose::STANDARD.decode(signature)
+        .map_err(|e| format!("Invalid signature: {}", e))?;
+    let signature = Signature::from_slice(&signature)
+        .map_err(|e| format!("Invalid signature: {}", e))?;
+    Ok(verifying_key.verify(message.as_bytes(), &signature).is_ok())
+}
+
+// Verify a stored signature: the signature must match its own hashes, and the given content must match the hashes
+pub fn verify_row(row: &DocumentSignatureRow, snapshot: Option<&[u8]>, json: Option<&serde_json::Value>) -> Result<(bool, Option<bool>, Option<bool>), String> {
+    let message = signed_message(&row.org, &row.document, row.version, &row.snapshot_sha256, &row.json_sha256);
+    let signature_valid = verify_signature(&message, &row.signature, &row.public_key)?;
+    let snapshot_matches = snapshot.map(|snapshot| sha256_hex(snapshot) == row.snapshot_sha256);
+    let json_matches = match json {
+        Some(json) => Some(json_sha256(json)? == row.json_sha256),
+        None => None,
+    };
+    Ok((signature_valid, snapshot_matches, json_matches))
+}
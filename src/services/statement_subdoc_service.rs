@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use loro::{ExportMode, LoroDoc, ToJson};
+use loro_websocket_server::HubRegistry;
+use serde_json::Value;
+use tracing::{info, warn};
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::models::{ColabPackage, ColabStatementModel};
+use crate::models::lorodoc::{get_block_id, get_child_map, get_child_movable_list, get_doc_type, get_list_map, get_string, stmt_to_loro_doc};
+use crate::services::{doc_edit_service, doc_load_service, lazy_block_service, quarantine_service, room_assignment_service};
+use crate::ws::docctx::DocContext;
+
+// Statements of sheets stored as linked subdocuments.
+// A linked row of a statement grid holds `subdocId` instead of an embedded statement. The subdocument
+// has the structure of a colab-statement and is synced in its own room `<sheet>~stmt-<id>`, so edits,
+// ACLs and approvals of the statement don't contend with the rest of the sheet. The JSON of the sheet
+// is composed with the statements of its subdocuments when it is stored or exported.
+
+const ROOM_PREFIX: &str = "stmt-";
+pub const LINKED_ROW: &str = "linked";
+
+/// A local statement that was moved into a subdocument
+pub struct ExtractedStatement {
+    pub block_id: String,
+    pub row: usize,
+    pub subdoc_id: Uuid,
+}
+
+/// Room that syncs a statement subdocument of a sheet
+pub fn subdoc_room(sheet_id: &str, subdoc_id: Uuid) -> String {
+    lazy_block_service::block_room(sheet_id, &format!("{}{}", ROOM_PREFIX, subdoc_id))
+}
+
+/// The subdocument synced by a block room, None for the bodies of lazy blocks
+pub fn parse_block(block_id: &str) -> Option<Uuid> {
+    block_id.strip_prefix(ROOM_PREFIX).and_then(|id| Uuid::parse_str(id).ok())
+}
+
+// Load a statement subdocument as the snapshot of its room, with the context of the sheet
+pub async fn load(org_id: &str, sheet_id: &str, subdoc_id: Uuid) -> Result<Option<(Vec<u8>, DocContext)>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let row = match db.get_statement_subdocs(org_id, &[subdoc_id]).await
+        .map_err(|e| format!("Failed to load statement subdocument '{}': {}", subdoc_id, e))?
+        .into_iter()
+        .next() {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    if row.sheet.to_string() != sheet_id {
+        return Err(format!("Statement subdocument '{}' doesn't belong to document '{}'", subdoc_id, sheet_id));
+    }
+    let (_, mut ctx) = match quarantine_service::fetch_doc_snapshot(org_id, sheet_id, None).await? {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let package: ColabPackage = serde_cbor::from_slice(&row.package)
+        .map_err(|e| format!("Failed to decode statement subdocument '{}': {}", subdoc_id, e))?;
+    ctx.peer_map = package.peer_map;
+    ctx.last_updating_peer = None;
+    ctx.state_only = false;
+    ctx.block_id = Some(format!("{}{}", ROOM_PREFIX, subdoc_id));
+    Ok(Some((package.snapshot, ctx)))
+}
+
+// Persist a statement subdocument
+pub async fn save(snapshot: Vec<u8>, ctx: &DocContext, subdoc_id: Uuid, by_prpl: &str) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let loro_doc = LoroDoc::new();
+    loro_doc.import(&snapshot)
+        .map_err(|e| format!("Failed to import statement subdocument '{}': {}", subdoc_id, e))?;
+    let json = loro_doc.get_deep_value().to_json_value();
+    let package = serde_cbor::to_vec(&ColabPackage { snapshot, peer_map: ctx.peer_map.clone() })
+        .map_err(|e| format!("Failed to serialize statement subdocument '{}': {}", subdoc_id, e))?;
+    db.upsert_statement_subdoc(&ctx.org, subdoc_id, ctx.doc_id, package, json, by_prpl)
+        .await
+        .map_err(|e| format!("Failed to save statement subdocument '{}': {}", subdoc_id, e))?;
+    info!("Saved statement subdocument {} of document {}", subdoc_id, ctx.doc_id);
+    Ok(())
+}
+
+// The linked rows of the JSON of a sheet
+fn linked_rows(json: &mut Value) -> Vec<&mut Value> {
+    let mut linked = Vec::new();
+    let blocks = match json.get_mut("content").and_then(|content| content.as_array_mut()) {
+        Some(blocks) => blocks,
+        None => return linked,
+    };
+    for block in blocks.iter_mut() {
+        if let Some(rows) = block.get_mut("rows").and_then(|rows| rows.as_array_mut()) {
+            linked.extend(rows.iter_mut().filter(|row| row.get("type").and_then(|t| t.as_str()) == Some(LINKED_ROW)));
+        }
+    }
+    linked
+}
+
+// Compose the JSON of a sheet with the statements of its subdocuments
+pub async fn compose_json(org_id: &str, json: &mut Value) -> Result<(), String> {
+    let mut rows = linked_rows(json);
+    let subdoc_ids: Vec<Uuid> = rows
+        .iter()
+        .filter_map(|row| row.get("subdocId").and_then(|id| id.as_str()).and_then(|id| Uuid::parse_str(id).ok()))
+        .collect();
+    if subdoc_ids.is_empty() {
+        return Ok(());
+    }
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let statements: HashMap<String, Value> = db.get_statement_subdocs(org_id, &subdoc_ids)
+        .await
+        .map_err(|e| format!("Failed to load statement subdocuments: {}", e))?
+        .into_iter()
+        .map(|row| (row.id.to_string(), row.json))
+        .collect();
+    for row in rows.iter_mut() {
+        let statement = row.get("subdocId").and_then(|id| id.as_str()).and_then(|id| statements.get(id)).cloned();
+        if let (Some(statement), Some(row)) = (statement, row.as_object_mut()) {
+            row.insert("statement".to_string(), statement);
+        }
+    }
+    Ok(())
+}
+
+// The local rows of the statement grids of a sheet, with the JSON of their statement
+fn local_statements(doc: &LoroDoc) -> Vec<(String, usize, Value)> {
+    let content = doc.get_movable_list("content");
+    let mut statements = Vec::new();
+    for i in 0..content.len() {
+        let block = match get_list_map(&content, i) {
+            Some(block) if get_string(&block, "type").as_deref() == Some("statement-grid") => block,
+            _ => continue,
+        };
+        let block_id = get_block_id(&block, i);
+        let rows = match get_child_movable_list(&block, "rows") {
+            Some(rows) => rows,
+            None => continue,
+        };
+        for r in 0..rows.len() {
+            let row = match get_list_map(&rows, r) {
+                Some(row) if get_string(&row, "type").as_deref() == Some("local") => row,
+                _ => continue,
+            };
+            if let Some(statement) = get_child_map(&row, "statement") {
+                statements.push((block_id.clone(), r, statement.get_deep_value().to_json_value()));
+            }
+        }
+    }
+    statements
+}
+
+// Move the local statements of a sheet into linked subdocuments.
+// The subdocuments are stored first, the rows are only linked when their statement didn't change in the meantime.
+pub async fn extract(registry: Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, by_prpl: &str) -> Result<Vec<ExtractedStatement>, String> {
+    let doc_uuid = Uuid::parse_str(doc_id).map_err(|e| format!("Invalid document UUID '{}': {}", doc_id, e))?;
+    // The rooms of the sheet and its blocks live on the pod owning the sheet
+    room_assignment_service::ensure_local(doc_id)?;
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    // 1. Store every local statement as a subdocument
+    let (doc, _) = doc_load_service::load_loro_doc(&registry, org_id, doc_id)
+        .await?
+        .ok_or_else(|| format!("Document '{}' not found in organization '{}'", doc_id, org_id))?;
+    if get_doc_type(&doc).as_deref() != Some("colab-sheet") {
+        return Err("Only sheets have statements to link".to_string());
+    }
+    if lazy_block_service::is_lazy(&doc) {
+        return Err("The statement grids are loaded lazily, join them before linking statements".to_string());
+    }
+    let mut extracted: Vec<(ExtractedStatement, Value)> = Vec::new();
+    for (block_id, row, json) in local_statements(&doc) {
+        let statement: ColabStatementModel = match serde_json::from_value(json.clone()) {
+            Ok(statement) => statement,
+            Err(e) => {
+                warn!("Leaving row {} of block '{}' of document {} embedded: {}", row, block_id, doc_id, e);
+                continue;
+            }
+        };
+        let subdoc = stmt_to_loro_doc(&statement).ok_or_else(|| format!("Failed to build the statement of row {} of block '{}'", row, block_id))?;
+        subdoc.commit();
+        let snapshot = subdoc.export(ExportMode::Snapshot)
+            .map_err(|e| format!("Failed to export the statement of row {} of block '{}': {}", row, block_id, e))?;
+        let subdoc_json = subdoc.get_deep_value().to_json_value();
+        let peer_map = HashMap::from([(subdoc.peer_id(), "s/colabri-doc".to_string())]);
+        let package = serde_cbor::to_vec(&ColabPackage { snapshot, peer_map })
+            .map_err(|e| format!("Failed to serialize the statement of row {} of block '{}': {}", row, block_id, e))?;
+        let subdoc_id = Uuid::new_v4();
+        db.upsert_statement_subdoc(org_id, subdoc_id, doc_uuid, package, subdoc_json, by_prpl)
+            .await
+            .map_err(|e| format!("Failed to store the statement of row {} of block '{}': {}", row, block_id, e))?;
+        extracted.push((ExtractedStatement { block_id, row, subdoc_id }, json));
+    }
+    if extracted.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // 2. Link the rows to their subdocument, connected clients reconnect
+    let links: Vec<(String, usize, Uuid, Value)> = extracted
+        .iter()
+        .map(|(e, json)| (e.block_id.clone(), e.row, e.subdoc_id, json.clone()))
+        .collect();
+    doc_edit_service::edit_doc(registry, org_id, doc_id, move |doc: &LoroDoc| {
+        let current: HashMap<(String, usize), Value> = local_statements(doc)
+            .into_iter()
+            .map(|(block_id, row, json)| ((block_id, row), json))
+            .collect();
+        if links.iter().any(|(block_id, row, _, json)| current.get(&(block_id.clone(), *row)) != Some(json)) {
+            return Err("The statements changed while linking them, try again".to_string());
+        }
+        let content = doc.get_movable_list("content");
+        for i in 0..content.len() {
+            let block = match get_list_map(&content, i) {
+                Some(block) => block,
+                None => continue,
+            };
+            let block_id = get_block_id(&block, i);
+            let rows = match get_child_movable_list(&block, "rows") {
+                Some(rows) => rows,
+                None => continue,
+            };
+            for (_, r, subdoc_id, _) in links.iter().filter(|(id, ..)| *id == block_id) {
+                let row = get_list_map(&rows, *r).ok_or_else(|| format!("Row {} of block '{}' disappeared", r, block_id))?;
+                row.insert("type", LINKED_ROW).map_err(|e| format!("Failed to link row {} of block '{}': {}", r, block_id, e))?;
+                row.insert("subdocId", subdoc_id.to_string()).map_err(|e| format!("Failed to link row {} of block '{}': {}", r, block_id, e))?;
+                row.delete("statement").map_err(|e| format!("Failed to link row {} of block '{}': {}", r, block_id, e))?;
+            }
+        }
+        doc.commit();
+        Ok(())
+    }, true).await?;
+    info!("Linked {} statements of document {} as subdocuments", extracted.len(), doc_id);
+    Ok(extracted.into_iter().map(|(e, _)| e).collect())
+}
@@ -0,0 +1,88 @@
+use serde_json::Value;
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::services::evidence_service::sha256_hex;
+use crate::models::{DocumentStorageResponse, StorageTier, VersionStorage};
+
+// Keys of the JSON that hold metadata rather than content
+const METADATA_KEYS: [&str; 3] = ["acls", "sourceHash", "refreshedAt"];
+
+fn strip_metadata(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for key in METADATA_KEYS {
+                map.remove(key);
+            }
+            map.values_mut().for_each(strip_metadata);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_metadata),
+        _ => {}
+    }
+}
+
+// Hash of the content of a document, equal for versions that only differ in their properties,
+// ACLs or approvals. serde_json keeps object keys sorted, so the hash is stable.
+pub fn content_hash(json: &Value) -> String {
+    let mut content = json.get("content").cloned().unwrap_or(Value::Null);
+    strip_metadata(&mut content);
+    sha256_hex(content.to_string().as_bytes())
+}
+
+// Get the storage used by every version of a document, None if the document does not exist.
+// Versions beyond the history budget are marked prunable, a preview of what the compaction job trims.
+pub async fn storage_report(org_id: &str, doc_uuid: Uuid) -> Result<Option<DocumentStorageResponse>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    let budget = match db.get_document_history_budget(org_id, doc_uuid).await {
+        Ok(Some(budget)) => budget.map(|b| b.max(1) as u32),
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(format!("Failed to load history budget of document '{}': {}", doc_uuid, e)),
+    };
+    let rows = db.get_document_stream_sizes(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load versions of document '{}': {}", doc_uuid, e))?;
+
+    // The oldest versions beyond the budget are prunable, the latest version never is
+    let n_prunable = match budget {
+        Some(budget) => rows.len().saturating_sub(budget as usize),
+        None => 0,
+    };
+
+    let mut total_bytes: u64 = 0;
+    let mut prunable_bytes: u64 = 0;
+    let mut versions = Vec::with_capacity(rows.len());
+    for (idx, row) in rows.into_iter().enumerate() {
+        let bytes = row.size.max(0) as u64;
+        let prunable = idx < n_prunable;
+        total_bytes += bytes;
+        if prunable {
+            prunable_bytes += bytes;
+        }
+        versions.push(VersionStorage {
+            version: row.version.max(0) as u32,
+            stream_id: row.id.to_string(),
+            bytes,
+            tier: if row.cold { StorageTier::Cold } else { StorageTier::Hot },
+            cumulative_bytes: total_bytes,
+            created_at: row.created_at,
+            created_by: row.created_by,
+            content_hash: row.content_sha256,
+            prunable,
+        });
+    }
+
+    Ok(Some(DocumentStorageResponse {
+        history_budget: budget,
+        total_bytes,
+        prunable_bytes,
+        versions,
+    }))
+}
+
+// Set the number of versions of a document the compaction job keeps, false if the document does not exist
+pub async fn set_history_budget(org_id: &str, doc_uuid: Uuid, budget: Option<u32>, by_prpl: &str) -> Result<bool, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.set_document_history_budget(org_id, doc_uuid, budget.map(|b| b as i32), by_prpl)
+        .await
+        .map_err(|e| format!("Failed to set history budget of document '{}': {}", doc_uuid, e))
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeSet;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use loro::{LoroDoc, LoroMap, ToJson};
+use tracing::warn;
+use uuid::Uuid;
+use crate::models::{ColabSuggestion, ColabSuggestionState};
+use crate::models::lorodoc::{changed_containers, get_child_map, is_inside_container};
+use crate::services::acl_service;
+
+// Suggestions are stored in the root "suggestions" map of the document, keyed by suggestion id.
+// The suggested changes themselves are kept as a Loro update and only imported when accepted.
+
+pub fn read_suggestions(doc: &LoroDoc) -> Vec<ColabSuggestion> {
+    let suggestions_map = doc.get_map("suggestions");
+    let keys: Vec<String> = suggestions_map.keys().map(|k| k.to_string()).collect();
+    let mut suggestions = Vec::with_capacity(keys.len());
+    for key in keys {
+        if let Some(suggestion_map) = get_child_map(&suggestions_map, &key) {
+            match serde_json::from_value::<ColabSuggestion>(suggestion_map.get_deep_value().to_json_value()) {
+                Ok(suggestion) => suggestions.push(suggestion),
+                Err(e) => warn!("Skipping malformed suggestion '{}': {}", key, e),
+            }
+        }
+    }
+    suggestions.sort_by_key(|s| s.timestamp);
+    suggestions
+}
+
+pub fn find_suggestion(doc: &LoroDoc, suggestion_id: &Uuid) -> Option<ColabSuggestion> {
+    read_suggestions(doc).into_iter().find(|s| &s.id == suggestion_id)
+}
+
+pub fn decode_update(suggestion: &ColabSuggestion) -> Result<Vec<u8>, String> {
+    general_purpose::STANDARD
+        .decode(&suggestion.update)
+        .map_err(|e| format!("Invalid update in suggestion '{}': {}", suggestion.id, e))
+}
+
+// Apply a suggested update on a fork of the document and make sure it only touches the block
+// or language at `path`. Returns the fork and the peers that authored the update.
+pub fn apply_to_fork(doc: &LoroDoc, path: &str, update: &[u8]) -> Result<(LoroDoc, Vec<u64>), String> {
+    let fork = doc.fork();
+    let scope_id = acl_service::find_scope_map(&fork, path)?.id();
+
+    // 1. Import the update, it must apply on top of the current document
+    let before = fork.oplog_vv();
+    let status = fork
+        .import(update)
+        .map_err(|e| format!("Failed to import the suggested update: {}", e))?;
+    if status.pending.is_some() {
+        return Err("The suggested update depends on changes that are not part of the document".to_string());
+    }
+    let after = fork.oplog_vv();
+
+    // 2. Find the peers that authored the update
+    let mut peers = Vec::new();
+    for (peer, counter) in after.iter() {
+        if before.get(peer).map_or(true, |c| c < counter) {
+            peers.push(*peer);
+        }
+    }
+    if peers.is_empty() {
+        return Err("The suggested update contains no new changes".to_string());
+    }
+
+    // 3. Every operation must target the scope or one of its descendants
+    for container in changed_containers(&fork, &before, &after) {
+        if !is_inside_container(&fork, &container, &scope_id) {
+            return Err(format!("The suggested update modifies '{}', which is outside of '{}'", container, path));
+        }
+    }
+
+    Ok((fork, peers))
+}
+
+// The state of the block or language at `path` as it would be with the suggestion accepted
+pub fn preview(doc: &LoroDoc, suggestion: &ColabSuggestion) -> Result<serde_json::Value, String> {
+    let update = decode_update(suggestion)?;
+    let (fork, _) = apply_to_fork(doc, &suggestion.path, &update)?;
+    let scope_map = acl_service::find_scope_map(&fork, &suggestion.path)?;
+    Ok(scope_map.get_deep_value().to_json_value())
+}
+
+// The peers that authored a suggestion must not be known as someone else in the document
+pub fn check_peers(peer_map: &std::collections::HashMap<u64, String>, peers: &[u64], author: &str) -> Result<(), String> {
+    for peer in peers {
+        if let Some(prpl) = peer_map.get(peer) {
+            if prpl != author {
+                return Err(format!("Peer {} already belongs to another principal", peer));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn add_suggestion(doc: &LoroDoc, suggestion: &ColabSuggestion) -> Result<(), String> {
+    let suggestion_map = doc
+        .get_map("suggestions")
+        .get_or_create_container(suggestion.id.to_string().as_str(), LoroMap::new())
+        .map_err(|e| format!("Failed to create suggestion '{}': {}", suggestion.id, e))?;
+    let values: [(&str, String); 6] = [
+        ("id", suggestion.id.to_string()),
+        ("path", suggestion.path.clone()),
+        ("author", suggestion.author.clone()),
+        ("state", suggestion.state.to_string()),
+        ("update", suggestion.update.clone()),
+        ("timestamp", suggestion.timestamp.to_rfc3339()),
+    ];
+    for (key, value) in values {
+        suggestion_map
+            .insert(key, value.as_str())
+            .map_err(|e| format!("Failed to write suggestion '{}': {}", suggestion.id, e))?;
+    }
+    Ok(())
+}
+
+// Record the decision on a suggestion
+pub fn decide_suggestion(doc: &LoroDoc, suggestion_id: &Uuid, state: &ColabSuggestionState, by_prpl: &str, decided_at: &DateTime<Utc>) -> Result<(), String> {
+    let suggestion_map = get_child_map(&doc.get_map("suggestions"), &suggestion_id.to_string())
+        .ok_or_else(|| format!("Suggestion '{}' not found", suggestion_id))?;
+    let values = [
+        ("state", state.to_string()),
+        ("decidedBy", by_prpl.to_string()),
+        ("decidedAt", decided_at.to_rfc3339()),
+    ];
+    for (key, value) in values {
+        suggestion_map
+            .insert(key, value.as_str())
+            .map_err(|e| format!("Failed to update suggestion '{}': {}", suggestion_id, e))?;
+    }
+    Ok(())
+}
+
+// Import an accepted suggestion into the document and mark it as accepted
+pub fn accept_suggestion(doc: &LoroDoc, suggestion: &ColabSuggestion, by_prpl: &str, decided_at: &DateTime<Utc>) -> Result<(), String> {
+    let update = decode_update(suggestion)?;
+    let status = doc
+        .import(&update)
+        .map_err(|e| format!("Failed to import suggestion '{}': {}", suggestion.id, e))?;
+    if status.pending.is_some() {
+        return Err(format!("Suggestion '{}' depends on changes that are not part of the document", suggestion.id));
+    }
+    decide_suggestion(doc, &suggestion.id, &ColabSuggestionState::Accepted, by_prpl, decided_at)
+}
+
+// Users that hold Suggest but no Edit, Manage or AddRemove only propose changes
+pub fn is_suggest_only(permissions: &BTreeSet<String>) -> bool {
+    permissions.contains("suggest")
+        && !["edit", "manage", "add-remove"].iter().any(|p| permissions.contains(*p))
+}
@@ -0,0 +1,187 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use loro::ToJson;
+use loro_websocket_server::HubRegistry;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use uuid::Uuid;
+use crate::config;
+use crate::db::dbcolab::{self, DocumentSummaryRow};
+use crate::models::{ColabModel, ColabSheetBlock, ColabStatementModel};
+use crate::services::citation_service::render_text;
+use crate::services::{doc_load_service, script_service};
+use crate::ws::docctx::DocContext;
+
+// Provider name of the built-in fallback
+const EXTRACT_PROVIDER: &str = "extract";
+
+static SUMMARY_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Request sent to the summary provider
+#[derive(Serialize)]
+struct SummaryRequest<'a> {
+    #[serde(rename = "orgId")]
+    org_id: &'a str,
+    #[serde(rename = "docId")]
+    doc_id: &'a str,
+    version: u32,
+    #[serde(rename = "contentType")]
+    content_type: &'a str,
+    #[serde(rename = "langCode")]
+    lang_code: Option<&'a str>,
+    text: &'a str,
+    #[serde(rename = "maxChars")]
+    max_chars: usize,
+}
+
+#[derive(Deserialize)]
+struct SummaryResponse {
+    summary: String,
+}
+
+fn get_client() -> &'static Client {
+    SUMMARY_CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .expect("Failed to build summary client")
+    })
+}
+
+fn max_chars() -> usize {
+    config::get_config().summary_max_chars.unwrap_or(400).max(1)
+}
+
+pub async fn get(org_id: &str, doc_uuids: &[Uuid]) -> Result<Vec<DocumentSummaryRow>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_document_summaries(org_id, doc_uuids)
+        .await
+        .map_err(|e| format!("Failed to load document summaries: {}", e))
+}
+
+// The version of a document that was published last, None when it was never published
+pub async fn published_version(org_id: &str, doc_uuid: Uuid) -> Result<Option<u32>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let signature = db.get_latest_document_signature(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load the published version of document '{}': {}", doc_uuid, e))?;
+    Ok(signature.map(|signature| signature.version as u32))
+}
+
+// Summarize a published version in the background, publishing doesn't wait for the provider
+pub fn spawn_generate(registry: Arc<HubRegistry<DocContext>>, org_id: String, doc_uuid: Uuid, version: u32) {
+    tokio::spawn(async move {
+        if let Err(e) = generate(&registry, &org_id, doc_uuid, version).await {
+            error!("Failed to summarize document '{}' version {}: {}", doc_uuid, version, e);
+        }
+    });
+}
+
+// Summarize a version of a document and store the summary
+pub async fn generate(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, version: u32) -> Result<(), String> {
+    let doc_id = doc_uuid.to_string();
+
+    // 1. Collect the text of the version
+    let (loro_doc, _) = doc_load_service::load_loro_doc_version(registry, org_id, &doc_id, version)
+        .await?
+        .ok_or_else(|| format!("Version {} not found", version))?;
+    let model: ColabModel = serde_json::from_value(loro_doc.get_deep_value().to_json_value())
+        .map_err(|e| format!("Failed to parse document: {}", e))?;
+    let (content_type, lang_code) = match &model {
+        ColabModel::Statement(statement) => (statement.properties.content_type.clone(), statement.properties.master_lang_code.clone()),
+        ColabModel::Sheet(sheet) => (sheet.properties.content_type.clone(), sheet.properties.master_lang_code.clone()),
+    };
+    let text = document_text(&model);
+
+    // 2. Ask the provider, or fall back to the start of the text
+    let (summary, provider) = match config::get_config().summary_provider_url.as_deref() {
+        Some(url) if !text.is_empty() => {
+            let request = SummaryRequest {
+                org_id,
+                doc_id: &doc_id,
+                version,
+                content_type: &content_type,
+                lang_code: lang_code.as_deref(),
+                text: &text,
+                max_chars: max_chars(),
+            };
+            (request_summary(url, &request).await?, url.to_string())
+        }
+        _ => (extract(&text, max_chars()), EXTRACT_PROVIDER.to_string()),
+    };
+
+    // 3. Store it
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.upsert_document_summary(org_id, doc_uuid, version as i32, &summary, &provider)
+        .await
+        .map_err(|e| format!("Failed to store summary: {}", e))?;
+    info!("Summarized document '{}' version {} with '{}'", doc_id, version, provider);
+    Ok(())
+}
+
+async fn request_summary(url: &str, request: &SummaryRequest<'_>) -> Result<String, String> {
+    let mut http_request = get_client().post(url).json(request);
+    if let Some(token) = config::get_config().summary_provider_token.as_deref() {
+        http_request = http_request.bearer_auth(token);
+    }
+    let response = http_request.send().await.map_err(|e| format!("Summary provider unreachable: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Summary provider returned {}", response.status()));
+    }
+    let response: SummaryResponse = response.json().await.map_err(|e| format!("Invalid summary provider response: {}", e))?;
+    // Providers may overshoot, the limit is enforced here
+    Ok(extract(&response.summary, max_chars()))
+}
+
+// The plain text of a document: the titles and texts of the blocks and the statements in the master language
+pub fn document_text(model: &ColabModel) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    match model {
+        ColabModel::Statement(statement) => parts.push(statement_text(statement)),
+        ColabModel::Sheet(sheet) => {
+            for block in &sheet.content {
+                match block {
+                    ColabSheetBlock::Text(text) => {
+                        parts.push(render_text(&text.title));
+                        parts.push(render_text(&text.text_element));
+                    }
+                    ColabSheetBlock::StatementGrid(grid) => {
+                        parts.push(render_text(&grid.title));
+                        parts.extend(grid.rows.iter().filter_map(|row| row.statement.as_ref()).map(statement_text));
+                    }
+                    ColabSheetBlock::Board(board) => {
+                        parts.push(render_text(&board.title));
+                        parts.extend(board.columns.iter().flat_map(|column| &column.cards).map(|card| render_text(&card.title)));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    parts.retain(|part| !part.trim().is_empty());
+    parts.join("\n")
+}
+
+// The text of the master language of a statement, or of any language without one
+pub fn statement_text(statement: &ColabStatementModel) -> String {
+    statement.properties.master_lang_code.as_ref()
+        .and_then(|lang_code| statement.content.get(lang_code))
+        .or_else(|| statement.content.values().next())
+        .map(|element| render_text(&element.text_element))
+        .unwrap_or_default()
+}
+
+// The start of a text, cut at a word boundary, or between graphemes in scripts without spaces
+pub fn extract(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if script_service::grapheme_count(&text) <= max_chars {
+        return text;
+    }
+    let cut = script_service::take_graphemes(&text, max_chars.saturating_sub(1));
+    let cut = match cut.rfind(' ') {
+        Some(index) if index > cut.len() / 2 => &cut[..index],
+        _ => cut,
+    };
+    format!("{}…", cut.trim_end())
+}
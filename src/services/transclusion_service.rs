@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use chrono::Utc;
+use loro::{LoroDoc, ToJson};
+use loro_websocket_server::HubRegistry;
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::models::{ColabSheetBlock, ColabSheetTransclusionBlock, ResolvedTransclusion};
+use crate::services::{acl_service, doc_edit_service, doc_load_service, hub_service, lazy_block_service, statement_subdoc_service};
+use crate::services::evidence_service::sha256_hex;
+use crate::ws::docctx::DocContext;
+
+// Blocks of sheets transcluding a block of another document.
+// A transclusion block holds `sourceDocId` and `sourceBlockId`, the server resolves the source block
+// on request and on export. Saved sheets index their transclusions, saved sources notify the open
+// sheets transcluding a changed block by updating `sourceHash` and `refreshedAt` on the transclusion.
+// Transclusions are not followed into the source, transcluding a transclusion resolves to nothing.
+
+pub const BLOCK_TYPE: &str = "transclusion";
+
+// The latest saved JSON per document waiting to be indexed, saves in between are skipped
+static PENDING: OnceLock<Mutex<HashMap<Uuid, (String, Value)>>> = OnceLock::new();
+static PENDING_NOTIFY: Notify = Notify::const_new();
+
+fn get_pending() -> &'static Mutex<HashMap<Uuid, (String, Value)>> {
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// The transclusions in the JSON of a sheet: block id, source document and source block
+pub fn links(json: &Value) -> Vec<(String, Uuid, String)> {
+    json.get("content")
+        .and_then(|content| content.as_array())
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some(BLOCK_TYPE))
+                .filter_map(|block| {
+                    let block_id = block.get("id")?.as_str()?.to_string();
+                    let source = Uuid::parse_str(block.get("sourceDocId")?.as_str()?).ok()?;
+                    let source_block = block.get("sourceBlockId")?.as_str()?.to_string();
+                    Some((block_id, source, source_block))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn find_block<'a>(json: &'a Value, block_id: &str) -> Option<&'a Value> {
+    json.get("content")?
+        .as_array()?
+        .iter()
+        .enumerate()
+        .find(|(i, block)| block.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()).unwrap_or_else(|| i.to_string()) == block_id)
+        .map(|(_, block)| block)
+}
+
+fn block_hash(block: &Value) -> String {
+    sha256_hex(block.to_string().as_bytes())
+}
+
+// Queue the JSON of a saved document, to index its transclusions and notify the sheets transcluding it
+pub fn schedule(org_id: &str, doc_uuid: Uuid, json: Value) {
+    get_pending().lock().unwrap().insert(doc_uuid, (org_id.to_string(), json));
+    PENDING_NOTIFY.notify_one();
+}
+
+// Start the worker handling the queued documents one at a time
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>) {
+    tokio::spawn(async move {
+        loop {
+            PENDING_NOTIFY.notified().await;
+            loop {
+                let next = {
+                    let mut pending = get_pending().lock().unwrap();
+                    let doc_uuid = pending.keys().next().copied();
+                    doc_uuid.and_then(|doc_uuid| pending.remove(&doc_uuid).map(|(org_id, json)| (doc_uuid, org_id, json)))
+                };
+                let (doc_uuid, org_id, json) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                if let Err(e) = process(&registry, &org_id, doc_uuid, &json).await {
+                    error!("Failed to process the transclusions of document '{}': {}", doc_uuid, e);
+                }
+            }
+        }
+    });
+}
+
+async fn process(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, json: &Value) -> Result<(), String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+
+    // 1. Index the transclusions of saved sheets
+    let is_sheet = json.get("properties").and_then(|p| p.get("type")).and_then(|t| t.as_str()) == Some("colab-sheet");
+    if is_sheet {
+        db.replace_document_transclusions(org_id, doc_uuid, &links(json))
+            .await
+            .map_err(|e| format!("Failed to index transclusions: {}", e))?;
+    }
+
+    // 2. Notify the sheets transcluding a changed block of the document
+    let transclusions = db.get_transclusions_of_source(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load transclusions: {}", e))?;
+    for transclusion in transclusions {
+        let block = match find_block(json, &transclusion.source_block) {
+            Some(block) if block.get("type").and_then(|t| t.as_str()) != Some(BLOCK_TYPE) => block,
+            _ => continue,
+        };
+        let hash = block_hash(block);
+        if transclusion.source_hash.as_deref() == Some(hash.as_str()) {
+            continue;
+        }
+        db.set_transclusion_source_hash(org_id, transclusion.sheet, &transclusion.block_id, &hash)
+            .await
+            .map_err(|e| format!("Failed to record the notified hash: {}", e))?;
+
+        // Only open sheets are notified, closed ones resolve the source when they are opened
+        let sheet_id = transclusion.sheet.to_string();
+        if hub_service::get_open_doc_handle(registry, org_id, &sheet_id).await.is_none() {
+            continue;
+        }
+        let block_id = transclusion.block_id.clone();
+        let refreshed_at = Utc::now().to_rfc3339();
+        let result = doc_edit_service::edit_doc(registry.clone(), org_id, &sheet_id, move |doc: &LoroDoc| {
+            let block = acl_service::find_scope_map(doc, &format!("/content/{}", block_id))?;
+            block.insert("sourceHash", hash.as_str()).map_err(|e| format!("Failed to set the source hash: {}", e))?;
+            block.insert("refreshedAt", refreshed_at.as_str()).map_err(|e| format!("Failed to set the refresh time: {}", e))?;
+            doc.commit();
+            Ok(())
+        }, false).await;
+        match result {
+            Ok(()) => info!("Notified block '{}' of document {} about a change of block '{}' of document {}", transclusion.block_id, sheet_id, transclusion.source_block, doc_uuid),
+            Err(e) => warn!("Failed to notify block '{}' of document {}: {}", transclusion.block_id, sheet_id, e),
+        }
+    }
+    Ok(())
+}
+
+// The JSON of a block of a document, with the rows and statements that are stored apart composed in
+async fn block_of(loro_doc: &LoroDoc, org_id: &str, doc_uuid: Uuid, block_id: &str) -> Result<Option<Value>, String> {
+    let block = match acl_service::find_scope_map(loro_doc, &format!("/content/{}", block_id)) {
+        Ok(block) => block.get_deep_value().to_json_value(),
+        Err(_) => return Ok(None),
+    };
+    let mut sheet = json!({ "content": [block] });
+    lazy_block_service::assemble_json(org_id, doc_uuid, &mut sheet).await?;
+    statement_subdoc_service::compose_json(org_id, &mut sheet).await?;
+    Ok(Some(sheet["content"][0].take()))
+}
+
+// The latest content of a source block, None when the document or the block doesn't exist
+pub async fn source_block(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, source: Uuid, source_block: &str) -> Result<Option<Value>, String> {
+    let (loro_doc, _) = match doc_load_service::load_loro_doc(registry, org_id, &source.to_string()).await? {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    block_of(&loro_doc, org_id, source, source_block).await
+}
+
+// The source block of a transclusion in the latest published version of its document.
+// Public views only show transcluded content that was published.
+pub async fn published_block(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, transclusion: &ColabSheetTransclusionBlock) -> Result<Option<(u32, ColabSheetBlock)>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let source = transclusion.source_doc_id;
+    let signature = match db.get_latest_document_signature(org_id, source).await
+        .map_err(|e| format!("Failed to load the published version of document '{}': {}", source, e))? {
+        Some(signature) => signature,
+        None => return Ok(None),
+    };
+    let version = signature.version as u32;
+    let (loro_doc, _) = match doc_load_service::load_loro_doc_version(registry, org_id, &source.to_string(), version).await? {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let block = match block_of(&loro_doc, org_id, source, &transclusion.source_block_id).await? {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+    let block: ColabSheetBlock = serde_json::from_value(block)
+        .map_err(|e| format!("Failed to parse block '{}' of document '{}': {}", transclusion.source_block_id, source, e))?;
+    if matches!(block, ColabSheetBlock::Transclusion(_)) {
+        return Ok(None);
+    }
+    Ok(Some((version, block)))
+}
+
+// Resolve the transclusions of a sheet for a principal, checked against the ACLs of the source documents.
+// Returns None when the sheet doesn't exist.
+pub async fn resolve(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, prpl: &str) -> Result<Option<Vec<ResolvedTransclusion>>, String> {
+    let (loro_doc, _) = match doc_load_service::load_loro_doc(registry, org_id, doc_id).await? {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let mut resolved = Vec::new();
+    for (block_id, source, source_block_id) in links(&loro_doc.get_deep_value().to_json_value()) {
+        let path = format!("/content/{}", source_block_id);
+        let permissions = acl_service::permissions_on_path(registry, org_id, &source.to_string(), &path, prpl).await?;
+        let (status, block) = match permissions {
+            None => ("missing", None),
+            Some(permissions) if !permissions.contains("view") => ("forbidden", None),
+            Some(_) => match source_block(registry, org_id, source, &source_block_id).await? {
+                None => ("missing", None),
+                Some(block) if block.get("type").and_then(|t| t.as_str()) == Some(BLOCK_TYPE) => ("nested", None),
+                Some(block) => ("resolved", Some(block)),
+            },
+        };
+        resolved.push(ResolvedTransclusion {
+            block_id,
+            source_doc_id: source.to_string(),
+            source_block_id,
+            status: status.to_string(),
+            block,
+        });
+    }
+    Ok(Some(resolved))
+}
@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use loro::{LoroDoc, ToJson};
+use serde_json::Value;
+use crate::models::{ColabModel, UnreadBlock};
+use crate::models::lorodoc::is_inside_container;
+use crate::services::{acl_service, doc_changes_service, policy_scan_service, script_service};
+
+// Catch-up digests of the changes to a document since an earlier state.
+// Blocks of sheets and languages of statements are compared as for polling integrations. The authors
+// of a block are the peers of the operations since the earlier state that target the block or
+// anything nested in it. The summary compares the plain texts of the block before and after, as in
+// the policy scan: the text that was inserted, deleted or replaced between the common start and end.
+
+// Longest excerpt of a text in a summary, in graphemes
+const EXCERPT_CHARS: usize = 80;
+
+/// The changes since an earlier state
+pub struct Digest {
+    pub authors: Vec<String>,
+    pub blocks: Vec<UnreadBlock>,
+}
+
+// The plain text per path of a document in its JSON form, grouped by the block or language holding it
+fn block_texts(json: &Value) -> BTreeMap<String, Vec<(String, String)>> {
+    let mut texts: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    let Ok(model) = serde_json::from_value::<ColabModel>(json.clone()) else {
+        return texts;
+    };
+    for block in policy_scan_service::scan_blocks(&model, json) {
+        let block_path = block.path.split('/').take(3).collect::<Vec<_>>().join("/");
+        texts.entry(block_path).or_default().push((block.path, block.text));
+    }
+    texts
+}
+
+fn excerpt(text: &[char]) -> String {
+    let text: String = text.iter().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ");
+    if script_service::grapheme_count(&text) <= EXCERPT_CHARS {
+        return format!("\"{}\"", text);
+    }
+    format!("\"{}…\"", script_service::take_graphemes(&text, EXCERPT_CHARS))
+}
+
+// What happened to a text between two states
+fn summarize(before: Option<&str>, after: Option<&str>) -> Option<String> {
+    let before: Vec<char> = before.unwrap_or_default().chars().collect();
+    let after: Vec<char> = after.unwrap_or_default().chars().collect();
+    if before == after {
+        return None;
+    }
+    let prefix = before.iter().zip(&after).take_while(|(a, b)| a == b).count();
+    let suffix = before[prefix..].iter().rev().zip(after[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let removed = &before[prefix..before.len() - suffix];
+    let inserted = &after[prefix..after.len() - suffix];
+    Some(match (removed.is_empty(), inserted.is_empty()) {
+        (true, _) => format!("Inserted {}", excerpt(inserted)),
+        (_, true) => format!("Deleted {}", excerpt(removed)),
+        _ => format!("Replaced {} with {}", excerpt(removed), excerpt(inserted)),
+    })
+}
+
+// The summary lines of a block, one per text that changed
+fn block_summary(before: Option<&Vec<(String, String)>>, after: Option<&Vec<(String, String)>>) -> Vec<String> {
+    let before: BTreeMap<&str, &str> = before.into_iter().flatten().map(|(path, text)| (path.as_str(), text.as_str())).collect();
+    let after: BTreeMap<&str, &str> = after.into_iter().flatten().map(|(path, text)| (path.as_str(), text.as_str())).collect();
+    let paths: BTreeSet<&str> = before.keys().chain(after.keys()).copied().collect();
+    let mut summary: Vec<String> = paths
+        .into_iter()
+        .filter_map(|path| summarize(before.get(path).copied(), after.get(path).copied()))
+        .collect();
+    if summary.is_empty() {
+        summary.push("Formatting or properties changed".to_string());
+    }
+    summary
+}
+
+/// Compare the latest state of a document with the state at a version vector.
+/// The inner error is a message for the caller when the version vector isn't part of the history.
+pub fn digest(latest: &LoroDoc, peer_map: &HashMap<u64, String>, since: &HashMap<String, i32>) -> Result<Result<Digest, String>, String> {
+    let base = match doc_changes_service::checkout_vv(latest, peer_map, since) {
+        Ok(base) => base,
+        Err(e) => return Ok(Err(e)),
+    };
+    let (base_vv, latest_vv) = (base.state_vv(), latest.state_vv());
+    if base_vv == latest_vv {
+        return Ok(Ok(Digest { authors: Vec::new(), blocks: Vec::new() }));
+    }
+
+    // 1. The blocks that were added, changed or removed
+    let base_json = base.get_deep_value().to_json_value();
+    let latest_json = latest.get_deep_value().to_json_value();
+    let before = doc_changes_service::block_hashes(&base_json);
+    let after = doc_changes_service::block_hashes(&latest_json);
+    let mut statuses: Vec<(String, &str)> = Vec::new();
+    for (id, hash) in &after {
+        match before.get(id) {
+            None => statuses.push((id.clone(), "added")),
+            Some(before_hash) if before_hash != hash => statuses.push((id.clone(), "changed")),
+            _ => {}
+        }
+    }
+    statuses.extend(before.keys().filter(|id| !after.contains_key(*id)).map(|id| (id.clone(), "removed")));
+
+    // 2. Who changed what
+    let scopes = acl_service::collect_acl_scopes(latest)?;
+    let updates = latest.export_json_updates_without_peer_compression(&base_vv, &latest_vv);
+    let name = |peer: u64| peer_map.get(&peer).cloned().unwrap_or_else(|| format!("peer:{}", peer));
+    let authors: BTreeSet<String> = updates.changes.iter().map(|change| name(change.id.peer)).collect();
+
+    // 3. The digest per block
+    let texts_before = block_texts(&base_json);
+    let texts_after = block_texts(&latest_json);
+    let mut blocks = Vec::with_capacity(statuses.len());
+    for (id, status) in statuses {
+        let path = format!("/content/{}", id);
+        let scope_id = scopes.iter().find(|scope| scope.path == path).and_then(|scope| scope.map.as_ref()).map(|map| map.id());
+        let block_authors: BTreeSet<String> = match &scope_id {
+            Some(scope_id) => updates
+                .changes
+                .iter()
+                .filter(|change| change.ops.iter().any(|op| is_inside_container(latest, &op.container, scope_id)))
+                .map(|change| name(change.id.peer))
+                .collect(),
+            None => BTreeSet::new(),
+        };
+        blocks.push(UnreadBlock {
+            summary: block_summary(texts_before.get(&path), texts_after.get(&path)),
+            path,
+            status: status.to_string(),
+            authors: block_authors.into_iter().collect(),
+        });
+    }
+
+    Ok(Ok(Digest { authors: authors.into_iter().collect(), blocks }))
+}
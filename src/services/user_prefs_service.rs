@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use loro_websocket_server::HubRegistry;
+use tracing::warn;
+use uuid::Uuid;
+use crate::db::dbcolab::{self, DocumentUserPrefsRow};
+use crate::models::{DocumentUserPrefsRequest, DocumentUserPrefsResponse, UnreadChanges};
+use crate::services::doc_changes_service::{self, Since};
+use crate::services::doc_load_service;
+use crate::ws::docctx::DocContext;
+
+// Preferences and read markers of users per document.
+// Marking a document read stores its version vector, the unread changes are the blocks that
+// changed since that state, compared the same way as the changes for polling integrations. When the
+// version vector can't be checked out anymore the latest saved version read is compared instead.
+
+/// The preferences of a user on a document with the changes since the user last read it.
+/// Users that never stored preferences get the defaults.
+pub async fn get(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, prpl: &str) -> Result<DocumentUserPrefsResponse, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let row = db.get_document_user_prefs(org_id, doc_uuid, prpl)
+        .await
+        .map_err(|e| format!("Failed to load the preferences: {}", e))?;
+    response(registry, org_id, doc_uuid, prpl, row).await
+}
+
+/// The version vector of a document when the user last marked it read, None when it never did
+pub async fn read_marker(org_id: &str, doc_uuid: Uuid, prpl: &str) -> Result<Option<HashMap<String, i32>>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let row = db.get_document_user_prefs(org_id, doc_uuid, prpl)
+        .await
+        .map_err(|e| format!("Failed to load the preferences: {}", e))?;
+    match row.and_then(|row| row.last_read_vv) {
+        Some(vv) => serde_json::from_value(vv.0).map(Some).map_err(|e| format!("Malformed read marker: {}", e)),
+        None => Ok(None),
+    }
+}
+
+/// Update the preferences of a user on a document, moving the read marker to the latest state when asked
+pub async fn update(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, prpl: &str, request: &DocumentUserPrefsRequest) -> Result<DocumentUserPrefsResponse, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let doc_id = doc_uuid.to_string();
+
+    let read_marker = if request.mark_read {
+        let (loro_doc, ctx) = doc_load_service::load_loro_doc(registry, org_id, &doc_id)
+            .await?
+            .ok_or_else(|| format!("Document '{}' not found", doc_id))?;
+        let vv: HashMap<String, i32> = loro_doc
+            .state_vv()
+            .iter()
+            .map(|(peer, counter)| (peer.to_string(), *counter))
+            .collect();
+        let vv = serde_json::to_value(vv).map_err(|e| format!("Failed to serialize the version vector: {}", e))?;
+        Some((ctx.doc_version as i32, vv))
+    } else {
+        None
+    };
+
+    let row = db.upsert_document_user_prefs(
+        org_id,
+        doc_uuid,
+        prpl,
+        read_marker,
+        request.pinned_blocks.as_deref(),
+        request.collapsed_sections.as_deref(),
+    )
+    .await
+    .map_err(|e| format!("Failed to store the preferences: {}", e))?;
+    response(registry, org_id, doc_uuid, prpl, Some(row)).await
+}
+
+async fn response(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_uuid: Uuid, prpl: &str, row: Option<DocumentUserPrefsRow>) -> Result<DocumentUserPrefsResponse, String> {
+    let unread = match &row {
+        Some(row) => unread(registry, org_id, &doc_uuid.to_string(), row).await?,
+        None => None,
+    };
+    Ok(DocumentUserPrefsResponse {
+        doc_id: doc_uuid.to_string(),
+        prpl: prpl.to_string(),
+        last_read_version: row.as_ref().and_then(|row| row.last_read_version).map(|version| version as u32),
+        last_read_at: row.as_ref().and_then(|row| row.last_read_at),
+        pinned_blocks: row.as_ref().map(|row| row.pinned_blocks.clone()).unwrap_or_default(),
+        collapsed_sections: row.as_ref().map(|row| row.collapsed_sections.clone()).unwrap_or_default(),
+        unread,
+        updated_at: row.as_ref().map(|row| row.updated_at),
+    })
+}
+
+// The changes since the read marker, None when there is no marker or it can't be compared anymore
+async fn unread(registry: &Arc<HubRegistry<DocContext>>, org_id: &str, doc_id: &str, row: &DocumentUserPrefsRow) -> Result<Option<UnreadChanges>, String> {
+    let Some(vv) = &row.last_read_vv else {
+        return Ok(None);
+    };
+    let Some((latest, ctx)) = doc_load_service::load_loro_doc(registry, org_id, doc_id).await? else {
+        return Ok(None);
+    };
+
+    let mut candidates = Vec::new();
+    match serde_json::from_value::<HashMap<String, i32>>(vv.0.clone()) {
+        Ok(vv) => candidates.push(Since::VersionVector(vv)),
+        Err(e) => warn!("Ignoring the malformed read marker of document '{}': {}", doc_id, e),
+    }
+    if let Some(version) = row.last_read_version.filter(|version| *version > 0) {
+        candidates.push(Since::Version(version as u32));
+    }
+
+    for since in &candidates {
+        match doc_changes_service::changes_since(registry, org_id, doc_id, &latest, &ctx.peer_map, since).await? {
+            Ok(Some(changes)) => return Ok(Some(UnreadChanges {
+                has_unread: true,
+                changed_blocks: changes.changed,
+                removed_blocks: changes.removed,
+            })),
+            Ok(None) => return Ok(Some(UnreadChanges { has_unread: false, changed_blocks: Vec::new(), removed_blocks: Vec::new() })),
+            Err(e) => warn!("Failed to compare document '{}' with the read marker: {}", doc_id, e),
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use loro::{LoroDoc, VersionVector};
+use crate::models::NamedVersionEntry;
+
+// Version vectors supplied by API callers.
+// loro panics when a version vector points beyond the operations it knows, so supplied version
+// vectors are checked against the oplog of the document before they are used.
+// Callers may key counters by principal instead of peer id, a principal is resolved through the
+// peer map of the document when it maps to a single peer.
+
+// Resolve a version vector keyed by peer ids or principals to one keyed by peer ids.
+// Returns one message per key that can't be resolved.
+pub fn resolve(vv: &HashMap<String, i32>, peer_map: &HashMap<u64, String>) -> Result<HashMap<u64, i32>, Vec<String>> {
+    let mut keys: Vec<(&String, &i32)> = vv.iter().collect();
+    keys.sort();
+    let mut resolved: HashMap<u64, i32> = HashMap::new();
+    let mut problems = Vec::new();
+    for (key, counter) in keys {
+        let peer = match key.parse::<u64>() {
+            Ok(peer) => peer,
+            Err(_) => {
+                let mut peers: Vec<u64> = peer_map
+                    .iter()
+                    .filter(|(_, prpl)| *prpl == key)
+                    .map(|(peer, _)| *peer)
+                    .collect();
+                peers.sort();
+                match peers.as_slice() {
+                    [peer] => *peer,
+                    [] => {
+                        problems.push(format!("{}: unknown principal, no peer of the document belongs to it", key));
+                        continue;
+                    }
+                    _ => {
+                        let peers: Vec<String> = peers.iter().map(|peer| peer.to_string()).collect();
+                        problems.push(format!("{}: the principal has several peers ({}), key the counters by peer id", key, peers.join(", ")));
+                        continue;
+                    }
+                }
+            }
+        };
+        if resolved.insert(peer, *counter).is_some_and(|previous| previous != *counter) {
+            problems.push(format!("peer {}: given twice with different counters", peer));
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    Ok(resolved)
+}
+
+// The entries of a version vector with the principal of each peer, ordered by peer
+pub fn named(vv: &VersionVector, peer_map: &HashMap<u64, String>) -> Vec<NamedVersionEntry> {
+    let mut entries: Vec<NamedVersionEntry> = vv
+        .iter()
+        .map(|(peer, counter)| NamedVersionEntry {
+            peer: peer.to_string(),
+            prpl: peer_map.get(peer).cloned(),
+            counter: *counter,
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.peer.parse::<u64>().unwrap_or_default());
+    entries
+}
+
+// Check a version vector against the peers and counters of a document.
+// Returns one message per peer that is out of range.
+pub fn validate(doc: &LoroDoc, vv: &HashMap<u64, i32>) -> Result<VersionVector, Vec<String>> {
+    let oplog_vv = doc.oplog_vv();
+    let mut peers: Vec<(&u64, &i32)> = vv.iter().collect();
+    peers.sort();
+    let problems: Vec<String> = peers
+        .into_iter()
+        .filter_map(|(peer, counter)| {
+            let known = oplog_vv.get(peer).copied();
+            if *counter < 0 {
+                Some(format!("peer {}: counter {} is negative", peer, counter))
+            } else if *counter == 0 {
+                None
+            } else {
+                match known {
+                    None => Some(format!("peer {}: unknown peer, the document has no operations by it", peer)),
+                    Some(end) if *counter > end => Some(format!("peer {}: counter {} is beyond the {} operations of the peer", peer, counter, end)),
+                    Some(_) => None,
+                }
+            }
+        })
+        .collect();
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    Ok(VersionVector::from_iter(vv.iter().map(|(peer, counter)| (*peer, *counter))))
+}
@@ -0,0 +1,45 @@
+use uuid::Uuid;
+use crate::db::dbcolab;
+use crate::models::DocumentWatchResponse;
+
+// Watch subscriptions of users on documents.
+// A watch subscribes a user to the events of a document next to the principals in its ACLs, it is kept
+// when the ACLs change. Workflow notifications list the watchers of the document, and the digests only
+// cover followed documents that are still watched. Following a document watches it, unwatching it also
+// stops following it.
+
+/// The principals watching a document
+pub async fn watchers(org_id: &str, doc_uuid: Uuid) -> Result<Vec<String>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_document_watchers(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load the watchers: {}", e))
+}
+
+/// Watch a document, watching it again keeps the original watch
+pub async fn watch(org_id: &str, doc_uuid: Uuid, prpl: &str) -> Result<DocumentWatchResponse, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let row = db.watch_document(org_id, doc_uuid, prpl)
+        .await
+        .map_err(|e| format!("Failed to watch the document: {}", e))?;
+    Ok(DocumentWatchResponse {
+        doc_id: doc_uuid.to_string(),
+        prpl: prpl.to_string(),
+        watching: true,
+        since: Some(row.created_at),
+    })
+}
+
+/// Stop watching a document
+pub async fn unwatch(org_id: &str, doc_uuid: Uuid, prpl: &str) -> Result<DocumentWatchResponse, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.unwatch_document(org_id, doc_uuid, prpl)
+        .await
+        .map_err(|e| format!("Failed to unwatch the document: {}", e))?;
+    Ok(DocumentWatchResponse {
+        doc_id: doc_uuid.to_string(),
+        prpl: prpl.to_string(),
+        watching: false,
+        since: None,
+    })
+}
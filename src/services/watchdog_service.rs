@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use loro_websocket_server::HubRegistry;
+use tokio::time::timeout;
+use tracing::{error, info, warn};
+use crate::config;
+use crate::db::dbcolab;
+use crate::ws::docctx::DocContext;
+
+// How long a single lock or database probe may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+static HEALTHY: AtomicBool = AtomicBool::new(true);
+static CONSECUTIVE_FAILURES: AtomicU32 = AtomicU32::new(0);
+static DB_REACHABLE: AtomicBool = AtomicBool::new(true);
+static CONSECUTIVE_DB_FAILURES: AtomicU32 = AtomicU32::new(0);
+
+// Whether the watchdog considers the instance alive
+pub fn is_healthy() -> bool {
+    HEALTHY.load(Ordering::Relaxed)
+}
+
+// Whether the database answers the watchdog's pings. Only readiness depends on it: restarting
+// the instance doesn't bring the database back, and it would drop the unsaved rooms.
+pub fn is_db_reachable() -> bool {
+    DB_REACHABLE.load(Ordering::Relaxed)
+}
+
+// Start the watchdog. It periodically acquires the registry and hub locks and probes the database pool.
+// After too many consecutive lock failures the health check fails, so the orchestrator restarts the instance.
+// After too many consecutive database failures the readiness check fails, so no new traffic is routed to it.
+pub fn spawn(registry: Arc<HubRegistry<DocContext>>) {
+    let config = config::get_config();
+    let interval = Duration::from_millis(config.watchdog_interval_ms.unwrap_or(10_000));
+    let threshold = config.watchdog_failure_threshold.unwrap_or(3).max(1);
+    info!("Starting liveness watchdog, interval: {:?}, failure threshold: {}", interval, threshold);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match probe_locks(&registry).await {
+                Ok(()) => {
+                    if CONSECUTIVE_FAILURES.swap(0, Ordering::Relaxed) > 0 {
+                        info!("Watchdog lock probe succeeded again");
+                    }
+                    HEALTHY.store(true, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    let failures = CONSECUTIVE_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+                    warn!("Watchdog lock probe failed ({}/{}): {}", failures, threshold, e);
+                    if failures >= threshold && HEALTHY.swap(false, Ordering::Relaxed) {
+                        error!("Watchdog lock probe failed {} times in a row, reporting unhealthy. Last failure: {}. {}", failures, e, diagnostic_state());
+                    }
+                }
+            }
+            match probe_db().await {
+                Ok(()) => {
+                    if CONSECUTIVE_DB_FAILURES.swap(0, Ordering::Relaxed) > 0 {
+                        info!("Watchdog database probe succeeded again");
+                    }
+                    DB_REACHABLE.store(true, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    let failures = CONSECUTIVE_DB_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+                    warn!("Watchdog database probe failed ({}/{}): {}", failures, threshold, e);
+                    if failures >= threshold && DB_REACHABLE.swap(false, Ordering::Relaxed) {
+                        error!("Watchdog database probe failed {} times in a row, reporting not ready. Last failure: {}. {}", failures, e, diagnostic_state());
+                    }
+                }
+            }
+        }
+    });
+}
+
+// Acquire the registry lock and every hub lock
+async fn probe_locks(registry: &Arc<HubRegistry<DocContext>>) -> Result<(), String> {
+
+    // 1. The registry lock, released before the hub locks are taken
+    let hubs: Vec<_> = match timeout(PROBE_TIMEOUT, registry.hubs().lock()).await {
+        Ok(hubs) => hubs.iter().map(|(org_id, hub)| (org_id.clone(), hub.clone())).collect(),
+        Err(_) => return Err(format!("Timed out acquiring the hub registry lock after {:?}", PROBE_TIMEOUT)),
+    };
+
+    // 2. Every hub lock
+    for (org_id, hub) in hubs {
+        if timeout(PROBE_TIMEOUT, hub.lock()).await.is_err() {
+            return Err(format!("Timed out acquiring the hub lock of organization '{}' after {:?}", org_id, PROBE_TIMEOUT));
+        }
+    }
+    Ok(())
+}
+
+// Run a query on the database pool
+async fn probe_db() -> Result<(), String> {
+    if let Some(db) = dbcolab::get_db() {
+        match timeout(PROBE_TIMEOUT, db.ping()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(format!("Database probe failed: {}", e)),
+            Err(_) => return Err(format!("Timed out probing the database after {:?}", PROBE_TIMEOUT)),
+        }
+    }
+    Ok(())
+}
+
+// The state worth logging when the instance goes unhealthy
+fn diagnostic_state() -> String {
+    match dbcolab::get_db() {
+        Some(db) => {
+            let (idle, total) = db.pool_state();
+            format!("Database pool: {} idle, {} total", idle, total)
+        }
+        None => "Database not initialized".to_string(),
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use crate::models::{DocumentQuarantinedEvent, DocumentStateChangedEvent, UserDigestEvent};
+
+const MAX_ATTEMPTS: u32 = 3;
+
+static WEBHOOK_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// An event delivered to webhooks, `data` holds the payload of the event type
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhookEvent {
+    pub event: String,
+    pub org: String,
+    pub document: String,
+    pub timestamp: DateTime<Utc>,
+    pub data: Value,
+}
+
+/// The data of an event type, every emitted event has one so the events schema covers it
+pub trait WebhookPayload: Serialize + for<'s> ToSchema<'s> {
+    const EVENT: &'static str;
+    const DESCRIPTION: &'static str;
+}
+
+impl WebhookPayload for DocumentStateChangedEvent {
+    const EVENT: &'static str = "document.state-changed";
+    const DESCRIPTION: &'static str = "A document moved to another workflow state";
+}
+
+impl WebhookPayload for DocumentQuarantinedEvent {
+    const EVENT: &'static str = "document.quarantined";
+    const DESCRIPTION: &'static str = "A document was quarantined after repeated failed loads";
+}
+
+impl WebhookPayload for UserDigestEvent {
+    const EVENT: &'static str = "user.digest";
+    const DESCRIPTION: &'static str = "The daily or weekly digest of the documents a user follows, the envelope has no document";
+}
+
+impl WebhookEvent {
+    pub fn new<P: WebhookPayload>(org: &str, document: &str, timestamp: DateTime<Utc>, data: &P) -> Self {
+        Self {
+            event: P::EVENT.to_string(),
+            org: org.to_string(),
+            document: document.to_string(),
+            timestamp,
+            data: serde_json::to_value(data).unwrap_or(Value::Null),
+        }
+    }
+}
+
+fn get_client() -> &'static Client {
+    WEBHOOK_CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to build webhook client")
+    })
+}
+
+// Deliver an event to every url in the background
+pub fn dispatch(urls: Vec<String>, event: WebhookEvent) {
+    for url in urls {
+        let event = event.clone();
+        tokio::spawn(async move {
+            deliver(&url, &event).await;
+        });
+    }
+}
+
+// POST the event to the url, retrying failed deliveries with an exponential backoff
+async fn deliver(url: &str, event: &WebhookEvent) -> bool {
+    for attempt in 1..=MAX_ATTEMPTS {
+        match get_client()
+            .post(url)
+            .header("X-Colabri-Event", event.event.as_str())
+            .json(event)
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => {
+                info!("Delivered '{}' event for document '{}' to {}", event.event, event.document, url);
+                return true;
+            }
+            Ok(response) => {
+                warn!("Webhook {} answered {} for '{}' event (attempt {}/{})", url, response.status(), event.event, attempt, MAX_ATTEMPTS);
+            }
+            Err(e) => {
+                warn!("Failed to deliver '{}' event to {} (attempt {}/{}): {}", event.event, url, attempt, MAX_ATTEMPTS, e);
+            }
+        }
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+        }
+    }
+    false
+}
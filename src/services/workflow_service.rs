@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use loro::{LoroDoc, LoroList};
+use std::collections::HashMap;
+use tracing::{error, info};
+use uuid::Uuid;
+use crate::clients::app_service_client;
+use crate::db::dbcolab;
+use crate::models::{ColabModelPermission, DocumentStateChangedEvent, WorkflowDefinition, WorkflowStateDefinition};
+use crate::services::{acl_cache_service, acl_service, watch_service};
+use crate::services::webhook_service::{self, WebhookEvent};
+
+/// The state of documents that are released to downstream consumers, they get signed on entering it
+pub const PUBLISHED_STATE: &str = "published";
+
+/// The state of documents that are no longer worked on
+pub const ARCHIVED_STATE: &str = "archived";
+
+/// The permissions that are revoked when a document gets archived, View is kept
+pub const ARCHIVE_REVOKED_PERMISSIONS: [ColabModelPermission; 3] = [
+    ColabModelPermission::Edit,
+    ColabModelPermission::Manage,
+    ColabModelPermission::Suggest,
+];
+
+/// A workflow transition that was made
+#[derive(Clone)]
+pub struct TransitionEvent {
+    pub org_id: String,
+    pub doc_uuid: Uuid,
+    pub from: String,
+    pub to: String,
+    pub by_prpl: String,
+    pub comment: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+// Get the workflow definition of an organization, falling back to the default lifecycle
+pub async fn get_workflow(org_id: &str) -> Result<WorkflowDefinition, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    match db.get_org_workflow(org_id).await {
+        Ok(Some(definition)) => serde_json::from_value(definition)
+            .map_err(|e| format!("Invalid workflow definition for organization '{}': {}", org_id, e)),
+        Ok(None) => Ok(WorkflowDefinition::default()),
+        Err(e) => Err(format!("Failed to load workflow of organization '{}': {}", org_id, e)),
+    }
+}
+
+// Get the workflow state of a document, None if the document does not exist
+pub async fn get_state(org_id: &str, doc_uuid: Uuid) -> Result<Option<String>, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    db.get_document_workflow_state(org_id, doc_uuid)
+        .await
+        .map_err(|e| format!("Failed to load workflow state of document '{}': {}", doc_uuid, e))
+}
+
+pub fn is_published(state: &str) -> bool {
+    state == PUBLISHED_STATE
+}
+
+pub fn is_archived(state: &str) -> bool {
+    state == ARCHIVED_STATE
+}
+
+// Write the state into the document properties and apply the ACL template of the state.
+// Archiving also strips the revoked permissions from every ACL scope of the document.
+pub fn apply_state(doc: &LoroDoc, state: &WorkflowStateDefinition) -> Result<(), String> {
+    doc.get_map("properties")
+        .insert("workflowState", state.name.as_str())
+        .map_err(|e| format!("Failed to set workflow state: {}", e))?;
+    if let Some(template) = &state.acl_template {
+        apply_acl_template(doc, template)?;
+    }
+    if is_archived(&state.name) {
+        let removed = acl_service::strip_loro_permissions(doc, &ARCHIVE_REVOKED_PERMISSIONS)?;
+        info!("Removed {} ACL entries from archived document", removed);
+    }
+    Ok(())
+}
+
+// Revoke the document ACL rows in the database of an archived document.
+// Library ACLs are shared with other documents and are left alone, writes are refused on connect instead.
+pub async fn revoke_archived_db_acls(org_id: &str, doc_uuid: Uuid) -> Result<u64, String> {
+    let db = dbcolab::get_db().ok_or_else(|| "Database not initialized".to_string())?;
+    let permissions: Vec<String> = ARCHIVE_REVOKED_PERMISSIONS.iter().map(|p| p.to_string()).collect();
+    let revoked = db.delete_document_acls(org_id, doc_uuid, &permissions)
+        .await
+        .map_err(|e| format!("Failed to revoke ACLs of archived document '{}': {}", doc_uuid, e))?;
+    acl_cache_service::invalidate_document(org_id, doc_uuid);
+    Ok(revoked)
+}
+
+// Replace the document level ACLs of the permissions in the template
+pub fn apply_acl_template(doc: &LoroDoc, template: &HashMap<String, Vec<String>>) -> Result<(), String> {
+    let acls = doc.get_map("acls");
+    for (permission, prpls) in template {
+        let list = acls
+            .insert_container(permission.as_str(), LoroList::new())
+            .map_err(|e| format!("Failed to apply ACL template for '{}': {}", permission, e))?;
+        for prpl in prpls {
+            list.push(prpl.as_str())
+                .map_err(|e| format!("Failed to apply ACL template for '{}': {}", permission, e))?;
+        }
+    }
+    Ok(())
+}
+
+// Run the hooks of a transition: call the webhooks of the workflow and notify the app service
+pub fn run_transition_hooks(workflow: &WorkflowDefinition, transition: &TransitionEvent) {
+    let webhooks = workflow.webhooks.clone();
+    let event = transition.clone();
+    tokio::spawn(async move {
+        let watchers = watch_service::watchers(&event.org_id, event.doc_uuid).await.unwrap_or_else(|e| {
+            error!("Failed to load the watchers of document '{}': {}", event.doc_uuid, e);
+            Vec::new()
+        });
+        webhook_service::dispatch(webhooks, WebhookEvent::new(
+            &event.org_id,
+            &event.doc_uuid.to_string(),
+            event.timestamp,
+            &DocumentStateChangedEvent {
+                from: event.from,
+                to: event.to,
+                by_prpl: event.by_prpl,
+                comment: event.comment,
+                watchers,
+            },
+        ));
+    });
+
+    // The app service picks up the new state and notifies the users and watchers of the document
+    if let Some(client) = app_service_client::get_app_service_client() {
+        let org_id = transition.org_id.clone();
+        let doc_uuid = transition.doc_uuid;
+        tokio::spawn(async move {
+            match client.sync_document(&org_id, &doc_uuid).await {
+                Ok(_) => {
+                    info!("Notified app service about workflow transition of document: {}", doc_uuid);
+                    // The app service writes the ACL rows of the template of the new state
+                    acl_cache_service::invalidate_document(&org_id, doc_uuid);
+                }
+                Err(e) => error!("Failed to notify app service about workflow transition of document '{}': {}", doc_uuid, e),
+            }
+        });
+    }
+}
@@ -1,5 +1,6 @@
 use moka::sync::Cache;
-use std::sync::OnceLock;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 use std::time::Duration;
 use tracing::info;
 
@@ -7,6 +8,17 @@ use tracing::info;
 pub struct ConnCtx {
     pub uid: String,
     pub org_id: String,
+    // Versions the client announced in the handshake
+    pub client_version: ClientVersion,
+    // Roles of the token, the principals of the user are loaded with them when it joins a room
+    pub roles: Vec<String>,
+}
+
+/// Versions of the client library and of the document schema it speaks, None when not announced
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ClientVersion {
+    pub client: Option<String>,
+    pub schema: Option<String>,
 }
 
 /// Global connection context cache
@@ -30,3 +42,41 @@ pub fn get_conn_ctx_cache() -> &'static Cache<u64, ConnCtx> {
         .get()
         .expect("Connection context cache not initialized. Call init_conn_ctx_cache() first.")
 }
+
+/// Rooms a connection joined, with whether it may write to them
+static CONN_ROOMS: OnceLock<Mutex<HashMap<u64, HashMap<String, bool>>>> = OnceLock::new();
+
+fn get_conn_rooms() -> &'static Mutex<HashMap<u64, HashMap<String, bool>>> {
+    CONN_ROOMS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Record that a connection joined a room
+pub fn record_join(conn_id: u64, room: &str, writable: bool) {
+    let mut conn_rooms = get_conn_rooms().lock().unwrap();
+    conn_rooms.entry(conn_id).or_default().insert(room.to_string(), writable);
+}
+
+/// The rooms a connection joined
+pub fn joined_rooms(conn_id: u64) -> Vec<String> {
+    let conn_rooms = get_conn_rooms().lock().unwrap();
+    conn_rooms.get(&conn_id).map(|rooms| rooms.keys().cloned().collect()).unwrap_or_default()
+}
+
+/// Change whether a connection may write to a room it joined, after its permissions were re-evaluated
+pub fn set_writable(conn_id: u64, room: &str, writable: bool) {
+    let mut conn_rooms = get_conn_rooms().lock().unwrap();
+    if let Some(writable_room) = conn_rooms.get_mut(&conn_id).and_then(|rooms| rooms.get_mut(room)) {
+        *writable_room = writable;
+    }
+}
+
+/// Whether a connection may write to a room, connections that were never re-evaluated keep their permission
+pub fn is_writable(conn_id: u64, room: &str) -> bool {
+    let conn_rooms = get_conn_rooms().lock().unwrap();
+    conn_rooms.get(&conn_id).and_then(|rooms| rooms.get(room)).copied().unwrap_or(true)
+}
+
+/// Forget the rooms of a closed connection
+pub fn forget_rooms(conn_id: u64) {
+    get_conn_rooms().lock().unwrap().remove(&conn_id);
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use crate::models::{DocumentSettings, StorageTier};
 
 #[derive(Clone, Debug)]
 pub struct DocContext {
@@ -9,4 +10,12 @@ pub struct DocContext {
     pub doc_owner: String,
     pub peer_map: HashMap<u64, String>,
     pub last_updating_peer: Option<u64>,
+    // The tier the document was loaded from
+    pub tier: StorageTier,
+    // Overrides of the behavior of this document
+    pub settings: DocumentSettings,
+    // Loaded from a state-only snapshot, saves merge the changes into the stored history
+    pub state_only: bool,
+    // The block of a sheet in lazy block mode whose body this room syncs
+    pub block_id: Option<String>,
 }
@@ -1,11 +1,12 @@
 use moka::sync::Cache;
 use serde_json::Value;
 use std::sync::OnceLock;
-use std::time::Duration;
-use tokio::runtime::Handle;
-use tracing::{error, info};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 
 use crate::clients::app_service_client;
+use crate::config;
+use crate::db::dbcolab;
 
 #[derive(Clone, Debug)]
 pub struct UserCtx {
@@ -35,6 +36,13 @@ impl UserCtx {
 
 static USER_CTX_CACHE: OnceLock<Cache<String, UserCtx>> = OnceLock::new();
 
+// When the cache was initialized, the start of the warm start window
+static STARTED_AT: OnceLock<Instant> = OnceLock::new();
+
+// Users whose principals were refreshed recently, so repeated denials don't hammer the app service
+static RECENT_REFRESHES: OnceLock<Cache<String, ()>> = OnceLock::new();
+const REFRESH_COOLDOWN: Duration = Duration::from_secs(10);
+
 pub fn init_user_ctx_cache() {
     USER_CTX_CACHE.get_or_init(|| {
         Cache::builder()
@@ -42,6 +50,7 @@ pub fn init_user_ctx_cache() {
             .time_to_idle(Duration::from_secs(4 * 60 * 60))
             .build()
     });
+    STARTED_AT.get_or_init(Instant::now);
     info!("User cache initialized");
 }
 pub fn get_user_ctx_cache() -> &'static Cache<String, UserCtx> {
@@ -99,8 +108,21 @@ pub async fn get_or_fetch_user_ctx_async(uid: &str, token_roles: Vec<String>, fo
         info!("User context cache miss for uid {}. Refreshing from app service.", uid);
     }
 
+    // Right after a restart, serve the reconnect storm from the persisted principals
+    if !force_refresh || in_warm_start() {
+        if let Some(principals) = load_persisted_prpls(uid).await {
+            let new_ctx = UserCtx {
+                principals,
+                token_roles,
+            };
+            cache.insert(uid.to_string(), new_ctx.clone());
+            return Ok(new_ctx);
+        }
+    }
+
     // Fetch principals from the app service to create a new user context
     let fetched_prpls = fetch_user_prpls_from_service(uid).await?;
+    persist_prpls(uid, &fetched_prpls);
     
     // Create a new user context and insert it into the cache
     let new_ctx = UserCtx {
@@ -113,12 +135,101 @@ pub async fn get_or_fetch_user_ctx_async(uid: &str, token_roles: Vec<String>, fo
     Ok(new_ctx)
 }
 
-pub fn get_or_fetch_user_ctx_blocking(uid: &str, token_roles: Vec<String>, force_refresh: bool) -> Result<UserCtx, String> {
-    let uid_owned = uid.to_string();
+// Refresh the principals of a user after access was denied, the user may just have been granted access.
+// Within the cooldown after a refresh the cached context is returned, returns None for unknown users.
+pub async fn refresh_user_ctx(uid: &str) -> Result<Option<UserCtx>, String> {
+    let cached = match get_user_ctx_from_cache(uid) {
+        Some(ctx) => ctx,
+        None => return Ok(None),
+    };
+    let recent = RECENT_REFRESHES.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(100_000)
+            .time_to_live(REFRESH_COOLDOWN)
+            .build()
+    });
+    if recent.contains_key(uid) {
+        return Ok(Some(cached));
+    }
+    recent.insert(uid.to_string(), ());
+    info!("Refreshing principals of user {} after a denied access", uid);
+    get_or_fetch_user_ctx_async(uid, cached.token_roles, true).await.map(Some)
+}
 
-    tokio::task::block_in_place(move || {
-        Handle::current().block_on(async move {
-            get_or_fetch_user_ctx_async(&uid_owned, token_roles, force_refresh).await
-        })
-    })
-}
\ No newline at end of file
+// Refresh the principals of a user in the background
+pub fn spawn_refresh(uid: &str) {
+    let uid = uid.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = refresh_user_ctx(&uid).await {
+            error!("Failed to refresh principals of user {}: {}", uid, e);
+        }
+    });
+}
+
+// Replace the principals of a cached user, as pushed by the app service. The roles come from the token
+// of the user, so users that are not cached are left alone, they get fresh principals when they connect.
+pub fn update_principals(uid: &str, principals: Vec<String>) -> Option<UserCtx> {
+    let cache = get_user_ctx_cache();
+    let mut ctx = cache.get(uid)?;
+    ctx.principals = principals;
+    cache.insert(uid.to_string(), ctx.clone());
+    persist_prpls(uid, &ctx.principals);
+    Some(ctx)
+}
+
+// The persistence TTL of principals, None when the warm persistence is disabled
+fn persist_ttl_secs() -> Option<u64> {
+    config::get_config().user_ctx_persist_ttl_secs.filter(|ttl| *ttl > 0)
+}
+
+// Whether the instance started recently enough to trust persisted principals over a forced refresh
+fn in_warm_start() -> bool {
+    let window = Duration::from_secs(config::get_config().user_ctx_warm_start_secs.unwrap_or(300));
+    STARTED_AT.get().map(|started| started.elapsed() < window).unwrap_or(false)
+}
+
+async fn load_persisted_prpls(uid: &str) -> Option<Vec<String>> {
+    let ttl = persist_ttl_secs()?;
+    let db = dbcolab::get_db()?;
+    match db.get_user_ctx_snapshot(uid, ttl).await {
+        Ok(Some(principals)) => {
+            info!("Loaded persisted principals for user {}", uid);
+            Some(principals)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Failed to load persisted principals for user {}: {}", uid, e);
+            None
+        }
+    }
+}
+
+// Persist the principals of a user in the background
+fn persist_prpls(uid: &str, principals: &[String]) {
+    if persist_ttl_secs().is_none() {
+        return;
+    }
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => return,
+    };
+    let uid = uid.to_string();
+    let principals = principals.to_vec();
+    tokio::spawn(async move {
+        if let Err(e) = db.upsert_user_ctx_snapshot(&uid, &principals).await {
+            warn!("Failed to persist principals for user {}: {}", uid, e);
+        }
+    });
+}
+
+// Delete the persisted principals that expired, called once at startup
+pub async fn prune_persisted_prpls() {
+    let (ttl, db) = match (persist_ttl_secs(), dbcolab::get_db()) {
+        (Some(ttl), Some(db)) => (ttl, db),
+        _ => return,
+    };
+    match db.prune_user_ctx_snapshots(ttl).await {
+        Ok(pruned) => info!("Pruned {} expired persisted principals", pruned),
+        Err(e) => warn!("Failed to prune persisted principals: {}", e),
+    }
+}
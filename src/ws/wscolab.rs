@@ -1,4 +1,4 @@
-use loro::{ LoroDoc, ToJson};
+use loro::LoroDoc;
 use loro_protocol::{CrdtType, UpdateStatusCode};
 use loro_websocket_server::{AuthArgs, CloseConnectionArgs, HandshakeAuthArgs, LoadDocArgs, LoadedDoc, SaveDocArgs, UpdateArgs, UpdatedDoc};
 use loro_websocket_server::protocol::Permission;
@@ -10,16 +10,22 @@ use serde_cbor;
 
 use crate::models::ColabPackage;
 use crate::{db::dbcolab, clients::app_service_client };
-use crate::services::auth_service::{get_user_prpls, get_auth_token};
+use crate::services::auth_service::{self, get_auth_token, validate_user_token};
+use crate::services::{acl_cache_service::{self, AccessDecision}, acl_service, analytics_service, approval_round_service, archival_service, client_version_service, cors_service, doc_type_service, guest_service, recording_service, initial_sync_service, json_projection_service, lazy_block_service, limits_service, load_limit_service, panic_guard_service, statement_subdoc_service, policy_scan_service, room_assignment_service, journal_service, link_index_service, numbering_service, save_policy_service, save_retry_service, save_verification_service, init_hook_service, save_status_service, schema_migration_service, storage_service, suggestion_service, transclusion_service, workflow_service};
 use crate::auth::is_org_member;
 use super::docctx::{DocContext};
 use super::userctx::{self};
 use super::connctx::{self, ConnCtx};
 
+// Error of an authorization denied because the user is not a member of the organization
+const ORG_ACCESS_DENIED: &str = "User lacks access to organization";
+
 /// Authenticate a client
 ///
 /// This function is called during the WebSocket handshake to authenticate the client.
 /// It should check whether the request is made with a valid cookie from a trusted origin.
+/// The handshake hook is synchronous and runs on the accept loop, so it only validates the token.
+/// The principals of the user are loaded asynchronously when it joins its first room.
 /// # Arguments
 /// * `workspace_id` - The ID of the workspace the client is trying to access
 /// * `token` - An optional authentication token provided by the loro-protocol framework (not used)
@@ -28,36 +34,54 @@ use super::connctx::{self, ConnCtx};
 pub fn on_auth_handshake(args: HandshakeAuthArgs) -> bool {
     let org_id = args.workspace;
 
+    // Refuse browsers on pages of other origins
+    if !cors_service::handshake_allowed(args.request) {
+        error!("Refusing handshake on organization {} from an origin that isn't allowed", org_id);
+        return false;
+    }
+
+    // Refuse clients below the minimum versions
+    let client_version = client_version_service::from_request(args.request);
+    match client_version_service::check(&client_version) {
+        client_version_service::Compatibility::Supported => {}
+        client_version_service::Compatibility::Deprecated(reason) => warn!("Accepting deprecated client on organization {}: {}", org_id, reason),
+        client_version_service::Compatibility::Refused(reason) => {
+            error!("Refusing client on organization {}: {}", org_id, reason);
+            return false;
+        }
+    }
+
     // Extract the token from the request
     let auth_token =  match get_auth_token(args.request) {
         Ok(t) => t,
+        // Without a token, admit a guest when guest access is enabled
+        Err(_) if guest_service::is_enabled() => {
+            guest_service::admit_guest(args.conn_id, &org_id, client_version);
+            return true;
+        }
         Err(e) => {
             error!("Failed to get auth token from handshake request: {}", e);
             return false;
         }
     };
 
-    // Extract the prpls of the user
-    match get_user_prpls(&auth_token, true) {
-        Ok((uid, prpls)) => {
-            info!("User {} authenticated with principals: {:?}", uid, prpls);
-            // Validate user has access to the organization
-            if !is_org_member(&prpls, &org_id) {
-                error!("User {} does not have access to organization {}", uid, org_id);
-                return false;
-            } else {
-                let conn_ctx = ConnCtx {
-                    uid: uid.to_string(),
-                    org_id: org_id.to_string(),
-                };
-                let conn_ctx_cache = connctx::get_conn_ctx_cache();
-                conn_ctx_cache.insert(args.conn_id, conn_ctx);
-                return true;
-            }
+    // Validate the token, the membership of the organization is checked when joining a room
+    match validate_user_token(&auth_token) {
+        Ok((uid, roles)) => {
+            info!("User {} authenticated on organization {}", uid, org_id);
+            let conn_ctx = ConnCtx {
+                uid,
+                org_id: org_id.to_string(),
+                client_version,
+                roles,
+            };
+            let conn_ctx_cache = connctx::get_conn_ctx_cache();
+            conn_ctx_cache.insert(args.conn_id, conn_ctx);
+            true
         }
         Err(e) => {
-            error!("Failed to get user principals from auth token: {}", e);
-            return false;
+            error!("Failed to validate the auth token of the handshake: {}", e);
+            false
         }
     }
 }
@@ -69,68 +93,156 @@ pub fn on_auth_handshake(args: HandshakeAuthArgs) -> bool {
 /// * `args` - Authentication arguments
 pub fn on_authenticate(args: AuthArgs) -> Pin<Box<dyn Future<Output = Result<Option<Permission>, String>> + Send>> {
     Box::pin(async move {
+        let (room, conn_id) = (args.room.clone(), args.conn_id);
+        let result = authenticate(args).await;
+        recording_service::record_join(&room, conn_id, &result).await;
+        result
+    })
+}
 
-        // Get the doc_id
-        let doc_id: String = args.room;
+async fn authenticate(args: AuthArgs) -> Result<Option<Permission>, String> {
+    // Get the doc_id, block rooms of lazily loaded sheets are authorized on their sheet
+    let doc_id: String = args.room;
+    let sheet_id = lazy_block_service::parse_room(&doc_id).0.to_string();
 
-        // Get the connection context from the cache
-        let conn_ctx_cache = connctx::get_conn_ctx_cache();
-        let conn_ctx = match conn_ctx_cache.get(&args.conn_id) {
-            Some(ctx) => ctx,
-            None => {
-                error!("No connection context found for connection_id: {}", args.conn_id);
-                return Err("No connection context found".to_string());
-            }
-        };
+    // Rooms owned by another pod are refused with a redirect to that pod
+    if let Some(reason) = room_assignment_service::redirect_reason(&sheet_id) {
+        info!("Room {} is owned by another pod, refusing join: {}", doc_id, reason);
+        return Err(reason);
+    }
 
-        let uid_for_fetch = conn_ctx.uid.clone();
-        let org_for_fetch = conn_ctx.org_id.clone();
+    // Get the connection context from the cache
+    let conn_ctx_cache = connctx::get_conn_ctx_cache();
+    let conn_ctx = match conn_ctx_cache.get(&args.conn_id) {
+        Some(ctx) => ctx,
+        None => {
+            error!("No connection context found for connection_id: {}", args.conn_id);
+            return Err("No connection context found".to_string());
+        }
+    };
 
-        // Load the user context to get the principals
-        let user_ctx = match userctx::get_user_ctx_from_cache(&uid_for_fetch) {
-            Some(ctx) => ctx,
-            None => {
-                error!("Unable to load user context for uid {} from cache", conn_ctx.uid);
-                return Err("Unable to load user context from cache".to_string());
-            }
-        };
-        if !is_org_member(&user_ctx.principals, &org_for_fetch) {
-            error!("User {} does not have access to organization {}", conn_ctx.uid, org_for_fetch);
-            return Err("User lacks access to organization".to_string());
+    let uid_for_fetch = conn_ctx.uid.clone();
+
+    // Guests may only join the guest documents, their principals never need a refresh
+    if guest_service::is_guest(&uid_for_fetch) {
+        let permission = guest_service::guest_permission(&sheet_id);
+        match &permission {
+            Some(permission) => connctx::record_join(args.conn_id, &doc_id, matches!(permission, Permission::Write)),
+            None => info!("Guest {} may not join document {}", uid_for_fetch, doc_id),
         }
+        return Ok(permission);
+    }
 
-        // Check if the user can view the document
-        let db = match dbcolab::get_db() {
-            Some(db) => db,
-            None => {
-                error!("Database not initialized");
-                return Err("Database not initialized".to_string());
-            }
-        };
-        let doc_uuid = match Uuid::parse_str(&doc_id) {
-            Ok(uuid) => uuid,
-            Err(e) => {
-                error!("Invalid document UUID '{}': {}", doc_id, e);
-                return Err(format!("Invalid document UUID: {}", e));
+    // Load the user context to get the principals. Principals refreshed or pushed since the load take precedence,
+    // the roles always come from the token of this connection.
+    let mut user_ctx = match auth_service::load_user_ctx(&uid_for_fetch, conn_ctx.roles.clone()).await {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            error!("Unable to load user context for uid {}: {}", conn_ctx.uid, e);
+            return Err("Unable to load user context".to_string());
+        }
+    };
+    if let Some(cached) = userctx::get_user_ctx_from_cache(&uid_for_fetch) {
+        user_ctx.principals = cached.principals;
+    }
+
+    // Users that were just granted access may still have stale principals, refresh them and retry once
+    let mut result = authorize_user(&conn_ctx, &sheet_id, &user_ctx).await;
+    let denied = matches!(&result, Ok(None)) || matches!(&result, Err(e) if e == ORG_ACCESS_DENIED);
+    if denied {
+        match userctx::refresh_user_ctx(&uid_for_fetch).await {
+            Ok(Some(refreshed)) if refreshed.principals != user_ctx.principals => {
+                info!("Principals of user {} changed, retrying authorization for document {}", uid_for_fetch, doc_id);
+                let refreshed = userctx::UserCtx { principals: refreshed.principals, token_roles: user_ctx.token_roles.clone() };
+                result = authorize_user(&conn_ctx, &sheet_id, &refreshed).await;
             }
-        };
-        // Make the DB call to see if the user can view the document
-        let _ = match db.get_viewable_document(&conn_ctx.org_id, doc_uuid, &user_ctx.principals).await {
-            Ok(Some(_)) => {
-                // The document was found, return Write permission
-                return Ok(Some(Permission::Write))
-            },
-            Ok(None) => {
-                info!("User {} does not have access to document {}", conn_ctx.uid, doc_id);
-                // Deny access
-                return Ok(None);
+            Ok(_) => {}
+            Err(e) => error!("Failed to refresh principals of user {}: {}", uid_for_fetch, e),
+        }
+    }
+
+    // Remember the rooms of the connection, so its permissions can be re-evaluated when its principals change
+    if let Ok(Some(permission)) = &result {
+        connctx::record_join(args.conn_id, &doc_id, matches!(permission, Permission::Write));
+    }
+    result
+}
+
+// Decide the permission of a user on a document, None denies access
+pub(crate) async fn authorize_user(conn_ctx: &ConnCtx, doc_id: &str, user_ctx: &userctx::UserCtx) -> Result<Option<Permission>, String> {
+    if !is_org_member(&user_ctx.principals, &conn_ctx.org_id) {
+        error!("User {} does not have access to organization {}", conn_ctx.uid, conn_ctx.org_id);
+        return Err(ORG_ACCESS_DENIED.to_string());
+    }
+
+    // Check if the user can view the document
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => {
+            error!("Database not initialized");
+            return Err("Database not initialized".to_string());
+        }
+    };
+    let doc_uuid = match Uuid::parse_str(&doc_id) {
+        Ok(uuid) => uuid,
+        Err(e) => {
+            error!("Invalid document UUID '{}': {}", doc_id, e);
+            return Err(format!("Invalid document UUID: {}", e));
+        }
+    };
+    // Decisions taken recently for the same principals are reused
+    let all_prpls = user_ctx.get_all_prpls();
+    if let Some(decision) = acl_cache_service::get(&conn_ctx.org_id, doc_uuid, &all_prpls) {
+        return Ok(match decision {
+            AccessDecision::Denied => None,
+            AccessDecision::Read => Some(Permission::Read),
+            AccessDecision::Write => Some(Permission::Write),
+        });
+    }
+    // Make the DB call to see if the user can view the document
+    let _ = match db.get_viewable_document(&conn_ctx.org_id, doc_uuid, &user_ctx.principals).await {
+        Ok(Some(_)) => {
+            // Archived documents can't be reopened for writing
+            match db.get_document_workflow_state(&conn_ctx.org_id, doc_uuid).await {
+                Ok(Some(state)) if workflow_service::is_archived(&state) => {
+                    info!("Document {} is archived, granting read access to user {}", doc_id, conn_ctx.uid);
+                    acl_cache_service::insert(&conn_ctx.org_id, doc_uuid, &all_prpls, AccessDecision::Read);
+                    return Ok(Some(Permission::Read))
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Failed to load workflow state of document {}: {}", doc_id, e);
+                    return Ok(Some(Permission::Read))
+                }
             }
-            Err(e) => {
-                error!("Database error checking access for user {} to document {}: {}", conn_ctx.uid, doc_id, e);
-                return Err(format!("Database error: {}", e));
+            // Users that may only suggest edits get read access, their changes go through the suggestions API
+            match acl_service::document_db_permissions(&conn_ctx.org_id, doc_uuid, &all_prpls).await {
+                Ok(Some(permissions)) if suggestion_service::is_suggest_only(&permissions) => {
+                    info!("User {} may only suggest edits on document {}, granting read access", conn_ctx.uid, doc_id);
+                    acl_cache_service::insert(&conn_ctx.org_id, doc_uuid, &all_prpls, AccessDecision::Read);
+                    return Ok(Some(Permission::Read))
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Failed to evaluate permissions of user {} on document {}: {}", conn_ctx.uid, doc_id, e);
+                    return Ok(Some(Permission::Read))
+                }
             }
-        };
-    })
+            // The document was found, return Write permission
+            acl_cache_service::insert(&conn_ctx.org_id, doc_uuid, &all_prpls, AccessDecision::Write);
+            return Ok(Some(Permission::Write))
+        },
+        Ok(None) => {
+            info!("User {} does not have access to document {}", conn_ctx.uid, doc_id);
+            // Deny access
+            acl_cache_service::insert(&conn_ctx.org_id, doc_uuid, &all_prpls, AccessDecision::Denied);
+            return Ok(None);
+        }
+        Err(e) => {
+            error!("Database error checking access for user {} to document {}: {}", conn_ctx.uid, doc_id, e);
+            return Err(format!("Database error: {}", e));
+        }
+    };
 }
 
 /// Hanlde the closing of a connection
@@ -140,9 +252,13 @@ pub fn on_authenticate(args: AuthArgs) -> Pin<Box<dyn Future<Output = Result<Opt
 pub fn on_close_connection(args: CloseConnectionArgs) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> {
     Box::pin(async move {
         let conn_id = args.conn_id;
+        for room in connctx::joined_rooms(conn_id) {
+            recording_service::record(&room, recording_service::Direction::In, "close", Some(conn_id), &[], None).await;
+        }
         // Remove from connection context cache
         let conn_ctx_cache = connctx::get_conn_ctx_cache();
         conn_ctx_cache.invalidate(&conn_id);
+        connctx::forget_rooms(conn_id);
         info!("Connection context removed for connection_id: {}", conn_id);
         Ok(())
     })
@@ -167,18 +283,117 @@ pub fn on_load_document(args: LoadDocArgs) -> Pin<Box<dyn Future<Output = Result
     let doc_id = args.room;
     let org_id = args.workspace;
     Box::pin(async move {
-        match crate::services::doc_db_service::fetch_doc_snapshot_from_db(&org_id, &doc_id, None).await {
+        let (org, room) = (org_id.to_string(), doc_id.to_string());
+        let loaded = panic_guard_service::run("on_load_document", &org, &room, load_document(org_id.to_string(), doc_id.to_string()))
+            .await
+            .unwrap_or_else(|panic| Err(panic.to_string()));
+        if recording_service::is_recording(&room) {
+            let (snapshot, detail) = match &loaded {
+                Ok(LoadedDoc { snapshot: Some(snapshot), .. }) => (snapshot.as_slice(), None),
+                Ok(_) => (&[][..], Some("not found".to_string())),
+                Err(e) => (&[][..], Some(format!("error: {}", e))),
+            };
+            recording_service::record(&room, recording_service::Direction::Server, "load", None, snapshot, detail).await;
+        }
+        loaded
+    })
+}
+
+async fn load_document(org_id: String, doc_id: String) -> Result<LoadedDoc<DocContext>, String> {
+    // A draining pod accepts no new rooms
+    if crate::services::drain_service::is_draining() {
+        warn!("Refusing to load document {} while draining", doc_id);
+        return Err(crate::services::drain_service::DRAINING_ERROR.to_string());
+    }
+
+    // Rooms owned by another pod are never loaded here, whoever opens them: joins are redirected before
+    // and edits through the API are refused
+    if let Err(reason) = room_assignment_service::ensure_local(lazy_block_service::parse_room(&doc_id).0) {
+        warn!("Refusing to load document {} owned by another pod: {}", doc_id, reason);
+        return Err(reason);
+    }
+
+    // Wait for a free slot, a burst of loads would exhaust the database pool
+    let _permit = load_limit_service::acquire(&doc_id).await?;
+
+    // Block rooms of sheets hold the body of a lazy block or a linked statement
+    if let (sheet_id, Some(block_id)) = lazy_block_service::parse_room(&doc_id) {
+        let loaded = match statement_subdoc_service::parse_block(block_id) {
+            Some(subdoc_id) => statement_subdoc_service::load(&org_id, sheet_id, subdoc_id).await,
+            None => lazy_block_service::load_block(&org_id, sheet_id, block_id).await,
+        };
+        return match loaded {
             Ok(Some((snapshot, ctx))) => Ok(LoadedDoc { snapshot: Some(snapshot), ctx: Some(ctx) }),
             Ok(None) => Ok(LoadedDoc { snapshot: None, ctx: None }),
-            Err(e) => Err(e),
+            Err(e) => {
+                error!("{}", e);
+                Err(e)
+            }
+        };
+    }
+
+    match crate::services::quarantine_service::fetch_doc_snapshot(&org_id, &doc_id, None).await {
+        Ok(Some((snapshot, mut ctx))) => {
+            // Refuse documents of types the organization doesn't register
+            if let Err(e) = doc_type_service::ensure_registered_snapshot(&org_id, &snapshot).await {
+                warn!("Refusing to load document {}: {}", doc_id, e);
+                return Err(e);
+            }
+
+            // Move comment anchors along with edits made since they were last resolved
+            let mut snapshot = crate::services::comment_service::reanchor_snapshot(&doc_id, snapshot, &mut ctx);
+
+            // Recover the updates accepted after the last save, when the pod went down before saving them
+            if let Some(replayed) = journal_service::replay(&org_id, &snapshot, &mut ctx).await {
+                match save_document(doc_id.to_string(), replayed.clone(), Some(ctx.clone()), true).await {
+                    Ok(()) => ctx.last_updating_peer = None,
+                    Err(e) => error!("Failed to save the journaled updates of document {}: {}", doc_id, e),
+                }
+                snapshot = replayed;
+            }
+
+            // Upgrade documents of older schema versions before anything relies on their structure
+            if let Some(migrated) = schema_migration_service::migrate_snapshot(&snapshot, &mut ctx) {
+                match save_document(doc_id.to_string(), migrated.clone(), Some(ctx.clone()), true).await {
+                    Ok(()) => ctx.last_updating_peer = None,
+                    Err(e) => error!("Failed to save the migration of document {}: {}", doc_id, e),
+                }
+                snapshot = migrated;
+            }
+
+            // Prepare documents of content types with initialization hooks on their first load
+            if let Some(initialized) = init_hook_service::initialize(&org_id, &snapshot, &mut ctx).await {
+                match save_document(doc_id.to_string(), initialized.clone(), Some(ctx.clone()), true).await {
+                    Ok(()) => ctx.last_updating_peer = None,
+                    Err(e) => error!("Failed to save the initialization of document {}: {}", doc_id, e),
+                }
+                snapshot = initialized;
+            }
+
+            // Rooms that only admit readers skip the op history, joining viewers get the state only
+            if ctx.last_updating_peer.is_none() && initial_sync_service::is_read_only_room(&org_id, &ctx).await {
+                match initial_sync_service::to_state_only(&snapshot) {
+                    Ok(state) => {
+                        info!("Loading read-only document {} from a state-only snapshot ({} of {} bytes)", doc_id, state.len(), snapshot.len());
+                        snapshot = state;
+                        ctx.state_only = true;
+                    }
+                    Err(e) => warn!("Loading document {} with its history: {}", doc_id, e),
+                }
+            }
+            archival_service::touch(&org_id, ctx.doc_id);
+            Ok(LoadedDoc { snapshot: Some(snapshot), ctx: Some(ctx) })
         }
-    })
+        Ok(None) => Ok(LoadedDoc { snapshot: None, ctx: None }),
+        Err(e) => Err(e),
+    }
 }
 
 /// Save a document to storage
 /// 
 /// This function is called periodically (based on save_interval_ms) to persist
-/// the current state of a document to storage.
+/// the current state of a document to storage. Whether the save happens right away
+/// is up to the save policy of the document type.
 /// 
 /// # Arguments
 /// * `doc_id` - The unique identifier of the document to save (format: "org_id/doc_uuid")
@@ -196,129 +411,229 @@ pub fn on_save_document(args: SaveDocArgs<DocContext>) -> Pin<Box<dyn Future<Out
             return Ok(());
         }
 
-        // Start saving the loro document
-        info!("Saving loro document for room: {}", doc_id);
+        let org = context.as_ref().map(|ctx| ctx.org.clone()).unwrap_or_default();
+        let room = doc_id.to_string();
+        if recording_service::is_recording(&room) {
+            recording_service::record(&room, recording_service::Direction::Server, "save", None, &snapshot, None).await;
+        }
+        panic_guard_service::run("on_save_document", &org, &room, save_document(room.clone(), snapshot, context, false))
+            .await
+            .unwrap_or_else(|panic| Err(panic.to_string()))
+    })
+}
 
-        // Check if context is available
-        let mut context = match context {
-            Some(ctx) => ctx,
-            None => {
-                error!("No doc context available when saving for document: {}", doc_id);
-                return Err("No doc context available when saving".to_string());
-            }
+/// Persist a deferred save once it is due
+pub async fn save_deferred_document(save: save_policy_service::DeferredSave) -> Result<(), String> {
+    save_document(save.room, save.snapshot, Some(save.ctx), true).await
+}
+
+// Persist a document, `force` skips the save policy
+async fn save_document(doc_id: String, mut snapshot: Vec<u8>, context: Option<DocContext>, force: bool) -> Result<(), String> {
+    // Start saving the loro document
+    info!("Saving loro document for room: {}", doc_id);
+
+    // Check if context is available
+    let mut context = match context {
+        Some(ctx) => ctx,
+        None => {
+            error!("No doc context available when saving for document: {}", doc_id);
+            return Err("No doc context available when saving".to_string());
+        }
+    };
+
+    // Get document identifiers
+    let org = context.org.clone();
+    let doc_uuid = context.doc_id.clone();
+    let doc_stream_uuid = context.doc_stream_id.clone();
+
+    // Get the principal that updated the document most recently
+    let updating_peer_id = match context.last_updating_peer {
+        Some(pid) => pid,
+        None => {
+            // No updating peer, nothing to save
+            info!("Aborting save. No last updating peer found in context for document: {}", doc_uuid);
+            return Ok(());
+        }
+    };
+    let by_prpl = match context.peer_map.get(&updating_peer_id) {
+        Some(prpl) => prpl.clone(),
+        None => {
+            error!("Error Saving. No principal found for updating peer {} in document: {}", updating_peer_id, doc_uuid);
+            return Err("No principal found for updating peer".to_string());
+        }
+    };
+
+    // Block rooms of sheets store the body of their lazy block or their linked statement
+    if let Some(block_id) = context.block_id.clone() {
+        return match statement_subdoc_service::parse_block(&block_id) {
+            Some(subdoc_id) => statement_subdoc_service::save(snapshot, &context, subdoc_id, &by_prpl).await,
+            None => lazy_block_service::save_block(snapshot, &context, &block_id, &by_prpl).await,
         };
+    }
+
+    // Convert snapshot to JSON for storage in statement
+    let mut loro_doc = LoroDoc::new();
+    if let Err(e) = loro_doc.import(&snapshot) {
+        error!("Failed to import snapshot for document '{}': {}", doc_uuid, e);
+        return Err(format!("Failed to import snapshot for document '{}': {}", doc_uuid, e));
+    }
+
+    // Rooms loaded from a state-only snapshot have no history, their changes are merged into the stored history
+    if context.state_only {
+        loro_doc = initial_sync_service::merge_into_history(&org, &doc_id, &loro_doc).await.map_err(|e| {
+            error!("{}", e);
+            e
+        })?;
+        snapshot = loro_doc.export(loro::ExportMode::Snapshot)
+            .map_err(|e| format!("Failed to export snapshot for document '{}': {}", doc_uuid, e))?;
+    }
 
-        // Get document identifiers
-        let org = context.org.clone();
-        let doc_uuid = context.doc_id.clone();
-        let doc_stream_uuid = context.doc_stream_id.clone();
+    // Get the JSON representation
+    let json = json_projection_service::document_json(&org, doc_uuid, &loro_doc).await;
+
+    // Don't persist documents beyond the limits of the organization
+    let limits = limits_service::with_document_settings(limits_service::get_limits(&org).await, &context.settings);
+    if let Err(violation) = limits_service::check_snapshot_size(&snapshot, &limits)
+        .and_then(|_| limits_service::check_json(&json, &limits))
+    {
+        error!("Refusing to save document '{}': {}", doc_uuid, violation);
+        save_status_service::record_error(doc_uuid, &violation.to_string());
+        return Err(violation.to_error_string());
+    }
+
+    // Don't persist documents of types the organization doesn't register
+    if let Err(e) = doc_type_service::ensure_registered_doc(&org, &loro_doc).await {
+        error!("Refusing to save document '{}': {}", doc_uuid, e);
+        save_status_service::record_error(doc_uuid, &e);
+        return Err(e);
+    }
 
-        // Get the principal that updated the document most recently
-        let updating_peer_id = match context.last_updating_peer {
-            Some(pid) => pid,
+    // Defer the save when the save policy of the document type says it is not due yet
+    let policy = save_policy_service::with_document_settings(save_policy_service::get_policy(&org, &json).await, &context.settings);
+    if !force {
+        let save = save_policy_service::DeferredSave { room: doc_id.clone(), snapshot, ctx: context.clone() };
+        let save = match save_policy_service::should_save_now(save, &policy) {
+            Some(save) => save,
             None => {
-                // No updating peer, nothing to save
-                info!("Aborting save. No last updating peer found in context for document: {}", doc_uuid);
+                info!("Deferring save of document {} by its save policy", doc_uuid);
                 return Ok(());
             }
         };
-        let by_prpl = match context.peer_map.get(&updating_peer_id) {
-            Some(prpl) => prpl.clone(),
-            None => {
-                error!("Error Saving. No principal found for updating peer {} in document: {}", updating_peer_id, doc_uuid);
-                return Err("No principal found for updating peer".to_string());
-            }
-        };
+        snapshot = save.snapshot;
+    }
 
-        // Create the ColabPackage to store in the database
-        let colab_package = ColabPackage {
-            snapshot: snapshot.clone(),
-            peer_map: context.peer_map.clone(),
-        };
+    // Persist the history only when the policy asks for it, the state is rebuilt on load
+    if policy.persistence == save_policy_service::Persistence::Updates {
+        snapshot = loro_doc.export(loro::ExportMode::all_updates())
+            .map_err(|e| format!("Failed to export updates for document '{}': {}", doc_uuid, e))?;
+    }
 
-        // Serialize the ColabPackage to CBOR
-        let blob = match serde_cbor::to_vec(&colab_package) {
-            Ok(data) => data,
-            Err(e) => {
-                error!("Failed to serialize ColabPackage for document '{}': {}", doc_id, e);
-                return Err(format!("Failed to serialize ColabPackage: {}", e));
-            }
-        };        
+    // Create the ColabPackage to store in the database
+    let colab_package = ColabPackage {
+        snapshot,
+        peer_map: context.peer_map.clone(),
+    };
 
-        // Convert snapshot to JSON for storage in statement
-        let loro_doc = LoroDoc::new();
-        if let Err(e) = loro_doc.import(&snapshot) {
-            error!("Failed to import snapshot for document '{}': {}", doc_uuid, e);
-            return Err(format!("Failed to import snapshot for document '{}': {}", doc_uuid, e));
+    // Serialize the ColabPackage to CBOR
+    let blob = match serde_cbor::to_vec(&colab_package) {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Failed to serialize ColabPackage for document '{}': {}", doc_id, e);
+            return Err(format!("Failed to serialize ColabPackage: {}", e));
         }
+    };        
 
-        // Get the JSON representations
-        let loro_value = loro_doc.get_deep_value();
-        let json = loro_value.to_json_value();
-        let state_vv = loro_doc.state_vv();
-        let state_vv_json = match serde_json::to_value(&state_vv) {
-            Ok(val) => val,
-            Err(e) => {
-                error!("Failed to serialize state_vv for document '{}': {}", doc_uuid, e);
-                return Err(format!("Failed to serialize state_vv: {}", e));
-            }
-        };
-        let peer_map_json = match serde_json::to_value(&context.peer_map.clone()) {
-            Ok(val) => val,
-            Err(e) => {
-                error!("Failed to serialize peer_map for document '{}': {}", doc_uuid, e);
-                return Err(format!("Failed to serialize peer_map: {}", e));
-            }
-        };
+    let state_vv = loro_doc.state_vv();
+    let state_vv_json = match serde_json::to_value(&state_vv) {
+        Ok(val) => val,
+        Err(e) => {
+            error!("Failed to serialize state_vv for document '{}': {}", doc_uuid, e);
+            return Err(format!("Failed to serialize state_vv: {}", e));
+        }
+    };
+    let peer_map_json = match serde_json::to_value(&context.peer_map.clone()) {
+        Ok(val) => val,
+        Err(e) => {
+            error!("Failed to serialize peer_map for document '{}': {}", doc_uuid, e);
+            return Err(format!("Failed to serialize peer_map: {}", e));
+        }
+    };
 
-        // Figure out the type of ColabDocument
-        let doc_type: String = json.get("properties").and_then(|props| props.get("type")).and_then(|t| t.as_str()).map(|s| s.to_string()).ok_or_else(|| {
-            error!("Document '{}' is missing 'properties.type' field", doc_uuid);
-            "Document is missing 'properties.type' field".to_string()
-        })?;
-        
-        // Get database connection
-        let db = match dbcolab::get_db() {
-            Some(db) => db,
-            None => {
-                error!("Database not initialized, cannot save document: {}", doc_uuid);
-                return Err("Database not initialized".to_string());
-            }
-        };
+    // Figure out the type of ColabDocument
+    let doc_type: String = json.get("properties").and_then(|props| props.get("type")).and_then(|t| t.as_str()).map(|s| s.to_string()).ok_or_else(|| {
+        error!("Document '{}' is missing 'properties.type' field", doc_uuid);
+        "Document is missing 'properties.type' field".to_string()
+    })?;
+    
+    // Get database connection
+    let db = match dbcolab::get_db() {
+        Some(db) => db,
+        None => {
+            error!("Database not initialized, cannot save document: {}", doc_uuid);
+            return Err("Database not initialized".to_string());
+        }
+    };
 
-        // Save to database with incremented version
-        match db.update_colab_doc(&org, doc_uuid, &doc_type, doc_stream_uuid, blob, json, state_vv_json, peer_map_json, &by_prpl).await {
-            Ok(_) => {
-                info!("Statement updated successfully {}", doc_uuid);
-            }
-            Err(e) => {
-                error!("Failed to update statement '{}': {}", doc_uuid, e);
-                return Err(format!("Failed to update statement '{}': {}", doc_uuid, e));
+    // The saved content is scanned for policy violations once it is stored
+    let scan_json = if policy_scan_service::is_configured() { Some(json.clone()) } else { None };
+    // The references of the saved content are indexed, the sheets transcluding it notified, new blocks numbered
+    let transclusion_json = json.clone();
+    let content_sha256 = storage_service::content_hash(&json);
+
+    // Check the JSON against the snapshot written with it, divergences are logged and counted
+    if save_verification_service::is_enabled() {
+        save_verification_service::verify(&org, doc_uuid, &colab_package.snapshot, &json).await;
+    }
+
+    // Save to database with incremented version
+    match db.update_colab_doc(&org, doc_uuid, &doc_type, doc_stream_uuid, blob, json, &content_sha256, state_vv_json, peer_map_json, &by_prpl).await {
+        Ok(_) => {
+            info!("Statement updated successfully {}", doc_uuid);
+            save_policy_service::record_saved(doc_uuid);
+            save_retry_service::clear(doc_uuid);
+            journal_service::compact(&org, doc_uuid, &state_vv).await;
+            link_index_service::index(&org, doc_uuid, &transclusion_json).await;
+            save_status_service::record_saved(doc_uuid, state_vv);
+            archival_service::touch(&org, doc_uuid);
+            if let Some(scan_json) = scan_json {
+                policy_scan_service::schedule(&org, doc_uuid, scan_json);
             }
-        };        
-
-        // Clear the last updating peer in the context
-        context.last_updating_peer = None;
-
-        // Call the app service sync endpoint to notify about the update
-        if let Some(client) = app_service_client::get_app_service_client() {
-            let client = client.clone();
-            let org_clone = org.clone();
-            let doc_uuid_clone = doc_uuid.clone();
-            tokio::spawn(async move {
-                match client.sync_document(&org_clone, &doc_uuid_clone).await {
-                    Ok(_) => {
-                        info!("Successfully notified app service about document update: {}", doc_uuid_clone);
-                    }
-                    Err(e) => {
-                        error!("Failed to notify app service about document update '{}': {}", doc_uuid_clone, e);
-                    }
-                }
-            });
+            numbering_service::schedule(&org, doc_uuid, transclusion_json.clone());
+            transclusion_service::schedule(&org, doc_uuid, transclusion_json);
+        }
+        Err(e) => {
+            error!("Failed to update statement '{}': {}", doc_uuid, e);
+            save_status_service::record_error(doc_uuid, &format!("Database error: {}", e));
+            // Keep the changes around, the retry queue persists them once the database is back
+            let save = save_policy_service::DeferredSave { room: doc_id.clone(), snapshot: colab_package.snapshot, ctx: context.clone() };
+            save_retry_service::schedule(doc_uuid, save, &e.to_string());
+            return Err(format!("Failed to update statement '{}': {}", doc_uuid, e));
         }
+    };        
+
+    // Clear the last updating peer in the context
+    context.last_updating_peer = None;
+
+    // Call the app service sync endpoint to notify about the update
+    if let Some(client) = app_service_client::get_app_service_client() {
+        let client = client.clone();
+        let org_clone = org.clone();
+        let doc_uuid_clone = doc_uuid.clone();
+        tokio::spawn(async move {
+            match client.sync_document(&org_clone, &doc_uuid_clone).await {
+                Ok(_) => {
+                    info!("Successfully notified app service about document update: {}", doc_uuid_clone);
+                    // The app service writes the ACL rows of the document from the synced ACLs
+                    acl_cache_service::invalidate_document(&org_clone, doc_uuid_clone);
+                }
+                Err(e) => {
+                    error!("Failed to notify app service about document update '{}': {}", doc_uuid_clone, e);
+                }
+            }
+        });
+    }
 
-        return Ok(());
-    })
+    return Ok(());
 }
 
 /// Handle document updates
@@ -327,187 +642,267 @@ pub fn on_save_document(args: SaveDocArgs<DocContext>) -> Pin<Box<dyn Future<Out
 /// It should validate the updates.
 pub fn on_update(args: UpdateArgs<DocContext>) -> Pin<Box<dyn Future<Output = UpdatedDoc<DocContext>> + Send + 'static>> {
     Box::pin(async move {
-        
-        // Get the connection ID
+        let (org, room, ctx) = (args.workspace.to_string(), args.room.to_string(), args.ctx.clone());
         let conn_id = args.conn_id;
-        let room_id = args.room;
-        let org_id = args.workspace;
+        recording_service::record_updates(&room, conn_id, &args.updates).await;
+        let updated = match panic_guard_service::run("on_update", &org, &room, update_document(args)).await {
+            Ok(updated) => updated,
+            Err(_) => UpdatedDoc {
+                status: UpdateStatusCode::Unknown,
+                ctx,
+                doc: None,
+            },
+        };
+        recording_service::record_update_status(&room, conn_id, &updated.status).await;
+        updated
+    })
+}
+
+async fn update_document(args: UpdateArgs<DocContext>) -> UpdatedDoc<DocContext> {
+    
+    // Get the connection ID
+    let conn_id = args.conn_id;
+    let room_id = args.room;
+    let org_id = args.workspace;
+
+    // We're currently only interested in Loro updates
+    if args.crdt != CrdtType::Loro {
+        return UpdatedDoc {
+            status: UpdateStatusCode::Ok,
+            ctx: args.ctx,
+            doc: None,
+        };
+    }
 
-        // We're currently only interested in Loro updates
-        if args.crdt != CrdtType::Loro {
+    // Check if the UID of this peer matches the current UID of this connection
+    let mut doc_ctx = match args.ctx {
+        Some(ctx) => ctx,
+        None => {
+            error!("When updating document: No context available for document: {} ({} updates)", room_id, args.updates.len());
             return UpdatedDoc {
-                status: UpdateStatusCode::Ok,
-                ctx: args.ctx,
+                status: UpdateStatusCode::Unknown,
+                ctx: None,
                 doc: None,
             };
         }
+    };
 
-        // Check if the UID of this peer matches the current UID of this connection
-        let mut doc_ctx = match args.ctx {
+    // Figure out which user is behind this connection
+    let is_system_update = conn_id == 0;
+    let conn_ctx_cache = connctx::get_conn_ctx_cache();
+    let by_prpl: String;
+    let user_uid: Option<String>;
+    let user_prpls: Vec<String>;
+    if !is_system_update {
+        let conn_ctx= match conn_ctx_cache.get(&conn_id) {
             Some(ctx) => ctx,
             None => {
-                error!("When updating document: No context available for document: {} ({} updates)", room_id, args.updates.len());
+                error!("No connection context found for connection_id: {}", conn_id);
                 return UpdatedDoc {
-                    status: UpdateStatusCode::Unknown,
-                    ctx: None,
+                    status: UpdateStatusCode::PermissionDenied,
+                    ctx: Some(doc_ctx),
                     doc: None,
                 };
             }
         };
+        let uid: String = conn_ctx.uid.clone();
+        let conn_org = conn_ctx.org_id.clone();
+        info!("Received update from user: {} on doc: {}", uid, room_id);
 
-        // Figure out which user is behind this connection
-        let is_system_update = conn_id == 0;
-        let conn_ctx_cache = connctx::get_conn_ctx_cache();
-        let by_prpl: String;
-        let user_uid: Option<String>;
-        let user_prpls: Vec<String>;
-        if !is_system_update {
-            let conn_ctx= match conn_ctx_cache.get(&conn_id) {
-                Some(ctx) => ctx,
-                None => {
-                    error!("No connection context found for connection_id: {}", conn_id);
-                    return UpdatedDoc {
-                        status: UpdateStatusCode::PermissionDenied,
-                        ctx: Some(doc_ctx),
-                        doc: None,
-                    };
-                }
-            };
-            let uid: String = conn_ctx.uid.clone();
-            let conn_org = conn_ctx.org_id.clone();
-            info!("Received update from user: {} on doc: {}", uid, room_id);
-
-            let user_ctx = match userctx::get_user_ctx_from_cache(&uid) {
-                Some(ctx) => ctx,
-                None => {
-                    error!("Unable to load user context for uid {}", uid);
-                    return UpdatedDoc {
-                        status: UpdateStatusCode::PermissionDenied,
-                        ctx: Some(doc_ctx),
-                        doc: None,
-                    };
-                }
+        // The write permission may have been revoked since the connection joined
+        if !connctx::is_writable(conn_id, &room_id) {
+            warn!("Rejected update by user {} on document {}, the write permission was revoked", uid, room_id);
+            return UpdatedDoc {
+                status: UpdateStatusCode::PermissionDenied,
+                ctx: Some(doc_ctx),
+                doc: None,
             };
-            if !is_org_member(&user_ctx.principals, &conn_org) {
-                error!("User {} does not have access to organization {}", uid, conn_org);
+        }
+
+        let user_ctx = match userctx::get_user_ctx_from_cache(&uid) {
+            Some(ctx) => ctx,
+            None => {
+                error!("Unable to load user context for uid {}", uid);
                 return UpdatedDoc {
                     status: UpdateStatusCode::PermissionDenied,
                     ctx: Some(doc_ctx),
                     doc: None,
                 };
             }
-            user_prpls = user_ctx.principals.clone();
-            user_uid = Some(uid.clone());
-            by_prpl = match user_ctx.get_user_principal(&org_id) {
-                Some(prpl) => prpl,
-                None => {
-                    error!("No principal found for user {} in organization {}", uid, org_id);
-                    return UpdatedDoc {
-                        status: UpdateStatusCode::PermissionDenied,
-                        ctx: Some(doc_ctx),
-                        doc: None,
-                    };
-                }
+        };
+        if !is_org_member(&user_ctx.principals, &conn_org) {
+            error!("User {} does not have access to organization {}", uid, conn_org);
+            userctx::spawn_refresh(&uid);
+            return UpdatedDoc {
+                status: UpdateStatusCode::PermissionDenied,
+                ctx: Some(doc_ctx),
+                doc: None,
             };
-        } else {
-            info!("Received update from system");
-            by_prpl = "s/colabri-system".to_string();
-            user_prpls = vec!["s/colabri-system".to_string()];
-            user_uid = None;
-        }    
-
-        // Ensure we have a loro document
-        let loro_doc = match args.doc {
-            Some(ref doc) => doc,
+        }
+        user_prpls = user_ctx.principals.clone();
+        user_uid = Some(uid.clone());
+        by_prpl = match user_ctx.get_user_principal(&org_id) {
+            Some(prpl) => prpl,
             None => {
-                error!("No LoroDoc available while processing update for doc: {}", room_id);
+                error!("No principal found for user {} in organization {}", uid, org_id);
                 return UpdatedDoc {
-                    status: UpdateStatusCode::Unknown,
+                    status: UpdateStatusCode::PermissionDenied,
                     ctx: Some(doc_ctx),
                     doc: None,
                 };
             }
         };
-
-        // Get the initial peers in the document
-        let init_version_vector = loro_doc.oplog_vv();
-
-        // Apply the updates
-        let _ = loro_doc.import_batch(&args.updates);
-
-        // Get the updated version vector
-        let updated_version_vector = loro_doc.oplog_vv();
-
-        // Figure out which peer did the update by comparing the version vectors
-        let mut updating_peer: Option<u64> = None;
-        for peer_id in updated_version_vector.keys().cloned() {
-            let updated_version = updated_version_vector.get(&peer_id).unwrap();
-            let init_version = init_version_vector.get(&peer_id).cloned().unwrap_or(0);
-            if updated_version > &init_version {
-                updating_peer = Some(peer_id);
-                break;
-            }
+    } else {
+        info!("Received update from system");
+        by_prpl = "s/colabri-system".to_string();
+        user_prpls = vec!["s/colabri-system".to_string()];
+        user_uid = None;
+    }    
+
+    // Ensure we have a loro document
+    let loro_doc = match args.doc {
+        Some(ref doc) => doc,
+        None => {
+            error!("No LoroDoc available while processing update for doc: {}", room_id);
+            return UpdatedDoc {
+                status: UpdateStatusCode::Unknown,
+                ctx: Some(doc_ctx),
+                doc: None,
+            };
         }
+    };
 
-        // Make sure we found the updating peer
-        let updating_peer_id = match updating_peer {
-            Some(pid) => pid,
-            None => {
-                info!("Update resulted in no operations for doc: {}", room_id);
-                return UpdatedDoc {
-                    status: UpdateStatusCode::Ok,
-                    ctx: Some(doc_ctx),
-                    doc: Some(loro_doc.clone()),
-                };
-            }
-        };
-
-        // Check if this peer is already known in the peer_map in the document context
-        let peer_map = &mut doc_ctx.peer_map;
-        let ok_peer = match peer_map.get(&updating_peer_id) {
-            Some(found_prpl) => {
-                // Check if this principal is one of the user principals
-                if !user_prpls.contains(found_prpl) {
-                    false
-                } else {
-                    true
-                }
-            }
-            None => {
-                // No principal found for this peer, that's fine just add it.
-                info!("Adding new peer {} for prpl {} in document {}", updating_peer_id, by_prpl, room_id);
-                peer_map.insert(updating_peer_id, by_prpl.clone());
-                true
-            },
+    // Reject updates by users to documents that were made read-only or are held for review
+    if !is_system_update && (doc_ctx.settings.read_only || doc_ctx.settings.maintenance.is_some()) {
+        warn!("Rejected update by '{}' on document {}, the document is read-only or in maintenance", by_prpl, room_id);
+        return UpdatedDoc {
+            status: UpdateStatusCode::PermissionDenied,
+            ctx: Some(doc_ctx),
+            doc: None,
         };
+    }
 
-        // If the peer was not ok, reject the update
-        if !ok_peer {
-            if let Some(uid) = user_uid {
-                error!("User {} attempted to update document {} with invalid peer {}", uid, room_id, updating_peer_id);
-            } else {
-                error!("System attempted to update document {} with invalid peer {}", room_id, updating_peer_id);
-            }
+    // Reject updates to blocks that are frozen by an approval round
+    if !is_system_update {
+        if let Err(e) = approval_round_service::check_updates_allowed(loro_doc, &args.updates, &user_prpls) {
+            warn!("Rejected update by '{}' on document {}: {}", by_prpl, room_id, e);
             return UpdatedDoc {
                 status: UpdateStatusCode::PermissionDenied,
                 ctx: Some(doc_ctx),
                 doc: None,
             };
         }
+    }
+
+    // Reject updates that would push the document beyond the limits of the organization
+    let limits = limits_service::with_document_settings(limits_service::get_limits(&org_id).await, &doc_ctx.settings);
+    if let Err(violation) = limits_service::check_updates(loro_doc, &args.updates, &limits) {
+        warn!("Rejected update by '{}' on document {}: {}", by_prpl, room_id, violation);
+        return UpdatedDoc {
+            status: UpdateStatusCode::PayloadTooLarge,
+            ctx: Some(doc_ctx),
+            doc: None,
+        };
+    }
+
+    // Get the initial peers in the document
+    let init_version_vector = loro_doc.oplog_vv();
+
+    // Apply the updates
+    let _ = loro_doc.import_batch(&args.updates);
+    save_policy_service::record_update(doc_ctx.doc_id);
+
+    // Get the updated version vector
+    let updated_version_vector = loro_doc.oplog_vv();
+
+    // Figure out which peer did the update by comparing the version vectors
+    let mut updating_peer: Option<u64> = None;
+    for peer_id in updated_version_vector.keys().cloned() {
+        let updated_version = updated_version_vector.get(&peer_id).unwrap();
+        let init_version = init_version_vector.get(&peer_id).cloned().unwrap_or(0);
+        if updated_version > &init_version {
+            updating_peer = Some(peer_id);
+            break;
+        }
+    }
 
-        // Update the last updating peer in the document context
-        info!("Prpl {} updated document {} with peer {}", by_prpl, room_id, updating_peer_id);
-        doc_ctx.last_updating_peer = Some(updating_peer_id);
+    // Make sure we found the updating peer
+    let updating_peer_id = match updating_peer {
+        Some(pid) => pid,
+        None => {
+            info!("Update resulted in no operations for doc: {}", room_id);
+            return UpdatedDoc {
+                status: UpdateStatusCode::Ok,
+                ctx: Some(doc_ctx),
+                doc: Some(loro_doc.clone()),
+            };
+        }
+    };
 
-        // Check the actual operations in the updates to see if there are any that we might want to reject.
-        //let updates = loro_doc.export_json_updates_without_peer_compression(&init_version_vector, &updated_version_vector);
-        info!("TODO: Implement operation level validation for document updates. Currently accepting all updates by '{}' for document '{}' with owner '{}'", by_prpl, room_id, doc_ctx.doc_owner);
+    // Check if this peer is already known in the peer_map in the document context
+    let peer_map = &mut doc_ctx.peer_map;
+    let ok_peer = match peer_map.get(&updating_peer_id) {
+        Some(found_prpl) => {
+            // Check if this principal is one of the user principals
+            if !user_prpls.contains(found_prpl) {
+                false
+            } else {
+                true
+            }
+        }
+        None => {
+            // No principal found for this peer, that's fine just add it.
+            info!("Adding new peer {} for prpl {} in document {}", updating_peer_id, by_prpl, room_id);
+            peer_map.insert(updating_peer_id, by_prpl.clone());
+            true
+        },
+    };
 
-        // Return OK
+    // If the peer was not ok, reject the update
+    if !ok_peer {
+        if let Some(uid) = user_uid {
+            error!("User {} attempted to update document {} with invalid peer {}", uid, room_id, updating_peer_id);
+            // The principals of the user may be stale, the next attempt uses refreshed ones
+            userctx::spawn_refresh(&uid);
+        } else {
+            error!("System attempted to update document {} with invalid peer {}", room_id, updating_peer_id);
+        }
         return UpdatedDoc {
-            status: UpdateStatusCode::Ok,
+            status: UpdateStatusCode::PermissionDenied,
             ctx: Some(doc_ctx),
-            doc: Some(loro_doc.clone()),
+            doc: None,
         };
-    })
-}
\ No newline at end of file
+    }
+
+    // Update the last updating peer in the document context
+    info!("Prpl {} updated document {} with peer {}", by_prpl, room_id, updating_peer_id);
+    doc_ctx.last_updating_peer = Some(updating_peer_id);
+
+    // Check the actual operations in the updates to see if there are any that we might want to reject.
+    //let updates = loro_doc.export_json_updates_without_peer_compression(&init_version_vector, &updated_version_vector);
+    info!("TODO: Implement operation level validation for document updates. Currently accepting all updates by '{}' for document '{}' with owner '{}'", by_prpl, room_id, doc_ctx.doc_owner);
+
+    analytics_service::record_update(&org_id, doc_ctx.doc_id);
+
+    // Journal the accepted updates before they are acknowledged, so a crash before the next save loses nothing.
+    // Updates that can't be journaled are refused, the client keeps them and sends them again.
+    // The journal replays into the sheet, block rooms of lazily loaded sheets are left out.
+    let counter_end = updated_version_vector.get(&updating_peer_id).copied().unwrap_or(0);
+    if doc_ctx.block_id.is_none() {
+        if let Err(e) = journal_service::append(&org_id, doc_ctx.doc_id, updating_peer_id, counter_end, &by_prpl, &args.updates).await {
+            error!("Refusing update by '{}' on document {}, it could not be journaled: {}", by_prpl, room_id, e);
+            return UpdatedDoc {
+                status: UpdateStatusCode::Unknown,
+                ctx: Some(doc_ctx),
+                doc: None,
+            };
+        }
+    }
+
+    // Return OK
+    return UpdatedDoc {
+        status: UpdateStatusCode::Ok,
+        ctx: Some(doc_ctx),
+        doc: Some(loro_doc.clone()),
+    };
+}
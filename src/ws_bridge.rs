@@ -1,77 +1,236 @@
 use axum::{
-    extract::Request,
+    extract::{Path, RawQuery, Request},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
+    routing::get,
+    Router,
 };
-use hyper::upgrade::Upgraded;
 use hyper_util::rt::TokioIo;
-use std::sync::Arc;
-use tokio::io::{AsyncRead, AsyncWrite};
-use tracing::{debug, error};
-
-/// Bridge handler that extracts the upgraded connection and passes it to loro-websocket-server
-pub async fn loro_websocket_bridge(
-    req: Request,
-    registry: Arc<loro_websocket_server::HubRegistry>,
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, error, warn};
+use crate::{config, ws_deflate};
+
+// WebSocket connections on the main HTTP port, at `/ws/{org}` and `/ws/{org}/{doc}`.
+// loro-websocket-server only serves connections it accepts on its own listener, so the bridge passes
+// the handshake on to that listener over the loopback interface and answers the client with its response.
+// Once both sides upgraded, the bridge copies the bytes both ways: the client and the WebSocket server
+// speak a single WebSocket session, framed and buffered once. The WebSocket server authenticates the
+// client as usual and its refusal is answered to the client. Clients that offer permessage-deflate get it
+// from the bridge, the WebSocket server doesn't compress (see ws_deflate).
+//
+// The listener on WEBSOCKET_PORT is still needed: it has to run and be reachable from this process on
+// HOST (or loopback when HOST is unspecified), only exposing it beyond the pod is optional. Attaching the
+// upgraded connections to the HubRegistry in-process needs loro-websocket-server to serve a connection it
+// didn't accept itself, it has no entry point for that yet. The document in the path isn't used by the
+// bridge, rooms are still joined through the protocol, it only helps routing at the ingress.
+
+// Headers of the handshake the WebSocket server looks at
+const FORWARDED_HEADERS: [&str; 9] = [
+    "sec-websocket-key",
+    "sec-websocket-version",
+    "sec-websocket-protocol",
+    "authorization",
+    "cookie",
+    "origin",
+    "user-agent",
+    "x-colabri-client-version",
+    "x-colabri-schema-version",
+];
+
+// Headers of the response to the handshake that are not passed on to the client
+const DROPPED_RESPONSE_HEADERS: [&str; 3] = ["content-length", "transfer-encoding", "sec-websocket-extensions"];
+
+// Largest response head of the WebSocket server to a handshake
+const MAX_RESPONSE_HEAD: usize = 16 * 1024;
+
+// Characters of an org that are sent to the WebSocket server as they are, the others are percent-encoded
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+/// Routes of the WebSocket bridge
+pub fn create_ws_bridge_routes() -> Router {
+    Router::new()
+        .route("/ws/:org_id", get(ws_bridge))
+        .route("/ws/:org_id/:doc_id", get(ws_bridge_doc))
+}
+
+async fn ws_bridge(
+    Path(org_id): Path<String>,
+    RawQuery(query): RawQuery,
+    request: Request,
+) -> Response {
+    bridge(&org_id, query, request).await
+}
+
+async fn ws_bridge_doc(
+    Path((org_id, _doc_id)): Path<(String, String)>,
+    RawQuery(query): RawQuery,
+    request: Request,
 ) -> Response {
-    // Check if this is a WebSocket upgrade request
-    if !is_upgrade_request(&req) {
-        return (
-            axum::http::StatusCode::BAD_REQUEST,
-            "Expected WebSocket upgrade request",
-        )
-            .into_response();
+    bridge(&org_id, query, request).await
+}
+
+// The WebSocket listener as reached from this process, as host and port
+fn upstream_authority() -> String {
+    let config = config::get_config();
+    let host = match config.host.as_str() {
+        "0.0.0.0" => "127.0.0.1".to_string(),
+        "::" | "[::]" => "[::1]".to_string(),
+        host if host.contains(':') && !host.starts_with('[') => format!("[{}]", host),
+        host => host.to_string(),
+    };
+    format!("{}:{}", host, config.websocket_port())
+}
+
+// The path of the handshake, the org is decoded from the path of the client and encoded again,
+// so it stays a single segment whatever it contains
+fn upstream_path(org_id: &str, query: Option<String>) -> String {
+    let org_id = utf8_percent_encode(org_id, PATH_SEGMENT);
+    match query {
+        Some(query) => format!("/{}?{}", org_id, query),
+        None => format!("/{}", org_id),
+    }
+}
+
+fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    let upgrade = headers
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    upgrade && headers.contains_key(header::SEC_WEBSOCKET_KEY)
+}
+
+// The handshake as sent to the WebSocket server
+fn handshake_request(path: &str, authority: &str, headers: &HeaderMap) -> Vec<u8> {
+    let mut head = format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n", path, authority);
+    for name in FORWARDED_HEADERS {
+        for value in headers.get_all(name) {
+            if let Ok(value) = value.to_str() {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+    }
+    head.push_str("\r\n");
+    head.into_bytes()
+}
+
+// The response of the WebSocket server to the handshake
+struct HandshakeResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    // Bytes the WebSocket server sent after the response head, already part of the session
+    rest: Vec<u8>,
+}
+
+// Read the response head of the WebSocket server
+async fn read_handshake_response(upstream: &mut TcpStream) -> Result<HandshakeResponse, String> {
+    let mut buf = Vec::with_capacity(1024);
+    let head_end = loop {
+        if let Some(index) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            break index;
+        }
+        if buf.len() > MAX_RESPONSE_HEAD {
+            return Err(format!("Response head exceeds {} bytes", MAX_RESPONSE_HEAD));
+        }
+        let mut chunk = [0u8; 1024];
+        let n = upstream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("Connection closed during the handshake".to_string());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = std::str::from_utf8(&buf[..head_end]).map_err(|_| "Response head is not UTF-8".to_string())?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .ok_or_else(|| "Invalid status line".to_string())?;
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.trim().as_bytes()), HeaderValue::from_str(value.trim())) {
+            headers.append(name, value);
+        }
+    }
+    Ok(HandshakeResponse { status, headers, rest: buf[head_end + 4..].to_vec() })
+}
+
+async fn bridge(org_id: &str, query: Option<String>, mut request: Request) -> Response {
+    if !is_websocket_upgrade(request.headers()) {
+        return (StatusCode::BAD_REQUEST, "Expected a WebSocket upgrade").into_response();
     }
 
-    // Perform the WebSocket upgrade
-    let (response, upgraded) = match hyper::upgrade::on(req).await {
-        Ok(upgraded) => {
-            let response = Response::builder()
-                .status(hyper::StatusCode::SWITCHING_PROTOCOLS)
-                .header(hyper::header::UPGRADE, "websocket")
-                .header(hyper::header::CONNECTION, "upgrade")
-                .body(axum::body::Body::empty())
-                .unwrap();
-            (response, upgraded)
+    let compress = ws_deflate::accepts_offer(
+        request.headers().get_all(header::SEC_WEBSOCKET_EXTENSIONS).iter().filter_map(|value| value.to_str().ok()),
+    );
+
+    // Pass the handshake on first, so a refused handshake is answered with its own status
+    let authority = upstream_authority();
+    let mut upstream = match TcpStream::connect(&authority).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            error!("Failed to reach the WebSocket server at {} for the bridge: {}", authority, e);
+            return (StatusCode::BAD_GATEWAY, "WebSocket server unavailable").into_response();
         }
+    };
+    let handshake = handshake_request(&upstream_path(org_id, query), &authority, request.headers());
+    if let Err(e) = upstream.write_all(&handshake).await {
+        error!("Failed to pass the handshake on to the WebSocket server: {}", e);
+        return (StatusCode::BAD_GATEWAY, "WebSocket server unavailable").into_response();
+    }
+    let response = match read_handshake_response(&mut upstream).await {
+        Ok(response) => response,
         Err(e) => {
-            error!("Upgrade error: {}", e);
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to upgrade connection",
-            )
-                .into_response();
+            error!("Invalid handshake response of the WebSocket server: {}", e);
+            return (StatusCode::BAD_GATEWAY, "WebSocket server unavailable").into_response();
         }
     };
+    if response.status != StatusCode::SWITCHING_PROTOCOLS {
+        debug!("WebSocket server refused the bridged handshake on organization {}: {}", org_id, response.status);
+        return (response.status, "WebSocket handshake refused").into_response();
+    }
 
-    // Spawn a task to handle the upgraded connection with loro-websocket-server
+    // Answer the client with the response of the WebSocket server, then join both connections
+    let on_upgrade = hyper::upgrade::on(&mut request);
+    let org = org_id.to_string();
     tokio::spawn(async move {
-        let io = TokioIo::new(upgraded);
-        // Convert to a type compatible with tokio-tungstenite
-        if let Err(e) = handle_loro_connection(io, registry).await {
-            error!("Error handling loro connection: {}", e);
+        let mut client = match on_upgrade.await {
+            Ok(upgraded) => TokioIo::new(upgraded),
+            Err(e) => {
+                warn!("Failed to upgrade the bridged connection on organization {}: {}", org, e);
+                return;
+            }
+        };
+        if compress {
+            let (sent, received) = ws_deflate::relay(client, upstream, response.rest).await;
+            debug!(
+                "Compressed connection on organization {} ended, saved {} of {} bytes sent and {} of {} bytes received",
+                org, sent.saved(), sent.raw, received.saved(), received.raw
+            );
+            return;
+        }
+        if !response.rest.is_empty() && client.write_all(&response.rest).await.is_err() {
+            return;
+        }
+        if let Err(e) = tokio::io::copy_bidirectional(&mut client, &mut upstream).await {
+            debug!("Bridged connection on organization {} ended: {}", org, e);
         }
     });
 
-    response
-}
-
-fn is_upgrade_request(req: &Request) -> bool {
-    req.headers()
-        .get(hyper::header::UPGRADE)
-        .and_then(|v| v.to_str().ok())
-        .map(|v| v.eq_ignore_ascii_case("websocket"))
-        .unwrap_or(false)
-}
-
-async fn handle_loro_connection<T>(
-    _io: T,
-    _registry: Arc<loro_websocket_server::HubRegistry>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
-where
-    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
-{
-    // TODO: This needs to be adapted based on loro-websocket-server's API
-    // The challenge is that handle_conn expects a TcpStream, not a generic stream
-    debug!("Loro connection handler called");
-    Ok(())
+    let mut upgrade = StatusCode::SWITCHING_PROTOCOLS.into_response();
+    for (name, value) in response.headers.iter() {
+        if !DROPPED_RESPONSE_HEADERS.contains(&name.as_str()) {
+            upgrade.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+    if compress {
+        upgrade.headers_mut().insert(header::SEC_WEBSOCKET_EXTENSIONS, HeaderValue::from_static(ws_deflate::NEGOTIATED_EXTENSION));
+    }
+    upgrade
 }
@@ -0,0 +1,565 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tracing::debug;
+use crate::config;
+
+// permessage-deflate (RFC 7692) for the WebSocket connections of the bridge.
+// loro-websocket-server doesn't negotiate extensions, so the bridge negotiates the extension with the client
+// and talks plain WebSocket to the server: messages from the server are compressed on the way out, messages
+// from the client are decompressed on the way in. Messages are compressed without context takeover, so a
+// connection only keeps the window of the client's messages. The bytes before and after compression are
+// counted for the metrics.
+
+// The extension as answered to the client
+pub const NEGOTIATED_EXTENSION: &str = "permessage-deflate; server_no_context_takeover";
+
+// Largest message the client may send, the limit of the WebSocket server
+const MAX_MESSAGE_BYTES: usize = 64 << 20;
+
+// The empty stored block ending a sync flush, left out of the messages on the wire
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const FIN: u8 = 0x80;
+const RSV1: u8 = 0x40;
+
+static COMPRESSED_CONNECTIONS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static SENT_BYTES_TOTAL: AtomicU64 = AtomicU64::new(0);
+static SENT_WIRE_BYTES_TOTAL: AtomicU64 = AtomicU64::new(0);
+static RECEIVED_BYTES_TOTAL: AtomicU64 = AtomicU64::new(0);
+static RECEIVED_WIRE_BYTES_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Connections that negotiated permessage-deflate on this instance
+pub fn compressed_connections_total() -> u64 {
+    COMPRESSED_CONNECTIONS_TOTAL.load(Ordering::Relaxed)
+}
+
+/// Payload bytes of the messages sent to the compressed connections, before and after compression
+pub fn sent_bytes_total() -> ByteCount {
+    ByteCount { raw: SENT_BYTES_TOTAL.load(Ordering::Relaxed), wire: SENT_WIRE_BYTES_TOTAL.load(Ordering::Relaxed) }
+}
+
+/// Payload bytes of the messages received from the compressed connections, after and before decompression
+pub fn received_bytes_total() -> ByteCount {
+    ByteCount { raw: RECEIVED_BYTES_TOTAL.load(Ordering::Relaxed), wire: RECEIVED_WIRE_BYTES_TOTAL.load(Ordering::Relaxed) }
+}
+
+/// Payload bytes of the messages in one direction of a connection, uncompressed and as sent on the wire
+#[derive(Default)]
+pub struct ByteCount {
+    pub raw: u64,
+    pub wire: u64,
+}
+
+impl ByteCount {
+    /// Bytes compression kept off the wire
+    pub fn saved(&self) -> u64 {
+        self.raw.saturating_sub(self.wire)
+    }
+
+    fn add(&mut self, raw: usize, wire: usize) {
+        self.raw += raw as u64;
+        self.wire += wire as u64;
+    }
+}
+
+/// Whether one of the offers of the client's Sec-WebSocket-Extensions headers can be accepted
+pub fn accepts_offer<'a>(offers: impl Iterator<Item = &'a str>) -> bool {
+    if !config::get_config().ws_compression.unwrap_or(true) {
+        return false;
+    }
+    offers
+        .flat_map(|header| header.split(','))
+        .any(acceptable_offer)
+}
+
+// An offer is accepted when it only asks for what the server does. Compressing with a smaller window
+// than 15 bits isn't supported, those offers are declined.
+fn acceptable_offer(offer: &str) -> bool {
+    let mut params = offer.split(';').map(str::trim);
+    if params.next() != Some("permessage-deflate") {
+        return false;
+    }
+    let mut seen: Vec<&str> = Vec::new();
+    for param in params {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+        if seen.contains(&name) {
+            return false;
+        }
+        seen.push(name);
+        let acceptable = match (name, value) {
+            ("server_no_context_takeover" | "client_no_context_takeover", None) => true,
+            ("client_max_window_bits", None) => true,
+            ("client_max_window_bits", Some(bits)) => bits.parse::<u8>().is_ok_and(|bits| (8..=15).contains(&bits)),
+            ("server_max_window_bits", Some(bits)) => bits == "15",
+            _ => false,
+        };
+        if !acceptable {
+            return false;
+        }
+    }
+    true
+}
+
+// A frame as read from a connection
+struct Frame {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    mask: Option<[u8; 4]>,
+    payload: Vec<u8>,
+}
+
+impl Frame {
+    fn is_control(&self) -> bool {
+        self.opcode & 0x8 != 0
+    }
+}
+
+// Read a frame, None when the connection ended
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R, max_payload: usize) -> Result<Option<Frame>, String> {
+    let mut head = [0u8; 2];
+    match reader.read_exact(&mut head).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    }
+    let len = match head[1] & 0x7f {
+        126 => reader.read_u16().await.map_err(|e| e.to_string())? as u64,
+        127 => reader.read_u64().await.map_err(|e| e.to_string())?,
+        len => len as u64,
+    };
+    if len > max_payload as u64 {
+        return Err(format!("Frame of {} bytes exceeds the limit of {} bytes", len, max_payload));
+    }
+    let mask = if head[1] & 0x80 != 0 {
+        let mut mask = [0u8; 4];
+        reader.read_exact(&mut mask).await.map_err(|e| e.to_string())?;
+        Some(mask)
+    } else {
+        None
+    };
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload).await.map_err(|e| e.to_string())?;
+    Ok(Some(Frame { fin: head[0] & FIN != 0, rsv1: head[0] & RSV1 != 0, opcode: head[0] & 0x0f, mask, payload }))
+}
+
+// Write a frame, the payload is masked with the mask when there is one
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, fin: bool, rsv1: bool, opcode: u8, mask: Option<[u8; 4]>, payload: &[u8]) -> Result<(), String> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(if fin { FIN } else { 0 } | if rsv1 { RSV1 } else { 0 } | opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => frame.push(mask_bit | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    writer.write_all(&frame).await.map_err(|e| e.to_string())
+}
+
+fn unmask(payload: &mut [u8], mask: Option<[u8; 4]>) {
+    if let Some(mask) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+}
+
+// Compress a message on its own, without the tail of the sync flush
+fn deflate(compress: &mut Compress, data: &[u8]) -> Result<Vec<u8>, String> {
+    compress.reset();
+    let mut out = Vec::with_capacity(data.len() / 2 + 64);
+    loop {
+        if out.len() == out.capacity() {
+            out.reserve(out.capacity());
+        }
+        let consumed = compress.total_in() as usize;
+        compress.compress_vec(&data[consumed..], &mut out, FlushCompress::Sync).map_err(|e| e.to_string())?;
+        if compress.total_in() as usize == data.len() && out.len() < out.capacity() {
+            break;
+        }
+    }
+    if out.ends_with(&DEFLATE_TAIL) {
+        out.truncate(out.len() - DEFLATE_TAIL.len());
+    }
+    Ok(out)
+}
+
+// Decompress a message, the window of earlier messages is kept in the decompressor
+fn inflate(decompress: &mut Decompress, data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut input = Vec::with_capacity(data.len() + DEFLATE_TAIL.len());
+    input.extend_from_slice(data);
+    input.extend_from_slice(&DEFLATE_TAIL);
+    let start = decompress.total_in();
+    let mut out = Vec::with_capacity((data.len() * 4).clamp(1024, MAX_MESSAGE_BYTES));
+    loop {
+        if out.len() == out.capacity() {
+            if out.len() >= MAX_MESSAGE_BYTES {
+                return Err(format!("Decompressed message exceeds the limit of {} bytes", MAX_MESSAGE_BYTES));
+            }
+            out.reserve(out.capacity());
+        }
+        let (consumed, produced) = ((decompress.total_in() - start) as usize, out.len());
+        let status = decompress.decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync).map_err(|e| e.to_string())?;
+        let done = (decompress.total_in() - start) as usize == input.len() && out.len() < out.capacity();
+        let stalled = (decompress.total_in() - start) as usize == consumed && out.len() == produced;
+        if done || stalled || status == Status::StreamEnd {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+/// Pass the messages of the client on to the server, decompressing the compressed ones.
+/// Frames stay masked with the client's mask.
+async fn inbound<R, W>(client: &mut R, server: &mut W, received: &mut ByteCount) -> Result<(), String>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut decompress = Decompress::new(false);
+    // The opcode, mask and compressed payload of the compressed message being received
+    let mut message: Option<(u8, Option<[u8; 4]>, Vec<u8>)> = None;
+    // Whether an uncompressed message is being received
+    let mut fragmented = false;
+    while let Some(mut frame) = read_frame(client, MAX_MESSAGE_BYTES).await? {
+        let continuation = frame.opcode == OPCODE_CONTINUATION;
+        if frame.rsv1 && (frame.is_control() || continuation) {
+            return Err(format!("Unexpected RSV1 bit on a frame with opcode {}", frame.opcode));
+        }
+        if frame.is_control() {
+            write_frame(server, frame.fin, false, frame.opcode, frame.mask, &unmasked(&mut frame)).await?;
+            continue;
+        }
+        if continuation != (fragmented || message.is_some()) {
+            return Err(if continuation {
+                "Continuation frame outside of a message".to_string()
+            } else {
+                "New message before the end of the previous one".to_string()
+            });
+        }
+
+        // Messages without RSV1 on their first frame are passed on as they are
+        let is_compressed = if continuation { message.is_some() } else { frame.rsv1 };
+        if !is_compressed {
+            fragmented = !frame.fin;
+            let payload = unmasked(&mut frame);
+            received.add(payload.len(), payload.len());
+            RECEIVED_BYTES_TOTAL.fetch_add(payload.len() as u64, Ordering::Relaxed);
+            RECEIVED_WIRE_BYTES_TOTAL.fetch_add(payload.len() as u64, Ordering::Relaxed);
+            write_frame(server, frame.fin, false, frame.opcode, frame.mask, &payload).await?;
+            continue;
+        }
+
+        let mask = frame.mask;
+        let fin = frame.fin;
+        let opcode = frame.opcode;
+        let payload = unmasked(&mut frame);
+        let (_, _, buffered) = message.get_or_insert_with(|| (opcode, mask, Vec::new()));
+        if buffered.len() + payload.len() > MAX_MESSAGE_BYTES {
+            return Err(format!("Message exceeds the limit of {} bytes", MAX_MESSAGE_BYTES));
+        }
+        buffered.extend_from_slice(&payload);
+        if !fin {
+            continue;
+        }
+        let Some((opcode, mask, compressed)) = message.take() else {
+            continue;
+        };
+        let data = inflate(&mut decompress, &compressed)?;
+        received.add(data.len(), compressed.len());
+        RECEIVED_BYTES_TOTAL.fetch_add(data.len() as u64, Ordering::Relaxed);
+        RECEIVED_WIRE_BYTES_TOTAL.fetch_add(compressed.len() as u64, Ordering::Relaxed);
+        write_frame(server, true, false, opcode, mask, &data).await?;
+    }
+    Ok(())
+}
+
+/// Pass the messages of the server on to the client, compressing the ones of at least min_bytes
+async fn outbound<R, W>(server: &mut R, client: &mut W, min_bytes: usize, sent: &mut ByteCount) -> Result<(), String>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut compress = Compress::new(Compression::default(), false);
+    // The opcode and payload of the message being received
+    let mut message: Option<(u8, Vec<u8>)> = None;
+    while let Some(mut frame) = read_frame(server, usize::MAX).await? {
+        if frame.is_control() {
+            write_frame(client, frame.fin, false, frame.opcode, None, &unmasked(&mut frame)).await?;
+            continue;
+        }
+
+        let fin = frame.fin;
+        let opcode = frame.opcode;
+        let payload = unmasked(&mut frame);
+        let (_, buffered) = message.get_or_insert_with(|| (opcode, Vec::new()));
+        buffered.extend_from_slice(&payload);
+        if !fin {
+            continue;
+        }
+        let Some((opcode, data)) = message.take() else {
+            continue;
+        };
+        let compressed = if data.len() >= min_bytes {
+            Some(deflate(&mut compress, &data)?).filter(|compressed| compressed.len() < data.len())
+        } else {
+            None
+        };
+        let wire = compressed.as_ref().map_or(data.len(), Vec::len);
+        sent.add(data.len(), wire);
+        SENT_BYTES_TOTAL.fetch_add(data.len() as u64, Ordering::Relaxed);
+        SENT_WIRE_BYTES_TOTAL.fetch_add(wire as u64, Ordering::Relaxed);
+        match compressed {
+            Some(compressed) => write_frame(client, true, true, opcode, None, &compressed).await?,
+            None => write_frame(client, true, false, opcode, None, &data).await?,
+        }
+    }
+    Ok(())
+}
+
+fn unmasked(frame: &mut Frame) -> Vec<u8> {
+    let mut payload = std::mem::take(&mut frame.payload);
+    unmask(&mut payload, frame.mask);
+    payload
+}
+
+/// Relay a connection that negotiated the extension until either side closes it.
+/// `rest` holds the bytes the server sent after its handshake response.
+/// Returns the bytes sent and received on the connection.
+pub async fn relay<C>(client: C, server: TcpStream, rest: Vec<u8>) -> (ByteCount, ByteCount)
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    COMPRESSED_CONNECTIONS_TOTAL.fetch_add(1, Ordering::Relaxed);
+    let min_bytes = config::get_config().ws_compression_min_bytes.unwrap_or(256);
+    let (client_read, mut client_write) = tokio::io::split(client);
+    let (server_read, mut server_write) = server.into_split();
+    let mut client_read = BufReader::new(client_read);
+    let mut server_read = std::io::Cursor::new(rest).chain(BufReader::new(server_read));
+
+    let mut sent = ByteCount::default();
+    let mut received = ByteCount::default();
+    let result = tokio::select! {
+        result = inbound(&mut client_read, &mut server_write, &mut received) => result,
+        result = outbound(&mut server_read, &mut client_write, min_bytes, &mut sent) => result,
+    };
+    if let Err(e) = result {
+        debug!("Compressed WebSocket connection ended: {}", e);
+    }
+    (sent, received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASK: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+    // A frame as sent by a client, masked
+    fn client_frame(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![first_byte];
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&MASK);
+        frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ MASK[i % 4]));
+        frame
+    }
+
+    // A frame as sent by the server, unmasked
+    fn server_frame(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![first_byte];
+        match payload.len() {
+            len if len < 126 => frame.push(len as u8),
+            len => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    // The frames written to a connection, unmasked
+    async fn frames_of(mut bytes: &[u8]) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        while let Some(mut frame) = read_frame(&mut bytes, usize::MAX).await.unwrap() {
+            frame.payload = unmasked(&mut frame);
+            frames.push(frame);
+        }
+        frames
+    }
+
+    // Compress like a client keeping its context between messages
+    fn client_deflate(compress: &mut Compress, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() + 64);
+        compress.compress_vec(data, &mut out, FlushCompress::Sync).unwrap();
+        assert!(out.ends_with(&DEFLATE_TAIL));
+        out.truncate(out.len() - DEFLATE_TAIL.len());
+        out
+    }
+
+    async fn run_inbound(input: &[u8]) -> (Result<(), String>, Vec<u8>, ByteCount) {
+        let (mut client, mut server, mut received) = (input, Vec::new(), ByteCount::default());
+        let result = inbound(&mut client, &mut server, &mut received).await;
+        (result, server, received)
+    }
+
+    fn text(len: usize) -> Vec<u8> {
+        "the quick brown fox jumps over the lazy dog ".bytes().cycle().take(len).collect()
+    }
+
+    #[test]
+    fn deflate_leaves_out_the_tail() {
+        let data = text(4000);
+        let mut compress = Compress::new(Compression::default(), false);
+        let compressed = deflate(&mut compress, &data).unwrap();
+        assert!(!compressed.ends_with(&DEFLATE_TAIL));
+        assert!(compressed.len() < data.len());
+
+        // Every message is compressed on its own, a fresh decompressor reads each of them
+        let again = deflate(&mut compress, &data).unwrap();
+        assert_eq!(again, compressed);
+        assert_eq!(inflate(&mut Decompress::new(false), &compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn inflate_keeps_the_window_of_earlier_messages() {
+        let data = text(2000);
+        let mut compress = Compress::new(Compression::default(), false);
+        let first = client_deflate(&mut compress, &data);
+        let second = client_deflate(&mut compress, &data);
+        assert!(second.len() < first.len());
+
+        let mut decompress = Decompress::new(false);
+        assert_eq!(inflate(&mut decompress, &first).unwrap(), data);
+        assert_eq!(inflate(&mut decompress, &second).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn inbound_joins_fragmented_messages_around_control_frames() {
+        let data = text(3000);
+        let compressed = client_deflate(&mut Compress::new(Compression::default(), false), &data);
+        let (head, tail) = compressed.split_at(compressed.len() / 2);
+        let mut input = Vec::new();
+        input.extend(client_frame(RSV1 | 0x1, head));
+        input.extend(client_frame(FIN | 0x9, b"ping"));
+        input.extend(client_frame(FIN, tail));
+        input.extend(client_frame(0x2, b"abc"));
+        input.extend(client_frame(FIN | 0xA, b"pong"));
+        input.extend(client_frame(FIN, b"def"));
+        input.extend(client_frame(FIN | 0x8, &[0x03, 0xe8]));
+
+        let (result, output, received) = run_inbound(&input).await;
+        result.unwrap();
+        let frames = frames_of(&output).await;
+        let summary: Vec<(bool, bool, u8, &[u8])> = frames.iter().map(|f| (f.fin, f.rsv1, f.opcode, f.payload.as_slice())).collect();
+        assert_eq!(summary, vec![
+            (true, false, 0x9, &b"ping"[..]),
+            (true, false, 0x1, &data[..]),
+            (false, false, 0x2, &b"abc"[..]),
+            (true, false, 0xA, &b"pong"[..]),
+            (true, false, 0x0, &b"def"[..]),
+            (true, false, 0x8, &[0x03, 0xe8][..]),
+        ]);
+        assert!(frames.iter().all(|frame| frame.mask == Some(MASK)));
+        assert_eq!(received.raw, (data.len() + 6) as u64);
+        assert_eq!(received.wire, (compressed.len() + 6) as u64);
+    }
+
+    #[tokio::test]
+    async fn outbound_compresses_large_messages_only() {
+        let data = text(3000);
+        let (head, tail) = data.split_at(1000);
+        let mut input = Vec::new();
+        input.extend(server_frame(0x1, head));
+        input.extend(server_frame(FIN | 0x9, b"ping"));
+        input.extend(server_frame(FIN, tail));
+        input.extend(server_frame(FIN | 0x2, b"small"));
+
+        let (mut server, mut client, mut sent) = (&input[..], Vec::new(), ByteCount::default());
+        outbound(&mut server, &mut client, 256, &mut sent).await.unwrap();
+        let frames = frames_of(&client).await;
+        assert_eq!(frames.len(), 3);
+        assert_eq!((frames[0].opcode, frames[0].payload.as_slice()), (0x9, &b"ping"[..]));
+        assert!(frames[1].fin && frames[1].rsv1 && frames[1].opcode == 0x1);
+        assert!(!frames[1].payload.ends_with(&DEFLATE_TAIL));
+        assert_eq!(inflate(&mut Decompress::new(false), &frames[1].payload).unwrap(), data);
+        assert_eq!((frames[2].rsv1, frames[2].opcode, frames[2].payload.as_slice()), (false, 0x2, &b"small"[..]));
+        assert!(frames.iter().all(|frame| frame.mask.is_none()));
+        assert_eq!(sent.raw, (data.len() + 5) as u64);
+        assert_eq!(sent.wire, (frames[1].payload.len() + 5) as u64);
+        assert!(sent.saved() > 0);
+    }
+
+    #[tokio::test]
+    async fn oversized_frames_are_rejected() {
+        let mut frame = vec![FIN | 0x2, 0x80 | 127];
+        frame.extend_from_slice(&((MAX_MESSAGE_BYTES as u64) + 1).to_be_bytes());
+        frame.extend_from_slice(&MASK);
+        let (result, output, _) = run_inbound(&frame).await;
+        assert!(result.unwrap_err().contains("exceeds the limit"));
+        assert!(output.is_empty());
+    }
+
+    #[tokio::test]
+    async fn bad_frames_are_rejected() {
+        // A continuation without a message
+        let (result, _, _) = run_inbound(&client_frame(FIN, b"abc")).await;
+        assert!(result.unwrap_err().contains("outside of a message"));
+
+        // A new message before the end of the previous one
+        let mut input = client_frame(0x1, b"abc");
+        input.extend(client_frame(FIN | 0x1, b"def"));
+        let (result, _, _) = run_inbound(&input).await;
+        assert!(result.unwrap_err().contains("before the end"));
+
+        // RSV1 on a control frame
+        let (result, _, _) = run_inbound(&client_frame(FIN | RSV1 | 0x9, b"ping")).await;
+        assert!(result.unwrap_err().contains("RSV1"));
+
+        // A compressed message that doesn't inflate
+        let (result, output, _) = run_inbound(&client_frame(FIN | RSV1 | 0x2, &[0xff, 0xff, 0xff, 0xff])).await;
+        assert!(result.is_err());
+        assert!(output.is_empty());
+
+        // A frame cut short
+        let frame = client_frame(FIN | 0x2, b"abcdef");
+        let (result, _, _) = run_inbound(&frame[..frame.len() - 2]).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn offers_are_accepted_when_they_ask_for_what_the_server_does() {
+        assert!(acceptable_offer("permessage-deflate"));
+        assert!(acceptable_offer("permessage-deflate; client_max_window_bits"));
+        assert!(acceptable_offer("permessage-deflate; server_no_context_takeover; client_max_window_bits=10"));
+        assert!(acceptable_offer("permessage-deflate; server_max_window_bits=15"));
+        assert!(!acceptable_offer("permessage-deflate; server_max_window_bits=10"));
+        assert!(!acceptable_offer("permessage-deflate; client_no_context_takeover; client_no_context_takeover"));
+        assert!(!acceptable_offer("x-webkit-deflate-frame"));
+    }
+}